@@ -0,0 +1,76 @@
+//! Contention on the shared state: trace reads and session writes from many tasks while another
+//! keeps `APP_STATE` busy (as the OrgBrain does during an ask), and concurrent RAG searches.
+//! A plain timing harness: `cargo bench --bench state_locks`.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use pocketflow_template_rust::app_state::{self, Sessions, APP_STATE};
+use pocketflow_template_rust::config::CosConfig;
+use pocketflow_template_rust::domain::{EmployeeAgentId, EmployeeRole};
+
+const TASKS: usize = 8;
+
+/// Runs `op` `iterations` times on each of `TASKS` tasks and returns operations per second.
+async fn throughput<F, Fut>(iterations: usize, op: F) -> f64
+where
+    F: Fn(usize) -> Fut + Copy + Send + 'static,
+    Fut: std::future::Future<Output = ()> + Send,
+{
+    let start = Instant::now();
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|t| {
+            tokio::spawn(async move {
+                for i in 0..iterations {
+                    op(t * iterations + i).await;
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    (TASKS * iterations) as f64 / start.elapsed().as_secs_f64()
+}
+
+async fn trace_and_session_ops(i: usize) {
+    let _ = app_state::traces().await.len();
+    let agent = EmployeeAgentId(format!("employee_bench_{}", i % 16));
+    let _ = Sessions::next_private_key(&agent);
+    let _ = app_state::sessions().await.conversation_cache.get(&agent).map(Vec::len);
+}
+
+async fn rag_op(i: usize) {
+    let query = format!("remote work policy {}", i % 8);
+    let _ = app_state::rag_search(query, 5, &[EmployeeRole::Ceo]).await;
+}
+
+#[tokio::main]
+async fn main() {
+    let mut config = CosConfig::default();
+    config.llm.offline = true;
+    app_state::set_config(config);
+    APP_STATE.lock().await.init_rag_from(Path::new("bench-no-such.csv")).await.unwrap();
+
+    let idle = throughput(20_000, trace_and_session_ops).await;
+    // Hold the global lock in 5 ms slices, like an ask that drains events and updates truth.
+    let busy = tokio::spawn(async {
+        loop {
+            let _state = APP_STATE.lock().await;
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    });
+    let contended = throughput(20_000, trace_and_session_ops).await;
+    busy.abort();
+    println!("trace + session ops/s: {idle:>12.0} idle, {contended:>12.0} with APP_STATE busy");
+
+    let sequential = {
+        let start = Instant::now();
+        for i in 0..200 {
+            rag_op(i).await;
+        }
+        200.0 / start.elapsed().as_secs_f64()
+    };
+    let concurrent = throughput(200 / TASKS, rag_op).await;
+    println!("rag searches/s:        {sequential:>12.0} one task, {concurrent:>12.0} on {TASKS}");
+}
@@ -2,108 +2,315 @@ use axum::{
     extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
     response::{sse::Event, IntoResponse, Sse},
-    routing::{get, post},
+    routing::{delete, get, post},
     Json, Router,
 };
-use futures::{stream, Stream, StreamExt};
+use futures::{stream, StreamExt};
 use base64::Engine;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::{collections::HashMap, convert::Infallible, net::SocketAddr, time::Duration};
 use tokio::sync::broadcast;
-use tokio_stream::wrappers::BroadcastStream;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use tokio_util::sync::CancellationToken;
 use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use utoipa::{IntoParams, OpenApi, ToSchema};
 
-use crate::app_state::APP_STATE;
-use crate::domain::{EmployeeRole, ReasoningTrace};
+use crate::app_state::{handles, sessions, traces, APP_STATE};
+use crate::utils::{neo4j_timeout, with_timeout};
+use crate::domain::{Annotation, EmployeeAgentId, EmployeeRole, ReasoningTrace};
+use crate::visibility::VisibilityRules;
 
 fn normalize_employee_name(s: &str) -> String {
-    s.trim().to_lowercase()
+    crate::neo4j::writer::slugify_identifier(s)
 }
 
-fn resolve_employee_agent_id(
+/// A ready-to-return error response, boxed so the `Result`s carrying it stay small.
+type ErrorResponse = Box<axum::response::Response>;
+
+fn employee_agent_id_from_name(name: &str) -> Result<String, ErrorResponse> {
+    let n = normalize_employee_name(name);
+    if n.is_empty() {
+        return Err(Box::new(
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "invalid employee name", "name": name})),
+            )
+                .into_response(),
+        ));
+    }
+    Ok(format!("employee_{}", n))
+}
+
+/// Events skipped by lagging SSE clients since startup.
+static SSE_LAGGED_EVENTS: AtomicU64 = AtomicU64::new(0);
+
+/// Carries the id of an ask, in both directions: see `ask` and `cancel_ask`.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+fn identity_error(status: StatusCode, code: &str, error: &str) -> axum::response::Response {
+    (status, Json(json!({"error": error, "code": code}))).into_response()
+}
+
+/// A failure from `service`, `utils` or the writer, with the status of its `CosError` kind.
+fn error_response(e: &anyhow::Error) -> axum::response::Response {
+    let (status, body) = crate::error::http_error(e);
+    (status, Json(body)).into_response()
+}
+
+/// Per-employee bearer tokens from `COS_EMPLOYEE_TOKENS` (`token:Employee Name`, comma-separated),
+/// mapped to agent ids.
+fn employee_tokens() -> HashMap<String, String> {
+    crate::app_state::config()
+        .http
+        .employee_tokens
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|pair| {
+            let (token, name) = pair.split_once(':')?;
+            let (token, slug) = (token.trim(), normalize_employee_name(name));
+            if token.is_empty() || slug.is_empty() {
+                return None;
+            }
+            Some((token.to_string(), format!("employee_{}", slug)))
+        })
+        .collect()
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+}
+
+/// Agent ids that may be asserted without a token (`COS_ALLOWED_IMPERSONATION`, names,
+/// comma-separated). `None` when unset, which keeps legacy header identity open.
+fn impersonation_allowlist() -> Option<Vec<String>> {
+    let config = crate::app_state::config();
+    let raw = config.http.allowed_impersonation.as_deref()?;
+    Some(
+        raw.split(',')
+            .map(normalize_employee_name)
+            .filter(|s| !s.is_empty())
+            .map(|s| format!("employee_{}", s))
+            .collect(),
+    )
+}
+
+/// Identity asserted by the request itself: `x-employee-name` header, then body
+/// `employee_name`, then body `agent_id`.
+fn asserted_employee_agent_id(
     headers: &HeaderMap,
     employee_name_body: Option<&str>,
     agent_id_body: Option<&str>,
-) -> Option<String> {
+) -> Option<Result<String, ErrorResponse>> {
     if let Some(v) = headers
         .get("x-employee-name")
         .and_then(|v| v.to_str().ok())
         .map(|s| s.trim())
         .filter(|s| !s.is_empty())
     {
-        let n = normalize_employee_name(v);
-        return Some(format!("employee_{}", n));
+        return Some(employee_agent_id_from_name(v));
     }
     if let Some(v) = employee_name_body.map(|s| s.trim()).filter(|s| !s.is_empty()) {
-        let n = normalize_employee_name(v);
-        return Some(format!("employee_{}", n));
+        return Some(employee_agent_id_from_name(v));
     }
     agent_id_body
         .map(|s| s.trim())
         .filter(|s| !s.is_empty())
-        .map(|s| s.to_string())
+        .map(|s| Ok(s.to_string()))
 }
 
-fn employee_role_from_agent_id(agent_id: &str) -> EmployeeRole {
-    match agent_id {
-        "employee_john" => EmployeeRole::Ceo,
-        "employee_sarah" => EmployeeRole::Hr,
-        "employee_bob" => EmployeeRole::Engineer,
-        _ => EmployeeRole::Engineer,
-    }
+/// Resolves the caller identity. Errors are ready-to-return responses.
+///
+/// A bearer token from `COS_EMPLOYEE_TOKENS` wins, and any asserted identity that disagrees
+/// with it is rejected (403 `identity_conflict`). Without a token the asserted identity is
+/// used, restricted to `COS_ALLOWED_IMPERSONATION` when that is set (403
+/// `impersonation_not_allowed`).
+fn resolve_employee_agent_id(
+    headers: &HeaderMap,
+    employee_name_body: Option<&str>,
+    agent_id_body: Option<&str>,
+) -> Result<String, ErrorResponse> {
+    let asserted = asserted_employee_agent_id(headers, employee_name_body, agent_id_body);
+    let allowlist = impersonation_allowlist();
+    resolve_identity(bearer_token(headers), asserted, &employee_tokens(), allowlist.as_deref())
 }
 
-fn role_default_visibility(role: &EmployeeRole, topic: &str) -> &'static str {
-    let t = topic.trim().to_lowercase();
-    match role {
-        EmployeeRole::Ceo => "full",
-        EmployeeRole::Hr => {
-            if t.contains("hr")
-                || t.contains("people")
-                || t.contains("hiring")
-                || t.contains("policy")
-                || t.contains("compensation")
-                || t.contains("performance")
-            {
-                "summary"
-            } else {
-                "none"
+/// `resolve_employee_agent_id` over an explicit token table and allowlist.
+fn resolve_identity(
+    token: Option<&str>,
+    asserted: Option<Result<String, ErrorResponse>>,
+    tokens: &HashMap<String, String>,
+    allowlist: Option<&[String]>,
+) -> Result<String, ErrorResponse> {
+    if let Some(token) = token {
+        let Some(token_agent_id) = tokens.get(token).cloned() else {
+            return Err(Box::new(identity_error(
+                StatusCode::UNAUTHORIZED,
+                "invalid_token",
+                "unknown bearer token",
+            )));
+        };
+        if let Some(asserted) = asserted {
+            if asserted? != token_agent_id {
+                return Err(Box::new(identity_error(
+                    StatusCode::FORBIDDEN,
+                    "identity_conflict",
+                    "asserted employee does not match the bearer token",
+                )));
             }
         }
-        EmployeeRole::Engineer => {
-            if t.contains("engineer")
-                || t.contains("eng")
-                || t.contains("tech")
-                || t.contains("product")
-                || t.contains("reliab")
-                || t.contains("infra")
-            {
-                "summary"
-            } else {
-                "none"
-            }
+        return Ok(token_agent_id);
+    }
+
+    let agent_id = match asserted {
+        Some(r) => r?,
+        None => {
+            return Err(Box::new(
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({"error": "missing x-employee-name"})),
+                )
+                    .into_response(),
+            ));
+        }
+    };
+    if let Some(allowed) = allowlist {
+        if !allowed.contains(&agent_id) {
+            return Err(Box::new(identity_error(
+                StatusCode::FORBIDDEN,
+                "impersonation_not_allowed",
+                "this employee must authenticate with a bearer token",
+            )));
         }
     }
+    Ok(agent_id)
+}
+
+/// `resolve_employee_agent_id`, then mapped to the canonical employee when the identity is an
+/// alias (e.g. an email-derived `employee_email_*` linked to a seeded employee).
+async fn resolve_canonical_agent_id(
+    headers: &HeaderMap,
+    employee_name_body: Option<&str>,
+    agent_id_body: Option<&str>,
+) -> Result<String, ErrorResponse> {
+    let agent_id = resolve_employee_agent_id(headers, employee_name_body, agent_id_body)?;
+    Ok(canonical_agent_id(agent_id).await)
 }
 
-fn visibility_for_agent(trace: &ReasoningTrace, agent_id: &str) -> String {
-    if let Some(level) = trace.routing.get(agent_id) {
-        return level.clone();
+/// The canonical employee `agent_id` is an alias of, or `agent_id` itself without Neo4j or when
+/// the lookup fails.
+pub async fn canonical_agent_id(agent_id: String) -> String {
+    let neo4j = handles().neo4j;
+    let Some(client) = neo4j else {
+        return agent_id;
+    };
+    let lookup = crate::neo4j::writer::resolve_canonical_employee(client.graph(), &agent_id);
+    match client.breaker().call(lookup).await {
+        Ok(canonical) => canonical,
+        Err(e) => {
+            eprintln!("resolve canonical employee {agent_id}: {e:#}");
+            agent_id
+        }
     }
+}
+
+pub fn employee_role_from_agent_id(agent_id: &str) -> EmployeeRole {
+    known_role_from_agent_id(agent_id).unwrap_or(EmployeeRole::Engineer)
+}
+
+/// The role of a known employee agent; `employee_role_from_agent_id` treats others as engineers.
+/// Seeded employees get the role from `employees.toml`; others the one stored on their
+/// `Employee` node.
+fn known_role_from_agent_id(agent_id: &str) -> Option<EmployeeRole> {
+    crate::roles::role_of(agent_id)
+}
+
+/// How `visibility_for_agent` arrived at `agent_id`'s level on `trace`.
+pub(crate) fn explain_visibility(
+    rules: &VisibilityRules,
+    trace: &ReasoningTrace,
+    agent_id: &str,
+) -> VisibilityExplanation {
     let role = employee_role_from_agent_id(agent_id);
-    role_default_visibility(&role, &trace.topic).to_string()
+    let known = known_role_from_agent_id(agent_id).is_some();
+    let (level, reason, matched_keyword) = match trace.routing.get(agent_id) {
+        Some(level) => (level.clone(), "routing", None),
+        // Deny mode doesn't extend the engineer fallback to agents without a known role.
+        None if rules.default_deny() && !known => ("none".to_string(), "default_deny", None),
+        None => {
+            let (level, keyword) = rules.role_default(&role, &trace.topic);
+            let reason = match keyword {
+                Some(_) => "topic_keyword",
+                None if rules.default_deny() => "default_deny",
+                None => "role_default",
+            };
+            (level.to_string(), reason, keyword.map(str::to_string))
+        }
+    };
+    VisibilityExplanation {
+        agent_id: agent_id.to_string(),
+        decision_id: trace.decision_id.clone(),
+        version: trace.version,
+        level,
+        reason: reason.to_string(),
+        role,
+        topic: trace.topic.clone(),
+        matched_keyword,
+    }
+}
+
+/// `agent_id`'s level on `trace`: its routing entry, else its role's default under `rules`.
+pub(crate) fn visibility_for_agent(
+    rules: &VisibilityRules,
+    trace: &ReasoningTrace,
+    agent_id: &str,
+) -> String {
+    explain_visibility(rules, trace, agent_id).level
+}
+
+/// `trace` as `agent_id` may see it: `None` at level `none`, without evidence and assumptions at
+/// `summary`.
+pub(crate) fn trace_visible_to(
+    rules: &VisibilityRules,
+    trace: &ReasoningTrace,
+    agent_id: &str,
+) -> Option<ReasoningTrace> {
+    let level = visibility_for_agent(rules, trace, agent_id);
+    if level == "none" {
+        return None;
+    }
+    let mut tt = trace.clone();
+    if level == "summary" {
+        tt.evidence = Vec::new();
+        tt.assumptions = Vec::new();
+    }
+    Some(tt)
+}
+
+fn low_confidence_threshold() -> f32 {
+    crate::app_state::config().orgbrain.low_confidence_threshold
+}
+
+/// The `low_confidence` event for `trace` when its confidence is below `threshold`.
+fn low_confidence_alert(trace: &ReasoningTrace, threshold: f32) -> Option<ServerEvent> {
+    (trace.confidence < threshold).then(|| ServerEvent::LowConfidence {
+        decision_id: trace.decision_id.clone(),
+        confidence: trace.confidence,
+        topic: trace.topic.clone(),
+    })
 }
 
 fn build_cors_layer() -> CorsLayer {
-    let origins_raw = std::env::var("COS_CORS_ORIGINS").ok();
-    let origins_raw_for_split = origins_raw.clone().unwrap_or_else(|| "*".to_string());
-    let origins: Vec<String> = origins_raw_for_split
-        .split(',')
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-        .collect();
+    let configured = crate::app_state::config().cors.origins.clone();
+    let origins = configured.clone().unwrap_or_else(|| vec!["*".to_string()]);
 
     let allow_origin = if origins.iter().any(|o| o == "*") {
         AllowOrigin::any()
@@ -122,9 +329,10 @@ fn build_cors_layer() -> CorsLayer {
             axum::http::Method::POST,
             axum::http::Method::OPTIONS,
         ])
-        .allow_headers(Any);
+        .allow_headers(Any)
+        .expose_headers([axum::http::HeaderName::from_static(REQUEST_ID_HEADER)]);
 
-    if origins_raw.is_some() && !origins_raw.as_deref().unwrap_or("").contains('*') {
+    if configured.is_some_and(|o| !o.iter().any(|o| o.contains('*'))) {
         cors = cors.allow_credentials(true);
     }
     cors
@@ -134,12 +342,153 @@ fn build_cors_layer() -> CorsLayer {
 pub struct ApiState {
     pub events_tx: broadcast::Sender<ServerEvent>,
     pub api_key: Option<String>,
+    /// Asks in flight by request id, with the employee who may cancel them.
+    pub in_flight: Arc<Mutex<InFlightAsks>>,
+}
+
+pub type InFlightAsks = HashMap<uuid::Uuid, (EmployeeAgentId, CancellationToken)>;
+
+impl ApiState {
+    /// State for `app()` without binding a socket, so the router can also be driven in-process
+    /// (e.g. with `tower::ServiceExt::oneshot`).
+    pub fn new(api_key: Option<String>, sse_capacity: usize) -> Self {
+        let (events_tx, _rx) = broadcast::channel::<ServerEvent>(sse_capacity.max(1));
+        Self { events_tx, api_key, in_flight: Default::default() }
+    }
+
+    fn lock_in_flight(&self) -> std::sync::MutexGuard<'_, InFlightAsks> {
+        self.in_flight.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Removes an ask from `ApiState::in_flight` when its handler finishes or is dropped.
+struct InFlightAsk {
+    in_flight: Arc<Mutex<InFlightAsks>>,
+    request_id: uuid::Uuid,
+}
+
+impl Drop for InFlightAsk {
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        in_flight.remove(&self.request_id);
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum ServerEvent {
-    Trace(ReasoningTrace),
+    Trace(Box<ReasoningTrace>),
+    LowConfidence {
+        decision_id: String,
+        confidence: f32,
+        topic: String,
+    },
+    /// The staleness check marked the current version of a decision stale.
+    StaleDecision {
+        decision_id: String,
+        version: i64,
+        score: f64,
+        topic: String,
+    },
+    /// An ask by `agent_id` reached `stage` (`analyzing`, `retrieving` or `deciding`). Sent to
+    /// the asker and the CEO only. `request_id` is what `POST /v1/ask/{id}/cancel` takes.
+    Progress {
+        stage: String,
+        agent_id: String,
+        request_id: Option<uuid::Uuid>,
+    },
+}
+
+impl ServerEvent {
+    /// A `Trace` event; the trace is boxed, being much larger than the other events.
+    pub fn trace(trace: ReasoningTrace) -> Self {
+        ServerEvent::Trace(Box::new(trace))
+    }
+
+    /// The `type` tag this event serializes with.
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            ServerEvent::Trace(_) => "trace",
+            ServerEvent::LowConfidence { .. } => "low_confidence",
+            ServerEvent::StaleDecision { .. } => "stale_decision",
+            ServerEvent::Progress { .. } => "progress",
+        }
+    }
+
+    fn topic(&self) -> Option<&str> {
+        match self {
+            ServerEvent::Trace(t) => Some(&t.topic),
+            ServerEvent::LowConfidence { topic, .. } => Some(topic),
+            ServerEvent::StaleDecision { topic, .. } => Some(topic),
+            ServerEvent::Progress { .. } => None,
+        }
+    }
+
+    fn decision_id(&self) -> Option<&str> {
+        match self {
+            ServerEvent::Trace(t) => Some(&t.decision_id),
+            ServerEvent::LowConfidence { decision_id, .. } => Some(decision_id),
+            ServerEvent::StaleDecision { decision_id, .. } => Some(decision_id),
+            ServerEvent::Progress { .. } => None,
+        }
+    }
+}
+
+/// Optional `/v1/stream` filters, applied after the visibility check.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct StreamFilter {
+    /// Lowercased topics; matched case-insensitively against the event topic.
+    pub topics: Option<Vec<String>>,
+    pub decision_id: Option<String>,
+    /// Event `type` tags, e.g. `trace`, `low_confidence`.
+    pub event_types: Option<Vec<String>>,
+}
+
+fn comma_list(v: Option<&String>) -> Option<Vec<String>> {
+    let items: Vec<String> = v?
+        .split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if items.is_empty() {
+        None
+    } else {
+        Some(items)
+    }
+}
+
+impl StreamFilter {
+    fn from_query(q: &HashMap<String, String>) -> Self {
+        Self {
+            topics: comma_list(q.get("topics")),
+            decision_id: q
+                .get("decision_id")
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty()),
+            event_types: comma_list(q.get("event_types")),
+        }
+    }
+
+    fn matches(&self, evt: &ServerEvent) -> bool {
+        if let Some(types) = &self.event_types {
+            if !types.iter().any(|t| t == evt.event_type()) {
+                return false;
+            }
+        }
+        // Events without a topic or decision (progress) only go through the type filter.
+        if let (Some(topics), Some(topic)) = (&self.topics, evt.topic()) {
+            let topic = topic.trim().to_lowercase();
+            if !topics.contains(&topic) {
+                return false;
+            }
+        }
+        if let (Some(id), Some(decision_id)) = (&self.decision_id, evt.decision_id()) {
+            if decision_id != id {
+                return false;
+            }
+        }
+        true
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -150,6 +499,15 @@ pub struct AskRequest {
     pub agent_id: Option<String>,
     pub employee_name: Option<String>,
     pub response_audio: Option<bool>,
+    /// ElevenLabs `output_format` for `response_audio` (e.g. `mp3_22050_32`, `pcm_16000`);
+    /// default MP3.
+    pub output_format: Option<String>,
+    /// Inline documents to ground the answer in, shown to the OrgBrain as evidence.
+    #[serde(default)]
+    pub context_documents: Vec<String>,
+    /// Truth whose current content is shown to the OrgBrain the same way.
+    #[serde(default)]
+    pub context_truth_ids: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -158,6 +516,12 @@ pub struct AskResponse {
     pub trace: ReasoningTrace,
     pub audio_base64: Option<String>,
     pub audio_mime: Option<String>,
+    /// The OrgBrain asked a question instead of deciding; answer it with another ask.
+    #[serde(default)]
+    pub needs_clarification: bool,
+    /// The question, when `needs_clarification` (also the `response_text`).
+    #[serde(default)]
+    pub clarifying_question: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -168,16 +532,72 @@ pub struct KnowledgeIngestRequest {
     pub agent_id: Option<String>,
     pub routing: serde_json::Value,
     pub add_to_rag: Option<bool>,
+    /// Write a new version even when `content` equals the current one.
+    #[serde(default)]
+    pub force: bool,
+    /// Also run the OrgBrain over the new knowledge (needs the LLM); see `analysis_trace`.
+    #[serde(default)]
+    pub analyze: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AnnounceRequest {
+    pub title: String,
+    pub body: String,
+    /// Agent id -> `full` | `summary` | `none`, over the default of `summary` for every seeded
+    /// employee.
+    #[serde(default)]
+    pub routing: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AnnotationRequest {
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AnnotationResponse {
+    pub decision_id: String,
+    pub version: i64,
+    pub annotation: Annotation,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct KnowledgeIngestResponse {
     pub trace: ReasoningTrace,
+    /// The ingest's trigger event, also in `analysis_trace.trigger_events`.
+    pub correlation_id: Option<uuid::Uuid>,
+    /// The OrgBrain's reaction when `analyze` was set and the content was new.
+    pub analysis_trace: Option<ReasoningTrace>,
+    /// Why the analysis failed; the knowledge itself was still ingested.
+    pub analysis_error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct HealthResponse {
     pub ok: bool,
+    /// Audit batches that failed to persist since startup.
+    pub audit_write_failures: u64,
+    /// Events SSE clients missed because they fell behind the broadcast channel.
+    pub sse_lagged_events: u64,
+    /// Decision, truth and org-update writes behind a trace that failed since startup.
+    pub persistence_failures: u64,
+    /// Embeddings served from the cache since startup.
+    pub embedding_cache_hits: u64,
+    /// Embeddings that had to be requested since startup.
+    pub embedding_cache_misses: u64,
+    /// Webhook deliveries that succeeded since startup.
+    pub webhook_delivered: u64,
+    /// Webhook deliveries that failed after all retries since startup.
+    pub webhook_failed: u64,
+    /// Webhook deliveries dropped because an endpoint's queue was full.
+    pub webhook_dropped: u64,
+    /// Neo4j was unreachable at startup and is still being reconnected: nothing is persisted
+    /// and graph endpoints answer 503. `ok` is false meanwhile.
+    pub degraded: bool,
+    /// OpenAI chat tokens spent since startup, per component; the reviewer is counted apart
+    /// from the OrgBrain whose decisions it reviews.
+    pub llm_usage: Vec<crate::usage::ComponentUsage>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -231,471 +651,3022 @@ pub struct Pagination {
     pub limit: Option<usize>,
 }
 
-#[derive(OpenApi)]
-#[openapi(
-    paths(
-        health,
-        ask,
-        ingest_knowledge,
-        list_traces,
-        agent_traces,
-        graph_snapshot,
-        agent_graph_snapshot,
-        current_decisions,
-        current_truth,
-        sse_stream,
-        openapi_json
-    ),
-    components(
-        schemas(
-            AskRequest,
-            AskResponse,
-            KnowledgeIngestRequest,
-            KnowledgeIngestResponse,
-            HealthResponse,
-            TraceListResponse,
-            AgentTraceListResponse,
-            ReasoningTrace,
-            ServerEvent,
-            GraphSnapshotResponse,
-            GraphNode,
-            GraphEdge,
-            CurrentDecisionsResponse,
-            CurrentTruthResponse,
-            Pagination
-        )
-    ),
-    tags(
-        (name = "cos", description = "AI Chief of Staff backend")
-    )
-)]
-pub struct ApiDoc;
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(IntoParams)]
+pub struct TraceQuery {
+    pub limit: Option<usize>,
+    /// Case-insensitive substring of the trace topic (an exact topic also matches).
+    pub topic: Option<String>,
+    /// Only traces with `confidence >= min_confidence`.
+    pub min_confidence: Option<f32>,
+}
 
-pub fn app(state: ApiState) -> Router {
-    let cors = build_cors_layer();
+impl TraceQuery {
+    /// Filters are applied before `limit`, so a page is always full when enough traces match.
+    fn matches(&self, t: &ReasoningTrace) -> bool {
+        if let Some(topic) = self.topic.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            if !t.topic.to_lowercase().contains(&topic.to_lowercase()) {
+                return false;
+            }
+        }
+        self.min_confidence.map(|m| t.confidence >= m).unwrap_or(true)
+    }
+}
 
-    Router::new()
-        .route("/health", get(health))
-        .route("/v1/ask", post(ask))
-        .route("/v1/knowledge", post(ingest_knowledge))
-        .route("/v1/traces", get(list_traces))
-        .route("/v1/agents/:agent_id/traces", get(agent_traces))
-        .route("/v1/graph/snapshot", get(graph_snapshot))
-        .route("/v1/agents/:agent_id/graph/snapshot", get(agent_graph_snapshot))
-        .route("/v1/decisions/current", get(current_decisions))
-        .route("/v1/truth/current", get(current_truth))
-        .route("/v1/stream", get(sse_stream))
-        .route("/openapi.json", get(openapi_json))
-        .with_state(state)
-        .layer(cors)
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(IntoParams)]
+pub struct AuditQuery {
+    pub employee_id: Option<String>,
+    /// Only events at or after this RFC 3339 timestamp.
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    /// Route template, e.g. `/v1/traces`.
+    pub route: Option<String>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
 }
 
-fn unauthorized() -> axum::response::Response {
-    (
-        StatusCode::UNAUTHORIZED,
-        Json(json!({"error": "unauthorized"})),
-    )
-        .into_response()
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BackfillRolesResponse {
+    /// Employees that got a role from `COS_ROLE_RULES`.
+    pub updated: usize,
 }
 
-fn auth_ok(headers: &HeaderMap, state: &ApiState) -> bool {
-    let Some(expected) = &state.api_key else {
-        return true;
-    };
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RepairCommunicationsResponse {
+    /// `COMMUNICATES_WITH` edges whose count was recomputed from `SENT` / `TO` edges.
+    pub recomputed: i64,
+    /// Edges with no message behind them, now at count 0.
+    pub zeroed: i64,
+}
 
-    let provided = headers
-        .get("x-api-key")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("");
-    provided == expected
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IdentitySuggestionsResponse {
+    pub suggestions: Vec<crate::neo4j::writer::IdentitySuggestion>,
 }
 
-#[utoipa::path(
-    get,
-    path = "/health",
-    responses((status = 200, body = HealthResponse))
-)]
-async fn health() -> impl IntoResponse {
-    Json(HealthResponse { ok: true })
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct EmployeeAliasRequest {
+    /// Employee to make an alias, e.g. `employee_email_john_acme_com`.
+    pub alias_id: String,
+    /// Also move the alias's `SENT`, `TO` and `PARTICIPATED_IN` edges (default `true`).
+    pub merge_edges: Option<bool>,
 }
 
-#[utoipa::path(
-    post,
-    path = "/v1/ask",
-    request_body = AskRequest,
-    responses(
-        (status = 200, body = AskResponse),
-        (status = 500, body = serde_json::Value)
-    )
-)]
-async fn ask(
-    State(api_state): State<ApiState>,
-    headers: HeaderMap,
-    Json(req): Json<AskRequest>,
-) -> impl IntoResponse {
-    if !auth_ok(&headers, &api_state) {
-        return unauthorized();
-    }
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EmployeeAliasResponse {
+    pub alias_id: String,
+    pub canonical_employee_id: String,
+    /// Edges moved to the canonical employee; all zero without `merge_edges`.
+    pub moved: crate::neo4j::writer::AliasMerge,
+}
 
-    // Identity is required (either header or request body field for audio clients).
-    let Some(_caller_agent_id) = resolve_employee_agent_id(
-        &headers,
-        req.employee_name.as_deref(),
-        req.agent_id.as_deref(),
-    ) else {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "missing x-employee-name"})),
-        )
-            .into_response();
-    };
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(IntoParams)]
+pub struct DecisionDiffQuery {
+    /// Older version number.
+    pub from: i64,
+    /// Newer version number.
+    pub to: i64,
+}
 
-    let text = if let Some(t) = req.text.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
-        t.to_string()
-    } else if let Some(b64) = req.audio_base64.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
-        let bytes = match base64::engine::general_purpose::STANDARD.decode(b64) {
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(IntoParams)]
+pub struct DecisionEvidenceQuery {
+    /// Version number; the current version when absent.
+    #[serde(default)]
+    pub version: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DecisionDiffResponse {
+    pub decision_id: String,
+    pub from: i64,
+    pub to: i64,
+    /// Changed fields only.
+    pub changes: Vec<crate::version_diff::FieldChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TruthDependentsResponse {
+    pub truth_id: String,
+    pub dependents: Vec<crate::neo4j::writer::TruthDependent>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EventDecisionsResponse {
+    pub event_id: String,
+    pub decisions: Vec<crate::neo4j::writer::EventDecision>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(IntoParams)]
+pub struct StatsQuery {
+    /// Window in days, ending now (default 30, at most 365).
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AgentTraceCount {
+    pub agent_id: String,
+    pub traces: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StatsResponse {
+    pub days: i64,
+    pub total_decisions: i64,
+    /// Decision versions written per day, with their average confidence.
+    pub decisions_per_day: Vec<crate::neo4j::writer::DayStats>,
+    /// Topics with the most decision versions, at most 10.
+    pub top_topics: Vec<crate::neo4j::writer::TopicCount>,
+    /// Traces held in memory since this process started, by participating agent.
+    pub traces_per_agent: Vec<AgentTraceCount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StaleDecision {
+    pub decision_id: String,
+    /// Current version, the one marked stale.
+    pub version: i64,
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    pub created_at: String,
+    pub age_days: i64,
+    pub newer_emails: i64,
+    pub newer_truth_versions: i64,
+    /// Score from the current counts; see `COS_STALE_THRESHOLD`.
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StaleDecisionsResponse {
+    pub threshold: f64,
+    /// Highest score first.
+    pub decisions: Vec<StaleDecision>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApprovalResponse {
+    pub decision_id: String,
+    /// The approved version, now `CURRENT`.
+    pub version: i64,
+    pub graph_updates: crate::domain::GraphUpdates,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(IntoParams)]
+pub struct SimilarQuery {
+    /// Number of decisions to return (default 5, at most 50).
+    pub k: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SimilarDecision {
+    pub decision_id: String,
+    /// Current version of the decision.
+    pub version: i64,
+    pub summary: String,
+    pub confidence: f64,
+    /// Cosine similarity of the summaries' embeddings.
+    pub similarity: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SimilarDecisionsResponse {
+    pub decision_id: String,
+    pub similar: Vec<SimilarDecision>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(IntoParams)]
+pub struct TruthImpactQuery {
+    pub limit: Option<usize>,
+    /// Ask the model for a one-paragraph summary of the impact.
+    pub summarize: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TruthImpactResponse {
+    pub truth_id: String,
+    /// One entry per affected decision (its current version), direct dependents first.
+    pub decisions: Vec<crate::neo4j::writer::ImpactedDecision>,
+    /// Every employee routed to an affected decision.
+    pub employees: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(IntoParams)]
+pub struct ClusterListQuery {
+    /// By member count: `desc` (default) or `asc`.
+    pub order: Option<String>,
+    /// Default 50, at most 500.
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ClustersResponse {
+    /// Clusters with at least `ORG_MIN_CLUSTER_SIZE` members.
+    pub clusters: Vec<crate::neo4j::writer::KnowledgeClusterSummary>,
+    pub limit: usize,
+    pub offset: usize,
+    /// The messages outside every listed cluster, with `ORG_UNCLUSTERED_GROUP=1`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unclustered: Option<crate::neo4j::writer::KnowledgeClusterSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RelabelClustersResponse {
+    /// Clusters with at least two members.
+    pub clusters: usize,
+    /// Clusters whose name or description changed.
+    pub relabeled: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(IntoParams)]
+pub struct CompactQuery {
+    /// Newest versions to keep per object, besides the first (default `COS_COMPACT_KEEP_LATEST`, 5).
+    pub keep_latest: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CompactResponse {
+    pub keep_latest: usize,
+    /// Decisions and truth objects that had versions removed.
+    pub objects_compacted: usize,
+    pub versions_deleted: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditListResponse {
+    pub events: Vec<crate::audit::AuditEvent>,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CypherQueryRequest {
+    pub query: String,
+    #[serde(default)]
+    pub params: serde_json::Map<String, serde_json::Value>,
+    /// Row cap for this query; never above `COS_CYPHER_MAX_ROWS`.
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CypherQueryResponse {
+    pub rows: Vec<serde_json::Value>,
+    /// True when the row cap cut the result short.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PrivateNote {
+    pub key: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PrivateNotesResponse {
+    pub agent_id: String,
+    pub notes: Vec<PrivateNote>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct VisibilityExplanation {
+    pub agent_id: String,
+    pub decision_id: String,
+    pub version: i64,
+    /// `full`, `summary` or `none`.
+    pub level: String,
+    /// `routing` (explicit entry for the agent), `topic_keyword` (one of the role's topic rules
+    /// matched), `role_default` (no entry and no rule matched) or `default_deny` (the same under
+    /// `COS_VISIBILITY_DEFAULT=deny`, or an agent without a known role).
+    pub reason: String,
+    pub role: EmployeeRole,
+    pub topic: String,
+    /// Tag, word, substring or regex of the rule that matched.
+    pub matched_keyword: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ClearMemoryResponse {
+    pub agent_id: String,
+    pub turns_cleared: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UrgentMessagesResponse {
+    pub messages: Vec<GraphNode>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(IntoParams)]
+pub struct UrgentMessagesQuery {
+    pub limit: Option<usize>,
+    /// Only messages ingested in the last N days (default 30).
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(IntoParams)]
+pub struct TraceExportQuery {
+    /// Only export traces created at or after this RFC 3339 timestamp.
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(IntoParams)]
+pub struct ReplayQuery {
+    /// Write the replay as the decision's next version (default false: nothing is written).
+    #[serde(default)]
+    pub commit: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(IntoParams)]
+pub struct DecisionReportQuery {
+    /// `markdown` (default) or `html`.
+    pub format: Option<String>,
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        health,
+        ask,
+        cancel_ask,
+        ingest_knowledge,
+        announce,
+        list_traces,
+        export_traces,
+        annotate_trace,
+        agent_traces,
+        trace_visibility,
+        agent_private_notes,
+        clear_agent_memory,
+        graph_snapshot,
+        agent_graph_snapshot,
+        current_decisions,
+        decision_diff,
+        decision_evidence,
+        decision_report,
+        similar_decisions,
+        stale_decisions,
+        stats,
+        refresh_decision,
+        approve_decision_version,
+        replay_decision,
+        truth_dependents,
+        event_decisions,
+        truth_impact,
+        current_truth,
+        urgent_messages,
+        email_thread,
+        graph_cypher,
+        audit_log,
+        backfill_roles,
+        repair_communications,
+        identity_suggestions,
+        link_employee_alias,
+        list_clusters,
+        cluster_detail,
+        relabel_clusters,
+        admin_config,
+        compact_versions,
+        sse_stream,
+        openapi_json,
+        openapi_yaml
+    ),
+    components(
+        schemas(
+            AskRequest,
+            AskResponse,
+            CancelAskResponse,
+            KnowledgeIngestRequest,
+            AnnounceRequest,
+            KnowledgeIngestResponse,
+            AnnotationRequest,
+            AnnotationResponse,
+            Annotation,
+            HealthResponse,
+            crate::usage::ComponentUsage,
+            TraceListResponse,
+            AgentTraceListResponse,
+            VisibilityExplanation,
+            ReasoningTrace,
+            crate::domain::GraphUpdates,
+            crate::domain::GraphUpdateEntry,
+            ServerEvent,
+            StreamFilter,
+            GraphSnapshotResponse,
+            GraphNode,
+            GraphEdge,
+            CurrentDecisionsResponse,
+            DecisionDiffQuery,
+            DecisionDiffResponse,
+            crate::version_diff::FieldChange,
+            DecisionEvidenceQuery,
+            DecisionReportQuery,
+            ReplayQuery,
+            crate::replay::ReplayOutcome,
+            crate::neo4j::writer::DecisionEvidence,
+            crate::neo4j::writer::RetrievedSnippet,
+            TruthDependentsResponse,
+            EventDecisionsResponse,
+            crate::neo4j::writer::EventDecision,
+            StatsQuery,
+            StatsResponse,
+            AgentTraceCount,
+            crate::neo4j::writer::DayStats,
+            crate::neo4j::writer::TopicCount,
+            StaleDecision,
+            StaleDecisionsResponse,
+            ApprovalResponse,
+            SimilarQuery,
+            SimilarDecision,
+            SimilarDecisionsResponse,
+            TruthImpactQuery,
+            TruthImpactResponse,
+            crate::neo4j::writer::ImpactedDecision,
+            crate::neo4j::writer::RoutedEmployee,
+            crate::neo4j::writer::TruthDependent,
+            CurrentTruthResponse,
+            UrgentMessagesResponse,
+            UrgentMessagesQuery,
+            crate::neo4j::writer::EmailThread,
+            crate::neo4j::writer::ThreadMessage,
+            PrivateNote,
+            PrivateNotesResponse,
+            ClearMemoryResponse,
+            CypherQueryRequest,
+            CypherQueryResponse,
+            AuditQuery,
+            AuditListResponse,
+            BackfillRolesResponse,
+            EmployeeAliasRequest,
+            EmployeeAliasResponse,
+            crate::neo4j::writer::AliasMerge,
+            RepairCommunicationsResponse,
+            IdentitySuggestionsResponse,
+            crate::neo4j::writer::IdentitySuggestion,
+            crate::neo4j::writer::IdentityCandidate,
+            ClusterListQuery,
+            ClustersResponse,
+            crate::neo4j::writer::KnowledgeClusterSummary,
+            crate::neo4j::writer::ClusterTopic,
+            crate::neo4j::writer::KnowledgeClusterDetail,
+            crate::neo4j::writer::ClusterMember,
+            crate::neo4j::writer::ClusterDecision,
+            RelabelClustersResponse,
+            crate::config::CosConfig,
+            crate::config::HttpConfig,
+            crate::config::Neo4jConfig,
+            crate::config::LlmConfig,
+            crate::config::SpeechConfig,
+            crate::config::RagConfig,
+            crate::config::CorsConfig,
+            crate::config::LimitsConfig,
+            CompactQuery,
+            CompactResponse,
+            crate::audit::AuditEvent,
+            Pagination,
+            TraceQuery,
+            TraceExportQuery
+        )
+    ),
+    tags(
+        (name = "cos", description = "AI Chief of Staff backend")
+    )
+)]
+pub struct ApiDoc;
+
+pub fn app(state: ApiState) -> Router {
+    let cors = build_cors_layer();
+
+    Router::new()
+        .route("/health", get(health))
+        .route("/v1/ask", post(ask))
+        .route("/v1/ask/:request_id/cancel", post(cancel_ask))
+        .route("/v1/knowledge", post(ingest_knowledge))
+        .route("/v1/announce", post(announce))
+        .route("/v1/traces", get(list_traces))
+        .route("/v1/traces/export", get(export_traces))
+        .route("/v1/traces/:decision_id/annotations", post(annotate_trace))
+        .route("/v1/agents/:agent_id/traces", get(agent_traces))
+        .route(
+            "/v1/agents/:agent_id/traces/:decision_id/visibility",
+            get(trace_visibility),
+        )
+        .route("/v1/agents/:agent_id/private-notes", get(agent_private_notes))
+        .route("/v1/agents/:agent_id/memory", delete(clear_agent_memory))
+        .route("/v1/graph/snapshot", get(graph_snapshot))
+        .route("/v1/agents/:agent_id/graph/snapshot", get(agent_graph_snapshot))
+        .route("/v1/decisions/current", get(current_decisions))
+        .route("/v1/decisions/:decision_id/diff", get(decision_diff))
+        .route("/v1/decisions/:decision_id/evidence", get(decision_evidence))
+        .route("/v1/decisions/:decision_id/report", get(decision_report))
+        .route("/v1/decisions/:decision_id/similar", get(similar_decisions))
+        .route("/v1/decisions/stale", get(stale_decisions))
+        .route("/v1/stats", get(stats))
+        .route("/v1/decisions/:decision_id/refresh", post(refresh_decision))
+        .route(
+            "/v1/decisions/:decision_id/versions/:version/approve",
+            post(approve_decision_version),
+        )
+        .route("/v1/decisions/:decision_id/replay", post(replay_decision))
+        .route("/v1/truth/current", get(current_truth))
+        .route("/v1/truth/:truth_id/dependents", get(truth_dependents))
+        .route("/v1/events/:event_id/decisions", get(event_decisions))
+        .route("/v1/truth/:truth_id/impact", get(truth_impact))
+        .route("/v1/messages/urgent", get(urgent_messages))
+        .route("/v1/threads/:thread_id", get(email_thread))
+        .route("/v1/graph/cypher", post(graph_cypher))
+        .route("/v1/admin/audit", get(audit_log))
+        .route("/v1/admin/backfill-roles", post(backfill_roles))
+        .route("/v1/admin/repair-communications", post(repair_communications))
+        .route("/v1/admin/identity-suggestions", get(identity_suggestions))
+        .route("/v1/employees/:employee_id/aliases", post(link_employee_alias))
+        .route("/v1/clusters", get(list_clusters))
+        .route("/v1/clusters/:cluster_id", get(cluster_detail))
+        .route("/v1/admin/relabel-clusters", post(relabel_clusters))
+        .route("/v1/admin/config", get(admin_config))
+        .route("/v1/maintenance/compact", post(compact_versions))
+        .route("/v1/stream", get(sse_stream))
+        .route("/openapi.json", get(openapi_json))
+        .route("/openapi.yaml", get(openapi_yaml))
+        .route_layer(axum::middleware::from_fn(audit_middleware))
+        .with_state(state)
+        .layer(cors)
+}
+
+/// Attaches audit details for the audit middleware; the response itself is unchanged.
+fn with_audit(
+    mut resp: axum::response::Response,
+    employee_id: Option<String>,
+    resource_ids: Vec<String>,
+) -> axum::response::Response {
+    resp.extensions_mut().insert(crate::audit::AuditInfo {
+        employee_id,
+        resource_ids,
+    });
+    resp
+}
+
+fn audit_resource_ids(nodes: &[GraphNode], key: &str) -> Vec<String> {
+    nodes
+        .iter()
+        .filter_map(|n| n.properties.get(key).and_then(|v| v.as_str()).map(|s| s.to_string()))
+        .collect()
+}
+
+/// Records who called which data route and with what outcome. Runs after the handler, and
+/// only queues the event, so it adds no storage round-trip to the request.
+async fn audit_middleware(
+    matched: Option<axum::extract::MatchedPath>,
+    req: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let route = matched
+        .map(|m| m.as_str().to_string())
+        .unwrap_or_else(|| req.uri().path().to_string());
+    if !crate::audit::is_audited_route(&route) {
+        return next.run(req).await;
+    }
+
+    let path = req.uri().path().to_string();
+    let method = req.method().to_string();
+    let header_employee = resolve_employee_agent_id(req.headers(), None, None).ok();
+
+    let resp = next.run(req).await;
+
+    let info = resp
+        .extensions()
+        .get::<crate::audit::AuditInfo>()
+        .cloned()
+        .unwrap_or_default();
+    crate::audit::record(crate::audit::AuditEvent::new(
+        info.employee_id.or(header_employee),
+        route,
+        path,
+        method,
+        info.resource_ids,
+        resp.status().as_u16(),
+    ));
+    resp
+}
+
+fn unauthorized() -> axum::response::Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(json!({"error": "unauthorized"})),
+    )
+        .into_response()
+}
+
+fn forbidden() -> axum::response::Response {
+    (StatusCode::FORBIDDEN, Json(json!({"error": "forbidden"}))).into_response()
+}
+
+/// The caller's agent id when the caller is the CEO; otherwise the 400 (no identity) or 403
+/// to return.
+async fn require_ceo(headers: &HeaderMap) -> Result<String, ErrorResponse> {
+    let agent_id = resolve_canonical_agent_id(headers, None, None).await?;
+    if employee_role_from_agent_id(&agent_id) != EmployeeRole::Ceo {
+        return Err(Box::new(forbidden()));
+    }
+    Ok(agent_id)
+}
+
+fn neo4j_unavailable() -> axum::response::Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({"error": "neo4j not initialized"})),
+    )
+        .into_response()
+}
+
+/// The storage a handler reads: Neo4j, or the in-process store of `COS_STORAGE=memory`.
+enum Store {
+    Neo4j(crate::neo4j::Neo4jClient),
+    Memory(Arc<tokio::sync::Mutex<crate::memory_store::MemoryStore>>),
+}
+
+/// Neo4j when connected, else the memory store; the 503 to return when neither is initialized.
+fn require_store() -> Result<Store, ErrorResponse> {
+    let state = handles();
+    match (state.neo4j, state.memory) {
+        (Some(client), _) => Ok(Store::Neo4j(client)),
+        (None, Some(memory)) => Ok(Store::Memory(memory)),
+        (None, None) => Err(Box::new(neo4j_unavailable())),
+    }
+}
+
+fn auth_ok(headers: &HeaderMap, state: &ApiState) -> bool {
+    let Some(expected) = &state.api_key else {
+        return true;
+    };
+
+    // A per-employee token stands in for the shared key.
+    if bearer_token(headers)
+        .map(|t| employee_tokens().contains_key(t))
+        .unwrap_or(false)
+    {
+        return true;
+    }
+
+    let provided = headers
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    provided == expected
+}
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    responses((status = 200, body = HealthResponse))
+)]
+async fn health() -> impl IntoResponse {
+    let (embedding_cache_hits, embedding_cache_misses) = crate::app_state::embedding_cache_stats();
+    let (webhook_delivered, webhook_failed, webhook_dropped) = crate::webhooks::stats();
+    let degraded = crate::app_state::neo4j_degraded();
+    Json(HealthResponse {
+        ok: !degraded,
+        audit_write_failures: crate::audit::write_failures(),
+        sse_lagged_events: SSE_LAGGED_EVENTS.load(Ordering::Relaxed),
+        persistence_failures: crate::service::persistence_failures(),
+        embedding_cache_hits,
+        embedding_cache_misses,
+        webhook_delivered,
+        webhook_failed,
+        webhook_dropped,
+        degraded,
+        llm_usage: crate::usage::snapshot(),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/ask",
+    request_body = AskRequest,
+    responses(
+        (status = 200, body = AskResponse),
+        (status = 400, body = serde_json::Value),
+        (status = 404, body = serde_json::Value),
+        (status = 409, body = serde_json::Value),
+        (status = 413, body = serde_json::Value),
+        (status = 499, body = serde_json::Value),
+        (status = 500, body = serde_json::Value)
+    )
+)]
+async fn ask(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Json(req): Json<AskRequest>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+
+    // Identity is required (either header or request body field for audio clients).
+    let caller_agent_id = match resolve_canonical_agent_id(
+        &headers,
+        req.employee_name.as_deref(),
+        req.agent_id.as_deref(),
+    )
+    .await
+    {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+
+    let output_format = req.output_format.as_deref().map(str::trim).filter(|f| !f.is_empty());
+    let Some(response_mime) = crate::utils::tts_mime(output_format) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "unsupported output_format",
+                "allowed": crate::utils::TTS_OUTPUT_FORMATS.iter().map(|(f, _)| *f).collect::<Vec<_>>()
+            })),
+        )
+            .into_response();
+    };
+
+    let text = if let Some(t) = req.text.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        t.to_string()
+    } else if let Some(b64) = req.audio_base64.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let bytes = match base64::engine::general_purpose::STANDARD.decode(b64) {
             Ok(b) => b,
             Err(_) => {
                 return (
-                    StatusCode::BAD_REQUEST,
-                    Json(json!({"error": "audio_base64 must be valid base64"})),
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({"error": "audio_base64 must be valid base64"})),
+                )
+                    .into_response();
+            }
+        };
+
+        match crate::utils::elevenlabs_stt_from_bytes(bytes, req.audio_mime.as_deref()).await {
+            Ok(t) => t,
+            Err(e) => return error_response(&e),
+        }
+    } else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "provide either non-empty text or audio_base64"})),
+        )
+            .into_response();
+    };
+
+    let text = crate::service::sanitize_input_text(&text);
+    if text.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "text must contain printable characters"})),
+        )
+            .into_response();
+    }
+    let max_chars = crate::service::ask_max_chars();
+    let text_chars = text.chars().count();
+    if text_chars > max_chars {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Json(json!({
+                "error": "text too long",
+                "max_chars": max_chars,
+                "length": text_chars
+            })),
+        )
+            .into_response();
+    }
+
+    let context = match ask_context(&req).await {
+        Ok(context) => context,
+        Err(resp) => return resp,
+    };
+
+    // A client may pick the id itself to be able to cancel before the first progress event.
+    let request_id = match headers.get(REQUEST_ID_HEADER).and_then(|v| v.to_str().ok()) {
+        Some(raw) => match uuid::Uuid::parse_str(raw.trim()) {
+            Ok(id) => id,
+            Err(_) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({"error": "x-request-id must be a UUID"})),
+                )
+                    .into_response();
+            }
+        },
+        None => uuid::Uuid::new_v4(),
+    };
+    let cancel = CancellationToken::new();
+    {
+        let mut in_flight = api_state.lock_in_flight();
+        if in_flight.contains_key(&request_id) {
+            return (
+                StatusCode::CONFLICT,
+                Json(json!({"error": "request_id already in flight", "request_id": request_id})),
+            )
+                .into_response();
+        }
+        let owner = EmployeeAgentId(caller_agent_id.clone());
+        in_flight.insert(request_id, (owner, cancel.clone()));
+    }
+    let _in_flight = InFlightAsk { in_flight: api_state.in_flight.clone(), request_id };
+
+    let audit_employee = caller_agent_id.clone();
+    let options = crate::service::AskOptions {
+        progress: Some(api_state.events_tx.clone()),
+        request_id: Some(request_id),
+        cancel: Some(cancel),
+        context,
+        ..Default::default()
+    };
+    let asked = crate::service::ask_and_persist_with(text, Some(caller_agent_id), options).await;
+    let mut resp = match asked {
+        Ok((response_text, trace)) => {
+            // A clarifying question decides nothing: it isn't broadcast or audited as a decision.
+            let needs_clarification =
+                trace.kind.as_deref() == Some(crate::service::CLARIFICATION_KIND);
+            let clarifying_question = needs_clarification.then(|| response_text.clone());
+            let mut audit_ids = Vec::new();
+            if !needs_clarification {
+                audit_ids.push(trace.decision_id.clone());
+                let _ = api_state.events_tx.send(ServerEvent::trace(trace.clone()));
+                if let Some(alert) = low_confidence_alert(&trace, low_confidence_threshold()) {
+                    let _ = api_state.events_tx.send(alert);
+                }
+            }
+            let want_audio = req.response_audio.unwrap_or(false);
+            let resp = if want_audio {
+                let tts = crate::utils::elevenlabs_tts_bytes(
+                    &response_text,
+                    trace.language.as_deref(),
+                    output_format,
+                )
+                .await;
+                match tts {
+                    Ok(bytes) => {
+                        let audio_base64 = Some(base64::engine::general_purpose::STANDARD.encode(bytes));
+                        let audio_mime = Some(response_mime.to_string());
+                        (
+                            StatusCode::OK,
+                            Json(AskResponse {
+                                response_text,
+                                trace,
+                                audio_base64,
+                                audio_mime,
+                                needs_clarification,
+                                clarifying_question,
+                            }),
+                        )
+                            .into_response()
+                    }
+                    Err(e) => error_response(&e),
+                }
+            } else {
+                (
+                    StatusCode::OK,
+                    Json(AskResponse {
+                        response_text,
+                        trace,
+                        audio_base64: None,
+                        audio_mime: None,
+                        needs_clarification,
+                        clarifying_question,
+                    }),
+                )
+                    .into_response()
+            };
+            with_audit(resp, Some(audit_employee), audit_ids)
+        }
+        Err(e) => error_response(&e),
+    };
+    if let Ok(value) = axum::http::HeaderValue::from_str(&request_id.to_string()) {
+        resp.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    resp
+}
+
+/// Validates the request's context documents and truth ids against `COS_ASK_CONTEXT_MAX_DOCS`
+/// and `COS_ASK_CONTEXT_MAX_CHARS` and resolves them.
+async fn ask_context(
+    req: &AskRequest,
+) -> Result<Vec<crate::service::ContextDocument>, axum::response::Response> {
+    let max_docs = crate::service::ask_context_max_docs();
+    let count = req.context_documents.len() + req.context_truth_ids.len();
+    if count > max_docs {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "too many context documents",
+                "max_documents": max_docs,
+                "count": count
+            })),
+        )
+            .into_response());
+    }
+
+    let max_chars = crate::service::ask_context_max_chars();
+    let mut documents = Vec::with_capacity(req.context_documents.len());
+    for (index, doc) in req.context_documents.iter().enumerate() {
+        let doc = crate::service::sanitize_input_text(doc);
+        if doc.is_empty() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "context documents must not be empty", "index": index})),
+            )
+                .into_response());
+        }
+        let length = doc.chars().count();
+        if length > max_chars {
+            return Err((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Json(json!({
+                    "error": "context document too long",
+                    "index": index,
+                    "max_chars": max_chars,
+                    "length": length
+                })),
+            )
+                .into_response());
+        }
+        documents.push(doc);
+    }
+
+    let mut truth_ids: Vec<String> = Vec::with_capacity(req.context_truth_ids.len());
+    for id in &req.context_truth_ids {
+        let id = id.trim();
+        if id.is_empty() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "context_truth_ids must not be empty"})),
+            )
+                .into_response());
+        }
+        if !truth_ids.iter().any(|t| t == id) {
+            truth_ids.push(id.to_string());
+        }
+    }
+
+    crate::service::resolve_context(&documents, &truth_ids)
+        .await
+        .map_err(|e| error_response(&e))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CancelAskResponse {
+    pub request_id: uuid::Uuid,
+    pub cancelled: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/ask/{request_id}/cancel",
+    params(("request_id" = uuid::Uuid, Path, description = "From the ask's x-request-id")),
+    responses(
+        (status = 200, body = CancelAskResponse),
+        (status = 404, body = serde_json::Value)
+    )
+)]
+async fn cancel_ask(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(request_id): Path<uuid::Uuid>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+    // Someone else's ask looks the same as a finished one.
+    let token = api_state
+        .lock_in_flight()
+        .get(&request_id)
+        .filter(|(owner, _)| owner.0 == caller_agent_id)
+        .map(|(_, token)| token.clone());
+    let Some(token) = token else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "no such ask in flight", "request_id": request_id})),
+        )
+            .into_response();
+    };
+    token.cancel();
+    let resp = Json(CancelAskResponse { request_id, cancelled: true }).into_response();
+    with_audit(resp, Some(caller_agent_id), vec![request_id.to_string()])
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/knowledge",
+    request_body = KnowledgeIngestRequest,
+    responses(
+        (status = 200, body = KnowledgeIngestResponse),
+        (status = 400, body = serde_json::Value),
+        (status = 500, body = serde_json::Value)
+    )
+)]
+async fn ingest_knowledge(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Json(req): Json<KnowledgeIngestRequest>,
+) -> axum::response::Response {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+
+    if req.truth_id.trim().is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "truth_id must be non-empty"})),
+        )
+            .into_response();
+    }
+    if req.kind.trim().is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "kind must be non-empty"})),
+        )
+            .into_response();
+    }
+    if !req.routing.is_object() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "routing must be an object mapping agent_id -> level"})),
+        )
+            .into_response();
+    }
+
+    let add_to_rag = req.add_to_rag.unwrap_or(true);
+    let kind = req.kind.clone();
+    match crate::service::ingest_knowledge(
+        req.truth_id,
+        req.kind,
+        req.content,
+        req.agent_id,
+        req.routing,
+        add_to_rag,
+        req.force,
+    )
+    .await
+    {
+        Ok(trace) => {
+            let _ = api_state.events_tx.send(ServerEvent::trace(trace.clone()));
+            let mut ids = vec![trace.decision_id.clone()];
+            // Unchanged content gives the OrgBrain nothing new to react to.
+            let (analysis_trace, analysis_error) = if req.analyze && !trace.deduplicated {
+                match crate::service::analyze_knowledge(&trace, &kind).await {
+                    Ok(analysis) => {
+                        let _ = api_state.events_tx.send(ServerEvent::trace(analysis.clone()));
+                        ids.push(analysis.decision_id.clone());
+                        (Some(analysis), None)
+                    }
+                    Err(e) => {
+                        eprintln!("knowledge analysis for {}: {e:#}", trace.decision_id);
+                        (None, Some(e.to_string()))
+                    }
+                }
+            } else {
+                (None, None)
+            };
+            let resp = Json(KnowledgeIngestResponse {
+                correlation_id: trace.trigger_events.first().copied(),
+                trace,
+                analysis_trace,
+                analysis_error,
+            })
+            .into_response();
+            with_audit(resp, None, ids)
+        }
+        Err(e) => error_response(&e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/announce",
+    request_body = AnnounceRequest,
+    responses(
+        (status = 200, body = ReasoningTrace),
+        (status = 400, body = serde_json::Value),
+        (status = 403, body = serde_json::Value),
+        (status = 500, body = serde_json::Value)
+    )
+)]
+async fn announce(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Json(req): Json<AnnounceRequest>,
+) -> axum::response::Response {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let caller_agent_id = match require_ceo(&headers).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+    if req.title.trim().is_empty() || req.body.trim().is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "title and body must be non-empty"})),
+        )
+            .into_response();
+    }
+    let routing = req.routing.unwrap_or_default();
+    if let Some((agent, level)) = routing
+        .iter()
+        .find(|(_, level)| !crate::visibility::VISIBILITY_LEVELS.contains(&level.as_str()))
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": "routing levels must be full, summary or none",
+                "agent_id": agent,
+                "level": level
+            })),
+        )
+            .into_response();
+    }
+
+    match crate::service::announce(req.title, req.body, caller_agent_id.clone(), routing).await {
+        Ok(trace) => {
+            let _ = api_state.events_tx.send(ServerEvent::trace(trace.clone()));
+            let ids = vec![format!("{}:v{}", trace.decision_id, trace.version)];
+            with_audit(Json(trace).into_response(), Some(caller_agent_id), ids)
+        }
+        Err(e) => error_response(&e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/traces",
+    params(TraceQuery),
+    responses((status = 200, body = TraceListResponse))
+)]
+async fn list_traces(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Query(p): Query<TraceQuery>,
+) -> axum::response::Response {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    // Only CEO may view all traces.
+    let agent_id = match require_ceo(&headers).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+
+    let limit = p.limit.unwrap_or(50);
+    let traces: Vec<ReasoningTrace> = traces()
+        .await
+        .iter()
+        .rev()
+        .filter(|t| p.matches(t))
+        .take(limit)
+        .cloned()
+        .collect();
+    let ids = traces.iter().map(|t| t.decision_id.clone()).collect();
+    let resp = (StatusCode::OK, Json(TraceListResponse { traces })).into_response();
+    with_audit(resp, Some(agent_id), ids)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/traces/export",
+    params(TraceExportQuery),
+    responses((status = 200, body = String, content_type = "application/x-ndjson", description = "One ReasoningTrace per line"))
+)]
+async fn export_traces(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Query(q): Query<TraceExportQuery>,
+) -> axum::response::Response {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    // Only CEO may export all traces.
+    if let Err(resp) = require_ceo(&headers).await {
+        return *resp;
+    }
+
+    // Walk the trace list one entry at a time so the full set is never buffered.
+    let since = q.since;
+    let lines = stream::unfold(0usize, move |mut idx| async move {
+        loop {
+            let trace = traces().await.get(idx).cloned()?;
+            idx += 1;
+            if since.map(|s| trace.created_at >= s).unwrap_or(true) {
+                let mut line = serde_json::to_string(&trace).unwrap_or_else(|_| "{}".to_string());
+                line.push('\n');
+                return Some((Ok::<_, Infallible>(line), idx));
+            }
+        }
+    });
+
+    (
+        StatusCode::OK,
+        [(axum::http::header::CONTENT_TYPE, "application/x-ndjson")],
+        axum::body::Body::from_stream(lines),
+    )
+        .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/decisions/{decision_id}/report",
+    params(
+        ("decision_id" = String, Path, description = "Decision id"),
+        DecisionReportQuery
+    ),
+    responses(
+        (status = 200, body = String, content_type = "text/markdown", description = "Or HTML"),
+        (status = 400, body = serde_json::Value),
+        (status = 403, body = serde_json::Value),
+        (status = 404, body = serde_json::Value)
+    )
+)]
+async fn decision_report(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(decision_id): Path<String>,
+    Query(q): Query<DecisionReportQuery>,
+) -> axum::response::Response {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+    let html = match q.format.as_deref().map(str::trim) {
+        None | Some("") | Some("markdown") | Some("md") => false,
+        Some("html") => true,
+        Some(other) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "format must be markdown or html", "format": other})),
+            )
+                .into_response();
+        }
+    };
+
+    // Every version of the decision the caller may see, cut to their level, one per version.
+    let rules = handles().visibility_rules;
+    let mut found = false;
+    let mut versions: std::collections::BTreeMap<i64, (ReasoningTrace, String)> =
+        Default::default();
+    for t in traces().await.iter().filter(|t| t.decision_id == decision_id) {
+        found = true;
+        let level = visibility_for_agent(&rules, t, &caller_agent_id);
+        if level == "none" {
+            continue;
+        }
+        let mut tt = t.clone();
+        if level == "summary" {
+            tt.evidence = Vec::new();
+            tt.assumptions = Vec::new();
+        }
+        versions.insert(tt.version, (tt, level));
+    }
+    let Some((trace, level)) = versions.values().next_back().cloned() else {
+        let (status, error) = if found {
+            (StatusCode::FORBIDDEN, "forbidden")
+        } else {
+            (StatusCode::NOT_FOUND, "decision not found")
+        };
+        return (status, Json(json!({"error": error}))).into_response();
+    };
+    let history: Vec<ReasoningTrace> = versions.into_values().map(|(t, _)| t).collect();
+
+    let report = crate::report::DecisionReport { trace: &trace, history: &history, level: &level };
+    let (content_type, body) = if html {
+        ("text/html; charset=utf-8", report.html())
+    } else {
+        ("text/markdown; charset=utf-8", report.markdown())
+    };
+    let ids = history.iter().map(|t| format!("{}:v{}", t.decision_id, t.version)).collect();
+    let resp = (StatusCode::OK, [(axum::http::header::CONTENT_TYPE, content_type)], body)
+        .into_response();
+    with_audit(resp, Some(caller_agent_id), ids)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/traces/{decision_id}/annotations",
+    params(("decision_id" = String, Path, description = "Decision id")),
+    request_body = AnnotationRequest,
+    responses(
+        (status = 200, body = AnnotationResponse),
+        (status = 400, body = serde_json::Value),
+        (status = 403, body = serde_json::Value),
+        (status = 404, body = serde_json::Value),
+        (status = 500, body = serde_json::Value)
+    )
+)]
+async fn annotate_trace(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(decision_id): Path<String>,
+    Json(req): Json<AnnotationRequest>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+
+    let text = crate::service::sanitize_input_text(&req.text);
+    if text.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "text must be non-empty"})),
+        )
+            .into_response();
+    }
+    let text = crate::redaction::redact(&text);
+
+    // Annotate the latest version; the caller needs at least summary visibility on it.
+    let rules = handles().visibility_rules;
+    let all_traces = traces().await;
+    let Some(trace) = all_traces
+        .iter()
+        .rev()
+        .find(|t| t.decision_id == decision_id)
+    else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "trace not found"})),
+        )
+            .into_response();
+    };
+    if visibility_for_agent(&rules, trace, &caller_agent_id) == "none" {
+        return forbidden();
+    }
+    let version = trace.version;
+    drop(all_traces);
+    let state = handles();
+    let (neo4j, memory) = (state.neo4j, state.memory);
+
+    let annotation = Annotation {
+        annotation_id: uuid::Uuid::new_v4().to_string(),
+        author: caller_agent_id.clone(),
+        text,
+        created_at: chrono::Utc::now(),
+    };
+
+    if let Some(client) = neo4j {
+        let persisted = client.breaker().call(crate::neo4j::writer::persist_annotation(
+            client.graph(),
+            &decision_id,
+            version,
+            &annotation.annotation_id,
+            &annotation.author,
+            &annotation.text,
+        ));
+        if let Err(e) = persisted.await {
+            return error_response(&e);
+        }
+    } else if let Some(mem) = memory {
+        mem.lock()
+            .await
+            .add_annotation(&decision_id, version, annotation.clone());
+    }
+
+    if let Some(t) = traces()
+        .await
+        .iter_mut()
+        .rev()
+        .find(|t| t.decision_id == decision_id && t.version == version)
+    {
+        t.annotations.push(annotation.clone());
+    }
+
+    let resp = Json(AnnotationResponse {
+        decision_id: decision_id.clone(),
+        version,
+        annotation,
+    })
+    .into_response();
+    with_audit(resp, Some(caller_agent_id), vec![decision_id])
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/agents/{agent_id}/traces",
+    params(
+        ("agent_id" = String, Path, description = "Employee/agent id"),
+        TraceQuery
+    ),
+    responses((status = 200, body = AgentTraceListResponse))
+)]
+async fn agent_traces(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+    Query(p): Query<TraceQuery>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+
+    // Only allow a caller to request their own agent view (or CEO).
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+    let caller_role = employee_role_from_agent_id(&caller_agent_id);
+    if caller_role != EmployeeRole::Ceo && caller_agent_id != agent_id {
+        return forbidden();
+    }
+
+    let limit = p.limit.unwrap_or(50);
+    let rules = handles().visibility_rules;
+    let all_traces = traces().await;
+    let mut out = Vec::new();
+
+    for t in all_traces.iter().rev().filter(|t| p.matches(t)) {
+        let level = visibility_for_agent(&rules, t, &agent_id);
+        if level == "none" {
+            continue;
+        }
+
+        let mut tt = t.clone();
+        if level == "summary" {
+            tt.evidence = Vec::new();
+            tt.assumptions = Vec::new();
+        }
+
+        out.push(tt);
+        if out.len() >= limit {
+            break;
+        }
+    }
+
+    drop(all_traces);
+    let ids = out.iter().map(|t| t.decision_id.clone()).collect();
+    let resp = Json(AgentTraceListResponse {
+        agent_id,
+        traces: out,
+    })
+    .into_response();
+    with_audit(resp, Some(caller_agent_id), ids)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/agents/{agent_id}/traces/{decision_id}/visibility",
+    params(
+        ("agent_id" = String, Path, description = "Employee/agent id"),
+        ("decision_id" = String, Path, description = "Decision id")
+    ),
+    responses(
+        (status = 200, body = VisibilityExplanation),
+        (status = 403, body = serde_json::Value),
+        (status = 404, body = serde_json::Value)
+    )
+)]
+async fn trace_visibility(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path((agent_id, decision_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+    let caller_role = employee_role_from_agent_id(&caller_agent_id);
+    if caller_role != EmployeeRole::Ceo && caller_agent_id != agent_id {
+        return forbidden();
+    }
+
+    // Explains the latest version, the one the trace endpoints show.
+    let rules = handles().visibility_rules;
+    let explanation = traces()
+        .await
+        .iter()
+        .rev()
+        .find(|t| t.decision_id == decision_id)
+        .map(|t| explain_visibility(&rules, t, &agent_id));
+    let Some(explanation) = explanation else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "trace not found"})),
+        )
+            .into_response();
+    };
+    let resp = Json(explanation).into_response();
+    with_audit(resp, Some(caller_agent_id), vec![decision_id])
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/agents/{agent_id}/private-notes",
+    params(("agent_id" = String, Path, description = "Employee/agent id")),
+    responses(
+        (status = 200, body = PrivateNotesResponse),
+        (status = 403, body = serde_json::Value),
+        (status = 500, body = serde_json::Value)
+    )
+)]
+async fn agent_private_notes(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+
+    // Private notes are decrypted for their owner only; the CEO does not get an override here.
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+    if caller_agent_id != agent_id {
+        return forbidden();
+    }
+
+    let notes = sessions()
+        .await
+        .read_private_notes(&EmployeeAgentId(agent_id.clone()));
+    let notes = match notes {
+        Ok(n) => n,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": e.to_string()})),
+            )
+                .into_response();
+        }
+    };
+
+    Json(PrivateNotesResponse {
+        agent_id,
+        notes: notes
+            .into_iter()
+            .map(|(k, content)| PrivateNote { key: k.0, content })
+            .collect(),
+    })
+    .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/graph/snapshot",
+    params(Pagination),
+    responses(
+        (status = 200, body = GraphSnapshotResponse),
+        (status = 500, body = serde_json::Value),
+        (status = 503, body = serde_json::Value)
+    )
+)]
+async fn graph_snapshot(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Query(p): Query<Pagination>,
+) -> axum::response::Response {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let limit = p.limit.unwrap_or(5000) as i64;
+
+    let client = match require_store() {
+        Ok(Store::Neo4j(c)) => c,
+        Ok(Store::Memory(mem)) => {
+            let (nodes, edges) = mem.lock().await.snapshot(limit as usize);
+            return Json(GraphSnapshotResponse { nodes, edges }).into_response();
+        }
+        Err(resp) => return *resp,
+    };
+
+    if let Err(e) = client.breaker().check() {
+        return error_response(&e);
+    }
+    let graph = client.graph();
+
+    let node_query = neo4rs::query(
+        r#"
+MATCH (n)
+WITH n,
+     CASE
+       WHEN n.sent_at IS NULL THEN properties(n)
+       ELSE n { .*, sent_at: toString(n.sent_at) }
+     END AS p,
+     toString(n.created_at) AS created_at_s,
+     coalesce(
+       n.name,
+       n.label,
+       n.summary,
+       n.decision,
+       n.truth_id,
+       n.employee_id,
+       n.team_id,
+       n.topic,
+       n.decision_id,
+       n.decision_version_id,
+       n.truth_version_id,
+       elementId(n)
+     ) AS display_label
+WITH n, p, created_at_s,
+     CASE
+       WHEN display_label = elementId(n) THEN coalesce(head(labels(n)), 'Node') + ':' + display_label
+       ELSE display_label
+     END AS display_label2
+RETURN elementId(n) AS id,
+       labels(n) AS labels,
+       p { .*, label: display_label2, created_at: created_at_s } AS props
+LIMIT $limit
+"#,
+    )
+    .param("limit", limit);
+
+    let edge_query = neo4rs::query(
+        r#"
+MATCH (a)-[r]->(b)
+WITH a, r, b,
+     properties(r) AS p,
+     toString(r.created_at) AS created_at_s,
+     coalesce(r.name, r.label, type(r)) AS display_label
+RETURN elementId(r) AS id,
+       type(r) AS t,
+       elementId(a) AS from,
+       elementId(b) AS to,
+       p { .*, label: display_label, created_at: created_at_s } AS props
+LIMIT $limit
+"#,
+    )
+    .param("limit", limit);
+
+    let mut nodes_out = Vec::new();
+    let execute = graph.execute(node_query);
+    let mut stream = match with_timeout("neo4j", neo4j_timeout(), execute).await {
+        Ok(s) => s,
+        Err(e) => return error_response(&e),
+    };
+
+    while let Ok(Some(row)) = stream.next().await {
+        let id: String = row.get("id").unwrap_or_default();
+        let labels: Vec<String> = row.get("labels").unwrap_or_default();
+        let properties = match row.get::<neo4rs::BoltType>("props") {
+            Ok(v) => bolt_to_json(v),
+            Err(_) => serde_json::Value::Null,
+        };
+
+        nodes_out.push(GraphNode {
+            id,
+            labels,
+            properties,
+        });
+    }
+
+    let mut edges_out = Vec::new();
+    let execute = graph.execute(edge_query);
+    let mut stream = match with_timeout("neo4j", neo4j_timeout(), execute).await {
+        Ok(s) => s,
+        Err(e) => return error_response(&e),
+    };
+
+    while let Ok(Some(row)) = stream.next().await {
+        let id: String = row.get("id").unwrap_or_default();
+        let edge_type: String = row.get("t").unwrap_or_default();
+        let from: String = row.get("from").unwrap_or_default();
+        let to: String = row.get("to").unwrap_or_default();
+        let properties = match row.get::<neo4rs::BoltType>("props") {
+            Ok(v) => bolt_to_json(v),
+            Err(_) => serde_json::Value::Null,
+        };
+
+        edges_out.push(GraphEdge {
+            id,
+            edge_type,
+            from,
+            to,
+            properties,
+        });
+    }
+
+    Json(GraphSnapshotResponse {
+        nodes: nodes_out,
+        edges: edges_out,
+    })
+    .into_response()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/agents/{agent_id}/memory",
+    params(("agent_id" = String, Path, description = "Employee/agent id")),
+    responses(
+        (status = 200, body = ClearMemoryResponse),
+        (status = 403, body = serde_json::Value),
+        (status = 500, body = serde_json::Value)
+    )
+)]
+async fn clear_agent_memory(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+    if caller_agent_id != agent_id
+        && employee_role_from_agent_id(&caller_agent_id) != EmployeeRole::Ceo
+    {
+        return forbidden();
+    }
+
+    let cached = sessions()
+        .await
+        .conversation_cache
+        .remove(&EmployeeAgentId(agent_id.clone()))
+        .map(|turns| turns.len() as i64)
+        .unwrap_or(0);
+    let neo4j = handles().neo4j;
+    // Neo4j holds the full history; the cache only the recent turns.
+    let turns_cleared = match neo4j {
+        Some(client) => match client
+            .breaker()
+            .call(crate::neo4j::writer::clear_conversation(client.graph(), &agent_id))
+            .await
+        {
+            Ok(n) => n,
+            Err(e) => return error_response(&e),
+        },
+        None => cached,
+    };
+
+    let resp = Json(ClearMemoryResponse {
+        agent_id,
+        turns_cleared,
+    })
+    .into_response();
+    with_audit(resp, Some(caller_agent_id), Vec::new())
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/agents/{agent_id}/graph/snapshot",
+    params(
+        ("agent_id" = String, Path, description = "Employee/agent id"),
+        Pagination
+    ),
+    responses(
+        (status = 200, body = GraphSnapshotResponse),
+        (status = 500, body = serde_json::Value),
+        (status = 503, body = serde_json::Value)
+    )
+)]
+async fn agent_graph_snapshot(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(agent_id): Path<String>,
+    Query(p): Query<Pagination>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+
+    let limit = p.limit.unwrap_or(5000) as i64;
+
+    let client = match require_store() {
+        Ok(Store::Neo4j(c)) => c,
+        Ok(Store::Memory(mem)) => {
+            let (nodes, edges) = mem.lock().await.agent_snapshot(&agent_id, limit as usize);
+            return Json(GraphSnapshotResponse { nodes, edges }).into_response();
+        }
+        Err(resp) => return *resp,
+    };
+
+    if let Err(e) = client.breaker().check() {
+        return error_response(&e);
+    }
+    let graph = client.graph();
+
+    let q = neo4rs::query(
+        r#"
+MATCH (n)
+WHERE (n:DecisionVersion OR n:TruthVersion) AND $agent_id IN coalesce(n.routing_agents, [])
+WITH collect(n) AS versions
+UNWIND versions AS v
+OPTIONAL MATCH (a)-[r]->(b)
+WHERE a = v OR b = v
+WITH a, r, b,
+     properties(a) AS a_p,
+     properties(r) AS r_p,
+     properties(b) AS b_p,
+     toString(a.created_at) AS a_created_at_s,
+     toString(r.created_at) AS r_created_at_s,
+     toString(b.created_at) AS b_created_at_s,
+     coalesce(
+       a.name,
+       a.label,
+       a.summary,
+       a.decision,
+       a.truth_id,
+       a.employee_id,
+       a.team_id,
+       a.topic,
+       a.decision_id,
+       a.decision_version_id,
+       a.truth_version_id,
+       elementId(a)
+     ) AS a_display_label,
+     coalesce(r.name, r.label, type(r)) AS r_display_label,
+     coalesce(
+       b.name,
+       b.label,
+       b.summary,
+       b.decision,
+       b.truth_id,
+       b.employee_id,
+       b.team_id,
+       b.topic,
+       b.decision_id,
+       b.decision_version_id,
+       b.truth_version_id,
+       elementId(b)
+     ) AS b_display_label
+WITH a, r, b,
+     a_p, r_p, b_p,
+     a_created_at_s, r_created_at_s, b_created_at_s,
+     CASE
+       WHEN a_display_label = elementId(a) THEN coalesce(head(labels(a)), 'Node') + ':' + a_display_label
+       ELSE a_display_label
+     END AS a_display_label2,
+     r_display_label,
+     CASE
+       WHEN b_display_label = elementId(b) THEN coalesce(head(labels(b)), 'Node') + ':' + b_display_label
+       ELSE b_display_label
+     END AS b_display_label2
+RETURN elementId(a) AS a_id,
+       labels(a) AS a_labels,
+       a_p { .*, label: a_display_label2, created_at: a_created_at_s } AS a_props,
+       elementId(r) AS r_id,
+       type(r) AS r_type,
+       r_p { .*, label: r_display_label, created_at: r_created_at_s } AS r_props,
+       elementId(b) AS b_id,
+       labels(b) AS b_labels,
+       b_p { .*, label: b_display_label2, created_at: b_created_at_s } AS b_props
+LIMIT $limit
+"#,
+    )
+    .param("agent_id", agent_id)
+    .param("limit", limit);
+
+    let mut nodes: HashMap<String, GraphNode> = HashMap::new();
+    let mut edges: HashMap<String, GraphEdge> = HashMap::new();
+
+    let execute = graph.execute(q);
+    let mut stream = match with_timeout("neo4j", neo4j_timeout(), execute).await {
+        Ok(s) => s,
+        Err(e) => return error_response(&e),
+    };
+
+    while let Ok(Some(row)) = stream.next().await {
+        let a_id: String = row.get("a_id").unwrap_or_default();
+        if !a_id.is_empty() {
+            let a_labels: Vec<String> = row.get("a_labels").unwrap_or_default();
+            let a_props = match row.get::<neo4rs::BoltType>("a_props") {
+                Ok(v) => bolt_to_json(v),
+                Err(_) => serde_json::Value::Null,
+            };
+            nodes.entry(a_id.clone()).or_insert(GraphNode {
+                id: a_id,
+                labels: a_labels,
+                properties: a_props,
+            });
+        }
+
+        let b_id: String = row.get("b_id").unwrap_or_default();
+        if !b_id.is_empty() {
+            let b_labels: Vec<String> = row.get("b_labels").unwrap_or_default();
+            let b_props = match row.get::<neo4rs::BoltType>("b_props") {
+                Ok(v) => bolt_to_json(v),
+                Err(_) => serde_json::Value::Null,
+            };
+            nodes.entry(b_id.clone()).or_insert(GraphNode {
+                id: b_id,
+                labels: b_labels,
+                properties: b_props,
+            });
+        }
+
+        let r_id: String = row.get("r_id").unwrap_or_default();
+        if !r_id.is_empty() {
+            let r_type: String = row.get("r_type").unwrap_or_default();
+            let r_props = match row.get::<neo4rs::BoltType>("r_props") {
+                Ok(v) => bolt_to_json(v),
+                Err(_) => serde_json::Value::Null,
+            };
+            let from: String = row.get("a_id").unwrap_or_default();
+            let to: String = row.get("b_id").unwrap_or_default();
+            edges.entry(r_id.clone()).or_insert(GraphEdge {
+                id: r_id,
+                edge_type: r_type,
+                from,
+                to,
+                properties: r_props,
+            });
+        }
+    }
+
+    Json(GraphSnapshotResponse {
+        nodes: nodes.into_values().collect(),
+        edges: edges.into_values().collect(),
+    })
+    .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/decisions/current",
+    params(Pagination),
+    responses(
+        (status = 200, body = CurrentDecisionsResponse),
+        (status = 500, body = serde_json::Value),
+        (status = 503, body = serde_json::Value)
+    )
+)]
+async fn current_decisions(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Query(p): Query<Pagination>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+
+    let limit = p.limit.unwrap_or(200) as i64;
+    let client = match require_store() {
+        Ok(Store::Neo4j(c)) => c,
+        Ok(Store::Memory(mem)) => {
+            let (decisions, decision_versions) = mem.lock().await.current_decisions(limit as usize);
+            let ids = audit_resource_ids(&decision_versions, "decision_version_id");
+            let resp = Json(CurrentDecisionsResponse {
+                decisions,
+                decision_versions,
+            })
+            .into_response();
+            return with_audit(resp, None, ids);
+        }
+        Err(resp) => return *resp,
+    };
+
+    if let Err(e) = client.breaker().check() {
+        return error_response(&e);
+    }
+    let graph = client.graph();
+    let q = neo4rs::query(
+        r#"
+MATCH (d:Decision)-[:CURRENT]->(dv:DecisionVersion)
+RETURN elementId(d) AS d_id, labels(d) AS d_labels, properties(d) AS d_props,
+       elementId(dv) AS dv_id, labels(dv) AS dv_labels, properties(dv) AS dv_props
+LIMIT $limit
+"#,
+    )
+    .param("limit", limit);
+
+    let mut decisions: HashMap<String, GraphNode> = HashMap::new();
+    let mut versions: HashMap<String, GraphNode> = HashMap::new();
+    let execute = graph.execute(q);
+    let mut stream = match with_timeout("neo4j", neo4j_timeout(), execute).await {
+        Ok(s) => s,
+        Err(e) => return error_response(&e),
+    };
+
+    while let Ok(Some(row)) = stream.next().await {
+        let d_id: String = row.get("d_id").unwrap_or_default();
+        let d_labels: Vec<String> = row.get("d_labels").unwrap_or_default();
+        let d_props = match row.get::<neo4rs::BoltType>("d_props") {
+            Ok(v) => bolt_to_json(v),
+            Err(_) => serde_json::Value::Null,
+        };
+        decisions.entry(d_id.clone()).or_insert(GraphNode {
+            id: d_id,
+            labels: d_labels,
+            properties: d_props,
+        });
+
+        let dv_id: String = row.get("dv_id").unwrap_or_default();
+        let dv_labels: Vec<String> = row.get("dv_labels").unwrap_or_default();
+        let dv_props = match row.get::<neo4rs::BoltType>("dv_props") {
+            Ok(v) => bolt_to_json(v),
+            Err(_) => serde_json::Value::Null,
+        };
+        versions.entry(dv_id.clone()).or_insert(GraphNode {
+            id: dv_id,
+            labels: dv_labels,
+            properties: dv_props,
+        });
+    }
+
+    let decision_versions: Vec<GraphNode> = versions.into_values().collect();
+    let ids = audit_resource_ids(&decision_versions, "decision_version_id");
+    let resp = Json(CurrentDecisionsResponse {
+        decisions: decisions.into_values().collect(),
+        decision_versions,
+    })
+    .into_response();
+    with_audit(resp, None, ids)
+}
+
+/// Visibility of a decision version for `agent_id`, from the routing stored on the version.
+/// The CEO sees everything.
+fn version_visibility(props: &serde_json::Value, agent_id: &str) -> String {
+    if employee_role_from_agent_id(agent_id) == EmployeeRole::Ceo {
+        return "full".to_string();
+    }
+    props
+        .get("routing_json")
+        .and_then(|v| v.as_str())
+        .and_then(|raw| serde_json::from_str::<serde_json::Value>(raw).ok())
+        .and_then(|routing| routing.get(agent_id).and_then(|v| v.as_str()).map(str::to_string))
+        .unwrap_or_else(|| "none".to_string())
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/decisions/{decision_id}/diff",
+    params(
+        ("decision_id" = String, Path, description = "Decision id"),
+        DecisionDiffQuery
+    ),
+    responses(
+        (status = 200, body = DecisionDiffResponse),
+        (status = 403, body = serde_json::Value),
+        (status = 404, body = serde_json::Value),
+        (status = 500, body = serde_json::Value)
+    )
+)]
+async fn decision_diff(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(decision_id): Path<String>,
+    Query(q): Query<DecisionDiffQuery>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+
+    let store = handles().graph_store;
+    let Some(store) = store else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "storage not initialized"})),
+        )
+            .into_response();
+    };
+
+    let mut versions = Vec::with_capacity(2);
+    for version in [q.from, q.to] {
+        match store.decision_version(&decision_id, version).await {
+            Ok(Some(props)) => versions.push(props),
+            Ok(None) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(json!({"error": "decision version not found", "version": version})),
                 )
                     .into_response();
             }
-        };
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({"error": e.to_string()})),
+                )
+                    .into_response();
+            }
+        }
+    }
+    let (from, to) = (&versions[0], &versions[1]);
+
+    // The caller needs at least summary visibility on both versions; summary callers only see
+    // how the summary and confidence changed.
+    let levels = [
+        version_visibility(from, &caller_agent_id),
+        version_visibility(to, &caller_agent_id),
+    ];
+    if levels.iter().any(|l| l == "none") {
+        return forbidden();
+    }
+    let fields: &[&str] = if levels.iter().all(|l| l == "full") {
+        crate::version_diff::DECISION_DIFF_FIELDS
+    } else {
+        &["summary", "confidence"]
+    };
+
+    let resp = Json(DecisionDiffResponse {
+        decision_id: decision_id.clone(),
+        from: q.from,
+        to: q.to,
+        changes: crate::version_diff::diff_properties(from, to, fields),
+    })
+    .into_response();
+    with_audit(
+        resp,
+        Some(caller_agent_id),
+        vec![
+            format!("{}:v{}", decision_id, q.from),
+            format!("{}:v{}", decision_id, q.to),
+        ],
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/decisions/{decision_id}/evidence",
+    params(
+        ("decision_id" = String, Path, description = "Decision id"),
+        DecisionEvidenceQuery
+    ),
+    responses(
+        (status = 200, body = crate::neo4j::writer::DecisionEvidence),
+        (status = 403, body = serde_json::Value),
+        (status = 404, body = serde_json::Value),
+        (status = 500, body = serde_json::Value)
+    )
+)]
+async fn decision_evidence(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(decision_id): Path<String>,
+    Query(q): Query<DecisionEvidenceQuery>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+
+    let store = handles().graph_store;
+    let Some(store) = store else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "storage not initialized"})),
+        )
+            .into_response();
+    };
+
+    let evidence = match store.decision_evidence(&decision_id, q.version).await {
+        Ok(Some(evidence)) => evidence,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(json!({"error": "decision version not found", "version": q.version})),
+            )
+                .into_response();
+        }
+        Err(e) => return error_response(&e),
+    };
+
+    // Raw snippets can reveal more than the summary, so only full visibility sees them.
+    let props = json!({"routing_json": evidence.routing_json});
+    if version_visibility(&props, &caller_agent_id) != "full" {
+        return forbidden();
+    }
+
+    let ids = vec![format!("{}:v{}", decision_id, evidence.version)];
+    let resp = Json(evidence).into_response();
+    with_audit(resp, Some(caller_agent_id), ids)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/stats",
+    params(StatsQuery),
+    responses(
+        (status = 200, body = StatsResponse),
+        (status = 500, body = serde_json::Value)
+    )
+)]
+async fn stats(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Query(q): Query<StatsQuery>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+    let is_ceo = employee_role_from_agent_id(&caller_agent_id) == EmployeeRole::Ceo;
+    let days = q.days.unwrap_or(30).clamp(1, 365);
+
+    let (store, traces_per_agent) = {
+        let state = handles();
+        let since = chrono::Utc::now() - chrono::Duration::days(days);
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for t in traces().await.iter().filter(|t| t.created_at >= since) {
+            let level = visibility_for_agent(&state.visibility_rules, t, &caller_agent_id);
+            if !is_ceo && level == "none" {
+                continue;
+            }
+            for agent in &t.agents_involved {
+                *counts.entry(agent.0.clone()).or_default() += 1;
+            }
+        }
+        let mut counts: Vec<AgentTraceCount> = counts
+            .into_iter()
+            .map(|(agent_id, traces)| AgentTraceCount { agent_id, traces })
+            .collect();
+        counts.sort_by(|a, b| b.traces.cmp(&a.traces).then(a.agent_id.cmp(&b.agent_id)));
+        (state.graph_store, counts)
+    };
+    let Some(store) = store else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "storage not initialized"})),
+        )
+            .into_response();
+    };
+    // Non-CEO callers only count decision versions routed to them.
+    let scope = (!is_ceo).then_some(caller_agent_id.as_str());
+    let decision_stats = match store.decision_stats(days, scope, 10).await {
+        Ok(s) => s,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": e.to_string()})),
+            )
+                .into_response();
+        }
+    };
+
+    let resp = Json(StatsResponse {
+        days,
+        total_decisions: decision_stats.per_day.iter().map(|d| d.decisions).sum(),
+        decisions_per_day: decision_stats.per_day,
+        top_topics: decision_stats.top_topics,
+        traces_per_agent,
+    })
+    .into_response();
+    with_audit(resp, Some(caller_agent_id), Vec::new())
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/decisions/stale",
+    params(Pagination),
+    responses(
+        (status = 200, body = StaleDecisionsResponse),
+        (status = 500, body = serde_json::Value)
+    )
+)]
+async fn stale_decisions(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Query(p): Query<Pagination>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
 
-        match crate::utils::elevenlabs_stt_from_bytes(bytes, req.audio_mime.as_deref()).await {
-            Ok(t) => t,
-            Err(e) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": e.to_string()})),
-                )
-                    .into_response();
-            }
-        }
-    } else {
+    let store = handles().graph_store;
+    let Some(store) = store else {
         return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "provide either non-empty text or audio_base64"})),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "storage not initialized"})),
         )
             .into_response();
     };
+    let activity = match store.decision_activity().await {
+        Ok(a) => a,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": e.to_string()})),
+            )
+                .into_response();
+        }
+    };
+    // Versions marked by the last check and routed to the caller (at least summary).
+    let mut decisions: Vec<StaleDecision> = activity
+        .into_iter()
+        .filter(|a| a.stale)
+        .filter(|a| {
+            version_visibility(&json!({"routing_json": a.routing_json}), &caller_agent_id) != "none"
+        })
+        .map(|a| StaleDecision {
+            score: crate::staleness::staleness_score(&a),
+            decision_id: a.decision_id,
+            version: a.version,
+            summary: a.summary,
+            topic: a.topic,
+            created_at: a.created_at,
+            age_days: a.age_days,
+            newer_emails: a.newer_emails,
+            newer_truth_versions: a.newer_truth_versions,
+        })
+        .collect();
+    decisions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    decisions.truncate(p.limit.unwrap_or(200));
 
-    let resolved_agent_id = resolve_employee_agent_id(
-        &headers,
-        req.employee_name.as_deref(),
-        req.agent_id.as_deref(),
-    );
-    match crate::service::ask_and_persist(text, resolved_agent_id).await {
-        Ok((response_text, trace)) => {
-            let _ = api_state.events_tx.send(ServerEvent::Trace(trace.clone()));
-            let want_audio = req.response_audio.unwrap_or(false);
-            if want_audio {
-                match crate::utils::elevenlabs_tts_to_mp3_bytes(&response_text).await {
-                    Ok(bytes) => {
-                        let audio_base64 = Some(base64::engine::general_purpose::STANDARD.encode(bytes));
-                        let audio_mime = Some("audio/mpeg".to_string());
-                        (
-                            StatusCode::OK,
-                            Json(AskResponse {
-                                response_text,
-                                trace,
-                                audio_base64,
-                                audio_mime,
-                            }),
-                        )
-                            .into_response()
-                    }
-                    Err(e) => (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": e.to_string()})),
-                    )
-                        .into_response(),
-                }
-            } else {
-                (
-                    StatusCode::OK,
-                    Json(AskResponse {
-                        response_text,
-                        trace,
-                        audio_base64: None,
-                        audio_mime: None,
-                    }),
-                )
-                    .into_response()
+    let ids = decisions
+        .iter()
+        .map(|d| format!("{}:v{}", d.decision_id, d.version))
+        .collect();
+    let resp = Json(StaleDecisionsResponse {
+        threshold: crate::staleness::stale_threshold(),
+        decisions,
+    })
+    .into_response();
+    with_audit(resp, Some(caller_agent_id), ids)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/decisions/{decision_id}/refresh",
+    params(("decision_id" = String, Path, description = "Decision id")),
+    responses(
+        (status = 200, body = AskResponse),
+        (status = 403, body = serde_json::Value),
+        (status = 404, body = serde_json::Value),
+        (status = 500, body = serde_json::Value)
+    )
+)]
+async fn refresh_decision(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(decision_id): Path<String>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let agent_id = match require_ceo(&headers).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+
+    match crate::staleness::refresh_decision(&decision_id, agent_id.clone()).await {
+        Ok(Some((response_text, trace))) => {
+            let ids = vec![format!("{}:v{}", trace.decision_id, trace.version)];
+            let _ = api_state.events_tx.send(ServerEvent::trace(trace.clone()));
+            let resp = Json(AskResponse {
+                response_text,
+                trace,
+                audio_base64: None,
+                audio_mime: None,
+                needs_clarification: false,
+                clarifying_question: None,
+            })
+            .into_response();
+            with_audit(resp, Some(agent_id), ids)
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "decision not found"})),
+        )
+            .into_response(),
+        Err(e) => error_response(&e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/decisions/{decision_id}/versions/{version}/approve",
+    params(
+        ("decision_id" = String, Path, description = "Decision id"),
+        ("version" = i64, Path, description = "Pending version to approve")
+    ),
+    responses(
+        (status = 200, body = ApprovalResponse),
+        (status = 403, body = serde_json::Value),
+        (status = 404, body = serde_json::Value),
+        (status = 409, body = serde_json::Value)
+    )
+)]
+async fn approve_decision_version(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path((decision_id, version)): Path<(String, i64)>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let agent_id = match require_ceo(&headers).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+    let Some(store) = handles().graph_store else {
+        return neo4j_unavailable();
+    };
+
+    match store.promote_decision_version(&decision_id, version).await {
+        Ok(Some(upd)) => {
+            if let Some(t) = traces()
+                .await
+                .iter_mut()
+                .find(|t| t.decision_id == decision_id && t.version == version)
+            {
+                t.needs_approval = false;
             }
+            let ids = vec![format!("{decision_id}:v{version}")];
+            let resp = Json(ApprovalResponse {
+                decision_id,
+                version,
+                graph_updates: crate::domain::GraphUpdates {
+                    nodes: upd.nodes,
+                    edges: upd.edges,
+                },
+            })
+            .into_response();
+            with_audit(resp, Some(agent_id), ids)
         }
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": e.to_string()})),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "no such pending version"})),
         )
             .into_response(),
+        Err(e) => error_response(&e),
     }
 }
 
 #[utoipa::path(
     post,
-    path = "/v1/knowledge",
-    request_body = KnowledgeIngestRequest,
+    path = "/v1/decisions/{decision_id}/replay",
+    params(
+        ("decision_id" = String, Path, description = "Decision id"),
+        ReplayQuery
+    ),
     responses(
-        (status = 200, body = KnowledgeIngestResponse),
-        (status = 400, body = serde_json::Value),
+        (status = 200, body = crate::replay::ReplayOutcome),
+        (status = 403, body = serde_json::Value),
+        (status = 404, body = serde_json::Value),
         (status = 500, body = serde_json::Value)
     )
 )]
-async fn ingest_knowledge(
+async fn replay_decision(
     State(api_state): State<ApiState>,
     headers: HeaderMap,
-    Json(req): Json<KnowledgeIngestRequest>,
-) -> axum::response::Response {
+    Path(decision_id): Path<String>,
+    Query(q): Query<ReplayQuery>,
+) -> impl IntoResponse {
     if !auth_ok(&headers, &api_state) {
         return unauthorized();
     }
+    let agent_id = match require_ceo(&headers).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
 
-    if req.truth_id.trim().is_empty() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "truth_id must be non-empty"})),
+    match crate::replay::replay_decision(&decision_id, agent_id.clone(), q.commit).await {
+        Ok(Some(outcome)) => {
+            let mut ids = vec![format!("{}:v{}", decision_id, outcome.original_version)];
+            if outcome.committed {
+                ids.push(format!("{}:v{}", decision_id, outcome.trace.version));
+                let _ = api_state.events_tx.send(ServerEvent::trace(outcome.trace.clone()));
+            }
+            with_audit(Json(outcome).into_response(), Some(agent_id), ids)
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "decision not found"})),
         )
-            .into_response();
+            .into_response(),
+        Err(e) => error_response(&e),
     }
-    if req.kind.trim().is_empty() {
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/decisions/{decision_id}/similar",
+    params(
+        ("decision_id" = String, Path, description = "Decision id"),
+        SimilarQuery
+    ),
+    responses(
+        (status = 200, body = SimilarDecisionsResponse),
+        (status = 403, body = serde_json::Value),
+        (status = 404, body = serde_json::Value),
+        (status = 500, body = serde_json::Value),
+        (status = 503, body = serde_json::Value)
+    )
+)]
+async fn similar_decisions(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(decision_id): Path<String>,
+    Query(q): Query<SimilarQuery>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+    if !crate::llm::openai_configured() {
         return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "kind must be non-empty"})),
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({"error": "similarity search needs OPENAI_API_KEY"})),
         )
             .into_response();
     }
-    if !req.routing.is_object() {
+
+    let store = handles().graph_store;
+    let Some(store) = store else {
         return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "routing must be an object mapping agent_id -> level"})),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "storage not initialized"})),
         )
             .into_response();
-    }
-
-    let add_to_rag = req.add_to_rag.unwrap_or(true);
-    match crate::service::ingest_knowledge(
-        req.truth_id,
-        req.kind,
-        req.content,
-        req.agent_id,
-        req.routing,
-        add_to_rag,
-    )
-    .await
-    {
-        Ok(trace) => {
-            let _ = api_state.events_tx.send(ServerEvent::Trace(trace.clone()));
-            (StatusCode::OK, Json(KnowledgeIngestResponse { trace })).into_response()
+    };
+    let candidates = match store.current_decision_embeddings().await {
+        Ok(c) => c,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": e.to_string()})),
+            )
+                .into_response();
         }
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": e.to_string()})),
+    };
+    let Some(target) = candidates.iter().find(|c| c.decision_id == decision_id).cloned() else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "decision not found"})),
         )
-            .into_response(),
+            .into_response();
+    };
+    if version_visibility(&json!({"routing_json": target.routing_json}), &caller_agent_id) == "none" {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(json!({"error": "decision not visible to caller"})),
+        )
+            .into_response();
     }
+    // Versions written before embeddings were stored (or whose background embed failed) get
+    // one now.
+    let embedding = if target.embedding.is_empty() {
+        let embedded = crate::similar::embed_decision(
+            store.as_ref(),
+            &target.decision_id,
+            target.version,
+            &target.summary,
+        )
+        .await;
+        match embedded {
+            Ok(e) => e,
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({"error": format!("embedding failed: {e:#}")})),
+                )
+                    .into_response();
+            }
+        }
+    } else {
+        target.embedding.clone()
+    };
+
+    // Only decisions routed to the caller (at least summary).
+    let visible: Vec<_> = candidates
+        .into_iter()
+        .filter(|c| {
+            version_visibility(&json!({"routing_json": c.routing_json}), &caller_agent_id) != "none"
+        })
+        .collect();
+    let k = q.k.unwrap_or(5).clamp(1, 50);
+    let similar: Vec<SimilarDecision> = crate::similar::nearest(&decision_id, &embedding, visible, k)
+        .into_iter()
+        .map(|(c, similarity)| SimilarDecision {
+            decision_id: c.decision_id,
+            version: c.version,
+            summary: c.summary,
+            confidence: c.confidence,
+            similarity,
+        })
+        .collect();
+
+    let mut ids = vec![format!("{}:v{}", decision_id, target.version)];
+    ids.extend(similar.iter().map(|d| format!("{}:v{}", d.decision_id, d.version)));
+    let resp = Json(SimilarDecisionsResponse {
+        decision_id,
+        similar,
+    })
+    .into_response();
+    with_audit(resp, Some(caller_agent_id), ids)
 }
 
 #[utoipa::path(
     get,
-    path = "/v1/traces",
-    params(Pagination),
-    responses((status = 200, body = TraceListResponse))
+    path = "/v1/truth/{truth_id}/dependents",
+    params(
+        ("truth_id" = String, Path, description = "Truth id"),
+        Pagination
+    ),
+    responses(
+        (status = 200, body = TruthDependentsResponse),
+        (status = 500, body = serde_json::Value)
+    )
 )]
-async fn list_traces(
+async fn truth_dependents(
     State(api_state): State<ApiState>,
     headers: HeaderMap,
+    Path(truth_id): Path<String>,
     Query(p): Query<Pagination>,
-) -> axum::response::Response {
+) -> impl IntoResponse {
     if !auth_ok(&headers, &api_state) {
         return unauthorized();
     }
-    // Only CEO may view all traces.
-    let Some(agent_id) = resolve_employee_agent_id(&headers, None, None) else {
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+
+    let store = handles().graph_store;
+    let Some(store) = store else {
         return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "missing x-employee-name"})),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "storage not initialized"})),
         )
             .into_response();
     };
-    if employee_role_from_agent_id(&agent_id) != EmployeeRole::Ceo {
+    let limit = p.limit.unwrap_or(200);
+    let dependents = match store.truth_dependents(&truth_id, limit).await {
+        Ok(d) => d,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": e.to_string()})),
+            )
+                .into_response();
+        }
+    };
+    // Only decision versions routed to the caller (at least summary).
+    let dependents: Vec<_> = dependents
+        .into_iter()
+        .filter(|d| {
+            version_visibility(&json!({"routing_json": d.routing_json}), &caller_agent_id) != "none"
+        })
+        .collect();
+
+    let ids = dependents
+        .iter()
+        .map(|d| format!("{}:v{}", d.decision_id, d.version))
+        .collect();
+    let resp = Json(TruthDependentsResponse {
+        truth_id,
+        dependents,
+    })
+    .into_response();
+    with_audit(resp, Some(caller_agent_id), ids)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/events/{event_id}/decisions",
+    params(
+        ("event_id" = String, Path, description = "Event id (UUID)"),
+        Pagination
+    ),
+    responses(
+        (status = 200, body = EventDecisionsResponse),
+        (status = 400, body = serde_json::Value),
+        (status = 500, body = serde_json::Value)
+    )
+)]
+async fn event_decisions(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(event_id): Path<String>,
+    Query(p): Query<Pagination>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
+    // Stored as the hyphenated lowercase form.
+    let event_id = match uuid::Uuid::parse_str(event_id.trim()) {
+        Ok(id) => id.to_string(),
+        Err(_) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "event_id must be a UUID"})),
+            )
+                .into_response();
+        }
+    };
+
+    let Some(store) = handles().graph_store else {
         return (
-            StatusCode::FORBIDDEN,
-            Json(json!({"error": "forbidden"})),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "storage not initialized"})),
         )
             .into_response();
-    }
+    };
+    let decisions = match store.event_decisions(&event_id, p.limit.unwrap_or(200)).await {
+        Ok(d) => d,
+        Err(e) => return error_response(&e),
+    };
+    // Only decision versions routed to the caller (at least summary).
+    let decisions: Vec<_> = decisions
+        .into_iter()
+        .filter(|d| {
+            version_visibility(&json!({"routing_json": d.routing_json}), &caller_agent_id) != "none"
+        })
+        .collect();
 
-    let limit = p.limit.unwrap_or(50);
-    let state = APP_STATE.lock().await;
-    let mut traces = state.traces.clone();
-    traces.reverse();
-    traces.truncate(limit);
-    (StatusCode::OK, Json(TraceListResponse { traces })).into_response()
+    let ids = decisions
+        .iter()
+        .map(|d| format!("{}:v{}", d.decision_id, d.version))
+        .collect();
+    let resp = Json(EventDecisionsResponse {
+        event_id,
+        decisions,
+    })
+    .into_response();
+    with_audit(resp, Some(caller_agent_id), ids)
 }
 
 #[utoipa::path(
     get,
-    path = "/v1/agents/{agent_id}/traces",
+    path = "/v1/truth/{truth_id}/impact",
     params(
-        ("agent_id" = String, Path, description = "Employee/agent id"),
-        Pagination
+        ("truth_id" = String, Path, description = "Truth id"),
+        TruthImpactQuery
     ),
-    responses((status = 200, body = AgentTraceListResponse))
+    responses(
+        (status = 200, body = TruthImpactResponse),
+        (status = 500, body = serde_json::Value)
+    )
 )]
-async fn agent_traces(
+async fn truth_impact(
     State(api_state): State<ApiState>,
     headers: HeaderMap,
-    Path(agent_id): Path<String>,
-    Query(p): Query<Pagination>,
+    Path(truth_id): Path<String>,
+    Query(q): Query<TruthImpactQuery>,
 ) -> impl IntoResponse {
     if !auth_ok(&headers, &api_state) {
         return unauthorized();
     }
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
 
-    // Only allow a caller to request their own agent view (or CEO).
-    let Some(caller_agent_id) = resolve_employee_agent_id(&headers, None, None) else {
+    let store = handles().graph_store;
+    let truth = APP_STATE
+        .lock()
+        .await
+        .latest_truth(&truth_id)
+        .map(str::to_string);
+    let Some(store) = store else {
         return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "missing x-employee-name"})),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "storage not initialized"})),
         )
             .into_response();
     };
-    let caller_role = employee_role_from_agent_id(&caller_agent_id);
-    if caller_role != EmployeeRole::Ceo && caller_agent_id != agent_id {
-        return (
-            StatusCode::FORBIDDEN,
-            Json(json!({"error": "forbidden"})),
-        )
-            .into_response();
-    }
+    let decisions = match store.truth_impact(&truth_id, q.limit.unwrap_or(200)).await {
+        Ok(d) => d,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": e.to_string()})),
+            )
+                .into_response();
+        }
+    };
+    // Only decisions routed to the caller (at least summary).
+    let decisions: Vec<_> = decisions
+        .into_iter()
+        .filter(|d| {
+            version_visibility(&json!({"routing_json": d.routing_json}), &caller_agent_id) != "none"
+        })
+        .collect();
 
-    let limit = p.limit.unwrap_or(50);
-    let state = APP_STATE.lock().await;
-    let mut out = Vec::new();
+    let mut employees: Vec<String> = decisions
+        .iter()
+        .flat_map(|d| d.routing.iter().map(|r| r.agent_id.clone()))
+        .collect();
+    employees.sort();
+    employees.dedup();
 
-    for t in state.traces.iter().rev() {
-        let level = visibility_for_agent(t, &agent_id);
-        if level == "none" {
-            continue;
-        }
+    let summary = if q.summarize.unwrap_or(false) && !decisions.is_empty() {
+        summarize_impact(&truth_id, truth.as_deref(), &decisions).await
+    } else {
+        None
+    };
 
-        let mut tt = t.clone();
-        if level == "summary" {
-            tt.evidence = Vec::new();
-            tt.assumptions = Vec::new();
-        }
+    let ids = decisions
+        .iter()
+        .map(|d| format!("{}:v{}", d.decision_id, d.version))
+        .collect();
+    let resp = Json(TruthImpactResponse {
+        truth_id,
+        decisions,
+        employees,
+        summary,
+    })
+    .into_response();
+    with_audit(resp, Some(caller_agent_id), ids)
+}
 
-        out.push(tt);
-        if out.len() >= limit {
-            break;
+/// One paragraph on what changing `truth_id` would affect; `None` if the model call fails.
+async fn summarize_impact(
+    truth_id: &str,
+    truth: Option<&str>,
+    decisions: &[crate::neo4j::writer::ImpactedDecision],
+) -> Option<String> {
+    let system = "You assess the impact of changing an organizational policy. In one short paragraph, say which decisions would need revisiting and who would need to be told. Plain text only.";
+    let user = json!({
+        "truth_id": truth_id,
+        "current_truth": truth,
+        "decisions": decisions,
+    })
+    .to_string();
+    match crate::utils::openai_chat(system, &user).await {
+        Ok(out) => Some(out.trim().to_string()),
+        Err(e) => {
+            eprintln!("impact summary for {truth_id} failed: {e:#}");
+            None
         }
     }
-
-    Json(AgentTraceListResponse {
-        agent_id,
-        traces: out,
-    })
-    .into_response()
 }
 
 #[utoipa::path(
     get,
-    path = "/v1/graph/snapshot",
+    path = "/v1/truth/current",
     params(Pagination),
     responses(
-        (status = 200, body = GraphSnapshotResponse),
-        (status = 500, body = serde_json::Value)
+        (status = 200, body = CurrentTruthResponse),
+        (status = 500, body = serde_json::Value),
+        (status = 503, body = serde_json::Value)
     )
 )]
-async fn graph_snapshot(
+async fn current_truth(
     State(api_state): State<ApiState>,
     headers: HeaderMap,
     Query(p): Query<Pagination>,
-) -> axum::response::Response {
+) -> impl IntoResponse {
     if !auth_ok(&headers, &api_state) {
         return unauthorized();
     }
-    let limit = p.limit.unwrap_or(5000) as i64;
 
-    let state = APP_STATE.lock().await;
-    let client = match state.neo4j.clone() {
-        Some(c) => c,
-        None => {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": "neo4j not initialized"})),
-        )
+    let limit = p.limit.unwrap_or(200) as i64;
+    let client = match require_store() {
+        Ok(Store::Neo4j(c)) => c,
+        Ok(Store::Memory(mem)) => {
+            let (truth_objects, truth_versions) = mem.lock().await.current_truth(limit as usize);
+            let ids = audit_resource_ids(&truth_versions, "truth_version_id");
+            let resp = Json(CurrentTruthResponse {
+                truth_objects,
+                truth_versions,
+            })
             .into_response();
+            return with_audit(resp, None, ids);
         }
+        Err(resp) => return *resp,
     };
 
-    drop(state);
-
+    if let Err(e) = client.breaker().check() {
+        return error_response(&e);
+    }
     let graph = client.graph();
-
-    let node_query = neo4rs::query(
+    let q = neo4rs::query(
         r#"
-MATCH (n)
-WITH n,
-     properties(n) AS p,
-     toString(n.created_at) AS created_at_s,
-     coalesce(
-       n.name,
-       n.label,
-       n.summary,
-       n.decision,
-       n.truth_id,
-       n.employee_id,
-       n.team_id,
-       n.topic,
-       n.decision_id,
-       n.decision_version_id,
-       n.truth_version_id,
-       elementId(n)
-     ) AS display_label
-WITH n, p, created_at_s,
-     CASE
-       WHEN display_label = elementId(n) THEN coalesce(head(labels(n)), 'Node') + ':' + display_label
-       ELSE display_label
-     END AS display_label2
-RETURN elementId(n) AS id,
-       labels(n) AS labels,
-       p { .*, label: display_label2, created_at: created_at_s } AS props
+MATCH (o:TruthObject)-[:CURRENT]->(tv:TruthVersion)
+WHERE coalesce(o.active, true)
+RETURN elementId(o) AS o_id, labels(o) AS o_labels, properties(o) AS o_props,
+       elementId(tv) AS tv_id, labels(tv) AS tv_labels, properties(tv) AS tv_props
 LIMIT $limit
 "#,
     )
     .param("limit", limit);
 
-    let edge_query = neo4rs::query(
+    let mut objs: HashMap<String, GraphNode> = HashMap::new();
+    let mut vers: HashMap<String, GraphNode> = HashMap::new();
+    let execute = graph.execute(q);
+    let mut stream = match with_timeout("neo4j", neo4j_timeout(), execute).await {
+        Ok(s) => s,
+        Err(e) => return error_response(&e),
+    };
+
+    while let Ok(Some(row)) = stream.next().await {
+        let o_id: String = row.get("o_id").unwrap_or_default();
+        let o_labels: Vec<String> = row.get("o_labels").unwrap_or_default();
+        let o_props = match row.get::<neo4rs::BoltType>("o_props") {
+            Ok(v) => bolt_to_json(v),
+            Err(_) => serde_json::Value::Null,
+        };
+        objs.entry(o_id.clone()).or_insert(GraphNode {
+            id: o_id,
+            labels: o_labels,
+            properties: o_props,
+        });
+
+        let tv_id: String = row.get("tv_id").unwrap_or_default();
+        let tv_labels: Vec<String> = row.get("tv_labels").unwrap_or_default();
+        let tv_props = match row.get::<neo4rs::BoltType>("tv_props") {
+            Ok(v) => bolt_to_json(v),
+            Err(_) => serde_json::Value::Null,
+        };
+        vers.entry(tv_id.clone()).or_insert(GraphNode {
+            id: tv_id,
+            labels: tv_labels,
+            properties: tv_props,
+        });
+    }
+
+    let truth_versions: Vec<GraphNode> = vers.into_values().collect();
+    let ids = audit_resource_ids(&truth_versions, "truth_version_id");
+    let resp = Json(CurrentTruthResponse {
+        truth_objects: objs.into_values().collect(),
+        truth_versions,
+    })
+    .into_response();
+    with_audit(resp, None, ids)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/messages/urgent",
+    params(UrgentMessagesQuery),
+    responses(
+        (status = 200, body = UrgentMessagesResponse),
+        (status = 500, body = serde_json::Value),
+        (status = 503, body = serde_json::Value)
+    )
+)]
+async fn urgent_messages(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Query(q): Query<UrgentMessagesQuery>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+
+    let limit = q.limit.unwrap_or(20) as i64;
+    let days = q.days.unwrap_or(30);
+    let client = match require_store() {
+        Ok(Store::Neo4j(c)) => c,
+        // Memory mode does not ingest email.
+        Ok(Store::Memory(_)) => {
+            return Json(UrgentMessagesResponse { messages: Vec::new() }).into_response();
+        }
+        Err(resp) => return *resp,
+    };
+
+    if let Err(e) = client.breaker().check() {
+        return error_response(&e);
+    }
+    let graph = client.graph();
+    let q = neo4rs::query(
         r#"
-MATCH (a)-[r]->(b)
-WITH a, r, b,
-     properties(r) AS p,
-     toString(r.created_at) AS created_at_s,
-     coalesce(r.name, r.label, type(r)) AS display_label
-RETURN elementId(r) AS id,
-       type(r) AS t,
-       elementId(a) AS from,
-       elementId(b) AS to,
-       p { .*, label: display_label, created_at: created_at_s } AS props
+MATCH (m:EmailMessage)
+WITH m, coalesce(m.sent_at, m.created_at) AS at
+WHERE m.urgency IS NOT NULL
+  AND at >= datetime() - duration({days: $days})
+RETURN elementId(m) AS id, labels(m) AS labels,
+       m { .*, sent_at: toString(m.sent_at) } AS props
+ORDER BY m.urgency DESC, at DESC
 LIMIT $limit
 "#,
     )
+    .param("days", days)
     .param("limit", limit);
 
-    let mut nodes_out = Vec::new();
-    let mut stream = match graph.execute(node_query).await {
+    let mut messages = Vec::new();
+    let execute = graph.execute(q);
+    let mut stream = match with_timeout("neo4j", neo4j_timeout(), execute).await {
         Ok(s) => s,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": e.to_string()})),
-            )
-                .into_response();
-        }
+        Err(e) => return error_response(&e),
     };
 
     while let Ok(Some(row)) = stream.next().await {
@@ -705,407 +3676,724 @@ LIMIT $limit
             Ok(v) => bolt_to_json(v),
             Err(_) => serde_json::Value::Null,
         };
-
-        nodes_out.push(GraphNode {
+        messages.push(GraphNode {
             id,
             labels,
             properties,
         });
     }
 
-    let mut edges_out = Vec::new();
-    let mut stream = match graph.execute(edge_query).await {
-        Ok(s) => s,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": e.to_string()})),
-            )
-                .into_response();
-        }
-    };
+    Json(UrgentMessagesResponse { messages }).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/threads/{thread_id}",
+    params(("thread_id" = String, Path, description = "Thread id")),
+    responses(
+        (status = 200, body = crate::neo4j::writer::EmailThread),
+        (status = 404, body = serde_json::Value),
+        (status = 500, body = serde_json::Value),
+        (status = 503, body = serde_json::Value)
+    )
+)]
+async fn email_thread(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(thread_id): Path<String>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+
+    let client = match require_store() {
+        Ok(Store::Neo4j(c)) => c,
+        // Memory mode does not ingest email, so it has no threads.
+        Ok(Store::Memory(_)) => {
+            return (StatusCode::NOT_FOUND, Json(json!({"error": "thread not found"})))
+                .into_response();
+        }
+        Err(resp) => return *resp,
+    };
+
+    let load = crate::neo4j::writer::load_email_thread(client.graph(), &thread_id);
+    match client.breaker().call(load).await {
+        Ok(Some(thread)) => Json(thread).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "thread not found"})),
+        )
+            .into_response(),
+        Err(e) => error_response(&e),
+    }
+}
+
+/// Clauses that can modify the graph (or call procedures that might).
+const CYPHER_WRITE_KEYWORDS: [&str; 10] = [
+    "CREATE", "MERGE", "DELETE", "DETACH", "SET", "REMOVE", "DROP", "LOAD", "FOREACH", "CALL",
+];
+
+/// Returns the first write keyword in `query`, ignoring string literals, backticked names
+/// and comments so `WHERE m.subject = 'merge plan'` is still allowed.
+fn cypher_write_clause(query: &str) -> Option<&'static str> {
+    let mut code = String::with_capacity(query.len());
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                let mut escaped = false;
+                for n in chars.by_ref() {
+                    if escaped {
+                        escaped = false;
+                    } else if n == '\\' && c != '`' {
+                        escaped = true;
+                    } else if n == c {
+                        break;
+                    }
+                }
+                code.push(' ');
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for n in chars.by_ref() {
+                    if n == '\n' {
+                        break;
+                    }
+                }
+                code.push(' ');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for n in chars.by_ref() {
+                    if prev == '*' && n == '/' {
+                        break;
+                    }
+                    prev = n;
+                }
+                code.push(' ');
+            }
+            _ => code.push(c),
+        }
+    }
+
+    code.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|w| !w.is_empty())
+        .find_map(|w| {
+            CYPHER_WRITE_KEYWORDS
+                .iter()
+                .find(|k| w.eq_ignore_ascii_case(k))
+                .copied()
+        })
+}
+
+pub(crate) fn json_to_bolt(v: serde_json::Value) -> neo4rs::BoltType {
+    match v {
+        serde_json::Value::Null => neo4rs::BoltType::Null(neo4rs::BoltNull),
+        serde_json::Value::Bool(b) => b.into(),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n.as_f64().unwrap_or_default().into(),
+        },
+        serde_json::Value::String(s) => s.into(),
+        serde_json::Value::Array(a) => {
+            let items: Vec<neo4rs::BoltType> = a.into_iter().map(json_to_bolt).collect();
+            neo4rs::BoltType::List(items.into())
+        }
+        serde_json::Value::Object(o) => {
+            let mut m = neo4rs::BoltMap::new();
+            for (k, val) in o {
+                m.put(k.into(), json_to_bolt(val));
+            }
+            neo4rs::BoltType::Map(m)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/graph/cypher",
+    request_body = CypherQueryRequest,
+    responses(
+        (status = 200, body = CypherQueryResponse),
+        (status = 400, body = serde_json::Value),
+        (status = 403, body = serde_json::Value),
+        (status = 504, body = serde_json::Value),
+        (status = 503, body = serde_json::Value)
+    )
+)]
+async fn graph_cypher(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Json(req): Json<CypherQueryRequest>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    // Ad-hoc queries can see everything, so only the CEO may run them.
+    if let Err(resp) = require_ceo(&headers).await {
+        return *resp;
+    }
+
+    if let Some(keyword) = cypher_write_clause(&req.query) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "write clauses are not allowed", "clause": keyword})),
+        )
+            .into_response();
+    }
+
+    let config = crate::app_state::config();
+    let max_rows = config.neo4j.cypher_max_rows;
+    let row_cap = req.limit.unwrap_or(max_rows).min(max_rows);
+    let timeout = Duration::from_secs(config.neo4j.cypher_timeout_secs);
 
-    while let Ok(Some(row)) = stream.next().await {
-        let id: String = row.get("id").unwrap_or_default();
-        let edge_type: String = row.get("t").unwrap_or_default();
-        let from: String = row.get("from").unwrap_or_default();
-        let to: String = row.get("to").unwrap_or_default();
-        let properties = match row.get::<neo4rs::BoltType>("props") {
-            Ok(v) => bolt_to_json(v),
-            Err(_) => serde_json::Value::Null,
-        };
+    let Ok(Store::Neo4j(client)) = require_store() else {
+        return neo4j_unavailable();
+    };
 
-        edges_out.push(GraphEdge {
-            id,
-            edge_type,
-            from,
-            to,
-            properties,
-        });
+    let mut q = neo4rs::query(&req.query);
+    for (k, v) in req.params {
+        q = q.param(&k, json_to_bolt(v));
     }
 
-    Json(GraphSnapshotResponse {
-        nodes: nodes_out,
-        edges: edges_out,
-    })
-    .into_response()
+    // Always rolled back, so a write the keyword check missed is never committed.
+    match client.read_only_query(q, row_cap, timeout).await {
+        Ok((rows, truncated)) => {
+            let rows = rows
+                .into_iter()
+                .map(|fields| {
+                    let obj: serde_json::Map<String, serde_json::Value> =
+                        fields.into_iter().map(|(k, v)| (k, bolt_to_json(v))).collect();
+                    serde_json::Value::Object(obj)
+                })
+                .collect();
+            Json(CypherQueryResponse { rows, truncated }).into_response()
+        }
+        Err(e) => error_response(&e),
+    }
 }
 
 #[utoipa::path(
     get,
-    path = "/v1/agents/{agent_id}/graph/snapshot",
-    params(
-        ("agent_id" = String, Path, description = "Employee/agent id"),
-        Pagination
-    ),
+    path = "/v1/admin/audit",
+    params(AuditQuery),
     responses(
-        (status = 200, body = GraphSnapshotResponse),
+        (status = 200, body = AuditListResponse),
+        (status = 403, body = serde_json::Value),
         (status = 500, body = serde_json::Value)
     )
 )]
-async fn agent_graph_snapshot(
+async fn audit_log(
     State(api_state): State<ApiState>,
     headers: HeaderMap,
-    Path(agent_id): Path<String>,
-    Query(p): Query<Pagination>,
+    Query(q): Query<AuditQuery>,
 ) -> impl IntoResponse {
     if !auth_ok(&headers, &api_state) {
         return unauthorized();
     }
+    if let Err(resp) = require_ceo(&headers).await {
+        return *resp;
+    }
 
-    let limit = p.limit.unwrap_or(5000) as i64;
+    let filter = crate::audit::AuditFilter {
+        employee_id: q.employee_id,
+        since: q.since,
+        route: q.route,
+        limit: q.limit.unwrap_or(100).min(1000),
+        offset: q.offset.unwrap_or(0),
+    };
+    match crate::audit::list(&filter).await {
+        Ok(events) => Json(AuditListResponse {
+            events,
+            limit: filter.limit,
+            offset: filter.offset,
+        })
+        .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": e.to_string()})),
+        )
+            .into_response(),
+    }
+}
 
-    let state = APP_STATE.lock().await;
-    let client = match state.neo4j.clone() {
-        Some(c) => c,
-        None => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "neo4j not initialized"})),
-            )
-                .into_response();
+#[utoipa::path(
+    post,
+    path = "/v1/admin/backfill-roles",
+    responses(
+        (status = 200, body = BackfillRolesResponse),
+        (status = 403, body = serde_json::Value),
+        (status = 500, body = serde_json::Value),
+        (status = 503, body = serde_json::Value)
+    )
+)]
+async fn backfill_roles(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    if let Err(resp) = require_ceo(&headers).await {
+        return *resp;
+    }
+
+    let client = match require_store() {
+        Ok(Store::Neo4j(c)) => c,
+        // Memory mode has no Employee nodes to update.
+        Ok(Store::Memory(_)) => {
+            return Json(BackfillRolesResponse { updated: 0 }).into_response();
         }
+        Err(resp) => return *resp,
     };
-    drop(state);
 
-    let graph = client.graph();
+    let rules = crate::roles::role_rules_from_env();
+    let backfill = crate::neo4j::writer::backfill_employee_roles(client.graph(), &rules);
+    match client.breaker().call(backfill).await {
+        Ok(updated) => Json(BackfillRolesResponse { updated }).into_response(),
+        Err(e) => error_response(&e),
+    }
+}
 
-    let q = neo4rs::query(
-        r#"
-MATCH (n)
-WHERE (n:DecisionVersion OR n:TruthVersion) AND $agent_id IN coalesce(n.routing_agents, [])
-WITH collect(n) AS versions
-UNWIND versions AS v
-OPTIONAL MATCH (a)-[r]->(b)
-WHERE a = v OR b = v
-WITH a, r, b,
-     properties(a) AS a_p,
-     properties(r) AS r_p,
-     properties(b) AS b_p,
-     toString(a.created_at) AS a_created_at_s,
-     toString(r.created_at) AS r_created_at_s,
-     toString(b.created_at) AS b_created_at_s,
-     coalesce(
-       a.name,
-       a.label,
-       a.summary,
-       a.decision,
-       a.truth_id,
-       a.employee_id,
-       a.team_id,
-       a.topic,
-       a.decision_id,
-       a.decision_version_id,
-       a.truth_version_id,
-       elementId(a)
-     ) AS a_display_label,
-     coalesce(r.name, r.label, type(r)) AS r_display_label,
-     coalesce(
-       b.name,
-       b.label,
-       b.summary,
-       b.decision,
-       b.truth_id,
-       b.employee_id,
-       b.team_id,
-       b.topic,
-       b.decision_id,
-       b.decision_version_id,
-       b.truth_version_id,
-       elementId(b)
-     ) AS b_display_label
-WITH a, r, b,
-     a_p, r_p, b_p,
-     a_created_at_s, r_created_at_s, b_created_at_s,
-     CASE
-       WHEN a_display_label = elementId(a) THEN coalesce(head(labels(a)), 'Node') + ':' + a_display_label
-       ELSE a_display_label
-     END AS a_display_label2,
-     r_display_label,
-     CASE
-       WHEN b_display_label = elementId(b) THEN coalesce(head(labels(b)), 'Node') + ':' + b_display_label
-       ELSE b_display_label
-     END AS b_display_label2
-RETURN elementId(a) AS a_id,
-       labels(a) AS a_labels,
-       a_p { .*, label: a_display_label2, created_at: a_created_at_s } AS a_props,
-       elementId(r) AS r_id,
-       type(r) AS r_type,
-       r_p { .*, label: r_display_label, created_at: r_created_at_s } AS r_props,
-       elementId(b) AS b_id,
-       labels(b) AS b_labels,
-       b_p { .*, label: b_display_label2, created_at: b_created_at_s } AS b_props
-LIMIT $limit
-"#,
+#[utoipa::path(
+    post,
+    path = "/v1/admin/repair-communications",
+    responses(
+        (status = 200, body = RepairCommunicationsResponse),
+        (status = 403, body = serde_json::Value),
+        (status = 500, body = serde_json::Value),
+        (status = 503, body = serde_json::Value)
     )
-    .param("agent_id", agent_id)
-    .param("limit", limit);
-
-    let mut nodes: HashMap<String, GraphNode> = HashMap::new();
-    let mut edges: HashMap<String, GraphEdge> = HashMap::new();
+)]
+async fn repair_communications(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    if let Err(resp) = require_ceo(&headers).await {
+        return *resp;
+    }
 
-    let mut stream = match graph.execute(q).await {
-        Ok(s) => s,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": e.to_string()})),
-            )
+    let client = match require_store() {
+        Ok(Store::Neo4j(c)) => c,
+        // Memory mode has no email graph to repair.
+        Ok(Store::Memory(_)) => {
+            return Json(RepairCommunicationsResponse { recomputed: 0, zeroed: 0 })
                 .into_response();
         }
+        Err(resp) => return *resp,
     };
 
-    while let Ok(Some(row)) = stream.next().await {
-        let a_id: String = row.get("a_id").unwrap_or_default();
-        if !a_id.is_empty() {
-            let a_labels: Vec<String> = row.get("a_labels").unwrap_or_default();
-            let a_props = match row.get::<neo4rs::BoltType>("a_props") {
-                Ok(v) => bolt_to_json(v),
-                Err(_) => serde_json::Value::Null,
-            };
-            nodes.entry(a_id.clone()).or_insert(GraphNode {
-                id: a_id,
-                labels: a_labels,
-                properties: a_props,
-            });
+    let repair = crate::neo4j::writer::repair_communication_counts(client.graph());
+    match client.breaker().call(repair).await {
+        Ok((recomputed, zeroed)) => {
+            Json(RepairCommunicationsResponse { recomputed, zeroed }).into_response()
         }
+        Err(e) => error_response(&e),
+    }
+}
 
-        let b_id: String = row.get("b_id").unwrap_or_default();
-        if !b_id.is_empty() {
-            let b_labels: Vec<String> = row.get("b_labels").unwrap_or_default();
-            let b_props = match row.get::<neo4rs::BoltType>("b_props") {
-                Ok(v) => bolt_to_json(v),
-                Err(_) => serde_json::Value::Null,
-            };
-            nodes.entry(b_id.clone()).or_insert(GraphNode {
-                id: b_id,
-                labels: b_labels,
-                properties: b_props,
-            });
-        }
+#[utoipa::path(
+    get,
+    path = "/v1/admin/identity-suggestions",
+    responses(
+        (status = 200, body = IdentitySuggestionsResponse),
+        (status = 403, body = serde_json::Value),
+        (status = 500, body = serde_json::Value),
+        (status = 503, body = serde_json::Value)
+    )
+)]
+async fn identity_suggestions(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    if let Err(resp) = require_ceo(&headers).await {
+        return *resp;
+    }
 
-        let r_id: String = row.get("r_id").unwrap_or_default();
-        if !r_id.is_empty() {
-            let r_type: String = row.get("r_type").unwrap_or_default();
-            let r_props = match row.get::<neo4rs::BoltType>("r_props") {
-                Ok(v) => bolt_to_json(v),
-                Err(_) => serde_json::Value::Null,
-            };
-            let from: String = row.get("a_id").unwrap_or_default();
-            let to: String = row.get("b_id").unwrap_or_default();
-            edges.entry(r_id.clone()).or_insert(GraphEdge {
-                id: r_id,
-                edge_type: r_type,
-                from,
-                to,
-                properties: r_props,
-            });
+    let client = match require_store() {
+        Ok(Store::Neo4j(c)) => c,
+        // Memory mode ingests no email identities.
+        Ok(Store::Memory(_)) => {
+            return Json(IdentitySuggestionsResponse { suggestions: Vec::new() })
+                .into_response();
         }
+        Err(resp) => return *resp,
+    };
+
+    let listed = crate::neo4j::writer::list_identity_suggestions(client.graph());
+    match client.breaker().call(listed).await {
+        Ok(suggestions) => Json(IdentitySuggestionsResponse { suggestions }).into_response(),
+        Err(e) => error_response(&e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/employees/{employee_id}/aliases",
+    request_body = EmployeeAliasRequest,
+    params(
+        ("employee_id" = String, Path, description = "Canonical employee id")
+    ),
+    responses(
+        (status = 200, body = EmployeeAliasResponse),
+        (status = 400, body = serde_json::Value),
+        (status = 403, body = serde_json::Value),
+        (status = 404, body = serde_json::Value),
+        (status = 500, body = serde_json::Value),
+        (status = 503, body = serde_json::Value)
+    )
+)]
+async fn link_employee_alias(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(employee_id): Path<String>,
+    Json(req): Json<EmployeeAliasRequest>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    if let Err(resp) = require_ceo(&headers).await {
+        return *resp;
     }
 
-    Json(GraphSnapshotResponse {
-        nodes: nodes.into_values().collect(),
-        edges: edges.into_values().collect(),
-    })
-    .into_response()
+    let Ok(Store::Neo4j(client)) = require_store() else {
+        return neo4j_unavailable();
+    };
+
+    // Link to the end of an existing chain so aliases stay one hop from their canonical employee.
+    let canonical = crate::neo4j::writer::resolve_canonical_employee(client.graph(), &employee_id);
+    let canonical_id = match client.breaker().call(canonical).await {
+        Ok(id) => id,
+        Err(e) => return error_response(&e),
+    };
+    if req.alias_id.trim().is_empty() || req.alias_id == canonical_id {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "alias_id must name another employee"})),
+        )
+            .into_response();
+    }
+
+    let link = crate::neo4j::writer::link_employee_alias(
+        client.graph(),
+        &req.alias_id,
+        &canonical_id,
+        req.merge_edges.unwrap_or(true),
+        "manual",
+        1.0,
+    );
+    match client.breaker().call(link).await {
+        Ok(Some(moved)) => Json(EmployeeAliasResponse {
+            alias_id: req.alias_id,
+            canonical_employee_id: canonical_id,
+            moved,
+        })
+        .into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "employee not found"})),
+        )
+            .into_response(),
+        Err(e) => error_response(&e),
+    }
 }
 
 #[utoipa::path(
     get,
-    path = "/v1/decisions/current",
-    params(Pagination),
+    path = "/v1/clusters",
+    params(ClusterListQuery),
     responses(
-        (status = 200, body = CurrentDecisionsResponse),
+        (status = 200, body = ClustersResponse),
+        (status = 400, body = serde_json::Value),
         (status = 500, body = serde_json::Value)
     )
 )]
-async fn current_decisions(
+async fn list_clusters(
     State(api_state): State<ApiState>,
     headers: HeaderMap,
-    Query(p): Query<Pagination>,
+    Query(q): Query<ClusterListQuery>,
 ) -> impl IntoResponse {
     if !auth_ok(&headers, &api_state) {
         return unauthorized();
     }
-
-    let limit = p.limit.unwrap_or(200) as i64;
-    let state = APP_STATE.lock().await;
-    let client = match state.neo4j.clone() {
-        Some(c) => c,
-        None => {
+    let ascending = match q.order.as_deref().map(str::trim) {
+        None | Some("") | Some("desc") => false,
+        Some("asc") => true,
+        Some(other) => {
             return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "neo4j not initialized"})),
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": "order must be asc or desc", "order": other})),
             )
                 .into_response();
         }
     };
-    drop(state);
+    let limit = q.limit.unwrap_or(50).min(500);
+    let offset = q.offset.unwrap_or(0);
+    let Some(client) = handles().neo4j else {
+        // Memory mode stores no clusters.
+        return Json(ClustersResponse { clusters: Vec::new(), limit, offset, unclustered: None })
+            .into_response();
+    };
+    let min_size = crate::triage::min_cluster_size();
+    let listed = crate::neo4j::writer::list_knowledge_clusters(
+        client.graph(),
+        min_size,
+        ascending,
+        offset,
+        limit,
+    );
+    let clusters = match client.breaker().call(listed).await {
+        Ok(clusters) => clusters,
+        Err(e) => return error_response(&e),
+    };
+    let unclustered = if crate::triage::unclustered_group_enabled() {
+        let summary = crate::neo4j::writer::unclustered_summary(client.graph(), min_size);
+        match client.breaker().call(summary).await {
+            Ok(summary) => Some(summary),
+            Err(e) => return error_response(&e),
+        }
+    } else {
+        None
+    };
+    Json(ClustersResponse { clusters, limit, offset, unclustered }).into_response()
+}
 
-    let graph = client.graph();
-    let q = neo4rs::query(
-        r#"
-MATCH (d:Decision)-[:CURRENT]->(dv:DecisionVersion)
-RETURN elementId(d) AS d_id, labels(d) AS d_labels, properties(d) AS d_props,
-       elementId(dv) AS dv_id, labels(dv) AS dv_labels, properties(dv) AS dv_props
-LIMIT $limit
-"#,
+/// Related decisions loaded per cluster, before visibility filtering.
+const CLUSTER_DECISIONS_LIMIT: usize = 50;
+
+#[utoipa::path(
+    get,
+    path = "/v1/clusters/{cluster_id}",
+    params(("cluster_id" = String, Path, description = "Cluster id")),
+    responses(
+        (status = 200, body = crate::neo4j::writer::KnowledgeClusterDetail),
+        (status = 404, body = serde_json::Value),
+        (status = 500, body = serde_json::Value),
+        (status = 503, body = serde_json::Value)
     )
-    .param("limit", limit);
+)]
+async fn cluster_detail(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+    Path(cluster_id): Path<String>,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    let caller_agent_id = match resolve_canonical_agent_id(&headers, None, None).await {
+        Ok(id) => id,
+        Err(resp) => return *resp,
+    };
 
-    let mut decisions: HashMap<String, GraphNode> = HashMap::new();
-    let mut versions: HashMap<String, GraphNode> = HashMap::new();
-    let mut stream = match graph.execute(q).await {
-        Ok(s) => s,
-        Err(e) => {
+    let client = match require_store() {
+        Ok(Store::Neo4j(c)) => c,
+        Ok(Store::Memory(_)) => {
+            return (StatusCode::NOT_FOUND, Json(json!({"error": "cluster not found"})))
+                .into_response();
+        }
+        Err(resp) => return *resp,
+    };
+
+    let loaded = if cluster_id == crate::triage::UNCLUSTERED_ID
+        && crate::triage::unclustered_group_enabled()
+    {
+        let load = crate::neo4j::writer::load_unclustered_messages(
+            client.graph(),
+            crate::triage::min_cluster_size(),
+            CLUSTER_DECISIONS_LIMIT,
+        );
+        client.breaker().call(load).await.map(Some)
+    } else {
+        let load = crate::neo4j::writer::load_knowledge_cluster(
+            client.graph(),
+            &cluster_id,
+            CLUSTER_DECISIONS_LIMIT,
+        );
+        client.breaker().call(load).await
+    };
+    let mut detail = match loaded {
+        Ok(Some(detail)) => detail,
+        Ok(None) => {
             return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": e.to_string()})),
+                StatusCode::NOT_FOUND,
+                Json(json!({"error": "cluster not found"})),
             )
                 .into_response();
         }
+        Err(e) => return error_response(&e),
     };
+    // Decisions are listed like elsewhere: only those routed to the caller with at least summary.
+    detail.decisions.retain(|d| {
+        let props = json!({"routing_json": d.routing_json});
+        version_visibility(&props, &caller_agent_id) != "none"
+    });
+    let ids = detail
+        .decisions
+        .iter()
+        .map(|d| format!("{}:v{}", d.decision_id, d.version))
+        .collect();
+    with_audit(Json(detail).into_response(), Some(caller_agent_id), ids)
+}
 
-    while let Ok(Some(row)) = stream.next().await {
-        let d_id: String = row.get("d_id").unwrap_or_default();
-        let d_labels: Vec<String> = row.get("d_labels").unwrap_or_default();
-        let d_props = match row.get::<neo4rs::BoltType>("d_props") {
-            Ok(v) => bolt_to_json(v),
-            Err(_) => serde_json::Value::Null,
-        };
-        decisions.entry(d_id.clone()).or_insert(GraphNode {
-            id: d_id,
-            labels: d_labels,
-            properties: d_props,
-        });
+#[utoipa::path(
+    post,
+    path = "/v1/admin/relabel-clusters",
+    responses(
+        (status = 200, body = RelabelClustersResponse),
+        (status = 403, body = serde_json::Value),
+        (status = 503, body = serde_json::Value),
+        (status = 500, body = serde_json::Value)
+    )
+)]
+async fn relabel_clusters(
+    State(api_state): State<ApiState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    if let Err(resp) = require_ceo(&headers).await {
+        return *resp;
+    }
+    if !crate::llm::openai_configured() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({"error": "cluster labels need OPENAI_API_KEY"})),
+        )
+            .into_response();
+    }
 
-        let dv_id: String = row.get("dv_id").unwrap_or_default();
-        let dv_labels: Vec<String> = row.get("dv_labels").unwrap_or_default();
-        let dv_props = match row.get::<neo4rs::BoltType>("dv_props") {
-            Ok(v) => bolt_to_json(v),
-            Err(_) => serde_json::Value::Null,
+    let client = match require_store() {
+        Ok(Store::Neo4j(c)) => c,
+        // Memory mode stores no clusters.
+        Ok(Store::Memory(_)) => {
+            return Json(RelabelClustersResponse { clusters: 0, relabeled: 0 }).into_response();
+        }
+        Err(resp) => return *resp,
+    };
+
+    let listed = crate::neo4j::writer::list_knowledge_clusters(
+        client.graph(),
+        crate::triage::min_cluster_size(),
+        false,
+        0,
+        usize::MAX,
+    );
+    let clusters = match client.breaker().call(listed).await {
+        Ok(c) => c,
+        Err(e) => return error_response(&e),
+    };
+    let min_members = crate::triage::cluster_label_min_members();
+    let mut relabeled = 0usize;
+    for cluster in &clusters {
+        if (cluster.member_count as usize) < min_members {
+            continue;
+        }
+        let samples: Vec<String> =
+            cluster.subjects.iter().map(|s| crate::redaction::redact(s)).collect();
+        let Some(label) = crate::triage::label_cluster(&samples).await else {
+            continue;
         };
-        versions.entry(dv_id.clone()).or_insert(GraphNode {
-            id: dv_id,
-            labels: dv_labels,
-            properties: dv_props,
-        });
+        if label.name == cluster.name && label.description == cluster.description {
+            continue;
+        }
+        let rename = crate::neo4j::writer::set_knowledge_cluster_label(
+            client.graph(),
+            &cluster.cluster_id,
+            &label.name,
+            &label.description,
+        );
+        match client.breaker().call(rename).await {
+            Ok(()) => relabeled += 1,
+            Err(e) => eprintln!("relabel cluster {}: {e:#}", cluster.cluster_id),
+        }
     }
-
-    Json(CurrentDecisionsResponse {
-        decisions: decisions.into_values().collect(),
-        decision_versions: versions.into_values().collect(),
+    Json(RelabelClustersResponse {
+        clusters: clusters.len(),
+        relabeled,
     })
     .into_response()
 }
 
 #[utoipa::path(
     get,
-    path = "/v1/truth/current",
-    params(Pagination),
+    path = "/v1/admin/config",
     responses(
-        (status = 200, body = CurrentTruthResponse),
+        (status = 200, body = crate::config::CosConfig),
+        (status = 403, body = serde_json::Value)
+    )
+)]
+async fn admin_config(State(api_state): State<ApiState>, headers: HeaderMap) -> impl IntoResponse {
+    if !auth_ok(&headers, &api_state) {
+        return unauthorized();
+    }
+    if let Err(resp) = require_ceo(&headers).await {
+        return *resp;
+    }
+    // Secrets serialize masked (see `config::CosConfig`).
+    Json(crate::app_state::config().as_ref().clone()).into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/maintenance/compact",
+    params(CompactQuery),
+    responses(
+        (status = 200, body = CompactResponse),
+        (status = 403, body = serde_json::Value),
         (status = 500, body = serde_json::Value)
     )
 )]
-async fn current_truth(
+async fn compact_versions(
     State(api_state): State<ApiState>,
     headers: HeaderMap,
-    Query(p): Query<Pagination>,
+    Query(q): Query<CompactQuery>,
 ) -> impl IntoResponse {
     if !auth_ok(&headers, &api_state) {
         return unauthorized();
     }
+    if let Err(resp) = require_ceo(&headers).await {
+        return *resp;
+    }
 
-    let limit = p.limit.unwrap_or(200) as i64;
-    let state = APP_STATE.lock().await;
-    let client = match state.neo4j.clone() {
-        Some(c) => c,
-        None => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "neo4j not initialized"})),
-            )
-                .into_response();
-        }
-    };
-    drop(state);
-
-    let graph = client.graph();
-    let q = neo4rs::query(
-        r#"
-MATCH (o:TruthObject)-[:CURRENT]->(tv:TruthVersion)
-RETURN elementId(o) AS o_id, labels(o) AS o_labels, properties(o) AS o_props,
-       elementId(tv) AS tv_id, labels(tv) AS tv_labels, properties(tv) AS tv_props
-LIMIT $limit
-"#,
-    )
-    .param("limit", limit);
+    let keep_latest = q
+        .keep_latest
+        .map(|n| n.max(1))
+        .unwrap_or_else(crate::graph_store::compact_keep_latest);
 
-    let mut objs: HashMap<String, GraphNode> = HashMap::new();
-    let mut vers: HashMap<String, GraphNode> = HashMap::new();
-    let mut stream = match graph.execute(q).await {
-        Ok(s) => s,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": e.to_string()})),
-            )
-                .into_response();
-        }
+    let store = handles().graph_store;
+    let Some(store) = store else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "storage not initialized"})),
+        )
+            .into_response();
     };
-
-    while let Ok(Some(row)) = stream.next().await {
-        let o_id: String = row.get("o_id").unwrap_or_default();
-        let o_labels: Vec<String> = row.get("o_labels").unwrap_or_default();
-        let o_props = match row.get::<neo4rs::BoltType>("o_props") {
-            Ok(v) => bolt_to_json(v),
-            Err(_) => serde_json::Value::Null,
-        };
-        objs.entry(o_id.clone()).or_insert(GraphNode {
-            id: o_id,
-            labels: o_labels,
-            properties: o_props,
-        });
-
-        let tv_id: String = row.get("tv_id").unwrap_or_default();
-        let tv_labels: Vec<String> = row.get("tv_labels").unwrap_or_default();
-        let tv_props = match row.get::<neo4rs::BoltType>("tv_props") {
-            Ok(v) => bolt_to_json(v),
-            Err(_) => serde_json::Value::Null,
-        };
-        vers.entry(tv_id.clone()).or_insert(GraphNode {
-            id: tv_id,
-            labels: tv_labels,
-            properties: tv_props,
-        });
+    match store.compact_versions(keep_latest).await {
+        Ok(summary) => Json(CompactResponse {
+            keep_latest,
+            objects_compacted: summary.objects_compacted,
+            versions_deleted: summary.versions_deleted,
+        })
+        .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": e.to_string()})),
+        )
+            .into_response(),
     }
+}
 
-    Json(CurrentTruthResponse {
-        truth_objects: objs.into_values().collect(),
-        truth_versions: vers.into_values().collect(),
-    })
-    .into_response()
+/// `COS_SSE_REQUIRE_IDENTITY=1`: close `/v1/stream` right after the `identity required` frame
+/// when the client has no identity, instead of keeping it open with pings.
+pub fn sse_require_identity() -> bool {
+    crate::app_state::config().http.sse_require_identity
 }
 
 #[utoipa::path(
@@ -1113,6 +4401,9 @@ LIMIT $limit
     path = "/v1/stream",
     params(
         ("employee_name" = Option<String>, Query, description = "Employee name (for browser EventSource; alternative to x-employee-name header)"),
+        ("topics" = Option<String>, Query, description = "Comma-separated topics (case-insensitive)"),
+        ("decision_id" = Option<String>, Query, description = "Only events for this decision"),
+        ("event_types" = Option<String>, Query, description = "Comma-separated event types, e.g. trace,low_confidence"),
     ),
     responses((status = 200, body = String, description = "SSE stream"))
 )]
@@ -1120,47 +4411,78 @@ async fn sse_stream(
     State(api_state): State<ApiState>,
     headers: HeaderMap,
     Query(q): Query<HashMap<String, String>>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+) -> Sse<stream::BoxStream<'static, Result<Event, Infallible>>> {
     let rx = api_state.events_tx.subscribe();
 
     let employee_name = q.get("employee_name").map(|s| s.as_str());
-    let agent_id = resolve_employee_agent_id(&headers, employee_name, None);
+    let agent_id = resolve_canonical_agent_id(&headers, employee_name, None).await.ok();
+    let filter = StreamFilter::from_query(&q);
+    let rules = handles().visibility_rules;
 
-    let initial = stream::once(async {
-        Ok(Event::default().event("cos").data("{\"type\":\"connected\"}"))
-    });
+    // Without an identity nothing below is visible, so say so instead of only sending pings.
+    let first = match &agent_id {
+        Some(_) => "{\"type\":\"connected\"}".to_string(),
+        None => json!({"type": "error", "message": "identity required"}).to_string(),
+    };
+    let first = Event::default().event("cos").data(first);
+    if agent_id.is_none() && sse_require_identity() {
+        return Sse::new(stream::iter([Ok(first)]).boxed());
+    }
+
+    let subscription = json!({"type": "subscription", "data": &filter}).to_string();
+    let initial = stream::iter([
+        Ok(first),
+        Ok(Event::default().event("cos").data(subscription)),
+    ]);
 
     let stream = initial.chain(
         BroadcastStream::new(rx)
-        .filter_map(|msg| async move { msg.ok() })
-        .filter_map(move |evt| {
+        .filter_map(move |msg| {
             let agent_id = agent_id.clone();
+            let filter = filter.clone();
+            let rules = rules.clone();
             async move {
-                match (&evt, agent_id.as_deref()) {
+                // A slow client fell behind the channel: tell it to resync instead of
+                // silently skipping events.
+                let evt = match msg {
+                    Ok(evt) => evt,
+                    Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                        SSE_LAGGED_EVENTS.fetch_add(missed, Ordering::Relaxed);
+                        return Some(json!({"type": "lagged", "missed": missed}).to_string());
+                    }
+                };
+                let visible = match (&evt, agent_id.as_deref()) {
                     (ServerEvent::Trace(t), Some(aid)) => {
-                        let level = visibility_for_agent(t, aid);
-                        if level == "none" {
-                            return None;
-                        }
-                        let mut tt = t.clone();
-                        if level == "summary" {
-                            tt.evidence = Vec::new();
-                            tt.assumptions = Vec::new();
+                        Some(ServerEvent::trace(trace_visible_to(&rules, t, aid)?))
+                    }
+                    // Low-confidence and staleness alerts are for the CEO only.
+                    (
+                        ServerEvent::LowConfidence { .. } | ServerEvent::StaleDecision { .. },
+                        Some(aid),
+                    ) => {
+                        if employee_role_from_agent_id(aid) == EmployeeRole::Ceo {
+                            Some(evt.clone())
+                        } else {
+                            None
                         }
-                        Some(ServerEvent::Trace(tt))
                     }
-                    // If no identity is provided, do not emit any events.
+                    // Progress says who is asking: it is for the asker and the CEO.
+                    (ServerEvent::Progress { agent_id: asker, .. }, Some(aid)) => {
+                        let ceo = employee_role_from_agent_id(aid) == EmployeeRole::Ceo;
+                        (asker == aid || ceo).then(|| evt.clone())
+                    }
+                    // No identity: nothing is visible (the client got the identity error).
                     _ => None,
-                }
+                };
+                visible
+                    .filter(|e| filter.matches(e))
+                    .map(|e| serde_json::to_string(&e).unwrap_or_else(|_| "{}".to_string()))
             }
         })
-        .map(|evt| {
-            let data = serde_json::to_string(&evt).unwrap_or_else(|_| "{}".to_string());
-            Ok(Event::default().event("cos").data(data))
-        }),
+        .map(|data| Ok(Event::default().event("cos").data(data))),
     );
 
-    Sse::new(stream).keep_alive(
+    Sse::new(stream.boxed()).keep_alive(
         axum::response::sse::KeepAlive::new()
             .interval(Duration::from_secs(10))
             .text("ping"),
@@ -1173,26 +4495,51 @@ async fn sse_stream(
     responses((status = 200, body = serde_json::Value))
 )]
 async fn openapi_json() -> impl IntoResponse {
-    Json(serde_json::to_value(&ApiDoc::openapi()).unwrap_or_else(|_| json!({})))
+    Json(serde_json::to_value(ApiDoc::openapi()).unwrap_or_else(|_| json!({})))
+}
+
+#[utoipa::path(
+    get,
+    path = "/openapi.yaml",
+    responses((status = 200, description = "The OpenAPI spec as YAML", content_type = "application/yaml", body = String))
+)]
+async fn openapi_yaml() -> axum::response::Response {
+    match spec_yaml() {
+        Ok(yaml) => ([(axum::http::header::CONTENT_TYPE, "application/yaml")], yaml).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": format!("spec serialization failed: {e}") })),
+        )
+            .into_response(),
+    }
+}
+
+/// The spec as YAML, serialized from the same document as `/openapi.json`.
+fn spec_yaml() -> anyhow::Result<String> {
+    let v = serde_json::to_value(ApiDoc::openapi())?;
+    Ok(serde_yaml::to_string(&v)?)
 }
 
 pub async fn run_server(addr: SocketAddr) -> anyhow::Result<()> {
-    let (tx, _rx) = broadcast::channel::<ServerEvent>(256);
-    let api_key = std::env::var("COS_API_KEY").ok();
-    let app = app(ApiState {
-        events_tx: tx,
-        api_key,
-    });
+    let config = crate::app_state::config();
+    crate::audit::spawn_flusher();
+    let api_state =
+        ApiState::new(config.http.api_key.clone(), config.http.sse_channel_capacity);
+    crate::maintenance::spawn_scheduler(api_state.events_tx.clone());
+    crate::webhooks::spawn_dispatcher(&api_state.events_tx);
+    let app = app(api_state);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
     axum::serve(listener, app).await?;
     Ok(())
 }
 
-pub async fn write_spec_json(path: &str) -> anyhow::Result<()> {
-    let v = serde_json::to_value(&ApiDoc::openapi()).unwrap_or_else(|_| json!({}));
+/// Writes the spec as JSON to `json_path` and as YAML to `yaml_path`.
+pub async fn write_spec(json_path: &std::path::Path, yaml_path: &std::path::Path) -> anyhow::Result<()> {
+    let v = serde_json::to_value(ApiDoc::openapi()).unwrap_or_else(|_| json!({}));
     let bytes = serde_json::to_vec_pretty(&v)?;
-    tokio::fs::write(path, bytes).await?;
+    tokio::fs::write(json_path, bytes).await?;
+    tokio::fs::write(yaml_path, spec_yaml()?).await?;
     Ok(())
 }
 
@@ -1228,3 +4575,194 @@ fn bolt_to_json(v: neo4rs::BoltType) -> serde_json::Value {
         other => serde_json::Value::String(format!("{other:?}")),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cypher_write_clauses_are_found_outside_literals_and_comments() {
+        for (query, clause) in [
+            ("MATCH (n) MERGE (n)-[:X]->(:Y) RETURN n", "MERGE"),
+            ("match (n) detach delete n", "DETACH"),
+            ("MATCH (n) SET n.x = 1", "SET"),
+            ("LOAD CSV FROM 'file:///etc/passwd' AS row RETURN row", "LOAD"),
+            ("CALL apoc.load.json('http://169.254.169.254/') YIELD value RETURN value", "CALL"),
+            ("MATCH (n) FOREACH (x IN [1] | CREATE (:Z)) RETURN n", "FOREACH"),
+        ] {
+            assert_eq!(cypher_write_clause(query), Some(clause), "{query}");
+        }
+        for query in [
+            "MATCH (m) WHERE m.subject = 'merge plan' RETURN m",
+            "MATCH (m) WHERE m.note = \"it's a \\\"set\\\" of items\" RETURN m",
+            "MATCH (`create`) RETURN `create`",
+            "MATCH (n) // then delete it\nRETURN n",
+            "MATCH (n) /* CALL db.labels() */ RETURN n.settings, n.created_at",
+        ] {
+            assert_eq!(cypher_write_clause(query), None, "{query}");
+        }
+    }
+
+    /// Resolves with the token `tok-sarah` for Sarah and, when given, an allowlist of agent ids.
+    fn identity(
+        header: Option<&str>,
+        token: Option<&str>,
+        employee_name: Option<&str>,
+        agent_id: Option<&str>,
+        allowlist: Option<&[&str]>,
+    ) -> Result<String, StatusCode> {
+        let mut headers = HeaderMap::new();
+        if let Some(name) = header {
+            headers.insert("x-employee-name", name.parse().unwrap());
+        }
+        if let Some(token) = token {
+            headers.insert("authorization", format!("Bearer {token}").parse().unwrap());
+        }
+        let tokens = HashMap::from([("tok-sarah".to_string(), "employee_sarah".to_string())]);
+        let allowlist: Option<Vec<String>> =
+            allowlist.map(|ids| ids.iter().map(|s| s.to_string()).collect());
+        let asserted = asserted_employee_agent_id(&headers, employee_name, agent_id);
+        resolve_identity(bearer_token(&headers), asserted, &tokens, allowlist.as_deref())
+            .map_err(|resp| resp.status())
+    }
+
+    fn stream_filter(params: &[(&str, &str)]) -> StreamFilter {
+        let q: HashMap<String, String> =
+            params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        StreamFilter::from_query(&q)
+    }
+
+    #[test]
+    fn stream_filters_parse_comma_lists_case_insensitively() {
+        let filter = stream_filter(&[("topics", " Hiring, ,Budget "), ("event_types", ",")]);
+        assert_eq!(filter.topics, Some(vec!["hiring".to_string(), "budget".to_string()]));
+        assert_eq!(filter.event_types, None);
+        assert_eq!(stream_filter(&[("decision_id", "  ")]).decision_id, None);
+    }
+
+    #[test]
+    fn stream_filters_compose_and_skip_fields_an_event_lacks() {
+        let trace = ServerEvent::trace(ReasoningTrace::sample("hiring_freeze", "Hiring", 0.9));
+        let progress = ServerEvent::Progress {
+            stage: "deciding".into(),
+            agent_id: "employee_bob".into(),
+            request_id: None,
+        };
+
+        let by_topic = stream_filter(&[("topics", "hiring")]);
+        assert!(by_topic.matches(&trace) && by_topic.matches(&progress));
+        assert!(!stream_filter(&[("topics", "budget")]).matches(&trace));
+
+        let both = stream_filter(&[("topics", "hiring"), ("decision_id", "other")]);
+        assert!(!both.matches(&trace));
+
+        let types = stream_filter(&[("event_types", "low_confidence,progress")]);
+        assert!(!types.matches(&trace) && types.matches(&progress));
+    }
+
+    #[test]
+    fn stored_roles_apply_to_unseeded_employees() {
+        crate::roles::record_stored_role("employee_email_ceo_corp_example", EmployeeRole::Ceo);
+        let stored = employee_role_from_agent_id("employee_email_ceo_corp_example");
+        assert_eq!(stored, EmployeeRole::Ceo);
+        assert_eq!(employee_role_from_agent_id("employee_sarah"), EmployeeRole::Hr);
+        assert_eq!(employee_role_from_agent_id("employee_unknown"), EmployeeRole::Engineer);
+    }
+
+    #[test]
+    fn deny_mode_gives_agents_without_a_known_role_nothing() {
+        let rules = VisibilityRules::default().with_default_deny(true);
+        let trace = ReasoningTrace::sample("platform_migration", "infra", 0.9);
+        let explain = |agent| explain_visibility(&rules, &trace, agent);
+        let bob = explain("employee_bob");
+        assert_eq!((bob.level.as_str(), bob.reason.as_str()), ("summary", "topic_keyword"));
+        let stranger = explain("employee_unknown");
+        assert_eq!((stranger.level.as_str(), stranger.reason.as_str()), ("none", "default_deny"));
+        let sarah = explain("employee_sarah");
+        assert_eq!((sarah.level.as_str(), sarah.reason.as_str()), ("none", "default_deny"));
+        let allow = explain_visibility(&VisibilityRules::default(), &trace, "employee_unknown");
+        assert_eq!((allow.level.as_str(), allow.reason.as_str()), ("summary", "topic_keyword"));
+    }
+
+    #[test]
+    fn asserted_identity_prefers_header_then_body_name_then_agent_id() {
+        let all = identity(Some("John"), None, Some("Sarah"), Some("employee_bob"), None);
+        assert_eq!(all.unwrap(), "employee_john");
+        let body = identity(None, None, Some("Sarah"), Some("employee_bob"), None);
+        assert_eq!(body.unwrap(), "employee_sarah");
+        let agent = identity(None, None, Some("  "), Some("employee_bob"), None);
+        assert_eq!(agent.unwrap(), "employee_bob");
+        assert_eq!(identity(None, None, None, None, None), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(identity(Some("!!!"), None, None, None, None), Err(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn bearer_token_wins_and_rejects_any_conflicting_assertion() {
+        assert_eq!(identity(None, Some("tok-sarah"), None, None, None).unwrap(), "employee_sarah");
+        let agreeing = identity(Some("Sarah"), Some("tok-sarah"), Some("sarah"), None, None);
+        assert_eq!(agreeing.unwrap(), "employee_sarah");
+        for (header, name, agent_id) in [
+            (Some("John"), None, None),
+            (None, Some("John"), None),
+            (None, None, Some("employee_john")),
+        ] {
+            let conflict = identity(header, Some("tok-sarah"), name, agent_id, None);
+            assert_eq!(conflict, Err(StatusCode::FORBIDDEN));
+        }
+        // Only the winning assertion (the header) is compared with the token.
+        let shadowed = identity(Some("Sarah"), Some("tok-sarah"), Some("John"), None, None);
+        assert_eq!(shadowed.unwrap(), "employee_sarah");
+        let unknown = identity(Some("Sarah"), Some("tok-forged"), None, None, None);
+        assert_eq!(unknown, Err(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn allowlist_limits_asserted_identities_but_not_tokens() {
+        let allow = Some(&["employee_bob"][..]);
+        assert_eq!(identity(Some("Bob"), None, None, None, allow).unwrap(), "employee_bob");
+        assert_eq!(identity(Some("John"), None, None, None, allow), Err(StatusCode::FORBIDDEN));
+        let by_agent_id = identity(None, None, None, Some("employee_john"), allow);
+        assert_eq!(by_agent_id, Err(StatusCode::FORBIDDEN));
+        let token = identity(None, Some("tok-sarah"), None, None, allow);
+        assert_eq!(token.unwrap(), "employee_sarah");
+    }
+
+    #[test]
+    fn employee_names_map_to_slugged_agent_ids() {
+        assert_eq!(employee_agent_id_from_name("  Zoë  O'Brien ").unwrap(), "employee_zoe_o_brien");
+        let err = employee_agent_id_from_name("!!!").unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn low_confidence_alert_only_below_threshold() {
+        let trace = ReasoningTrace::sample("hiring_freeze", "hiring", 0.3);
+        let alert = low_confidence_alert(&trace, 0.4).expect("0.3 is below 0.4");
+        assert_eq!(alert.event_type(), "low_confidence");
+        let json = serde_json::to_value(&alert).unwrap();
+        assert_eq!(json["type"], "low_confidence");
+        assert_eq!(json["data"]["decision_id"], "hiring_freeze");
+        assert_eq!(json["data"]["topic"], "hiring");
+
+        assert!(low_confidence_alert(&trace, 0.3).is_none());
+        let confident = ReasoningTrace::sample("hiring_freeze", "hiring", 0.9);
+        assert!(low_confidence_alert(&confident, 0.4).is_none());
+    }
+
+    #[tokio::test]
+    async fn spec_is_written_as_matching_json_and_yaml() {
+        let dir = std::env::temp_dir().join(format!("cos-spec-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (json_path, yaml_path) = (dir.join("spec.json"), dir.join("spec.yaml"));
+        write_spec(&json_path, &yaml_path).await.unwrap();
+
+        let from_json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&json_path).unwrap()).unwrap();
+        let from_yaml: serde_json::Value =
+            serde_yaml::from_str(&std::fs::read_to_string(&yaml_path).unwrap()).unwrap();
+        assert_eq!(from_json, from_yaml);
+        assert!(from_json["paths"]["/v1/ask"]["post"].is_object());
+        assert!(from_json["paths"]["/v1/graph/cypher"]["post"].is_object());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
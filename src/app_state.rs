@@ -1,73 +1,324 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use once_cell::sync::Lazy;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::sync::{Mutex, MutexGuard};
 
 use rrag::prelude::*;
-use std::env;
 use std::fs::File;
 use std::path::Path;
-use uuid::Uuid;
 
-use crate::domain::{EmployeeAgentId, Event, PrivateStoreKey, ReasoningTrace};
+use crate::crypto::NoteCipher;
+use crate::graph_store::GraphStore;
+use crate::domain::{EmployeeAgentId, EmployeeRole, Event, PrivateStoreKey, ReasoningTrace};
+use crate::memory_store::MemoryStore;
+use crate::redaction::redact;
+use crate::config::CosConfig;
 use crate::neo4j::Neo4jClient;
 use crate::neo4j::writer::{
-    merge_employee_from_email, persist_email_message, persist_knowledge_cluster, seed_employees,
+    assign_message_to_cluster, email_date_rfc3339, email_thread_id, join_knowledge_cluster,
+    load_current_truth, merge_employee_from_email, persist_email_message, persist_email_thread,
+    persist_email_thread_membership, seed_employees, set_knowledge_cluster_label,
+    EmailMessageRecord, RetrievedSnippet,
 };
 use crate::runtime::event_bus::EventBus;
+use crate::visibility::VisibilityRules;
 
+/// `COS_TRUTH_HISTORY` (default 20, minimum 1): entries kept per key in `AppState::org_truth`.
+pub fn truth_history_cap() -> usize {
+    config().orgbrain.truth_history.max(1)
+}
+
+/// `COS_MAX_EVENTS_PER_BRAIN` (default 50, minimum 1): events the terminal flow's OrgBrain
+/// reasons over in one call.
+pub fn max_events_per_brain() -> usize {
+    config().orgbrain.max_events.max(1)
+}
+
+/// Set while the server runs without the Neo4j it is configured for (see
+/// `AppState::init_neo4j_or_degrade`).
+static NEO4J_DEGRADED: AtomicBool = AtomicBool::new(false);
+
+/// Whether Neo4j was unreachable at startup and hasn't been reconnected yet.
+pub fn neo4j_degraded() -> bool {
+    NEO4J_DEGRADED.load(Ordering::Relaxed)
+}
+
+/// `COS_MIN_EVENT_CONFIDENCE` (default 0, i.e. keep everything).
+pub fn min_event_confidence() -> f32 {
+    config().orgbrain.min_event_confidence
+}
+
+/// Event queue and org truth, which the OrgBrain reads and updates together. Traces,
+/// per-employee sessions and backend handles have their own locks (`traces`, `sessions`,
+/// `handles`), so unrelated requests don't wait on each other. When holding more than one,
+/// take `APP_STATE` first, then `traces`, then `sessions`.
 pub static APP_STATE: Lazy<Mutex<AppState>> = Lazy::new(|| Mutex::new(AppState::new()));
 
+static HANDLES: Lazy<RwLock<Handles>> = Lazy::new(|| RwLock::new(Handles::default()));
+static TRACES: Lazy<Mutex<Vec<ReasoningTrace>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static SESSIONS: Lazy<Mutex<Sessions>> = Lazy::new(|| Mutex::new(Sessions::default()));
+/// Atomic so private keys stay unique without holding any lock.
+static PRIVATE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Backend handles: set at startup, then only read. Everything in here is cheap to clone.
+#[derive(Clone, Default)]
+pub struct Handles {
+    /// Searched concurrently: `RragSystem` only needs `&self`, so no lock is held across a search.
+    pub rag: Option<Arc<RragSystem>>,
+    pub neo4j: Option<Neo4jClient>,
+    pub memory: Option<Arc<Mutex<MemoryStore>>>,
+    /// Versioning backend: the Neo4j client or the memory store, whichever was initialized.
+    pub graph_store: Option<Arc<dyn GraphStore>>,
+    note_cipher: Option<Arc<NoteCipher>>,
+    /// Role defaults for traces without a routing entry (`COS_VISIBILITY_RULES`).
+    pub visibility_rules: Arc<VisibilityRules>,
+    /// Loaded by `main` before anything else; defaults until then.
+    pub config: Arc<CosConfig>,
+}
+
+/// A snapshot of the backend handles. The lock is only held for the clone.
+pub fn handles() -> Handles {
+    HANDLES.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn handles_mut() -> std::sync::RwLockWriteGuard<'static, Handles> {
+    HANDLES.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The process configuration (see `config::CosConfig`).
+pub fn config() -> Arc<CosConfig> {
+    HANDLES.read().unwrap_or_else(|e| e.into_inner()).config.clone()
+}
+
+pub fn set_config(config: CosConfig) {
+    handles_mut().config = Arc::new(config);
+}
+
+/// Reasoning traces, oldest first.
+pub async fn traces() -> MutexGuard<'static, Vec<ReasoningTrace>> {
+    TRACES.lock().await
+}
+
+pub async fn sessions() -> MutexGuard<'static, Sessions> {
+    SESSIONS.lock().await
+}
+
 type PrivateMem = HashMap<PrivateStoreKey, String>;
 
+/// Per-employee state: encrypted private notes and the recent conversation turns.
+#[derive(Default)]
+pub struct Sessions {
+    pub private_store: HashMap<EmployeeAgentId, PrivateMem>,
+    pub conversation_cache: HashMap<EmployeeAgentId, Vec<(String, String)>>,
+}
+
+impl Sessions {
+    /// The next private note key for `agent`. Sequence numbers are unique and increasing across
+    /// all agents for the life of the process.
+    pub fn next_private_key(agent: &EmployeeAgentId) -> PrivateStoreKey {
+        let seq = PRIVATE_SEQ.fetch_add(1, Ordering::Relaxed) + 1;
+        PrivateStoreKey(format!("{}:{}", agent.0, seq))
+    }
+
+    pub fn store_private(&mut self, agent: &EmployeeAgentId, content: String) -> PrivateStoreKey {
+        let key = Self::next_private_key(agent);
+        // Never fall back to plaintext: without a key the note is dropped.
+        let Some(cipher) = handles().note_cipher else {
+            return key;
+        };
+        match cipher.encrypt(&redact(&content)) {
+            Ok(sealed) => {
+                self.private_store
+                    .entry(agent.clone())
+                    .or_default()
+                    .insert(key.clone(), sealed);
+            }
+            Err(e) => eprintln!("private note not stored: {e}"),
+        }
+        key
+    }
+
+    /// Decrypted private notes of `owner`. Only call this on paths serving that agent.
+    pub fn read_private_notes(
+        &self,
+        owner: &EmployeeAgentId,
+    ) -> Result<Vec<(PrivateStoreKey, String)>> {
+        let Some(cipher) = handles().note_cipher else {
+            return Ok(Vec::new());
+        };
+        let mut notes = self
+            .private_store
+            .get(owner)
+            .map(|mem| {
+                mem.iter()
+                    .map(|(k, sealed)| Ok((k.clone(), cipher.decrypt(sealed)?)))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        notes.sort_by(|a, b| a.0 .0.cmp(&b.0 .0));
+        Ok(notes)
+    }
+}
+
+/// Top `k` snippets for `query`, leaving out documents any of `roles` may not see (see
+/// `rag::visible_to`).
+pub async fn rag_search(
+    query: String,
+    k: usize,
+    roles: &[EmployeeRole],
+) -> Result<Vec<RetrievedSnippet>> {
+    let Some(rag) = handles().rag else {
+        return Ok(Vec::new());
+    };
+    // Over-fetch so that collapsing chunks of the same document and dropping documents the
+    // roles can't see still leaves k snippets.
+    let search = rag.search(query, Some(k * 3));
+    let results =
+        crate::utils::with_timeout("openai", crate::utils::openai_timeout(), search).await?;
+    Ok(top_snippets(results.results, k, roles))
+}
+
+/// The first `k` of `results` that `roles` may see, keeping only the best-ranked chunk of each
+/// chunked document (`parent_id`).
+fn top_snippets(
+    results: Vec<SearchResult>,
+    k: usize,
+    roles: &[EmployeeRole],
+) -> Vec<RetrievedSnippet> {
+    let mut seen_parents = HashSet::new();
+    let mut out = Vec::new();
+    for r in results {
+        if !crate::rag::visible_to(r.metadata.get(crate::rag::VISIBILITY_KEY), roles) {
+            continue;
+        }
+        if let Some(parent) = r.metadata.get("parent_id").and_then(|v| v.as_str()) {
+            if !seen_parents.insert(parent.to_string()) {
+                continue;
+            }
+        }
+        let source = ["source", "file"]
+            .iter()
+            .find_map(|key| r.metadata.get(*key).and_then(|v| v.as_str()))
+            .map(str::to_string);
+        out.push(RetrievedSnippet {
+            content: r.content,
+            score: r.score,
+            source,
+        });
+        if out.len() >= k {
+            break;
+        }
+    }
+    out
+}
+
 pub struct AppState {
     pub event_bus: EventBus,
-    pub private_store: HashMap<EmployeeAgentId, PrivateMem>,
     pub org_truth: HashMap<String, Vec<String>>,
-    pub traces: Vec<ReasoningTrace>,
-    pub conversation_cache: HashMap<EmployeeAgentId, Vec<(String, String)>>,
-    pub rag: Option<Arc<Mutex<RragSystem>>>,
-    pub neo4j: Option<Neo4jClient>,
-    private_seq: u64,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
             event_bus: EventBus::new(),
-            private_store: HashMap::new(),
             org_truth: HashMap::new(),
-            traces: Vec::new(),
-            conversation_cache: HashMap::new(),
-            rag: None,
-            neo4j: None,
-            private_seq: 0,
         }
     }
 
     pub async fn init_neo4j(&mut self) -> Result<()> {
-        let client = Neo4jClient::connect_from_env().await?;
-        client.run_migrations().await?;
-        seed_employees(client.graph()).await?;
-        self.neo4j = Some(client);
+        let client = prepare_neo4j().await?;
+        let stored = load_stored_state(&client).await;
+        self.attach_neo4j(client, stored);
+        Ok(())
+    }
+
+    /// Like `init_neo4j`, but when Neo4j can't be set up (and `COS_REQUIRE_NEO4J` is off) logs a
+    /// warning and keeps going without it: the graph handles stay `None`, so graph endpoints
+    /// answer 503, while a background task reconnects with backoff and attaches the client once
+    /// migrations and seeding succeed.
+    pub async fn init_neo4j_or_degrade(&mut self) -> Result<()> {
+        let e = match self.init_neo4j().await {
+            Ok(()) => return Ok(()),
+            Err(e) if config().neo4j.required => return Err(e),
+            Err(e) => e,
+        };
+        eprintln!(
+            "warning: neo4j unavailable, running in degraded mode without persistence: {e:#}"
+        );
+        NEO4J_DEGRADED.store(true, Ordering::Relaxed);
+        tokio::spawn(reconnect_neo4j());
+        Ok(())
+    }
+
+    /// Merges the truth recorded before this boot into the OrgBrain's view, applies the stored
+    /// roles and publishes `client` as the graph backend. Does no I/O, so it is cheap to run
+    /// under the `APP_STATE` lock.
+    fn attach_neo4j(&mut self, client: Neo4jClient, stored: StoredState) {
+        if let Some(truth) = stored.truth {
+            for (truth_id, versions) in truth {
+                self.org_truth.entry(truth_id).or_insert(versions);
+            }
+        }
+        if let Some(roles) = stored.roles {
+            crate::roles::load_stored_roles(roles);
+        }
+        let mut handles = handles_mut();
+        handles.graph_store = Some(Arc::new(client.clone()));
+        handles.neo4j = Some(client);
+    }
+
+    /// Self-contained mode: decisions and truth are versioned in memory instead of Neo4j.
+    pub fn init_memory_store(&mut self) {
+        let memory = Arc::new(Mutex::new(MemoryStore::new()));
+        let mut handles = handles_mut();
+        handles.graph_store = Some(memory.clone());
+        handles.memory = Some(memory);
+    }
+
+    pub fn init_visibility_rules(&mut self) -> Result<()> {
+        let rules = VisibilityRules::from_config(&config().visibility)?;
+        handles_mut().visibility_rules = Arc::new(rules);
+        Ok(())
+    }
+
+    /// Loads the private-note keys. Without `COS_PRIVATE_KEY` private notes are not persisted.
+    pub fn init_private_notes(&mut self) -> Result<()> {
+        let cipher = NoteCipher::from_env()?;
+        if cipher.is_none() {
+            eprintln!("COS_PRIVATE_KEY not set; private notes will not be persisted");
+        }
+        handles_mut().note_cipher = cipher.map(Arc::new);
         Ok(())
     }
 
     pub async fn init_rag(&mut self) -> Result<()> {
+        self.init_rag_from(Path::new("knowledge.csv")).await
+    }
+
+    /// Builds the RAG system, seeding it (and the graph) from the email CSV at `path` if present.
+    pub async fn init_rag_from(&mut self, path: &Path) -> Result<()> {
+        let source = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "knowledge.csv".to_string());
         let rag = RragSystemBuilder::new()
             .with_name("OrgBrain")
             .with_environment("development")
             .build()
             .await?;
 
-        let max_docs: usize = env::var("RAG_MAX_DOCS")
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(1000);
+        let max_docs = config().rag.max_docs;
 
-        let path = Path::new("knowledge.csv");
         if path.exists() {
             let file = File::open(path)?;
             let mut rdr = csv::ReaderBuilder::new()
@@ -75,27 +326,31 @@ impl AppState {
                 .flexible(true)
                 .from_reader(file);
 
+            let cols = CsvColumns::from_headers(rdr.headers()?);
+
             let mut ingested = 0usize;
-            let neo4j = self.neo4j.clone();
+            // Messages whose Date didn't parse; they are stored without `sent_at`.
+            let mut unparseable_dates = 0usize;
+            let neo4j = handles().neo4j;
 
-            let cluster_enabled = env::var("OPENAI_API_KEY")
-                .ok()
-                .map(|v| !v.trim().is_empty())
-                .unwrap_or(false);
+            // Offline mode has no embeddings, so no clustering (triage falls back to the stub).
+            let cluster_enabled = crate::llm::openai_configured();
 
-            let cluster_sim_threshold: f32 = env::var("ORG_EMAIL_CLUSTER_SIM")
-                .ok()
-                .and_then(|v| v.parse().ok())
-                .unwrap_or(0.85);
+            let cluster_sim_threshold = config().ingest.cluster_sim;
 
-            let mut cluster_centroids: Vec<Vec<f32>> = Vec::new();
-            let mut cluster_members: Vec<Vec<String>> = Vec::new();
-            let mut cluster_labels: Vec<String> = Vec::new();
+            // Messages this run added to each cluster; only those clusters are (re)labelled.
+            let mut cluster_members: HashMap<String, Vec<String>> = HashMap::new();
+            // `subject: snippet` per clustered message, for LLM cluster labels.
+            let mut cluster_samples: HashMap<String, String> = HashMap::new();
+            // Cluster of each thread seen so far.
+            let mut thread_clusters: HashMap<String, String> = HashMap::new();
 
             for result in rdr.records() {
                 let record = result?;
-                let file_name = record.get(0).unwrap_or("").to_string();
-                let message = record.get(1).unwrap_or("").to_string();
+                let file_name = record.get(cols.file).unwrap_or("").to_string();
+                let message = record.get(cols.message).unwrap_or("").to_string();
+                let csv_date = cols.get(&record, cols.date);
+                let folder = cols.get(&record, cols.folder);
 
                 if message.trim().is_empty() {
                     continue;
@@ -104,94 +359,195 @@ impl AppState {
                 if let Some(client) = neo4j.clone() {
                     let graph = client.graph();
 
-                    let parsed = parse_email_blob(&message);
-                    if let Some(from_email) = parsed.from_email.as_deref() {
-                        let _ = merge_employee_from_email(
-                            graph,
-                            from_email,
-                            parsed.from_name.as_deref(),
-                        )
-                        .await;
-                    }
+                    // An mbox-style cell holds several messages; each is persisted on its own.
+                    let parts = split_mbox(&message);
+                    for (idx, part) in parts.iter().enumerate() {
+                        let parsed = parse_email_blob(part);
+                        if let Some(from_email) = parsed.from_email.as_deref() {
+                            let _ = merge_employee_from_email(
+                                graph,
+                                from_email,
+                                parsed.from_name.as_deref(),
+                            )
+                            .await;
+                        }
 
-                    for (to_email, to_name) in parsed.to_emails.iter() {
-                        let _ = merge_employee_from_email(graph, to_email, to_name.as_deref()).await;
-                    }
+                        for (to_email, to_name) in parsed.to_emails.iter() {
+                            let _ =
+                                merge_employee_from_email(graph, to_email, to_name.as_deref())
+                                    .await;
+                        }
 
-                    let from_employee_id = parsed
-                        .from_email
-                        .as_deref()
-                        .map(crate::neo4j::writer::canonical_employee_id_from_email)
-                        .unwrap_or_else(|| "employee_email_unknown".to_string());
-
-                    let to_employee_ids: Vec<String> = parsed
-                        .to_emails
-                        .iter()
-                        .map(|(e, _)| crate::neo4j::writer::canonical_employee_id_from_email(e))
-                        .collect();
-
-                    let topic_ids = derive_topics(&parsed.subject);
-                    let msg_id = parsed
-                        .message_id
-                        .clone()
-                        .unwrap_or_else(|| file_name.clone());
-
-                    let _ = persist_email_message(
-                        graph,
-                        &msg_id,
-                        &file_name,
-                        parsed.subject.as_deref().unwrap_or(""),
-                        parsed.date.as_deref().unwrap_or(""),
-                        &from_employee_id,
-                        &to_employee_ids,
-                        &topic_ids,
-                    )
-                    .await;
-
-                    if cluster_enabled {
-                        let text = build_embedding_text(
+                        let from_employee_id = parsed
+                            .from_email
+                            .as_deref()
+                            .map(crate::neo4j::writer::canonical_employee_id_from_email)
+                            .unwrap_or_else(|| "employee_email_unknown".to_string());
+
+                        let to_employee_ids: Vec<String> = parsed
+                            .to_emails
+                            .iter()
+                            .map(|(e, _)| crate::neo4j::writer::canonical_employee_id_from_email(e))
+                            .collect();
+
+                        let topic_ids = derive_topics(&parsed.subject);
+                        let msg_id = parsed.message_id.clone().unwrap_or_else(|| {
+                            if parts.len() > 1 {
+                                format!("{file_name}#{}", idx + 1)
+                            } else {
+                                file_name.clone()
+                            }
+                        });
+
+                        let triage = crate::triage::triage_email(
                             parsed.subject.as_deref().unwrap_or(""),
                             &parsed.body,
-                        );
-                        if let Ok(emb) = openai_embedding(&text).await {
-                            assign_to_clusters(
-                                msg_id.clone(),
-                                &topic_ids,
-                                emb,
-                                cluster_sim_threshold,
-                                &mut cluster_centroids,
-                                &mut cluster_members,
-                                &mut cluster_labels,
+                            cluster_enabled,
+                        )
+                        .await;
+
+                        let date = parsed.date.as_deref().or(csv_date.as_deref()).unwrap_or("");
+                        if email_date_rfc3339(date).is_none() {
+                            unparseable_dates += 1;
+                        }
+                        let redacted_subject = redact(parsed.subject.as_deref().unwrap_or(""));
+                        let message = EmailMessageRecord {
+                            message_id: &msg_id,
+                            file: &file_name,
+                            subject: &redacted_subject,
+                            date,
+                            folder: folder.as_deref().unwrap_or(""),
+                            from_employee_id: &from_employee_id,
+                            to_employee_ids: &to_employee_ids,
+                            topic_ids: &topic_ids,
+                            urgency: triage.urgency,
+                            sentiment: &triage.sentiment,
+                        };
+                        let _ = persist_email_message(graph, &message).await;
+
+                        // Reply to In-Reply-To, else to the last entry of References.
+                        let parent_id = parsed
+                            .in_reply_to
+                            .clone()
+                            .or_else(|| parsed.references.last().cloned())
+                            .filter(|p| *p != msg_id);
+                        if let Some(parent_id) = &parent_id {
+                            let _ = persist_email_thread(
+                                graph,
+                                &msg_id,
+                                parent_id,
+                                &parsed.references,
+                            )
+                            .await;
+                        }
+                        let thread_id =
+                            email_thread_id(&msg_id, parent_id.as_deref(), &parsed.references);
+                        let members: Vec<String> =
+                            std::iter::once(msg_id.clone()).chain(parent_id).collect();
+                        let _ = persist_email_thread_membership(
+                            graph,
+                            &thread_id,
+                            &members,
+                            &redacted_subject,
+                        )
+                        .await;
+
+                        if cluster_enabled {
+                            let text = build_embedding_text(
+                                parsed.subject.as_deref().unwrap_or(""),
+                                &parsed.body,
                             );
+                            if let Ok(emb) = openai_embedding(&text).await {
+                                cluster_samples.insert(
+                                    msg_id.clone(),
+                                    cluster_sample(
+                                        parsed.subject.as_deref().unwrap_or(""),
+                                        &parsed.body,
+                                    ),
+                                );
+                                // A thread stays in one cluster, whatever its replies
+                                // look like.
+                                let thread_cluster = thread_clusters.get(&thread_id).cloned();
+                                let assigned = match thread_cluster {
+                                    Some(cluster_id) => {
+                                        join_knowledge_cluster(graph, &cluster_id, &msg_id, &emb)
+                                            .await
+                                            .map(|joined| {
+                                                joined.then_some((cluster_id, false))
+                                            })
+                                    }
+                                    None => assign_message_to_cluster(
+                                        graph,
+                                        &msg_id,
+                                        &emb,
+                                        cluster_sim_threshold,
+                                    )
+                                    .await
+                                    .map(|a| Some((a.cluster_id, a.existing))),
+                                };
+                                match assigned {
+                                    Ok(Some((cluster_id, existing))) => {
+                                        if !existing {
+                                            cluster_members
+                                                .entry(cluster_id.clone())
+                                                .or_default()
+                                                .push(msg_id.clone());
+                                        }
+                                        thread_clusters.insert(thread_id.clone(), cluster_id);
+                                    }
+                                    Ok(None) => {}
+                                    Err(e) => eprintln!("Clustering {msg_id}: {e:#}"),
+                                }
+                            }
                         }
                     }
                 }
 
-                let doc = Document::new(message)
-                    .with_metadata("source", "knowledge.csv".into())
-                    .with_metadata("file", file_name.into())
-                    .with_content_hash();
-                rag.process_document(doc).await?;
+                let mut metadata: Vec<(&str, serde_json::Value)> = vec![
+                    ("source", source.clone().into()),
+                    ("file", file_name.into()),
+                ];
+                if let Some(folder) = folder {
+                    metadata.push(("folder", folder.into()));
+                }
+                if let Some(date) = csv_date {
+                    metadata.push(("date", date.into()));
+                }
+                for doc in crate::rag::documents_for(&redact(&message), &metadata) {
+                    rag.process_document(doc).await?;
+                }
 
                 ingested += 1;
                 if ingested >= max_docs {
                     break;
                 }
             }
+            if unparseable_dates > 0 {
+                println!("{unparseable_dates} email(s) had no parseable Date (no sent_at).");
+            }
 
             if cluster_enabled {
                 if let Some(client) = neo4j {
                     let graph = client.graph();
-                    for (idx, member_ids) in cluster_members.iter().enumerate() {
-                        if member_ids.len() < 2 {
+                    let label_min_members = crate::triage::cluster_label_min_members();
+                    let min_cluster_size = crate::triage::min_cluster_size();
+                    for (cluster_id, member_ids) in &cluster_members {
+                        if member_ids.len() < label_min_members.max(min_cluster_size) {
                             continue;
                         }
-                        let cluster_id = format!("cluster_{}", Uuid::new_v4());
-                        let label = cluster_labels
-                            .get(idx)
-                            .cloned()
-                            .unwrap_or_else(|| "cluster".to_string());
-                        let _ = persist_knowledge_cluster(graph, &cluster_id, &label, member_ids).await;
+                        let samples: Vec<String> = member_ids
+                            .iter()
+                            .filter_map(|id| cluster_samples.get(id).cloned())
+                            .collect();
+                        if let Some(label) = crate::triage::label_cluster(&samples).await {
+                            let _ = set_knowledge_cluster_label(
+                                graph,
+                                cluster_id,
+                                &label.name,
+                                &label.description,
+                            )
+                            .await;
+                        }
                     }
                 }
             }
@@ -210,57 +566,191 @@ impl AppState {
             }
         }
 
-        self.rag = Some(Arc::new(Mutex::new(rag)));
+        handles_mut().rag = Some(Arc::new(rag));
         Ok(())
     }
 
-    pub fn store_private(&mut self, agent: &EmployeeAgentId, content: String) -> PrivateStoreKey {
-        self.private_seq += 1;
-        let key = PrivateStoreKey(format!("{}:{}", agent.0, self.private_seq));
-        self.private_store
-            .entry(agent.clone())
-            .or_default()
-            .insert(key.clone(), content);
-        key
-    }
-
     pub fn emit(&mut self, event: Event) {
         self.event_bus.emit(event);
     }
 
+    /// Queued events for the OrgBrain. Events below `COS_MIN_EVENT_CONFIDENCE` are logged and
+    /// dropped here, so speculative signals never reach reasoning or decision persistence.
     pub fn drain_events(&mut self) -> Vec<Event> {
-        self.event_bus.drain()
+        self.drain_events_up_to(usize::MAX)
+    }
+
+    /// Like `drain_events`, but takes at most `max` events, oldest first. The rest stay queued
+    /// in order for the next call.
+    pub fn drain_events_up_to(&mut self, max: usize) -> Vec<Event> {
+        self.take_events(max, min_event_confidence())
+    }
+
+    fn take_events(&mut self, max: usize, floor: f32) -> Vec<Event> {
+        let mut out = Vec::new();
+        while out.len() < max {
+            let Some(e) = self.event_bus.pop() else {
+                break;
+            };
+            if e.confidence < floor {
+                eprintln!(
+                    "dropping event {} from {} (topic {:?}): confidence {} below floor {}",
+                    e.event_id, e.emitted_by.0, e.topic, e.confidence, floor
+                );
+                continue;
+            }
+            out.push(e);
+        }
+        out
+    }
+
+    /// Puts events taken by `drain_events` back ahead of newer ones.
+    pub fn requeue_events(&mut self, events: &[Event]) {
+        self.event_bus.requeue(events);
+    }
+
+    pub fn queued_events(&self) -> usize {
+        self.event_bus.len()
     }
 
+    /// Appends to `node`'s history, keeping at most `truth_history_cap()` entries.
     pub fn update_org_truth(&mut self, node: &str, content: String) {
-        self.org_truth.entry(node.to_string()).or_default().push(content);
+        let history = self.org_truth.entry(node.to_string()).or_default();
+        history.push(content);
+        let cap = truth_history_cap();
+        if history.len() > cap {
+            history.drain(..history.len() - cap);
+        }
     }
 
     pub fn latest_truth(&self, node: &str) -> Option<&str> {
         self.org_truth.get(node).and_then(|v| v.last().map(|s| s.as_str()))
     }
+}
 
-    pub fn add_trace(&mut self, trace: ReasoningTrace) {
-        self.traces.push(trace);
+/// Connects to Neo4j, applies the migrations and seeds the employees.
+async fn prepare_neo4j() -> Result<Neo4jClient> {
+    let client = Neo4jClient::connect(&config().neo4j).await?;
+    client.run_migrations().await?;
+    let seeds = crate::employees::SeedConfig::from_env()?;
+    let unseeded = seed_employees(client.graph(), &seeds.employees, seeds.mark_removed).await?;
+    if unseeded > 0 {
+        println!("marked {unseeded} employees no longer in the seed list as seeded=false");
     }
+    Ok(client)
+}
 
-    pub async fn rag_search(&self, query: String, k: usize) -> Result<Vec<String>> {
-        let Some(rag) = &self.rag else {
-            return Ok(Vec::new());
+/// What `attach_neo4j` takes from the graph; `None` where loading it failed.
+struct StoredState {
+    truth: Option<HashMap<String, Vec<String>>>,
+    roles: Option<HashMap<String, EmployeeRole>>,
+}
+
+/// Loads the current org truth and the stored employee roles, each bounded by
+/// `NEO4J_TIMEOUT_MS`. Failures are logged and leave that part out.
+async fn load_stored_state(client: &Neo4jClient) -> StoredState {
+    let limit = crate::utils::neo4j_timeout();
+    let truth = crate::utils::with_timeout("neo4j", limit, load_current_truth(client.graph()));
+    let truth = match truth.await {
+        Ok(truth) => {
+            if !truth.is_empty() {
+                println!("loaded {} org truth entries from neo4j", truth.len());
+            }
+            Some(truth)
+        }
+        Err(e) => {
+            eprintln!("load org truth from neo4j: {e:#}");
+            None
+        }
+    };
+    let roles = crate::neo4j::writer::load_employee_roles(client.graph());
+    let roles = match crate::utils::with_timeout("neo4j", limit, roles).await {
+        Ok(roles) => Some(roles),
+        Err(e) => {
+            eprintln!("load employee roles from neo4j: {e:#}");
+            None
+        }
+    };
+    StoredState { truth, roles }
+}
+
+/// Retries `prepare_neo4j` after 1, 2, 4, … seconds (at most `COS_NEO4J_RECONNECT_MAX_SECS`)
+/// until it succeeds, then attaches the client and leaves degraded mode.
+async fn reconnect_neo4j() {
+    let max = std::time::Duration::from_secs(config().neo4j.reconnect_max_secs);
+    let mut delay = next_reconnect_delay(None, max);
+    loop {
+        tokio::time::sleep(delay).await;
+        match prepare_neo4j().await {
+            Ok(client) => {
+                // Loaded before taking the lock, so requests aren't held up by the graph.
+                let stored = load_stored_state(&client).await;
+                APP_STATE.lock().await.attach_neo4j(client, stored);
+                NEO4J_DEGRADED.store(false, Ordering::Relaxed);
+                println!("neo4j connected; leaving degraded mode");
+                return;
+            }
+            Err(e) => {
+                delay = next_reconnect_delay(Some(delay), max);
+                eprintln!("neo4j reconnect failed, retrying in {}s: {e:#}", delay.as_secs());
+            }
+        }
+    }
+}
+
+/// Wait before the next reconnect attempt: 1 s first, then double the `previous` wait, at most
+/// `max`.
+fn next_reconnect_delay(
+    previous: Option<std::time::Duration>,
+    max: std::time::Duration,
+) -> std::time::Duration {
+    previous.map_or(std::time::Duration::from_secs(1), |d| d * 2).min(max)
+}
+
+/// Column positions in knowledge.csv. Looked up by header name when the
+/// header row names `file` and `message`, otherwise positional (file, message).
+struct CsvColumns {
+    file: usize,
+    message: usize,
+    date: Option<usize>,
+    folder: Option<usize>,
+}
+
+impl CsvColumns {
+    fn from_headers(headers: &csv::StringRecord) -> Self {
+        let find = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name))
         };
-        let rag = rag.lock().await;
-        let results = rag.search(query, Some(k)).await?;
-        let mut out = Vec::new();
-        for r in results.results {
-            out.push(r.content);
+        match (find("file"), find("message")) {
+            (Some(file), Some(message)) => Self {
+                file,
+                message,
+                date: find("date"),
+                folder: find("folder"),
+            },
+            _ => Self {
+                file: 0,
+                message: 1,
+                date: None,
+                folder: None,
+            },
         }
-        Ok(out)
+    }
+
+    fn get(&self, record: &csv::StringRecord, idx: Option<usize>) -> Option<String> {
+        idx.and_then(|i| record.get(i))
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
     }
 }
 
 #[derive(Debug, Default, Clone)]
 struct ParsedEmail {
     message_id: Option<String>,
+    in_reply_to: Option<String>,
+    references: Vec<String>,
     date: Option<String>,
     subject: Option<String>,
     from_email: Option<String>,
@@ -269,12 +759,45 @@ struct ParsedEmail {
     body: String,
 }
 
+/// The messages in an mbox-style blob: it starts with a `From ` separator line, and each
+/// further message starts at a `From ` line after a blank line. Separator lines are dropped and
+/// `>From ` lines unescaped. A blob without a leading separator is one message.
+fn split_mbox(blob: &str) -> Vec<String> {
+    let is_separator = |line: &str| line.starts_with("From ");
+    if !blob.trim_start().lines().next().is_some_and(is_separator) {
+        return vec![blob.to_string()];
+    }
+
+    let mut messages = Vec::new();
+    let mut current: Option<Vec<&str>> = None;
+    let mut prev_blank = true;
+    for line in blob.trim_start().lines() {
+        if prev_blank && is_separator(line) {
+            if let Some(lines) = current.take() {
+                messages.push(lines);
+            }
+            current = Some(Vec::new());
+        } else if let Some(lines) = current.as_mut() {
+            lines.push(line.strip_prefix('>').filter(|l| l.starts_with("From ")).unwrap_or(line));
+        }
+        prev_blank = line.trim().is_empty();
+    }
+    messages.extend(current);
+
+    messages
+        .into_iter()
+        .map(|lines| lines.join("\n").trim().to_string())
+        .filter(|m| !m.is_empty())
+        .collect()
+}
+
 fn parse_email_blob(message: &str) -> ParsedEmail {
     let mut out = ParsedEmail::default();
     let mut headers: HashMap<String, String> = HashMap::new();
 
     let mut in_headers = true;
     let mut body_lines: Vec<&str> = Vec::new();
+    let mut last_key: Option<String> = None;
 
     for line in message.lines() {
         if in_headers {
@@ -283,8 +806,18 @@ fn parse_email_blob(message: &str) -> ParsedEmail {
                 continue;
             }
 
+            // Folded header: a continuation of the previous one (long `References` are).
+            if line.starts_with([' ', '\t']) {
+                if let Some(e) = last_key.as_ref().and_then(|k| headers.get_mut(k)) {
+                    e.push(' ');
+                    e.push_str(line.trim());
+                }
+                continue;
+            }
+
             if let Some((k, v)) = line.split_once(':') {
                 let key = k.trim().to_lowercase();
+                last_key = Some(key.clone());
                 let val = v.trim().to_string();
                 headers
                     .entry(key)
@@ -305,6 +838,13 @@ fn parse_email_blob(message: &str) -> ParsedEmail {
         .get("message-id")
         .cloned()
         .map(|s| s.trim().trim_matches('<').trim_matches('>').to_string());
+    out.in_reply_to = headers
+        .get("in-reply-to")
+        .and_then(|v| parse_message_ids(v).into_iter().next());
+    out.references = headers
+        .get("references")
+        .map(|v| parse_message_ids(v))
+        .unwrap_or_default();
     out.date = headers.get("date").cloned();
     out.subject = headers.get("subject").cloned();
 
@@ -325,6 +865,32 @@ fn parse_email_blob(message: &str) -> ParsedEmail {
     out
 }
 
+/// Message ids from an `In-Reply-To` / `References` value: `<a@x> <b@y>`, brackets stripped.
+/// Falls back to whitespace-separated tokens when the ids are not bracketed.
+fn parse_message_ids(v: &str) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut rest = v;
+    while let Some(start) = rest.find('<') {
+        let Some(len) = rest[start + 1..].find('>') else {
+            break;
+        };
+        let id = rest[start + 1..start + 1 + len].trim();
+        if !id.is_empty() && !ids.iter().any(|x| x == id) {
+            ids.push(id.to_string());
+        }
+        rest = &rest[start + 1 + len + 1..];
+    }
+    if ids.is_empty() {
+        for tok in v.split_whitespace() {
+            let tok = tok.trim_matches(|c| c == '<' || c == '>' || c == ',');
+            if tok.contains('@') && !ids.iter().any(|x| x == tok) {
+                ids.push(tok.to_string());
+            }
+        }
+    }
+    ids
+}
+
 fn parse_many_recipients(s: &str) -> Vec<(String, Option<String>)> {
     let mut out = Vec::new();
     for part in s.split(',') {
@@ -333,11 +899,9 @@ fn parse_many_recipients(s: &str) -> Vec<(String, Option<String>)> {
             continue;
         }
 
-        if let Some((email_opt, name_opt)) = parse_name_email(part) {
-            if let Some(email) = email_opt {
-                out.push((email, name_opt));
-                continue;
-            }
+        if let Some((Some(email), name_opt)) = parse_name_email(part) {
+            out.push((email, name_opt));
+            continue;
         }
 
         for email in extract_emails(part) {
@@ -378,34 +942,48 @@ fn parse_name_email(s: &str) -> Option<(Option<String>, Option<String>)> {
     Some((None, None))
 }
 
+fn is_local_part_char(c: char) -> bool {
+    // RFC 5322 `atext` plus the dot separator.
+    c.is_ascii_alphanumeric() || "!#$%&'*+/=?^_`{|}~-.".contains(c)
+}
+
+fn is_domain_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-'
+}
+
 fn extract_emails(s: &str) -> Vec<String> {
     let mut out = Vec::new();
     let bytes = s.as_bytes();
     let mut i = 0usize;
     while i < bytes.len() {
         if bytes[i] == b'@' {
+            // Local part: either a quoted string ("first last"@...) or a run of atext.
             let mut l = i;
-            while l > 0 {
-                let c = bytes[l - 1] as char;
-                if c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-' {
+            if l > 0 && bytes[l - 1] == b'"' {
+                if let Some(open) = s[..l - 1].rfind('"') {
+                    l = open;
+                }
+            } else {
+                while l > 0 && is_local_part_char(bytes[l - 1] as char) {
                     l -= 1;
-                } else {
-                    break;
+                }
+                while l < i && bytes[l] == b'.' {
+                    l += 1;
                 }
             }
+
             let mut r = i + 1;
-            while r < bytes.len() {
-                let c = bytes[r] as char;
-                if c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-' {
-                    r += 1;
-                } else {
-                    break;
-                }
+            while r < bytes.len() && is_domain_char(bytes[r] as char) {
+                r += 1;
+            }
+            while r > i + 1 && bytes[r - 1] == b'.' {
+                r -= 1;
             }
+
             if l < i && r > i + 1 {
-                let cand = &s[l..r];
-                if cand.contains('.') {
-                    out.push(cand.trim().to_lowercase());
+                let domain = &s[i + 1..r];
+                if domain.contains('.') {
+                    out.push(s[l..r].trim().to_lowercase());
                 }
                 i = r;
                 continue;
@@ -419,12 +997,74 @@ fn extract_emails(s: &str) -> Vec<String> {
     out
 }
 
+const REPLY_PREFIXES: [&str; 7] = ["re", "fwd", "fw", "aw", "wg", "tr", "sv"];
+
+const TOPIC_STOPWORDS: [&str; 24] = [
+    "the", "and", "for", "with", "from", "this", "that", "your", "our", "are", "was", "has",
+    "have", "will", "about", "please", "meeting", "update", "updated", "question", "regarding",
+    "info", "fyi", "new",
+];
+
+/// Strips reply/forward markers ("Re:", "Fwd:", "RE[2]:", "[list]") and collapses whitespace.
+fn strip_reply_prefixes(subject: &str) -> String {
+    let mut s = subject.trim();
+    loop {
+        let before = s;
+        if s.starts_with('[') {
+            if let Some(end) = s.find(']') {
+                s = s[end + 1..].trim_start();
+            }
+        }
+        if let Some((head, rest)) = s.split_once(':') {
+            let head = head.trim().to_lowercase();
+            let head = head
+                .split_once('[')
+                .map(|(h, _)| h.trim().to_string())
+                .unwrap_or(head);
+            if REPLY_PREFIXES.contains(&head.as_str()) {
+                s = rest.trim_start();
+            }
+        }
+        if s == before {
+            break;
+        }
+    }
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Order-independent keyword key for a subject ("Q3 budget review" == "review: budget q3").
+fn subject_keywords(subject: &str, max: usize) -> String {
+    let mut words: Vec<String> = subject
+        .split(|c: char| !c.is_alphanumeric())
+        .map(|w| w.to_lowercase())
+        .filter(|w| w.chars().count() >= 2 && !TOPIC_STOPWORDS.contains(&w.as_str()))
+        .filter(|w| !w.chars().all(|c| c.is_ascii_digit()))
+        .collect();
+    words.sort();
+    words.dedup();
+    words.truncate(max);
+    words.join(" ")
+}
+
+/// `ORG_TOPIC_MODE=subject` (default) keeps the cleaned subject line as the topic;
+/// `keywords` maps it to a sorted keyword key so equivalent subjects share one :Topic.
 fn derive_topics(subject: &Option<String>) -> Vec<String> {
     let subj = subject.clone().unwrap_or_default();
-    let norm = subj.trim().to_lowercase();
+    let norm = strip_reply_prefixes(&subj).to_lowercase();
+    let norm = norm
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .to_string();
     if norm.is_empty() {
         return vec!["(no subject)".to_string()];
     }
+
+    let ingest = &config().ingest;
+    if ingest.topic_mode == "keywords" {
+        let key = subject_keywords(&norm, ingest.topic_max_keywords);
+        if !key.is_empty() {
+            return vec![key];
+        }
+    }
     vec![norm]
 }
 
@@ -445,17 +1085,110 @@ fn build_embedding_text(subject: &str, body: &str) -> String {
     out
 }
 
-async fn openai_embedding(text: &str) -> Result<Vec<f32>> {
-    let api_key = env::var("OPENAI_API_KEY")?;
-    let model = env::var("OPENAI_EMBED_MODEL")
-        .ok()
-        .filter(|v| !v.trim().is_empty())
-        .unwrap_or_else(|| "text-embedding-3-small".to_string());
+fn embedding_model() -> String {
+    config().rag.embed_model.clone()
+}
+
+/// (model, `utils::content_hash` of the text).
+type EmbedKey = (String, String);
+
+/// Embeddings by model and `utils::content_hash` of the text, so unchanged content is embedded
+/// once per process. With `COS_EMBED_CACHE_DIR` they are also kept on disk across runs.
+static EMBED_CACHE: Lazy<std::sync::Mutex<HashMap<EmbedKey, Vec<f32>>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+static EMBED_CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static EMBED_CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// Embedding cache (hits, misses) since startup.
+pub fn embedding_cache_stats() -> (u64, u64) {
+    (
+        EMBED_CACHE_HITS.load(Ordering::Relaxed),
+        EMBED_CACHE_MISSES.load(Ordering::Relaxed),
+    )
+}
+
+/// `{COS_EMBED_CACHE_DIR}/{model}/{hash}.json`, or `None` when the variable is unset.
+fn embed_cache_path(model: &str, hash: &str) -> Option<std::path::PathBuf> {
+    let dir = config().rag.embed_cache_dir.clone()?;
+    Some(embed_cache_file(Path::new(&dir), model, hash))
+}
+
+/// `{dir}/{model}/{hash}.json`, with characters other than `[A-Za-z0-9.-]` in `model` as `_`.
+fn embed_cache_file(dir: &Path, model: &str, hash: &str) -> std::path::PathBuf {
+    let model: String = model
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect();
+    dir.join(model).join(format!("{hash}.json"))
+}
+
+async fn cached_embedding(key: &EmbedKey) -> Option<Vec<f32>> {
+    let hit = EMBED_CACHE.lock().unwrap_or_else(|e| e.into_inner()).get(key).cloned();
+    if hit.is_some() {
+        return hit;
+    }
+    let raw = tokio::fs::read(embed_cache_path(&key.0, &key.1)?).await.ok()?;
+    let emb: Vec<f32> = serde_json::from_slice(&raw).ok()?;
+    EMBED_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(key.clone(), emb.clone());
+    Some(emb)
+}
+
+async fn store_embedding(key: EmbedKey, emb: &[f32]) {
+    if let Some(path) = embed_cache_path(&key.0, &key.1) {
+        // A cache that can't be written only costs a recomputation later.
+        let written = async {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, serde_json::to_vec(emb)?).await?;
+            Ok::<_, anyhow::Error>(())
+        };
+        if let Err(e) = written.await {
+            eprintln!("embedding cache not written to {}: {e}", path.display());
+        }
+    }
+    EMBED_CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(key, emb.to_vec());
+}
+
+pub async fn openai_embedding(text: &str) -> Result<Vec<f32>> {
+    let model = embedding_model();
+    let key = (model.clone(), crate::utils::content_hash(text));
+    if let Some(emb) = cached_embedding(&key).await {
+        EMBED_CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+        return Ok(emb);
+    }
+    EMBED_CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+    let request = embedding_request(&model, text);
+    let emb = crate::utils::with_timeout("openai", crate::utils::openai_timeout(), request).await?;
+    store_embedding(key, &emb).await;
+    Ok(emb)
+}
+
+async fn embedding_request(model: &str, text: &str) -> Result<Vec<f32>> {
+    let config = config();
+    let api_key = config.llm.api_key.clone().context("OPENAI_API_KEY is not set")?;
 
     let client = reqwest::Client::new();
-    let resp = client
-        .post("https://api.openai.com/v1/embeddings")
-        .bearer_auth(api_key)
+    let request = match crate::llm::openai_endpoint() {
+        crate::llm::OpenAiEndpoint::OpenAi { base } => {
+            client.post(format!("{base}/embeddings")).bearer_auth(api_key)
+        }
+        crate::llm::OpenAiEndpoint::Azure { base, api_version } => {
+            let deployment =
+                crate::llm::azure_deployment(config.llm.azure_embed_deployment.as_deref(), model);
+            client
+                .post(format!("{base}/openai/deployments/{deployment}/embeddings"))
+                .query(&[("api-version", api_version)])
+                .header("api-key", api_key)
+        }
+    };
+    let resp = request
         .json(&serde_json::json!({
             "model": model,
             "input": text
@@ -482,7 +1215,19 @@ async fn openai_embedding(text: &str) -> Result<Vec<f32>> {
     Ok(out)
 }
 
-fn cosine_sim(a: &[f32], b: &[f32]) -> f32 {
+/// One redacted `subject: snippet` line describing a message to the cluster labeller.
+fn cluster_sample(subject: &str, body: &str) -> String {
+    let snippet: String = body
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(200)
+        .collect();
+    redact(&format!("{}: {}", subject.trim(), snippet))
+}
+
+pub fn cosine_sim(a: &[f32], b: &[f32]) -> f32 {
     let mut dot = 0f32;
     let mut na = 0f32;
     let mut nb = 0f32;
@@ -498,51 +1243,250 @@ fn cosine_sim(a: &[f32], b: &[f32]) -> f32 {
     dot / (na.sqrt() * nb.sqrt())
 }
 
-fn assign_to_clusters(
-    message_id: String,
-    topic_ids: &[String],
-    emb: Vec<f32>,
-    sim_threshold: f32,
-    centroids: &mut Vec<Vec<f32>>,
-    members: &mut Vec<Vec<String>>,
-    labels: &mut Vec<String>,
-) {
-    let mut best_idx: Option<usize> = None;
-    let mut best_sim = -1f32;
-    for (i, c) in centroids.iter().enumerate() {
-        let s = cosine_sim(c, &emb);
-        if s > best_sim {
-            best_sim = s;
-            best_idx = Some(i);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::EventType;
+
+    #[test]
+    fn reconnects_back_off_up_to_the_configured_maximum() {
+        let max = std::time::Duration::from_secs(5);
+        let mut delay = None;
+        let mut waits = Vec::new();
+        for _ in 0..5 {
+            let next = next_reconnect_delay(delay, max);
+            waits.push(next.as_secs());
+            delay = Some(next);
         }
+        assert_eq!(waits, [1, 2, 4, 5, 5]);
     }
 
-    let label = topic_ids
-        .first()
-        .cloned()
-        .unwrap_or_else(|| "cluster".to_string());
+    #[tokio::test]
+    async fn an_unreachable_neo4j_degrades_unless_required() {
+        // Only the Neo4j settings change, so other tests reading the config are unaffected.
+        let mut unreachable = (*config()).clone();
+        unreachable.neo4j.uri = "127.0.0.1:1".to_string();
+        unreachable.neo4j.required = true;
+        set_config(unreachable.clone());
+        let mut state = AppState::new();
+        let limit = std::time::Duration::from_secs(30);
+        let started = tokio::time::timeout(limit, state.init_neo4j_or_degrade()).await.unwrap();
+        assert!(started.is_err());
+        assert!(!neo4j_degraded());
+
+        unreachable.neo4j.required = false;
+        set_config(unreachable);
+        let started = tokio::time::timeout(limit, state.init_neo4j_or_degrade()).await.unwrap();
+        assert!(started.is_ok(), "{started:?}");
+        assert!(neo4j_degraded());
+        assert!(handles().neo4j.is_none());
+    }
+
+    #[test]
+    fn extract_emails_accepts_rfc5322_local_parts() {
+        assert_eq!(
+            extract_emails("cc: o'brien+hr@corp.example.com, a.b_c-d@x.io"),
+            vec!["a.b_c-d@x.io", "o'brien+hr@corp.example.com"]
+        );
+        assert_eq!(extract_emails("to {ops}|team=1@corp.com"), vec!["{ops}|team=1@corp.com"]);
+        assert_eq!(extract_emails("\"jane doe\"@corp.com"), vec!["\"jane doe\"@corp.com"]);
+    }
+
+    #[test]
+    fn extract_emails_trims_dots_and_skips_bare_hosts() {
+        assert_eq!(extract_emails("mail .john.smith@corp.com."), vec!["john.smith@corp.com"]);
+        assert!(extract_emails("ping @corp.com or admin@localhost").is_empty());
+        assert_eq!(extract_emails("A@Corp.com a@corp.com"), vec!["a@corp.com"]);
+    }
 
-    if best_idx.is_none() || best_sim < sim_threshold {
-        centroids.push(emb);
-        members.push(vec![message_id]);
-        labels.push(label);
-        return;
+    #[test]
+    fn csv_columns_follow_the_header_names() {
+        let headers = csv::StringRecord::from(vec!["Date", " message ", "folder", "FILE"]);
+        let columns = CsvColumns::from_headers(&headers);
+        assert_eq!((columns.file, columns.message), (3, 1));
+        assert_eq!((columns.date, columns.folder), (Some(0), Some(2)));
+
+        let record = csv::StringRecord::from(vec![" 2001-05-14 ", "body", "", "a/1."]);
+        assert_eq!(columns.get(&record, columns.date).as_deref(), Some("2001-05-14"));
+        assert_eq!(columns.get(&record, columns.folder), None);
+        assert_eq!(columns.get(&record, Some(9)), None);
     }
 
-    let idx = best_idx.unwrap();
-    let k = members.get(idx).map(|m| m.len()).unwrap_or(1) as f32;
-    if let Some(c) = centroids.get_mut(idx) {
-        let len = c.len().min(emb.len());
-        for i in 0..len {
-            c[i] = (c[i] * k + emb[i]) / (k + 1.0);
+    #[test]
+    fn csv_columns_are_positional_without_file_and_message_headers() {
+        let headers = csv::StringRecord::from(vec!["path", "content", "date"]);
+        let columns = CsvColumns::from_headers(&headers);
+        assert_eq!((columns.file, columns.message), (0, 1));
+        assert_eq!((columns.date, columns.folder), (None, None));
+    }
+
+    #[test]
+    fn reply_markers_are_stripped_from_subjects() {
+        assert_eq!(strip_reply_prefixes("RE: Fwd: Q3   budget"), "Q3 budget");
+        assert_eq!(strip_reply_prefixes("[eng-list] Re[2]: AW: Offsite"), "Offsite");
+        assert_eq!(strip_reply_prefixes("Agenda: Monday"), "Agenda: Monday");
+        assert_eq!(strip_reply_prefixes("  "), "");
+    }
+
+    #[test]
+    fn keyword_keys_ignore_order_stopwords_and_numbers() {
+        assert_eq!(subject_keywords("q3 budget review", 4), "budget q3 review");
+        assert_eq!(
+            subject_keywords("review: budget q3", 4),
+            subject_keywords("Q3 budget review", 4)
+        );
+        assert_eq!(subject_keywords("Update on the 2001 offsite plans", 4), "offsite on plans");
+        assert_eq!(subject_keywords("alpha beta gamma delta epsilon", 2), "alpha beta");
+    }
+
+    #[test]
+    fn subjects_normalize_to_one_topic() {
+        let topic = |s: &str| derive_topics(&Some(s.to_string()));
+        assert_eq!(topic("Re: Q3 Budget!"), vec!["q3 budget"]);
+        assert_eq!(topic("FW: re: q3 budget"), vec!["q3 budget"]);
+        assert_eq!(topic("Re: "), vec!["(no subject)"]);
+        assert_eq!(derive_topics(&None), vec!["(no subject)"]);
+    }
+
+    #[test]
+    fn name_email_pairs_keep_display_names() {
+        assert_eq!(
+            parse_many_recipients("\"Sarah Lee\" <Sarah@Corp.com>, bob@corp.com"),
+            vec![
+                ("sarah@corp.com".to_string(), Some("Sarah Lee".to_string())),
+                ("bob@corp.com".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn message_ids_are_unbracketed_and_deduplicated() {
+        assert_eq!(parse_message_ids("<a@x> <b@y>\n <a@x>"), vec!["a@x", "b@y"]);
+        assert_eq!(parse_message_ids("a@x, b@y not-an-id"), vec!["a@x", "b@y"]);
+        assert!(parse_message_ids("<>").is_empty());
+    }
+
+    #[test]
+    fn reply_headers_thread_the_message() {
+        let email = parse_email_blob(
+            "Message-ID: <3@corp>\nIn-Reply-To: <2@corp> <1@corp>\n\
+             References: <1@corp>\n\t<2@corp>\nSubject: Re: Q3 budget\n\nAgreed.",
+        );
+        assert_eq!(email.message_id.as_deref(), Some("3@corp"));
+        assert_eq!(email.in_reply_to.as_deref(), Some("2@corp"));
+        assert_eq!(email.references, vec!["1@corp", "2@corp"]);
+        assert_eq!(email.body, "Agreed.");
+    }
+
+    #[test]
+    fn search_keeps_the_best_chunk_per_document() {
+        let hit = |id: &str, parent: Option<&str>| {
+            let r = SearchResult::new(id, format!("text {id}"), 0.5, 0)
+                .with_metadata("source", "handbook.md".into());
+            match parent {
+                Some(p) => r.with_metadata("parent_id", p.into()),
+                None => r,
+            }
+        };
+        let results = vec![
+            hit("a1", Some("a")),
+            hit("a2", Some("a")),
+            hit("solo", None),
+            hit("b1", Some("b")),
+        ];
+        let contents = |snippets: Vec<RetrievedSnippet>| -> Vec<String> {
+            snippets.into_iter().map(|s| s.content).collect()
+        };
+        let all = top_snippets(results.clone(), 5, &[EmployeeRole::Engineer]);
+        assert_eq!(all[0].source.as_deref(), Some("handbook.md"));
+        assert_eq!(contents(all), vec!["text a1", "text solo", "text b1"]);
+        assert_eq!(contents(top_snippets(results, 2, &[])), vec!["text a1", "text solo"]);
+    }
+
+    #[test]
+    fn draining_drops_events_below_the_floor_and_keeps_the_rest_queued() {
+        let mut state = AppState::new();
+        for (topic, confidence) in [("a", 0.9), ("b", 0.2), ("c", 0.6), ("d", 0.8)] {
+            let agent = EmployeeAgentId("employee_john".to_string());
+            state.emit(Event::new(agent, EventType::Update, topic.to_string(), confidence, vec![]));
         }
+        let topics = |events: Vec<Event>| -> Vec<String> {
+            events.into_iter().map(|e| e.topic).collect()
+        };
+        assert_eq!(topics(state.take_events(2, 0.5)), vec!["a", "c"]);
+        assert_eq!(state.queued_events(), 1);
+        assert_eq!(topics(state.take_events(5, 0.5)), vec!["d"]);
+        assert_eq!(state.queued_events(), 0);
     }
-    if let Some(m) = members.get_mut(idx) {
-        m.push(message_id);
+
+    #[test]
+    fn cluster_samples_are_single_line_bounded_and_redacted() {
+        let body = format!("Call me on\n\n  SSN 123-45-6789 {}", "word ".repeat(100));
+        let sample = cluster_sample("  Payroll  ", &body);
+        assert!(sample.starts_with("Payroll: Call me on SSN "), "{sample}");
+        assert!(!sample.contains("123-45-6789") && !sample.contains('\n'));
+        assert!(sample.chars().count() <= "Payroll: ".len() + 200);
     }
-    if labels.get(idx).map(|l| l.trim().is_empty()).unwrap_or(false) {
-        if let Some(l) = labels.get_mut(idx) {
-            *l = label;
+
+    #[test]
+    fn org_truth_keeps_only_the_newest_entries_per_key() {
+        let mut state = AppState::new();
+        let cap = truth_history_cap();
+        for i in 0..cap + 5 {
+            state.update_org_truth("policy", format!("v{i}"));
         }
+        let history = &state.org_truth["policy"];
+        assert_eq!(history.len(), cap);
+        assert_eq!(history[0], "v5");
+        assert_eq!(state.latest_truth("policy"), Some(format!("v{}", cap + 4).as_str()));
+    }
+
+    #[test]
+    fn private_keys_stay_unique_across_threads() {
+        let keys: Vec<PrivateStoreKey> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..8)
+                .map(|i| {
+                    scope.spawn(move || {
+                        let agent = EmployeeAgentId(format!("employee_{}", i % 2));
+                        (0..250).map(|_| Sessions::next_private_key(&agent)).collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers.into_iter().flat_map(|w| w.join().unwrap()).collect()
+        });
+        let seqs: HashSet<u64> =
+            keys.iter().map(|k| k.0.rsplit(':').next().unwrap().parse().unwrap()).collect();
+        assert_eq!(seqs.len(), 2_000);
+    }
+
+    #[test]
+    fn search_drops_documents_the_roles_may_not_see() {
+        let results = vec![
+            SearchResult::new("pay", "salaries", 0.9, 0).with_metadata("visibility", "ceo".into()),
+            SearchResult::new("faq", "office hours", 0.5, 1)
+                .with_metadata("visibility", "all".into()),
+        ];
+        let seen = |roles: &[EmployeeRole]| -> Vec<String> {
+            top_snippets(results.clone(), 5, roles).into_iter().map(|s| s.content).collect()
+        };
+        assert_eq!(seen(&[EmployeeRole::Ceo]), vec!["salaries", "office hours"]);
+        assert_eq!(seen(&[EmployeeRole::Engineer]), vec!["office hours"]);
+        assert_eq!(seen(&[EmployeeRole::Ceo, EmployeeRole::Hr]), vec!["office hours"]);
+    }
+
+    #[test]
+    fn embedding_cache_files_are_grouped_by_a_path_safe_model_name() {
+        let file = embed_cache_file(Path::new("/cache"), "azure/text-embedding-3.small", "ab12");
+        assert_eq!(file, Path::new("/cache/azure_text-embedding-3.small/ab12.json"));
+    }
+
+    #[tokio::test]
+    async fn stored_embeddings_are_served_from_memory() {
+        let key = ("test-model".to_string(), crate::utils::content_hash("cached text"));
+        assert_eq!(cached_embedding(&key).await, None);
+        store_embedding(key.clone(), &[0.5, 0.25]).await;
+        assert_eq!(cached_embedding(&key).await, Some(vec![0.5, 0.25]));
+        let other_model = ("other-model".to_string(), key.1.clone());
+        assert_eq!(cached_embedding(&other_model).await, None);
     }
 }
@@ -0,0 +1,328 @@
+use anyhow::{Context as _, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use neo4rs::query;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::app_state::handles;
+
+/// Route prefixes that touch organizational data and therefore get audited.
+const AUDITED_PREFIXES: [&str; 9] = [
+    "/v1/ask",
+    "/v1/knowledge",
+    "/v1/traces",
+    "/v1/agents",
+    "/v1/graph",
+    "/v1/decisions",
+    "/v1/truth",
+    "/v1/messages",
+    "/v1/admin/audit",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditEvent {
+    pub audit_id: String,
+    /// RFC 3339 UTC with fixed precision, so string order is chronological order.
+    pub timestamp: String,
+    pub employee_id: Option<String>,
+    /// Route template, e.g. `/v1/agents/:agent_id/traces`.
+    pub route: String,
+    /// Concrete request path.
+    pub path: String,
+    pub method: String,
+    pub resource_ids: Vec<String>,
+    pub status: u16,
+}
+
+/// Identity and resource ids a handler attaches to its response for the audit middleware.
+#[derive(Debug, Clone, Default)]
+pub struct AuditInfo {
+    pub employee_id: Option<String>,
+    pub resource_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub employee_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub route: Option<String>,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+static AUDIT_TX: OnceCell<mpsc::UnboundedSender<AuditEvent>> = OnceCell::new();
+
+/// Events kept in process when running without Neo4j (`COS_STORAGE=memory`).
+static MEMORY_LOG: Lazy<Mutex<Vec<AuditEvent>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+static WRITE_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+/// Number of audit batches that could not be written.
+pub fn write_failures() -> u64 {
+    WRITE_FAILURES.load(Ordering::Relaxed)
+}
+
+pub fn is_audited_route(route: &str) -> bool {
+    AUDITED_PREFIXES.iter().any(|p| route.starts_with(p))
+}
+
+impl AuditEvent {
+    pub fn new(
+        employee_id: Option<String>,
+        route: String,
+        path: String,
+        method: String,
+        resource_ids: Vec<String>,
+        status: u16,
+    ) -> Self {
+        Self {
+            audit_id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true),
+            employee_id,
+            route,
+            path,
+            method,
+            resource_ids,
+            status,
+        }
+    }
+}
+
+/// Queues an event for the flusher. Never blocks and never fails the caller.
+pub fn record(event: AuditEvent) {
+    match AUDIT_TX.get() {
+        Some(tx) => {
+            if tx.send(event).is_err() {
+                WRITE_FAILURES.fetch_add(1, Ordering::Relaxed);
+                eprintln!("audit: flusher stopped, event dropped");
+            }
+        }
+        None => {
+            WRITE_FAILURES.fetch_add(1, Ordering::Relaxed);
+            eprintln!("audit: flusher not started, event dropped");
+        }
+    }
+}
+
+/// Starts the background task that writes queued events in batches.
+///
+/// - `COS_AUDIT_BATCH`: max events per write (default 100).
+/// - `COS_AUDIT_FLUSH_MS`: how often a partial batch is flushed (default 2000).
+pub fn spawn_flusher() {
+    let (tx, mut rx) = mpsc::unbounded_channel::<AuditEvent>();
+    if AUDIT_TX.set(tx).is_err() {
+        return;
+    }
+
+    let audit = crate::app_state::config().audit.clone();
+    let batch_size = audit.batch.max(1);
+    let flush_ms = audit.flush_ms.max(1);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_millis(flush_ms));
+        let mut batch: Vec<AuditEvent> = Vec::new();
+        loop {
+            tokio::select! {
+                ev = rx.recv() => match ev {
+                    Some(ev) => {
+                        batch.push(ev);
+                        if batch.len() < batch_size {
+                            continue;
+                        }
+                    }
+                    None => {
+                        flush(std::mem::take(&mut batch)).await;
+                        break;
+                    }
+                },
+                _ = ticker.tick() => {}
+            }
+            if !batch.is_empty() {
+                flush(std::mem::take(&mut batch)).await;
+            }
+        }
+    });
+}
+
+async fn flush(batch: Vec<AuditEvent>) {
+    if batch.is_empty() {
+        return;
+    }
+    let n = batch.len();
+    if let Err(e) = write_batch(batch).await {
+        WRITE_FAILURES.fetch_add(1, Ordering::Relaxed);
+        eprintln!("audit: failed to write {n} events: {e:#}");
+    }
+}
+
+async fn write_batch(batch: Vec<AuditEvent>) -> Result<()> {
+    let neo4j = {
+        handles().neo4j
+    };
+
+    let Some(client) = neo4j else {
+        let max = crate::app_state::config().audit.memory_max;
+        let mut log = MEMORY_LOG.lock().await;
+        log.extend(batch);
+        if log.len() > max {
+            let excess = log.len() - max;
+            log.drain(..excess);
+        }
+        return Ok(());
+    };
+
+    let events: Vec<neo4rs::BoltType> = batch
+        .into_iter()
+        .map(|e| crate::api::json_to_bolt(serde_json::to_value(e).unwrap_or_default()))
+        .collect();
+    let q = query(
+        r#"
+UNWIND $events AS e
+MERGE (a:AuditEvent {audit_id: e.audit_id})
+SET a.timestamp = e.timestamp,
+    a.employee_id = e.employee_id,
+    a.route = e.route,
+    a.path = e.path,
+    a.method = e.method,
+    a.resource_ids = e.resource_ids,
+    a.status = e.status
+"#,
+    )
+    .param("events", events);
+
+    let write = client.graph().run(q);
+    crate::utils::with_timeout("neo4j", crate::utils::neo4j_timeout(), write)
+        .await
+        .context("write audit events")?;
+    Ok(())
+}
+
+/// Newest-first audit events matching `filter`.
+pub async fn list(filter: &AuditFilter) -> Result<Vec<AuditEvent>> {
+    let since = filter
+        .since
+        .map(|s| s.to_rfc3339_opts(SecondsFormat::Micros, true));
+
+    let neo4j = {
+        handles().neo4j
+    };
+
+    let Some(client) = neo4j else {
+        let log = MEMORY_LOG.lock().await;
+        return Ok(log
+            .iter()
+            .rev()
+            .filter(|e| {
+                filter
+                    .employee_id
+                    .as_ref()
+                    .map(|id| e.employee_id.as_ref() == Some(id))
+                    .unwrap_or(true)
+            })
+            .filter(|e| filter.route.as_ref().map(|r| e.route == *r).unwrap_or(true))
+            .filter(|e| since.as_ref().map(|s| e.timestamp >= *s).unwrap_or(true))
+            .skip(filter.offset)
+            .take(filter.limit)
+            .cloned()
+            .collect());
+    };
+
+    let q = query(
+        r#"
+MATCH (a:AuditEvent)
+WHERE ($employee_id IS NULL OR a.employee_id = $employee_id)
+  AND ($route IS NULL OR a.route = $route)
+  AND ($since IS NULL OR a.timestamp >= $since)
+RETURN a.audit_id AS audit_id,
+       a.timestamp AS timestamp,
+       a.employee_id AS employee_id,
+       a.route AS route,
+       a.path AS path,
+       a.method AS method,
+       coalesce(a.resource_ids, []) AS resource_ids,
+       a.status AS status
+ORDER BY a.timestamp DESC
+SKIP $offset
+LIMIT $limit
+"#,
+    )
+    .param("employee_id", filter.employee_id.clone())
+    .param("route", filter.route.clone())
+    .param("since", since)
+    .param("offset", filter.offset as i64)
+    .param("limit", filter.limit as i64);
+
+    let execute = client.graph().execute(q);
+    let mut stream = crate::utils::with_timeout("neo4j", crate::utils::neo4j_timeout(), execute)
+        .await
+        .context("list audit events")?;
+    let mut out = Vec::new();
+    while let Some(row) = stream.next().await.context("read audit events")? {
+        let status: i64 = row.get("status").unwrap_or_default();
+        out.push(AuditEvent {
+            audit_id: row.get("audit_id").unwrap_or_default(),
+            timestamp: row.get("timestamp").unwrap_or_default(),
+            employee_id: row.get("employee_id").ok(),
+            route: row.get("route").unwrap_or_default(),
+            path: row.get("path").unwrap_or_default(),
+            method: row.get("method").unwrap_or_default(),
+            resource_ids: row.get("resource_ids").unwrap_or_default(),
+            status: status as u16,
+        });
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(employee_id: &str, route: &str) -> AuditEvent {
+        let path = route.replace(":agent_id", employee_id);
+        AuditEvent::new(Some(employee_id.into()), route.into(), path, "GET".into(), vec![], 200)
+    }
+
+    #[test]
+    fn only_data_routes_are_audited() {
+        assert!(is_audited_route("/v1/agents/:agent_id/traces"));
+        assert!(is_audited_route("/v1/admin/audit"));
+        assert!(!is_audited_route("/health"));
+        assert!(!is_audited_route("/v1/admin/backfill-roles"));
+    }
+
+    #[tokio::test]
+    async fn memory_log_lists_newest_first_with_filters() {
+        // Unique ids: the memory log is shared by every test in the process.
+        let (a, b) = ("employee_audit_a", "employee_audit_b");
+        write_batch(vec![
+            event(a, "/v1/traces"),
+            event(b, "/v1/traces"),
+            event(a, "/v1/agents/:agent_id/traces"),
+        ])
+        .await
+        .unwrap();
+
+        let filter = |employee_id: &str, route: Option<&str>| AuditFilter {
+            employee_id: Some(employee_id.to_string()),
+            route: route.map(str::to_string),
+            limit: 10,
+            ..AuditFilter::default()
+        };
+        let mine = list(&filter(a, None)).await.unwrap();
+        let routes: Vec<&str> = mine.iter().map(|e| e.route.as_str()).collect();
+        assert_eq!(routes, ["/v1/agents/:agent_id/traces", "/v1/traces"]);
+        assert_eq!(mine[0].path, "/v1/agents/employee_audit_a/traces");
+
+        assert_eq!(list(&filter(a, Some("/v1/traces"))).await.unwrap().len(), 1);
+        let paged = AuditFilter { offset: 1, ..filter(a, None) };
+        assert_eq!(list(&paged).await.unwrap()[0].route, "/v1/traces");
+        let since = Some(Utc::now() + chrono::Duration::hours(1));
+        let future = AuditFilter { since, ..filter(b, None) };
+        assert!(list(&future).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,1156 @@
+use anyhow::{bail, Context as _, Result};
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::env;
+use std::path::Path;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Process-wide settings, read once at startup by `CosConfig::load` and shared through
+/// `app_state::config()`. Each field names its environment variable; the same value may be
+/// given in `cos.toml` under the section and field name (e.g. `[neo4j] fetch_size = 500`), and
+/// the environment wins. Secrets serialize masked, so the struct can be shown as is.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CosConfig {
+    pub http: HttpConfig,
+    pub neo4j: Neo4jConfig,
+    pub llm: LlmConfig,
+    pub speech: SpeechConfig,
+    pub rag: RagConfig,
+    pub cors: CorsConfig,
+    pub limits: LimitsConfig,
+    pub storage: StorageConfig,
+    pub orgbrain: OrgBrainConfig,
+    pub ingest: IngestConfig,
+    pub employees: EmployeesConfig,
+    pub visibility: VisibilityConfig,
+    pub privacy: PrivacyConfig,
+    pub maintenance: MaintenanceConfig,
+    pub audit: AuditConfig,
+    pub webhooks: WebhooksConfig,
+    pub script: ScriptConfig,
+    /// The `cos.toml` that was read, if any.
+    pub file: Option<String>,
+    /// Settings that are valid but leave a feature unavailable (e.g. no `ELEVEN_API_KEY`).
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct HttpConfig {
+    /// `COS_HTTP` (default true): serve the API when no subcommand is given.
+    pub enabled: bool,
+    /// `COS_HTTP_ADDR` (default `0.0.0.0:3000`).
+    pub addr: String,
+    /// `COS_API_KEY`: required as `x-api-key` on every request when set.
+    #[serde(serialize_with = "masked")]
+    pub api_key: Option<String>,
+    /// `COS_EMPLOYEE_TOKENS`: `token:Employee Name` pairs, comma-separated.
+    #[serde(serialize_with = "masked")]
+    pub employee_tokens: Option<String>,
+    /// `COS_ALLOWED_IMPERSONATION`: names that may be asserted without a token; unset allows
+    /// anyone, empty nobody.
+    pub allowed_impersonation: Option<String>,
+    /// `COS_SSE_CHANNEL_CAPACITY` (default 256, at least 1).
+    pub sse_channel_capacity: usize,
+    /// `COS_SSE_REQUIRE_IDENTITY` (default false).
+    pub sse_require_identity: bool,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Neo4jConfig {
+    /// `NEO4J_URI` (default `127.0.0.1:7687`).
+    pub uri: String,
+    /// `NEO4J_USER` (default `neo4j`).
+    pub user: String,
+    /// `NEO4J_PASSWORD` (default `neo4j`).
+    #[serde(serialize_with = "masked_str")]
+    pub password: String,
+    /// `NEO4J_FETCH_SIZE` (default 200).
+    pub fetch_size: usize,
+    /// `NEO4J_TIMEOUT_MS` (default 10000), per graph call.
+    pub timeout_ms: u64,
+    /// `COS_NEO4J_BREAKER_THRESHOLD` (default 5): consecutive failures that open the breaker.
+    pub breaker_threshold: u32,
+    /// `COS_NEO4J_BREAKER_COOLDOWN_SECS` (default 30).
+    pub breaker_cooldown_secs: u64,
+    /// `COS_CYPHER_MAX_ROWS` (default 1000), for `POST /v1/cypher`.
+    pub cypher_max_rows: usize,
+    /// `COS_CYPHER_TIMEOUT_SECS` (default 10), for `POST /v1/cypher`.
+    pub cypher_timeout_secs: u64,
+    /// `COS_REQUIRE_NEO4J` (default off): fail startup when Neo4j is unreachable instead of
+    /// running degraded while reconnecting.
+    pub required: bool,
+    /// `COS_NEO4J_RECONNECT_MAX_SECS` (default 60): longest wait between reconnect attempts.
+    pub reconnect_max_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LlmConfig {
+    /// `OPENAI_API_KEY`.
+    #[serde(serialize_with = "masked")]
+    pub api_key: Option<String>,
+    /// `OPENAI_MODEL` (default `gpt-4o-mini`).
+    pub model: String,
+    /// `COS_MODEL_FALLBACKS`: models tried in order when the primary fails, comma-separated.
+    pub fallbacks: Vec<String>,
+    /// `OPENAI_BASE_URL`, without a trailing slash.
+    pub base_url: Option<String>,
+    /// `OPENAI_API_TYPE`: `openai` (default) or `azure`.
+    pub api_type: String,
+    /// `AZURE_OPENAI_API_VERSION` (default `2024-06-01`).
+    pub azure_api_version: String,
+    /// `AZURE_OPENAI_DEPLOYMENT` (default: the model name).
+    pub azure_deployment: Option<String>,
+    /// `AZURE_OPENAI_EMBED_DEPLOYMENT` (default: the embedding model name).
+    pub azure_embed_deployment: Option<String>,
+    /// `COS_OFFLINE` (default false): stub chat and no embeddings.
+    pub offline: bool,
+    /// `OPENAI_TIMEOUT_MS` (default 60000), for chat completions, embeddings and RAG search.
+    pub timeout_ms: u64,
+    /// `COS_LLM_SCHEMA`: `retry` (default) re-asks once on output that doesn't match its
+    /// schema, `warn` only logs, `off` doesn't validate.
+    pub schema: String,
+    /// `COS_LLM_LOG_DIR`: every chat call's prompts and completion are written there when set.
+    pub log_dir: Option<String>,
+    /// `COS_LLM_LOG_REDACT_PRIVATE` (default false): mask `private_note` values in the log.
+    pub log_redact_private: bool,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SpeechConfig {
+    /// `ELEVEN_API_KEY`, for speech-to-text and text-to-speech.
+    #[serde(serialize_with = "masked")]
+    pub api_key: Option<String>,
+    /// `ELEVEN_VOICE_ID` (default `21m00Tcm4TlvDq8ikWAM`).
+    pub voice_id: String,
+    /// `ELEVEN_TTS_MODEL` (default `eleven_multilingual_v2`).
+    pub tts_model: String,
+    /// `ELEVEN_VOICE_ID_{LANG}`, by upper-case language code (e.g. `FRA`).
+    pub voice_id_by_language: BTreeMap<String, String>,
+    /// `ELEVEN_TTS_MODEL_{LANG}`, by upper-case language code.
+    pub tts_model_by_language: BTreeMap<String, String>,
+    /// `ELEVEN_TIMEOUT_MS` (default 30000).
+    pub timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RagConfig {
+    /// `OPENAI_EMBED_MODEL` (default `text-embedding-3-small`).
+    pub embed_model: String,
+    /// `COS_EMBED_CACHE_DIR`: embeddings cached on disk when set.
+    pub embed_cache_dir: Option<String>,
+    /// `RAG_MAX_DOCS` (default 1000): CSV rows ingested at startup.
+    pub max_docs: usize,
+    /// `RAG_CHUNK_CHARS` (default 2000): longer documents are split; 0 disables chunking.
+    pub chunk_chars: usize,
+    /// `RAG_CHUNK_OVERLAP` (default 200): characters shared by consecutive chunks.
+    pub chunk_overlap: usize,
+    /// `COS_RAG_SOURCE_ROLES`: `source=role|role` entries, comma-separated.
+    pub source_roles: String,
+    /// `COS_RAG_TOP_K` (default 3): snippets sent to the OrgBrain.
+    pub top_k: usize,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CorsConfig {
+    /// `COS_CORS_ORIGINS`, comma-separated; unset allows any origin without credentials.
+    pub origins: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LimitsConfig {
+    /// `COS_ASK_MAX_CHARS` (default 20000): longer asks are rejected.
+    pub ask_max_chars: usize,
+    /// `COS_ASK_SOFT_CHARS` (default 4000): longer asks are summarized first.
+    pub ask_soft_chars: usize,
+    /// `COS_ASK_CONTEXT_MAX_DOCS` (default 5): context documents plus truth ids per ask.
+    pub ask_context_max_docs: usize,
+    /// `COS_ASK_CONTEXT_MAX_CHARS` (default 8000): characters per context document.
+    pub ask_context_max_chars: usize,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StorageConfig {
+    /// `COS_STORAGE`: `neo4j` (default) or `memory`.
+    pub backend: String,
+    /// `COS_STRICT_PERSISTENCE` (default false): fail an ask whose decision isn't written.
+    pub strict_persistence: bool,
+    /// `COS_COMPACT_KEEP_LATEST` (default 5, at least 1): versions kept per object by compaction.
+    pub compact_keep_latest: usize,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OrgBrainConfig {
+    /// `COS_FLOW_FILE` (default `flows.toml`), for `chat`.
+    pub flow_file: String,
+    /// `COS_MAX_EVENTS_PER_BRAIN` (default 50, at least 1): events per OrgBrain call.
+    pub max_events: usize,
+    /// `COS_MIN_EVENT_CONFIDENCE` (default 0): less confident events are dropped.
+    pub min_event_confidence: f32,
+    /// `COS_TRUTH_HISTORY` (default 20, at least 1): entries kept per in-memory truth key.
+    pub truth_history: usize,
+    /// `COS_TRUTH_TOP_K` (default 8): truth entries in the prompt.
+    pub truth_top_k: usize,
+    /// `COS_TRUTH_PROMPT_CHARS` (default 8000): character budget for that truth.
+    pub truth_prompt_chars: usize,
+    /// `COS_GRAPH_CONTEXT_TRUTHS` (default 5): related truth from the graph; 0 leaves it out.
+    pub graph_context_truths: usize,
+    /// `COS_GRAPH_CONTEXT_DECISIONS` (default 5): related decisions; 0 leaves them out.
+    pub graph_context_decisions: usize,
+    /// `COS_REVIEWER` (default true): review each decision before it is stored.
+    pub reviewer: bool,
+    /// `COS_FANOUT` (default false): ask a role brain per routed role after a decision.
+    pub fanout: bool,
+    /// `COS_RETRY_MAX_ATTEMPTS` (default 3): retries of a step failing transiently.
+    pub retry_max_attempts: u64,
+    /// `COS_RETRY_BASE_MS` (default 500): first backoff, doubled per further attempt.
+    pub retry_base_ms: u64,
+    /// `COS_LOW_CONFIDENCE_THRESHOLD` (default 0.4): less confident traces raise an alert.
+    pub low_confidence_threshold: f32,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct IngestConfig {
+    /// `ORG_EMAIL_CLUSTER_SIM` (default 0.85): similarity needed to join a cluster.
+    pub cluster_sim: f32,
+    /// `ORG_MIN_CLUSTER_SIZE` (default 2, at least 1): smaller clusters aren't listed.
+    pub min_cluster_size: usize,
+    /// `ORG_UNCLUSTERED_GROUP` (default false): list the remaining messages as one group.
+    pub unclustered_group: bool,
+    /// `COS_CLUSTER_LABEL_MIN_MEMBERS` (default 3): smaller clusters keep a heuristic name.
+    pub cluster_label_min_members: usize,
+    /// `COS_CLUSTER_LABEL_SAMPLES` (default 10, at least 1): member lines sent to the labeller.
+    pub cluster_label_samples: usize,
+    /// `ORG_TOPIC_MODE`: `subject` (default) or `keywords`.
+    pub topic_mode: String,
+    /// `ORG_TOPIC_MAX_KEYWORDS` (default 4), in `keywords` mode.
+    pub topic_max_keywords: usize,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EmployeesConfig {
+    /// `COS_EMPLOYEES_FILE` (default `employees.toml`; a missing file keeps the built-in seeds).
+    pub file: String,
+    /// `COS_AUTO_ALIAS` (default false): link email senders to seeded employees by name.
+    pub auto_alias: bool,
+    /// `COS_ROLE_RULES`: `pattern->role` entries, comma-separated.
+    pub role_rules: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct VisibilityConfig {
+    /// `COS_VISIBILITY_RULES`: inline JSON, or a `.json` / `.toml` file; unset keeps the
+    /// built-in rules.
+    pub rules: Option<String>,
+    /// `COS_VISIBILITY_DEFAULT`: `allow` (default) or `deny`.
+    pub default: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PrivacyConfig {
+    /// `COS_PRIVATE_KEY`: base64 AES-256 key; private notes aren't persisted without one.
+    #[serde(serialize_with = "masked")]
+    pub private_key: Option<String>,
+    /// `COS_PRIVATE_KEY_ID` (default `k1`).
+    pub private_key_id: String,
+    /// `COS_PRIVATE_KEYS_RETIRED`: `id:base64key` pairs, comma-separated.
+    #[serde(serialize_with = "masked")]
+    pub private_keys_retired: Option<String>,
+    /// `COS_REDACTION`: `off`, `standard` (default) or `strict`.
+    pub redaction: String,
+    /// `COS_REDACTION_ACTION`: `mask` (default) or `drop`.
+    pub redaction_action: String,
+    /// `COS_CORPORATE_DOMAINS`, comma-separated.
+    pub corporate_domains: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MaintenanceConfig {
+    /// `COS_MAINTENANCE_INTERVAL_SECS` (default 3600; 0 disables the scheduler).
+    pub interval_secs: u64,
+    /// `COS_COMPACT_INTERVAL_SECS` (default 0, disabled): how often compaction runs; it deletes
+    /// versions, so it is opt-in.
+    pub compact_interval_secs: u64,
+    /// `COS_STALE_CHECK_INTERVAL_SECS` (default 86400; 0 disables the staleness check).
+    pub stale_check_interval_secs: u64,
+    /// `COS_STALE_AGE_DAYS` (default 90): age at which a decision counts fully.
+    pub stale_age_days: f64,
+    /// `COS_STALE_TRUTH_WEIGHT` (default 5): emails one newer truth version counts as.
+    pub stale_truth_weight: f64,
+    /// `COS_STALE_THRESHOLD` (default 10): score at which a decision is stale.
+    pub stale_threshold: f64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AuditConfig {
+    /// `COS_AUDIT_BATCH` (default 100, at least 1): events per write.
+    pub batch: usize,
+    /// `COS_AUDIT_FLUSH_MS` (default 2000): how often a partial batch is written.
+    pub flush_ms: u64,
+    /// `COS_AUDIT_MEMORY_MAX` (default 10000): events kept without Neo4j.
+    pub memory_max: usize,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WebhooksConfig {
+    /// `COS_WEBHOOK_URLS`, comma-separated; empty disables webhooks.
+    pub urls: Vec<String>,
+    /// `COS_WEBHOOK_SECRET`: HMAC key, required with `urls`.
+    #[serde(serialize_with = "masked")]
+    pub secret: Option<String>,
+    /// `COS_WEBHOOK_RECIPIENT`: whose visibility traces are cut to, required with `urls`.
+    pub recipient: Option<String>,
+    /// `COS_WEBHOOK_QUEUE` (default 256, at least 1): events buffered per endpoint.
+    pub queue: usize,
+    /// `COS_WEBHOOK_MAX_ATTEMPTS` (default 5, at least 1).
+    pub max_attempts: u64,
+    /// `COS_WEBHOOK_BACKOFF_MS` (default 1000), doubled per retry up to 60 s.
+    pub backoff_ms: u64,
+    /// `COS_WEBHOOK_TIMEOUT_MS` (default 10000).
+    pub timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ScriptConfig {
+    /// `COS_SCRIPT`: run this script when no subcommand is given.
+    pub path: Option<String>,
+    /// `COS_SCRIPT_OUT` (default `<script>.out.jsonl`).
+    pub out: Option<String>,
+    /// `COS_SCRIPT_DELAY_MS` (default 0): pause between lines.
+    pub delay_ms: u64,
+}
+
+impl Default for CosConfig {
+    fn default() -> Self {
+        Self {
+            http: HttpConfig {
+                enabled: true,
+                addr: "0.0.0.0:3000".to_string(),
+                api_key: None,
+                employee_tokens: None,
+                allowed_impersonation: None,
+                sse_channel_capacity: 256,
+                sse_require_identity: false,
+            },
+            neo4j: Neo4jConfig {
+                uri: "127.0.0.1:7687".to_string(),
+                user: "neo4j".to_string(),
+                password: "neo4j".to_string(),
+                fetch_size: 200,
+                timeout_ms: 10_000,
+                breaker_threshold: 5,
+                breaker_cooldown_secs: 30,
+                cypher_max_rows: 1000,
+                cypher_timeout_secs: 10,
+                required: false,
+                reconnect_max_secs: 60,
+            },
+            llm: LlmConfig {
+                api_key: None,
+                model: "gpt-4o-mini".to_string(),
+                fallbacks: Vec::new(),
+                base_url: None,
+                api_type: "openai".to_string(),
+                azure_api_version: "2024-06-01".to_string(),
+                azure_deployment: None,
+                azure_embed_deployment: None,
+                offline: false,
+                timeout_ms: 60_000,
+                schema: "retry".to_string(),
+                log_dir: None,
+                log_redact_private: false,
+            },
+            speech: SpeechConfig {
+                api_key: None,
+                voice_id: "21m00Tcm4TlvDq8ikWAM".to_string(),
+                tts_model: "eleven_multilingual_v2".to_string(),
+                voice_id_by_language: BTreeMap::new(),
+                tts_model_by_language: BTreeMap::new(),
+                timeout_ms: 30_000,
+            },
+            rag: RagConfig {
+                embed_model: "text-embedding-3-small".to_string(),
+                embed_cache_dir: None,
+                max_docs: 1000,
+                chunk_chars: 2000,
+                chunk_overlap: 200,
+                source_roles: String::new(),
+                top_k: 3,
+            },
+            cors: CorsConfig { origins: None },
+            limits: LimitsConfig {
+                ask_max_chars: 20_000,
+                ask_soft_chars: 4_000,
+                ask_context_max_docs: 5,
+                ask_context_max_chars: 8_000,
+            },
+            storage: StorageConfig {
+                backend: "neo4j".to_string(),
+                strict_persistence: false,
+                compact_keep_latest: 5,
+            },
+            orgbrain: OrgBrainConfig {
+                flow_file: "flows.toml".to_string(),
+                max_events: 50,
+                min_event_confidence: 0.0,
+                truth_history: 20,
+                truth_top_k: 8,
+                truth_prompt_chars: 8_000,
+                graph_context_truths: 5,
+                graph_context_decisions: 5,
+                reviewer: true,
+                fanout: false,
+                retry_max_attempts: 3,
+                retry_base_ms: 500,
+                low_confidence_threshold: 0.4,
+            },
+            ingest: IngestConfig {
+                cluster_sim: 0.85,
+                min_cluster_size: 2,
+                unclustered_group: false,
+                cluster_label_min_members: 3,
+                cluster_label_samples: 10,
+                topic_mode: "subject".to_string(),
+                topic_max_keywords: 4,
+            },
+            employees: EmployeesConfig {
+                file: "employees.toml".to_string(),
+                auto_alias: false,
+                role_rules: String::new(),
+            },
+            visibility: VisibilityConfig {
+                rules: None,
+                default: "allow".to_string(),
+            },
+            privacy: PrivacyConfig {
+                private_key: None,
+                private_key_id: "k1".to_string(),
+                private_keys_retired: None,
+                redaction: "standard".to_string(),
+                redaction_action: "mask".to_string(),
+                corporate_domains: Vec::new(),
+            },
+            maintenance: MaintenanceConfig {
+                interval_secs: 3600,
+                compact_interval_secs: 0,
+                stale_check_interval_secs: 86_400,
+                stale_age_days: 90.0,
+                stale_truth_weight: 5.0,
+                stale_threshold: 10.0,
+            },
+            audit: AuditConfig {
+                batch: 100,
+                flush_ms: 2000,
+                memory_max: 10_000,
+            },
+            webhooks: WebhooksConfig {
+                urls: Vec::new(),
+                secret: None,
+                recipient: None,
+                queue: 256,
+                max_attempts: 5,
+                backoff_ms: 1000,
+                timeout_ms: 10_000,
+            },
+            script: ScriptConfig {
+                path: None,
+                out: None,
+                delay_ms: 0,
+            },
+            file: None,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl CosConfig {
+    /// Reads `COS_CONFIG_FILE` (default `cos.toml`; a missing file is fine) and the
+    /// environment. Fails with every invalid setting listed, not just the first.
+    pub fn load() -> Result<Self> {
+        let path = env::var("COS_CONFIG_FILE").unwrap_or_else(|_| "cos.toml".to_string());
+        let path = Path::new(&path);
+        let file = if path.exists() {
+            let raw = std::fs::read_to_string(path)
+                .with_context(|| format!("read {}", path.display()))?;
+            let table: toml::Table =
+                toml::from_str(&raw).with_context(|| path.display().to_string())?;
+            Some((path.display().to_string(), table))
+        } else {
+            None
+        };
+        Self::from_sources(file)
+    }
+
+    fn from_sources(file: Option<(String, toml::Table)>) -> Result<Self> {
+        let d = Self::default();
+        let (file_name, table) = match file {
+            Some((name, table)) => (Some(name), table),
+            None => (None, toml::Table::new()),
+        };
+        let mut l = Loader { table, errors: Vec::new() };
+
+        let http = HttpConfig {
+            enabled: l.flag("COS_HTTP", "http.enabled", d.http.enabled),
+            addr: l.string("COS_HTTP_ADDR", "http.addr", &d.http.addr),
+            api_key: l.opt_string("COS_API_KEY", "http.api_key"),
+            employee_tokens: l.opt_string("COS_EMPLOYEE_TOKENS", "http.employee_tokens"),
+            // Set but empty means nobody, unlike unset.
+            allowed_impersonation: l
+                .raw_or_empty("COS_ALLOWED_IMPERSONATION", "http.allowed_impersonation"),
+            sse_channel_capacity: l.parse(
+                "COS_SSE_CHANNEL_CAPACITY",
+                "http.sse_channel_capacity",
+                d.http.sse_channel_capacity,
+            ),
+            sse_require_identity: l.flag(
+                "COS_SSE_REQUIRE_IDENTITY",
+                "http.sse_require_identity",
+                d.http.sse_require_identity,
+            ),
+        };
+        let neo4j = Neo4jConfig {
+            uri: l.string("NEO4J_URI", "neo4j.uri", &d.neo4j.uri),
+            user: l.string("NEO4J_USER", "neo4j.user", &d.neo4j.user),
+            password: l.string("NEO4J_PASSWORD", "neo4j.password", &d.neo4j.password),
+            fetch_size: l.parse("NEO4J_FETCH_SIZE", "neo4j.fetch_size", d.neo4j.fetch_size),
+            timeout_ms: l.parse("NEO4J_TIMEOUT_MS", "neo4j.timeout_ms", d.neo4j.timeout_ms),
+            breaker_threshold: l.parse(
+                "COS_NEO4J_BREAKER_THRESHOLD",
+                "neo4j.breaker_threshold",
+                d.neo4j.breaker_threshold,
+            ),
+            breaker_cooldown_secs: l.parse(
+                "COS_NEO4J_BREAKER_COOLDOWN_SECS",
+                "neo4j.breaker_cooldown_secs",
+                d.neo4j.breaker_cooldown_secs,
+            ),
+            cypher_max_rows: l.parse(
+                "COS_CYPHER_MAX_ROWS",
+                "neo4j.cypher_max_rows",
+                d.neo4j.cypher_max_rows,
+            ),
+            cypher_timeout_secs: l.parse(
+                "COS_CYPHER_TIMEOUT_SECS",
+                "neo4j.cypher_timeout_secs",
+                d.neo4j.cypher_timeout_secs,
+            ),
+            required: l.flag("COS_REQUIRE_NEO4J", "neo4j.required", d.neo4j.required),
+            reconnect_max_secs: l.parse(
+                "COS_NEO4J_RECONNECT_MAX_SECS",
+                "neo4j.reconnect_max_secs",
+                d.neo4j.reconnect_max_secs,
+            ),
+        };
+        let llm = LlmConfig {
+            api_key: l.opt_string("OPENAI_API_KEY", "llm.api_key"),
+            model: l.string("OPENAI_MODEL", "llm.model", &d.llm.model),
+            fallbacks: l
+                .opt_string("COS_MODEL_FALLBACKS", "llm.fallbacks")
+                .map(|raw| split_list(&raw))
+                .unwrap_or_default(),
+            base_url: l
+                .opt_string("OPENAI_BASE_URL", "llm.base_url")
+                .map(|v| v.trim_end_matches('/').to_string())
+                .filter(|v| !v.is_empty()),
+            api_type: l.string("OPENAI_API_TYPE", "llm.api_type", &d.llm.api_type).to_lowercase(),
+            azure_api_version: l.string(
+                "AZURE_OPENAI_API_VERSION",
+                "llm.azure_api_version",
+                &d.llm.azure_api_version,
+            ),
+            azure_deployment: l.opt_string("AZURE_OPENAI_DEPLOYMENT", "llm.azure_deployment"),
+            azure_embed_deployment: l
+                .opt_string("AZURE_OPENAI_EMBED_DEPLOYMENT", "llm.azure_embed_deployment"),
+            offline: l.flag("COS_OFFLINE", "llm.offline", d.llm.offline),
+            timeout_ms: l.parse("OPENAI_TIMEOUT_MS", "llm.timeout_ms", d.llm.timeout_ms),
+            schema: l.string("COS_LLM_SCHEMA", "llm.schema", &d.llm.schema).to_lowercase(),
+            log_dir: l.opt_string("COS_LLM_LOG_DIR", "llm.log_dir"),
+            log_redact_private: l.flag(
+                "COS_LLM_LOG_REDACT_PRIVATE",
+                "llm.log_redact_private",
+                d.llm.log_redact_private,
+            ),
+        };
+        let speech = SpeechConfig {
+            api_key: l.opt_string("ELEVEN_API_KEY", "speech.api_key"),
+            voice_id: l.string("ELEVEN_VOICE_ID", "speech.voice_id", &d.speech.voice_id),
+            tts_model: l.string("ELEVEN_TTS_MODEL", "speech.tts_model", &d.speech.tts_model),
+            voice_id_by_language: l
+                .by_language("ELEVEN_VOICE_ID_", "speech.voice_id_by_language"),
+            tts_model_by_language: l
+                .by_language("ELEVEN_TTS_MODEL_", "speech.tts_model_by_language"),
+            timeout_ms: l.parse("ELEVEN_TIMEOUT_MS", "speech.timeout_ms", d.speech.timeout_ms),
+        };
+        let rag = RagConfig {
+            embed_model: l.string("OPENAI_EMBED_MODEL", "rag.embed_model", &d.rag.embed_model),
+            embed_cache_dir: l.opt_string("COS_EMBED_CACHE_DIR", "rag.embed_cache_dir"),
+            max_docs: l.parse("RAG_MAX_DOCS", "rag.max_docs", d.rag.max_docs),
+            chunk_chars: l.parse("RAG_CHUNK_CHARS", "rag.chunk_chars", d.rag.chunk_chars),
+            chunk_overlap: l.parse("RAG_CHUNK_OVERLAP", "rag.chunk_overlap", d.rag.chunk_overlap),
+            source_roles: l
+                .opt_string("COS_RAG_SOURCE_ROLES", "rag.source_roles")
+                .unwrap_or_default(),
+            top_k: l.parse("COS_RAG_TOP_K", "rag.top_k", d.rag.top_k),
+        };
+        let cors = CorsConfig {
+            origins: l.opt_string("COS_CORS_ORIGINS", "cors.origins").map(|raw| split_list(&raw)),
+        };
+        let limits = LimitsConfig {
+            ask_max_chars: l.parse(
+                "COS_ASK_MAX_CHARS",
+                "limits.ask_max_chars",
+                d.limits.ask_max_chars,
+            ),
+            ask_soft_chars: l.parse(
+                "COS_ASK_SOFT_CHARS",
+                "limits.ask_soft_chars",
+                d.limits.ask_soft_chars,
+            ),
+            ask_context_max_docs: l.parse(
+                "COS_ASK_CONTEXT_MAX_DOCS",
+                "limits.ask_context_max_docs",
+                d.limits.ask_context_max_docs,
+            ),
+            ask_context_max_chars: l.parse(
+                "COS_ASK_CONTEXT_MAX_CHARS",
+                "limits.ask_context_max_chars",
+                d.limits.ask_context_max_chars,
+            ),
+        };
+        let storage = StorageConfig {
+            backend: l
+                .string("COS_STORAGE", "storage.backend", &d.storage.backend)
+                .to_lowercase(),
+            strict_persistence: l.flag(
+                "COS_STRICT_PERSISTENCE",
+                "storage.strict_persistence",
+                d.storage.strict_persistence,
+            ),
+            compact_keep_latest: l.parse(
+                "COS_COMPACT_KEEP_LATEST",
+                "storage.compact_keep_latest",
+                d.storage.compact_keep_latest,
+            ),
+        };
+        let b = &d.orgbrain;
+        let orgbrain = OrgBrainConfig {
+            flow_file: l.string("COS_FLOW_FILE", "orgbrain.flow_file", &b.flow_file),
+            max_events: l.parse("COS_MAX_EVENTS_PER_BRAIN", "orgbrain.max_events", b.max_events),
+            min_event_confidence: l.parse(
+                "COS_MIN_EVENT_CONFIDENCE",
+                "orgbrain.min_event_confidence",
+                b.min_event_confidence,
+            ),
+            truth_history: l.parse("COS_TRUTH_HISTORY", "orgbrain.truth_history", b.truth_history),
+            truth_top_k: l.parse("COS_TRUTH_TOP_K", "orgbrain.truth_top_k", b.truth_top_k),
+            truth_prompt_chars: l.parse(
+                "COS_TRUTH_PROMPT_CHARS",
+                "orgbrain.truth_prompt_chars",
+                b.truth_prompt_chars,
+            ),
+            graph_context_truths: l.parse(
+                "COS_GRAPH_CONTEXT_TRUTHS",
+                "orgbrain.graph_context_truths",
+                b.graph_context_truths,
+            ),
+            graph_context_decisions: l.parse(
+                "COS_GRAPH_CONTEXT_DECISIONS",
+                "orgbrain.graph_context_decisions",
+                b.graph_context_decisions,
+            ),
+            reviewer: l.flag("COS_REVIEWER", "orgbrain.reviewer", b.reviewer),
+            fanout: l.flag("COS_FANOUT", "orgbrain.fanout", b.fanout),
+            retry_max_attempts: l.parse(
+                "COS_RETRY_MAX_ATTEMPTS",
+                "orgbrain.retry_max_attempts",
+                b.retry_max_attempts,
+            ),
+            retry_base_ms: l.parse("COS_RETRY_BASE_MS", "orgbrain.retry_base_ms", b.retry_base_ms),
+            low_confidence_threshold: l.parse(
+                "COS_LOW_CONFIDENCE_THRESHOLD",
+                "orgbrain.low_confidence_threshold",
+                b.low_confidence_threshold,
+            ),
+        };
+        let i = &d.ingest;
+        let ingest = IngestConfig {
+            cluster_sim: l.parse("ORG_EMAIL_CLUSTER_SIM", "ingest.cluster_sim", i.cluster_sim),
+            min_cluster_size: l.parse(
+                "ORG_MIN_CLUSTER_SIZE",
+                "ingest.min_cluster_size",
+                i.min_cluster_size,
+            ),
+            unclustered_group: l.flag(
+                "ORG_UNCLUSTERED_GROUP",
+                "ingest.unclustered_group",
+                i.unclustered_group,
+            ),
+            cluster_label_min_members: l.parse(
+                "COS_CLUSTER_LABEL_MIN_MEMBERS",
+                "ingest.cluster_label_min_members",
+                i.cluster_label_min_members,
+            ),
+            cluster_label_samples: l.parse(
+                "COS_CLUSTER_LABEL_SAMPLES",
+                "ingest.cluster_label_samples",
+                i.cluster_label_samples,
+            ),
+            topic_mode: l
+                .string("ORG_TOPIC_MODE", "ingest.topic_mode", &i.topic_mode)
+                .to_lowercase(),
+            topic_max_keywords: l.parse(
+                "ORG_TOPIC_MAX_KEYWORDS",
+                "ingest.topic_max_keywords",
+                i.topic_max_keywords,
+            ),
+        };
+        let employees = EmployeesConfig {
+            file: l.string("COS_EMPLOYEES_FILE", "employees.file", &d.employees.file),
+            auto_alias: l.flag("COS_AUTO_ALIAS", "employees.auto_alias", d.employees.auto_alias),
+            role_rules: l
+                .opt_string("COS_ROLE_RULES", "employees.role_rules")
+                .unwrap_or_default(),
+        };
+        let visibility = VisibilityConfig {
+            rules: l.opt_string("COS_VISIBILITY_RULES", "visibility.rules"),
+            default: l
+                .string("COS_VISIBILITY_DEFAULT", "visibility.default", &d.visibility.default)
+                .to_lowercase(),
+        };
+        let p = &d.privacy;
+        let privacy = PrivacyConfig {
+            private_key: l.opt_string("COS_PRIVATE_KEY", "privacy.private_key"),
+            private_key_id: l.string(
+                "COS_PRIVATE_KEY_ID",
+                "privacy.private_key_id",
+                &p.private_key_id,
+            ),
+            private_keys_retired: l
+                .opt_string("COS_PRIVATE_KEYS_RETIRED", "privacy.private_keys_retired"),
+            redaction: l.string("COS_REDACTION", "privacy.redaction", &p.redaction).to_lowercase(),
+            redaction_action: l
+                .string("COS_REDACTION_ACTION", "privacy.redaction_action", &p.redaction_action)
+                .to_lowercase(),
+            corporate_domains: l
+                .opt_string("COS_CORPORATE_DOMAINS", "privacy.corporate_domains")
+                .map(|raw| split_list(&raw))
+                .unwrap_or_default(),
+        };
+        let m = &d.maintenance;
+        let maintenance = MaintenanceConfig {
+            interval_secs: l.parse(
+                "COS_MAINTENANCE_INTERVAL_SECS",
+                "maintenance.interval_secs",
+                m.interval_secs,
+            ),
+            compact_interval_secs: l.parse(
+                "COS_COMPACT_INTERVAL_SECS",
+                "maintenance.compact_interval_secs",
+                m.compact_interval_secs,
+            ),
+            stale_check_interval_secs: l.parse(
+                "COS_STALE_CHECK_INTERVAL_SECS",
+                "maintenance.stale_check_interval_secs",
+                m.stale_check_interval_secs,
+            ),
+            stale_age_days: l.parse(
+                "COS_STALE_AGE_DAYS",
+                "maintenance.stale_age_days",
+                m.stale_age_days,
+            ),
+            stale_truth_weight: l.parse(
+                "COS_STALE_TRUTH_WEIGHT",
+                "maintenance.stale_truth_weight",
+                m.stale_truth_weight,
+            ),
+            stale_threshold: l.parse(
+                "COS_STALE_THRESHOLD",
+                "maintenance.stale_threshold",
+                m.stale_threshold,
+            ),
+        };
+        let audit = AuditConfig {
+            batch: l.parse("COS_AUDIT_BATCH", "audit.batch", d.audit.batch),
+            flush_ms: l.parse("COS_AUDIT_FLUSH_MS", "audit.flush_ms", d.audit.flush_ms),
+            memory_max: l.parse("COS_AUDIT_MEMORY_MAX", "audit.memory_max", d.audit.memory_max),
+        };
+        let w = &d.webhooks;
+        let webhooks = WebhooksConfig {
+            urls: l
+                .opt_string("COS_WEBHOOK_URLS", "webhooks.urls")
+                .map(|raw| split_list(&raw))
+                .unwrap_or_default(),
+            secret: l.opt_string("COS_WEBHOOK_SECRET", "webhooks.secret"),
+            recipient: l.opt_string("COS_WEBHOOK_RECIPIENT", "webhooks.recipient"),
+            queue: l.parse("COS_WEBHOOK_QUEUE", "webhooks.queue", w.queue),
+            max_attempts: l.parse(
+                "COS_WEBHOOK_MAX_ATTEMPTS",
+                "webhooks.max_attempts",
+                w.max_attempts,
+            ),
+            backoff_ms: l.parse("COS_WEBHOOK_BACKOFF_MS", "webhooks.backoff_ms", w.backoff_ms),
+            timeout_ms: l.parse("COS_WEBHOOK_TIMEOUT_MS", "webhooks.timeout_ms", w.timeout_ms),
+        };
+        let script = ScriptConfig {
+            path: l.opt_string("COS_SCRIPT", "script.path"),
+            out: l.opt_string("COS_SCRIPT_OUT", "script.out"),
+            delay_ms: l.parse("COS_SCRIPT_DELAY_MS", "script.delay_ms", d.script.delay_ms),
+        };
+
+        let mut config = Self {
+            http,
+            neo4j,
+            llm,
+            speech,
+            rag,
+            cors,
+            limits,
+            storage,
+            orgbrain,
+            ingest,
+            employees,
+            visibility,
+            privacy,
+            maintenance,
+            audit,
+            webhooks,
+            script,
+            file: file_name,
+            warnings: Vec::new(),
+        };
+        let mut errors = l.errors;
+        config.validate(&mut errors);
+        if !errors.is_empty() {
+            bail!("invalid configuration:\n  - {}", errors.join("\n  - "));
+        }
+        Ok(config)
+    }
+
+    /// Adds the cross-field problems to `errors` and fills `warnings`.
+    fn validate(&mut self, errors: &mut Vec<String>) {
+        if self.http.addr.parse::<std::net::SocketAddr>().is_err() {
+            errors.push(format!(
+                "COS_HTTP_ADDR: expected host:port (e.g. 0.0.0.0:3000), got `{}`",
+                self.http.addr
+            ));
+        }
+        if self.http.sse_channel_capacity == 0 {
+            errors.push("COS_SSE_CHANNEL_CAPACITY: must be at least 1".to_string());
+        }
+        if self.neo4j.fetch_size == 0 {
+            errors.push("NEO4J_FETCH_SIZE: must be at least 1".to_string());
+        }
+        if self.neo4j.reconnect_max_secs == 0 {
+            errors.push("COS_NEO4J_RECONNECT_MAX_SECS: must be at least 1".to_string());
+        }
+        for (name, ms) in [
+            ("NEO4J_TIMEOUT_MS", self.neo4j.timeout_ms),
+            ("OPENAI_TIMEOUT_MS", self.llm.timeout_ms),
+            ("ELEVEN_TIMEOUT_MS", self.speech.timeout_ms),
+        ] {
+            if ms == 0 {
+                errors.push(format!("{name}: must be at least 1"));
+            }
+        }
+        match self.llm.api_type.as_str() {
+            "openai" => {}
+            "azure" if self.llm.base_url.is_none() => errors.push(
+                "OPENAI_API_TYPE=azure needs OPENAI_BASE_URL (the Azure resource endpoint)"
+                    .to_string(),
+            ),
+            "azure" => {}
+            other => errors.push(format!("OPENAI_API_TYPE: expected openai or azure, got `{other}`")),
+        }
+        if self.rag.chunk_chars > 0 && self.rag.chunk_overlap >= self.rag.chunk_chars {
+            errors.push(format!(
+                "RAG_CHUNK_OVERLAP ({}) must be smaller than RAG_CHUNK_CHARS ({})",
+                self.rag.chunk_overlap, self.rag.chunk_chars
+            ));
+        }
+        for origin in self.cors.origins.iter().flatten() {
+            if origin != "*" && origin.parse::<axum::http::HeaderValue>().is_err() {
+                errors.push(format!("COS_CORS_ORIGINS: `{origin}` is not a valid origin"));
+            }
+        }
+        if self.limits.ask_max_chars == 0 {
+            errors.push("COS_ASK_MAX_CHARS: must be at least 1".to_string());
+        }
+        for (name, value, allowed) in [
+            ("COS_LLM_SCHEMA", &self.llm.schema, &["retry", "warn", "off", "0", "false"][..]),
+            ("COS_STORAGE", &self.storage.backend, &["neo4j", "memory"]),
+            ("ORG_TOPIC_MODE", &self.ingest.topic_mode, &["subject", "keywords"]),
+            ("COS_VISIBILITY_DEFAULT", &self.visibility.default, &["allow", "deny"]),
+            (
+                "COS_REDACTION",
+                &self.privacy.redaction,
+                &["off", "0", "false", "standard", "strict"],
+            ),
+            ("COS_REDACTION_ACTION", &self.privacy.redaction_action, &["mask", "drop"]),
+        ] {
+            if !allowed.contains(&value.as_str()) {
+                errors.push(format!("{name}: expected {}, got `{value}`", allowed.join(", ")));
+            }
+        }
+        let m = &self.maintenance;
+        for (name, value) in [
+            ("COS_STALE_AGE_DAYS", m.stale_age_days),
+            ("COS_STALE_TRUTH_WEIGHT", m.stale_truth_weight),
+            ("COS_STALE_THRESHOLD", m.stale_threshold),
+        ] {
+            if !(value.is_finite() && value > 0.0) {
+                errors.push(format!("{name}: must be a positive number"));
+            }
+        }
+        let w = &self.webhooks;
+        if !w.urls.is_empty() {
+            for url in w.urls.iter().filter(|u| reqwest::Url::parse(u).is_err()) {
+                errors.push(format!("COS_WEBHOOK_URLS: `{url}` is not a valid URL"));
+            }
+            for (name, value) in [
+                ("COS_WEBHOOK_SECRET", &w.secret),
+                ("COS_WEBHOOK_RECIPIENT", &w.recipient),
+            ] {
+                if value.is_none() {
+                    errors.push(format!("{name} must be set when COS_WEBHOOK_URLS is"));
+                }
+            }
+        }
+
+        if !self.llm.offline && self.llm.api_key.is_none() {
+            self.warnings.push(
+                "OPENAI_API_KEY is not set: asks fail and ingestion skips embeddings and clusters"
+                    .to_string(),
+            );
+        }
+        if self.speech.api_key.is_none() {
+            self.warnings
+                .push("ELEVEN_API_KEY is not set: audio asks and response_audio fail".to_string());
+        }
+    }
+
+    /// Whether real OpenAI calls are possible: not offline and an API key is set.
+    pub fn openai_configured(&self) -> bool {
+        !self.llm.offline && self.llm.api_key.is_some()
+    }
+}
+
+/// Looks settings up in the environment, then in the `cos.toml` table, collecting errors.
+struct Loader {
+    table: toml::Table,
+    errors: Vec<String>,
+}
+
+impl Loader {
+    /// `env`, else the file's `section.field`; blank values count as unset.
+    fn raw(&self, env_name: &str, key: &str) -> Option<String> {
+        if let Ok(v) = env::var(env_name) {
+            let v = v.trim().to_string();
+            return (!v.is_empty()).then_some(v);
+        }
+        let value = self.file_value(key)?;
+        let v = match value {
+            toml::Value::String(s) => s.trim().to_string(),
+            toml::Value::Array(items) => items
+                .iter()
+                .map(|i| i.as_str().map(str::to_string).unwrap_or_else(|| i.to_string()))
+                .collect::<Vec<_>>()
+                .join(","),
+            other => other.to_string(),
+        };
+        (!v.is_empty()).then_some(v)
+    }
+
+    /// Like `raw`, but a blank value is `Some("")` rather than unset.
+    fn raw_or_empty(&self, env_name: &str, key: &str) -> Option<String> {
+        if let Ok(v) = env::var(env_name) {
+            return Some(v.trim().to_string());
+        }
+        if let Some(toml::Value::String(v)) = self.file_value(key) {
+            return Some(v.trim().to_string());
+        }
+        self.raw(env_name, key)
+    }
+
+    fn file_value(&self, key: &str) -> Option<&toml::Value> {
+        let (section, field) = key.split_once('.')?;
+        self.table.get(section)?.get(field)
+    }
+
+    fn string(&self, env_name: &str, key: &str, default: &str) -> String {
+        self.raw(env_name, key).unwrap_or_else(|| default.to_string())
+    }
+
+    fn opt_string(&self, env_name: &str, key: &str) -> Option<String> {
+        self.raw(env_name, key)
+    }
+
+    fn parse<T: FromStr>(&mut self, env_name: &str, key: &str, default: T) -> T {
+        let Some(raw) = self.raw(env_name, key) else {
+            return default;
+        };
+        match raw.parse() {
+            Ok(v) => v,
+            Err(_) => {
+                self.errors.push(format!(
+                    "{env_name} ({key}): expected {}, got `{raw}`",
+                    expected_kind::<T>()
+                ));
+                default
+            }
+        }
+    }
+
+    fn flag(&mut self, env_name: &str, key: &str, default: bool) -> bool {
+        let Some(raw) = self.raw(env_name, key) else {
+            return default;
+        };
+        match raw.to_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => true,
+            "0" | "false" | "no" | "off" => false,
+            _ => {
+                self.errors
+                    .push(format!("{env_name} ({key}): expected true or false, got `{raw}`"));
+                default
+            }
+        }
+    }
+
+    /// `{prefix}{LANG}` variables, over the file's `key` table.
+    fn by_language(&mut self, prefix: &str, key: &str) -> BTreeMap<String, String> {
+        let mut out = BTreeMap::new();
+        if let Some(toml::Value::Table(t)) = self.file_value(key) {
+            for (lang, v) in t {
+                if let Some(v) = v.as_str().map(str::trim).filter(|v| !v.is_empty()) {
+                    out.insert(lang.to_uppercase(), v.to_string());
+                }
+            }
+        }
+        for (name, v) in env::vars() {
+            let Some(lang) = name.strip_prefix(prefix).filter(|l| !l.is_empty()) else {
+                continue;
+            };
+            if !v.trim().is_empty() {
+                out.insert(lang.to_uppercase(), v.trim().to_string());
+            }
+        }
+        out
+    }
+}
+
+fn expected_kind<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    if name.starts_with('f') {
+        "a number"
+    } else {
+        "a whole number"
+    }
+}
+
+fn split_list(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn masked<S: Serializer>(value: &Option<String>, s: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(_) => s.serialize_str("********"),
+        None => s.serialize_none(),
+    }
+}
+
+fn masked_str<T: ?Sized, S: Serializer>(_: &T, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str("********")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_file(raw: &str) -> Result<CosConfig> {
+        CosConfig::from_sources(Some(("cos.toml".to_string(), toml::from_str(raw).unwrap())))
+    }
+
+    #[test]
+    fn file_values_fill_the_sections() {
+        let config = from_file(
+            r#"
+            [storage]
+            backend = "Memory"
+            [orgbrain]
+            max_events = 12
+            reviewer = false
+            [ingest]
+            topic_mode = "keywords"
+            cluster_sim = 0.9
+            [privacy]
+            corporate_domains = ["@acme.com", "acme.io"]
+            [webhooks]
+            urls = "https://a.example/hook, https://b.example/hook"
+            secret = "s3cret"
+            recipient = "ceo"
+            [maintenance]
+            compact_interval_secs = 86400
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.storage.backend, "memory");
+        assert_eq!(config.orgbrain.max_events, 12);
+        assert!(!config.orgbrain.reviewer);
+        assert_eq!(config.ingest.topic_mode, "keywords");
+        assert_eq!(config.ingest.cluster_sim, 0.9);
+        assert_eq!(config.privacy.corporate_domains, ["@acme.com", "acme.io"]);
+        assert_eq!(config.webhooks.urls.len(), 2);
+        assert_eq!(config.maintenance.compact_interval_secs, 86_400);
+        // Untouched settings keep their defaults.
+        assert_eq!(config.audit.batch, CosConfig::default().audit.batch);
+        assert_eq!(CosConfig::default().maintenance.compact_interval_secs, 0, "opt-in");
+    }
+
+    #[test]
+    fn invalid_values_are_all_listed() {
+        let err = from_file(
+            r#"
+            [storage]
+            backend = "sqlite"
+            [visibility]
+            default = "maybe"
+            [maintenance]
+            stale_threshold = -1.0
+            [orgbrain]
+            max_events = "many"
+            [webhooks]
+            urls = ["not a url"]
+            "#,
+        )
+        .unwrap_err()
+        .to_string();
+        for expected in [
+            "COS_STORAGE: expected neo4j, memory, got `sqlite`",
+            "COS_VISIBILITY_DEFAULT",
+            "COS_STALE_THRESHOLD: must be a positive number",
+            "COS_MAX_EVENTS_PER_BRAIN (orgbrain.max_events)",
+            "COS_WEBHOOK_URLS: `not a url` is not a valid URL",
+            "COS_WEBHOOK_SECRET must be set when COS_WEBHOOK_URLS is",
+            "COS_WEBHOOK_RECIPIENT must be set when COS_WEBHOOK_URLS is",
+        ] {
+            assert!(err.contains(expected), "missing `{expected}` in:\n{err}");
+        }
+    }
+
+    #[test]
+    fn secrets_are_masked() {
+        let masks = |c: &CosConfig| serde_json::to_string(c).unwrap().matches("********").count();
+        let mut config = CosConfig::default();
+        let unset = masks(&config);
+        config.privacy.private_key = Some("private-key".to_string());
+        config.privacy.private_keys_retired = Some("k0:old-key".to_string());
+        config.webhooks.secret = Some("webhook-secret".to_string());
+        let json = serde_json::to_string(&config).unwrap();
+        for secret in ["private-key", "old-key", "webhook-secret"] {
+            assert!(!json.contains(secret), "{secret} leaked");
+        }
+        assert_eq!(masks(&config), unset + 3);
+    }
+}
@@ -0,0 +1,153 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, bail, Context as _, Result};
+use base64::Engine;
+use std::collections::HashMap;
+
+const NONCE_LEN: usize = 12;
+
+/// AES-256-GCM envelope for private notes.
+///
+/// Sealed values look like `<key_id>:<base64(nonce || ciphertext)>`. The key id prefix lets
+/// old ciphertexts be opened after the active key is rotated.
+///
+/// - `COS_PRIVATE_KEY`: active key, 32 bytes, base64.
+/// - `COS_PRIVATE_KEY_ID`: id written on new ciphertexts (default `k1`).
+/// - `COS_PRIVATE_KEYS_RETIRED`: comma-separated `id:base64key` pairs accepted for decrypt only.
+pub struct NoteCipher {
+    active_id: String,
+    keys: HashMap<String, Aes256Gcm>,
+}
+
+fn parse_key(id: &str, b64: &str) -> Result<Aes256Gcm> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(b64.trim())
+        .with_context(|| format!("private key {id} is not valid base64"))?;
+    if bytes.len() != 32 {
+        bail!("private key {id} must be 32 bytes, got {}", bytes.len());
+    }
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&bytes)))
+}
+
+impl NoteCipher {
+    /// `Ok(None)` when no key is configured; an error when a configured key is malformed.
+    pub fn from_env() -> Result<Option<Self>> {
+        let privacy = &crate::app_state::config().privacy;
+        let Some(active) = &privacy.private_key else {
+            return Ok(None);
+        };
+        let retired = privacy.private_keys_retired.as_deref().unwrap_or_default();
+        Self::new(&privacy.private_key_id, active, retired).map(Some)
+    }
+
+    /// The active key `active_b64` under `active_id`, plus the `id:base64key` pairs of `retired`.
+    pub fn new(active_id: &str, active_b64: &str, retired: &str) -> Result<Self> {
+        if active_id.contains(':') {
+            bail!("COS_PRIVATE_KEY_ID must not contain ':'");
+        }
+
+        let mut keys = HashMap::new();
+        keys.insert(active_id.to_string(), parse_key(active_id, active_b64)?);
+
+        for pair in retired.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            let (id, b64) = pair
+                .split_once(':')
+                .ok_or_else(|| anyhow!("COS_PRIVATE_KEYS_RETIRED entries must be id:base64key"))?;
+            if id.trim() == active_id {
+                continue;
+            }
+            keys.insert(id.trim().to_string(), parse_key(id.trim(), b64)?);
+        }
+
+        Ok(Self { active_id: active_id.to_string(), keys })
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let cipher = self
+            .keys
+            .get(&self.active_id)
+            .ok_or_else(|| anyhow!("active private key missing"))?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| anyhow!("private note encryption failed"))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!(
+            "{}:{}",
+            self.active_id,
+            base64::engine::general_purpose::STANDARD.encode(sealed)
+        ))
+    }
+
+    pub fn decrypt(&self, sealed: &str) -> Result<String> {
+        let (key_id, b64) = sealed
+            .split_once(':')
+            .ok_or_else(|| anyhow!("sealed note has no key id"))?;
+        let cipher = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| anyhow!("unknown private key id {key_id}"))?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(b64)
+            .context("sealed note is not valid base64")?;
+        if bytes.len() < NONCE_LEN {
+            bail!("sealed note is truncated");
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("private note decryption failed"))?;
+        String::from_utf8(plaintext).context("decrypted note is not utf-8")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> String {
+        base64::engine::general_purpose::STANDARD.encode([byte; 32])
+    }
+
+    #[test]
+    fn sealed_notes_round_trip_with_a_fresh_nonce() {
+        let cipher = NoteCipher::new("k1", &key(1), "").unwrap();
+        let a = cipher.encrypt("offer: 120k").unwrap();
+        let b = cipher.encrypt("offer: 120k").unwrap();
+        assert!(a.starts_with("k1:"));
+        assert_ne!(a, b);
+        assert_eq!(cipher.decrypt(&a).unwrap(), "offer: 120k");
+    }
+
+    #[test]
+    fn retired_keys_still_open_old_notes() {
+        let old = NoteCipher::new("k1", &key(1), "").unwrap();
+        let sealed = old.encrypt("before rotation").unwrap();
+
+        let rotated = NoteCipher::new("k2", &key(2), &format!("k1:{}", key(1))).unwrap();
+        assert_eq!(rotated.decrypt(&sealed).unwrap(), "before rotation");
+        assert!(rotated.encrypt("after").unwrap().starts_with("k2:"));
+
+        let dropped = NoteCipher::new("k2", &key(2), "").unwrap();
+        assert!(dropped.decrypt(&sealed).is_err());
+    }
+
+    #[test]
+    fn tampered_or_malformed_input_is_rejected() {
+        let cipher = NoteCipher::new("k1", &key(1), "").unwrap();
+        let sealed = cipher.encrypt("secret").unwrap();
+        let mut tampered = sealed.into_bytes();
+        // A ciphertext byte, well clear of the key id and base64 padding.
+        let i = "k1:".len() + 24;
+        tampered[i] = if tampered[i] == b'A' { b'B' } else { b'A' };
+        assert!(cipher.decrypt(&String::from_utf8(tampered).unwrap()).is_err());
+        assert!(cipher.decrypt("no key id").is_err());
+        assert!(cipher.decrypt("k1:AAAA").is_err());
+
+        assert!(NoteCipher::new("k1", "c2hvcnQ=", "").is_err());
+        assert!(NoteCipher::new("k:1", &key(1), "").is_err());
+        assert!(NoteCipher::new("k1", &key(1), "missing-separator").is_err());
+    }
+}
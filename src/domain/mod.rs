@@ -15,6 +15,27 @@ pub enum EmployeeRole {
     Engineer,
 }
 
+impl EmployeeRole {
+    /// The serialized name: `ceo`, `hr` or `engineer`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ceo => "ceo",
+            Self::Hr => "hr",
+            Self::Engineer => "engineer",
+        }
+    }
+
+    /// Inverse of `as_str`, ignoring case and surrounding whitespace.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "ceo" => Some(Self::Ceo),
+            "hr" => Some(Self::Hr),
+            "engineer" => Some(Self::Engineer),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
 pub struct PrivateStoreKey(pub String);
 
@@ -46,8 +67,93 @@ pub struct RoutingDirective {
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct GraphUpdates {
-    pub nodes: Vec<String>,
-    pub edges: Vec<String>,
+    pub nodes: Vec<GraphUpdateEntry>,
+    pub edges: Vec<GraphUpdateEntry>,
+}
+
+/// A node or relationship a write created or touched, with enough to render it without a
+/// lookup. Traces stored before entries were structured hold bare element ids; those
+/// deserialize with the id as `display`, an empty `label` and no `key_property`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct GraphUpdateEntry {
+    /// Neo4j `elementId(...)`, or the `mem:...` id in memory storage.
+    pub element_id: String,
+    /// The node label or relationship type.
+    pub label: String,
+    /// `name=value` of the node's identifying property, e.g. `decision_id=hiring_freeze`.
+    /// `None` for relationships.
+    pub key_property: Option<String>,
+    pub display: String,
+}
+
+impl GraphUpdateEntry {
+    pub fn node(element_id: impl Into<String>, label: &str, key: &str, value: &str) -> Self {
+        Self {
+            element_id: element_id.into(),
+            label: label.to_string(),
+            key_property: Some(format!("{key}={value}")),
+            display: format!("{label} {value}"),
+        }
+    }
+
+    /// Version `version` of object `object_id`, keyed like `decision_version_id=<id>:v3`.
+    pub fn version(
+        element_id: impl Into<String>,
+        label: &str,
+        key: &str,
+        object_id: &str,
+        version: i64,
+    ) -> Self {
+        Self {
+            element_id: element_id.into(),
+            label: label.to_string(),
+            key_property: Some(format!("{key}={object_id}:v{version}")),
+            display: format!("{label} {object_id} v{version}"),
+        }
+    }
+
+    /// `from` and `to` are the `display` of the two ends.
+    pub fn edge(element_id: impl Into<String>, rel_type: &str, from: &str, to: &str) -> Self {
+        Self {
+            element_id: element_id.into(),
+            label: rel_type.to_string(),
+            key_property: None,
+            display: format!("{from} -[{rel_type}]-> {to}"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for GraphUpdateEntry {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            ElementId(String),
+            Entry {
+                element_id: String,
+                #[serde(default)]
+                label: String,
+                #[serde(default)]
+                key_property: Option<String>,
+                #[serde(default)]
+                display: String,
+            },
+        }
+        Ok(match Stored::deserialize(deserializer)? {
+            Stored::ElementId(element_id) => Self {
+                display: element_id.clone(),
+                element_id,
+                label: String::new(),
+                key_property: None,
+            },
+            Stored::Entry { element_id, label, key_property, display } => Self {
+                element_id,
+                label,
+                key_property,
+                display,
+            },
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -56,6 +162,7 @@ pub struct ReasoningTrace {
     pub topic: String,
     pub summary: String,
     pub version: i64,
+    pub confidence: f32,
     pub rationale: String,
     pub evidence: Vec<String>,
     pub assumptions: Vec<String>,
@@ -63,6 +170,36 @@ pub struct ReasoningTrace {
     pub agents_involved: Vec<EmployeeAgentId>,
     pub graph_updates: GraphUpdates,
     pub routing: HashMap<String, String>,
+    pub created_at: DateTime<Utc>,
+    /// ISO 639-3 code of the language the triggering input was written in, when detected.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Reviewer notes, plus notes attached after the fact via
+    /// `POST /v1/traces/{decision_id}/annotations`.
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    /// Set by knowledge ingest when the content matched the current version, so no new
+    /// version was created and `version` is the existing one.
+    #[serde(default)]
+    pub deduplicated: bool,
+    /// Set when the reviewer flagged the decision; its confidence has already been lowered.
+    #[serde(default)]
+    pub needs_approval: bool,
+    /// Graph writes for this trace that failed, so `graph_updates` is incomplete.
+    #[serde(default)]
+    pub persistence_warnings: Vec<String>,
+    /// `announcement` for CEO announcements (`POST /v1/announce`); absent for OrgBrain decisions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Annotation {
+    pub annotation_id: String,
+    /// Agent id of the employee who wrote it.
+    pub author: String,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
 }
 
 impl Event {
@@ -84,3 +221,65 @@ impl Event {
         }
     }
 }
+
+#[cfg(test)]
+impl ReasoningTrace {
+    /// A version-1 trace with no evidence, routing or graph updates.
+    pub(crate) fn sample(decision_id: &str, topic: &str, confidence: f32) -> Self {
+        Self {
+            decision_id: decision_id.to_string(),
+            topic: topic.to_string(),
+            summary: format!("{topic} summary"),
+            version: 1,
+            confidence,
+            rationale: String::new(),
+            evidence: Vec::new(),
+            assumptions: Vec::new(),
+            trigger_events: Vec::new(),
+            agents_involved: Vec::new(),
+            graph_updates: GraphUpdates { nodes: Vec::new(), edges: Vec::new() },
+            routing: HashMap::new(),
+            created_at: Utc::now(),
+            language: None,
+            annotations: Vec::new(),
+            deduplicated: false,
+            needs_approval: false,
+            persistence_warnings: Vec::new(),
+            kind: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn graph_updates_load_bare_ids_and_structured_entries() {
+        let updates: GraphUpdates = serde_json::from_value(json!({
+            "nodes": [
+                "4:ab:12",
+                { "element_id": "mem:DecisionVersion:hiring:v3", "label": "DecisionVersion",
+                  "key_property": "decision_version_id=hiring:v3",
+                  "display": "DecisionVersion hiring v3" }
+            ],
+            "edges": []
+        }))
+        .unwrap();
+        assert_eq!(updates.nodes[0].display, "4:ab:12");
+        assert_eq!(updates.nodes[0].label, "");
+        let v3 = GraphUpdateEntry::version(
+            "mem:DecisionVersion:hiring:v3",
+            "DecisionVersion",
+            "decision_version_id",
+            "hiring",
+            3,
+        );
+        assert_eq!(updates.nodes[1], v3);
+
+        let edge = GraphUpdateEntry::edge("5:ab:1", "CURRENT", "Decision hiring", &v3.display);
+        assert_eq!(edge.display, "Decision hiring -[CURRENT]-> DecisionVersion hiring v3");
+        assert_eq!(edge.key_property, None);
+    }
+}
@@ -0,0 +1,188 @@
+use anyhow::{bail, Context as _, Result};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::domain::EmployeeRole;
+
+/// One seeded employee: a canonical identity for the UI.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmployeeSeed {
+    pub employee_id: String,
+    pub name: String,
+    pub role: EmployeeRole,
+}
+
+/// The seed set from `employees.toml`:
+///
+/// ```toml
+/// mark_removed = true
+///
+/// [[employees]]
+/// employee_id = "employee_john"
+/// name = "John"
+/// role = "ceo"
+/// ```
+///
+/// `role` must be `ceo`, `hr` or `engineer`. With `mark_removed`, employees seeded earlier but
+/// no longer listed get `seeded = false`; they are never deleted.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeedConfig {
+    pub employees: Vec<EmployeeSeed>,
+    #[serde(default)]
+    pub mark_removed: bool,
+}
+
+impl Default for SeedConfig {
+    fn default() -> Self {
+        let seed = |employee_id: &str, name: &str, role| EmployeeSeed {
+            employee_id: employee_id.to_string(),
+            name: name.to_string(),
+            role,
+        };
+        Self {
+            employees: vec![
+                seed("employee_john", "John", EmployeeRole::Ceo),
+                seed("employee_sarah", "Sarah", EmployeeRole::Hr),
+                seed("employee_bob", "Bob", EmployeeRole::Engineer),
+            ],
+            mark_removed: false,
+        }
+    }
+}
+
+impl SeedConfig {
+    pub fn parse(raw: &str) -> Result<Self> {
+        let config: Self = toml::from_str(raw).context("invalid employee seeds")?;
+        let mut ids = HashSet::new();
+        for emp in &config.employees {
+            if emp.employee_id.trim().is_empty() || emp.name.trim().is_empty() {
+                bail!("employee seeds need a non-empty employee_id and name");
+            }
+            if !ids.insert(emp.employee_id.as_str()) {
+                bail!("employee `{}` is seeded twice", emp.employee_id);
+            }
+        }
+        Ok(config)
+    }
+
+    /// `COS_EMPLOYEES_FILE` (default `employees.toml`). A missing file keeps the built-in seeds.
+    pub fn from_env() -> Result<Self> {
+        let config = crate::app_state::config();
+        let path = Path::new(&config.employees.file);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("read {}", path.display()))?;
+        Self::parse(&raw).with_context(|| path.display().to_string())
+    }
+}
+
+/// `COS_AUTO_ALIAS=1` links employees discovered in email to the seeded employee their display
+/// name matches, at ingest time.
+pub fn auto_alias_enabled() -> bool {
+    crate::app_state::config().employees.auto_alias
+}
+
+/// How an email display name matched a seeded employee's name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameHeuristic {
+    /// Same name, ignoring case and punctuation: "john smith" vs "John Smith".
+    ExactName,
+    /// Same first name: "John Smith" vs "John".
+    FirstName,
+}
+
+impl NameHeuristic {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ExactName => "exact_name",
+            Self::FirstName => "first_name",
+        }
+    }
+
+    /// Stored as `confidence` on the `ALIAS_OF` edge.
+    pub fn confidence(&self) -> f64 {
+        match self {
+            Self::ExactName => 0.9,
+            Self::FirstName => 0.6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameMatch {
+    None,
+    One {
+        employee_id: String,
+        heuristic: NameHeuristic,
+    },
+    /// Several seeded employees match equally well; needs a human to pick.
+    Ambiguous {
+        employee_ids: Vec<String>,
+        heuristic: NameHeuristic,
+    },
+}
+
+fn name_tokens(name: &str) -> Vec<String> {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The seeded employee (`(employee_id, name)` pairs) `display_name` refers to. An exact name
+/// match wins over first-name matches; two or more matches on the best heuristic are ambiguous.
+pub fn match_seeded_name(display_name: &str, seeded: &[(String, String)]) -> NameMatch {
+    let tokens = name_tokens(display_name);
+    let Some(first) = tokens.first() else {
+        return NameMatch::None;
+    };
+    for heuristic in [NameHeuristic::ExactName, NameHeuristic::FirstName] {
+        let employee_ids: Vec<String> = seeded
+            .iter()
+            .filter(|(_, name)| {
+                let seed = name_tokens(name);
+                match heuristic {
+                    NameHeuristic::ExactName => seed == tokens,
+                    NameHeuristic::FirstName => seed.first() == Some(first),
+                }
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        match employee_ids.len() {
+            0 => continue,
+            1 => {
+                let employee_id = employee_ids.into_iter().next().unwrap_or_default();
+                return NameMatch::One { employee_id, heuristic };
+            }
+            _ => return NameMatch::Ambiguous { employee_ids, heuristic },
+        }
+    }
+    NameMatch::None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_need_unique_ids_and_known_roles() {
+        let seeds = SeedConfig::parse(
+            "mark_removed = true\n\n[[employees]]\nemployee_id = \"employee_ada\"\n\
+             name = \"Ada\"\nrole = \"ceo\"\n",
+        )
+        .unwrap();
+        assert!(seeds.mark_removed);
+        assert_eq!(seeds.employees[0].role, EmployeeRole::Ceo);
+
+        let err = |raw: &str| format!("{:#}", SeedConfig::parse(raw).unwrap_err());
+        let twice = "[[employees]]\nemployee_id = \"e\"\nname = \"A\"\nrole = \"hr\"\n";
+        assert!(err(&twice.repeat(2)).contains("seeded twice"));
+        let unnamed = "[[employees]]\nemployee_id = \"e\"\nname = \" \"\nrole = \"hr\"\n";
+        assert!(err(unnamed).contains("non-empty"));
+        let intern = "[[employees]]\nemployee_id = \"e\"\nname = \"A\"\nrole = \"intern\"\n";
+        assert!(err(intern).contains("invalid employee seeds"));
+    }
+}
@@ -0,0 +1,137 @@
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use thiserror::Error;
+
+/// Longest excerpt of an unparseable LLM answer kept in `LlmMalformedOutput`.
+const RAW_EXCERPT_CHARS: usize = 500;
+
+/// What went wrong, as far as a caller of `service`, `writer` or `utils` needs to know.
+/// Attached to `anyhow` errors with `.context(CosError::..)` so the underlying cause stays in
+/// the chain; the API maps the kind to a status code with `CosError::find`.
+#[derive(Debug, Error)]
+pub enum CosError {
+    #[error("language model unavailable")]
+    LlmUnavailable,
+    #[error("language model returned malformed output")]
+    LlmMalformedOutput { raw: String },
+    #[error("graph write failed")]
+    GraphWrite,
+    #[error("graph storage unavailable")]
+    GraphUnavailable,
+    #[error("speech-to-text failed")]
+    SttFailed,
+    #[error("text-to-speech failed")]
+    TtsFailed,
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("{0}")]
+    Validation(String),
+    /// The request no longer applies to the current state, e.g. approving a superseded version.
+    #[error("{0}")]
+    Conflict(String),
+    #[error("request cancelled")]
+    Cancelled,
+    /// An upstream (`openai`, `elevenlabs`, `neo4j`) didn't answer within its timeout.
+    #[error("{0} timed out")]
+    Timeout(&'static str),
+}
+
+impl CosError {
+    /// `LlmMalformedOutput` keeping at most the first `RAW_EXCERPT_CHARS` characters of `raw`,
+    /// redacted since it is returned to the caller.
+    pub fn malformed(raw: &str) -> Self {
+        let raw = crate::redaction::redact(raw);
+        let mut excerpt: String = raw.chars().take(RAW_EXCERPT_CHARS).collect();
+        if raw.chars().nth(RAW_EXCERPT_CHARS).is_some() {
+            excerpt.push('…');
+        }
+        Self::LlmMalformedOutput { raw: excerpt }
+    }
+
+    /// The outermost `CosError` in `e`'s chain, if any.
+    pub fn find(e: &anyhow::Error) -> Option<&CosError> {
+        e.downcast_ref::<CosError>()
+    }
+
+    /// Short machine-readable name, returned as `kind` in API errors.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::LlmUnavailable => "llm_unavailable",
+            Self::LlmMalformedOutput { .. } => "llm_malformed_output",
+            Self::GraphWrite => "graph_write",
+            Self::GraphUnavailable => "graph_unavailable",
+            Self::SttFailed => "stt_failed",
+            Self::TtsFailed => "tts_failed",
+            Self::NotFound(_) => "not_found",
+            Self::Validation(_) => "validation",
+            Self::Conflict(_) => "conflict",
+            Self::Cancelled => "cancelled",
+            Self::Timeout(_) => "upstream_timeout",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::LlmUnavailable | Self::GraphUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::LlmMalformedOutput { .. }
+            | Self::GraphWrite
+            | Self::SttFailed
+            | Self::TtsFailed => StatusCode::BAD_GATEWAY,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Validation(_) => StatusCode::BAD_REQUEST,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            // 499 "client closed request": nobody is waiting for the answer any more.
+            Self::Cancelled => StatusCode::from_u16(499).expect("valid status code"),
+            Self::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+        }
+    }
+}
+
+/// Status code and JSON body for `e`: `{"error", "kind"}` plus `raw` for malformed LLM output.
+/// Errors without a `CosError` are a plain 500.
+pub fn http_error(e: &anyhow::Error) -> (StatusCode, Value) {
+    let Some(kind) = CosError::find(e) else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, json!({ "error": e.to_string() }));
+    };
+    let mut body = json!({ "error": format!("{e:#}"), "kind": kind.kind() });
+    if let CosError::LlmMalformedOutput { raw } = kind {
+        body["raw"] = json!(raw);
+    }
+    (kind.status(), body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_kind_survives_context_and_sets_the_status() {
+        let e = anyhow::anyhow!("connection refused")
+            .context(CosError::GraphUnavailable)
+            .context("loading decisions");
+        let (status, body) = http_error(&e);
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["kind"], "graph_unavailable");
+        assert_eq!(
+            body["error"],
+            "loading decisions: graph storage unavailable: connection refused"
+        );
+
+        let (status, body) = http_error(&anyhow::anyhow!("boom"));
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(body.get("kind").is_none());
+    }
+
+    #[test]
+    fn malformed_output_is_redacted_truncated_and_returned() {
+        let raw = format!("{{\"summary\": \"SSN 123-45-6789\", {}", "x".repeat(600));
+        let e = anyhow::Error::from(CosError::malformed(&raw));
+        let (status, body) = http_error(&e);
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(body["kind"], "llm_malformed_output");
+        let excerpt = body["raw"].as_str().unwrap();
+        assert!(!excerpt.contains("123-45-6789"));
+        assert!(excerpt.ends_with('…'));
+        assert_eq!(excerpt.chars().count(), RAW_EXCERPT_CHARS + 1);
+    }
+}
@@ -0,0 +1,664 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::domain::Event;
+use crate::memory_store::MemoryStore;
+use crate::neo4j::writer::{
+    self, AskOutcome, CompactionSummary, DecisionActivity, DecisionContent, DecisionEmbedding,
+    DecisionEvidence, DecisionStats, EventDecision, GraphContext, GraphUpdateResult,
+    ImpactedDecision, StaleContext, StaleMark, TruthDependent,
+};
+use crate::neo4j::Neo4jClient;
+
+/// `COS_COMPACT_KEEP_LATEST` (default 5, minimum 1): versions kept per object by compaction.
+pub fn compact_keep_latest() -> usize {
+    crate::app_state::config().storage.compact_keep_latest.max(1)
+}
+
+/// The Decision / Truth versioning operations `service` and the OrgBrain need, independent of
+/// where the graph lives. Implemented over Neo4j and over the in-memory store, which doubles as
+/// the fake when no database is available.
+#[async_trait]
+pub trait GraphStore: Send + Sync {
+    /// Version number and content hash of the current version of `truth_id`, if any.
+    async fn current_truth_version(&self, truth_id: &str) -> Result<Option<(i64, Option<String>)>>;
+
+    /// Version and content of the current version of each active truth in `truth_ids`; unknown
+    /// and retired ids are left out.
+    async fn current_truth_contents(
+        &self,
+        truth_ids: &[String],
+    ) -> Result<HashMap<String, (i64, String)>>;
+
+    /// Writes the next version of `decision_id` and returns its number. Reading the current
+    /// version and creating the next one happen atomically. The version is linked `ABOUT`
+    /// `topic` and `BASED_ON` the current version of each truth in `based_on`, and carries the
+    /// rationale, evidence and assumptions in `content`.
+    #[allow(clippy::too_many_arguments)]
+    async fn persist_decision_version(
+        &self,
+        decision_id: String,
+        summary: String,
+        confidence: f64,
+        trigger_events: Vec<Uuid>,
+        agents_involved: Vec<String>,
+        routing: &Value,
+        topic: &str,
+        based_on: &[String],
+        content: &DecisionContent,
+    ) -> Result<(i64, GraphUpdateResult)>;
+
+    /// Writes the next version of `truth_id` and returns its number.
+    #[allow(clippy::too_many_arguments)]
+    async fn persist_truth_version(
+        &self,
+        truth_id: String,
+        kind: String,
+        summary: String,
+        confidence: f64,
+        trigger_events: Vec<Uuid>,
+        agents_involved: Vec<String>,
+        routing: &Value,
+    ) -> Result<(i64, GraphUpdateResult)>;
+
+    /// Writes everything in `outcome` together: if any part fails, none of it is kept.
+    /// Returns the decision version.
+    async fn persist_ask_outcome(&self, outcome: &AskOutcome) -> Result<(i64, GraphUpdateResult)>;
+
+    /// Appends `(role, content)` turns to `employee_id`'s conversation without a decision. Turns
+    /// aren't kept in memory mode.
+    async fn append_conversation_turns(
+        &self,
+        employee_id: &str,
+        turns: &[(String, String)],
+    ) -> Result<()>;
+
+    /// Properties of one decision version, `None` if it doesn't exist (or was compacted away).
+    async fn decision_version(&self, decision_id: &str, version: i64) -> Result<Option<Value>>;
+
+    /// Stores the embedding of a decision version's summary.
+    async fn set_decision_embedding(
+        &self,
+        decision_id: &str,
+        version: i64,
+        embedding: &[f32],
+    ) -> Result<()>;
+
+    /// The current version of every decision with its summary embedding (empty if none).
+    async fn current_decision_embeddings(&self) -> Result<Vec<DecisionEmbedding>>;
+
+    /// The current version of every decision with the activity on its topic since it was written.
+    async fn decision_activity(&self) -> Result<Vec<DecisionActivity>>;
+
+    /// Records the outcome of a staleness check.
+    async fn mark_staleness(&self, marks: &[StaleMark]) -> Result<()>;
+
+    /// What accumulated since the current version of `decision_id`, at most `limit` emails.
+    async fn stale_context(&self, decision_id: &str, limit: usize) -> Result<Option<StaleContext>>;
+
+    /// Decision versions of the last `days` days per day and per topic (the `top_topics` most
+    /// decided), counting only those routed to `agent_id` when given.
+    async fn decision_stats(
+        &self,
+        days: i64,
+        agent_id: Option<&str>,
+        top_topics: usize,
+    ) -> Result<DecisionStats>;
+
+    /// Current truth and decisions related to `topics` (lower-cased), at most `truth_limit` and
+    /// `decision_limit` of each.
+    async fn graph_context(
+        &self,
+        topics: &[String],
+        truth_limit: usize,
+        decision_limit: usize,
+    ) -> Result<GraphContext>;
+
+    /// Decision versions based on any version of `truth_id`.
+    async fn truth_dependents(&self, truth_id: &str, limit: usize) -> Result<Vec<TruthDependent>>;
+
+    /// Retrieved snippets stored on a decision version (the current one without `version`).
+    async fn decision_evidence(
+        &self,
+        decision_id: &str,
+        version: Option<i64>,
+    ) -> Result<Option<DecisionEvidence>>;
+
+    /// Decision versions whose `trigger_events` include `event_id`.
+    async fn event_decisions(&self, event_id: &str, limit: usize) -> Result<Vec<EventDecision>>;
+
+    /// Stores `events` (keyed by id, so storing one twice is harmless) for replay.
+    async fn persist_events(&self, events: &[Event]) -> Result<()>;
+
+    /// Stored events among `event_ids`, oldest first.
+    async fn load_events(&self, event_ids: &[String]) -> Result<Vec<Event>>;
+
+    /// Number of the current version of `decision_id`, `None` if there is no such decision.
+    async fn current_decision_version(&self, decision_id: &str) -> Result<Option<i64>>;
+
+    /// Makes a version written pending (see `AskOutcome::pending`) the decision's current one.
+    /// `None` if there is no such pending version; fails with `CosError::Conflict` if a newer
+    /// version became current in the meantime.
+    async fn promote_decision_version(
+        &self,
+        decision_id: &str,
+        version: i64,
+    ) -> Result<Option<GraphUpdateResult>>;
+
+    /// Current decision versions that relied on `truth_id` or share a topic with one that did.
+    async fn truth_impact(&self, truth_id: &str, limit: usize) -> Result<Vec<ImpactedDecision>>;
+
+    /// Marks `truth_id` inactive so it no longer counts as current truth.
+    async fn retire_truth(&self, truth_id: &str) -> Result<GraphUpdateResult>;
+
+    /// Retires `truth_id` and records that it was merged into `target`.
+    async fn merge_truth(&self, truth_id: &str, target: &str) -> Result<GraphUpdateResult>;
+
+    /// Drops intermediate versions, keeping the first and the newest `keep_latest` of each object.
+    async fn compact_versions(&self, keep_latest: usize) -> Result<CompactionSummary>;
+}
+
+#[async_trait]
+impl GraphStore for Neo4jClient {
+    async fn current_truth_version(&self, truth_id: &str) -> Result<Option<(i64, Option<String>)>> {
+        self.breaker().call(writer::current_truth_version(self.graph(), truth_id)).await
+    }
+
+    async fn current_truth_contents(
+        &self,
+        truth_ids: &[String],
+    ) -> Result<HashMap<String, (i64, String)>> {
+        self.breaker().call(writer::current_truth_contents(self.graph(), truth_ids)).await
+    }
+
+    async fn persist_decision_version(
+        &self,
+        decision_id: String,
+        summary: String,
+        confidence: f64,
+        trigger_events: Vec<Uuid>,
+        agents_involved: Vec<String>,
+        routing: &Value,
+        topic: &str,
+        based_on: &[String],
+        content: &DecisionContent,
+    ) -> Result<(i64, GraphUpdateResult)> {
+        self.breaker()
+            .call(writer::persist_decision_version(
+                self.graph(),
+                decision_id,
+                summary,
+                confidence,
+                trigger_events,
+                agents_involved,
+                routing.clone(),
+                topic.to_string(),
+                based_on.to_vec(),
+                content.clone(),
+            ))
+            .await
+    }
+
+    async fn persist_truth_version(
+        &self,
+        truth_id: String,
+        kind: String,
+        summary: String,
+        confidence: f64,
+        trigger_events: Vec<Uuid>,
+        agents_involved: Vec<String>,
+        routing: &Value,
+    ) -> Result<(i64, GraphUpdateResult)> {
+        self.breaker()
+            .call(writer::persist_truth_version(
+                self.graph(),
+                truth_id,
+                kind,
+                summary,
+                confidence,
+                trigger_events,
+                agents_involved,
+                routing.clone(),
+            ))
+            .await
+    }
+
+    async fn persist_ask_outcome(&self, outcome: &AskOutcome) -> Result<(i64, GraphUpdateResult)> {
+        self.breaker().call(writer::persist_ask_outcome(self.graph(), outcome)).await
+    }
+
+    async fn append_conversation_turns(
+        &self,
+        employee_id: &str,
+        turns: &[(String, String)],
+    ) -> Result<()> {
+        self.breaker()
+            .call(writer::append_conversation_turns(self.graph(), employee_id, turns))
+            .await
+    }
+
+    async fn decision_version(&self, decision_id: &str, version: i64) -> Result<Option<Value>> {
+        self.breaker().call(writer::decision_version(self.graph(), decision_id, version)).await
+    }
+
+    async fn set_decision_embedding(
+        &self,
+        decision_id: &str,
+        version: i64,
+        embedding: &[f32],
+    ) -> Result<()> {
+        self.breaker()
+            .call(writer::set_decision_embedding(self.graph(), decision_id, version, embedding))
+            .await
+    }
+
+    async fn current_decision_embeddings(&self) -> Result<Vec<DecisionEmbedding>> {
+        self.breaker().call(writer::current_decision_embeddings(self.graph())).await
+    }
+
+    async fn decision_activity(&self) -> Result<Vec<DecisionActivity>> {
+        self.breaker().call(writer::decision_activity(self.graph())).await
+    }
+
+    async fn mark_staleness(&self, marks: &[StaleMark]) -> Result<()> {
+        self.breaker().call(writer::mark_staleness(self.graph(), marks)).await
+    }
+
+    async fn stale_context(&self, decision_id: &str, limit: usize) -> Result<Option<StaleContext>> {
+        self.breaker().call(writer::stale_context(self.graph(), decision_id, limit)).await
+    }
+
+    async fn decision_stats(
+        &self,
+        days: i64,
+        agent_id: Option<&str>,
+        top_topics: usize,
+    ) -> Result<DecisionStats> {
+        self.breaker().call(writer::decision_stats(self.graph(), days, agent_id, top_topics)).await
+    }
+
+    async fn graph_context(
+        &self,
+        topics: &[String],
+        truth_limit: usize,
+        decision_limit: usize,
+    ) -> Result<GraphContext> {
+        self.breaker()
+            .call(writer::graph_context(self.graph(), topics, truth_limit, decision_limit))
+            .await
+    }
+
+    async fn truth_dependents(&self, truth_id: &str, limit: usize) -> Result<Vec<TruthDependent>> {
+        self.breaker().call(writer::truth_dependents(self.graph(), truth_id, limit)).await
+    }
+
+    async fn decision_evidence(
+        &self,
+        decision_id: &str,
+        version: Option<i64>,
+    ) -> Result<Option<DecisionEvidence>> {
+        self.breaker()
+            .call(writer::decision_evidence(self.graph(), decision_id, version))
+            .await
+    }
+
+    async fn event_decisions(&self, event_id: &str, limit: usize) -> Result<Vec<EventDecision>> {
+        self.breaker().call(writer::event_decisions(self.graph(), event_id, limit)).await
+    }
+
+    async fn persist_events(&self, events: &[Event]) -> Result<()> {
+        self.breaker().call(writer::persist_events(self.graph(), events)).await
+    }
+
+    async fn load_events(&self, event_ids: &[String]) -> Result<Vec<Event>> {
+        self.breaker().call(writer::load_events(self.graph(), event_ids)).await
+    }
+
+    async fn current_decision_version(&self, decision_id: &str) -> Result<Option<i64>> {
+        self.breaker()
+            .call(writer::current_decision_version(self.graph(), decision_id))
+            .await
+    }
+
+    async fn promote_decision_version(
+        &self,
+        decision_id: &str,
+        version: i64,
+    ) -> Result<Option<GraphUpdateResult>> {
+        self.breaker()
+            .call(writer::promote_decision_version(self.graph(), decision_id, version))
+            .await
+    }
+
+    async fn truth_impact(&self, truth_id: &str, limit: usize) -> Result<Vec<ImpactedDecision>> {
+        self.breaker().call(writer::truth_impact(self.graph(), truth_id, limit)).await
+    }
+
+    async fn retire_truth(&self, truth_id: &str) -> Result<GraphUpdateResult> {
+        self.breaker().call(writer::retire_truth(self.graph(), truth_id)).await
+    }
+
+    async fn merge_truth(&self, truth_id: &str, target: &str) -> Result<GraphUpdateResult> {
+        self.breaker().call(writer::merge_truth(self.graph(), truth_id, target)).await
+    }
+
+    async fn compact_versions(&self, keep_latest: usize) -> Result<CompactionSummary> {
+        self.breaker().call(writer::compact_versions(self.graph(), keep_latest)).await
+    }
+}
+
+#[async_trait]
+impl GraphStore for Mutex<MemoryStore> {
+    async fn current_truth_version(&self, truth_id: &str) -> Result<Option<(i64, Option<String>)>> {
+        Ok(self
+            .lock()
+            .await
+            .current_truth_version(truth_id)
+            .map(|(v, h)| (v, Some(h))))
+    }
+
+    async fn current_truth_contents(
+        &self,
+        truth_ids: &[String],
+    ) -> Result<HashMap<String, (i64, String)>> {
+        Ok(self.lock().await.current_truth_contents(truth_ids))
+    }
+
+    async fn persist_decision_version(
+        &self,
+        decision_id: String,
+        summary: String,
+        confidence: f64,
+        trigger_events: Vec<Uuid>,
+        agents_involved: Vec<String>,
+        routing: &Value,
+        topic: &str,
+        based_on: &[String],
+        content: &DecisionContent,
+    ) -> Result<(i64, GraphUpdateResult)> {
+        self.lock().await.persist_decision_version(
+            decision_id,
+            summary,
+            confidence,
+            trigger_events,
+            agents_involved,
+            routing,
+            topic,
+            based_on,
+            content,
+        )
+    }
+
+    async fn persist_truth_version(
+        &self,
+        truth_id: String,
+        kind: String,
+        summary: String,
+        confidence: f64,
+        trigger_events: Vec<Uuid>,
+        agents_involved: Vec<String>,
+        routing: &Value,
+    ) -> Result<(i64, GraphUpdateResult)> {
+        self.lock().await.persist_truth_version(
+            truth_id,
+            kind,
+            summary,
+            confidence,
+            trigger_events,
+            agents_involved,
+            routing,
+        )
+    }
+
+    async fn persist_ask_outcome(&self, outcome: &AskOutcome) -> Result<(i64, GraphUpdateResult)> {
+        self.lock().await.persist_ask_outcome(outcome)
+    }
+
+    async fn append_conversation_turns(
+        &self,
+        _employee_id: &str,
+        _turns: &[(String, String)],
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    async fn decision_version(&self, decision_id: &str, version: i64) -> Result<Option<Value>> {
+        Ok(self.lock().await.decision_version(decision_id, version))
+    }
+
+    async fn set_decision_embedding(
+        &self,
+        decision_id: &str,
+        version: i64,
+        embedding: &[f32],
+    ) -> Result<()> {
+        self.lock().await.set_decision_embedding(decision_id, version, embedding);
+        Ok(())
+    }
+
+    async fn current_decision_embeddings(&self) -> Result<Vec<DecisionEmbedding>> {
+        Ok(self.lock().await.current_decision_embeddings())
+    }
+
+    async fn decision_activity(&self) -> Result<Vec<DecisionActivity>> {
+        Ok(self.lock().await.decision_activity())
+    }
+
+    async fn mark_staleness(&self, marks: &[StaleMark]) -> Result<()> {
+        self.lock().await.mark_staleness(marks);
+        Ok(())
+    }
+
+    async fn stale_context(&self, decision_id: &str, _limit: usize) -> Result<Option<StaleContext>> {
+        Ok(self.lock().await.stale_context(decision_id))
+    }
+
+    async fn decision_stats(
+        &self,
+        days: i64,
+        agent_id: Option<&str>,
+        top_topics: usize,
+    ) -> Result<DecisionStats> {
+        Ok(self.lock().await.decision_stats(days, agent_id, top_topics))
+    }
+
+    async fn graph_context(
+        &self,
+        topics: &[String],
+        truth_limit: usize,
+        decision_limit: usize,
+    ) -> Result<GraphContext> {
+        Ok(self.lock().await.graph_context(topics, truth_limit, decision_limit))
+    }
+
+    async fn truth_dependents(&self, truth_id: &str, limit: usize) -> Result<Vec<TruthDependent>> {
+        Ok(self.lock().await.truth_dependents(truth_id, limit))
+    }
+
+    async fn decision_evidence(
+        &self,
+        decision_id: &str,
+        version: Option<i64>,
+    ) -> Result<Option<DecisionEvidence>> {
+        Ok(self.lock().await.decision_evidence(decision_id, version))
+    }
+
+    async fn event_decisions(&self, event_id: &str, limit: usize) -> Result<Vec<EventDecision>> {
+        Ok(self.lock().await.event_decisions(event_id, limit))
+    }
+
+    async fn persist_events(&self, events: &[Event]) -> Result<()> {
+        self.lock().await.persist_events(events);
+        Ok(())
+    }
+
+    async fn load_events(&self, event_ids: &[String]) -> Result<Vec<Event>> {
+        Ok(self.lock().await.load_events(event_ids))
+    }
+
+    async fn current_decision_version(&self, decision_id: &str) -> Result<Option<i64>> {
+        Ok(self.lock().await.current_decision_version(decision_id))
+    }
+
+    async fn promote_decision_version(
+        &self,
+        decision_id: &str,
+        version: i64,
+    ) -> Result<Option<GraphUpdateResult>> {
+        self.lock().await.promote_decision_version(decision_id, version)
+    }
+
+    async fn truth_impact(&self, truth_id: &str, limit: usize) -> Result<Vec<ImpactedDecision>> {
+        Ok(self.lock().await.truth_impact(truth_id, limit))
+    }
+
+    async fn retire_truth(&self, truth_id: &str) -> Result<GraphUpdateResult> {
+        Ok(self.lock().await.retire_truth(truth_id))
+    }
+
+    async fn merge_truth(&self, truth_id: &str, target: &str) -> Result<GraphUpdateResult> {
+        Ok(self.lock().await.merge_truth(truth_id, target))
+    }
+
+    async fn compact_versions(&self, keep_latest: usize) -> Result<CompactionSummary> {
+        Ok(self.lock().await.compact_versions(keep_latest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    async fn decide(store: &dyn GraphStore, summary: &str, based_on: &[String]) -> i64 {
+        store
+            .persist_decision_version(
+                "remote".to_string(),
+                summary.to_string(),
+                0.7,
+                Vec::new(),
+                vec!["employee_sarah".to_string()],
+                &json!({ "employee_sarah": "full" }),
+                "Remote work",
+                based_on,
+                &DecisionContent::default(),
+            )
+            .await
+            .unwrap()
+            .0
+    }
+
+    #[tokio::test]
+    async fn parallel_decision_writes_get_distinct_versions() {
+        let store = Arc::new(Mutex::new(MemoryStore::new()));
+        let writes = (0..10).map(|i| {
+            let store = Arc::clone(&store);
+            tokio::spawn(async move { decide(store.as_ref(), &format!("draft {i}"), &[]).await })
+        });
+        let mut versions = Vec::new();
+        for write in writes.collect::<Vec<_>>() {
+            versions.push(write.await.unwrap());
+        }
+        versions.sort_unstable();
+        assert_eq!(versions, (1..=10).collect::<Vec<_>>());
+        let (objects, versions) = store.lock().await.current_decisions(10);
+        assert_eq!((objects.len(), versions.len()), (1, 1), "a single CURRENT pointer");
+        assert!(versions[0].id.ends_with(":v10"), "{:?}", versions[0].id);
+    }
+
+    /// Against a live Neo4j (`NEO4J_URI`, `NEO4J_USER`, `NEO4J_PASSWORD`, or `cos.toml`).
+    #[tokio::test]
+    #[ignore = "needs a running Neo4j"]
+    async fn parallel_neo4j_decision_writes_get_distinct_versions() {
+        let settings = crate::config::CosConfig::load().unwrap().neo4j;
+        let client = Neo4jClient::connect(&settings).await.unwrap();
+        client.run_migrations().await.unwrap();
+        let decision_id = "decision_parallel_test";
+        let writes = (0..10).map(|i| {
+            let client = client.clone();
+            tokio::spawn(async move {
+                let store: &dyn GraphStore = &client;
+                store
+                    .persist_decision_version(
+                        decision_id.to_string(),
+                        format!("draft {i}"),
+                        0.8,
+                        vec![],
+                        vec![],
+                        &json!({}),
+                        "",
+                        &[],
+                        &DecisionContent::default(),
+                    )
+                    .await
+                    .unwrap()
+                    .0
+            })
+        });
+        let mut versions = Vec::new();
+        for write in writes.collect::<Vec<_>>() {
+            versions.push(write.await.unwrap());
+        }
+        versions.sort_unstable();
+        assert_eq!(versions, (1..=10).collect::<Vec<_>>());
+
+        let graph = client.graph();
+        let mut rows = graph
+            .execute(
+                neo4rs::query(
+                    "MATCH (:Decision {decision_id: $id})-[:CURRENT]->(dv:DecisionVersion) \
+                     RETURN dv.version AS version",
+                )
+                .param("id", decision_id),
+            )
+            .await
+            .unwrap();
+        let mut current = Vec::new();
+        while let Some(row) = rows.next().await.unwrap() {
+            current.push(row.get::<i64>("version").unwrap());
+        }
+        assert_eq!(current, vec![10], "a single CURRENT pointer");
+
+        let cleanup = neo4rs::query(
+            "MATCH (n) WHERE n.decision_id = 'decision_parallel_test' DETACH DELETE n",
+        );
+        graph.run(cleanup).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_memory_store_versions_through_the_trait() {
+        let store: Box<dyn GraphStore> = Box::new(Mutex::new(MemoryStore::new()));
+        let (v, _) = store
+            .persist_truth_version(
+                "policy".to_string(),
+                "policy".to_string(),
+                "two office days".to_string(),
+                1.0,
+                Vec::new(),
+                Vec::new(),
+                &json!({}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(v, 1);
+        let policy = vec!["policy".to_string(), "unknown".to_string()];
+        let contents = store.current_truth_contents(&policy).await.unwrap();
+        assert_eq!(contents.len(), 1);
+        assert_eq!(contents["policy"], (1, "two office days".to_string()));
+
+        assert_eq!(decide(store.as_ref(), "allow remote Fridays", &policy).await, 1);
+        assert_eq!(decide(store.as_ref(), "allow remote Mondays", &[]).await, 2);
+        assert_eq!(store.current_decision_version("remote").await.unwrap(), Some(2));
+        let v1 = store.decision_version("remote", 1).await.unwrap().unwrap();
+        assert_eq!(v1["summary"], "allow remote Fridays");
+        assert!(store.decision_version("remote", 3).await.unwrap().is_none());
+
+        let dependents = store.truth_dependents("policy", 10).await.unwrap();
+        assert_eq!(dependents.len(), 1);
+    }
+}
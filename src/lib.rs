@@ -0,0 +1,42 @@
+//! AI Chief of Staff backend: the HTTP API, the terminal flow and the storage behind them.
+//! The `cos` binary (`main.rs`) is a command line over these modules.
+
+pub mod state;
+pub mod utils;
+pub mod nodes;
+pub mod domain;
+pub mod employees;
+pub mod error;
+pub mod runtime;
+pub mod app_state;
+pub mod config;
+pub mod rag;
+pub mod neo4j;
+pub mod graph_store;
+pub mod api;
+pub mod service;
+pub mod memory_store;
+pub mod redaction;
+pub mod triage;
+pub mod crypto;
+pub mod audit;
+pub mod maintenance;
+pub mod roles;
+pub mod llm;
+pub mod llm_schema;
+pub mod llm_log;
+pub mod repl;
+pub mod review;
+pub mod retrieval;
+pub mod merge;
+pub mod org_updates;
+pub mod truth_context;
+pub mod version_diff;
+pub mod report;
+pub mod visibility;
+pub mod similar;
+pub mod staleness;
+pub mod replay;
+pub mod script;
+pub mod webhooks;
+pub mod usage;
@@ -0,0 +1,415 @@
+use anyhow::Result;
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs,
+    ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs,
+};
+use async_openai::config::{AzureConfig, Config, OpenAIConfig};
+use async_openai::Client;
+use async_trait::async_trait;
+use once_cell::sync::OnceCell;
+use serde_json::json;
+
+use crate::app_state::config;
+
+/// Anything that can answer a (system, user) prompt pair.
+#[async_trait]
+pub trait ChatProvider: Send + Sync {
+    async fn chat(&self, system: &str, user: &str) -> Result<String>;
+}
+
+/// OpenAI chat completions (`OPENAI_API_KEY`, `OPENAI_MODEL`, then `COS_MODEL_FALLBACKS`).
+pub struct OpenAiChat;
+
+/// `OPENAI_MODEL` (default gpt-4o-mini) followed by the comma-separated `COS_MODEL_FALLBACKS`,
+/// without duplicates.
+pub fn model_chain() -> Vec<String> {
+    let llm = &config().llm;
+    let mut chain = vec![llm.model.clone()];
+    for model in &llm.fallbacks {
+        if !chain.contains(model) {
+            chain.push(model.clone());
+        }
+    }
+    chain
+}
+
+/// Calls `call` with each model in turn, moving on only when a model fails with a transient
+/// error (rate limit, 5xx, timeout). A permanent error, or a failure of the last model, is
+/// returned with the list of models attempted.
+pub async fn with_fallbacks<F, Fut>(models: &[String], mut call: F) -> Result<String>
+where
+    F: FnMut(String) -> Fut,
+    Fut: std::future::Future<Output = Result<String>>,
+{
+    let mut attempted: Vec<&str> = Vec::new();
+    for (idx, model) in models.iter().enumerate() {
+        attempted.push(model);
+        match call(model.clone()).await {
+            Ok(out) => return Ok(out),
+            Err(e) => {
+                let next = models.get(idx + 1);
+                match next {
+                    Some(next) if is_transient(&e) => {
+                        eprintln!("model {model} failed ({e:#}); falling back to {next}");
+                    }
+                    _ => {
+                        return Err(e.context(format!("models attempted: {}", attempted.join(", "))));
+                    }
+                }
+            }
+        }
+    }
+    anyhow::bail!("no chat model configured")
+}
+
+pub const OPENAI_DEFAULT_BASE: &str = "https://api.openai.com/v1";
+
+/// Where OpenAI calls go. `OPENAI_BASE_URL` (e.g. a proxy) replaces the public endpoint; with
+/// `OPENAI_API_TYPE=azure` it is the Azure OpenAI resource endpoint instead.
+#[derive(Debug, Clone)]
+pub enum OpenAiEndpoint {
+    OpenAi { base: String },
+    Azure { base: String, api_version: String },
+}
+
+pub fn openai_endpoint() -> OpenAiEndpoint {
+    let config = config();
+    let llm = &config.llm;
+    match llm.base_url.clone() {
+        Some(base) if llm.api_type == "azure" => OpenAiEndpoint::Azure {
+            base,
+            api_version: llm.azure_api_version.clone(),
+        },
+        base => OpenAiEndpoint::OpenAi {
+            base: base.unwrap_or_else(|| OPENAI_DEFAULT_BASE.to_string()),
+        },
+    }
+}
+
+/// The Azure deployment serving `model`: `deployment` when configured, else a deployment
+/// named after the model.
+pub fn azure_deployment(deployment: Option<&str>, model: &str) -> String {
+    deployment.unwrap_or(model).to_string()
+}
+
+/// The configured `OPENAI_API_KEY`, or empty when unset.
+pub fn openai_api_key() -> String {
+    config().llm.api_key.clone().unwrap_or_default()
+}
+
+impl OpenAiChat {
+    async fn chat_with_model(model: String, system: &str, user: &str) -> Result<String> {
+        match openai_endpoint() {
+            OpenAiEndpoint::OpenAi { base } => {
+                let config = OpenAIConfig::new().with_api_base(base).with_api_key(openai_api_key());
+                let client = Client::with_config(config);
+                Self::complete(&client, model, system, user).await
+            }
+            OpenAiEndpoint::Azure { base, api_version } => {
+                let deployment = config().llm.azure_deployment.clone();
+                let config = AzureConfig::new()
+                    .with_api_base(base)
+                    .with_api_version(api_version)
+                    .with_api_key(openai_api_key())
+                    .with_deployment_id(azure_deployment(deployment.as_deref(), &model));
+                Self::complete(&Client::with_config(config), model, system, user).await
+            }
+        }
+    }
+
+    async fn complete<C: Config>(
+        client: &Client<C>,
+        model: String,
+        system: &str,
+        user: &str,
+    ) -> Result<String> {
+        let system_msg: ChatCompletionRequestMessage =
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(system)
+                .build()?
+                .into();
+        let user_msg: ChatCompletionRequestMessage = ChatCompletionRequestUserMessageArgs::default()
+            .content(user)
+            .build()?
+            .into();
+
+        // Built the same way for every model in the chain, so request settings carry across.
+        let req = CreateChatCompletionRequestArgs::default()
+            .model(model)
+            .messages(vec![system_msg, user_msg])
+            .build()?;
+
+        let resp = client.chat().create(req).await?;
+        if let Some(usage) = &resp.usage {
+            crate::usage::record(
+                system,
+                usage.prompt_tokens.into(),
+                usage.completion_tokens.into(),
+            );
+        }
+        let content = resp
+            .choices
+            .first()
+            .and_then(|c| c.message.content.clone())
+            .unwrap_or_default();
+        Ok(content)
+    }
+}
+
+#[async_trait]
+impl ChatProvider for OpenAiChat {
+    async fn chat(&self, system: &str, user: &str) -> Result<String> {
+        with_fallbacks(&model_chain(), |model| Self::chat_with_model(model, system, user)).await
+    }
+}
+
+/// Deterministic stand-in used with `COS_OFFLINE=1`. Recognises the prompts in this crate by
+/// their opening line and returns canned output of the shape each caller parses.
+pub struct OfflineChat;
+
+/// FNV-1a, so the same input always yields the same decision id.
+fn stable_hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn offline_topic(user: &str) -> String {
+    let words: Vec<String> = user
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() > 2)
+        .take(3)
+        .map(|w| w.to_lowercase())
+        .collect();
+    if words.is_empty() {
+        "general".to_string()
+    } else {
+        words.join(" ")
+    }
+}
+
+#[async_trait]
+impl ChatProvider for OfflineChat {
+    async fn chat(&self, system: &str, user: &str) -> Result<String> {
+        let out = if system.starts_with("You are an EmployeeAgent.") {
+            json!({
+                "event_type": "update",
+                "topic": offline_topic(user),
+                "confidence": 0.8,
+                "private_note": "offline mode"
+            })
+        } else if system.starts_with("You are the OrgBrain.") {
+            let h = stable_hash(user);
+            json!({
+                "decision_id": format!("offline-{:016x}", h),
+                "decision": "offline decision",
+                "summary": "Offline summary of the latest events.",
+                "rationale": "Generated by the offline chat provider.",
+                "evidence": [],
+                "assumptions": ["COS_OFFLINE is set"],
+                "response_text": "Noted (offline mode).",
+                "confidence": 0.8,
+                "routing": {
+                    "employee_john": "full",
+                    "employee_sarah": "summary",
+                    "employee_bob": "summary"
+                },
+                "org_updates": {}
+            })
+        } else if system.starts_with("You are the Reviewer.") {
+            json!({ "verdict": "approve", "notes": [], "confidence": 0.8 })
+        } else if system.starts_with("You triage corporate email.") {
+            json!({ "urgency": 0.0, "sentiment": "neutral" })
+        } else {
+            // Free-text prompts (e.g. summaries): echo a bounded prefix of the input.
+            return Ok(user.chars().take(500).collect());
+        };
+        Ok(out.to_string())
+    }
+}
+
+pub fn offline_mode() -> bool {
+    config().llm.offline
+}
+
+/// Whether real OpenAI calls are possible: not offline and `OPENAI_API_KEY` is set.
+pub fn openai_configured() -> bool {
+    config().openai_configured()
+}
+
+static CHAT_PROVIDER: OnceCell<Box<dyn ChatProvider>> = OnceCell::new();
+
+/// The process-wide provider: offline stub with `COS_OFFLINE=1`, OpenAI otherwise.
+pub fn chat_provider() -> &'static dyn ChatProvider {
+    CHAT_PROVIDER
+        .get_or_init(|| {
+            if offline_mode() {
+                Box::new(OfflineChat)
+            } else {
+                Box::new(OpenAiChat)
+            }
+        })
+        .as_ref()
+}
+
+/// Installs a custom provider (e.g. in tests). Fails once a provider has been used.
+pub fn set_chat_provider(provider: Box<dyn ChatProvider>) -> Result<()> {
+    CHAT_PROVIDER
+        .set(provider)
+        .map_err(|_| anyhow::anyhow!("chat provider already initialized"))
+}
+
+fn is_transient_status(status: u16) -> bool {
+    status == 408 || status == 429 || (500..600).contains(&status)
+}
+
+fn is_transient_reqwest(e: &reqwest::Error) -> bool {
+    e.is_timeout() || e.is_connect() || e.status().is_some_and(|s| is_transient_status(s.as_u16()))
+}
+
+/// Whether a failed upstream call is worth retrying: timeouts, connection failures, 408/429
+/// and 5xx responses, and OpenAI rate-limit / server errors. Anything else (bad request,
+/// auth, exhausted quota, unparseable output) is permanent.
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    use async_openai::error::OpenAIError;
+
+    err.chain().any(|cause| {
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            return is_transient_reqwest(e);
+        }
+        if let Some(e) = cause.downcast_ref::<OpenAIError>() {
+            return match e {
+                OpenAIError::Reqwest(inner) => is_transient_reqwest(inner),
+                OpenAIError::ApiError(api) => [api.code.as_deref(), api.r#type.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .any(|c| matches!(c, "rate_limit_exceeded" | "server_error" | "overloaded_error")),
+                _ => false,
+            };
+        }
+        if cause.downcast_ref::<tokio::time::error::Elapsed>().is_some() {
+            return true;
+        }
+        cause.downcast_ref::<std::io::Error>().is_some_and(|e| {
+            matches!(
+                e.kind(),
+                std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionRefused
+                    | std::io::ErrorKind::ConnectionAborted
+            )
+        })
+    })
+}
+
+/// A few silent MPEG-1 Layer III frames (128 kbps, 44.1 kHz), for offline TTS.
+pub fn silent_mp3() -> Vec<u8> {
+    const FRAME_LEN: usize = 417;
+    let mut out = Vec::with_capacity(FRAME_LEN * 4);
+    for _ in 0..4 {
+        let mut frame = vec![0u8; FRAME_LEN];
+        frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0xC4]);
+        out.extend_from_slice(&frame);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_schema::{validate, EMPLOYEE_EVENT_SCHEMA, ORG_DECISION_SCHEMA};
+    use serde_json::Value;
+
+    async fn offline(system: &str, user: &str) -> Value {
+        serde_json::from_str(&OfflineChat.chat(system, user).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn offline_answers_match_the_prompt_schemas() {
+        let event = offline("You are an EmployeeAgent.\n...", "We should freeze hiring").await;
+        assert!(validate(&EMPLOYEE_EVENT_SCHEMA, &event).is_empty(), "{event}");
+        assert_eq!(event["topic"], "should freeze hiring");
+
+        let decision = offline("You are the OrgBrain.\n...", "{\"events\":[]}").await;
+        assert!(validate(&ORG_DECISION_SCHEMA, &decision).is_empty(), "{decision}");
+
+        let triage = offline("You triage corporate email.\n...", "Subject: hi").await;
+        assert_eq!(triage["sentiment"], "neutral");
+    }
+
+    #[tokio::test]
+    async fn offline_decision_ids_are_stable_per_input() {
+        let id = |user: &'static str| async move {
+            offline("You are the OrgBrain.", user).await["decision_id"].clone()
+        };
+        assert_eq!(id("same events").await, id("same events").await);
+        assert_ne!(id("same events").await, id("other events").await);
+    }
+
+    #[tokio::test]
+    async fn offline_free_text_echoes_a_bounded_prefix() {
+        let long = "x".repeat(2_000);
+        let out = OfflineChat.chat("Summarize this thread.", &long).await.unwrap();
+        assert_eq!(out.len(), 500);
+        assert_eq!(offline_topic("a, b"), "general");
+    }
+
+    fn api_error(code: &str) -> anyhow::Error {
+        async_openai::error::OpenAIError::ApiError(async_openai::error::ApiError {
+            message: "upstream".into(),
+            r#type: None,
+            param: None,
+            code: Some(code.into()),
+        })
+        .into()
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_told_apart_from_permanent_ones() {
+        let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        assert!(is_transient(&anyhow::Error::from(reset).context("calling OpenAI")));
+        let elapsed = tokio::time::timeout(std::time::Duration::ZERO, std::future::pending::<()>())
+            .await
+            .unwrap_err();
+        assert!(is_transient(&elapsed.into()));
+        assert!(is_transient(&api_error("rate_limit_exceeded")));
+        assert!(is_transient_status(429) && is_transient_status(503));
+
+        assert!(!is_transient(&api_error("insufficient_quota")));
+        assert!(!is_transient(&anyhow::anyhow!("expected JSON, got prose")));
+        assert!(!is_transient_status(400) && !is_transient_status(401));
+    }
+
+    /// Models tried so far, and a stub call failing with `errors[model]` or answering the model.
+    async fn run_chain(models: &[&str], errors: &[(&str, &str)]) -> (Result<String>, Vec<String>) {
+        let models: Vec<String> = models.iter().map(|m| m.to_string()).collect();
+        let tried = std::sync::Mutex::new(Vec::new());
+        let result = with_fallbacks(&models, |model| {
+            tried.lock().unwrap().push(model.clone());
+            let error = errors.iter().find(|(m, _)| *m == model).map(|(_, code)| api_error(code));
+            async move { error.map_or(Ok(model), Err) }
+        })
+        .await;
+        (result, tried.into_inner().unwrap())
+    }
+
+    #[tokio::test]
+    async fn transient_failures_fall_back_to_the_next_model() {
+        let (out, tried) =
+            run_chain(&["gpt-a", "gpt-b", "gpt-c"], &[("gpt-a", "rate_limit_exceeded")]).await;
+        assert_eq!(out.unwrap(), "gpt-b");
+        assert_eq!(tried, vec!["gpt-a", "gpt-b"]);
+    }
+
+    #[tokio::test]
+    async fn permanent_or_last_failures_name_the_models_attempted() {
+        let (out, tried) = run_chain(&["gpt-a", "gpt-b"], &[("gpt-a", "invalid_api_key")]).await;
+        assert_eq!(tried, vec!["gpt-a"]);
+        assert!(format!("{:#}", out.unwrap_err()).starts_with("models attempted: gpt-a:"));
+
+        let errors = [("gpt-a", "server_error"), ("gpt-b", "server_error")];
+        let (out, tried) = run_chain(&["gpt-a", "gpt-b"], &errors).await;
+        assert_eq!(tried, vec!["gpt-a", "gpt-b"]);
+        assert!(out.unwrap_err().to_string().contains("gpt-a, gpt-b"));
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::json;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+tokio::task_local! {
+    /// The API request an LLM call is made for, set by `scope`.
+    static REQUEST_ID: uuid::Uuid;
+}
+
+/// `COS_LLM_LOG_DIR`: when set, every chat call's prompts and raw completion are written there
+/// as one JSON file. Off by default, since prompts carry email content and private notes.
+fn log_dir() -> Option<PathBuf> {
+    crate::app_state::config().llm.log_dir.as_ref().map(PathBuf::from)
+}
+
+/// `COS_LLM_LOG_REDACT_PRIVATE=1` replaces `private_note` values in logged text.
+fn redact_private() -> bool {
+    crate::app_state::config().llm.log_redact_private
+}
+
+static PRIVATE_NOTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#""private_note"\s*:\s*"(?:[^"\\]|\\.)*""#).unwrap());
+
+fn strip_private(text: &str) -> String {
+    PRIVATE_NOTE
+        .replace_all(text, r#""private_note": "[redacted]""#)
+        .into_owned()
+}
+
+/// Runs `fut` with LLM calls logged under `request_id` (when given).
+pub async fn scope<F: Future>(request_id: Option<uuid::Uuid>, fut: F) -> F::Output {
+    match request_id {
+        Some(id) => REQUEST_ID.scope(id, fut).await,
+        None => fut.await,
+    }
+}
+
+/// Writes one chat call to `COS_LLM_LOG_DIR` as `{timestamp}_{request_id}_{call}.json`. Calls
+/// outside a request (e.g. the OrgBrain worker) use `none` as the request id. Failures to write
+/// are logged and otherwise ignored.
+pub async fn record(system: &str, user: &str, outcome: &Result<String>, elapsed_ms: u128) {
+    let Some(dir) = log_dir() else {
+        return;
+    };
+    record_to(&dir, redact_private(), system, user, outcome, elapsed_ms).await;
+}
+
+async fn record_to(
+    dir: &Path,
+    redact: bool,
+    system: &str,
+    user: &str,
+    outcome: &Result<String>,
+    elapsed_ms: u128,
+) {
+    let request_id = REQUEST_ID.try_with(|id| *id).ok();
+    let clean = |s: &str| if redact { strip_private(s) } else { s.to_string() };
+    let (completion, error) = match outcome {
+        Ok(out) => (Some(clean(out)), None),
+        Err(e) => (None, Some(format!("{e:#}"))),
+    };
+    let now = chrono::Utc::now();
+    let entry = json!({
+        "request_id": request_id,
+        "at": now.to_rfc3339(),
+        "elapsed_ms": elapsed_ms as u64,
+        "system": clean(system),
+        "user": clean(user),
+        "completion": completion,
+        "error": error,
+    });
+
+    let call_id = uuid::Uuid::new_v4().simple().to_string();
+    let name = format!(
+        "{}_{}_{}.json",
+        now.format("%Y%m%dT%H%M%S%.3fZ"),
+        request_id.map(|id| id.to_string()).unwrap_or_else(|| "none".to_string()),
+        &call_id[..8]
+    );
+    let body = serde_json::to_vec_pretty(&entry).unwrap_or_default();
+    let written = async {
+        tokio::fs::create_dir_all(dir).await?;
+        tokio::fs::write(dir.join(name), body).await
+    };
+    if let Err(e) = written.await {
+        eprintln!("LLM log ({}): {e}", dir.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn private_notes_are_redacted_in_logged_text() {
+        let text = r#"{"topic": "offsite", "private_note": "I \"hate\" offsites"}"#;
+        assert_eq!(
+            strip_private(text),
+            r#"{"topic": "offsite", "private_note": "[redacted]"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn calls_are_logged_one_file_each_under_their_request() {
+        let dir = std::env::temp_dir().join(format!("cos-llm-log-{}", uuid::Uuid::new_v4()));
+        let request_id = uuid::Uuid::new_v4();
+        let completion = Ok(r#"{"private_note": "secret"}"#.to_string());
+        let call = record_to(&dir, true, "You are an EmployeeAgent.", "hi", &completion, 12);
+        scope(Some(request_id), call).await;
+        let failure = Err(anyhow::anyhow!("rate limited"));
+        record_to(&dir, false, "You are the OrgBrain.", "events", &failure, 3).await;
+
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            let body: serde_json::Value =
+                serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+            entries.push((name, body));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+        entries.sort_by_key(|(_, body)| body["system"].as_str().unwrap_or("").to_string());
+
+        let (name, ok) = &entries[0];
+        assert!(name.contains(&format!("_{request_id}_")), "{name}");
+        assert_eq!(ok["completion"], r#"{"private_note": "[redacted]"}"#);
+        assert_eq!(ok["elapsed_ms"], 12);
+        let (name, failed) = &entries[1];
+        assert!(name.contains("_none_"), "{name}");
+        assert_eq!(failed["error"], "rate limited");
+        assert!(failed["completion"].is_null());
+    }
+}
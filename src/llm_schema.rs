@@ -0,0 +1,242 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::future::Future;
+
+use crate::error::CosError;
+
+/// The EmployeeAgent's event, as its prompt asks for it.
+pub static EMPLOYEE_EVENT_SCHEMA: Lazy<Value> = Lazy::new(|| {
+    json!({
+        "type": "object",
+        "required": ["event_type", "topic", "confidence", "private_note"],
+        "properties": {
+            "event_type": {
+                "type": "string",
+                "enum": ["decision_signal", "update", "concern", "clarification"]
+            },
+            "topic": { "type": "string" },
+            "confidence": { "type": "number", "minimum": 0, "maximum": 1 },
+            "private_note": { "type": "string" }
+        }
+    })
+});
+
+/// The OrgBrain's decision, as its prompt asks for it.
+pub static ORG_DECISION_SCHEMA: Lazy<Value> = Lazy::new(|| {
+    json!({
+        "type": "object",
+        "required": [
+            "decision", "summary", "rationale", "evidence", "assumptions", "response_text",
+            "confidence", "routing", "org_updates"
+        ],
+        "properties": {
+            "decision_id": { "type": "string" },
+            "decision": { "type": "string" },
+            "summary": { "type": "string" },
+            "rationale": { "type": "string" },
+            "evidence": { "type": "array", "items": { "type": "string" } },
+            "assumptions": { "type": "array", "items": { "type": "string" } },
+            "response_text": { "type": "string" },
+            "confidence": { "type": "number", "minimum": 0, "maximum": 1 },
+            "routing": {
+                "type": "object",
+                "additionalProperties": { "enum": ["full", "summary", "none"] }
+            },
+            "needs_clarification": { "type": "boolean" },
+            "clarifying_question": { "type": "string" },
+            "org_updates": {
+                "type": "object",
+                "additionalProperties": {
+                    "anyOf": [
+                        { "type": "string" },
+                        {
+                            "type": "object",
+                            "required": ["op"],
+                            "properties": {
+                                "op": { "enum": ["update", "retire", "merge"] },
+                                "content": { "type": "string" },
+                                "target": { "type": "string" }
+                            }
+                        }
+                    ]
+                }
+            }
+        }
+    })
+});
+
+/// What to do when parsed LLM JSON doesn't match its schema (`COS_LLM_SCHEMA`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SchemaMode {
+    /// Ask once more with the errors appended to the prompt (default).
+    Retry,
+    /// Log the errors and use the output as is.
+    Warn,
+    /// Don't validate.
+    Off,
+}
+
+pub fn schema_mode() -> SchemaMode {
+    match crate::app_state::config().llm.schema.as_str() {
+        "warn" => SchemaMode::Warn,
+        "off" | "0" | "false" => SchemaMode::Off,
+        _ => SchemaMode::Retry,
+    }
+}
+
+/// Errors of `value` against `schema`, as `path: message` with a `$.key[0]` style path; empty
+/// when it conforms. A schema that doesn't compile is reported as one error.
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let validator = match jsonschema::validator_for(schema) {
+        Ok(v) => v,
+        Err(e) => return vec![format!("invalid schema: {e}")],
+    };
+    validator
+        .iter_errors(value)
+        .map(|e| format!("{}: {e}", json_path(&e.instance_path.to_string())))
+        .collect()
+}
+
+/// `/routing/employee_1` as `$.routing.employee_1`, and `/evidence/0` as `$.evidence[0]`.
+fn json_path(pointer: &str) -> String {
+    let mut path = String::from("$");
+    for segment in pointer.split('/').skip(1) {
+        let segment = segment.replace("~1", "/").replace("~0", "~");
+        if segment.parse::<usize>().is_ok() {
+            path.push_str(&format!("[{segment}]"));
+        } else {
+            path.push('.');
+            path.push_str(&segment);
+        }
+    }
+    path
+}
+
+/// The JSON object in an LLM answer: the whole text, else the span from the first `{` to the
+/// last `}`.
+pub fn parse_llm_json(out: &str) -> Option<Value> {
+    if let Ok(v) = serde_json::from_str(out) {
+        return Some(v);
+    }
+    let start = out.find('{')?;
+    let end = out.rfind('}')?;
+    if end <= start {
+        return None;
+    }
+    serde_json::from_str(&out[start..=end]).ok()
+}
+
+/// Calls `chat` with `system` and checks the JSON in the answer against `schema`. In retry
+/// mode a non-conforming answer gets one more call with the errors appended to `system`; a
+/// retry that still doesn't conform fails with `LlmMalformedOutput`. Answers that aren't JSON at
+/// all are returned untouched, since callers already handle plain text.
+pub async fn chat_checked<F, Fut>(
+    what: &str,
+    schema: &Value,
+    system: &str,
+    chat: F,
+) -> Result<String>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let out = chat(system.to_string()).await?;
+    let mode = schema_mode();
+    if mode == SchemaMode::Off {
+        return Ok(out);
+    }
+    let Some(parsed) = parse_llm_json(&out) else {
+        return Ok(out);
+    };
+    let errors = validate(schema, &parsed);
+    if errors.is_empty() {
+        return Ok(out);
+    }
+    eprintln!("{what} output does not match its schema: {}", errors.join("; "));
+    if mode == SchemaMode::Warn {
+        return Ok(out);
+    }
+
+    let retry_system = format!(
+        "{system}\n\nYour previous answer did not match the required JSON shape:\n- {}\nReturn \
+         corrected STRICT JSON only.",
+        errors.join("\n- ")
+    );
+    let retry = chat(retry_system).await?;
+    let errors = match parse_llm_json(&retry) {
+        Some(v) => validate(schema, &v),
+        None => vec!["$: not a JSON object".to_string()],
+    };
+    if errors.is_empty() {
+        return Ok(retry);
+    }
+    let errors = errors.join("; ");
+    eprintln!("{what} retry still does not match its schema: {errors}");
+    Err(anyhow!("{what} output does not match its schema: {errors}")
+        .context(CosError::malformed(&retry)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn event(event_type: &str, confidence: f64) -> String {
+        json!({
+            "event_type": event_type,
+            "topic": "hiring",
+            "confidence": confidence,
+            "private_note": ""
+        })
+        .to_string()
+    }
+
+    /// Runs `chat_checked` against the employee event schema with `answers` in order, returning
+    /// the result and the system prompts the chat saw.
+    async fn checked(answers: &[String]) -> (Result<String>, Vec<String>) {
+        let answers = Mutex::new(answers.to_vec());
+        let prompts = Mutex::new(Vec::new());
+        let chat = |system: String| {
+            prompts.lock().unwrap().push(system);
+            let answer = answers.lock().unwrap().remove(0);
+            async move { Ok(answer) }
+        };
+        let out = chat_checked("EmployeeAgent", &EMPLOYEE_EVENT_SCHEMA, "sys", chat).await;
+        (out, prompts.into_inner().unwrap())
+    }
+
+    #[test]
+    fn errors_name_the_offending_path() {
+        let decision = json!({
+            "decision": "d", "summary": "s", "rationale": "r", "evidence": ["e", 3],
+            "assumptions": [], "response_text": "t", "confidence": 1.5,
+            "routing": { "employee_1": "everything" }, "org_updates": {}
+        });
+        let errors = validate(&ORG_DECISION_SCHEMA, &decision);
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        assert!(errors.iter().any(|e| e.starts_with("$.evidence[1]: ")));
+        assert!(errors.iter().any(|e| e.starts_with("$.confidence: ")));
+        assert!(errors.iter().any(|e| e.starts_with("$.routing.employee_1: ")));
+        assert!(validate(&EMPLOYEE_EVENT_SCHEMA, &json!({}))[0].starts_with("$: "));
+    }
+
+    #[tokio::test]
+    async fn a_nonconforming_answer_is_retried_with_its_errors() {
+        let (out, prompts) = checked(&[event("rumour", 0.5), event("update", 0.5)]).await;
+        assert_eq!(out.unwrap(), event("update", 0.5));
+        assert!(prompts[1].starts_with("sys") && prompts[1].contains("$.event_type"));
+
+        let (out, prompts) = checked(&["I think it's an update.".to_string()]).await;
+        assert_eq!(out.unwrap(), "I think it's an update.");
+        assert_eq!(prompts.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_retry_that_still_does_not_conform_is_an_error() {
+        let (out, _) = checked(&[event("rumour", 0.5), event("update", 7.0)]).await;
+        let e = out.unwrap_err();
+        assert!(matches!(CosError::find(&e), Some(CosError::LlmMalformedOutput { .. })), "{e:#}");
+        assert!(format!("{e:#}").contains("$.confidence"));
+    }
+}
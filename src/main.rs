@@ -1,61 +1,135 @@
-mod state;
-mod utils;
-mod nodes;
-mod domain;
-mod runtime;
-mod app_state;
-mod rag;
-mod neo4j;
-mod api;
-mod service;
-
-use anyhow::Result;
-use std::env;
+
+use anyhow::{bail, Result};
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
 use pocketflow_rs::{build_flow, Context};
+use pocketflow_template_rust::{
+    api, app_state, config, employees, memory_store, neo4j, nodes, roles, runtime, script, state,
+};
 use state::MyState;
-use nodes::{EmployeeAgentNode, EndNode, GetInputNode, OrgBrainNode};
+use nodes::{
+    EmployeeAgentNode, EndNode, FanOutNode, GetInputNode, OrgBrainNode, RecordDecisionNode,
+    RetryNode, ReviewerAgentNode,
+};
 use app_state::APP_STATE;
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    dotenv::dotenv().ok();
-
-    {
-        let mut state = APP_STATE.lock().await;
-        state.init_neo4j().await?;
-        state.init_rag().await?;
-    }
+#[derive(Parser)]
+#[command(name = "cos", about = "AI Chief of Staff backend")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
 
-    let http_enabled = env::var("COS_HTTP")
-        .ok()
-        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
-        .unwrap_or(true);
+#[derive(Subcommand)]
+enum Command {
+    /// Run the HTTP API (`COS_HTTP_ADDR`, default 0.0.0.0:3000).
+    Serve,
+    /// Run the interactive terminal flow.
+    Chat,
+    /// Ingest an email CSV into RAG and the graph, then exit.
+    Ingest { csv: PathBuf },
+    /// Run Neo4j migrations, then exit.
+    Migrate,
+    /// Replay a JSONL scenario of `{"employee", "text"}` asks, then exit (non-zero if any failed).
+    Script {
+        path: PathBuf,
+        /// Results JSONL (default `COS_SCRIPT_OUT`, else `<path>.out.jsonl`).
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Pause between asks (default `COS_SCRIPT_DELAY_MS`, else 0).
+        #[arg(long)]
+        delay_ms: Option<u64>,
+    },
+    /// Write the OpenAPI spec (JSON to `--out`, YAML next to it), then exit. Needs no database or env vars.
+    Spec {
+        #[arg(long, default_value = "spec.json")]
+        out: PathBuf,
+    },
+}
 
-    if http_enabled {
-        let addr = env::var("COS_HTTP_ADDR").unwrap_or_else(|_| "0.0.0.0:3000".to_string());
-        let addr: std::net::SocketAddr = addr.parse()?;
-        api::write_spec_json("spec.json").await?;
-        return api::run_server(addr).await;
+/// Storage, private-note keys and RAG seeded from knowledge.csv: what serve and chat share.
+async fn init_runtime() -> Result<()> {
+    let mut state = APP_STATE.lock().await;
+    state.init_visibility_rules()?;
+    roles::load_seed_roles(&employees::SeedConfig::from_env()?.employees);
+    if memory_store::memory_storage_enabled() {
+        state.init_memory_store();
+    } else {
+        state.init_neo4j_or_degrade().await?;
     }
+    state.init_private_notes()?;
+    state.init_rag().await?;
+    Ok(())
+}
+
+async fn serve() -> Result<()> {
+    init_runtime().await?;
+    let addr: std::net::SocketAddr = app_state::config().http.addr.parse()?;
+    api::write_spec(Path::new("spec.json"), Path::new("spec.yaml")).await?;
+    api::run_server(addr).await
+}
 
+/// get_input -> employee -> brain -> reviewer -> record -> fan_out, back to get_input; used
+/// when there is no flow file. fan_out passes straight through unless `COS_FANOUT=1`.
+fn default_flow() -> pocketflow_rs::Flow<MyState> {
     let get_input = GetInputNode;
     let employee = EmployeeAgentNode;
     let brain = OrgBrainNode;
+    let reviewer = ReviewerAgentNode;
+    let record = RecordDecisionNode;
+    let fan_out = FanOutNode;
     let end = EndNode;
+    // One retry node per step, so Success routes back to the step that failed.
+    let employee_retry = RetryNode;
+    let brain_retry = RetryNode;
 
-    let flow = build_flow!(
+    build_flow!(
         start: ("get_input", get_input),
-        nodes: [("employee", employee), ("brain", brain), ("end", end)],
+        nodes: [
+            ("employee", employee),
+            ("brain", brain),
+            ("reviewer", reviewer),
+            ("record", record),
+            ("fan_out", fan_out),
+            ("end", end),
+            ("employee_retry", employee_retry),
+            ("brain_retry", brain_retry)
+        ],
         edges: [
             ("get_input", "employee", MyState::Success),
             ("get_input", "get_input", MyState::Failure),
             ("get_input", "end", MyState::Exit),
             ("employee", "brain", MyState::Success),
             ("employee", "get_input", MyState::Failure),
-            ("brain", "get_input", MyState::Success)
+            ("employee", "employee_retry", MyState::Retry),
+            ("employee_retry", "employee", MyState::Success),
+            ("employee_retry", "get_input", MyState::Failure),
+            ("brain", "reviewer", MyState::Success)
             ,("brain", "get_input", MyState::Failure)
+            ,("brain", "brain_retry", MyState::Retry)
+            ,("brain_retry", "brain", MyState::Success)
+            ,("brain_retry", "get_input", MyState::Failure)
+            ,("reviewer", "record", MyState::Success)
+            ,("reviewer", "get_input", MyState::Failure)
+            ,("record", "fan_out", MyState::Success)
+            ,("record", "get_input", MyState::Failure)
+            ,("fan_out", "get_input", MyState::Success)
+            ,("fan_out", "get_input", MyState::Failure)
         ]
-    );
+    )
+}
+
+async fn chat() -> Result<()> {
+    // Validate the flow before connecting to anything, so config mistakes fail fast.
+    let flow_file = PathBuf::from(&app_state::config().orgbrain.flow_file);
+    let flow = match runtime::flow_config::load_flow(&flow_file)? {
+        Some(flow) => {
+            println!("using flow from {}", flow_file.display());
+            flow
+        }
+        None => default_flow(),
+    };
+    init_runtime().await?;
 
     // Shared context
     let context = Context::new();
@@ -64,3 +138,129 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+async fn ingest(csv: PathBuf) -> Result<()> {
+    if !csv.exists() {
+        bail!("{} does not exist", csv.display());
+    }
+    // Ingestion writes email nodes to Neo4j; memory mode only seeds RAG.
+    let mut state = APP_STATE.lock().await;
+    if memory_store::memory_storage_enabled() {
+        state.init_memory_store();
+    } else {
+        state.init_neo4j().await?;
+    }
+    state.init_rag_from(&csv).await?;
+    println!("ingested {}", csv.display());
+    Ok(())
+}
+
+async fn run_script(path: PathBuf, out: Option<PathBuf>, delay_ms: Option<u64>) -> Result<()> {
+    if !path.exists() {
+        bail!("{} does not exist", path.display());
+    }
+    init_runtime().await?;
+    let out = out.unwrap_or_else(|| script::default_output_path(&path));
+    let delay = std::time::Duration::from_millis(delay_ms.unwrap_or_else(script::default_delay_ms));
+    script::run(&path, &out, delay).await
+}
+
+async fn migrate() -> Result<()> {
+    let client = neo4j::Neo4jClient::connect(&app_state::config().neo4j).await?;
+    client.run_migrations().await?;
+    println!("migrations applied");
+    Ok(())
+}
+
+/// What to run without a subcommand, keeping the env-driven behaviour: `COS_SCRIPT` when set,
+/// else serve unless `COS_HTTP` is off.
+fn default_command(script: Option<String>, http_enabled: bool) -> Command {
+    match script.filter(|v| !v.trim().is_empty()) {
+        Some(path) => Command::Script {
+            path: PathBuf::from(path),
+            out: None,
+            delay_ms: None,
+        },
+        None if http_enabled => Command::Serve,
+        None => Command::Chat,
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv::dotenv().ok();
+
+    let command = Cli::parse().command;
+    // The spec needs no configuration; everything else fails here on invalid settings.
+    if !matches!(command, Some(Command::Spec { .. })) {
+        let config = config::CosConfig::load()?;
+        for warning in &config.warnings {
+            eprintln!("config: {warning}");
+        }
+        app_state::set_config(config);
+    }
+
+    let command = match command {
+        Some(c) => c,
+        None => {
+            let config = app_state::config();
+            default_command(config.script.path.clone(), config.http.enabled)
+        }
+    };
+
+    match command {
+        Command::Serve => serve().await,
+        Command::Chat => chat().await,
+        Command::Ingest { csv } => ingest(csv).await,
+        Command::Script { path, out, delay_ms } => run_script(path, out, delay_ms).await,
+        Command::Migrate => migrate().await,
+        Command::Spec { out } => {
+            let yaml = out.with_extension("yaml");
+            api::write_spec(&out, &yaml).await?;
+            println!("wrote {} and {}", out.display(), yaml.display());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn parse(args: &[&str]) -> Option<Command> {
+        Cli::try_parse_from(std::iter::once("cos").chain(args.iter().copied()))
+            .unwrap()
+            .command
+    }
+
+    #[test]
+    fn cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn subcommands_parse_their_arguments() {
+        assert!(parse(&[]).is_none());
+        assert!(matches!(parse(&["serve"]), Some(Command::Serve)));
+        assert!(matches!(parse(&["ingest", "emails.csv"]), Some(Command::Ingest { csv })
+            if csv == Path::new("emails.csv")));
+        assert!(matches!(
+            parse(&["script", "s.jsonl", "--delay-ms", "50"]),
+            Some(Command::Script { out: None, delay_ms: Some(50), .. })
+        ));
+        assert!(matches!(parse(&["spec"]), Some(Command::Spec { out })
+            if out == Path::new("spec.json")));
+        assert!(Cli::try_parse_from(["cos", "ingest"]).is_err());
+    }
+
+    #[test]
+    fn no_subcommand_falls_back_to_the_environment() {
+        assert!(matches!(
+            default_command(Some("demo.jsonl".into()), true),
+            Command::Script { path, .. } if path == Path::new("demo.jsonl")
+        ));
+        assert!(matches!(default_command(Some("  ".into()), true), Command::Serve));
+        assert!(matches!(default_command(None, false), Command::Chat));
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio::time::MissedTickBehavior;
+
+use crate::api::ServerEvent;
+use crate::app_state::handles;
+use crate::error::CosError;
+
+/// The server's event channel, for jobs that report to `/v1/stream`.
+static EVENTS_TX: OnceCell<broadcast::Sender<ServerEvent>> = OnceCell::new();
+
+type JobFuture = Pin<Box<dyn Future<Output = Result<String>> + Send>>;
+
+/// A periodic maintenance job. `run` returns a one-line summary for the log.
+pub struct Job {
+    pub name: &'static str,
+    pub every: Duration,
+    pub run: fn() -> JobFuture,
+}
+
+/// `COS_MAINTENANCE_INTERVAL_SECS` (default 3600). `0` disables the scheduler, and with it
+/// every job.
+pub fn maintenance_interval() -> Option<Duration> {
+    let secs = crate::app_state::config().maintenance.interval_secs;
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// `COS_COMPACT_INTERVAL_SECS` (default 0): how often `compact_versions` runs. Compaction
+/// deletes versions, so it only runs when this is set.
+pub fn compact_interval() -> Option<Duration> {
+    let secs = crate::app_state::config().maintenance.compact_interval_secs;
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Every maintenance job, each on its own interval; add new ones here.
+fn jobs() -> Vec<Job> {
+    let mut jobs = Vec::new();
+    if let Some(compact_every) = compact_interval() {
+        jobs.push(Job {
+            name: "compact_versions",
+            every: compact_every,
+            run: || -> JobFuture { Box::pin(compact_versions()) },
+        });
+    }
+    if let Some(stale_every) = crate::staleness::stale_check_interval() {
+        jobs.push(Job {
+            name: "stale_decisions",
+            every: stale_every,
+            run: || -> JobFuture { Box::pin(stale_decisions()) },
+        });
+    }
+    jobs
+}
+
+async fn compact_versions() -> Result<String> {
+    let store = handles().graph_store
+        .ok_or(CosError::GraphUnavailable)?;
+    let keep_latest = crate::graph_store::compact_keep_latest();
+    let summary = store.compact_versions(keep_latest).await?;
+    Ok(format!(
+        "kept {} per object, {} objects compacted, {} versions deleted",
+        keep_latest, summary.objects_compacted, summary.versions_deleted
+    ))
+}
+
+async fn stale_decisions() -> Result<String> {
+    let store = handles().graph_store
+        .ok_or(CosError::GraphUnavailable)?;
+    let (newly_stale, checked) = crate::staleness::check_staleness(store.as_ref()).await?;
+    if let Some(tx) = EVENTS_TX.get() {
+        for (activity, score) in &newly_stale {
+            let _ = tx.send(ServerEvent::StaleDecision {
+                decision_id: activity.decision_id.clone(),
+                version: activity.version,
+                score: *score,
+                topic: activity.topic.clone().unwrap_or_default(),
+            });
+        }
+    }
+    Ok(format!("{} decisions checked, {} newly stale", checked, newly_stale.len()))
+}
+
+/// Starts one ticker per registered job. A tick that lands while the previous run is still
+/// going is skipped. Each run is its own task, so dropping the ticker never interrupts a job
+/// halfway through.
+pub fn spawn_scheduler(events_tx: broadcast::Sender<ServerEvent>) {
+    let _ = EVENTS_TX.set(events_tx);
+    if maintenance_interval().is_none() {
+        println!("maintenance scheduler disabled (COS_MAINTENANCE_INTERVAL_SECS=0)");
+        return;
+    }
+    for job in jobs() {
+        spawn_job(job);
+    }
+}
+
+/// Clears the running flag when a run ends, including by panic.
+struct RunningGuard(Arc<AtomicBool>);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+fn spawn_job(job: Job) {
+    let running = Arc::new(AtomicBool::new(false));
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(job.every);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        // The first tick fires immediately; wait one full interval before the first run.
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            if running.swap(true, Ordering::AcqRel) {
+                eprintln!("maintenance {}: previous run still in progress, skipping", job.name);
+                continue;
+            }
+            let guard = RunningGuard(running.clone());
+            let (name, run) = (job.name, job.run);
+            tokio::spawn(async move {
+                let _guard = guard;
+                let started = Instant::now();
+                let outcome = run().await;
+                let elapsed = started.elapsed();
+                match outcome {
+                    Ok(summary) => println!("maintenance {name}: ok in {elapsed:?}: {summary}"),
+                    Err(e) => eprintln!("maintenance {name}: failed after {elapsed:?}: {e:#}"),
+                }
+            });
+        }
+    });
+}
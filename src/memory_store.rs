@@ -0,0 +1,1462 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::api::{GraphEdge, GraphNode};
+use crate::domain::{Annotation, Event, GraphUpdateEntry};
+use crate::error::CosError;
+use crate::neo4j::writer::{
+    bounded_snippets, routed_employees, routing_agents, routing_to_json, AskOutcome,
+    CompactionSummary, ContextDecision, ContextTruth, DayStats, DecisionActivity, DecisionContent,
+    DecisionEmbedding, DecisionEvidence, DecisionStats, EventDecision, GraphContext,
+    GraphUpdateResult, ImpactedDecision, StaleContext, StaleMark, TopicCount, TruthDependent,
+};
+
+/// In-memory stand-in for the Decision/Truth part of the graph, used when `COS_STORAGE=memory`.
+///
+/// Mirrors the Neo4j model: every object keeps its versions in order, the last one is
+/// `CURRENT` and each version `SUPERSEDES` the one before it.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    decisions: HashMap<String, VersionedObject>,
+    truths: HashMap<String, VersionedObject>,
+    annotations: Vec<StoredAnnotation>,
+    /// Events by id, for replay.
+    events: HashMap<String, Event>,
+}
+
+#[derive(Debug, Clone)]
+struct StoredAnnotation {
+    /// Id of the decision or truth object the annotated version belongs to.
+    target_id: String,
+    version: i64,
+    annotation: Annotation,
+}
+
+#[derive(Debug, Clone)]
+struct VersionedObject {
+    id: String,
+    kind: Option<String>,
+    created_at: DateTime<Utc>,
+    versions: Vec<StoredVersion>,
+    /// False once retired or merged; inactive truth is left out of `current_truth`.
+    active: bool,
+    /// Truth id this object was merged into (`MERGED_INTO`).
+    merged_into: Option<String>,
+    /// Decision versions written for approval (`PENDING`), outside the chain until promoted.
+    pending: Vec<StoredVersion>,
+}
+
+#[derive(Debug, Clone)]
+struct StoredVersion {
+    version: i64,
+    created_at: DateTime<Utc>,
+    summary: String,
+    content_hash: String,
+    confidence: f64,
+    trigger_events: Vec<String>,
+    agents_involved: Vec<String>,
+    routing_agents: Vec<String>,
+    routing_json: String,
+    /// Versions removed between this one and the previous surviving one by compaction.
+    compacted_count: i64,
+    /// Decision versions only: the topic (`ABOUT`) and the (truth_id, version) pairs they were
+    /// `BASED_ON`.
+    topic: Option<String>,
+    based_on: Vec<(String, i64)>,
+    /// Decision versions only: rationale, evidence and assumptions.
+    content: DecisionContent,
+    /// Decision versions only: embedding of the summary, empty until computed.
+    embedding: Vec<f32>,
+    /// Decision versions only: set by the staleness check.
+    stale: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ObjectKind {
+    Decision,
+    Truth,
+}
+
+impl ObjectKind {
+    fn object_label(self) -> &'static str {
+        match self {
+            ObjectKind::Decision => "Decision",
+            ObjectKind::Truth => "TruthObject",
+        }
+    }
+
+    fn version_label(self) -> &'static str {
+        match self {
+            ObjectKind::Decision => "DecisionVersion",
+            ObjectKind::Truth => "TruthVersion",
+        }
+    }
+
+    fn id_key(self) -> &'static str {
+        match self {
+            ObjectKind::Decision => "decision_id",
+            ObjectKind::Truth => "truth_id",
+        }
+    }
+
+    fn version_id_key(self) -> &'static str {
+        match self {
+            ObjectKind::Decision => "decision_version_id",
+            ObjectKind::Truth => "truth_version_id",
+        }
+    }
+}
+
+pub fn memory_storage_enabled() -> bool {
+    crate::app_state::config().storage.backend == "memory"
+}
+
+fn object_node_id(kind: ObjectKind, id: &str) -> String {
+    format!("mem:{}:{}", kind.object_label(), id)
+}
+
+fn restore(
+    objects: &mut HashMap<String, VersionedObject>,
+    id: String,
+    saved: Option<VersionedObject>,
+) {
+    match saved {
+        Some(obj) => {
+            objects.insert(id, obj);
+        }
+        None => {
+            objects.remove(&id);
+        }
+    }
+}
+
+fn version_node_id(kind: ObjectKind, id: &str, version: i64) -> String {
+    format!("mem:{}:{}:v{}", kind.version_label(), id, version)
+}
+
+fn object_entry(kind: ObjectKind, id: &str) -> GraphUpdateEntry {
+    GraphUpdateEntry::node(object_node_id(kind, id), kind.object_label(), kind.id_key(), id)
+}
+
+fn version_entry(kind: ObjectKind, id: &str, version: i64) -> GraphUpdateEntry {
+    GraphUpdateEntry::version(
+        version_node_id(kind, id, version),
+        kind.version_label(),
+        kind.version_id_key(),
+        id,
+        version,
+    )
+}
+
+/// `edge` is what `MemoryStore::edge` builds between `from` and `to`.
+fn edge_entry(
+    edge: &GraphEdge,
+    from: &GraphUpdateEntry,
+    to: &GraphUpdateEntry,
+) -> GraphUpdateEntry {
+    GraphUpdateEntry::edge(edge.id.clone(), &edge.edge_type, &from.display, &to.display)
+}
+
+fn annotation_node_id(annotation_id: &str) -> String {
+    format!("mem:Annotation:{}", annotation_id)
+}
+
+fn employee_node_id(employee_id: &str) -> String {
+    format!("mem:Employee:{}", employee_id)
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn objects(&self, kind: ObjectKind) -> &HashMap<String, VersionedObject> {
+        match kind {
+            ObjectKind::Decision => &self.decisions,
+            ObjectKind::Truth => &self.truths,
+        }
+    }
+
+    fn objects_mut(&mut self, kind: ObjectKind) -> &mut HashMap<String, VersionedObject> {
+        match kind {
+            ObjectKind::Decision => &mut self.decisions,
+            ObjectKind::Truth => &mut self.truths,
+        }
+    }
+
+    fn next_version(&self, kind: ObjectKind, id: &str) -> i64 {
+        self.objects(kind)
+            .get(id)
+            .and_then(|o| o.versions.iter().chain(&o.pending).map(|v| v.version).max())
+            .map(|v| v + 1)
+            .unwrap_or(1)
+    }
+
+    /// Version number and content hash of the current version of `truth_id`, if any.
+    pub fn current_truth_version(&self, truth_id: &str) -> Option<(i64, String)> {
+        self.truths
+            .get(truth_id)
+            .and_then(|o| o.versions.last())
+            .map(|v| (v.version, v.content_hash.clone()))
+    }
+
+    /// Version and content of the current version of each active truth in `truth_ids`.
+    pub fn current_truth_contents(&self, truth_ids: &[String]) -> HashMap<String, (i64, String)> {
+        truth_ids
+            .iter()
+            .filter_map(|id| {
+                let current = self.truths.get(id).filter(|o| o.active)?.versions.last()?;
+                Some((id.clone(), (current.version, current.summary.clone())))
+            })
+            .collect()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn persist_version(
+        &mut self,
+        kind: ObjectKind,
+        id: String,
+        object_kind: Option<String>,
+        summary: String,
+        confidence: f64,
+        trigger_events: Vec<Uuid>,
+        agents_involved: Vec<String>,
+        routing: &Value,
+    ) -> Result<(i64, GraphUpdateResult)> {
+        // Read-and-increment under the same &mut borrow, like the single statement in Neo4j.
+        let version = self.next_version(kind, &id);
+        let now = Utc::now();
+        let obj = self
+            .objects_mut(kind)
+            .entry(id.clone())
+            .or_insert_with(|| VersionedObject {
+                id: id.clone(),
+                kind: object_kind.clone(),
+                created_at: now,
+                versions: Vec::new(),
+                active: true,
+                merged_into: None,
+                pending: Vec::new(),
+            });
+
+        // Same guarantee as the uniqueness constraint on the version id in Neo4j.
+        if obj.versions.iter().chain(&obj.pending).any(|v| v.version == version) {
+            bail!("{}:v{} already exists", id, version);
+        }
+        if obj.kind.is_none() {
+            obj.kind = object_kind;
+        }
+        // A new version brings a retired or merged object back.
+        obj.active = true;
+
+        obj.versions.push(StoredVersion {
+            version,
+            created_at: now,
+            content_hash: crate::utils::content_hash(&summary),
+            summary,
+            confidence,
+            trigger_events: trigger_events.into_iter().map(|u| u.to_string()).collect(),
+            agents_involved,
+            routing_agents: routing_agents(routing),
+            routing_json: routing_to_json(routing),
+            compacted_count: 0,
+            topic: None,
+            based_on: Vec::new(),
+            content: DecisionContent::default(),
+            embedding: Vec::new(),
+            stale: false,
+        });
+        obj.versions.sort_by_key(|v| v.version);
+
+        Ok((
+            version,
+            GraphUpdateResult {
+                nodes: vec![object_entry(kind, &id), version_entry(kind, &id, version)],
+                edges: Vec::new(),
+            },
+        ))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn persist_decision_version(
+        &mut self,
+        decision_id: String,
+        summary: String,
+        confidence: f64,
+        trigger_events: Vec<Uuid>,
+        agents_involved: Vec<String>,
+        routing: &Value,
+        topic: &str,
+        based_on: &[String],
+        content: &DecisionContent,
+    ) -> Result<(i64, GraphUpdateResult)> {
+        let (version, mut upd) = self.persist_version(
+            ObjectKind::Decision,
+            decision_id.clone(),
+            None,
+            summary,
+            confidence,
+            trigger_events,
+            agents_involved,
+            routing,
+        )?;
+
+        let topic = topic.trim().to_lowercase();
+        let based_on: Vec<(String, i64)> = based_on
+            .iter()
+            .filter_map(|id| {
+                let current = self.truths.get(id)?.versions.last()?;
+                Some((id.clone(), current.version))
+            })
+            .collect();
+        let from = version_entry(ObjectKind::Decision, &decision_id, version);
+        for (truth_id, truth_version) in &based_on {
+            let to = version_entry(ObjectKind::Truth, truth_id, *truth_version);
+            let edge = Self::edge("BASED_ON", from.element_id.clone(), to.element_id.clone());
+            upd.edges.push(edge_entry(&edge, &from, &to));
+        }
+        if let Some(v) = self
+            .decisions
+            .get_mut(&decision_id)
+            .and_then(|o| o.versions.iter_mut().find(|v| v.version == version))
+        {
+            v.topic = (!topic.is_empty()).then_some(topic);
+            v.based_on = based_on;
+            v.content = DecisionContent {
+                rationale: content.rationale.clone().filter(|r| !r.is_empty()),
+                retrieved: bounded_snippets(&content.retrieved),
+                ..content.clone()
+            };
+        }
+        Ok((version, upd))
+    }
+
+    /// Mirrors `writer::truth_dependents`.
+    pub fn truth_dependents(&self, truth_id: &str, limit: usize) -> Vec<TruthDependent> {
+        let mut out: Vec<TruthDependent> = Vec::new();
+        for obj in self.decisions.values() {
+            let current = obj.versions.last().map(|v| v.version);
+            for v in obj.versions.iter().rev() {
+                if let Some((_, truth_version)) = v.based_on.iter().find(|(id, _)| id == truth_id) {
+                    out.push(TruthDependent {
+                        decision_id: obj.id.clone(),
+                        version: v.version,
+                        summary: v.summary.clone(),
+                        confidence: v.confidence,
+                        truth_version: *truth_version,
+                        current: current == Some(v.version),
+                        routing_json: v.routing_json.clone(),
+                    });
+                }
+            }
+        }
+        out.sort_by(|a, b| a.decision_id.cmp(&b.decision_id).then(b.version.cmp(&a.version)));
+        out.truncate(limit);
+        out
+    }
+
+    /// Mirrors `writer::event_decisions`.
+    pub fn event_decisions(&self, event_id: &str, limit: usize) -> Vec<EventDecision> {
+        let mut out: Vec<EventDecision> = Vec::new();
+        for obj in self.decisions.values() {
+            let current = obj.versions.last().map(|v| v.version);
+            for v in obj.versions.iter().rev() {
+                if v.trigger_events.iter().any(|e| e == event_id) {
+                    out.push(EventDecision {
+                        decision_id: obj.id.clone(),
+                        version: v.version,
+                        summary: v.summary.clone(),
+                        confidence: v.confidence,
+                        current: current == Some(v.version),
+                        routing_json: v.routing_json.clone(),
+                    });
+                }
+            }
+        }
+        out.sort_by(|a, b| a.decision_id.cmp(&b.decision_id).then(b.version.cmp(&a.version)));
+        out.truncate(limit);
+        out
+    }
+
+    /// Mirrors `writer::set_decision_embedding`. Unknown versions are a no-op.
+    pub fn set_decision_embedding(&mut self, decision_id: &str, version: i64, embedding: &[f32]) {
+        if let Some(v) = self
+            .decisions
+            .get_mut(decision_id)
+            .and_then(|o| o.versions.iter_mut().find(|v| v.version == version))
+        {
+            v.embedding = embedding.to_vec();
+        }
+    }
+
+    /// Mirrors `writer::current_decision_embeddings`.
+    pub fn current_decision_embeddings(&self) -> Vec<DecisionEmbedding> {
+        self.decisions
+            .values()
+            .filter_map(|obj| {
+                let v = obj.versions.last()?;
+                Some(DecisionEmbedding {
+                    decision_id: obj.id.clone(),
+                    version: v.version,
+                    summary: v.summary.clone(),
+                    confidence: v.confidence,
+                    routing_json: v.routing_json.clone(),
+                    embedding: v.embedding.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Truth ids that `v`, or any decision version about the same topic, was based on.
+    fn truths_behind(&self, v: &StoredVersion) -> HashSet<String> {
+        let mut ids: HashSet<String> = v.based_on.iter().map(|(id, _)| id.clone()).collect();
+        if let Some(topic) = &v.topic {
+            for other in self.decisions.values().flat_map(|o| o.versions.iter()) {
+                if other.topic.as_ref() == Some(topic) {
+                    ids.extend(other.based_on.iter().map(|(id, _)| id.clone()));
+                }
+            }
+        }
+        ids
+    }
+
+    /// Truth ids among `truths_behind(v)` with versions written after `v`.
+    fn truths_changed_since(&self, v: &StoredVersion) -> (Vec<String>, i64) {
+        let mut changed = Vec::new();
+        let mut versions = 0i64;
+        for id in self.truths_behind(v) {
+            let newer = self
+                .truths
+                .get(&id)
+                .map(|o| o.versions.iter().filter(|tv| tv.created_at > v.created_at).count())
+                .unwrap_or(0);
+            if newer > 0 {
+                changed.push(id);
+                versions += newer as i64;
+            }
+        }
+        changed.sort();
+        (changed, versions)
+    }
+
+    /// Mirrors `writer::decision_activity`. The memory store keeps no emails, so `newer_emails`
+    /// is always 0.
+    pub fn decision_activity(&self) -> Vec<DecisionActivity> {
+        let now = Utc::now();
+        self.decisions
+            .values()
+            .filter_map(|obj| {
+                let v = obj.versions.last()?;
+                let (_, newer_truth_versions) = self.truths_changed_since(v);
+                Some(DecisionActivity {
+                    decision_id: obj.id.clone(),
+                    version: v.version,
+                    summary: v.summary.clone(),
+                    topic: v.topic.clone(),
+                    created_at: v.created_at.to_rfc3339(),
+                    age_days: (now - v.created_at).num_days(),
+                    newer_emails: 0,
+                    newer_truth_versions,
+                    stale: v.stale,
+                    routing_json: v.routing_json.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Mirrors `writer::mark_staleness`.
+    pub fn mark_staleness(&mut self, marks: &[StaleMark]) {
+        for mark in marks {
+            if let Some(v) = self
+                .decisions
+                .get_mut(&mark.decision_id)
+                .and_then(|o| o.versions.iter_mut().find(|v| v.version == mark.version))
+            {
+                v.stale = mark.stale;
+            }
+        }
+    }
+
+    /// Mirrors `writer::stale_context` (without emails).
+    pub fn stale_context(&self, decision_id: &str) -> Option<StaleContext> {
+        let v = self.decisions.get(decision_id)?.versions.last()?;
+        Some(StaleContext {
+            version: v.version,
+            summary: v.summary.clone(),
+            topic: v.topic.clone(),
+            email_subjects: Vec::new(),
+            truth_ids: self.truths_changed_since(v).0,
+        })
+    }
+
+    /// Mirrors `writer::decision_stats`.
+    pub fn decision_stats(
+        &self,
+        days: i64,
+        agent_id: Option<&str>,
+        top_topics: usize,
+    ) -> DecisionStats {
+        let since = Utc::now() - chrono::Duration::days(days);
+        let mut per_day: std::collections::BTreeMap<String, (i64, f64)> = Default::default();
+        let mut topics: HashMap<String, i64> = HashMap::new();
+        for v in self.decisions.values().flat_map(|o| o.versions.iter()) {
+            if v.created_at < since {
+                continue;
+            }
+            if let Some(aid) = agent_id {
+                if !v.routing_agents.iter().any(|a| a == aid) {
+                    continue;
+                }
+            }
+            let day = per_day.entry(v.created_at.format("%Y-%m-%d").to_string()).or_default();
+            day.0 += 1;
+            day.1 += v.confidence;
+            if let Some(topic) = &v.topic {
+                *topics.entry(topic.clone()).or_default() += 1;
+            }
+        }
+        let mut top: Vec<TopicCount> = topics
+            .into_iter()
+            .map(|(topic, decisions)| TopicCount { topic, decisions })
+            .collect();
+        top.sort_by(|a, b| b.decisions.cmp(&a.decisions).then(a.topic.cmp(&b.topic)));
+        top.truncate(top_topics);
+        DecisionStats {
+            per_day: per_day
+                .into_iter()
+                .map(|(day, (decisions, total))| DayStats {
+                    day,
+                    decisions,
+                    avg_confidence: total / decisions as f64,
+                })
+                .collect(),
+            top_topics: top,
+        }
+    }
+
+    /// Mirrors `writer::graph_context`.
+    pub fn graph_context(
+        &self,
+        topics: &[String],
+        truth_limit: usize,
+        decision_limit: usize,
+    ) -> GraphContext {
+        let on_topic = |v: &StoredVersion| {
+            v.topic.as_deref().is_some_and(|t| topics.contains(&t.to_lowercase()))
+        };
+
+        let mut uses: HashMap<&str, usize> = HashMap::new();
+        for v in self.decisions.values().flat_map(|o| o.versions.iter()).filter(|v| on_topic(v)) {
+            for (truth_id, _) in &v.based_on {
+                *uses.entry(truth_id.as_str()).or_default() += 1;
+            }
+        }
+        let mut truths: Vec<(usize, ContextTruth)> = self
+            .truths
+            .values()
+            .filter(|o| o.active)
+            .filter_map(|o| {
+                let v = o.versions.last()?;
+                let used = uses.get(o.id.as_str()).copied().unwrap_or(0);
+                let id = o.id.to_lowercase();
+                if used == 0 && !topics.iter().any(|t| id.contains(t.as_str())) {
+                    return None;
+                }
+                Some((
+                    used,
+                    ContextTruth {
+                        truth_id: o.id.clone(),
+                        version: v.version,
+                        summary: v.summary.clone(),
+                    },
+                ))
+            })
+            .collect();
+        truths.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.truth_id.cmp(&b.1.truth_id)));
+        truths.truncate(truth_limit);
+
+        let mut decisions: Vec<(DateTime<Utc>, ContextDecision)> = self
+            .decisions
+            .values()
+            .filter_map(|o| {
+                let v = o.versions.last().filter(|v| on_topic(v))?;
+                Some((
+                    v.created_at,
+                    ContextDecision {
+                        decision_id: o.id.clone(),
+                        version: v.version,
+                        summary: v.summary.clone(),
+                        confidence: v.confidence,
+                        topic: v.topic.clone().unwrap_or_default(),
+                        created_at: v.created_at.to_rfc3339(),
+                    },
+                ))
+            })
+            .collect();
+        decisions.sort_by_key(|d| std::cmp::Reverse(d.0));
+        decisions.truncate(decision_limit);
+
+        GraphContext {
+            truths: truths.into_iter().map(|(_, t)| t).collect(),
+            decisions: decisions.into_iter().map(|(_, d)| d).collect(),
+        }
+    }
+
+    /// Mirrors `writer::truth_impact`.
+    pub fn truth_impact(&self, truth_id: &str, limit: usize) -> Vec<ImpactedDecision> {
+        let relies_on = |v: &StoredVersion| v.based_on.iter().any(|(id, _)| id == truth_id);
+        let topics: HashSet<&str> = self
+            .decisions
+            .values()
+            .flat_map(|o| o.versions.iter())
+            .filter(|v| relies_on(v))
+            .filter_map(|v| v.topic.as_deref())
+            .collect();
+        let mut out: Vec<ImpactedDecision> = self
+            .decisions
+            .values()
+            .filter_map(|obj| {
+                let v = obj.versions.last()?;
+                let via = if relies_on(v) {
+                    "based_on"
+                } else if v.topic.as_deref().is_some_and(|t| topics.contains(t)) {
+                    "topic"
+                } else {
+                    return None;
+                };
+                Some(ImpactedDecision {
+                    decision_id: obj.id.clone(),
+                    version: v.version,
+                    summary: v.summary.clone(),
+                    confidence: v.confidence,
+                    topic: v.topic.clone(),
+                    via: via.to_string(),
+                    routing: routed_employees(&v.routing_json),
+                    routing_json: v.routing_json.clone(),
+                })
+            })
+            .collect();
+        out.sort_by(|a, b| a.via.cmp(&b.via).then(a.decision_id.cmp(&b.decision_id)));
+        out.truncate(limit);
+        out
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn persist_truth_version(
+        &mut self,
+        truth_id: String,
+        kind: String,
+        summary: String,
+        confidence: f64,
+        trigger_events: Vec<Uuid>,
+        agents_involved: Vec<String>,
+        routing: &Value,
+    ) -> Result<(i64, GraphUpdateResult)> {
+        self.persist_version(
+            ObjectKind::Truth,
+            truth_id,
+            Some(kind),
+            summary,
+            confidence,
+            trigger_events,
+            agents_involved,
+            routing,
+        )
+    }
+
+    /// Mirrors `writer::persist_ask_outcome` for the decision and truth versions; conversation
+    /// turns aren't kept in memory. Objects touched before a failure are put back as they were.
+    pub fn persist_ask_outcome(
+        &mut self,
+        outcome: &AskOutcome,
+    ) -> Result<(i64, GraphUpdateResult)> {
+        let saved_decision = self.decisions.get(&outcome.decision_id).cloned();
+        let saved_truths: Vec<(String, Option<VersionedObject>)> = outcome
+            .truths
+            .iter()
+            .map(|t| (t.truth_id.clone(), self.truths.get(&t.truth_id).cloned()))
+            .collect();
+
+        let result = self.write_ask_outcome(outcome);
+        if result.is_err() {
+            restore(&mut self.decisions, outcome.decision_id.clone(), saved_decision);
+            for (truth_id, saved) in saved_truths.into_iter().rev() {
+                restore(&mut self.truths, truth_id, saved);
+            }
+        }
+        result
+    }
+
+    fn write_ask_outcome(&mut self, outcome: &AskOutcome) -> Result<(i64, GraphUpdateResult)> {
+        let (version, mut upd) = self.persist_decision_version(
+            outcome.decision_id.clone(),
+            outcome.summary.clone(),
+            outcome.confidence,
+            outcome.trigger_events.clone(),
+            outcome.agents_involved.clone(),
+            &outcome.routing,
+            &outcome.topic,
+            &outcome.based_on,
+            &outcome.content,
+        )?;
+        if outcome.pending {
+            let obj = self.decisions.get_mut(&outcome.decision_id).expect("just written");
+            let idx = obj.versions.iter().position(|v| v.version == version).expect("just written");
+            let proposed = obj.versions.remove(idx);
+            obj.pending.push(proposed);
+        }
+        for truth in &outcome.truths {
+            let (_, truth_upd) = self.persist_truth_version(
+                truth.truth_id.clone(),
+                truth.kind.clone(),
+                truth.summary.clone(),
+                outcome.confidence,
+                outcome.trigger_events.clone(),
+                outcome.agents_involved.clone(),
+                &outcome.routing,
+            )?;
+            upd.nodes.extend(truth_upd.nodes);
+            upd.edges.extend(truth_upd.edges);
+        }
+        Ok((version, upd))
+    }
+
+    /// Mirrors `writer::promote_decision_version`.
+    pub fn promote_decision_version(
+        &mut self,
+        decision_id: &str,
+        version: i64,
+    ) -> Result<Option<GraphUpdateResult>> {
+        let Some(obj) = self.decisions.get_mut(decision_id) else {
+            return Ok(None);
+        };
+        let Some(idx) = obj.pending.iter().position(|v| v.version == version) else {
+            return Ok(None);
+        };
+        if let Some(current) = obj.versions.last().filter(|v| v.version > version) {
+            let moved =
+                format!("{decision_id} moved to v{} after v{version} was proposed", current.version);
+            return Err(CosError::Conflict(moved).into());
+        }
+        let approved = obj.pending.remove(idx);
+        obj.versions.push(approved);
+        Ok(Some(GraphUpdateResult {
+            nodes: vec![
+                object_entry(ObjectKind::Decision, decision_id),
+                version_entry(ObjectKind::Decision, decision_id, version),
+            ],
+            edges: Vec::new(),
+        }))
+    }
+
+    /// Mirrors `writer::decision_version`: the version's node properties.
+    pub fn decision_version(&self, decision_id: &str, version: i64) -> Option<Value> {
+        let obj = self.decisions.get(decision_id)?;
+        let v = obj.versions.iter().chain(&obj.pending).find(|v| v.version == version)?;
+        Some(self.version_node(ObjectKind::Decision, obj, v).properties)
+    }
+
+    /// Mirrors `writer::decision_evidence`.
+    pub fn decision_evidence(
+        &self,
+        decision_id: &str,
+        version: Option<i64>,
+    ) -> Option<DecisionEvidence> {
+        let obj = self.decisions.get(decision_id)?;
+        let v = match version {
+            Some(version) => obj.versions.iter().find(|v| v.version == version)?,
+            None => obj.versions.last()?,
+        };
+        Some(DecisionEvidence {
+            decision_id: decision_id.to_string(),
+            version: v.version,
+            snippets: v.content.retrieved.clone(),
+            routing_json: v.routing_json.clone(),
+        })
+    }
+
+    /// Mirrors `writer::persist_events`.
+    pub fn persist_events(&mut self, events: &[Event]) {
+        for event in events {
+            self.events.insert(event.event_id.to_string(), event.clone());
+        }
+    }
+
+    /// Mirrors `writer::load_events`.
+    pub fn load_events(&self, event_ids: &[String]) -> Vec<Event> {
+        let mut out: Vec<Event> =
+            event_ids.iter().filter_map(|id| self.events.get(id).cloned()).collect();
+        out.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.event_id.cmp(&b.event_id)));
+        out.dedup_by(|a, b| a.event_id == b.event_id);
+        out
+    }
+
+    /// Mirrors `writer::current_decision_version`.
+    pub fn current_decision_version(&self, decision_id: &str) -> Option<i64> {
+        Some(self.decisions.get(decision_id)?.versions.last()?.version)
+    }
+
+    /// Mirrors `writer::retire_truth`: marks `truth_id` inactive. Unknown ids are a no-op.
+    pub fn retire_truth(&mut self, truth_id: &str) -> GraphUpdateResult {
+        match self.truths.get_mut(truth_id) {
+            Some(obj) => {
+                obj.active = false;
+                GraphUpdateResult {
+                    nodes: vec![object_entry(ObjectKind::Truth, truth_id)],
+                    edges: Vec::new(),
+                }
+            }
+            None => GraphUpdateResult::empty(),
+        }
+    }
+
+    /// Mirrors `writer::merge_truth`: marks `truth_id` inactive and records `MERGED_INTO target`.
+    pub fn merge_truth(&mut self, truth_id: &str, target: &str) -> GraphUpdateResult {
+        if !self.truths.contains_key(target) {
+            return GraphUpdateResult::empty();
+        }
+        match self.truths.get_mut(truth_id) {
+            Some(obj) => {
+                obj.active = false;
+                obj.merged_into = Some(target.to_string());
+                let from = object_entry(ObjectKind::Truth, truth_id);
+                let to = object_entry(ObjectKind::Truth, target);
+                let edge =
+                    Self::edge("MERGED_INTO", from.element_id.clone(), to.element_id.clone());
+                GraphUpdateResult {
+                    edges: vec![edge_entry(&edge, &from, &to)],
+                    nodes: vec![from],
+                }
+            }
+            None => GraphUpdateResult::empty(),
+        }
+    }
+
+    /// Mirrors `writer::compact_versions`: keeps the first, the newest `keep_latest` and every
+    /// annotated version of each chain. Consecutive surviving versions are what `SUPERSEDES`
+    /// links, so the chain stays walkable; `pending` versions are not on it and are left alone.
+    pub fn compact_versions(&mut self, keep_latest: usize) -> CompactionSummary {
+        let keep = keep_latest.max(1);
+        let mut summary = CompactionSummary::default();
+        let annotations = &self.annotations;
+
+        for obj in self.decisions.values_mut().chain(self.truths.values_mut()) {
+            let n = obj.versions.len();
+            if n <= keep + 1 {
+                continue;
+            }
+            let annotated = |version: i64| {
+                annotations.iter().any(|a| a.target_id == obj.id && a.version == version)
+            };
+            let (mut kept, mut gap, mut deleted) = (Vec::with_capacity(n), 0, 0);
+            for (idx, mut v) in std::mem::take(&mut obj.versions).into_iter().enumerate() {
+                if (1..n - keep).contains(&idx) && !annotated(v.version) {
+                    gap += 1;
+                    continue;
+                }
+                v.compacted_count += gap as i64;
+                deleted += gap;
+                gap = 0;
+                kept.push(v);
+            }
+            obj.versions = kept;
+            if deleted > 0 {
+                summary.objects_compacted += 1;
+                summary.versions_deleted += deleted;
+            }
+        }
+        summary
+    }
+
+    /// Mirrors `persist_annotation`: returns `None` when no such version exists.
+    pub fn add_annotation(
+        &mut self,
+        decision_id: &str,
+        version: i64,
+        annotation: Annotation,
+    ) -> Option<GraphUpdateResult> {
+        let kind = [ObjectKind::Decision, ObjectKind::Truth].into_iter().find(|k| {
+            self.objects(*k)
+                .get(decision_id)
+                .map(|o| o.versions.iter().any(|v| v.version == version))
+                .unwrap_or(false)
+        })?;
+        let node = GraphUpdateEntry::node(
+            annotation_node_id(&annotation.annotation_id),
+            "Annotation",
+            "annotation_id",
+            &annotation.annotation_id,
+        );
+        let target = version_entry(kind, decision_id, version);
+        let edge = Self::edge("ANNOTATES", node.element_id.clone(), target.element_id.clone());
+        let edge = edge_entry(&edge, &node, &target);
+        self.annotations.push(StoredAnnotation {
+            target_id: decision_id.to_string(),
+            version,
+            annotation,
+        });
+        Some(GraphUpdateResult {
+            nodes: vec![node],
+            edges: vec![edge],
+        })
+    }
+
+    fn object_node(&self, kind: ObjectKind, obj: &VersionedObject) -> GraphNode {
+        let mut props = json!({
+            kind.id_key(): obj.id,
+            "created_at": obj.created_at.to_rfc3339(),
+            "label": obj.id,
+        });
+        if let Some(k) = &obj.kind {
+            props["kind"] = json!(k);
+        }
+        if !obj.active {
+            props["active"] = json!(false);
+        }
+        if let Some(target) = &obj.merged_into {
+            props["merged_into"] = json!(target);
+        }
+        GraphNode {
+            id: object_node_id(kind, &obj.id),
+            labels: vec![kind.object_label().to_string()],
+            properties: props,
+        }
+    }
+
+    fn version_node(&self, kind: ObjectKind, obj: &VersionedObject, v: &StoredVersion) -> GraphNode {
+        let mut props = json!({
+            kind.version_id_key(): format!("{}:v{}", obj.id, v.version),
+            kind.id_key(): obj.id,
+            "version": v.version,
+            "created_at": v.created_at.to_rfc3339(),
+            "summary": v.summary,
+            "content_hash": v.content_hash,
+            "confidence": v.confidence,
+            "trigger_events": v.trigger_events,
+            "agents_involved": v.agents_involved,
+            "routing_agents": v.routing_agents,
+            "routing_json": v.routing_json,
+            "label": v.summary,
+        });
+        if v.compacted_count > 0 {
+            props["compacted_count"] = json!(v.compacted_count);
+        }
+        if let Some(topic) = &v.topic {
+            props["topic"] = json!(topic);
+        }
+        if kind == ObjectKind::Decision {
+            props["rationale"] = json!(v.content.rationale);
+            props["evidence"] = json!(v.content.evidence);
+            props["assumptions"] = json!(v.content.assumptions);
+            if let Some(kind) = &v.content.kind {
+                props["kind"] = json!(kind);
+            }
+        }
+        GraphNode {
+            id: version_node_id(kind, &obj.id, v.version),
+            labels: vec![kind.version_label().to_string()],
+            properties: props,
+        }
+    }
+
+    fn employee_node(employee_id: &str) -> GraphNode {
+        GraphNode {
+            id: employee_node_id(employee_id),
+            labels: vec!["Employee".to_string()],
+            properties: json!({ "employee_id": employee_id, "label": employee_id }),
+        }
+    }
+
+    fn edge(edge_type: &str, from: String, to: String) -> GraphEdge {
+        GraphEdge {
+            id: format!("{}->{}:{}", from, to, edge_type),
+            edge_type: edge_type.to_string(),
+            from,
+            to,
+            properties: json!({ "label": edge_type }),
+        }
+    }
+
+    /// Current (object, version) pairs, like `MATCH (o)-[:CURRENT]->(v)`.
+    fn current(&self, kind: ObjectKind, limit: usize) -> (Vec<GraphNode>, Vec<GraphNode>) {
+        let mut objs = Vec::new();
+        let mut vers = Vec::new();
+        for obj in self.objects(kind).values().filter(|o| o.active).take(limit) {
+            if let Some(v) = obj.versions.last() {
+                objs.push(self.object_node(kind, obj));
+                vers.push(self.version_node(kind, obj, v));
+            }
+        }
+        (objs, vers)
+    }
+
+    pub fn current_decisions(&self, limit: usize) -> (Vec<GraphNode>, Vec<GraphNode>) {
+        self.current(ObjectKind::Decision, limit)
+    }
+
+    pub fn current_truth(&self, limit: usize) -> (Vec<GraphNode>, Vec<GraphNode>) {
+        self.current(ObjectKind::Truth, limit)
+    }
+
+    /// Nodes and edges around versions accepted by `include`.
+    fn collect(
+        &self,
+        include: impl Fn(&StoredVersion) -> bool,
+    ) -> (Vec<GraphNode>, Vec<GraphEdge>) {
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        let mut employees: HashSet<String> = HashSet::new();
+
+        for kind in [ObjectKind::Decision, ObjectKind::Truth] {
+            for obj in self.objects(kind).values() {
+                let mut object_added = false;
+                for (idx, v) in obj.versions.iter().enumerate() {
+                    if !include(v) {
+                        continue;
+                    }
+                    if !object_added {
+                        nodes.push(self.object_node(kind, obj));
+                        if let Some(target) = &obj.merged_into {
+                            edges.push(Self::edge(
+                                "MERGED_INTO",
+                                object_node_id(kind, &obj.id),
+                                object_node_id(kind, target),
+                            ));
+                        }
+                        object_added = true;
+                    }
+                    let vid = version_node_id(kind, &obj.id, v.version);
+                    nodes.push(self.version_node(kind, obj, v));
+
+                    if idx + 1 == obj.versions.len() {
+                        edges.push(Self::edge("CURRENT", object_node_id(kind, &obj.id), vid.clone()));
+                    }
+                    if idx > 0 {
+                        let prev = &obj.versions[idx - 1];
+                        edges.push(Self::edge(
+                            "SUPERSEDES",
+                            vid.clone(),
+                            version_node_id(kind, &obj.id, prev.version),
+                        ));
+                    }
+                    for (truth_id, truth_version) in &v.based_on {
+                        edges.push(Self::edge(
+                            "BASED_ON",
+                            vid.clone(),
+                            version_node_id(ObjectKind::Truth, truth_id, *truth_version),
+                        ));
+                    }
+                    for aid in &v.agents_involved {
+                        if employees.insert(aid.clone()) {
+                            nodes.push(Self::employee_node(aid));
+                        }
+                        edges.push(Self::edge("PARTICIPATED_IN", employee_node_id(aid), vid.clone()));
+                    }
+                    for a in self
+                        .annotations
+                        .iter()
+                        .filter(|a| a.target_id == obj.id && a.version == v.version)
+                    {
+                        let a = &a.annotation;
+                        let nid = annotation_node_id(&a.annotation_id);
+                        nodes.push(GraphNode {
+                            id: nid.clone(),
+                            labels: vec!["Annotation".to_string()],
+                            properties: json!({
+                                "annotation_id": a.annotation_id,
+                                "author": a.author,
+                                "text": a.text,
+                                "created_at": a.created_at.to_rfc3339(),
+                                "label": a.text,
+                            }),
+                        });
+                        edges.push(Self::edge("ANNOTATES", nid, vid.clone()));
+                    }
+                }
+            }
+        }
+
+        // Keep only edges whose endpoints are both present.
+        let ids: HashSet<String> = nodes.iter().map(|n| n.id.clone()).collect();
+        edges.retain(|e| ids.contains(&e.from) && ids.contains(&e.to));
+        (nodes, edges)
+    }
+
+    pub fn snapshot(&self, limit: usize) -> (Vec<GraphNode>, Vec<GraphEdge>) {
+        let (mut nodes, mut edges) = self.collect(|_| true);
+        nodes.truncate(limit);
+        edges.truncate(limit);
+        (nodes, edges)
+    }
+
+    pub fn agent_snapshot(&self, agent_id: &str, limit: usize) -> (Vec<GraphNode>, Vec<GraphEdge>) {
+        let (mut nodes, mut edges) =
+            self.collect(|v| v.routing_agents.iter().any(|a| a == agent_id));
+        nodes.truncate(limit);
+        edges.truncate(limit);
+        (nodes, edges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neo4j::writer::RetrievedSnippet;
+
+    fn decide(store: &mut MemoryStore, decision_id: &str, summary: &str) -> i64 {
+        let routing = json!({ "employee_john": "full" });
+        store
+            .persist_decision_version(
+                decision_id.to_string(),
+                summary.to_string(),
+                0.8,
+                Vec::new(),
+                vec!["employee_john".to_string()],
+                &routing,
+                "Hiring",
+                &[],
+                &DecisionContent::default(),
+            )
+            .unwrap()
+            .0
+    }
+
+    fn edges_of(store: &MemoryStore, edge_type: &str) -> Vec<GraphEdge> {
+        let (_, edges) = store.snapshot(1000);
+        edges.into_iter().filter(|e| e.edge_type == edge_type).collect()
+    }
+
+    #[test]
+    fn versions_chain_with_one_current_pointer() {
+        let mut store = MemoryStore::new();
+        assert_eq!(decide(&mut store, "freeze", "freeze hiring"), 1);
+        assert_eq!(decide(&mut store, "freeze", "freeze hiring until Q3"), 2);
+        assert_eq!(decide(&mut store, "freeze", "lift the freeze"), 3);
+        assert_eq!(store.current_decision_version("freeze"), Some(3));
+
+        let current = edges_of(&store, "CURRENT");
+        assert_eq!(current.len(), 1);
+        assert_eq!(current[0].to, version_node_id(ObjectKind::Decision, "freeze", 3));
+        assert_eq!(edges_of(&store, "SUPERSEDES").len(), 2);
+
+        let v2 = store.decision_version("freeze", 2).unwrap();
+        assert_eq!(v2["summary"], "freeze hiring until Q3");
+        assert_eq!(v2["topic"], "hiring");
+    }
+
+    #[test]
+    fn truth_versions_are_independent_of_decisions() {
+        let mut store = MemoryStore::new();
+        decide(&mut store, "policy", "a decision");
+        let (version, upd) = store
+            .persist_truth_version(
+                "policy".to_string(),
+                "policy".to_string(),
+                "remote work allowed".to_string(),
+                1.0,
+                Vec::new(),
+                Vec::new(),
+                &json!({}),
+            )
+            .unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(upd.nodes[1].key_property.as_deref(), Some("truth_version_id=policy:v1"));
+        let (v, hash) = store.current_truth_version("policy").unwrap();
+        assert_eq!((v, hash), (1, crate::utils::content_hash("remote work allowed")));
+    }
+
+    #[test]
+    fn annotations_attach_to_an_existing_version_only() {
+        let mut store = MemoryStore::new();
+        decide(&mut store, "freeze", "freeze hiring");
+        let note = |id: &str| Annotation {
+            annotation_id: id.to_string(),
+            author: "employee_john".to_string(),
+            text: "checked with finance".to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        assert!(store.add_annotation("freeze", 2, note("a0")).is_none());
+        assert!(store.add_annotation("missing", 1, note("a0")).is_none());
+
+        let upd = store.add_annotation("freeze", 1, note("a1")).unwrap();
+        assert_eq!(upd.nodes[0].element_id, annotation_node_id("a1"));
+        let annotates = edges_of(&store, "ANNOTATES");
+        assert_eq!(annotates.len(), 1);
+        assert_eq!(annotates[0].from, annotation_node_id("a1"));
+        assert_eq!(annotates[0].to, version_node_id(ObjectKind::Decision, "freeze", 1));
+    }
+
+    fn truth(store: &mut MemoryStore, truth_id: &str, summary: &str) -> i64 {
+        let (id, kind) = (truth_id.to_string(), "policy".to_string());
+        store
+            .persist_truth_version(id, kind, summary.into(), 1.0, vec![], vec![], &json!({}))
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn retired_and_merged_truth_leaves_the_current_set_until_rewritten() {
+        let mut store = MemoryStore::new();
+        truth(&mut store, "remote", "remote on Fridays");
+        truth(&mut store, "office", "office Mon-Thu");
+        truth(&mut store, "parking", "parking by badge");
+        let ids = ["remote", "office", "parking"].map(String::from);
+
+        assert!(store.merge_truth("remote", "missing").nodes.is_empty());
+        let merged = store.merge_truth("remote", "office");
+        assert_eq!(merged.edges.len(), 1);
+        assert_eq!(edges_of(&store, "MERGED_INTO").len(), 1);
+        store.retire_truth("parking");
+        let current = store.current_truth_contents(&ids);
+        assert_eq!(current.keys().collect::<Vec<_>>(), vec!["office"]);
+
+        assert_eq!(truth(&mut store, "parking", "parking by lottery"), 2);
+        let current = store.current_truth_contents(&ids);
+        assert_eq!(current.get("parking"), Some(&(2, "parking by lottery".to_string())));
+    }
+
+    fn propose(store: &mut MemoryStore, decision_id: &str, summary: &str) -> i64 {
+        let outcome = AskOutcome {
+            decision_id: decision_id.to_string(),
+            summary: summary.to_string(),
+            confidence: 0.8,
+            trigger_events: Vec::new(),
+            agents_involved: vec!["employee_john".to_string()],
+            routing: json!({ "employee_john": "full" }),
+            topic: "Hiring".to_string(),
+            based_on: Vec::new(),
+            content: DecisionContent::default(),
+            truths: Vec::new(),
+            employee_id: "employee_john".to_string(),
+            turns: Vec::new(),
+            pending: true,
+        };
+        store.persist_ask_outcome(&outcome).unwrap().0
+    }
+
+    #[test]
+    fn pending_versions_become_current_only_when_promoted() {
+        let mut store = MemoryStore::new();
+        decide(&mut store, "freeze", "freeze hiring");
+        assert_eq!(propose(&mut store, "freeze", "lift the freeze"), 2);
+        assert_eq!(store.current_decision_version("freeze"), Some(1));
+        assert_eq!(store.decision_version("freeze", 2).unwrap()["summary"], "lift the freeze");
+        assert!(store.promote_decision_version("freeze", 1).unwrap().is_none());
+
+        let upd = store.promote_decision_version("freeze", 2).unwrap().unwrap();
+        assert_eq!(upd.nodes[1].element_id, version_node_id(ObjectKind::Decision, "freeze", 2));
+        assert_eq!(store.current_decision_version("freeze"), Some(2));
+        assert!(store.promote_decision_version("freeze", 2).unwrap().is_none());
+    }
+
+    #[test]
+    fn a_superseded_pending_version_cannot_be_promoted() {
+        let mut store = MemoryStore::new();
+        decide(&mut store, "freeze", "freeze hiring");
+        assert_eq!(propose(&mut store, "freeze", "lift the freeze"), 2);
+        assert_eq!(decide(&mut store, "freeze", "freeze until Q3"), 3);
+        let e = store.promote_decision_version("freeze", 2).unwrap_err();
+        assert!(matches!(CosError::find(&e), Some(CosError::Conflict(_))), "{e:#}");
+        assert_eq!(store.current_decision_version("freeze"), Some(3));
+    }
+
+    #[test]
+    fn graph_context_ranks_truth_by_use_on_the_topics() {
+        let mut store = MemoryStore::new();
+        truth(&mut store, "remote_policy", "remote on Fridays");
+        truth(&mut store, "hiring_budget", "two hires in Q3");
+        truth(&mut store, "hiring_bonus", "no referral bonus");
+        truth(&mut store, "parking", "parking by badge");
+        store.retire_truth("hiring_bonus");
+        let based_on = ["remote_policy".to_string()];
+        let (routing, content) = (json!({}), DecisionContent::default());
+        let (id, summary) = ("freeze".to_string(), "freeze hiring".to_string());
+        store
+            .persist_decision_version(
+                id, summary, 0.8, vec![], vec![], &routing, "Hiring", &based_on, &content,
+            )
+            .unwrap();
+        decide(&mut store, "office", "open Berlin");
+
+        let ctx = store.graph_context(&["hiring".to_string()], 5, 5);
+        let truths: Vec<&str> = ctx.truths.iter().map(|t| t.truth_id.as_str()).collect();
+        assert_eq!(truths, vec!["remote_policy", "hiring_budget"]);
+        let decisions: Vec<&str> = ctx.decisions.iter().map(|d| d.decision_id.as_str()).collect();
+        assert_eq!(decisions, vec!["office", "freeze"], "newest first");
+        assert_eq!(store.graph_context(&["hiring".to_string()], 1, 0).truths.len(), 1);
+        assert!(store.graph_context(&["legal".to_string()], 5, 5).decisions.is_empty());
+    }
+
+    #[test]
+    fn an_ask_outcome_bases_its_decision_on_the_truth_before_it() {
+        let mut store = MemoryStore::new();
+        truth(&mut store, "remote", "remote on Fridays");
+        let outcome = AskOutcome {
+            decision_id: "office".to_string(),
+            summary: "open Berlin".to_string(),
+            confidence: 0.8,
+            trigger_events: Vec::new(),
+            agents_involved: vec!["employee_john".to_string()],
+            routing: json!({}),
+            topic: "office".to_string(),
+            based_on: vec!["remote".to_string()],
+            content: DecisionContent::default(),
+            truths: vec![crate::neo4j::writer::TruthWrite {
+                truth_id: "remote".to_string(),
+                kind: "policy".to_string(),
+                summary: "remote Mondays and Fridays".to_string(),
+            }],
+            employee_id: "employee_john".to_string(),
+            turns: Vec::new(),
+            pending: false,
+        };
+        let (version, upd) = store.persist_ask_outcome(&outcome).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(store.current_truth_version("remote").unwrap().0, 2);
+        let based_on = edges_of(&store, "BASED_ON");
+        assert_eq!(based_on.len(), 1);
+        assert_eq!(based_on[0].to, version_node_id(ObjectKind::Truth, "remote", 1));
+        assert_eq!(upd.nodes.len(), 4, "decision and truth, object and version each");
+    }
+
+    #[test]
+    fn decision_evidence_is_stored_bounded_per_version() {
+        let mut store = MemoryStore::new();
+        decide(&mut store, "freeze", "freeze hiring");
+        let snippet = |i: usize| RetrievedSnippet {
+            content: if i == 0 { "x".repeat(2500) } else { format!("snippet {i}") },
+            score: 0.5,
+            source: Some("hr.csv".to_string()),
+        };
+        let content = DecisionContent {
+            retrieved: (0..25).map(snippet).collect(),
+            ..Default::default()
+        };
+        store
+            .persist_decision_version(
+                "freeze".to_string(),
+                "freeze hiring until Q3".to_string(),
+                0.8,
+                Vec::new(),
+                Vec::new(),
+                &json!({ "employee_john": "full" }),
+                "Hiring",
+                &[],
+                &content,
+            )
+            .unwrap();
+
+        let current = store.decision_evidence("freeze", None).unwrap();
+        assert_eq!(current.version, 2);
+        assert_eq!(current.snippets.len(), 20);
+        assert!(current.snippets[0].content.ends_with("…[truncated]"));
+        assert_eq!(current.snippets[1].content, "snippet 1");
+        assert!(store.decision_evidence("freeze", Some(1)).unwrap().snippets.is_empty());
+        assert!(store.decision_evidence("freeze", Some(3)).is_none());
+        assert!(store.decision_evidence("hiring", None).is_none());
+    }
+
+    #[test]
+    fn stored_events_load_oldest_first_once_each() {
+        let mut store = MemoryStore::new();
+        let event = |topic: &str| {
+            let agent = crate::domain::EmployeeAgentId("employee_john".to_string());
+            let event_type = crate::domain::EventType::Update;
+            Event::new(agent, event_type, topic.to_string(), 0.8, Vec::new())
+        };
+        let (first, mut second) = (event("budget"), event("hiring"));
+        second.timestamp = first.timestamp + chrono::Duration::seconds(1);
+        store.persist_events(&[second.clone(), first.clone()]);
+        store.persist_events(std::slice::from_ref(&first));
+
+        let ids = [second.event_id, first.event_id, first.event_id].map(|id| id.to_string());
+        let mut ids = ids.to_vec();
+        ids.push(uuid::Uuid::new_v4().to_string());
+        let topics: Vec<String> = store.load_events(&ids).into_iter().map(|e| e.topic).collect();
+        assert_eq!(topics, vec!["budget", "hiring"]);
+    }
+
+    #[test]
+    fn context_truth_is_the_current_version_of_active_truth() {
+        let mut store = MemoryStore::new();
+        for (truth_id, summary) in [("venue", "Lisbon"), ("venue", "Porto"), ("budget", "40k")] {
+            let (id, kind) = (truth_id.to_string(), "policy".to_string());
+            let routing = json!({});
+            let summary = summary.to_string();
+            store
+                .persist_truth_version(id, kind, summary, 1.0, Vec::new(), Vec::new(), &routing)
+                .unwrap();
+        }
+        store.retire_truth("budget");
+
+        let ids = ["venue", "budget", "missing"].map(str::to_string);
+        let contents = store.current_truth_contents(&ids);
+        assert_eq!(contents.len(), 1);
+        assert_eq!(contents["venue"], (2, "Porto".to_string()));
+    }
+
+    /// Versions reached walking `SUPERSEDES` down from the `CURRENT` one, newest first.
+    fn chain(store: &MemoryStore, decision_id: &str) -> Vec<String> {
+        let supersedes = edges_of(store, "SUPERSEDES");
+        let current = edges_of(store, "CURRENT");
+        let object = object_node_id(ObjectKind::Decision, decision_id);
+        let mut next = current.iter().find(|e| e.from == object).map(|e| e.to.clone());
+        let mut walked = Vec::new();
+        while let Some(id) = next {
+            next = supersedes.iter().find(|e| e.from == id).map(|e| e.to.clone());
+            walked.push(id);
+        }
+        walked
+    }
+
+    #[test]
+    fn compaction_keeps_the_chain_walkable_from_current() {
+        let mut store = MemoryStore::new();
+        for i in 1..=6 {
+            decide(&mut store, "freeze", &format!("freeze hiring, take {i}"));
+        }
+        truth(&mut store, "remote", "remote on Fridays");
+        let summary = store.compact_versions(2);
+        assert_eq!((summary.objects_compacted, summary.versions_deleted), (1, 3));
+
+        let v = |n: i64| version_node_id(ObjectKind::Decision, "freeze", n);
+        assert_eq!(chain(&store, "freeze"), vec![v(6), v(5), v(1)]);
+        assert_eq!(store.current_decision_version("freeze"), Some(6));
+        let compacted = store.decision_version("freeze", 5).unwrap();
+        assert_eq!(compacted["compacted_count"], 3);
+        assert_eq!(store.compact_versions(2).versions_deleted, 0, "already compact");
+    }
+
+    #[test]
+    fn compaction_leaves_pending_and_annotated_versions_alone() {
+        let mut store = MemoryStore::new();
+        for i in 1..=6 {
+            decide(&mut store, "freeze", &format!("freeze hiring, take {i}"));
+        }
+        assert_eq!(propose(&mut store, "freeze", "lift the freeze"), 7);
+        assert_eq!(propose(&mut store, "freeze", "lift it in Q3"), 8);
+        let note = Annotation {
+            annotation_id: "a1".to_string(),
+            author: "employee_john".to_string(),
+            text: "checked with finance".to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        store.add_annotation("freeze", 3, note).unwrap();
+
+        let summary = store.compact_versions(1);
+        assert_eq!((summary.objects_compacted, summary.versions_deleted), (1, 3));
+        let v = |n: i64| version_node_id(ObjectKind::Decision, "freeze", n);
+        assert_eq!(chain(&store, "freeze"), vec![v(6), v(3), v(1)]);
+        assert_eq!(edges_of(&store, "ANNOTATES")[0].to, v(3));
+        assert_eq!(store.decision_version("freeze", 6).unwrap()["compacted_count"], 2);
+        assert_eq!(store.decision_version("freeze", 3).unwrap()["compacted_count"], 1);
+
+        assert!(store.decision_version("freeze", 8).is_some());
+        assert!(store.promote_decision_version("freeze", 8).unwrap().is_some());
+        assert_eq!(store.current_decision_version("freeze"), Some(8));
+        assert_eq!(chain(&store, "freeze"), vec![v(8), v(6), v(3), v(1)]);
+    }
+}
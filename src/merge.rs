@@ -0,0 +1,161 @@
+use serde_json::{json, Value};
+
+use crate::domain::{Event, EventType};
+
+/// Drained events that share a topic, ordered by confidence.
+#[derive(Debug, Clone)]
+pub struct TopicGroup {
+    pub topic: String,
+    /// Highest-confidence event on the topic; it drives the summary.
+    pub dominant: Event,
+    /// Lower-confidence events from other employees that disagree with `dominant`.
+    pub dissents: Vec<Event>,
+    /// Everything else on the topic.
+    pub supporting: Vec<Event>,
+}
+
+fn topic_key(topic: &str) -> String {
+    topic.trim().to_lowercase()
+}
+
+fn event_type_label(t: &EventType) -> &'static str {
+    match t {
+        EventType::DecisionSignal => "decision_signal",
+        EventType::Update => "update",
+        EventType::Concern => "concern",
+        EventType::Clarification => "clarification",
+    }
+}
+
+/// A concern disagrees with a decision signal or update; clarifications disagree with nothing.
+fn conflicts(a: &EventType, b: &EventType) -> bool {
+    let pushes = |t: &EventType| matches!(t, EventType::DecisionSignal | EventType::Update);
+    let objects = |t: &EventType| matches!(t, EventType::Concern);
+    (pushes(a) && objects(b)) || (objects(a) && pushes(b))
+}
+
+/// Groups events by (case-insensitive) topic, in order of first appearance. Within a group the
+/// highest-confidence event dominates; ties go to the earlier event.
+pub fn group_by_topic(events: &[Event]) -> Vec<TopicGroup> {
+    let mut keys: Vec<String> = Vec::new();
+    let mut buckets: Vec<Vec<&Event>> = Vec::new();
+    for event in events {
+        let key = topic_key(&event.topic);
+        match keys.iter().position(|k| *k == key) {
+            Some(idx) => buckets[idx].push(event),
+            None => {
+                keys.push(key);
+                buckets.push(vec![event]);
+            }
+        }
+    }
+
+    buckets
+        .into_iter()
+        .map(|mut bucket| {
+            bucket.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+            let dominant = bucket[0].clone();
+            let (dissents, supporting) = bucket[1..].iter().map(|e| (*e).clone()).partition(|e| {
+                e.emitted_by != dominant.emitted_by && conflicts(&dominant.event_type, &e.event_type)
+            });
+            TopicGroup {
+                topic: dominant.topic.clone(),
+                dominant,
+                dissents,
+                supporting,
+            }
+        })
+        .collect()
+}
+
+/// Compact form for the OrgBrain prompt: groups with more than one event, by event id.
+pub fn prompt_groups(groups: &[TopicGroup]) -> Value {
+    Value::Array(
+        groups
+            .iter()
+            .filter(|g| !g.dissents.is_empty() || !g.supporting.is_empty())
+            .map(|g| {
+                json!({
+                    "topic": g.topic,
+                    "dominant": g.dominant.event_id,
+                    "dissents": g.dissents.iter().map(|e| e.event_id).collect::<Vec<_>>(),
+                    "supporting": g.supporting.iter().map(|e| e.event_id).collect::<Vec<_>>(),
+                })
+            })
+            .collect(),
+    )
+}
+
+/// One assumption line per dissent, recorded on the decision whatever the LLM writes.
+pub fn dissent_assumptions(groups: &[TopicGroup]) -> Vec<String> {
+    groups
+        .iter()
+        .flat_map(|g| {
+            g.dissents.iter().map(move |d| {
+                format!(
+                    "Dissent on {}: {} raised a {} (confidence {:.2}), outweighed by {}'s {} (confidence {:.2})",
+                    g.topic,
+                    d.emitted_by.0,
+                    event_type_label(&d.event_type),
+                    d.confidence,
+                    g.dominant.emitted_by.0,
+                    event_type_label(&g.dominant.event_type),
+                    g.dominant.confidence
+                )
+            })
+        })
+        .collect()
+}
+
+/// Prompt addendum explaining `topic_groups`.
+pub const TOPIC_GROUPS_INSTRUCTION: &str = "
+`topic_groups` lists topics with several events. When a topic has dissents, base the summary on
+the dominant (higher-confidence) event and record each dissent in assumptions instead.
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::EmployeeAgentId;
+
+    fn event(by: &str, event_type: EventType, topic: &str, confidence: f32) -> Event {
+        let agent = EmployeeAgentId(by.to_string());
+        Event::new(agent, event_type, topic.to_string(), confidence, vec![])
+    }
+
+    #[test]
+    fn the_most_confident_event_dominates_and_conflicts_from_others_dissent() {
+        let events = vec![
+            event("employee_bob", EventType::Concern, "Hiring freeze", 0.6),
+            event("employee_john", EventType::DecisionSignal, "hiring freeze ", 0.9),
+            event("employee_sarah", EventType::Update, "Hiring Freeze", 0.7),
+            event("employee_john", EventType::Concern, "hiring freeze", 0.5),
+            event("employee_bob", EventType::Update, "Office move", 0.8),
+        ];
+        let groups = group_by_topic(&events);
+        assert_eq!(groups.len(), 2);
+        let hiring = &groups[0];
+        assert_eq!(hiring.dominant.event_id, events[1].event_id);
+        // John's own concern and Sarah's agreeing update support; Bob's concern dissents.
+        let dissents: Vec<_> = hiring.dissents.iter().map(|e| e.event_id).collect();
+        assert_eq!(dissents, vec![events[0].event_id]);
+        assert_eq!(hiring.supporting.len(), 2);
+
+        let prompt = prompt_groups(&groups);
+        assert_eq!(prompt.as_array().unwrap().len(), 1, "single-event topics are left out");
+        let lines = dissent_assumptions(&groups);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("Dissent on hiring freeze : employee_bob raised a concern"));
+    }
+
+    #[test]
+    fn ties_go_to_the_earlier_event_and_clarifications_never_dissent() {
+        let events = vec![
+            event("employee_bob", EventType::Clarification, "budget", 0.8),
+            event("employee_john", EventType::DecisionSignal, "budget", 0.8),
+        ];
+        let groups = group_by_topic(&events);
+        assert_eq!(groups[0].dominant.event_id, events[0].event_id);
+        assert!(groups[0].dissents.is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::{anyhow, Result};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::Neo4jConfig;
+use crate::error::CosError;
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Closed { failures: u32 },
+    /// Calls fail fast until `until`.
+    Open { until: Instant },
+    /// One probe call is in flight since `since`; everyone else still fails fast.
+    HalfOpen { since: Instant },
+}
+
+/// Fails Neo4j calls fast once the database looks down, instead of letting every request wait
+/// for its own connection timeout.
+///
+/// After `threshold` consecutive connection failures the breaker opens for `cooldown`. The
+/// first call after that is let through as a probe: success closes the breaker, failure opens
+/// it for another `cooldown`. A probe that never reports (its request was dropped) is replaced
+/// after `cooldown`. Query errors such as constraint violations don't count as failures.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    /// A `threshold` of 0 disables the breaker.
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self { threshold, cooldown, state: Mutex::new(State::Closed { failures: 0 }) }
+    }
+
+    /// `COS_NEO4J_BREAKER_THRESHOLD` (default 5, `0` disables) and
+    /// `COS_NEO4J_BREAKER_COOLDOWN_SECS` (default 30).
+    pub fn from_config(config: &Neo4jConfig) -> Self {
+        Self::new(config.breaker_threshold, Duration::from_secs(config.breaker_cooldown_secs))
+    }
+
+    /// Runs `call` unless the breaker is open, and records whether Neo4j was reachable. A call
+    /// that outlives `NEO4J_TIMEOUT_MS` fails with `CosError::Timeout` and counts as a failure.
+    pub async fn call<T>(&self, call: impl Future<Output = Result<T>>) -> Result<T> {
+        self.acquire(true)?;
+        let result = crate::utils::with_timeout("neo4j", crate::utils::neo4j_timeout(), call).await;
+        match &result {
+            Err(e) if is_unavailable(e) => self.record_failure(),
+            _ => self.record_success(),
+        }
+        result
+    }
+
+    /// Errors with `GraphUnavailable` while the breaker is open, without taking the probe. For
+    /// callers that query the graph directly and don't report how it went.
+    pub fn check(&self) -> Result<()> {
+        self.acquire(false)
+    }
+
+    fn acquire(&self, probe: bool) -> Result<()> {
+        if self.threshold == 0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut state = self.state();
+        let ready = match *state {
+            State::Closed { .. } => return Ok(()),
+            State::Open { until } => now >= until,
+            State::HalfOpen { since } => now >= since + self.cooldown,
+        };
+        if !ready {
+            return Err(anyhow!("neo4j circuit breaker open").context(CosError::GraphUnavailable));
+        }
+        if probe {
+            *state = State::HalfOpen { since: now };
+        }
+        Ok(())
+    }
+
+    fn record_success(&self) {
+        *self.state() = State::Closed { failures: 0 };
+    }
+
+    fn record_failure(&self) {
+        if self.threshold == 0 {
+            return;
+        }
+        let now = Instant::now();
+        let mut state = self.state();
+        *state = match *state {
+            State::Closed { failures } if failures + 1 < self.threshold => {
+                State::Closed { failures: failures + 1 }
+            }
+            _ => {
+                eprintln!("neo4j unreachable; failing graph calls for {:?}", self.cooldown);
+                State::Open { until: now + self.cooldown }
+            }
+        };
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Whether `e` means Neo4j could not be reached, as opposed to a query that failed.
+fn is_unavailable(e: &anyhow::Error) -> bool {
+    if e.chain().any(|c| {
+        matches!(
+            c.downcast_ref::<CosError>(),
+            Some(CosError::GraphUnavailable | CosError::Timeout(_))
+        )
+    }) {
+        return true;
+    }
+    let text = format!("{e:?}");
+    ["IOError", "ConnectionError", "Connection refused", "timed out", "Broken pipe"]
+        .iter()
+        .any(|marker| text.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unreachable() -> Result<()> {
+        Err(anyhow!("Connection refused"))
+    }
+
+    fn is_open(e: &anyhow::Error) -> bool {
+        matches!(CosError::find(e), Some(CosError::GraphUnavailable))
+    }
+
+    #[tokio::test]
+    async fn opens_after_consecutive_failures_and_probes_after_the_cooldown() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(50));
+        // Query errors don't count.
+        for _ in 0..3 {
+            let e = breaker.call(async { Err::<(), _>(anyhow!("constraint")) }).await;
+            assert!(!is_open(&e.unwrap_err()));
+        }
+        assert!(!is_open(&breaker.call(async { unreachable() }).await.unwrap_err()));
+        assert!(!is_open(&breaker.call(async { unreachable() }).await.unwrap_err()));
+        let e = breaker.call(async { Ok(()) }).await.unwrap_err();
+        assert!(is_open(&e), "{e:#}");
+        assert!(breaker.check().is_err());
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        // The probe fails, so the breaker opens for another cooldown.
+        assert!(!is_open(&breaker.call(async { unreachable() }).await.unwrap_err()));
+        assert!(is_open(&breaker.call(async { Ok(()) }).await.unwrap_err()));
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        breaker.call(async { Ok(()) }).await.unwrap();
+        breaker.call(async { Ok(()) }).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_zero_threshold_never_opens() {
+        let breaker = CircuitBreaker::new(0, Duration::from_secs(60));
+        for _ in 0..5 {
+            assert!(!is_open(&breaker.call(async { unreachable() }).await.unwrap_err()));
+        }
+        breaker.check().unwrap();
+    }
+}
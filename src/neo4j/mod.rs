@@ -1,45 +1,130 @@
+pub mod breaker;
 pub mod schema;
 pub mod writer;
 
-use anyhow::{Context as _, Result};
-use neo4rs::{ConfigBuilder, Graph};
-use std::env;
+use anyhow::{anyhow, Context as _, Result};
+use neo4rs::{BoltType, ConfigBuilder, Graph, Query};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::Neo4jConfig;
+use crate::error::CosError;
+use breaker::CircuitBreaker;
 
 #[derive(Clone)]
 pub struct Neo4jClient {
     graph: Graph,
+    breaker: Arc<CircuitBreaker>,
 }
 
 impl Neo4jClient {
-    pub async fn connect_from_env() -> Result<Self> {
-        let uri = env::var("NEO4J_URI").unwrap_or_else(|_| "127.0.0.1:7687".to_string());
-        let user = env::var("NEO4J_USER").unwrap_or_else(|_| "neo4j".to_string());
-        let password = env::var("NEO4J_PASSWORD").unwrap_or_else(|_| "neo4j".to_string());
-        let fetch_size: usize = env::var("NEO4J_FETCH_SIZE")
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(200);
-
+    pub async fn connect(settings: &Neo4jConfig) -> Result<Self> {
         let config = ConfigBuilder::default()
-            .uri(uri)
-            .user(user)
-            .password(password)
-            .fetch_size(fetch_size)
+            .uri(&settings.uri)
+            .user(&settings.user)
+            .password(&settings.password)
+            .fetch_size(settings.fetch_size)
             .build()
             .context("failed to build neo4j config")?;
 
         let graph = Graph::connect(config)
             .await
-            .context("failed to connect to neo4j")?;
+            .context("failed to connect to neo4j")
+            .context(CosError::GraphUnavailable)?;
 
-        Ok(Self { graph })
+        Ok(Self { graph, breaker: Arc::new(CircuitBreaker::from_config(settings)) })
     }
 
     pub fn graph(&self) -> &Graph {
         &self.graph
     }
 
+    /// Shared by every clone of the client. `GraphStore` calls go through it; code that queries
+    /// `graph()` directly should `check()` first.
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
     pub async fn run_migrations(&self) -> Result<()> {
         schema::run_migrations(&self.graph).await
     }
+
+    /// Runs `q` in a transaction that is always rolled back, also when `timeout` expires, so
+    /// nothing it writes is ever committed. Callers reject write clauses before getting here
+    /// (`api::graph_cypher`); this is the second line of defense. Returns at most `row_cap` rows
+    /// and whether more were available.
+    ///
+    /// A query Neo4j rejects (syntax, types) fails with `CosError::Validation`,
+    /// an expired `timeout` with `CosError::Timeout`, anything else with `GraphUnavailable`.
+    pub async fn read_only_query(
+        &self,
+        q: Query,
+        row_cap: usize,
+        timeout: Duration,
+    ) -> Result<(Vec<HashMap<String, BoltType>>, bool)> {
+        self.breaker.check()?;
+        let deadline = tokio::time::Instant::now() + timeout;
+        let begin = self.graph.start_txn();
+        let mut txn = match tokio::time::timeout_at(deadline, begin).await {
+            Ok(txn) => txn.map_err(query_error)?,
+            Err(_) => return Err(anyhow!("begin timed out").context(CosError::Timeout("neo4j"))),
+        };
+
+        let read = async {
+            let mut stream = txn.execute(q).await.map_err(query_error)?;
+            let mut rows = Vec::new();
+            while let Some(row) = stream.next(txn.handle()).await.map_err(query_error)? {
+                if rows.len() >= row_cap {
+                    return Ok((rows, true));
+                }
+                rows.push(row.to::<HashMap<String, BoltType>>().context("decode row")?);
+            }
+            anyhow::Ok((rows, false))
+        };
+        let result = tokio::time::timeout_at(deadline, read).await;
+
+        // Discards anything the query wrote; bounded in case the connection is wedged.
+        match tokio::time::timeout(ROLLBACK_TIMEOUT, txn.rollback()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => eprintln!("cypher rollback failed: {e}"),
+            Err(_) => eprintln!("cypher rollback timed out"),
+        }
+        match result {
+            Ok(rows) => rows,
+            Err(_) => Err(anyhow!("query timed out after {timeout:?}")
+                .context(CosError::Timeout("neo4j"))),
+        }
+    }
+}
+
+/// Upper bound on the rollback that ends a `read_only_query`.
+const ROLLBACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Only `Neo.ClientError.*` (other than `Security`) is the query's fault; connection and
+/// server errors mean the database is unavailable.
+fn query_error(e: neo4rs::Error) -> anyhow::Error {
+    let rejected = matches!(
+        &e,
+        neo4rs::Error::Neo4j(n)
+            if n.code().starts_with("Neo.ClientError.")
+                && !n.code().starts_with("Neo.ClientError.Security.")
+    );
+    let kind = if rejected {
+        CosError::Validation("query rejected by neo4j".to_string())
+    } else {
+        CosError::GraphUnavailable
+    };
+    anyhow::Error::new(e).context(kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_failures_are_unavailable_not_bad_queries() {
+        let e = query_error(neo4rs::Error::ConnectionError);
+        assert!(matches!(CosError::find(&e), Some(CosError::GraphUnavailable)));
+    }
 }
@@ -24,8 +24,17 @@ pub async fn run_migrations(graph: &Graph) -> Result<()> {
         "CREATE CONSTRAINT conversation_turn_id IF NOT EXISTS FOR (t:ConversationTurn) REQUIRE t.turn_id IS UNIQUE",
         // EmailMessage
         "CREATE CONSTRAINT email_message_id IF NOT EXISTS FOR (m:EmailMessage) REQUIRE m.message_id IS UNIQUE",
+        // Thread
+        "CREATE CONSTRAINT thread_thread_id IF NOT EXISTS FOR (t:Thread) REQUIRE t.thread_id IS UNIQUE",
         // KnowledgeCluster
         "CREATE CONSTRAINT knowledge_cluster_id IF NOT EXISTS FOR (c:KnowledgeCluster) REQUIRE c.cluster_id IS UNIQUE",
+        // Event
+        "CREATE CONSTRAINT event_event_id IF NOT EXISTS FOR (e:Event) REQUIRE e.event_id IS UNIQUE",
+        // AuditEvent
+        "CREATE CONSTRAINT audit_event_id IF NOT EXISTS FOR (a:AuditEvent) REQUIRE a.audit_id IS UNIQUE",
+        // Range index for time-windowed stats
+        "CREATE INDEX decision_version_created_at IF NOT EXISTS FOR (dv:DecisionVersion) ON (dv.created_at)",
+        "CREATE INDEX email_message_sent_at IF NOT EXISTS FOR (m:EmailMessage) ON (m.sent_at)",
     ];
 
     for stmt in statements {
@@ -35,5 +44,11 @@ pub async fn run_migrations(graph: &Graph) -> Result<()> {
     }
 
     txn.commit().await.context("commit neo4j migrations")?;
+
+    // Data migration: `EmailMessage.date` (raw header) became `date_raw` plus a parsed `sent_at`.
+    let (parsed, unparseable) = crate::neo4j::writer::backfill_email_sent_at(graph).await?;
+    if parsed + unparseable > 0 {
+        println!("Backfilled sent_at on {parsed} email(s); {unparseable} date(s) did not parse.");
+    }
     Ok(())
 }
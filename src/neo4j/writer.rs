@@ -1,27 +1,60 @@
 use anyhow::{Context as _, Result};
-use neo4rs::{query, Graph};
+use neo4rs::{query, BoltMap, BoltType, Graph};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::domain::{EmployeeRole, Event, GraphUpdateEntry};
+use crate::employees::EmployeeSeed;
+use crate::error::CosError;
+use crate::roles::{role_for_email, RoleRule};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GraphUpdateResult {
-    pub nodes: Vec<String>,
-    pub edges: Vec<String>,
+    pub nodes: Vec<GraphUpdateEntry>,
+    pub edges: Vec<GraphUpdateEntry>,
 }
 
-pub fn canonical_employee_id_from_email(email: &str) -> String {
-    let mut out = String::with_capacity(email.len() + 15);
-    out.push_str("employee_email_");
-    for ch in email.trim().to_lowercase().chars() {
-        match ch {
-            'a'..='z' | '0'..='9' => out.push(ch),
-            _ => out.push('_'),
+/// Lowercase ASCII slug: common Latin diacritics are folded ("é" -> "e"), every other run of
+/// non-alphanumeric characters becomes a single `_`, and leading/trailing `_` are stripped.
+pub fn slugify_identifier(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut pending_sep = false;
+    for ch in s.trim().to_lowercase().chars() {
+        let folded = match ch {
+            'a'..='z' | '0'..='9' => Some(ch.to_string()),
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => Some("a".to_string()),
+            'æ' => Some("ae".to_string()),
+            'ç' => Some("c".to_string()),
+            'è' | 'é' | 'ê' | 'ë' => Some("e".to_string()),
+            'ì' | 'í' | 'î' | 'ï' => Some("i".to_string()),
+            'ñ' => Some("n".to_string()),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => Some("o".to_string()),
+            'ù' | 'ú' | 'û' | 'ü' => Some("u".to_string()),
+            'ý' | 'ÿ' => Some("y".to_string()),
+            'ß' => Some("ss".to_string()),
+            _ => None,
+        };
+        match folded {
+            Some(f) => {
+                if pending_sep && !out.is_empty() {
+                    out.push('_');
+                }
+                pending_sep = false;
+                out.push_str(&f);
+            }
+            None => pending_sep = true,
         }
     }
     out
 }
 
+pub fn canonical_employee_id_from_email(email: &str) -> String {
+    format!("employee_email_{}", slugify_identifier(email))
+}
+
 pub async fn merge_employee_from_email(
     graph: &Graph,
     email: &str,
@@ -32,19 +65,25 @@ pub async fn merge_employee_from_email(
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty())
         .unwrap_or_else(|| email.trim().to_string());
+    // Best guess from COS_ROLE_RULES; an existing role always wins.
+    let role = role_for_email(email, &crate::roles::role_rules_from_env());
 
     let q = query(
         r#"
 MERGE (e:Employee {employee_id: $employee_id})
 ON CREATE SET e.created_at = datetime()
 SET e.name = coalesce(e.name, $name),
-    e.email = coalesce(e.email, $email)
-RETURN elementId(e) AS node_id
+    e.email = coalesce(e.email, $email),
+    e.role = coalesce(e.role, $role)
+RETURN elementId(e) AS node_id,
+       CASE WHEN e.seeded IS NULL THEN e.role END AS role,
+       e.canonical_employee_id IS NULL AND NOT coalesce(e.seeded, false) AS unlinked
 "#,
     )
-    .param("employee_id", employee_id)
+    .param("employee_id", employee_id.clone())
     .param("name", name)
-    .param("email", email.trim().to_lowercase());
+    .param("email", email.trim().to_lowercase())
+    .param("role", role);
 
     let mut stream = graph.execute(q).await.context("merge employee")?;
     let row = stream
@@ -53,18 +92,497 @@ RETURN elementId(e) AS node_id
         .context("read merge employee")?
         .context("merge employee returned no row")?;
     let node_id: String = row.get("node_id").context("missing employee node_id")?;
+    let stored: Option<String> = row.get("role").ok();
+    if let Some(role) = stored.as_deref().and_then(EmployeeRole::parse) {
+        crate::roles::record_stored_role(&employee_id, role);
+    }
+
+    let unlinked: bool = row.get("unlinked").unwrap_or(false);
+    let auto_link = unlinked && crate::employees::auto_alias_enabled();
+    if let Some(name) = display_name.filter(|_| auto_link) {
+        // The employee itself is merged either way; a failed link is only logged.
+        if let Err(e) = auto_link_employee(graph, &employee_id, name).await {
+            eprintln!("auto-link employee {employee_id}: {e:#}");
+        }
+    }
     Ok(node_id)
 }
 
+/// Links `employee_id` to the seeded employee `display_name` matches (see
+/// `employees::match_seeded_name`). An ambiguous match links nothing and records a
+/// `MAYBE_ALIAS_OF` edge to each candidate instead, listed by `list_identity_suggestions`.
+async fn auto_link_employee(graph: &Graph, employee_id: &str, display_name: &str) -> Result<()> {
+    let q = query(
+        r#"
+MATCH (s:Employee {seeded: true})
+WHERE s.employee_id <> $employee_id AND s.name IS NOT NULL
+RETURN s.employee_id AS employee_id, s.name AS name
+"#,
+    )
+    .param("employee_id", employee_id.to_string());
+    let mut stream = graph.execute(q).await.context("load seeded employees")?;
+    let mut seeded = Vec::new();
+    while let Some(row) = stream.next().await.context("read seeded employees")? {
+        let id: String = row.get("employee_id").unwrap_or_default();
+        let name: String = row.get("name").unwrap_or_default();
+        seeded.push((id, name));
+    }
+
+    match crate::employees::match_seeded_name(display_name, &seeded) {
+        crate::employees::NameMatch::None => Ok(()),
+        crate::employees::NameMatch::One { employee_id: canonical_id, heuristic } => {
+            link_employee_alias(
+                graph,
+                employee_id,
+                &canonical_id,
+                true,
+                heuristic.as_str(),
+                heuristic.confidence(),
+            )
+            .await?;
+            Ok(())
+        }
+        crate::employees::NameMatch::Ambiguous { employee_ids, heuristic } => {
+            let q = query(
+                r#"
+MATCH (a:Employee {employee_id: $employee_id})
+UNWIND $candidates AS cid
+MATCH (c:Employee {employee_id: cid})
+MERGE (a)-[m:MAYBE_ALIAS_OF]->(c)
+ON CREATE SET m.created_at = datetime()
+SET m.heuristic = $heuristic,
+    m.confidence = $confidence
+"#,
+            )
+            .param("employee_id", employee_id.to_string())
+            .param("candidates", employee_ids)
+            .param("heuristic", heuristic.as_str())
+            .param("confidence", heuristic.confidence());
+            graph.run(q).await.map(drop).context("record identity suggestion")
+        }
+    }
+}
+
+/// A seeded employee an unlinked email identity may be.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IdentityCandidate {
+    pub employee_id: String,
+    pub name: String,
+}
+
+/// An email identity that matched several seeded employees at ingest.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IdentitySuggestion {
+    pub employee_id: String,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    /// `exact_name` or `first_name`.
+    pub heuristic: String,
+    pub confidence: f64,
+    pub candidates: Vec<IdentityCandidate>,
+}
+
+/// Pending `MAYBE_ALIAS_OF` suggestions for employees that are not linked yet, oldest first.
+pub async fn list_identity_suggestions(graph: &Graph) -> Result<Vec<IdentitySuggestion>> {
+    let q = query(
+        r#"
+MATCH (a:Employee)-[m:MAYBE_ALIAS_OF]->(c:Employee)
+WHERE a.canonical_employee_id IS NULL
+WITH a, m, c
+ORDER BY c.employee_id
+WITH a, min(m.created_at) AS created_at, head(collect(m.heuristic)) AS heuristic,
+     head(collect(m.confidence)) AS confidence,
+     collect(c.employee_id) AS candidate_ids, collect(coalesce(c.name, '')) AS candidate_names
+RETURN a.employee_id AS employee_id, a.name AS name, a.email AS email, heuristic, confidence,
+       candidate_ids, candidate_names
+ORDER BY created_at
+"#,
+    );
+    let mut stream = graph.execute(q).await.context("list identity suggestions")?;
+    let mut out = Vec::new();
+    while let Some(row) = stream.next().await.context("read identity suggestions")? {
+        let ids: Vec<String> = row.get("candidate_ids").unwrap_or_default();
+        let names: Vec<String> = row.get("candidate_names").unwrap_or_default();
+        out.push(IdentitySuggestion {
+            employee_id: row.get("employee_id").unwrap_or_default(),
+            name: row.get("name").ok(),
+            email: row.get("email").ok(),
+            heuristic: row.get("heuristic").unwrap_or_default(),
+            confidence: row.get("confidence").unwrap_or_default(),
+            candidates: ids
+                .into_iter()
+                .zip(names)
+                .map(|(employee_id, name)| IdentityCandidate { employee_id, name })
+                .collect(),
+        });
+    }
+    Ok(out)
+}
+
+/// The employee `employee_id` is an alias of (its `canonical_employee_id`), or `employee_id`
+/// itself when it is canonical or unknown.
+pub async fn resolve_canonical_employee(graph: &Graph, employee_id: &str) -> Result<String> {
+    let q = query(
+        r#"
+OPTIONAL MATCH (e:Employee {employee_id: $employee_id})
+RETURN coalesce(e.canonical_employee_id, $employee_id) AS canonical
+"#,
+    )
+    .param("employee_id", employee_id.to_string());
+    let mut stream = graph.execute(q).await.context("resolve canonical employee")?;
+    Ok(stream
+        .next()
+        .await
+        .context("read canonical employee")?
+        .and_then(|row| row.get("canonical").ok())
+        .unwrap_or_else(|| employee_id.to_string()))
+}
+
+/// Edges moved from an alias to its canonical employee, by type.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+pub struct AliasMerge {
+    pub sent: i64,
+    pub to: i64,
+    pub participated_in: i64,
+}
+
+/// Makes `alias_id` an alias of `canonical_id`: an `ALIAS_OF` edge recording `heuristic` and
+/// `confidence`, plus `canonical_employee_id` on the alias. Pending `MAYBE_ALIAS_OF` suggestions
+/// for the alias are dropped. Employees that were aliases of `alias_id` become aliases of
+/// `canonical_id`, so chains stay one hop. With `merge_edges`, the alias's edges move to
+/// `canonical_id` too. One transaction; `None` when either employee doesn't exist.
+pub async fn link_employee_alias(
+    graph: &Graph,
+    alias_id: &str,
+    canonical_id: &str,
+    merge_edges: bool,
+    heuristic: &str,
+    confidence: f64,
+) -> Result<Option<AliasMerge>> {
+    let mut txn = graph.start_txn().await.context("start alias txn")?;
+    let result = async {
+        let q = query(
+            r#"
+MATCH (a:Employee {employee_id: $alias_id})
+MATCH (c:Employee {employee_id: $canonical_id})
+OPTIONAL MATCH (a)-[old:ALIAS_OF|MAYBE_ALIAS_OF]->()
+DELETE old
+WITH DISTINCT a, c
+SET a.canonical_employee_id = $canonical_id
+MERGE (a)-[r:ALIAS_OF]->(c)
+ON CREATE SET r.created_at = datetime()
+SET r.heuristic = $heuristic,
+    r.confidence = $confidence
+WITH a, c
+OPTIONAL MATCH (x:Employee)-[xr:ALIAS_OF]->(a)
+FOREACH (_ IN CASE WHEN x IS NULL THEN [] ELSE [1] END |
+  SET x.canonical_employee_id = $canonical_id
+  MERGE (x)-[:ALIAS_OF]->(c)
+  DELETE xr)
+RETURN count(*) AS linked
+"#,
+        )
+        .param("alias_id", alias_id.to_string())
+        .param("canonical_id", canonical_id.to_string())
+        .param("heuristic", heuristic.to_string())
+        .param("confidence", confidence);
+        let mut stream = txn.execute(q).await.context("link employee alias")?;
+        let linked: i64 = stream
+            .next(txn.handle())
+            .await
+            .context("read link employee alias")?
+            .and_then(|row| row.get("linked").ok())
+            .unwrap_or(0);
+        if linked == 0 {
+            return Ok(None);
+        }
+        let moved = if merge_edges {
+            repoint_alias_edges(&mut txn, alias_id, canonical_id).await?
+        } else {
+            AliasMerge::default()
+        };
+        Ok::<_, anyhow::Error>(Some(moved))
+    }
+    .await;
+
+    match result {
+        Ok(Some(moved)) => {
+            txn.commit().await.context("commit alias txn")?;
+            Ok(Some(moved))
+        }
+        other => {
+            let _ = txn.rollback().await;
+            other
+        }
+    }
+}
+
+/// Moves the `SENT`, `TO` and `PARTICIPATED_IN` edges of `alias_id` to `canonical_id`. Edges
+/// the canonical employee already has are not duplicated.
+async fn repoint_alias_edges(
+    txn: &mut neo4rs::Txn,
+    alias_id: &str,
+    canonical_id: &str,
+) -> Result<AliasMerge> {
+    let mut moved = AliasMerge::default();
+    for (rel, outgoing, count) in [
+        ("SENT", true, &mut moved.sent),
+        ("TO", false, &mut moved.to),
+        ("PARTICIPATED_IN", true, &mut moved.participated_in),
+    ] {
+        let (old, new) = if outgoing {
+            (format!("(a)-[r:{rel}]->(n)"), format!("(c)-[:{rel}]->(n)"))
+        } else {
+            (format!("(n)-[r:{rel}]->(a)"), format!("(n)-[:{rel}]->(c)"))
+        };
+        let q = query(&format!(
+            r#"
+MATCH (a:Employee {{employee_id: $alias_id}})
+MATCH (c:Employee {{employee_id: $canonical_id}})
+MATCH {old}
+MERGE {new}
+DELETE r
+RETURN count(*) AS moved
+"#
+        ))
+        .param("alias_id", alias_id.to_string())
+        .param("canonical_id", canonical_id.to_string());
+        let mut stream = txn
+            .execute(q)
+            .await
+            .with_context(|| format!("move {rel} edges"))?;
+        *count = stream
+            .next(txn.handle())
+            .await
+            .with_context(|| format!("read moved {rel} edges"))?
+            .and_then(|row| row.get("moved").ok())
+            .unwrap_or(0);
+    }
+    Ok(moved)
+}
+
+/// Applies `rules` to employees that have an email but no role. Returns how many were updated.
+pub async fn backfill_employee_roles(graph: &Graph, rules: &[RoleRule]) -> Result<usize> {
+    let q = query(
+        r#"
+MATCH (e:Employee)
+WHERE e.role IS NULL AND e.email IS NOT NULL
+RETURN e.employee_id AS employee_id, e.email AS email
+"#,
+    );
+    let mut stream = graph.execute(q).await.context("list employees without role")?;
+    let mut updates: Vec<BoltType> = Vec::new();
+    while let Some(row) = stream.next().await.context("read employees without role")? {
+        let employee_id: String = row.get("employee_id").unwrap_or_default();
+        let email: String = row.get("email").unwrap_or_default();
+        if let Some(role) = role_for_email(&email, rules) {
+            let mut m = BoltMap::new();
+            m.put("employee_id".into(), employee_id.into());
+            m.put("role".into(), role.into());
+            updates.push(BoltType::Map(m));
+        }
+    }
+    if updates.is_empty() {
+        return Ok(0);
+    }
+
+    let q = query(
+        r#"
+UNWIND $updates AS u
+MATCH (e:Employee {employee_id: u.employee_id})
+WHERE e.role IS NULL
+SET e.role = u.role
+RETURN e.employee_id AS employee_id, e.role AS role
+"#,
+    )
+    .param("updates", updates);
+    let mut stream = graph.execute(q).await.context("backfill employee roles")?;
+    let mut updated = 0;
+    while let Some(row) = stream.next().await.context("read backfill employee roles")? {
+        let employee_id: String = row.get("employee_id").unwrap_or_default();
+        let role: String = row.get("role").unwrap_or_default();
+        if let Some(role) = EmployeeRole::parse(&role) {
+            crate::roles::record_stored_role(&employee_id, role);
+        }
+        updated += 1;
+    }
+    Ok(updated)
+}
+
+/// The `role` of every unseeded `Employee` that has one, by employee id; unknown role names are
+/// skipped. Seeded employees, current or removed, take their role from `employees.toml`.
+pub async fn load_employee_roles(graph: &Graph) -> Result<HashMap<String, EmployeeRole>> {
+    let q = query(
+        r#"
+MATCH (e:Employee)
+WHERE e.role IS NOT NULL AND e.seeded IS NULL
+RETURN e.employee_id AS employee_id, e.role AS role
+"#,
+    );
+    let mut stream = graph.execute(q).await.context("load employee roles")?;
+    let mut roles = HashMap::new();
+    while let Some(row) = stream.next().await.context("read employee roles")? {
+        let employee_id: String = row.get("employee_id").unwrap_or_default();
+        let role: String = row.get("role").unwrap_or_default();
+        if let Some(role) = EmployeeRole::parse(&role) {
+            roles.insert(employee_id, role);
+        }
+    }
+    Ok(roles)
+}
+
+/// Recomputes every `COMMUNICATES_WITH` count as the number of distinct messages the sender
+/// `SENT` `TO` the recipient, creating missing edges. Edges with no such message get count 0.
+/// `last_contact` becomes the latest `sent_at` of those messages, if any has one. Returns (edges
+/// recomputed, edges zeroed).
+pub async fn repair_communication_counts(graph: &Graph) -> Result<(i64, i64)> {
+    let mut txn = graph.start_txn().await.context("start communication repair txn")?;
+    let result = async {
+        let q = query(
+            r#"
+MATCH (s:Employee)-[cw:COMMUNICATES_WITH]->(r:Employee)
+WHERE NOT (s)-[:SENT]->(:EmailMessage)-[:TO]->(r)
+SET cw.count = 0
+RETURN count(cw) AS zeroed
+"#,
+        );
+        let mut stream = txn.execute(q).await.context("zero communication counts")?;
+        let zeroed: i64 = stream
+            .next(txn.handle())
+            .await
+            .context("read zeroed communication counts")?
+            .and_then(|row| row.get("zeroed").ok())
+            .unwrap_or(0);
+
+        let q = query(
+            r#"
+MATCH (s:Employee)-[:SENT]->(m:EmailMessage)-[:TO]->(r:Employee)
+WHERE s <> r
+WITH s, r, count(DISTINCT m) AS messages, max(m.sent_at) AS last_sent
+MERGE (s)-[cw:COMMUNICATES_WITH]->(r)
+ON CREATE SET cw.created_at = datetime()
+SET cw.count = messages,
+    cw.last_contact = coalesce(last_sent, cw.last_contact)
+RETURN count(cw) AS recomputed
+"#,
+        );
+        let mut stream = txn.execute(q).await.context("recompute communication counts")?;
+        let recomputed: i64 = stream
+            .next(txn.handle())
+            .await
+            .context("read recomputed communication counts")?
+            .and_then(|row| row.get("recomputed").ok())
+            .unwrap_or(0);
+        Ok::<_, anyhow::Error>((recomputed, zeroed))
+    }
+    .await;
+
+    match result {
+        Ok(counts) => {
+            txn.commit().await.context("commit communication repair txn")?;
+            Ok(counts)
+        }
+        Err(e) => {
+            let _ = txn.rollback().await;
+            Err(e)
+        }
+    }
+}
+
+/// Formats without a zone seen in old corpora, read as UTC.
+const EMAIL_DATE_FALLBACKS: &[&str] = &[
+    "%a %b %e %H:%M:%S %Y",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%m/%d/%Y %H:%M:%S",
+    "%m/%d/%Y %I:%M:%S %p",
+    "%m/%d/%Y %H:%M",
+];
+
+/// An email `Date` header (RFC 2822, e.g. `Mon, 14 May 2001 16:39:00 -0700 (PDT)`) as RFC 3339,
+/// or `None` when it doesn't parse. RFC 3339 and the zone-less `EMAIL_DATE_FALLBACKS` are also
+/// accepted.
+pub fn email_date_rfc3339(date: &str) -> Option<String> {
+    // A trailing zone comment such as `(PDT)` is not part of RFC 2822's grammar for chrono.
+    let date = date.split_once('(').map_or(date, |(d, _)| d).trim();
+    if date.is_empty() {
+        return None;
+    }
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc2822(date) {
+        return Some(dt.to_rfc3339());
+    }
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(date) {
+        return Some(dt.to_rfc3339());
+    }
+    EMAIL_DATE_FALLBACKS.iter().find_map(|fmt| {
+        chrono::NaiveDateTime::parse_from_str(date, fmt)
+            .ok()
+            .map(|dt| dt.and_utc().to_rfc3339())
+    })
+}
+
+/// Moves the raw `date` of messages ingested before `sent_at` existed to `date_raw` and sets
+/// `sent_at` where it parses. Returns (parsed, unparseable); messages already migrated are
+/// skipped, so this is cheap to run on every start.
+pub async fn backfill_email_sent_at(graph: &Graph) -> Result<(usize, usize)> {
+    let q = query(
+        r#"
+MATCH (m:EmailMessage)
+WHERE m.date IS NOT NULL
+RETURN m.message_id AS message_id, toString(m.date) AS date
+"#,
+    );
+    let mut stream = graph.execute(q).await.context("query legacy email dates")?;
+    let mut rows: Vec<(String, Option<String>)> = Vec::new();
+    while let Some(row) = stream.next().await.context("read legacy email dates")? {
+        let message_id: String = row.get("message_id").unwrap_or_default();
+        let date: String = row.get("date").unwrap_or_default();
+        rows.push((message_id, email_date_rfc3339(&date)));
+    }
+
+    let parsed = rows.iter().filter(|(_, sent_at)| sent_at.is_some()).count();
+    for chunk in rows.chunks(500) {
+        let ids: Vec<String> = chunk.iter().map(|(id, _)| id.clone()).collect();
+        // Empty means the date didn't parse; only `date_raw` is kept then.
+        let sent_at: Vec<String> = chunk
+            .iter()
+            .map(|(_, s)| s.clone().unwrap_or_default())
+            .collect();
+        let q = query(
+            r#"
+UNWIND range(0, size($ids) - 1) AS i
+MATCH (m:EmailMessage {message_id: $ids[i]})
+SET m.date_raw = m.date,
+    m.sent_at = CASE WHEN $sent_at[i] = '' THEN NULL ELSE datetime($sent_at[i]) END
+REMOVE m.date
+"#,
+        )
+        .param("ids", ids)
+        .param("sent_at", sent_at);
+        graph.run(q).await.context("backfill email sent_at")?;
+    }
+    Ok((parsed, rows.len() - parsed))
+}
+
+/// One ingested message, as `persist_email_message` writes it.
+#[derive(Debug, Clone)]
+pub struct EmailMessageRecord<'a> {
+    pub message_id: &'a str,
+    pub file: &'a str,
+    pub subject: &'a str,
+    /// The raw `Date` header; `sent_at` is parsed from it when it is RFC 2822.
+    pub date: &'a str,
+    pub folder: &'a str,
+    pub from_employee_id: &'a str,
+    pub to_employee_ids: &'a [String],
+    pub topic_ids: &'a [String],
+    pub urgency: f64,
+    pub sentiment: &'a str,
+}
+
 pub async fn persist_email_message(
     graph: &Graph,
-    message_id: &str,
-    file: &str,
-    subject: &str,
-    date: &str,
-    from_employee_id: &str,
-    to_employee_ids: &[String],
-    topic_ids: &[String],
+    message: &EmailMessageRecord<'_>,
 ) -> Result<GraphUpdateResult> {
     let mut txn = graph.start_txn().await.context("start email txn")?;
 
@@ -74,36 +592,62 @@ MERGE (m:EmailMessage {message_id: $message_id})
 ON CREATE SET m.created_at = datetime()
 SET m.file = $file,
     m.subject = $subject,
-    m.date = $date
+    m.date_raw = $date,
+    m.sent_at = CASE WHEN $sent_at IS NULL THEN NULL ELSE datetime($sent_at) END,
+    m.folder = $folder,
+    m.urgency = $urgency,
+    m.sentiment = $sentiment
+REMOVE m.placeholder, m.date
 WITH m
-MERGE (sender:Employee {employee_id: $from_employee_id})
+MERGE (s:Employee {employee_id: $from_employee_id})
+WITH m, coalesce(s.canonical_employee_id, $from_employee_id) AS sender_id
+MERGE (sender:Employee {employee_id: sender_id})
 MERGE (sender)-[:SENT]->(m)
 WITH m, sender
-UNWIND $to_employee_ids AS to_id
-MERGE (r:Employee {employee_id: to_id})
-MERGE (m)-[:TO]->(r)
-WITH m, sender
-UNWIND $to_employee_ids AS to_id
-MERGE (r:Employee {employee_id: to_id})
-MERGE (sender)-[cw:COMMUNICATES_WITH]->(r)
-ON CREATE SET cw.created_at = datetime(), cw.count = 0
-SET cw.count = coalesce(cw.count, 0) + 1
-WITH m
-UNWIND $topic_ids AS tid
-MERGE (t:Topic {topic_id: tid})
-ON CREATE SET t.created_at = datetime(), t.topic = tid
-MERGE (m)-[:ABOUT]->(t)
-MERGE (m)-[:DEPENDS_ON]->(t)
+CALL {
+  WITH m, sender
+  UNWIND $to_employee_ids AS to_id
+  MERGE (to:Employee {employee_id: to_id})
+  WITH m, sender, collect(DISTINCT coalesce(to.canonical_employee_id, to_id)) AS recipient_ids
+  UNWIND recipient_ids AS recipient_id
+  MERGE (r:Employee {employee_id: recipient_id})
+  // Only a new TO edge is a new contact, so re-ingesting a message counts nothing twice.
+  OPTIONAL MATCH (m)-[seen:TO]->(r)
+  WITH m, sender, r, seen IS NULL AS first_seen
+  MERGE (m)-[:TO]->(r)
+  FOREACH (_ IN CASE WHEN first_seen AND r <> sender THEN [1] ELSE [] END |
+    MERGE (sender)-[cw:COMMUNICATES_WITH]->(r)
+    ON CREATE SET cw.created_at = datetime(), cw.count = 0
+    SET cw.count = coalesce(cw.count, 0) + 1,
+        cw.last_contact = CASE
+          WHEN $sent_at IS NULL THEN cw.last_contact
+          WHEN cw.last_contact IS NULL OR datetime($sent_at) > cw.last_contact
+            THEN datetime($sent_at)
+          ELSE cw.last_contact
+        END)
+}
+CALL {
+  WITH m
+  UNWIND $topic_ids AS tid
+  MERGE (t:Topic {topic_id: tid})
+  ON CREATE SET t.created_at = datetime(), t.topic = tid
+  MERGE (m)-[:ABOUT]->(t)
+  MERGE (m)-[:DEPENDS_ON]->(t)
+}
 RETURN elementId(m) AS message_node_id
 "#,
     )
-    .param("message_id", message_id.to_string())
-    .param("file", file.to_string())
-    .param("subject", subject.to_string())
-    .param("date", date.to_string())
-    .param("from_employee_id", from_employee_id.to_string())
-    .param("to_employee_ids", to_employee_ids.to_vec())
-    .param("topic_ids", topic_ids.to_vec());
+    .param("message_id", message.message_id.to_string())
+    .param("file", message.file.to_string())
+    .param("subject", message.subject.to_string())
+    .param("date", message.date.to_string())
+    .param("sent_at", email_date_rfc3339(message.date))
+    .param("folder", message.folder.to_string())
+    .param("from_employee_id", message.from_employee_id.to_string())
+    .param("to_employee_ids", message.to_employee_ids.to_vec())
+    .param("topic_ids", message.topic_ids.to_vec())
+    .param("urgency", message.urgency)
+    .param("sentiment", message.sentiment.to_string());
 
     let mut stream = txn
         .execute(q)
@@ -121,266 +665,2064 @@ RETURN elementId(m) AS message_node_id
     txn.commit().await.context("commit email txn")?;
 
     Ok(GraphUpdateResult {
-        nodes: vec![message_node_id],
+        nodes: vec![GraphUpdateEntry::node(
+            message_node_id,
+            "EmailMessage",
+            "message_id",
+            message.message_id,
+        )],
         edges: Vec::new(),
     })
 }
 
-pub async fn persist_knowledge_cluster(
+/// Links `message_id` to the message it replies to. A parent that has not been ingested yet
+/// becomes a placeholder `EmailMessage` that `persist_email_message` fills in later.
+pub async fn persist_email_thread(
     graph: &Graph,
-    cluster_id: &str,
-    label: &str,
-    member_message_ids: &[String],
+    message_id: &str,
+    parent_message_id: &str,
+    references: &[String],
 ) -> Result<GraphUpdateResult> {
-    let mut txn = graph.start_txn().await.context("start cluster txn")?;
+    let mut txn = graph.start_txn().await.context("start email thread txn")?;
 
     let q = query(
         r#"
-MERGE (c:KnowledgeCluster {cluster_id: $cluster_id})
-ON CREATE SET c.created_at = datetime()
-SET c.name = $label
-WITH c
-UNWIND $member_message_ids AS mid
-MATCH (m:EmailMessage {message_id: mid})
-MERGE (m)-[:IN_CLUSTER]->(c)
-RETURN elementId(c) AS cluster_node_id
+MATCH (m:EmailMessage {message_id: $message_id})
+SET m.references = $references
+MERGE (p:EmailMessage {message_id: $parent_message_id})
+ON CREATE SET p.created_at = datetime(), p.placeholder = true
+MERGE (m)-[r:REPLIES_TO]->(p)
+ON CREATE SET r.created_at = datetime()
+RETURN elementId(p) AS parent_node_id, elementId(r) AS edge_id
 "#,
     )
-    .param("cluster_id", cluster_id.to_string())
-    .param("label", label.to_string())
-    .param("member_message_ids", member_message_ids.to_vec());
+    .param("message_id", message_id.to_string())
+    .param("parent_message_id", parent_message_id.to_string())
+    .param("references", references.to_vec());
 
     let mut stream = txn
         .execute(q)
         .await
-        .context("persist knowledge cluster")?;
+        .context("persist email thread")?;
     let row = stream
         .next(txn.handle())
         .await
-        .context("read persist knowledge cluster")?
-        .context("persist knowledge cluster returned no row")?;
-    let cluster_node_id: String = row
-        .get("cluster_node_id")
-        .context("missing cluster_node_id")?;
+        .context("read persist email thread")?
+        .context("persist email thread returned no row")?;
+    let parent_node_id: String = row
+        .get("parent_node_id")
+        .context("missing parent_node_id")?;
+    let edge_id: String = row.get("edge_id").context("missing edge_id")?;
 
-    txn.commit().await.context("commit cluster txn")?;
+    txn.commit().await.context("commit email thread txn")?;
 
+    let parent =
+        GraphUpdateEntry::node(parent_node_id, "EmailMessage", "message_id", parent_message_id);
+    let edge = GraphUpdateEntry::edge(
+        edge_id,
+        "REPLIES_TO",
+        &format!("EmailMessage {message_id}"),
+        &parent.display,
+    );
     Ok(GraphUpdateResult {
-        nodes: vec![cluster_node_id],
-        edges: Vec::new(),
+        nodes: vec![parent],
+        edges: vec![edge],
     })
 }
 
-impl GraphUpdateResult {
-    pub fn empty() -> Self {
-        Self {
+/// The thread a message belongs to, derived from the thread's root: the first `References` id,
+/// else the parent, else the message itself. Hashed so it is safe in a URL.
+pub fn email_thread_id(
+    message_id: &str,
+    parent_message_id: Option<&str>,
+    references: &[String],
+) -> String {
+    let root = references
+        .first()
+        .map(String::as_str)
+        .or(parent_message_id)
+        .unwrap_or(message_id);
+    format!("thread_{}", &crate::utils::content_hash(root.trim())[..16])
+}
+
+/// `subject` without `Re:` / `Fw:` / `Fwd:` prefixes, lowercased, whitespace collapsed.
+pub fn normalize_subject(subject: &str) -> String {
+    let mut s = subject.trim();
+    loop {
+        let lower = s.to_ascii_lowercase();
+        let Some(prefix) = ["re:", "fwd:", "fw:"].iter().find(|p| lower.starts_with(*p)) else {
+            break;
+        };
+        s = s[prefix.len()..].trim_start();
+    }
+    s.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Puts `message_ids` (a message and, for a reply, its parent) `IN_THREAD` of `thread_id`. The
+/// thread keeps the first non-empty normalized subject it is given.
+pub async fn persist_email_thread_membership(
+    graph: &Graph,
+    thread_id: &str,
+    message_ids: &[String],
+    subject: &str,
+) -> Result<()> {
+    let q = query(
+        r#"
+MERGE (t:Thread {thread_id: $thread_id})
+ON CREATE SET t.created_at = datetime()
+SET t.subject = CASE WHEN coalesce(t.subject, '') = '' THEN $subject ELSE t.subject END
+WITH t
+UNWIND $message_ids AS mid
+MATCH (m:EmailMessage {message_id: mid})
+MERGE (m)-[r:IN_THREAD]->(t)
+ON CREATE SET r.created_at = datetime()
+"#,
+    )
+    .param("thread_id", thread_id.to_string())
+    .param("message_ids", message_ids.to_vec())
+    .param("subject", normalize_subject(subject));
+    graph.run(q).await.context("persist email thread membership")?;
+    Ok(())
+}
+
+/// A message of a thread, as `GET /v1/threads/{thread_id}` returns it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ThreadMessage {
+    pub message_id: String,
+    pub subject: String,
+    /// RFC 3339, when the `Date` header parsed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sent_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    pub to: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_reply_to: Option<String>,
+    /// Referenced by a reply but not ingested yet.
+    pub placeholder: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EmailThread {
+    pub thread_id: String,
+    /// Normalized subject (no `Re:` / `Fwd:`, lowercase).
+    pub subject: String,
+    /// Oldest first (by `sent_at`, else ingestion time).
+    pub messages: Vec<ThreadMessage>,
+    /// Employee ids that sent or received a message of the thread, sorted.
+    pub participants: Vec<String>,
+}
+
+/// The thread with its messages, or `None` when there is no such thread.
+pub async fn load_email_thread(graph: &Graph, thread_id: &str) -> Result<Option<EmailThread>> {
+    let q = query(
+        r#"
+MATCH (t:Thread {thread_id: $thread_id})
+OPTIONAL MATCH (m:EmailMessage)-[:IN_THREAD]->(t)
+OPTIONAL MATCH (s:Employee)-[:SENT]->(m)
+OPTIONAL MATCH (m)-[:TO]->(r:Employee)
+OPTIONAL MATCH (m)-[:REPLIES_TO]->(p:EmailMessage)
+WITH t, m, head(collect(DISTINCT s.employee_id)) AS from_id,
+     collect(DISTINCT r.employee_id) AS to_ids, head(collect(DISTINCT p.message_id)) AS parent
+RETURN coalesce(t.subject, '') AS thread_subject, m.message_id AS message_id,
+       coalesce(m.subject, '') AS subject, toString(m.sent_at) AS sent_at, from_id, to_ids,
+       parent, coalesce(m.placeholder, false) AS placeholder
+ORDER BY coalesce(m.sent_at, m.created_at), message_id
+"#,
+    )
+    .param("thread_id", thread_id.to_string());
+    let mut stream = graph.execute(q).await.context("query email thread")?;
+    let mut thread: Option<EmailThread> = None;
+    let mut participants = std::collections::BTreeSet::new();
+    while let Some(row) = stream.next().await.context("read email thread")? {
+        let thread = thread.get_or_insert_with(|| EmailThread {
+            thread_id: thread_id.to_string(),
+            subject: row.get("thread_subject").unwrap_or_default(),
+            messages: Vec::new(),
+            participants: Vec::new(),
+        });
+        let Some(message_id) = row.get::<Option<String>>("message_id").ok().flatten() else {
+            continue;
+        };
+        let from = row.get::<Option<String>>("from_id").ok().flatten();
+        let to: Vec<String> = row.get("to_ids").unwrap_or_default();
+        participants.extend(from.iter().cloned());
+        participants.extend(to.iter().cloned());
+        thread.messages.push(ThreadMessage {
+            message_id,
+            subject: row.get("subject").unwrap_or_default(),
+            sent_at: row.get::<Option<String>>("sent_at").ok().flatten(),
+            from,
+            to,
+            in_reply_to: row.get::<Option<String>>("parent").ok().flatten(),
+            placeholder: row.get("placeholder").unwrap_or_default(),
+        });
+    }
+    Ok(thread.map(|mut t| {
+        t.participants = participants.into_iter().collect();
+        t
+    }))
+}
+
+/// Attaches an annotation to version `version` of a decision (or truth object, for traces
+/// produced by knowledge ingest).
+pub async fn persist_annotation(
+    graph: &Graph,
+    decision_id: &str,
+    version: i64,
+    annotation_id: &str,
+    author: &str,
+    text: &str,
+) -> Result<GraphUpdateResult> {
+    let q = query(
+        r#"
+MATCH (v)
+WHERE ((v:DecisionVersion AND v.decision_id = $decision_id)
+    OR (v:TruthVersion AND v.truth_id = $decision_id))
+  AND v.version = $version
+WITH v LIMIT 1
+CREATE (a:Annotation {
+  annotation_id: $annotation_id,
+  author: $author,
+  text: $text,
+  created_at: datetime()
+})
+CREATE (a)-[r:ANNOTATES]->(v)
+WITH a, r, v
+MERGE (e:Employee {employee_id: $author})
+MERGE (e)-[:WROTE]->(a)
+RETURN elementId(a) AS annotation_node_id, elementId(r) AS edge_id, labels(v)[0] AS version_label
+"#,
+    )
+    .param("decision_id", decision_id.to_string())
+    .param("version", version)
+    .param("annotation_id", annotation_id.to_string())
+    .param("author", author.to_string())
+    .param("text", text.to_string());
+
+    let mut stream = graph.execute(q).await.context("persist annotation")?;
+    let Some(row) = stream.next().await.context("read persist annotation")? else {
+        return Ok(GraphUpdateResult {
             nodes: Vec::new(),
             edges: Vec::new(),
+        });
+    };
+    let node_id: String = row
+        .get("annotation_node_id")
+        .context("missing annotation_node_id")?;
+    let edge_id: String = row.get("edge_id").context("missing edge_id")?;
+    let version_label: String = row.get("version_label").context("missing version_label")?;
+
+    let node = GraphUpdateEntry::node(node_id, "Annotation", "annotation_id", annotation_id);
+    let edge = GraphUpdateEntry::edge(
+        edge_id,
+        "ANNOTATES",
+        &node.display,
+        &format!("{version_label} {decision_id} v{version}"),
+    );
+    Ok(GraphUpdateResult {
+        nodes: vec![node],
+        edges: vec![edge],
+    })
+}
+
+/// Where `assign_message_to_cluster` put a message.
+#[derive(Debug, Clone)]
+pub struct ClusterAssignment {
+    pub cluster_id: String,
+    /// The message was already `IN_CLUSTER`; nothing was changed.
+    pub existing: bool,
+}
+
+/// How often a centroid update is retried after losing a race with a concurrent one.
+const CENTROID_UPDATE_ATTEMPTS: usize = 5;
+
+/// Puts `message_id` in the cluster whose centroid is most similar to `embedding`, or in a new
+/// cluster (named after the message's first topic) when none reaches `threshold`. A message
+/// already `IN_CLUSTER` is left where it is, so re-ingesting the same emails is a no-op.
+///
+/// Centroids live on the `KnowledgeCluster` nodes (`centroid`, `member_count`) and are updated
+/// as a running mean. Each update checks the node's `centroid_version`, so a concurrent writer
+/// makes it re-read and retry instead of overwriting the other's change.
+pub async fn assign_message_to_cluster(
+    graph: &Graph,
+    message_id: &str,
+    embedding: &[f32],
+    threshold: f32,
+) -> Result<ClusterAssignment> {
+    if let Some(cluster_id) = message_cluster(graph, message_id).await? {
+        return Ok(ClusterAssignment {
+            cluster_id,
+            existing: true,
+        });
+    }
+
+    for _ in 0..CENTROID_UPDATE_ATTEMPTS {
+        let mut best: Option<(ClusterCentroid, f32)> = None;
+        for c in load_cluster_centroids(graph).await? {
+            let sim = crate::app_state::cosine_sim(&c.centroid, embedding);
+            if best.as_ref().map(|(_, s)| sim > *s).unwrap_or(true) {
+                best = Some((c, sim));
+            }
+        }
+        let Some((cluster, _)) = best.filter(|(_, sim)| *sim >= threshold) else {
+            let cluster_id = create_cluster_for_message(graph, message_id, embedding).await?;
+            return Ok(ClusterAssignment {
+                cluster_id,
+                existing: false,
+            });
+        };
+        if update_cluster_centroid(graph, &cluster, message_id, embedding).await? {
+            return Ok(ClusterAssignment {
+                cluster_id: cluster.cluster_id,
+                existing: false,
+            });
         }
     }
+    Err(anyhow::anyhow!(
+        "cluster assignment of {message_id} kept conflicting with concurrent updates"
+    ))
 }
 
-pub async fn seed_employees(graph: &Graph) -> Result<()> {
-    // Idempotent seed. These employees become the canonical identities for the UI.
-    // Note: neo4rs params must be Bolt-compatible (avoid passing serde_json::Value).
-    let employees = [
-        ("employee_john", "John", "ceo"),
-        ("employee_sarah", "Sarah", "hr"),
-        ("employee_bob", "Bob", "engineer"),
-    ];
+/// Adds `message_id` to cluster `cluster_id` regardless of similarity (e.g. to keep a thread in
+/// one cluster), moving its centroid towards `embedding`. `false` when the cluster doesn't exist.
+pub async fn join_knowledge_cluster(
+    graph: &Graph,
+    cluster_id: &str,
+    message_id: &str,
+    embedding: &[f32],
+) -> Result<bool> {
+    if message_cluster(graph, message_id).await?.is_some() {
+        return Ok(true);
+    }
+    for _ in 0..CENTROID_UPDATE_ATTEMPTS {
+        let Some(cluster) = load_cluster_centroid(graph, cluster_id).await? else {
+            return Ok(false);
+        };
+        if update_cluster_centroid(graph, &cluster, message_id, embedding).await? {
+            return Ok(true);
+        }
+    }
+    Err(anyhow::anyhow!(
+        "joining {message_id} to {cluster_id} kept conflicting with concurrent updates"
+    ))
+}
 
-    for (employee_id, name, role) in employees {
-        let q = query(
-            r#"
-MERGE (emp:Employee {employee_id: $employee_id})
-ON CREATE SET emp.created_at = datetime()
-SET emp.name = $name,
-    emp.role = $role
+struct ClusterCentroid {
+    cluster_id: String,
+    centroid: Vec<f32>,
+    member_count: i64,
+    version: i64,
+}
+
+const CENTROID_RETURN: &str = r#"
+RETURN c.cluster_id AS cluster_id,
+       coalesce(c.centroid, []) AS centroid,
+       coalesce(c.member_count, 0) AS member_count,
+       coalesce(c.centroid_version, 0) AS version
+"#;
+
+fn centroid_from_row(row: &neo4rs::Row) -> Result<ClusterCentroid> {
+    let centroid: Vec<f64> = row.get("centroid").context("missing centroid")?;
+    Ok(ClusterCentroid {
+        cluster_id: row.get("cluster_id").context("missing cluster_id")?,
+        centroid: centroid.into_iter().map(|x| x as f32).collect(),
+        member_count: row.get("member_count").context("missing member_count")?,
+        version: row.get("version").context("missing version")?,
+    })
+}
+
+async fn load_cluster_centroids(graph: &Graph) -> Result<Vec<ClusterCentroid>> {
+    let q = query(&format!(
+        "MATCH (c:KnowledgeCluster) WHERE c.centroid IS NOT NULL{CENTROID_RETURN}"
+    ));
+    let mut stream = graph.execute(q).await.context("load cluster centroids")?;
+    let mut out = Vec::new();
+    while let Some(row) = stream.next().await.context("read cluster centroid row")? {
+        out.push(centroid_from_row(&row)?);
+    }
+    Ok(out)
+}
+
+async fn load_cluster_centroid(graph: &Graph, cluster_id: &str) -> Result<Option<ClusterCentroid>> {
+    let q = query(&format!(
+        "MATCH (c:KnowledgeCluster {{cluster_id: $cluster_id}}){CENTROID_RETURN}"
+    ))
+    .param("cluster_id", cluster_id.to_string());
+    let mut stream = graph.execute(q).await.context("load cluster centroid")?;
+    match stream.next().await.context("read cluster centroid")? {
+        Some(row) => centroid_from_row(&row).map(Some),
+        None => Ok(None),
+    }
+}
+
+async fn message_cluster(graph: &Graph, message_id: &str) -> Result<Option<String>> {
+    let q = query(
+        r#"
+MATCH (:EmailMessage {message_id: $message_id})-[:IN_CLUSTER]->(c:KnowledgeCluster)
+RETURN c.cluster_id AS cluster_id
+LIMIT 1
 "#,
-        )
-        .param("employee_id", employee_id.to_string())
-        .param("name", name.to_string())
-        .param("role", role.to_string());
+    )
+    .param("message_id", message_id.to_string());
+    let mut stream = graph.execute(q).await.context("load message cluster")?;
+    match stream.next().await.context("read message cluster")? {
+        Some(row) => Ok(Some(row.get("cluster_id").context("missing cluster_id")?)),
+        None => Ok(None),
+    }
+}
+
+/// The centroid of `member_count` members once `embedding` joins them.
+fn running_mean(centroid: &[f32], member_count: i64, embedding: &[f32]) -> Vec<f64> {
+    let k = member_count.max(0) as f32;
+    if centroid.is_empty() || k == 0.0 {
+        return embedding.iter().map(|&x| x as f64).collect();
+    }
+    centroid
+        .iter()
+        .zip(embedding)
+        .map(|(&c, &e)| ((c * k + e) / (k + 1.0)) as f64)
+        .collect()
+}
+
+/// Writes the running mean of `cluster`'s centroid and `embedding` and links the message, if
+/// the node's `centroid_version` is still the one `cluster` was read at. `false` when another
+/// writer got there first.
+async fn update_cluster_centroid(
+    graph: &Graph,
+    cluster: &ClusterCentroid,
+    message_id: &str,
+    embedding: &[f32],
+) -> Result<bool> {
+    let centroid = running_mean(&cluster.centroid, cluster.member_count, embedding);
+
+    // Setting `updated_at` first takes the node's write lock, so the version check below sees
+    // the latest committed value.
+    let q = query(
+        r#"
+MATCH (m:EmailMessage {message_id: $message_id})
+MATCH (c:KnowledgeCluster {cluster_id: $cluster_id})
+SET c.updated_at = datetime()
+WITH m, c
+WHERE coalesce(c.centroid_version, 0) = $expected
+SET c.centroid = $centroid,
+    c.member_count = coalesce(c.member_count, 0) + 1,
+    c.centroid_version = $expected + 1
+MERGE (m)-[:IN_CLUSTER]->(c)
+RETURN c.cluster_id AS cluster_id
+"#,
+    )
+    .param("message_id", message_id.to_string())
+    .param("cluster_id", cluster.cluster_id.clone())
+    .param("expected", cluster.version)
+    .param("centroid", centroid);
+
+    let mut txn = graph.start_txn().await.context("start centroid txn")?;
+    let mut stream = txn.execute(q).await.context("update cluster centroid")?;
+    let updated = stream
+        .next(txn.handle())
+        .await
+        .context("read cluster centroid update")?
+        .is_some();
+    txn.commit().await.context("commit centroid txn")?;
+    Ok(updated)
+}
+
+async fn create_cluster_for_message(
+    graph: &Graph,
+    message_id: &str,
+    embedding: &[f32],
+) -> Result<String> {
+    let cluster_id = format!("cluster_{}", Uuid::new_v4());
+    let q = query(
+        r#"
+MATCH (m:EmailMessage {message_id: $message_id})
+OPTIONAL MATCH (m)-[:ABOUT]->(t:Topic)
+WITH m, head(collect(t.topic_id)) AS topic
+CREATE (c:KnowledgeCluster {cluster_id: $cluster_id})
+SET c.created_at = datetime(),
+    c.name = coalesce(topic, 'cluster'),
+    c.centroid = $centroid,
+    c.member_count = 1,
+    c.centroid_version = 1
+MERGE (m)-[:IN_CLUSTER]->(c)
+RETURN c.cluster_id AS cluster_id
+"#,
+    )
+    .param("message_id", message_id.to_string())
+    .param("cluster_id", cluster_id.clone())
+    .param(
+        "centroid",
+        embedding.iter().map(|&x| x as f64).collect::<Vec<f64>>(),
+    );
+    let mut stream = graph.execute(q).await.context("create knowledge cluster")?;
+    stream
+        .next()
+        .await
+        .context("read create knowledge cluster")?
+        .with_context(|| format!("email message {message_id} not found"))?;
+    Ok(cluster_id)
+}
+
+/// A topic of a cluster's messages, with how many members are `ABOUT` it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ClusterTopic {
+    pub topic: String,
+    pub messages: i64,
+}
+
+/// A `KnowledgeCluster` as listed by `list_knowledge_clusters`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct KnowledgeClusterSummary {
+    pub cluster_id: String,
+    pub name: String,
+    /// One-sentence LLM description; empty for heuristically named clusters.
+    pub description: String,
+    pub member_count: i64,
+    /// Up to five topics of the members, most common first.
+    pub top_topics: Vec<ClusterTopic>,
+    /// Up to ten member subjects, used for relabeling.
+    #[serde(skip)]
+    pub subjects: Vec<String>,
+}
+
+/// Binds `m` to the messages outside every cluster of at least `$min_size` members.
+const UNCLUSTERED_MEMBERS: &str = r#"
+MATCH (m:EmailMessage)
+WHERE NOT EXISTS {
+  MATCH (m)-[:IN_CLUSTER]->(c:KnowledgeCluster)
+  WHERE size([(x:EmailMessage)-[:IN_CLUSTER]->(c) | x]) >= $min_size
+}
+"#;
+
+/// Binds `m` to the members of cluster `$cluster_id`.
+const CLUSTER_MEMBERS: &str = r#"
+MATCH (m:EmailMessage)-[:IN_CLUSTER]->(:KnowledgeCluster {cluster_id: $cluster_id})
+"#;
+
+/// Collects the top five topics of `members` as `topic_names` and `topic_counts`.
+const MEMBER_TOPICS: &str = r#"
+CALL {
+  WITH members
+  UNWIND members AS m
+  MATCH (m)-[:ABOUT]->(t:Topic)
+  WITH t.topic_id AS topic, count(*) AS messages
+  ORDER BY messages DESC, topic
+  RETURN collect(topic)[..5] AS topic_names, collect(messages)[..5] AS topic_counts
+}
+"#;
+
+fn cluster_summary_from_row(row: &neo4rs::Row) -> Result<KnowledgeClusterSummary> {
+    let topic_names: Vec<String> = row.get("topic_names").unwrap_or_default();
+    let topic_counts: Vec<i64> = row.get("topic_counts").unwrap_or_default();
+    Ok(KnowledgeClusterSummary {
+        cluster_id: row.get("cluster_id").context("missing cluster_id")?,
+        name: row.get("name").unwrap_or_default(),
+        description: row.get("description").unwrap_or_default(),
+        member_count: row.get("member_count").unwrap_or_default(),
+        top_topics: topic_names
+            .into_iter()
+            .zip(topic_counts)
+            .map(|(topic, messages)| ClusterTopic { topic, messages })
+            .collect(),
+        subjects: row.get("subjects").unwrap_or_default(),
+    })
+}
+
+/// Clusters with at least `min_size` members, ordered by member count (largest first unless
+/// `ascending`), skipping `offset` and returning at most `limit`. Each has up to ten member
+/// subjects.
+pub async fn list_knowledge_clusters(
+    graph: &Graph,
+    min_size: usize,
+    ascending: bool,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<KnowledgeClusterSummary>> {
+    let direction = if ascending { "ASC" } else { "DESC" };
+    let q = query(&format!(
+        r#"
+MATCH (m:EmailMessage)-[:IN_CLUSTER]->(c:KnowledgeCluster)
+WITH c, collect(m) AS members
+WHERE size(members) >= $min_size
+WITH c, members, size(members) AS member_count
+ORDER BY member_count {direction}, c.cluster_id
+SKIP $offset LIMIT $limit
+{MEMBER_TOPICS}
+RETURN c.cluster_id AS cluster_id, coalesce(c.name, '') AS name,
+       coalesce(c.description, '') AS description, member_count,
+       [m IN members | m.subject][..10] AS subjects, topic_names, topic_counts
+ORDER BY member_count {direction}, cluster_id
+"#
+    ))
+    .param("min_size", min_size as i64)
+    .param("offset", offset as i64)
+    .param("limit", limit.min(i64::MAX as usize) as i64);
+    let mut stream = graph.execute(q).await.context("list knowledge clusters")?;
+    let mut out = Vec::new();
+    while let Some(row) = stream.next().await.context("read knowledge clusters")? {
+        out.push(cluster_summary_from_row(&row)?);
+    }
+    Ok(out)
+}
+
+fn unclustered_description(min_size: usize) -> String {
+    format!("Messages outside every cluster of at least {min_size} members.")
+}
+
+/// The catch-all group of messages outside every cluster of at least `min_size` members,
+/// summarized like a cluster with id `UNCLUSTERED_ID`.
+pub async fn unclustered_summary(
+    graph: &Graph,
+    min_size: usize,
+) -> Result<KnowledgeClusterSummary> {
+    let q = query(&format!(
+        r#"
+{UNCLUSTERED_MEMBERS}
+WITH collect(m) AS members
+{MEMBER_TOPICS}
+RETURN $cluster_id AS cluster_id, 'Unclustered' AS name, $description AS description,
+       size(members) AS member_count, [m IN members | m.subject][..10] AS subjects,
+       topic_names, topic_counts
+"#
+    ))
+    .param("min_size", min_size as i64)
+    .param("cluster_id", crate::triage::UNCLUSTERED_ID)
+    .param("description", unclustered_description(min_size));
+    let mut stream = graph.execute(q).await.context("summarize unclustered messages")?;
+    let row = stream
+        .next()
+        .await
+        .context("read unclustered messages")?
+        .context("unclustered summary returned no row")?;
+    cluster_summary_from_row(&row)
+}
+
+/// A member message of a cluster, as `GET /v1/clusters/{cluster_id}` returns it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ClusterMember {
+    pub message_id: String,
+    pub subject: String,
+    /// RFC 3339, when the `Date` header parsed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sent_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+}
+
+/// A current decision on a topic the cluster's messages are `ABOUT`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ClusterDecision {
+    pub decision_id: String,
+    pub version: i64,
+    pub summary: String,
+    /// Topics shared with the cluster's messages.
+    pub topics: Vec<String>,
+    /// Member messages about those topics.
+    pub shared_messages: i64,
+    /// Stored routing, used for visibility; not returned.
+    #[serde(skip)]
+    pub routing_json: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct KnowledgeClusterDetail {
+    pub cluster_id: String,
+    pub name: String,
+    pub description: String,
+    /// Oldest first (by `sent_at`, else ingestion time).
+    pub members: Vec<ClusterMember>,
+    /// Employee ids that sent or received a member message, sorted.
+    pub employees: Vec<String>,
+    /// Most shared messages first.
+    pub decisions: Vec<ClusterDecision>,
+}
+
+/// The cluster with its members, their employees and up to `decision_limit` related current
+/// decisions, or `None` when there is no such cluster.
+pub async fn load_knowledge_cluster(
+    graph: &Graph,
+    cluster_id: &str,
+    decision_limit: usize,
+) -> Result<Option<KnowledgeClusterDetail>> {
+    let q = query(
+        r#"
+MATCH (c:KnowledgeCluster {cluster_id: $cluster_id})
+RETURN coalesce(c.name, '') AS name, coalesce(c.description, '') AS description
+"#,
+    )
+    .param("cluster_id", cluster_id.to_string());
+    let mut stream = graph.execute(q).await.context("query knowledge cluster")?;
+    let Some(row) = stream.next().await.context("read knowledge cluster")? else {
+        return Ok(None);
+    };
+    let detail = KnowledgeClusterDetail {
+        cluster_id: cluster_id.to_string(),
+        name: row.get("name").unwrap_or_default(),
+        description: row.get("description").unwrap_or_default(),
+        members: Vec::new(),
+        employees: Vec::new(),
+        decisions: Vec::new(),
+    };
+    fill_cluster_detail(graph, detail, CLUSTER_MEMBERS, 0, decision_limit)
+        .await
+        .map(Some)
+}
+
+/// The catch-all group of `unclustered_summary`, in the shape of `load_knowledge_cluster`.
+pub async fn load_unclustered_messages(
+    graph: &Graph,
+    min_size: usize,
+    decision_limit: usize,
+) -> Result<KnowledgeClusterDetail> {
+    let detail = KnowledgeClusterDetail {
+        cluster_id: crate::triage::UNCLUSTERED_ID.to_string(),
+        name: "Unclustered".to_string(),
+        description: unclustered_description(min_size),
+        members: Vec::new(),
+        employees: Vec::new(),
+        decisions: Vec::new(),
+    };
+    fill_cluster_detail(graph, detail, UNCLUSTERED_MEMBERS, min_size, decision_limit).await
+}
+
+/// Adds the members `members_match` binds to `m`, their employees and related decisions.
+async fn fill_cluster_detail(
+    graph: &Graph,
+    mut detail: KnowledgeClusterDetail,
+    members_match: &str,
+    min_size: usize,
+    decision_limit: usize,
+) -> Result<KnowledgeClusterDetail> {
+    let cluster_id = detail.cluster_id.clone();
+    let q = query(&format!(
+        r#"
+{members_match}
+OPTIONAL MATCH (s:Employee)-[:SENT]->(m)
+WITH m, head(collect(DISTINCT s.employee_id)) AS from_id
+RETURN m.message_id AS message_id, coalesce(m.subject, '') AS subject,
+       toString(m.sent_at) AS sent_at, from_id
+ORDER BY coalesce(m.sent_at, m.created_at), message_id
+"#
+    ))
+    .param("cluster_id", cluster_id.clone())
+    .param("min_size", min_size as i64);
+    let mut stream = graph.execute(q).await.context("query cluster members")?;
+    while let Some(row) = stream.next().await.context("read cluster members")? {
+        let Some(message_id) = row.get::<Option<String>>("message_id").ok().flatten() else {
+            continue;
+        };
+        detail.members.push(ClusterMember {
+            message_id,
+            subject: row.get("subject").unwrap_or_default(),
+            sent_at: row.get::<Option<String>>("sent_at").ok().flatten(),
+            from: row.get::<Option<String>>("from_id").ok().flatten(),
+        });
+    }
+
+    let q = query(&format!(
+        r#"
+{members_match}
+MATCH (e:Employee)-[:SENT|TO]-(m)
+RETURN DISTINCT e.employee_id AS employee_id
+ORDER BY employee_id
+"#
+    ))
+    .param("cluster_id", cluster_id.clone())
+    .param("min_size", min_size as i64);
+    let mut stream = graph.execute(q).await.context("query cluster employees")?;
+    while let Some(row) = stream.next().await.context("read cluster employees")? {
+        if let Ok(id) = row.get::<String>("employee_id") {
+            detail.employees.push(id);
+        }
+    }
+
+    let q = query(&format!(
+        r#"
+{members_match}
+MATCH (m)-[:ABOUT]->(t:Topic)<-[:ABOUT]-(dv:DecisionVersion)<-[:CURRENT]-(:Decision)
+WITH dv, collect(DISTINCT t.topic_id) AS topics, count(DISTINCT m) AS shared_messages
+RETURN dv.decision_id AS decision_id, dv.version AS version,
+       coalesce(dv.summary, '') AS summary, topics, shared_messages,
+       coalesce(dv.routing_json, '{{}}') AS routing_json
+ORDER BY shared_messages DESC, decision_id
+LIMIT $limit
+"#
+    ))
+    .param("cluster_id", cluster_id)
+    .param("min_size", min_size as i64)
+    .param("limit", decision_limit as i64);
+    let mut stream = graph.execute(q).await.context("query cluster decisions")?;
+    while let Some(row) = stream.next().await.context("read cluster decisions")? {
+        detail.decisions.push(ClusterDecision {
+            decision_id: row.get("decision_id").context("missing decision_id")?,
+            version: row.get("version").unwrap_or_default(),
+            summary: row.get("summary").unwrap_or_default(),
+            topics: row.get("topics").unwrap_or_default(),
+            shared_messages: row.get("shared_messages").unwrap_or_default(),
+            routing_json: row.get("routing_json").unwrap_or_default(),
+        });
+    }
+    Ok(detail)
+}
+
+pub async fn set_knowledge_cluster_label(
+    graph: &Graph,
+    cluster_id: &str,
+    name: &str,
+    description: &str,
+) -> Result<()> {
+    let q = query(
+        r#"
+MATCH (c:KnowledgeCluster {cluster_id: $cluster_id})
+SET c.name = $name, c.description = $description
+"#,
+    )
+    .param("cluster_id", cluster_id.to_string())
+    .param("name", name.to_string())
+    .param("description", description.to_string());
+    graph.run(q).await.context("set knowledge cluster label")?;
+    Ok(())
+}
+
+impl GraphUpdateResult {
+    pub fn empty() -> Self {
+        Self {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+}
+
+/// Idempotent seed: upserts `employees` with `seeded = true`. With `mark_removed`, employees
+/// seeded earlier but missing from `employees` get `seeded = false` (never deleted); returns how
+/// many were marked. Employees discovered in email have no `seeded` flag and are left alone.
+pub async fn seed_employees(
+    graph: &Graph,
+    employees: &[EmployeeSeed],
+    mark_removed: bool,
+) -> Result<i64> {
+    // Note: neo4rs params must be Bolt-compatible (avoid passing serde_json::Value).
+    for emp in employees {
+        let q = query(
+            r#"
+MERGE (emp:Employee {employee_id: $employee_id})
+ON CREATE SET emp.created_at = datetime()
+SET emp.name = $name,
+    emp.role = $role,
+    emp.seeded = true
+"#,
+        )
+        .param("employee_id", emp.employee_id.clone())
+        .param("name", emp.name.clone())
+        .param("role", emp.role.as_str());
+
+        graph
+            .run(q)
+            .await
+            .with_context(|| format!("seed employee {}", emp.employee_id))?;
+    }
+    if !mark_removed {
+        return Ok(0);
+    }
+
+    let ids: Vec<String> = employees.iter().map(|e| e.employee_id.clone()).collect();
+    let q = query(
+        r#"
+MATCH (emp:Employee {seeded: true})
+WHERE NOT emp.employee_id IN $ids
+SET emp.seeded = false
+RETURN count(emp) AS unseeded
+"#,
+    )
+    .param("ids", ids);
+    let mut stream = graph.execute(q).await.context("unmark removed seeds")?;
+    let unseeded: i64 = stream
+        .next()
+        .await
+        .context("read unmark removed seeds")?
+        .and_then(|row| row.get("unseeded").ok())
+        .unwrap_or(0);
+    Ok(unseeded)
+}
+
+/// Appends a `ConversationTurn` to `employee_id`'s memory; run inside `persist_ask_outcome` or
+/// `append_conversation_turns`.
+fn conversation_turn_query(employee_id: &str, role: &str, content: &str) -> neo4rs::Query {
+    query(
+        r#"
+MATCH (e:Employee {employee_id: $employee_id})
+CREATE (t:ConversationTurn {
+  turn_id: $turn_id,
+  created_at: datetime(),
+  role: $role,
+  content: $content
+})
+MERGE (e)-[:SAID]->(t)
+"#,
+    )
+    .param("employee_id", employee_id.to_string())
+    .param("turn_id", Uuid::new_v4().to_string())
+    .param("role", role.to_string())
+    .param("content", content.to_string())
+}
+
+/// Appends `turns` to `employee_id`'s memory in one transaction, for an ask that wrote no
+/// decision (a clarifying question).
+pub async fn append_conversation_turns(
+    graph: &Graph,
+    employee_id: &str,
+    turns: &[(String, String)],
+) -> Result<()> {
+    let mut txn = graph.start_txn().await.context("start neo4j txn")?;
+    for (role, content) in turns {
+        if let Err(e) = txn.run(conversation_turn_query(employee_id, role, content)).await {
+            let _ = txn.rollback().await;
+            return Err(e).context("persist conversation turn");
+        }
+    }
+    txn.commit().await.map(drop).context("commit conversation turns")
+}
+
+pub async fn load_recent_conversation_turns(
+    graph: &Graph,
+    employee_id: &str,
+    limit: i64,
+) -> Result<Vec<(String, String)>> {
+    let q = query(
+        r#"
+MATCH (e:Employee {employee_id: $employee_id})
+OPTIONAL MATCH (e)-[:ALIAS_OF]->(c:Employee)
+WITH coalesce(c, e) AS owner
+MATCH (owner)-[:SAID]->(t:ConversationTurn)
+RETURN t.role AS role, t.content AS content
+ORDER BY t.created_at DESC
+LIMIT $limit
+"#,
+    )
+    .param("employee_id", employee_id.to_string())
+    .param("limit", limit);
+
+    let mut stream = graph.execute(q).await.context("load recent conversation")?;
+    let mut out = Vec::new();
+    while let Ok(Some(row)) = stream.next().await {
+        let role: String = row.get("role").unwrap_or_else(|_| "user".to_string());
+        let content: String = row.get("content").unwrap_or_default();
+        out.push((role, content));
+    }
+    Ok(out)
+}
+
+/// Deletes the conversation turns of `employee_id` and any summary of them. Returns the number
+/// of turns deleted.
+pub async fn clear_conversation(graph: &Graph, employee_id: &str) -> Result<i64> {
+    let q = query(
+        r#"
+MATCH (e:Employee {employee_id: $employee_id})
+OPTIONAL MATCH (e)-[:SAID]->(t:ConversationTurn)
+WITH e, collect(t) AS turns
+OPTIONAL MATCH (e)--(s:ConversationSummary)
+WITH turns, collect(s) AS summaries
+FOREACH (n IN turns + summaries | DETACH DELETE n)
+RETURN size(turns) AS cleared
+"#,
+    )
+    .param("employee_id", employee_id.to_string());
+
+    let mut stream = graph.execute(q).await.context("clear conversation")?;
+    match stream.next().await.context("read clear conversation")? {
+        Some(row) => Ok(row.get("cleared").unwrap_or(0)),
+        None => Ok(0),
+    }
+}
+
+pub(crate) fn routing_to_json(routing: &Value) -> String {
+    serde_json::to_string(routing).unwrap_or_else(|_| "{}".to_string())
+}
+
+pub(crate) fn routing_agents(routing: &Value) -> Vec<String> {
+    routing
+        .as_object()
+        .map(|obj| {
+            obj.iter()
+                .filter_map(|(k, v)| {
+                    let level = v.as_str().unwrap_or("none");
+                    if level == "none" {
+                        None
+                    } else {
+                        Some(k.clone())
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default()
+}
+
+/// Version number and content hash of the current version of `truth_id`, if any.
+/// Versions written before hashing was introduced have no hash.
+pub async fn current_truth_version(
+    graph: &Graph,
+    truth_id: &str,
+) -> Result<Option<(i64, Option<String>)>> {
+    let mut stream = graph
+        .execute(
+            query(
+                r#"
+MATCH (o:TruthObject {truth_id: $truth_id})-[:CURRENT]->(tv:TruthVersion)
+RETURN tv.version AS v, tv.content_hash AS content_hash
+"#,
+            )
+            .param("truth_id", truth_id.to_string()),
+        )
+        .await
+        .context("query current truth version")?;
+
+    match stream.next().await.context("read current truth version")? {
+        Some(row) => {
+            let v: i64 = row.get("v").context("missing truth version")?;
+            Ok(Some((v, row.get("content_hash").ok())))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Content of each truth object's `CURRENT` version, keyed by truth id, in the shape of
+/// `AppState::org_truth` (one entry per key).
+/// Version and content of the current version of each active truth in `truth_ids`; unknown
+/// and retired ids are left out.
+pub async fn current_truth_contents(
+    graph: &Graph,
+    truth_ids: &[String],
+) -> Result<HashMap<String, (i64, String)>> {
+    let q = query(
+        r#"
+UNWIND $truth_ids AS tid
+MATCH (o:TruthObject {truth_id: tid})-[:CURRENT]->(tv:TruthVersion)
+WHERE coalesce(o.active, true)
+RETURN o.truth_id AS truth_id, tv.version AS version, coalesce(tv.summary, '') AS summary
+"#,
+    )
+    .param("truth_ids", truth_ids.to_vec());
+    let mut stream = graph.execute(q).await.context("load truth contents")?;
+    let mut out = HashMap::new();
+    while let Some(row) = stream.next().await.context("read truth contents")? {
+        let truth_id: String = row.get("truth_id").context("missing truth_id")?;
+        let version: i64 = row.get("version").unwrap_or_default();
+        out.insert(truth_id, (version, row.get("summary").unwrap_or_default()));
+    }
+    Ok(out)
+}
+
+pub async fn load_current_truth(graph: &Graph) -> Result<HashMap<String, Vec<String>>> {
+    let q = query(
+        r#"
+MATCH (o:TruthObject)-[:CURRENT]->(tv:TruthVersion)
+WHERE coalesce(o.active, true)
+RETURN o.truth_id AS truth_id, tv.summary AS summary
+"#,
+    );
+    let mut stream = graph.execute(q).await.context("load current truth")?;
+    let mut out = HashMap::new();
+    while let Some(row) = stream.next().await.context("read current truth")? {
+        let truth_id: String = row.get("truth_id").context("missing truth_id")?;
+        let summary: String = row.get("summary").unwrap_or_default();
+        if !summary.is_empty() {
+            out.insert(truth_id, vec![summary]);
+        }
+    }
+    Ok(out)
+}
+
+/// Properties of version `version` of `decision_id`, or `None` if there is no such version.
+pub async fn decision_version(graph: &Graph, decision_id: &str, version: i64) -> Result<Option<Value>> {
+    let q = query(
+        r#"
+MATCH (dv:DecisionVersion {decision_id: $decision_id, version: $version})
+RETURN dv.summary AS summary, dv.confidence AS confidence,
+       coalesce(dv.trigger_events, []) AS trigger_events,
+       coalesce(dv.agents_involved, []) AS agents_involved,
+       coalesce(dv.routing_agents, []) AS routing_agents,
+       coalesce(dv.routing_json, '{}') AS routing_json,
+       dv.rationale AS rationale,
+       coalesce(dv.evidence, []) AS evidence,
+       coalesce(dv.assumptions, []) AS assumptions,
+       toString(dv.created_at) AS created_at
+"#,
+    )
+    .param("decision_id", decision_id.to_string())
+    .param("version", version);
+    let mut stream = graph.execute(q).await.context("query decision version")?;
+    let Some(row) = stream.next().await.context("read decision version")? else {
+        return Ok(None);
+    };
+    Ok(Some(serde_json::json!({
+        "decision_id": decision_id,
+        "version": version,
+        "summary": row.get::<String>("summary").unwrap_or_default(),
+        "confidence": row.get::<f64>("confidence").unwrap_or_default(),
+        "trigger_events": row.get::<Vec<String>>("trigger_events").unwrap_or_default(),
+        "agents_involved": row.get::<Vec<String>>("agents_involved").unwrap_or_default(),
+        "routing_agents": row.get::<Vec<String>>("routing_agents").unwrap_or_default(),
+        "routing_json": row.get::<String>("routing_json").unwrap_or_default(),
+        "rationale": row.get::<Option<String>>("rationale").ok().flatten(),
+        "evidence": row.get::<Vec<String>>("evidence").unwrap_or_default(),
+        "assumptions": row.get::<Vec<String>>("assumptions").unwrap_or_default(),
+        "created_at": row.get::<String>("created_at").unwrap_or_default(),
+    })))
+}
+
+/// A decision version that was `BASED_ON` some version of a truth.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TruthDependent {
+    pub decision_id: String,
+    pub version: i64,
+    pub summary: String,
+    pub confidence: f64,
+    /// Version of the truth the decision relied on.
+    pub truth_version: i64,
+    /// Whether this is the decision's current version.
+    pub current: bool,
+    /// Stored routing, used for visibility; not returned.
+    #[serde(skip)]
+    pub routing_json: String,
+}
+
+/// Decision versions based on any version of `truth_id`, newest first per decision.
+pub async fn truth_dependents(graph: &Graph, truth_id: &str, limit: usize) -> Result<Vec<TruthDependent>> {
+    let q = query(
+        r#"
+MATCH (dv:DecisionVersion)-[:BASED_ON]->(tv:TruthVersion {truth_id: $truth_id})
+OPTIONAL MATCH (d:Decision)-[:CURRENT]->(dv)
+RETURN dv.decision_id AS decision_id, dv.version AS version, coalesce(dv.summary, '') AS summary,
+       coalesce(dv.confidence, 0.0) AS confidence, tv.version AS truth_version,
+       d IS NOT NULL AS current, coalesce(dv.routing_json, '{}') AS routing_json
+ORDER BY decision_id, version DESC
+LIMIT $limit
+"#,
+    )
+    .param("truth_id", truth_id.to_string())
+    .param("limit", limit as i64);
+    let mut stream = graph.execute(q).await.context("query truth dependents")?;
+    let mut out = Vec::new();
+    while let Some(row) = stream.next().await.context("read truth dependents")? {
+        out.push(TruthDependent {
+            decision_id: row.get("decision_id").unwrap_or_default(),
+            version: row.get("version").unwrap_or_default(),
+            summary: row.get("summary").unwrap_or_default(),
+            confidence: row.get("confidence").unwrap_or_default(),
+            truth_version: row.get("truth_version").unwrap_or_default(),
+            current: row.get("current").unwrap_or_default(),
+            routing_json: row.get("routing_json").unwrap_or_default(),
+        });
+    }
+    Ok(out)
+}
+
+/// A decision version whose `trigger_events` include some event.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EventDecision {
+    pub decision_id: String,
+    pub version: i64,
+    pub summary: String,
+    pub confidence: f64,
+    /// Whether this is the decision's current version.
+    pub current: bool,
+    /// Stored routing, used for visibility; not returned.
+    #[serde(skip)]
+    pub routing_json: String,
+}
+
+/// Upserts an `:Event` node per event, keyed by `event_id`, with the whole event as `json` so
+/// the OrgBrain can be replayed on it.
+pub async fn persist_events(graph: &Graph, events: &[Event]) -> Result<()> {
+    for event in events {
+        let event_type = serde_json::to_value(&event.event_type)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let q = query(
+            r#"
+MERGE (e:Event {event_id: $event_id})
+ON CREATE SET e.created_at = datetime()
+SET e.emitted_by = $emitted_by,
+    e.event_type = $event_type,
+    e.topic = $topic,
+    e.confidence = $confidence,
+    e.timestamp = datetime($timestamp),
+    e.json = $json
+"#,
+        )
+        .param("event_id", event.event_id.to_string())
+        .param("emitted_by", event.emitted_by.0.clone())
+        .param("event_type", event_type)
+        .param("topic", event.topic.clone())
+        .param("confidence", event.confidence as f64)
+        .param("timestamp", event.timestamp.to_rfc3339())
+        .param("json", serde_json::to_string(event)?);
+        graph.run(q).await.context("persist event")?;
+    }
+    Ok(())
+}
+
+/// Stored events among `event_ids`, oldest first. Ids without an `:Event` node are skipped.
+pub async fn load_events(graph: &Graph, event_ids: &[String]) -> Result<Vec<Event>> {
+    let q = query(
+        r#"
+MATCH (e:Event)
+WHERE e.event_id IN $event_ids AND e.json IS NOT NULL
+RETURN e.json AS json
+ORDER BY e.timestamp, e.event_id
+"#,
+    )
+    .param("event_ids", event_ids.to_vec());
+    let mut stream = graph.execute(q).await.context("query events")?;
+    let mut out = Vec::new();
+    while let Some(row) = stream.next().await.context("read events")? {
+        let raw: String = row.get("json").unwrap_or_default();
+        match serde_json::from_str(&raw) {
+            Ok(event) => out.push(event),
+            Err(e) => eprintln!("skipping stored event that does not parse: {e}"),
+        }
+    }
+    Ok(out)
+}
+
+/// Version number of the current version of `decision_id`.
+pub async fn current_decision_version(graph: &Graph, decision_id: &str) -> Result<Option<i64>> {
+    let q = query(
+        r#"
+MATCH (:Decision {decision_id: $decision_id})-[:CURRENT]->(dv:DecisionVersion)
+RETURN dv.version AS version
+"#,
+    )
+    .param("decision_id", decision_id.to_string());
+    let mut stream = graph.execute(q).await.context("query current decision version")?;
+    let Some(row) = stream.next().await.context("read current decision version")? else {
+        return Ok(None);
+    };
+    Ok(row.get("version").ok())
+}
+
+/// Decision versions triggered by `event_id`, newest first per decision.
+pub async fn event_decisions(
+    graph: &Graph,
+    event_id: &str,
+    limit: usize,
+) -> Result<Vec<EventDecision>> {
+    let q = query(
+        r#"
+MATCH (dv:DecisionVersion)
+WHERE $event_id IN coalesce(dv.trigger_events, [])
+OPTIONAL MATCH (d:Decision)-[:CURRENT]->(dv)
+RETURN dv.decision_id AS decision_id, dv.version AS version, coalesce(dv.summary, '') AS summary,
+       coalesce(dv.confidence, 0.0) AS confidence, d IS NOT NULL AS current,
+       coalesce(dv.routing_json, '{}') AS routing_json
+ORDER BY decision_id, version DESC
+LIMIT $limit
+"#,
+    )
+    .param("event_id", event_id.to_string())
+    .param("limit", limit as i64);
+    let mut stream = graph.execute(q).await.context("query event decisions")?;
+    let mut out = Vec::new();
+    while let Some(row) = stream.next().await.context("read event decisions")? {
+        out.push(EventDecision {
+            decision_id: row.get("decision_id").unwrap_or_default(),
+            version: row.get("version").unwrap_or_default(),
+            summary: row.get("summary").unwrap_or_default(),
+            confidence: row.get("confidence").unwrap_or_default(),
+            current: row.get("current").unwrap_or_default(),
+            routing_json: row.get("routing_json").unwrap_or_default(),
+        });
+    }
+    Ok(out)
+}
+
+/// An employee a decision is routed to, and at which level.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RoutedEmployee {
+    pub agent_id: String,
+    pub level: String,
+}
+
+/// The routing stored as `routing_json`, sorted by agent id.
+pub fn routed_employees(routing_json: &str) -> Vec<RoutedEmployee> {
+    let routing: HashMap<String, String> = serde_json::from_str(routing_json).unwrap_or_default();
+    let mut out: Vec<RoutedEmployee> = routing
+        .into_iter()
+        .map(|(agent_id, level)| RoutedEmployee { agent_id, level })
+        .collect();
+    out.sort_by(|a, b| a.agent_id.cmp(&b.agent_id));
+    out
+}
+
+/// A current decision version that an update of some truth would affect.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImpactedDecision {
+    pub decision_id: String,
+    pub version: i64,
+    pub summary: String,
+    pub confidence: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    /// `based_on` when the version relies on the truth, `topic` when it only shares a topic with
+    /// a decision that does.
+    pub via: String,
+    /// Who the version is routed to; these are the people to notify.
+    pub routing: Vec<RoutedEmployee>,
+    /// Stored routing, used for visibility; not returned.
+    #[serde(skip)]
+    pub routing_json: String,
+}
+
+/// Current decision versions `BASED_ON` any version of `truth_id`, then current versions about a
+/// topic that any such version (current or not) is about.
+pub async fn truth_impact(graph: &Graph, truth_id: &str, limit: usize) -> Result<Vec<ImpactedDecision>> {
+    let q = query(
+        r#"
+OPTIONAL MATCH (:TruthVersion {truth_id: $truth_id})<-[:BASED_ON]-(:DecisionVersion)-[:ABOUT]->(t:Topic)
+WITH collect(DISTINCT t.topic_id) AS topics
+MATCH (:Decision)-[:CURRENT]->(dv:DecisionVersion)
+OPTIONAL MATCH (dv)-[b:BASED_ON]->(:TruthVersion {truth_id: $truth_id})
+OPTIONAL MATCH (dv)-[:ABOUT]->(dt:Topic)
+WITH dv, topics, count(b) > 0 AS direct, head(collect(dt.topic_id)) AS topic
+WHERE direct OR topic IN topics
+RETURN dv.decision_id AS decision_id, dv.version AS version, coalesce(dv.summary, '') AS summary,
+       coalesce(dv.confidence, 0.0) AS confidence, topic,
+       CASE WHEN direct THEN 'based_on' ELSE 'topic' END AS via,
+       coalesce(dv.routing_json, '{}') AS routing_json
+ORDER BY via, decision_id
+LIMIT $limit
+"#,
+    )
+    .param("truth_id", truth_id.to_string())
+    .param("limit", limit as i64);
+    let mut stream = graph.execute(q).await.context("query truth impact")?;
+    let mut out = Vec::new();
+    while let Some(row) = stream.next().await.context("read truth impact")? {
+        let routing_json: String = row.get("routing_json").unwrap_or_default();
+        out.push(ImpactedDecision {
+            decision_id: row.get("decision_id").unwrap_or_default(),
+            version: row.get("version").unwrap_or_default(),
+            summary: row.get("summary").unwrap_or_default(),
+            confidence: row.get("confidence").unwrap_or_default(),
+            topic: row.get::<Option<String>>("topic").ok().flatten(),
+            via: row.get("via").unwrap_or_default(),
+            routing: routed_employees(&routing_json),
+            routing_json,
+        });
+    }
+    Ok(out)
+}
+
+/// The current version of a decision with the embedding of its summary (empty when none was
+/// stored).
+#[derive(Debug, Clone)]
+pub struct DecisionEmbedding {
+    pub decision_id: String,
+    pub version: i64,
+    pub summary: String,
+    pub confidence: f64,
+    pub routing_json: String,
+    pub embedding: Vec<f32>,
+}
+
+/// Stores `embedding` as `summary_embedding` on one decision version.
+pub async fn set_decision_embedding(
+    graph: &Graph,
+    decision_id: &str,
+    version: i64,
+    embedding: &[f32],
+) -> Result<()> {
+    let q = query(
+        r#"
+MATCH (dv:DecisionVersion {decision_id: $decision_id, version: $version})
+SET dv.summary_embedding = $embedding
+"#,
+    )
+    .param("decision_id", decision_id.to_string())
+    .param("version", version)
+    .param(
+        "embedding",
+        embedding.iter().map(|x| *x as f64).collect::<Vec<f64>>(),
+    );
+    graph.run(q).await.context("set decision embedding")?;
+    Ok(())
+}
+
+/// The current version of every decision, with its summary embedding if it has one.
+pub async fn current_decision_embeddings(graph: &Graph) -> Result<Vec<DecisionEmbedding>> {
+    let q = query(
+        r#"
+MATCH (:Decision)-[:CURRENT]->(dv:DecisionVersion)
+RETURN dv.decision_id AS decision_id, dv.version AS version, coalesce(dv.summary, '') AS summary,
+       coalesce(dv.confidence, 0.0) AS confidence, coalesce(dv.routing_json, '{}') AS routing_json,
+       coalesce(dv.summary_embedding, []) AS embedding
+"#,
+    );
+    let mut stream = graph.execute(q).await.context("query decision embeddings")?;
+    let mut out = Vec::new();
+    while let Some(row) = stream.next().await.context("read decision embeddings")? {
+        out.push(DecisionEmbedding {
+            decision_id: row.get("decision_id").unwrap_or_default(),
+            version: row.get("version").unwrap_or_default(),
+            summary: row.get("summary").unwrap_or_default(),
+            confidence: row.get("confidence").unwrap_or_default(),
+            routing_json: row.get("routing_json").unwrap_or_default(),
+            embedding: row
+                .get::<Vec<f64>>("embedding")
+                .unwrap_or_default()
+                .into_iter()
+                .map(|x| x as f32)
+                .collect(),
+        });
+    }
+    Ok(out)
+}
+
+/// A current decision version with the activity since it was written, as scored by the staleness
+/// check.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DecisionActivity {
+    pub decision_id: String,
+    pub version: i64,
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    pub created_at: String,
+    pub age_days: i64,
+    /// `EmailMessage`s about the topic ingested after this version.
+    pub newer_emails: i64,
+    /// `TruthVersion`s written after this version, of truths that this version or another one
+    /// about the topic relied on.
+    pub newer_truth_versions: i64,
+    /// Whether the last staleness check marked this version stale.
+    pub stale: bool,
+    /// Stored routing, used for visibility; not returned.
+    #[serde(skip)]
+    pub routing_json: String,
+}
+
+/// The outcome of the staleness check for one decision version.
+#[derive(Debug, Clone)]
+pub struct StaleMark {
+    pub decision_id: String,
+    pub version: i64,
+    pub stale: bool,
+    pub score: f64,
+}
+
+/// What accumulated since the current version of a decision, for re-running the OrgBrain on it.
+#[derive(Debug, Clone, Default)]
+pub struct StaleContext {
+    pub version: i64,
+    pub summary: String,
+    pub topic: Option<String>,
+    /// Subjects of the newest `EmailMessage`s about the topic ingested since the version.
+    pub email_subjects: Vec<String>,
+    /// Truths (see `DecisionActivity::newer_truth_versions`) with versions written since.
+    pub truth_ids: Vec<String>,
+}
 
-        graph
-            .run(q)
-            .await
-            .with_context(|| format!("seed employee {employee_id}"))?;
+/// Every current decision version with the email and truth activity on its topic since it was
+/// written.
+pub async fn decision_activity(graph: &Graph) -> Result<Vec<DecisionActivity>> {
+    let q = query(
+        r#"
+MATCH (:Decision)-[:CURRENT]->(dv:DecisionVersion)
+OPTIONAL MATCH (dv)-[:ABOUT]->(t:Topic)
+CALL {
+  WITH dv, t
+  OPTIONAL MATCH (m:EmailMessage)-[:ABOUT]->(t)
+  WHERE m.created_at > dv.created_at
+  RETURN count(m) AS newer_emails
+}
+CALL {
+  WITH dv, t
+  OPTIONAL MATCH (dv)-[:BASED_ON]->(own:TruthVersion)
+  OPTIONAL MATCH (t)<-[:ABOUT]-(:DecisionVersion)-[:BASED_ON]->(shared:TruthVersion)
+  WITH dv, collect(DISTINCT own.truth_id) + collect(DISTINCT shared.truth_id) AS truth_ids
+  OPTIONAL MATCH (tv:TruthVersion)
+  WHERE tv.truth_id IN truth_ids AND tv.created_at > dv.created_at
+  RETURN count(DISTINCT tv) AS newer_truth_versions
+}
+RETURN dv.decision_id AS decision_id, dv.version AS version, coalesce(dv.summary, '') AS summary,
+       t.topic_id AS topic, toString(dv.created_at) AS created_at,
+       duration.inDays(dv.created_at, datetime()).days AS age_days,
+       newer_emails, newer_truth_versions, coalesce(dv.stale, false) AS stale,
+       coalesce(dv.routing_json, '{}') AS routing_json
+"#,
+    );
+    let mut stream = graph.execute(q).await.context("query decision activity")?;
+    let mut out = Vec::new();
+    while let Some(row) = stream.next().await.context("read decision activity")? {
+        out.push(DecisionActivity {
+            decision_id: row.get("decision_id").unwrap_or_default(),
+            version: row.get("version").unwrap_or_default(),
+            summary: row.get("summary").unwrap_or_default(),
+            topic: row.get::<Option<String>>("topic").ok().flatten(),
+            created_at: row.get("created_at").unwrap_or_default(),
+            age_days: row.get("age_days").unwrap_or_default(),
+            newer_emails: row.get("newer_emails").unwrap_or_default(),
+            newer_truth_versions: row.get("newer_truth_versions").unwrap_or_default(),
+            stale: row.get("stale").unwrap_or_default(),
+            routing_json: row.get("routing_json").unwrap_or_default(),
+        });
     }
-    Ok(())
+    Ok(out)
 }
 
-pub async fn persist_conversation_turn(
-    graph: &Graph,
-    employee_id: &str,
-    role: &str,
-    content: &str,
-) -> Result<()> {
+/// Records the staleness check on each decision version: `stale`, `stale_score` and
+/// `stale_checked_at`.
+pub async fn mark_staleness(graph: &Graph, marks: &[StaleMark]) -> Result<()> {
+    if marks.is_empty() {
+        return Ok(());
+    }
+    let marks: Vec<BoltType> = marks
+        .iter()
+        .map(|mark| {
+            let mut m = BoltMap::new();
+            m.put("decision_id".into(), mark.decision_id.clone().into());
+            m.put("version".into(), mark.version.into());
+            m.put("stale".into(), mark.stale.into());
+            m.put("score".into(), mark.score.into());
+            BoltType::Map(m)
+        })
+        .collect();
     let q = query(
         r#"
-MATCH (e:Employee {employee_id: $employee_id})
-CREATE (t:ConversationTurn {
-  turn_id: $turn_id,
-  created_at: datetime(),
-  role: $role,
-  content: $content
-})
-MERGE (e)-[:SAID]->(t)
+UNWIND $marks AS mk
+MATCH (dv:DecisionVersion {decision_id: mk.decision_id, version: mk.version})
+SET dv.stale = mk.stale, dv.stale_score = mk.score, dv.stale_checked_at = datetime()
 "#,
     )
-    .param("employee_id", employee_id.to_string())
-    .param("turn_id", Uuid::new_v4().to_string())
-    .param("role", role.to_string())
-    .param("content", content.to_string());
-
-    graph
-        .run(q)
-        .await
-        .context("persist conversation turn")?;
+    .param("marks", marks);
+    graph.run(q).await.context("mark decision staleness")?;
     Ok(())
 }
 
-pub async fn load_recent_conversation_turns(
-    graph: &Graph,
-    employee_id: &str,
-    limit: i64,
-) -> Result<Vec<(String, String)>> {
+/// The current version of `decision_id` and what accumulated on its topic since; `None` if the
+/// decision doesn't exist.
+pub async fn stale_context(graph: &Graph, decision_id: &str, limit: usize) -> Result<Option<StaleContext>> {
     let q = query(
         r#"
-MATCH (:Employee {employee_id: $employee_id})-[:SAID]->(t:ConversationTurn)
-RETURN t.role AS role, t.content AS content
-ORDER BY t.created_at DESC
-LIMIT $limit
+MATCH (:Decision {decision_id: $decision_id})-[:CURRENT]->(dv:DecisionVersion)
+OPTIONAL MATCH (dv)-[:ABOUT]->(t:Topic)
+CALL {
+  WITH dv, t
+  OPTIONAL MATCH (m:EmailMessage)-[:ABOUT]->(t)
+  WHERE m.created_at > dv.created_at
+  WITH m ORDER BY m.created_at DESC LIMIT $limit
+  RETURN collect(coalesce(m.subject, '')) AS email_subjects
+}
+CALL {
+  WITH dv, t
+  OPTIONAL MATCH (dv)-[:BASED_ON]->(own:TruthVersion)
+  OPTIONAL MATCH (t)<-[:ABOUT]-(:DecisionVersion)-[:BASED_ON]->(shared:TruthVersion)
+  WITH dv, collect(DISTINCT own.truth_id) + collect(DISTINCT shared.truth_id) AS truth_ids
+  OPTIONAL MATCH (tv:TruthVersion)
+  WHERE tv.truth_id IN truth_ids AND tv.created_at > dv.created_at
+  RETURN collect(DISTINCT tv.truth_id) AS truth_ids
+}
+RETURN dv.version AS version, coalesce(dv.summary, '') AS summary, t.topic_id AS topic,
+       email_subjects, truth_ids
 "#,
     )
-    .param("employee_id", employee_id.to_string())
-    .param("limit", limit);
+    .param("decision_id", decision_id.to_string())
+    .param("limit", limit as i64);
+    let mut stream = graph.execute(q).await.context("query stale context")?;
+    let Some(row) = stream.next().await.context("read stale context")? else {
+        return Ok(None);
+    };
+    Ok(Some(StaleContext {
+        version: row.get("version").unwrap_or_default(),
+        summary: row.get("summary").unwrap_or_default(),
+        topic: row.get::<Option<String>>("topic").ok().flatten(),
+        email_subjects: row.get("email_subjects").unwrap_or_default(),
+        truth_ids: row.get("truth_ids").unwrap_or_default(),
+    }))
+}
 
-    let mut stream = graph.execute(q).await.context("load recent conversation")?;
-    let mut out = Vec::new();
-    while let Ok(Some(row)) = stream.next().await {
-        let role: String = row.get("role").unwrap_or_else(|_| "user".to_string());
-        let content: String = row.get("content").unwrap_or_default();
-        out.push((role, content));
+/// Decision versions written on one (UTC) day.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DayStats {
+    /// `YYYY-MM-DD`.
+    pub day: String,
+    pub decisions: i64,
+    pub avg_confidence: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TopicCount {
+    pub topic: String,
+    pub decisions: i64,
+}
+
+/// Aggregates over the decision versions of a time window.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct DecisionStats {
+    /// Days without decisions are left out.
+    pub per_day: Vec<DayStats>,
+    pub top_topics: Vec<TopicCount>,
+}
+
+/// Decision versions written in the last `days` days, per day and per topic. With `agent_id`,
+/// only versions routed to that agent count.
+pub async fn decision_stats(
+    graph: &Graph,
+    days: i64,
+    agent_id: Option<&str>,
+    top_topics: usize,
+) -> Result<DecisionStats> {
+    let window = r#"
+MATCH (dv:DecisionVersion)
+WHERE dv.created_at >= datetime() - duration({days: $days})
+  AND ($agent_id = '' OR $agent_id IN coalesce(dv.routing_agents, []))
+"#;
+    let per_day = query(&format!(
+        r#"{window}
+WITH date.truncate('day', dv.created_at) AS day, dv.confidence AS confidence
+RETURN toString(day) AS day, count(*) AS decisions, coalesce(avg(confidence), 0.0) AS avg_confidence
+ORDER BY day
+"#
+    ))
+    .param("days", days)
+    .param("agent_id", agent_id.unwrap_or_default().to_string());
+    let mut stats = DecisionStats::default();
+    let mut stream = graph.execute(per_day).await.context("query decisions per day")?;
+    while let Some(row) = stream.next().await.context("read decisions per day")? {
+        stats.per_day.push(DayStats {
+            day: row.get("day").unwrap_or_default(),
+            decisions: row.get("decisions").unwrap_or_default(),
+            avg_confidence: row.get("avg_confidence").unwrap_or_default(),
+        });
     }
-    Ok(out)
+
+    let topics = query(&format!(
+        r#"{window}
+MATCH (dv)-[:ABOUT]->(t:Topic)
+RETURN t.topic_id AS topic, count(dv) AS decisions
+ORDER BY decisions DESC, topic
+LIMIT $limit
+"#
+    ))
+    .param("days", days)
+    .param("agent_id", agent_id.unwrap_or_default().to_string())
+    .param("limit", top_topics as i64);
+    let mut stream = graph.execute(topics).await.context("query top topics")?;
+    while let Some(row) = stream.next().await.context("read top topics")? {
+        stats.top_topics.push(TopicCount {
+            topic: row.get("topic").unwrap_or_default(),
+            decisions: row.get("decisions").unwrap_or_default(),
+        });
+    }
+    Ok(stats)
 }
 
-fn routing_to_json(routing: &Value) -> String {
-    serde_json::to_string(routing).unwrap_or_else(|_| "{}".to_string())
+/// The current version of a truth related to the topics of an ask.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextTruth {
+    pub truth_id: String,
+    pub version: i64,
+    pub summary: String,
 }
 
-fn routing_agents(routing: &Value) -> Vec<String> {
-    routing
-        .as_object()
-        .map(|obj| {
-            obj.iter()
-                .filter_map(|(k, v)| {
-                    let level = v.as_str().unwrap_or("none");
-                    if level == "none" {
-                        None
-                    } else {
-                        Some(k.clone())
-                    }
-                })
-                .collect::<Vec<_>>()
-        })
-        .unwrap_or_default()
+/// The current version of a decision on one of the topics of an ask.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextDecision {
+    pub decision_id: String,
+    pub version: i64,
+    pub summary: String,
+    pub confidence: f64,
+    pub topic: String,
+    pub created_at: String,
 }
 
-pub async fn next_decision_version(graph: &Graph, decision_id: &str) -> Result<i64> {
-    let mut stream = graph
-        .execute(
-            query(
-                r#"
-MATCH (d:Decision {decision_id: $decision_id})-[:CURRENT]->(dv:DecisionVersion)
-RETURN dv.version AS v
+/// What the graph knows about a set of topics, for the OrgBrain prompt.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GraphContext {
+    pub truths: Vec<ContextTruth>,
+    pub decisions: Vec<ContextDecision>,
+}
+
+/// For `topics` (lower-cased): the current version of up to `truth_limit` active truths that
+/// decisions on those topics were based on, or whose id contains a topic, most used first; and
+/// up to `decision_limit` current decision versions about those topics, newest first.
+pub async fn graph_context(
+    graph: &Graph,
+    topics: &[String],
+    truth_limit: usize,
+    decision_limit: usize,
+) -> Result<GraphContext> {
+    let mut out = GraphContext::default();
+    if topics.is_empty() {
+        return Ok(out);
+    }
+
+    if truth_limit > 0 {
+        let q = query(
+            r#"
+MATCH (o:TruthObject)-[:CURRENT]->(tv:TruthVersion)
+WHERE coalesce(o.active, true)
+OPTIONAL MATCH (t:Topic)<-[:ABOUT]-(:DecisionVersion)-[:BASED_ON]->(:TruthVersion {truth_id: o.truth_id})
+WHERE toLower(t.topic_id) IN $topics
+WITH o, tv, count(t) AS uses
+WHERE uses > 0 OR any(topic IN $topics WHERE toLower(o.truth_id) CONTAINS topic)
+RETURN o.truth_id AS truth_id, tv.version AS version, coalesce(tv.summary, '') AS summary
+ORDER BY uses DESC, truth_id
+LIMIT $limit
 "#,
-            )
-            .param("decision_id", decision_id.to_string()),
         )
-        .await
-        .context("query current decision version")?;
-
-    if let Some(row) = stream.next().await.context("read decision version")? {
-        let v: i64 = row.get("v").context("missing decision version")?;
-        Ok(v + 1)
-    } else {
-        Ok(1)
+        .param("topics", topics.to_vec())
+        .param("limit", truth_limit as i64);
+        let mut stream = graph.execute(q).await.context("query graph context truth")?;
+        while let Some(row) = stream.next().await.context("read graph context truth")? {
+            out.truths.push(ContextTruth {
+                truth_id: row.get("truth_id").unwrap_or_default(),
+                version: row.get("version").unwrap_or_default(),
+                summary: row.get("summary").unwrap_or_default(),
+            });
+        }
     }
-}
 
-pub async fn next_truth_version(graph: &Graph, truth_id: &str) -> Result<i64> {
-    let mut stream = graph
-        .execute(
-            query(
-                r#"
-MATCH (o:TruthObject {truth_id: $truth_id})-[:CURRENT]->(tv:TruthVersion)
-RETURN tv.version AS v
+    if decision_limit > 0 {
+        let q = query(
+            r#"
+MATCH (:Decision)-[:CURRENT]->(dv:DecisionVersion)-[:ABOUT]->(t:Topic)
+WHERE toLower(t.topic_id) IN $topics
+RETURN dv.decision_id AS decision_id, dv.version AS version,
+       coalesce(dv.summary, '') AS summary, coalesce(dv.confidence, 0.0) AS confidence,
+       t.topic_id AS topic, toString(dv.created_at) AS created_at
+ORDER BY dv.created_at DESC
+LIMIT $limit
 "#,
-            )
-            .param("truth_id", truth_id.to_string()),
         )
-        .await
-        .context("query current truth version")?;
+        .param("topics", topics.to_vec())
+        .param("limit", decision_limit as i64);
+        let mut stream = graph.execute(q).await.context("query graph context decisions")?;
+        while let Some(row) = stream.next().await.context("read graph context decisions")? {
+            out.decisions.push(ContextDecision {
+                decision_id: row.get("decision_id").unwrap_or_default(),
+                version: row.get("version").unwrap_or_default(),
+                summary: row.get("summary").unwrap_or_default(),
+                confidence: row.get("confidence").unwrap_or_default(),
+                topic: row.get("topic").unwrap_or_default(),
+                created_at: row.get("created_at").unwrap_or_default(),
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Whether `e` is a uniqueness-constraint violation, i.e. a concurrent writer got there first.
+fn is_constraint_violation(e: &anyhow::Error) -> bool {
+    let text = format!("{e:?}");
+    text.contains("ConstraintValidationFailed") || text.contains("already exists with label")
+}
+
+/// Runs a versioned write whose query returns `object_node_id`, `version_node_id` and
+/// `version`, in one transaction. The query computes the version itself after locking the
+/// object node, so concurrent writers serialize; if two still race on creating the object,
+/// the loser hits the uniqueness constraint and is retried once.
+async fn persist_versioned(
+    graph: &Graph,
+    q: neo4rs::Query,
+    kind: Versioned,
+    id: &str,
+) -> Result<(i64, GraphUpdateResult)> {
+    match persist_versioned_once(graph, q.clone(), kind, id).await {
+        Err(e) if is_constraint_violation(&e) => persist_versioned_once(graph, q, kind, id).await,
+        other => other,
+    }
+    .context(CosError::GraphWrite)
+}
 
-    if let Some(row) = stream.next().await.context("read truth version")? {
-        let v: i64 = row.get("v").context("missing truth version")?;
-        Ok(v + 1)
-    } else {
-        Ok(1)
+async fn persist_versioned_once(
+    graph: &Graph,
+    q: neo4rs::Query,
+    kind: Versioned,
+    id: &str,
+) -> Result<(i64, GraphUpdateResult)> {
+    let mut txn = graph.start_txn().await.context("start neo4j txn")?;
+    match run_versioned(&mut txn, q, kind, id).await {
+        Ok(result) => {
+            txn.commit()
+                .await
+                .with_context(|| format!("commit {}", kind.what))?;
+            Ok(result)
+        }
+        Err(e) => {
+            let _ = txn.rollback().await;
+            Err(e)
+        }
     }
 }
 
+/// The object and version labels of a versioned write, for its `GraphUpdateEntry`s.
+#[derive(Debug, Clone, Copy)]
+struct Versioned {
+    object: &'static str,
+    version: &'static str,
+    key: &'static str,
+    version_key: &'static str,
+    what: &'static str,
+}
+
+const DECISION: Versioned = Versioned {
+    object: "Decision",
+    version: "DecisionVersion",
+    key: "decision_id",
+    version_key: "decision_version_id",
+    what: "persist_decision_version",
+};
+
+const TRUTH: Versioned = Versioned {
+    object: "TruthObject",
+    version: "TruthVersion",
+    key: "truth_id",
+    version_key: "truth_version_id",
+    what: "persist_truth_version",
+};
+
+/// Runs one versioned write of object `id` inside `txn`, see `persist_versioned`.
+async fn run_versioned(
+    txn: &mut neo4rs::Txn,
+    q: neo4rs::Query,
+    kind: Versioned,
+    id: &str,
+) -> Result<(i64, GraphUpdateResult)> {
+    let what = kind.what;
+    let mut stream = txn
+        .execute(q)
+        .await
+        .with_context(|| format!("execute {what}"))?;
+    let row = stream
+        .next(txn.handle())
+        .await
+        .with_context(|| format!("read {what} result"))?
+        .with_context(|| format!("{what} returned no row"))?;
+    let object_node_id: String = row.get("object_node_id").context("missing object_node_id")?;
+    let version_node_id: String = row.get("version_node_id").context("missing version_node_id")?;
+    let version: i64 = row.get("version").context("missing version")?;
+    Ok((
+        version,
+        GraphUpdateResult {
+            nodes: vec![
+                GraphUpdateEntry::node(object_node_id, kind.object, kind.key, id),
+                GraphUpdateEntry::version(
+                    version_node_id,
+                    kind.version,
+                    kind.version_key,
+                    id,
+                    version,
+                ),
+            ],
+            edges: Vec::new(),
+        },
+    ))
+}
+
+/// The reasoning stored on a `DecisionVersion` next to its summary. Versions written before it
+/// was recorded have none of these properties, so every field is optional when read back.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DecisionContent {
+    #[serde(default)]
+    pub rationale: Option<String>,
+    #[serde(default)]
+    pub evidence: Vec<String>,
+    #[serde(default)]
+    pub assumptions: Vec<String>,
+    /// The RAG snippets the OrgBrain was given, as retrieved (see `bounded_snippets`).
+    #[serde(default)]
+    pub retrieved: Vec<RetrievedSnippet>,
+    /// Stored as `kind`: `announcement` for CEO announcements, unset for OrgBrain decisions.
+    #[serde(default)]
+    pub kind: Option<String>,
+}
+
+/// A RAG snippet as retrieval returned it, before the model saw it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RetrievedSnippet {
+    pub content: String,
+    pub score: f32,
+    /// `source` metadata of the document (e.g. the CSV file it was ingested from).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// Snippets stored per decision version, and characters kept of each.
+const MAX_STORED_SNIPPETS: usize = 20;
+const MAX_STORED_SNIPPET_CHARS: usize = 2000;
+
+/// `snippets` cut down to what is stored on a version: at most `MAX_STORED_SNIPPETS`, each
+/// within `MAX_STORED_SNIPPET_CHARS` (ending in `…[truncated]` when cut).
+pub fn bounded_snippets(snippets: &[RetrievedSnippet]) -> Vec<RetrievedSnippet> {
+    snippets
+        .iter()
+        .take(MAX_STORED_SNIPPETS)
+        .map(|s| {
+            let mut s = s.clone();
+            if s.content.chars().count() > MAX_STORED_SNIPPET_CHARS {
+                s.content = s.content.chars().take(MAX_STORED_SNIPPET_CHARS).collect();
+                s.content.push_str("…[truncated]");
+            }
+            s
+        })
+        .collect()
+}
+
+/// The retrieved snippets stored on one decision version.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DecisionEvidence {
+    pub decision_id: String,
+    pub version: i64,
+    /// Empty for versions written before snippets were stored.
+    pub snippets: Vec<RetrievedSnippet>,
+    /// Stored routing, used for visibility; not returned.
+    #[serde(skip)]
+    pub routing_json: String,
+}
+
+/// Retrieved snippets of version `version` of `decision_id`, or of its current version.
+pub async fn decision_evidence(
+    graph: &Graph,
+    decision_id: &str,
+    version: Option<i64>,
+) -> Result<Option<DecisionEvidence>> {
+    let q = query(
+        r#"
+MATCH (d:Decision {decision_id: $decision_id})-[:CURRENT]->(cur:DecisionVersion)
+MATCH (dv:DecisionVersion {decision_id: $decision_id})
+WHERE ($version IS NULL AND dv = cur) OR dv.version = $version
+RETURN dv.version AS version, coalesce(dv.retrieved_json, '[]') AS retrieved_json,
+       coalesce(dv.routing_json, '{}') AS routing_json
+"#,
+    )
+    .param("decision_id", decision_id.to_string())
+    .param("version", version);
+    let mut stream = graph.execute(q).await.context("query decision evidence")?;
+    let Some(row) = stream.next().await.context("read decision evidence")? else {
+        return Ok(None);
+    };
+    let retrieved_json: String = row.get("retrieved_json").unwrap_or_default();
+    Ok(Some(DecisionEvidence {
+        decision_id: decision_id.to_string(),
+        version: row.get("version").unwrap_or_default(),
+        snippets: serde_json::from_str(&retrieved_json).unwrap_or_default(),
+        routing_json: row.get("routing_json").unwrap_or_default(),
+    }))
+}
+
+/// Creates the next `DecisionVersion` of `decision_id` and moves `CURRENT` to it, linking it
+/// `ABOUT` its topic and `BASED_ON` the current version of each truth in `based_on`.
+/// Returns the version number that was written.
+#[allow(clippy::too_many_arguments)]
 pub async fn persist_decision_version(
     graph: &Graph,
     decision_id: String,
-    version: i64,
     summary: String,
     confidence: f64,
     trigger_events: Vec<Uuid>,
     agents_involved: Vec<String>,
     routing: Value,
-) -> Result<GraphUpdateResult> {
-    let routing_json = routing_to_json(&routing);
-    let routing_agents = routing_agents(&routing);
-    let decision_version_id = format!("{}:v{}", decision_id.clone(), version);
-    let mut txn = graph.start_txn().await.context("start neo4j txn")?;
+    topic: String,
+    based_on: Vec<String>,
+    content: DecisionContent,
+) -> Result<(i64, GraphUpdateResult)> {
+    let q = decision_version_query(
+        decision_id.clone(),
+        summary,
+        confidence,
+        trigger_events,
+        agents_involved,
+        &routing,
+        &topic,
+        based_on,
+        content,
+        false,
+    );
+    persist_versioned(graph, q, DECISION, &decision_id).await
+}
 
-    // MERGE decision and CREATE version.
-    // Note: we set CURRENT pointer transactionally by deleting existing CURRENT and creating new.
-    let q = query(
+#[allow(clippy::too_many_arguments)]
+fn decision_version_query(
+    decision_id: String,
+    summary: String,
+    confidence: f64,
+    trigger_events: Vec<Uuid>,
+    agents_involved: Vec<String>,
+    routing: &Value,
+    topic: &str,
+    based_on: Vec<String>,
+    content: DecisionContent,
+    pending: bool,
+) -> neo4rs::Query {
+    let routing_json = routing_to_json(routing);
+    let routing_agents = routing_agents(routing);
+
+    // Setting updated_at takes the write lock on the Decision before the current version is
+    // read, so a concurrent writer waits and then sees this version as CURRENT. A pending
+    // version takes the next number too, but is only linked PENDING; CURRENT stays put.
+    query(
         r#"
 MERGE (d:Decision {decision_id: $decision_id})
 ON CREATE SET d.created_at = datetime()
+SET d.updated_at = datetime()
+WITH d
+OPTIONAL MATCH (d)-[c:CURRENT]->(old:DecisionVersion)
+OPTIONAL MATCH (d)-[:PENDING]->(p:DecisionVersion)
+WITH d, c, old, coalesce(old.version, 0) AS current, coalesce(max(p.version), 0) AS proposed
+WITH d, c, old, CASE WHEN current > proposed THEN current ELSE proposed END + 1 AS version
 CREATE (dv:DecisionVersion {
-  decision_version_id: $decision_version_id,
+  decision_version_id: $decision_id + ':v' + toString(version),
   decision_id: $decision_id,
-  version: $version,
+  version: version,
   created_at: datetime(),
   summary: $summary,
   confidence: $confidence,
   trigger_events: $trigger_events,
   agents_involved: $agents_involved,
   routing_agents: $routing_agents,
-  routing_json: $routing_json
+  routing_json: $routing_json,
+  rationale: CASE WHEN $rationale = '' THEN null ELSE $rationale END,
+  evidence: $evidence,
+  assumptions: $assumptions,
+  retrieved_json: $retrieved_json,
+  kind: $kind,
+  pending: CASE WHEN $pending THEN true ELSE null END
 })
-WITH d, dv
-OPTIONAL MATCH (d)-[c:CURRENT]->(old:DecisionVersion)
-FOREACH (_ IN CASE WHEN c IS NULL THEN [] ELSE [1] END | DELETE c)
-MERGE (d)-[:CURRENT]->(dv)
-WITH d, dv, old
-FOREACH (_ IN CASE WHEN old IS NULL THEN [] ELSE [1] END | MERGE (dv)-[:SUPERSEDES]->(old))
-WITH d, dv
+FOREACH (_ IN CASE WHEN $pending THEN [1] ELSE [] END | MERGE (d)-[:PENDING]->(dv))
+FOREACH (_ IN CASE WHEN c IS NULL OR $pending THEN [] ELSE [1] END | DELETE c)
+FOREACH (_ IN CASE WHEN $pending THEN [] ELSE [1] END | MERGE (d)-[:CURRENT]->(dv))
+WITH d, dv, old, version
+FOREACH (_ IN CASE WHEN old IS NULL OR $pending THEN [] ELSE [1] END |
+  MERGE (dv)-[:SUPERSEDES]->(old))
+WITH d, dv, version
+FOREACH (_ IN CASE WHEN $topic = '' THEN [] ELSE [1] END |
+  MERGE (t:Topic {topic_id: $topic})
+  ON CREATE SET t.created_at = datetime(), t.topic = $topic
+  MERGE (dv)-[:ABOUT]->(t))
+WITH d, dv, version
+CALL {
+  WITH dv
+  MATCH (o:TruthObject)-[:CURRENT]->(tv:TruthVersion)
+  WHERE o.truth_id IN $based_on
+  MERGE (dv)-[:BASED_ON]->(tv)
+  RETURN count(tv) AS based_on_count
+}
+WITH d, dv, version
 UNWIND $agents_involved AS aid
-MERGE (e:Employee {employee_id: aid})
+MERGE (a:Employee {employee_id: aid})
+WITH d, dv, version, coalesce(a.canonical_employee_id, aid) AS eid
+MERGE (e:Employee {employee_id: eid})
 MERGE (e)-[:PARTICIPATED_IN]->(dv)
-RETURN elementId(d) AS decision_node_id, elementId(dv) AS version_node_id
+RETURN elementId(d) AS object_node_id, elementId(dv) AS version_node_id, version
 "#,
     )
     .param("decision_id", decision_id)
-    .param("decision_version_id", decision_version_id)
-    .param("version", version)
     .param("summary", summary)
     .param("confidence", confidence)
     .param(
@@ -392,77 +2734,154 @@ RETURN elementId(d) AS decision_node_id, elementId(dv) AS version_node_id
     )
     .param("agents_involved", agents_involved)
     .param("routing_agents", routing_agents)
-    .param("routing_json", routing_json);
-
-    let mut stream = txn.execute(q).await.context("execute persist_decision_version")?;
-    let row = stream
-        .next(txn.handle())
-        .await
-        .context("read persist_decision_version result")?
-        .context("persist_decision_version returned no row")?;
+    .param("routing_json", routing_json)
+    .param("topic", topic.trim().to_lowercase())
+    .param("based_on", based_on)
+    .param("rationale", content.rationale.unwrap_or_default())
+    .param("evidence", content.evidence)
+    .param("assumptions", content.assumptions)
+    .param(
+        "retrieved_json",
+        serde_json::to_string(&bounded_snippets(&content.retrieved)).unwrap_or_default(),
+    )
+    .param("kind", content.kind)
+    .param("pending", pending)
+}
 
-    let decision_node_id: String = row.get("decision_node_id").context("missing decision_node_id")?;
+/// Makes pending version `version` of `decision_id` its `CURRENT` version, superseding the
+/// current one. `None` when there is no such pending version; a `Conflict` error when a newer
+/// version became current after it was proposed.
+pub async fn promote_decision_version(
+    graph: &Graph,
+    decision_id: &str,
+    version: i64,
+) -> Result<Option<GraphUpdateResult>> {
+    let q = query(
+        r#"
+MATCH (d:Decision {decision_id: $decision_id})
+      -[p:PENDING]->(dv:DecisionVersion {version: $version})
+SET d.updated_at = datetime()
+WITH d, p, dv
+OPTIONAL MATCH (d)-[c:CURRENT]->(old:DecisionVersion)
+WITH d, p, dv, c, old, (old IS NULL OR old.version < dv.version) AS promote
+FOREACH (_ IN CASE WHEN promote THEN [1] ELSE [] END |
+  DELETE p
+  REMOVE dv.pending
+  SET dv.approved_at = datetime()
+  MERGE (d)-[:CURRENT]->(dv))
+FOREACH (_ IN CASE WHEN promote AND c IS NOT NULL THEN [1] ELSE [] END |
+  DELETE c
+  MERGE (dv)-[:SUPERSEDES]->(old))
+RETURN elementId(d) AS object_node_id, elementId(dv) AS version_node_id, promote,
+       old.version AS current
+"#,
+    )
+    .param("decision_id", decision_id.to_string())
+    .param("version", version);
+    let mut stream = graph.execute(q).await.context("promote decision version")?;
+    let Some(row) = stream.next().await.context("read promoted version")? else {
+        return Ok(None);
+    };
+    if !row.get::<bool>("promote").unwrap_or(false) {
+        let current: i64 = row.get("current").unwrap_or_default();
+        let moved = format!("{decision_id} moved to v{current} after v{version} was proposed");
+        return Err(CosError::Conflict(moved).into());
+    }
+    let object_node_id: String = row.get("object_node_id").context("missing object_node_id")?;
     let version_node_id: String = row.get("version_node_id").context("missing version_node_id")?;
-
-    txn.commit().await.context("commit persist_decision_version")?;
-
-    Ok(GraphUpdateResult {
-        nodes: vec![decision_node_id, version_node_id],
+    Ok(Some(GraphUpdateResult {
+        nodes: vec![
+            GraphUpdateEntry::node(object_node_id, DECISION.object, DECISION.key, decision_id),
+            GraphUpdateEntry::version(
+                version_node_id,
+                DECISION.version,
+                DECISION.version_key,
+                decision_id,
+                version,
+            ),
+        ],
         edges: Vec::new(),
-    })
+    }))
 }
 
+/// Creates the next `TruthVersion` of `truth_id` and moves `CURRENT` to it.
+/// Returns the version number that was written.
+#[allow(clippy::too_many_arguments)]
 pub async fn persist_truth_version(
     graph: &Graph,
     truth_id: String,
     kind: String,
-    version: i64,
     summary: String,
     confidence: f64,
     trigger_events: Vec<Uuid>,
     agents_involved: Vec<String>,
     routing: Value,
-) -> Result<GraphUpdateResult> {
-    let routing_json = routing_to_json(&routing);
-    let routing_agents = routing_agents(&routing);
-    let truth_version_id = format!("{}:v{}", truth_id.clone(), version);
-    let mut txn = graph.start_txn().await.context("start neo4j txn")?;
+) -> Result<(i64, GraphUpdateResult)> {
+    let q = truth_version_query(
+        truth_id.clone(),
+        kind,
+        summary,
+        confidence,
+        trigger_events,
+        agents_involved,
+        &routing,
+    );
+    persist_versioned(graph, q, TRUTH, &truth_id).await
+}
 
-    let q = query(
+fn truth_version_query(
+    truth_id: String,
+    kind: String,
+    summary: String,
+    confidence: f64,
+    trigger_events: Vec<Uuid>,
+    agents_involved: Vec<String>,
+    routing: &Value,
+) -> neo4rs::Query {
+    let routing_json = routing_to_json(routing);
+    let routing_agents = routing_agents(routing);
+    let content_hash = crate::utils::content_hash(&summary);
+
+    // See persist_decision_version for why updated_at is set first.
+    query(
         r#"
 MERGE (o:TruthObject {truth_id: $truth_id})
 ON CREATE SET o.created_at = datetime(), o.kind = $kind
 ON MATCH SET o.kind = coalesce(o.kind, $kind)
+SET o.updated_at = datetime(), o.active = true
+WITH o
+OPTIONAL MATCH (o)-[c:CURRENT]->(old:TruthVersion)
+WITH o, c, old, coalesce(old.version, 0) + 1 AS version
 CREATE (tv:TruthVersion {
-  truth_version_id: $truth_version_id,
+  truth_version_id: $truth_id + ':v' + toString(version),
   truth_id: $truth_id,
-  version: $version,
+  version: version,
   created_at: datetime(),
   summary: $summary,
+  content_hash: $content_hash,
   confidence: $confidence,
   trigger_events: $trigger_events,
   agents_involved: $agents_involved,
   routing_agents: $routing_agents,
   routing_json: $routing_json
 })
-WITH o, tv
-OPTIONAL MATCH (o)-[c:CURRENT]->(old:TruthVersion)
 FOREACH (_ IN CASE WHEN c IS NULL THEN [] ELSE [1] END | DELETE c)
 MERGE (o)-[:CURRENT]->(tv)
-WITH o, tv, old
+WITH o, tv, old, version
 FOREACH (_ IN CASE WHEN old IS NULL THEN [] ELSE [1] END | MERGE (tv)-[:SUPERSEDES]->(old))
-WITH o, tv
+WITH o, tv, version
 UNWIND $agents_involved AS aid
-MERGE (e:Employee {employee_id: aid})
+MERGE (a:Employee {employee_id: aid})
+WITH o, tv, version, coalesce(a.canonical_employee_id, aid) AS eid
+MERGE (e:Employee {employee_id: eid})
 MERGE (e)-[:PARTICIPATED_IN]->(tv)
-RETURN elementId(o) AS truth_node_id, elementId(tv) AS version_node_id
+RETURN elementId(o) AS object_node_id, elementId(tv) AS version_node_id, version
 "#,
     )
     .param("truth_id", truth_id)
     .param("kind", kind)
-    .param("truth_version_id", truth_version_id)
-    .param("version", version)
     .param("summary", summary)
+    .param("content_hash", content_hash)
     .param("confidence", confidence)
     .param(
         "trigger_events",
@@ -473,22 +2892,293 @@ RETURN elementId(o) AS truth_node_id, elementId(tv) AS version_node_id
     )
     .param("agents_involved", agents_involved)
     .param("routing_agents", routing_agents)
-    .param("routing_json", routing_json);
+    .param("routing_json", routing_json)
+}
 
-    let mut stream = txn.execute(q).await.context("execute persist_truth_version")?;
-    let row = stream
-        .next(txn.handle())
-        .await
-        .context("read persist_truth_version result")?
-        .context("persist_truth_version returned no row")?;
+/// A truth version written as part of an `AskOutcome`.
+#[derive(Debug, Clone)]
+pub struct TruthWrite {
+    pub truth_id: String,
+    pub kind: String,
+    pub summary: String,
+}
 
-    let truth_node_id: String = row.get("truth_node_id").context("missing truth_node_id")?;
-    let version_node_id: String = row.get("version_node_id").context("missing version_node_id")?;
+/// Everything one `/ask` persists. `truths` share the decision's confidence, trigger events,
+/// agents and routing; `turns` are `(role, content)` pairs of `employee_id`'s conversation.
+#[derive(Debug, Clone)]
+pub struct AskOutcome {
+    pub decision_id: String,
+    pub summary: String,
+    pub confidence: f64,
+    pub trigger_events: Vec<Uuid>,
+    pub agents_involved: Vec<String>,
+    pub routing: Value,
+    pub topic: String,
+    pub based_on: Vec<String>,
+    pub content: DecisionContent,
+    pub truths: Vec<TruthWrite>,
+    pub employee_id: String,
+    pub turns: Vec<(String, String)>,
+    /// Write the decision version as pending: linked `PENDING` from its Decision, with `CURRENT`
+    /// left where it is until `promote_decision_version`.
+    pub pending: bool,
+}
+
+/// Writes the decision version, the truth versions and the conversation turns of `outcome` in
+/// one transaction, so a failure leaves none of them behind. Like `persist_versioned`, a lost
+/// race on a version number is retried once. Returns the decision version that was written.
+pub async fn persist_ask_outcome(
+    graph: &Graph,
+    outcome: &AskOutcome,
+) -> Result<(i64, GraphUpdateResult)> {
+    match persist_ask_outcome_once(graph, outcome).await {
+        Err(e) if is_constraint_violation(&e) => persist_ask_outcome_once(graph, outcome).await,
+        other => other,
+    }
+    .context(CosError::GraphWrite)
+}
+
+async fn persist_ask_outcome_once(
+    graph: &Graph,
+    outcome: &AskOutcome,
+) -> Result<(i64, GraphUpdateResult)> {
+    let mut txn = graph.start_txn().await.context("start neo4j txn")?;
+    let result = async {
+        // The decision goes first so BASED_ON points at the truth it was decided on, not at
+        // the versions this ask writes.
+        let decision = decision_version_query(
+            outcome.decision_id.clone(),
+            outcome.summary.clone(),
+            outcome.confidence,
+            outcome.trigger_events.clone(),
+            outcome.agents_involved.clone(),
+            &outcome.routing,
+            &outcome.topic,
+            outcome.based_on.clone(),
+            outcome.content.clone(),
+            outcome.pending,
+        );
+        let (version, mut upd) =
+            run_versioned(&mut txn, decision, DECISION, &outcome.decision_id).await?;
+        for truth in &outcome.truths {
+            let q = truth_version_query(
+                truth.truth_id.clone(),
+                truth.kind.clone(),
+                truth.summary.clone(),
+                outcome.confidence,
+                outcome.trigger_events.clone(),
+                outcome.agents_involved.clone(),
+                &outcome.routing,
+            );
+            let (_, truth_upd) = run_versioned(&mut txn, q, TRUTH, &truth.truth_id)
+                .await
+                .with_context(|| format!("truth {}", truth.truth_id))?;
+            upd.nodes.extend(truth_upd.nodes);
+            upd.edges.extend(truth_upd.edges);
+        }
+        for (role, content) in &outcome.turns {
+            txn.run(conversation_turn_query(&outcome.employee_id, role, content))
+                .await
+                .context("persist conversation turn")?;
+        }
+        Ok::<_, anyhow::Error>((version, upd))
+    }
+    .await;
 
-    txn.commit().await.context("commit persist_truth_version")?;
+    match result {
+        Ok(result) => {
+            txn.commit().await.context("commit ask outcome")?;
+            Ok(result)
+        }
+        Err(e) => {
+            let _ = txn.rollback().await;
+            Err(e)
+        }
+    }
+}
 
+/// Marks `truth_id` inactive, which takes it out of the current truth. Its versions are kept.
+/// Unknown ids are a no-op.
+pub async fn retire_truth(graph: &Graph, truth_id: &str) -> Result<GraphUpdateResult> {
+    let q = query(
+        r#"
+MATCH (o:TruthObject {truth_id: $truth_id})
+SET o.active = false, o.retired_at = datetime(), o.updated_at = datetime()
+RETURN elementId(o) AS object_node_id
+"#,
+    )
+    .param("truth_id", truth_id.to_string());
+    let mut stream = graph.execute(q).await.context("retire truth")?;
+    let nodes = match stream.next().await.context("read retire truth")? {
+        Some(row) => vec![GraphUpdateEntry::node(
+            row.get::<String>("object_node_id").context("missing object_node_id")?,
+            TRUTH.object,
+            TRUTH.key,
+            truth_id,
+        )],
+        None => Vec::new(),
+    };
     Ok(GraphUpdateResult {
-        nodes: vec![truth_node_id, version_node_id],
+        nodes,
         edges: Vec::new(),
     })
 }
+
+/// Marks `truth_id` inactive and links it to `target` with `MERGED_INTO`. A no-op unless both
+/// truth objects exist.
+pub async fn merge_truth(graph: &Graph, truth_id: &str, target: &str) -> Result<GraphUpdateResult> {
+    let q = query(
+        r#"
+MATCH (o:TruthObject {truth_id: $truth_id})
+MATCH (t:TruthObject {truth_id: $target})
+SET o.active = false, o.merged_into = $target, o.updated_at = datetime()
+MERGE (o)-[r:MERGED_INTO]->(t)
+ON CREATE SET r.created_at = datetime()
+RETURN elementId(o) AS object_node_id, elementId(r) AS edge_id
+"#,
+    )
+    .param("truth_id", truth_id.to_string())
+    .param("target", target.to_string());
+    let mut stream = graph.execute(q).await.context("merge truth")?;
+    let Some(row) = stream.next().await.context("read merge truth")? else {
+        return Ok(GraphUpdateResult::empty());
+    };
+    let node = GraphUpdateEntry::node(
+        row.get::<String>("object_node_id").context("missing object_node_id")?,
+        TRUTH.object,
+        TRUTH.key,
+        truth_id,
+    );
+    let edge = GraphUpdateEntry::edge(
+        row.get::<String>("edge_id").context("missing edge_id")?,
+        "MERGED_INTO",
+        &node.display,
+        &format!("{} {target}", TRUTH.object),
+    );
+    Ok(GraphUpdateResult {
+        nodes: vec![node],
+        edges: vec![edge],
+    })
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct CompactionSummary {
+    pub objects_compacted: usize,
+    pub versions_deleted: usize,
+}
+
+/// For every Decision and TruthObject whose approved chain (`CURRENT` and the versions it
+/// `SUPERSEDES`, transitively) is longer than `keep_latest + 1`, deletes the chain versions
+/// between the first one and the `keep_latest` newest, one transaction per object. `PENDING`
+/// versions are not on the chain and annotated versions are kept, so neither is ever deleted.
+/// Each surviving version is re-linked `SUPERSEDES` to the next surviving older one and its
+/// `compacted_count` records how many versions were removed in between.
+pub async fn compact_versions(graph: &Graph, keep_latest: usize) -> Result<CompactionSummary> {
+    let keep = keep_latest.max(1) as i64;
+    let mut summary = CompactionSummary::default();
+
+    for Versioned { object, version: label, key: id_key, .. } in [DECISION, TRUTH] {
+        let mut stream = graph
+            .execute(
+                query(&format!(
+                    "MATCH (v:{label}) WITH v.{id_key} AS id, count(*) AS n WHERE n > $keep + 1 RETURN id"
+                ))
+                .param("keep", keep),
+            )
+            .await
+            .with_context(|| format!("list {label} chains to compact"))?;
+        let mut ids: Vec<String> = Vec::new();
+        while let Some(row) = stream.next().await.context("read compaction candidates")? {
+            if let Ok(id) = row.get::<String>("id") {
+                ids.push(id);
+            }
+        }
+
+        let compact = format!(
+            r#"
+MATCH (:{object} {{{id_key}: $id}})-[:CURRENT]->(cur:{label})
+MATCH path = (cur)-[:SUPERSEDES*0..]->(v:{label})
+WITH v, max(length(path)) AS depth
+ORDER BY depth
+WITH collect(v) AS vs, collect(size([(v)<-[:ANNOTATES]-() | 1]) > 0) AS annotated
+WHERE size(vs) > $keep + 1
+WITH vs, [i IN range($keep, size(vs) - 2) WHERE NOT annotated[i]] AS doomed
+WITH vs, doomed, [i IN range(0, size(vs) - 1) WHERE NOT i IN doomed] AS kept
+UNWIND range(0, size(kept) - 2) AS k
+WITH vs, doomed, vs[kept[k]] AS newer, vs[kept[k + 1]] AS older, kept[k + 1] - kept[k] - 1 AS gap
+FOREACH (_ IN CASE WHEN gap > 0 THEN [1] ELSE [] END |
+  MERGE (newer)-[:SUPERSEDES]->(older)
+  SET newer.compacted_count = coalesce(newer.compacted_count, 0) + gap)
+WITH DISTINCT vs, doomed
+FOREACH (i IN doomed | DETACH DELETE vs[i])
+RETURN size(doomed) AS deleted
+"#
+        );
+        for id in ids {
+            let mut txn = graph.start_txn().await.context("start neo4j txn")?;
+            let result = async {
+                let mut stream = txn
+                    .execute(query(&compact).param("id", id.clone()).param("keep", keep))
+                    .await
+                    .with_context(|| format!("compact {label} chain of {id}"))?;
+                let deleted: i64 = match stream.next(txn.handle()).await? {
+                    Some(row) => row.get("deleted").unwrap_or(0),
+                    None => 0,
+                };
+                Ok::<_, anyhow::Error>(deleted)
+            }
+            .await;
+            match result {
+                Ok(deleted) => {
+                    txn.commit().await.context("commit compaction")?;
+                    if deleted > 0 {
+                        summary.objects_compacted += 1;
+                        summary.versions_deleted += deleted as usize;
+                    }
+                }
+                Err(e) => {
+                    let _ = txn.rollback().await;
+                    return Err(e);
+                }
+            }
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugify_folds_diacritics_and_collapses_separators() {
+        assert_eq!(slugify_identifier("  José  García-Müller "), "jose_garcia_muller");
+        assert_eq!(slugify_identifier("Æsa Straße"), "aesa_strasse");
+        assert_eq!(slugify_identifier("__Bob__ (Eng) #2"), "bob_eng_2");
+        assert_eq!(slugify_identifier("李 !!"), "");
+    }
+
+    #[test]
+    fn a_thread_is_named_after_its_root_message() {
+        let root = email_thread_id("<root@x>", None, &[]);
+        assert!(root.starts_with("thread_") && root.len() == "thread_".len() + 16);
+        assert_eq!(email_thread_id("<reply@x>", Some("<root@x>"), &[]), root);
+        let refs = vec!["<root@x>".to_string(), "<reply@x>".to_string()];
+        assert_eq!(email_thread_id("<reply2@x>", Some("<reply@x>"), &refs), root);
+        assert_ne!(email_thread_id("<other@x>", None, &[]), root);
+    }
+
+    #[test]
+    fn subjects_lose_reply_and_forward_prefixes() {
+        assert_eq!(normalize_subject("RE: Fwd:  re:Q3   Budget "), "q3 budget");
+        assert_eq!(normalize_subject("Fw: Offsite"), "offsite");
+        assert_eq!(normalize_subject("Regional sales"), "regional sales");
+    }
+
+    #[test]
+    fn centroids_move_by_the_running_mean() {
+        assert_eq!(running_mean(&[1.0, 0.0], 3, &[0.0, 1.0]), vec![0.75, 0.25]);
+        assert_eq!(running_mean(&[], 3, &[0.5, 1.0]), vec![0.5, 1.0]);
+        assert_eq!(running_mean(&[1.0, 0.0], 0, &[0.5, 1.0]), vec![0.5, 1.0]);
+    }
+}
@@ -1,20 +1,115 @@
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use async_trait::async_trait;
 use pocketflow_rs::{Context, Node, ProcessResult};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tokio::io::{self, AsyncBufReadExt};
+use std::time::Duration;
 
+use crate::repl::{
+    parse_command, read_line, ReplCommand, CLI_AGENT_ID_KEY, DEFAULT_CLI_AGENT_ID, HELP,
+};
 use crate::state::MyState;
 
-use crate::app_state::APP_STATE;
-use crate::domain::{EmployeeAgentId, Event, EventType, GraphUpdates, ReasoningTrace};
-use crate::neo4j::writer::{next_decision_version, next_truth_version, persist_decision_version, persist_truth_version};
+use crate::app_state::{handles, sessions, traces, APP_STATE};
+use crate::domain::{EmployeeAgentId, EmployeeRole, Event, EventType, GraphUpdates, ReasoningTrace};
+use crate::neo4j::writer::{DecisionContent, RetrievedSnippet};
+use crate::llm_schema::{chat_checked, EMPLOYEE_EVENT_SCHEMA, ORG_DECISION_SCHEMA};
 use crate::utils::{elevenlabs_stt_from_file, elevenlabs_tts_to_mp3_bytes, openai_chat, play_mp3_bytes};
 
 pub struct GetInputNode;
 
 pub struct EndNode;
 
+/// Context key holding the number of retries made for the current failing step.
+pub const RETRY_ATTEMPT_KEY: &str = "retry_attempt";
+
+/// `COS_RETRY_MAX_ATTEMPTS` (default 3): retries before RetryNode gives up.
+fn retry_max_attempts() -> u64 {
+    crate::app_state::config().orgbrain.retry_max_attempts
+}
+
+/// `COS_RETRY_BASE_MS` (default 500): first backoff; each further attempt doubles it.
+fn retry_base_ms() -> u64 {
+    crate::app_state::config().orgbrain.retry_base_ms
+}
+
+/// Backoff before retry `attempt` (1-based): `base_ms` doubled per earlier attempt.
+fn backoff_ms(base_ms: u64, attempt: u64) -> u64 {
+    base_ms.saturating_mul(1u64 << attempt.saturating_sub(1).min(16))
+}
+
+/// Retry for transient upstream errors, Failure for everything else.
+fn failure_state(node: &str, e: &anyhow::Error) -> ProcessResult<MyState> {
+    if crate::llm::is_transient(e) {
+        eprintln!("{node} transient error: {e:#}");
+        ProcessResult::new(MyState::Retry, "retry".to_string())
+    } else {
+        eprintln!("{node} error: {e:#}");
+        ProcessResult::new(MyState::Failure, "failure".to_string())
+    }
+}
+
+/// Puts drained events back on the queue after a transient failure, so the retry sees them.
+async fn requeue_on_transient(events: &[Event], e: anyhow::Error) -> anyhow::Error {
+    if crate::llm::is_transient(&e) {
+        APP_STATE.lock().await.requeue_events(events);
+    }
+    e
+}
+
+/// Acting employee for the terminal flow (set by `/as`).
+fn cli_agent_id(context: &Context) -> String {
+    context
+        .get(CLI_AGENT_ID_KEY)
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_CLI_AGENT_ID)
+        .to_string()
+}
+
+async fn print_traces(agent_id: &str, n: usize) {
+    let rules = handles().visibility_rules;
+    let traces = traces().await;
+    let visible: Vec<(String, &ReasoningTrace)> = traces
+        .iter()
+        .rev()
+        .map(|t| (crate::api::visibility_for_agent(&rules, t, agent_id), t))
+        .filter(|(level, _)| level != "none")
+        .take(n)
+        .collect();
+    if visible.is_empty() {
+        println!("No traces visible to {}.", agent_id);
+        return;
+    }
+    for (level, t) in visible {
+        println!(
+            "[{} v{}] {} (confidence {:.2}): {}",
+            t.decision_id, t.version, t.topic, t.confidence, t.summary
+        );
+        if level == "full" {
+            println!("    rationale: {}", t.rationale);
+            for e in &t.evidence {
+                println!("    evidence: {}", e);
+            }
+        }
+    }
+}
+
+async fn print_truth() {
+    let state = APP_STATE.lock().await;
+    if state.org_truth.is_empty() {
+        println!("No organizational truth recorded yet.");
+        return;
+    }
+    let mut keys: Vec<&String> = state.org_truth.keys().collect();
+    keys.sort();
+    for k in keys {
+        let versions = &state.org_truth[k];
+        if let Some(latest) = versions.last() {
+            println!("{} (v{}): {}", k, versions.len(), latest);
+        }
+    }
+}
+
 fn extract_first_json_object(s: &str) -> Option<String> {
     let start = s.find('{')?;
     let end = s.rfind('}')?;
@@ -28,26 +123,55 @@ fn extract_first_json_object(s: &str) -> Option<String> {
 impl Node for GetInputNode {
     type State = MyState;
 
-    async fn execute(&self, _context: &Context) -> Result<serde_json::Value> {
-        println!("Enter text, or 'stt:/path/to/audio', or 'exit': ");
-        let mut reader = io::BufReader::new(tokio::io::stdin());
-        let mut line = String::new();
-        let n = reader.read_line(&mut line).await?;
-        if n == 0 {
-            return Ok(json!({"mode": "exit", "text": ""}));
-        }
-        let raw = line.trim().to_string();
-
-        if raw == "exit" {
+    async fn execute(&self, context: &Context) -> Result<serde_json::Value> {
+        let agent_id = cli_agent_id(context);
+        let Some(line) = read_line(format!("[{}] > ", agent_id)).await? else {
             return Ok(json!({"mode": "exit", "text": ""}));
-        }
+        };
+        let raw = crate::service::sanitize_input_text(&line);
 
-        if let Some(path) = raw.strip_prefix("stt:") {
-            let text = elevenlabs_stt_from_file(path.trim()).await?;
-            return Ok(json!({"mode": "stt", "text": text}));
+        match parse_command(&raw) {
+            ReplCommand::Exit => Ok(json!({"mode": "exit", "text": ""})),
+            ReplCommand::As(id) => {
+                println!("Now acting as {}", id);
+                Ok(json!({"mode": "as", "agent_id": id}))
+            }
+            ReplCommand::Traces(n) => {
+                print_traces(&agent_id, n).await;
+                Ok(json!({"mode": "command", "text": ""}))
+            }
+            ReplCommand::Truth => {
+                print_truth().await;
+                Ok(json!({"mode": "command", "text": ""}))
+            }
+            ReplCommand::Help => {
+                println!("{}", HELP);
+                Ok(json!({"mode": "command", "text": ""}))
+            }
+            ReplCommand::Invalid(msg) => {
+                println!("{}", msg);
+                Ok(json!({"mode": "command", "text": ""}))
+            }
+            ReplCommand::Stt(path) => {
+                let text = elevenlabs_stt_from_file(&path).await?;
+                Ok(json!({"mode": "stt", "text": text}))
+            }
+            ReplCommand::Text(raw) => {
+                let max_chars = crate::service::ask_max_chars();
+                let len = raw.chars().count();
+                if len > max_chars {
+                    println!(
+                        "That input is {} characters long; the limit is {}. Please shorten it and try again.",
+                        len, max_chars
+                    );
+                    return Ok(json!({"mode": "too_long", "text": ""}));
+                }
+                if raw.is_empty() {
+                    return Ok(json!({"mode": "command", "text": ""}));
+                }
+                Ok(json!({"mode": "text", "text": raw}))
+            }
         }
-
-        Ok(json!({"mode": "text", "text": raw}))
     }
 
     async fn post_process(
@@ -62,6 +186,15 @@ impl Node for GetInputNode {
             if mode == "exit" {
                 return Ok(ProcessResult::new(MyState::Exit, "exit".to_string()));
             }
+            if mode == "as" {
+                if let Some(id) = val.get("agent_id").and_then(|v| v.as_str()) {
+                    context.set(CLI_AGENT_ID_KEY, json!(id));
+                }
+            }
+            // Handled locally; Failure loops back for the next line.
+            if matches!(mode, "too_long" | "as" | "command") {
+                return Ok(ProcessResult::new(MyState::Failure, "failure".to_string()));
+            }
 
             println!("You said: {}", text);
             context.set("input_text", json!(text));
@@ -88,7 +221,7 @@ impl Node for EmployeeAgentNode {
             .unwrap_or("")
             .to_string();
 
-        let agent_id = EmployeeAgentId("employee_1".to_string());
+        let agent_id = EmployeeAgentId(cli_agent_id(context));
 
         let system = r#"You are an EmployeeAgent.
 Given the user's input, emit a single event for the OrgBrain to process.
@@ -100,7 +233,14 @@ Return STRICT JSON with keys:
 - private_note: a short private note (may include sensitive/rough thoughts)
 "#;
 
-        let out = openai_chat(system, &input_text).await?;
+        let input_text = &input_text;
+        let out = chat_checked(
+            "EmployeeAgent",
+            &EMPLOYEE_EVENT_SCHEMA,
+            system,
+            move |system| async move { openai_chat(&system, input_text).await },
+        )
+        .await?;
         let parsed: serde_json::Value = serde_json::from_str(&out).unwrap_or_else(|_| {
             json!({
                 "event_type": "update",
@@ -136,8 +276,7 @@ Return STRICT JSON with keys:
             .unwrap_or("")
             .to_string();
 
-        let mut state = APP_STATE.lock().await;
-        let private_key = state.store_private(&agent_id, private_note);
+        let private_key = sessions().await.store_private(&agent_id, private_note);
         let event = Event::new(agent_id.clone(), event_type, topic, confidence, vec![private_key]);
         let event_id = event.event_id;
 
@@ -146,7 +285,7 @@ Return STRICT JSON with keys:
             event_id, event.event_type, event.topic, event.confidence
         );
 
-        state.emit(event);
+        APP_STATE.lock().await.emit(event);
 
         Ok(json!({"event_id": event_id.to_string(), "agent_id": agent_id.0}))
     }
@@ -158,16 +297,49 @@ Return STRICT JSON with keys:
     ) -> Result<ProcessResult<MyState>> {
         if let Ok(val) = result {
             context.set("last_employee_event", val.clone());
+            context.set(RETRY_ATTEMPT_KEY, json!(0));
             Ok(ProcessResult::new(MyState::Success, "success".to_string()))
         } else {
-            if let Err(e) = result {
-                eprintln!("EmployeeAgentNode error: {e}");
+            match result {
+                Err(e) => Ok(failure_state("EmployeeAgentNode", e)),
+                Ok(_) => Ok(ProcessResult::new(MyState::Failure, "failure".to_string())),
             }
-            Ok(ProcessResult::new(MyState::Failure, "failure".to_string()))
         }
     }
 }
 
+/// Context key holding the OrgBrain's draft decision until `RecordDecisionNode` writes it.
+pub const BRAIN_DRAFT_KEY: &str = "brain_draft";
+
+/// Context key holding the reviewer's verdict on the draft in `BRAIN_DRAFT_KEY`.
+pub const REVIEW_KEY: &str = "brain_review";
+
+/// What `OrgBrainNode` decided, handed to the reviewer and then to `RecordDecisionNode`.
+#[derive(Debug, Serialize, Deserialize)]
+struct BrainDraft {
+    /// The events reasoned over, already drained from the queue.
+    events: Vec<Event>,
+    /// The OrgBrain's output as parsed.
+    parsed: serde_json::Value,
+    rag_snippets: Vec<String>,
+    retrieved: Vec<RetrievedSnippet>,
+    /// Truth ids the prompt included, which the decision is `BASED_ON`.
+    truth_included: Vec<String>,
+    evidence: Vec<String>,
+    assumptions: Vec<String>,
+    trace_topic: String,
+}
+
+fn brain_draft(context: &Context) -> Result<Option<BrainDraft>> {
+    context
+        .get(BRAIN_DRAFT_KEY)
+        .map(|v| serde_json::from_value(v.clone()))
+        .transpose()
+        .context("invalid brain draft in context")
+}
+
+/// Reasons over the queued events and leaves a draft decision in `BRAIN_DRAFT_KEY`; nothing is
+/// persisted until `RecordDecisionNode`.
 pub struct OrgBrainNode;
 
 #[async_trait]
@@ -176,9 +348,13 @@ impl Node for OrgBrainNode {
 
     async fn execute(&self, _context: &Context) -> Result<serde_json::Value> {
         let mut state = APP_STATE.lock().await;
-        let events = state.drain_events();
-        let neo4j = state.neo4j.clone();
+        let events = state.drain_events_up_to(crate::app_state::max_events_per_brain());
+        let remaining = state.queued_events();
         drop(state);
+        let store = handles().graph_store;
+        if remaining > 0 {
+            println!("{remaining} more event(s) queued for the next OrgBrain call.");
+        }
 
         if events.is_empty() {
             return Ok(json!({"response": "No new events.", "decision": "noop"}));
@@ -186,20 +362,41 @@ impl Node for OrgBrainNode {
 
         let events_json = serde_json::to_string(&events)?;
 
-        let rag_snippets = {
-            let state = APP_STATE.lock().await;
-            state.rag_search(format!("{}", events_json), 3).await?
+        // Every employee behind these events may see the answer, so only sources all of their
+        // roles may see are retrieved.
+        let roles: Vec<_> = events
+            .iter()
+            .map(|e| crate::api::employee_role_from_agent_id(&e.emitted_by.0))
+            .collect();
+        let rag_result = crate::app_state::rag_search(
+            events_json,
+            crate::retrieval::rag_top_k(),
+            &roles,
+        )
+        .await;
+        let retrieved = match rag_result {
+            Ok(snippets) => snippets,
+            Err(e) => return Err(requeue_on_transient(&events, e).await),
         };
+        let rag_snippets: Vec<String> = retrieved.iter().map(|s| s.content.clone()).collect();
 
-        let truth_snapshot = {
+        // Only the truth relevant to these events, within COS_TRUTH_PROMPT_CHARS.
+        let truth_selection = {
             let state = APP_STATE.lock().await;
-            state.org_truth.clone()
+            let pinned = crate::truth_context::pinned_truth_ids(&traces().await, &events);
+            crate::truth_context::select_truth(
+                &state.org_truth,
+                &events,
+                &pinned,
+                crate::truth_context::truth_top_k(),
+                crate::truth_context::truth_prompt_chars(),
+            )
         };
 
         let system = r#"You are the OrgBrain.
 You maintain the Organization Truth (versioned), and produce a reasoning trace.
 
-Use retrieved policy snippets if relevant.
+Use retrieved policy snippets if relevant. graph_context holds the current truth and the latest decisions on these topics from the organization graph; stay consistent with them or say what changed.
 
 Return STRICT JSON with keys:
 - decision_id: stable string identifier for this decision (if new, create a new UUID string)
@@ -211,17 +408,40 @@ Return STRICT JSON with keys:
 - response_text: what to say to the user
 - confidence: number in [0,1]
 - routing: object mapping agent_id -> one of ["full","summary","none"]
-- org_updates: object mapping truth_id -> update_string (can be empty)
+- org_updates: object mapping truth_id -> update_string, or -> {"op": "update"|"retire"|"merge", "content"?, "target"?} (retire drops a truth; merge retires it into target, optionally with new content for target; can be empty)
 "#;
 
+        let graph_context = match &store {
+            Some(store) => {
+                crate::retrieval::graph_context(store.as_ref(), &events, &truth_selection.included)
+                    .await
+            }
+            None => json!({}),
+        };
+
+        let topic_groups = crate::merge::group_by_topic(&events);
+        // The trace is about the topic of the most confident event.
+        let trace_topic = topic_groups
+            .iter()
+            .max_by(|a, b| a.dominant.confidence.total_cmp(&b.dominant.confidence))
+            .map(|g| g.topic.clone())
+            .unwrap_or_else(|| "general".to_string());
         let user = json!({
             "events": events,
+            "topic_groups": crate::merge::prompt_groups(&topic_groups),
             "rag": rag_snippets,
-            "org_truth": truth_snapshot
+            "org_truth": truth_selection.prompt_value(),
+            "graph_context": graph_context
         })
         .to_string();
 
-        let out = openai_chat(system, &user).await?;
+        let system = format!("{}{}", system, crate::merge::TOPIC_GROUPS_INSTRUCTION);
+        let user = &user;
+        let chat = move |system: String| async move { openai_chat(&system, user).await };
+        let out = match chat_checked("OrgBrain", &ORG_DECISION_SCHEMA, &system, chat).await {
+            Ok(out) => out,
+            Err(e) => return Err(requeue_on_transient(&events, e).await),
+        };
         let parsed: serde_json::Value = serde_json::from_str(&out)
             .or_else(|_| {
                 let extracted = match extract_first_json_object(&out) {
@@ -247,6 +467,131 @@ Return STRICT JSON with keys:
             })
         });
 
+        let mut evidence: Vec<String> = parsed
+            .get("evidence")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|x| x.as_str().map(|s| s.to_string()))
+                    .filter(|s| !s.starts_with(crate::truth_context::TRUTH_EVIDENCE_PREFIX))
+                    .collect()
+            })
+            .unwrap_or_default();
+        evidence.extend(truth_selection.evidence());
+        let mut assumptions: Vec<String> = parsed
+            .get("assumptions")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|x| x.as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        for dissent in crate::merge::dissent_assumptions(&topic_groups) {
+            if !assumptions.contains(&dissent) {
+                assumptions.push(dissent);
+            }
+        }
+
+        Ok(serde_json::to_value(BrainDraft {
+            events,
+            parsed,
+            rag_snippets,
+            retrieved,
+            truth_included: truth_selection.included,
+            evidence,
+            assumptions,
+            trace_topic,
+        })?)
+    }
+
+    async fn post_process(
+        &self,
+        context: &mut Context,
+        result: &Result<serde_json::Value>,
+    ) -> Result<ProcessResult<MyState>> {
+        match result {
+            Ok(val) => {
+                // No queued events: nothing for the reviewer or the record step.
+                if val.get("events").is_some() {
+                    context.set(BRAIN_DRAFT_KEY, val.clone());
+                } else {
+                    context.remove(BRAIN_DRAFT_KEY);
+                }
+                context.remove(REVIEW_KEY);
+                context.set(RETRY_ATTEMPT_KEY, json!(0));
+                Ok(ProcessResult::new(MyState::Success, "success".to_string()))
+            }
+            Err(e) => Ok(failure_state("OrgBrainNode", e)),
+        }
+    }
+}
+
+/// Second opinion on the OrgBrain's draft before `RecordDecisionNode` persists it (see
+/// `review::review_decision`). Passes straight through with `COS_REVIEWER=0` or when the
+/// OrgBrain had nothing to decide; a failed review approves.
+pub struct ReviewerAgentNode;
+
+#[async_trait]
+impl Node for ReviewerAgentNode {
+    type State = MyState;
+
+    async fn execute(&self, context: &Context) -> Result<serde_json::Value> {
+        let Some(draft) = brain_draft(context)? else {
+            return Ok(serde_json::Value::Null);
+        };
+        let review = crate::review::review_decision(&draft.parsed, &draft.rag_snippets).await;
+        if review.verdict != crate::review::Verdict::Approve {
+            println!("Reviewer ({:?}): {}", review.verdict, review.notes.join("; "));
+        }
+        Ok(serde_json::to_value(review)?)
+    }
+
+    async fn post_process(
+        &self,
+        context: &mut Context,
+        result: &Result<serde_json::Value>,
+    ) -> Result<ProcessResult<MyState>> {
+        match result {
+            Ok(val) => {
+                if !val.is_null() {
+                    context.set(REVIEW_KEY, val.clone());
+                }
+                Ok(ProcessResult::new(MyState::Success, "success".to_string()))
+            }
+            Err(e) => Ok(failure_state("ReviewerAgentNode", e)),
+        }
+    }
+}
+
+/// Records the OrgBrain's draft with the reviewer's verdict: applies its org updates, writes
+/// the decision and truth versions, keeps the trace and speaks the answer.
+pub struct RecordDecisionNode;
+
+#[async_trait]
+impl Node for RecordDecisionNode {
+    type State = MyState;
+
+    async fn execute(&self, context: &Context) -> Result<serde_json::Value> {
+        let Some(draft) = brain_draft(context)? else {
+            return Ok(json!({"response": "No new events.", "decision": "noop"}));
+        };
+        let review = match context.get(REVIEW_KEY) {
+            Some(v) => serde_json::from_value(v.clone()).context("invalid review in context")?,
+            None => crate::review::Review::approve(),
+        };
+        let BrainDraft {
+            events,
+            parsed,
+            retrieved,
+            truth_included,
+            evidence,
+            mut assumptions,
+            trace_topic,
+            ..
+        } = draft;
+        let store = handles().graph_store;
+
         let decision = parsed
             .get("decision_id")
             .and_then(|v| v.as_str())
@@ -269,24 +614,6 @@ Return STRICT JSON with keys:
             .and_then(|v| v.as_str())
             .unwrap_or("")
             .to_string();
-        let evidence: Vec<String> = parsed
-            .get("evidence")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|x| x.as_str().map(|s| s.to_string()))
-                    .collect()
-            })
-            .unwrap_or_default();
-        let assumptions: Vec<String> = parsed
-            .get("assumptions")
-            .and_then(|v| v.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|x| x.as_str().map(|s| s.to_string()))
-                    .collect()
-            })
-            .unwrap_or_default();
         let response_text = parsed
             .get("response_text")
             .and_then(|v| v.as_str())
@@ -297,7 +624,10 @@ Return STRICT JSON with keys:
             .and_then(|v| v.as_f64())
             .unwrap_or(0.5) as f32;
 
-        let routing_val = parsed.get("routing").cloned().unwrap_or_else(|| json!({}));
+        let routing_val = crate::service::canonical_routing(
+            parsed.get("routing").cloned().unwrap_or_else(|| json!({})),
+        )
+        .await;
         let routing_map: std::collections::HashMap<String, String> = routing_val
             .as_object()
             .map(|obj| {
@@ -307,17 +637,9 @@ Return STRICT JSON with keys:
             })
             .unwrap_or_default();
 
-        let mut updated_nodes = Vec::new();
-        if let Some(obj) = parsed.get("org_updates").and_then(|v| v.as_object()) {
-            let mut state = APP_STATE.lock().await;
-            for (k, v) in obj {
-                let upd = v.as_str().unwrap_or("").to_string();
-                if !upd.is_empty() {
-                    state.update_org_truth(k, upd);
-                    updated_nodes.push(k.clone());
-                }
-            }
-        }
+        let confidence = review.adjusted_confidence(confidence);
+
+        let mut org_updates = crate::org_updates::parse_org_updates(parsed.get("org_updates"));
 
         let mut graph_updates = GraphUpdates {
             nodes: Vec::new(),
@@ -331,64 +653,105 @@ Return STRICT JSON with keys:
         };
 
         let mut decision_version: i64 = 1;
-        if let Some(client) = neo4j {
-            let graph = client.graph();
-
-            decision_version = next_decision_version(graph, &final_decision_id)
-                .await
-                .unwrap_or(1);
-
-            if let Ok(upd) = persist_decision_version(
-                graph,
-                final_decision_id.clone(),
-                decision_version,
-                if summary.is_empty() { decision_label.clone() } else { summary.clone() },
-                confidence as f64,
-                events.iter().map(|e| e.event_id).collect(),
-                events.iter().map(|e| e.emitted_by.0.clone()).collect(),
-                routing_val.clone(),
-            )
-            .await
-            {
-                graph_updates.nodes.extend(upd.nodes);
-                graph_updates.edges.extend(upd.edges);
+        let mut warnings = Vec::new();
+        if let Some(store) = store {
+            let contents = crate::org_updates::truth_contents(&org_updates);
+            let (truths, unchanged) = crate::service::truth_writes(store.as_ref(), &contents).await;
+            assumptions.extend(unchanged);
+            if let Err(e) = store.persist_events(&events).await {
+                let warning = crate::service::persistence_warning(&final_decision_id, "events", &e);
+                warnings.push(warning);
             }
-
-            for truth_id in &updated_nodes {
-                let v = next_truth_version(graph, truth_id).await.unwrap_or(1);
-                let content = {
-                    let state = APP_STATE.lock().await;
-                    state.latest_truth(truth_id).unwrap_or("").to_string()
-                };
-
-                if content.is_empty() {
-                    continue;
-                }
-
-                if let Ok(upd) = persist_truth_version(
-                    graph,
-                    truth_id.clone(),
-                    "org_truth".to_string(),
-                    v,
-                    content,
+            match store
+                .persist_decision_version(
+                    final_decision_id.clone(),
+                    if summary.is_empty() { decision_label.clone() } else { summary.clone() },
                     confidence as f64,
                     events.iter().map(|e| e.event_id).collect(),
                     events.iter().map(|e| e.emitted_by.0.clone()).collect(),
-                    routing_val.clone(),
+                    &routing_val,
+                    &trace_topic,
+                    &truth_included,
+                    &DecisionContent {
+                        rationale: Some(rationale.clone()),
+                        evidence: evidence.clone(),
+                        assumptions: assumptions.clone(),
+                        retrieved: retrieved.clone(),
+                        kind: None,
+                    },
                 )
                 .await
-                {
+            {
+                Ok((v, upd)) => {
+                    decision_version = v;
+                    crate::similar::spawn_embed_decision(
+                        store.clone(),
+                        final_decision_id.clone(),
+                        v,
+                        if summary.is_empty() { decision_label.clone() } else { summary.clone() },
+                    );
                     graph_updates.nodes.extend(upd.nodes);
                     graph_updates.edges.extend(upd.edges);
                 }
+                Err(e) => warnings.push(crate::service::persistence_warning(
+                    &final_decision_id,
+                    "decision version",
+                    &e,
+                )),
+            }
+
+            let mut failed_truths = Vec::new();
+            for truth in truths {
+                match store
+                    .persist_truth_version(
+                        truth.truth_id.clone(),
+                        truth.kind,
+                        truth.summary,
+                        confidence as f64,
+                        events.iter().map(|e| e.event_id).collect(),
+                        events.iter().map(|e| e.emitted_by.0.clone()).collect(),
+                        &routing_val,
+                    )
+                    .await
+                {
+                    Ok((_, upd)) => {
+                        graph_updates.nodes.extend(upd.nodes);
+                        graph_updates.edges.extend(upd.edges);
+                    }
+                    Err(e) => {
+                        warnings.push(crate::service::persistence_warning(
+                            &final_decision_id,
+                            &format!("truth {}", truth.truth_id),
+                            &e,
+                        ));
+                        failed_truths.push(truth.truth_id);
+                    }
+                }
             }
+            // An update whose version wasn't written is not applied (nor merged) either.
+            org_updates.retain(|u| {
+                u.new_content().is_none_or(|(id, _)| !failed_truths.iter().any(|f| f == id))
+            });
+
+            warnings.extend(
+                crate::org_updates::persist_structural(
+                    store.as_ref(),
+                    &mut org_updates,
+                    &mut graph_updates,
+                    &final_decision_id,
+                )
+                .await,
+            );
         }
+        // Only what was persisted reaches the OrgBrain's in-memory truth.
+        crate::org_updates::apply_to_state(&mut *APP_STATE.lock().await, &org_updates);
 
         let trace = ReasoningTrace {
             decision_id: final_decision_id,
-            topic: "general".to_string(),
+            topic: trace_topic,
             summary: if summary.is_empty() { decision_label.clone() } else { summary.clone() },
             version: decision_version,
+            confidence,
             rationale,
             evidence,
             assumptions,
@@ -396,12 +759,17 @@ Return STRICT JSON with keys:
             agents_involved: events.iter().map(|e| e.emitted_by.clone()).collect(),
             graph_updates,
             routing: routing_map,
+            created_at: chrono::Utc::now(),
+            language: None,
+            annotations: review.annotations(),
+            deduplicated: false,
+            needs_approval: review.needs_approval(),
+            persistence_warnings: warnings,
+            kind: None,
         };
 
-        {
-            let mut state = APP_STATE.lock().await;
-            state.add_trace(trace);
-        }
+        let decision_id = trace.decision_id.clone();
+        traces().await.push(trace);
 
         if !response_text.is_empty() {
             println!("OrgBrain: {}", response_text);
@@ -413,6 +781,7 @@ Return STRICT JSON with keys:
         }
 
         Ok(json!({
+            "decision_id": decision_id,
             "decision": decision_label,
             "response_text": response_text,
             "confidence": confidence
@@ -424,14 +793,132 @@ Return STRICT JSON with keys:
         context: &mut Context,
         result: &Result<serde_json::Value>,
     ) -> Result<ProcessResult<MyState>> {
-        if let Ok(val) = result {
-            context.set("brain_response", val.clone());
-            Ok(ProcessResult::new(MyState::Success, "success".to_string()))
-        } else {
-            if let Err(e) = result {
-                eprintln!("OrgBrainNode error: {e}");
+        context.remove(BRAIN_DRAFT_KEY);
+        context.remove(REVIEW_KEY);
+        match result {
+            Ok(val) => {
+                context.set("brain_response", val.clone());
+                Ok(ProcessResult::new(MyState::Success, "success".to_string()))
+            }
+            Err(e) => Ok(failure_state("RecordDecisionNode", e)),
+        }
+    }
+}
+
+/// `COS_FANOUT=1`: after the OrgBrain decides, `FanOutNode` asks a role brain for every role
+/// the decision is routed to. Off by default, which makes the node a pass-through.
+pub fn fan_out_enabled() -> bool {
+    crate::app_state::config().orgbrain.fanout
+}
+
+fn role_brain_prompt(role: &EmployeeRole) -> (&'static str, &'static str) {
+    match role {
+        EmployeeRole::Ceo => (
+            "CEO brain",
+            "You advise the CEO. Comment on the decision below from a business and strategy \
+             perspective: priorities, cost, risk to the company. Two or three sentences.",
+        ),
+        EmployeeRole::Hr => (
+            "HR brain",
+            "You advise HR. Comment on the decision below from a people perspective: policy, \
+             hiring, workload, fairness, compliance. Two or three sentences.",
+        ),
+        EmployeeRole::Engineer => (
+            "Engineer brain",
+            "You advise the engineering team. Comment on the decision below from a technical \
+             perspective: feasibility, effort, reliability, dependencies. Two or three sentences.",
+        ),
+    }
+}
+
+/// Runs after `OrgBrainNode`: asks a role-specialized brain about the decision for each role
+/// its routing reaches (level other than `none`), in parallel, and appends their comments to
+/// the in-memory trace's `evidence` as `<Role> brain: ...`. The stored decision version is not
+/// rewritten. A brain that fails is skipped.
+pub struct FanOutNode;
+
+#[async_trait]
+impl Node for FanOutNode {
+    type State = MyState;
+
+    async fn execute(&self, context: &Context) -> Result<serde_json::Value> {
+        if !fan_out_enabled() {
+            return Ok(json!({"comments": 0}));
+        }
+        let Some(decision_id) = context
+            .get("brain_response")
+            .and_then(|v| v.get("decision_id").and_then(|d| d.as_str()).map(str::to_string))
+        else {
+            return Ok(json!({"comments": 0}));
+        };
+        let Some(trace) = traces()
+            .await
+            .iter()
+            .rev()
+            .find(|t| t.decision_id == decision_id)
+            .cloned()
+        else {
+            return Ok(json!({"comments": 0}));
+        };
+
+        let mut roles: Vec<EmployeeRole> = Vec::new();
+        for (agent_id, level) in &trace.routing {
+            let role = crate::api::employee_role_from_agent_id(agent_id);
+            if level != "none" && !roles.contains(&role) {
+                roles.push(role);
+            }
+        }
+        if roles.is_empty() {
+            return Ok(json!({"comments": 0}));
+        }
+
+        let decision = json!({
+            "topic": trace.topic,
+            "summary": trace.summary,
+            "rationale": trace.rationale,
+            "evidence": trace.evidence,
+            "assumptions": trace.assumptions,
+        })
+        .to_string();
+        let calls = roles.iter().map(|role| {
+            let (name, system) = role_brain_prompt(role);
+            let decision = decision.clone();
+            async move { (name, openai_chat(system, &decision).await) }
+        });
+        let mut comments = Vec::new();
+        for (name, result) in futures::future::join_all(calls).await {
+            match result {
+                Ok(text) if !text.trim().is_empty() => {
+                    println!("{name}: {}", text.trim());
+                    comments.push(format!("{name}: {}", text.trim()));
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("{name} unavailable: {e:#}"),
             }
-            Ok(ProcessResult::new(MyState::Failure, "failure".to_string()))
+        }
+
+        let count = comments.len();
+        if count > 0 {
+            if let Some(t) = traces()
+                .await
+                .iter_mut()
+                .rev()
+                .find(|t| t.decision_id == decision_id && t.version == trace.version)
+            {
+                t.evidence.extend(comments);
+            }
+        }
+        Ok(json!({"comments": count}))
+    }
+
+    async fn post_process(
+        &self,
+        _context: &mut Context,
+        result: &Result<serde_json::Value>,
+    ) -> Result<ProcessResult<MyState>> {
+        match result {
+            Ok(_) => Ok(ProcessResult::new(MyState::Success, "success".to_string())),
+            Err(e) => Ok(failure_state("FanOutNode", e)),
         }
     }
 }
@@ -452,3 +939,111 @@ impl Node for EndNode {
         Ok(ProcessResult::new(MyState::Exit, "exit".to_string()))
     }
 }
+
+/// Sleeps with exponential backoff and routes back to the failed node (Success), or gives up
+/// after `COS_RETRY_MAX_ATTEMPTS` with a message to the user (Failure).
+pub struct RetryNode;
+
+#[async_trait]
+impl Node for RetryNode {
+    type State = MyState;
+
+    async fn execute(&self, context: &Context) -> Result<serde_json::Value> {
+        let attempt = context
+            .get(RETRY_ATTEMPT_KEY)
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+            + 1;
+        let max = retry_max_attempts();
+        if attempt > max {
+            println!(
+                "The assistant is temporarily unavailable (gave up after {} retries). Please try again in a moment.",
+                max
+            );
+            return Ok(json!({"give_up": true, "attempt": attempt}));
+        }
+        let delay_ms = backoff_ms(retry_base_ms(), attempt);
+        println!("Upstream error; retrying in {} ms (attempt {}/{})", delay_ms, attempt, max);
+        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        Ok(json!({"give_up": false, "attempt": attempt}))
+    }
+
+    async fn post_process(
+        &self,
+        context: &mut Context,
+        result: &Result<serde_json::Value>,
+    ) -> Result<ProcessResult<MyState>> {
+        let give_up = match result {
+            Ok(val) => val.get("give_up").and_then(|v| v.as_bool()).unwrap_or(true),
+            Err(_) => true,
+        };
+        if give_up {
+            context.set(RETRY_ATTEMPT_KEY, json!(0));
+            return Ok(ProcessResult::new(MyState::Failure, "failure".to_string()));
+        }
+        if let Ok(val) = result {
+            context.set(RETRY_ATTEMPT_KEY, val.get("attempt").cloned().unwrap_or(json!(0)));
+        }
+        Ok(ProcessResult::new(MyState::Success, "success".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn without_a_draft_review_and_record_pass_through() {
+        let mut context = Context::new();
+        let review = ReviewerAgentNode.execute(&context).await;
+        assert!(review.as_ref().unwrap().is_null());
+        let next = ReviewerAgentNode.post_process(&mut context, &review).await.unwrap();
+        assert_eq!(next.state, MyState::Success);
+        assert!(context.get(REVIEW_KEY).is_none());
+
+        let recorded = RecordDecisionNode.execute(&context).await;
+        let next = RecordDecisionNode.post_process(&mut context, &recorded).await.unwrap();
+        assert_eq!(next.state, MyState::Success);
+        assert_eq!(context.get("brain_response").unwrap()["decision"], "noop");
+    }
+
+    #[tokio::test]
+    async fn a_malformed_draft_fails_the_step_and_is_dropped() {
+        let mut context = Context::new();
+        context.set(BRAIN_DRAFT_KEY, json!({ "events": "not a list" }));
+        let recorded = RecordDecisionNode.execute(&context).await;
+        assert!(recorded.is_err());
+        let next = RecordDecisionNode.post_process(&mut context, &recorded).await.unwrap();
+        assert_eq!(next.state, MyState::Failure);
+        assert!(context.get(BRAIN_DRAFT_KEY).is_none());
+    }
+
+    #[test]
+    fn backoff_doubles_per_attempt_and_saturates() {
+        assert_eq!(backoff_ms(500, 1), 500);
+        assert_eq!(backoff_ms(500, 2), 1000);
+        assert_eq!(backoff_ms(500, 3), 2000);
+        assert_eq!(backoff_ms(500, 40), 500 << 16);
+        assert_eq!(backoff_ms(u64::MAX, 2), u64::MAX);
+    }
+
+    #[tokio::test]
+    async fn retry_gives_up_after_the_last_attempt_and_resets_the_count() {
+        let mut context = Context::new();
+        context.set(RETRY_ATTEMPT_KEY, json!(retry_max_attempts()));
+        let result = RetryNode.execute(&context).await;
+        assert_eq!(result.as_ref().unwrap()["give_up"], true);
+        let next = RetryNode.post_process(&mut context, &result).await.unwrap();
+        assert_eq!(next.state, MyState::Failure);
+        assert_eq!(context.get(RETRY_ATTEMPT_KEY), Some(&json!(0)));
+    }
+
+    #[tokio::test]
+    async fn a_scheduled_retry_routes_back_and_counts_the_attempt() {
+        let mut context = Context::new();
+        let result = Ok(json!({"give_up": false, "attempt": 2}));
+        let next = RetryNode.post_process(&mut context, &result).await.unwrap();
+        assert_eq!(next.state, MyState::Success);
+        assert_eq!(context.get(RETRY_ATTEMPT_KEY), Some(&json!(2)));
+    }
+}
@@ -0,0 +1,198 @@
+use serde_json::Value;
+
+use crate::app_state::AppState;
+use crate::domain::GraphUpdates;
+use crate::graph_store::GraphStore;
+
+/// What the OrgBrain asked to do with one truth id.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TruthOp {
+    /// Record new content as the next version.
+    Update(String),
+    /// Take the truth out of the current set; its versions are kept.
+    Retire,
+    /// Retire the truth into `target`, optionally recording `content` as `target`'s next version.
+    Merge {
+        target: String,
+        content: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrgUpdate {
+    pub truth_id: String,
+    pub op: TruthOp,
+}
+
+fn non_empty(v: Option<&Value>) -> Option<String> {
+    v.and_then(|v| v.as_str())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Parses `org_updates`: each truth id maps to a plain string (shorthand for `update`) or to
+/// `{ "op": "update"|"retire"|"merge", "content"?, "target"? }`. Entries that don't make sense
+/// (empty update, merge without a target or into itself, unknown op) are logged and skipped.
+pub fn parse_org_updates(v: Option<&Value>) -> Vec<OrgUpdate> {
+    let Some(obj) = v.and_then(|v| v.as_object()) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for (truth_id, entry) in obj {
+        let op = match entry {
+            Value::String(_) => non_empty(Some(entry)).map(TruthOp::Update),
+            Value::Object(o) => {
+                let content = non_empty(o.get("content"));
+                match o.get("op").and_then(|v| v.as_str()).unwrap_or("update") {
+                    "update" => content.map(TruthOp::Update),
+                    "retire" => Some(TruthOp::Retire),
+                    "merge" => non_empty(o.get("target"))
+                        .filter(|t| t != truth_id)
+                        .map(|target| TruthOp::Merge { target, content }),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        match op {
+            Some(op) => out.push(OrgUpdate {
+                truth_id: truth_id.clone(),
+                op,
+            }),
+            None => eprintln!("ignoring org update for {truth_id}: {entry}"),
+        }
+    }
+    out
+}
+
+impl OrgUpdate {
+    /// The truth id that gets `content` as its next version, if this update records any.
+    pub fn new_content(&self) -> Option<(&str, &str)> {
+        match &self.op {
+            TruthOp::Update(content) => Some((&self.truth_id, content)),
+            TruthOp::Merge { target, content: Some(content) } => Some((target, content)),
+            _ => None,
+        }
+    }
+}
+
+/// The (truth id, content) versions `updates` record, in order; a later update of the same
+/// truth replaces an earlier one.
+pub fn truth_contents(updates: &[OrgUpdate]) -> Vec<(String, String)> {
+    let mut out: Vec<(String, String)> = Vec::new();
+    for (truth_id, content) in updates.iter().filter_map(OrgUpdate::new_content) {
+        out.retain(|(id, _)| id != truth_id);
+        out.push((truth_id.to_string(), content.to_string()));
+    }
+    out
+}
+
+/// Applies `updates` to the in-process truth. Callers persist them first and only apply the
+/// ones that were stored, so the OrgBrain's view never runs ahead of the graph.
+pub fn apply_to_state(state: &mut AppState, updates: &[OrgUpdate]) {
+    for upd in updates {
+        match &upd.op {
+            TruthOp::Update(content) => state.update_org_truth(&upd.truth_id, content.clone()),
+            TruthOp::Retire => {
+                state.org_truth.remove(&upd.truth_id);
+            }
+            TruthOp::Merge { target, content } => {
+                state.org_truth.remove(&upd.truth_id);
+                if let Some(content) = content {
+                    state.update_org_truth(target, content.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Persists the retire and merge operations. Run after the new truth versions are written, so
+/// a merge target created in the same batch already exists. Returns a warning per failed write;
+/// the failed operations are removed from `updates`, so they are not applied in memory either.
+pub async fn persist_structural(
+    store: &dyn GraphStore,
+    updates: &mut Vec<OrgUpdate>,
+    graph_updates: &mut GraphUpdates,
+    decision_id: &str,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut failed = Vec::new();
+    for (i, upd) in updates.iter().enumerate() {
+        let result = match &upd.op {
+            TruthOp::Update(_) => continue,
+            TruthOp::Retire => store.retire_truth(&upd.truth_id).await,
+            TruthOp::Merge { target, .. } => store.merge_truth(&upd.truth_id, target).await,
+        };
+        match result {
+            Ok(res) => {
+                graph_updates.nodes.extend(res.nodes);
+                graph_updates.edges.extend(res.edges);
+            }
+            Err(e) => {
+                warnings.push(crate::service::persistence_warning(
+                    decision_id,
+                    &format!("org update {}", upd.truth_id),
+                    &e,
+                ));
+                failed.push(i);
+            }
+        }
+    }
+    for i in failed.into_iter().rev() {
+        updates.remove(i);
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn strings_and_typed_entries_parse_and_nonsense_is_skipped() {
+        let raw = json!({
+            "a_remote": "  Remote on Fridays ",
+            "b_parking": { "op": "retire" },
+            "c_office": { "op": "merge", "target": "d_site", "content": "One site" },
+            "e_blank": "  ",
+            "f_self": { "op": "merge", "target": "f_self" },
+            "g_unknown": { "op": "delete" },
+            "h_empty_update": { "op": "update" },
+        });
+        let ops: Vec<_> = parse_org_updates(Some(&raw)).into_iter().map(|u| u.op).collect();
+        assert_eq!(
+            ops,
+            vec![
+                TruthOp::Update("Remote on Fridays".into()),
+                TruthOp::Retire,
+                TruthOp::Merge {
+                    target: "d_site".into(),
+                    content: Some("One site".into()),
+                },
+            ]
+        );
+        assert!(parse_org_updates(Some(&json!(["not", "an", "object"]))).is_empty());
+    }
+
+    #[test]
+    fn applying_updates_records_new_content_and_drops_retired_truth() {
+        let mut state = AppState::new();
+        state.update_org_truth("parking", "by badge".into());
+        state.update_org_truth("office", "Mon-Thu".into());
+        let updates = parse_org_updates(Some(&json!({
+            "parking": { "op": "retire" },
+            "office": { "op": "merge", "target": "site", "content": "One site" },
+            "remote": "Fridays",
+        })));
+        let mut contents = truth_contents(&updates);
+        contents.sort();
+        assert_eq!(
+            contents,
+            vec![("remote".into(), "Fridays".into()), ("site".into(), "One site".into())]
+        );
+        apply_to_state(&mut state, &updates);
+        assert!(state.latest_truth("parking").is_none() && state.latest_truth("office").is_none());
+        assert_eq!(state.latest_truth("site"), Some("One site"));
+    }
+}
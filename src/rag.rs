@@ -1,8 +1,200 @@
 use anyhow::Result;
+use once_cell::sync::Lazy;
+use rrag::prelude::Document;
+use serde_json::Value;
 
-use crate::app_state::APP_STATE;
+use crate::app_state::rag_search;
+use crate::domain::EmployeeRole;
+use crate::roles::{wildcard_match, KNOWN_ROLES};
 
-pub async fn search_brain(query: String, k: usize) -> Result<Vec<String>> {
-    let state = APP_STATE.lock().await;
-    state.rag_search(query, k).await
+/// Document metadata listing the roles that may retrieve it (`ceo|hr`), or `all`.
+pub const VISIBILITY_KEY: &str = "visibility";
+
+pub async fn search_brain(
+    query: String,
+    k: usize,
+    roles: &[EmployeeRole],
+) -> Result<Vec<String>> {
+    let snippets = rag_search(query, k, roles).await?;
+    Ok(snippets.into_iter().map(|s| s.content).collect())
+}
+
+/// One `pattern->roles` entry of `COS_RAG_SOURCE_ROLES`, e.g. `knowledge.csv->ceo|hr` or
+/// `finance*->ceo`. The pattern is matched against a document's `kind`, then its `source`.
+#[derive(Debug, Clone)]
+pub struct SourceRule {
+    pub pattern: String,
+    pub roles: Vec<String>,
+}
+
+static SOURCE_RULES: Lazy<Vec<SourceRule>> =
+    Lazy::new(|| parse_source_rules(&crate::app_state::config().rag.source_roles));
+
+/// Parses `COS_RAG_SOURCE_ROLES` (comma-separated). Entries naming an unknown role are skipped.
+pub fn parse_source_rules(raw: &str) -> Vec<SourceRule> {
+    raw.split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .filter_map(|entry| {
+            let (pattern, roles) = entry.split_once("->")?;
+            let pattern = pattern.trim().to_lowercase();
+            let roles: Vec<String> = roles.split('|').map(|r| r.trim().to_lowercase()).collect();
+            if pattern.is_empty() || roles.iter().any(|r| !KNOWN_ROLES.contains(&r.as_str())) {
+                eprintln!("COS_RAG_SOURCE_ROLES: ignoring entry {:?}", entry.trim());
+                return None;
+            }
+            Some(SourceRule { pattern, roles })
+        })
+        .collect()
+}
+
+/// The `visibility` of a document from `source` with `kind`: the roles of the first rule
+/// matching the kind, else the first matching the source, else `all`.
+pub fn source_visibility(source: &str, kind: Option<&str>, rules: &[SourceRule]) -> String {
+    let matching = |text: &str| {
+        let text = text.trim().to_lowercase();
+        rules.iter().find(|r| wildcard_match(&r.pattern, &text))
+    };
+    kind.and_then(matching)
+        .or_else(|| matching(source))
+        .map(|r| r.roles.join("|"))
+        .unwrap_or_else(|| "all".to_string())
+}
+
+/// Whether a document with `visibility` metadata may be retrieved for every one of `roles`.
+/// Documents without the metadata are visible to all.
+pub fn visible_to(visibility: Option<&Value>, roles: &[EmployeeRole]) -> bool {
+    let Some(allowed) = visibility.and_then(|v| v.as_str()) else {
+        return true;
+    };
+    allowed == "all" || roles.iter().all(|role| allowed.split('|').any(|r| r == role.as_str()))
+}
+
+/// `RAG_CHUNK_CHARS` (default 2000): documents longer than this are split. 0 disables chunking.
+fn rag_chunk_chars() -> usize {
+    crate::app_state::config().rag.chunk_chars
+}
+
+/// `RAG_CHUNK_OVERLAP` (default 200): characters shared by consecutive chunks.
+fn rag_chunk_overlap() -> usize {
+    crate::app_state::config().rag.chunk_overlap
+}
+
+/// Splits `text` into windows of at most `size` chars overlapping by `overlap`, preferring to
+/// break at whitespace in the last fifth of a window so words are not cut in half.
+pub fn chunk_text(text: &str, size: usize, overlap: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if size == 0 || chars.len() <= size {
+        return vec![text.to_string()];
+    }
+    let overlap = overlap.min(size / 2);
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let mut end = (start + size).min(chars.len());
+        if end < chars.len() {
+            let min_end = end - size / 5;
+            if let Some(ws) = (min_end..end).rev().find(|&i| chars[i].is_whitespace()) {
+                end = ws + 1;
+            }
+        }
+        chunks.push(chars[start..end].iter().collect::<String>().trim().to_string());
+        if end == chars.len() {
+            break;
+        }
+        start = end.saturating_sub(overlap).max(start + 1);
+    }
+    chunks.retain(|c| !c.is_empty());
+    chunks
+}
+
+/// RAG documents for `content`: one document when it fits in `RAG_CHUNK_CHARS`, otherwise one
+/// per chunk, all sharing `parent_id` (the content hash) plus `chunk_index` / `chunk_count`.
+/// Each is tagged with its `visibility`, from the `source` and `kind` in `metadata`.
+pub fn documents_for(content: &str, metadata: &[(&str, Value)]) -> Vec<Document> {
+    let chunks = chunk_text(content, rag_chunk_chars(), rag_chunk_overlap());
+    let meta_str = |key: &str| {
+        metadata
+            .iter()
+            .find(|(k, _)| *k == key)
+            .and_then(|(_, v)| v.as_str())
+    };
+    let visibility = source_visibility(
+        meta_str("source").unwrap_or(""),
+        meta_str("kind"),
+        &SOURCE_RULES,
+    );
+    let with_meta = |mut doc: Document| {
+        for (k, v) in metadata {
+            doc = doc.with_metadata(*k, v.clone());
+        }
+        doc.with_metadata(VISIBILITY_KEY, visibility.clone().into())
+    };
+
+    if chunks.len() <= 1 {
+        return vec![with_meta(Document::new(content.to_string())).with_content_hash()];
+    }
+
+    let parent_id = crate::utils::content_hash(content);
+    let count = chunks.len();
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            with_meta(Document::new(chunk))
+                .with_metadata("parent_id", parent_id.clone().into())
+                .with_metadata("chunk_index", (i as u64).into())
+                .with_metadata("chunk_count", (count as u64).into())
+                .with_content_hash()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_one_chunk() {
+        assert_eq!(chunk_text("one two", 20, 5), vec!["one two"]);
+        assert_eq!(chunk_text("one two", 0, 5), vec!["one two"]);
+    }
+
+    #[test]
+    fn chunks_break_at_whitespace_and_overlap() {
+        let text = "alpha bravo charlie delta echo foxtrot golf hotel india juliet";
+        let chunks = chunk_text(text, 20, 6);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 20), "{chunks:?}");
+        // Each window ends after a space; the next one starts `overlap` chars before that.
+        assert_eq!(
+            chunks,
+            vec![
+                "alpha bravo charlie",
+                "arlie delta echo",
+                "echo foxtrot golf",
+                "golf hotel india",
+                "india juliet",
+            ]
+        );
+    }
+
+    #[test]
+    fn chunked_documents_share_a_parent() {
+        let content = "word ".repeat(1_000);
+        let docs = documents_for(&content, &[("source", "handbook.md".into())]);
+        assert!(docs.len() > 1);
+        let parent = crate::utils::content_hash(&content);
+        for (i, doc) in docs.iter().enumerate() {
+            assert_eq!(doc.metadata["parent_id"], parent.as_str());
+            assert_eq!(doc.metadata["chunk_index"], i as u64);
+            assert_eq!(doc.metadata["chunk_count"], docs.len() as u64);
+            assert_eq!(doc.metadata["source"], "handbook.md");
+            assert_eq!(doc.metadata[VISIBILITY_KEY], "all");
+        }
+
+        let single = documents_for("short note", &[]);
+        assert_eq!(single.len(), 1);
+        assert!(!single[0].metadata.contains_key("parent_id"));
+    }
 }
@@ -0,0 +1,195 @@
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+/// `COS_REDACTION`: `off` keeps content verbatim, `standard` (default) redacts SSNs,
+/// card numbers, phone numbers and non-corporate email addresses, `strict` additionally
+/// redacts corporate addresses and street addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactionMode {
+    Off,
+    Standard,
+    Strict,
+}
+
+/// `COS_REDACTION_ACTION`: `mask` (default) replaces spans with typed placeholders such as
+/// `[PHONE]` so retrieval still sees that something was there; `drop` removes them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactionAction {
+    Mask,
+    Drop,
+}
+
+#[derive(Debug, Clone)]
+pub struct RedactionPolicy {
+    pub mode: RedactionMode,
+    pub action: RedactionAction,
+    /// Domains from `COS_CORPORATE_DOMAINS` (comma-separated); subdomains match too.
+    pub corporate_domains: Vec<String>,
+}
+
+impl RedactionPolicy {
+    pub fn from_env() -> Self {
+        let privacy = &crate::app_state::config().privacy;
+        let mode = match privacy.redaction.as_str() {
+            "off" | "0" | "false" => RedactionMode::Off,
+            "strict" => RedactionMode::Strict,
+            _ => RedactionMode::Standard,
+        };
+        let action = match privacy.redaction_action.as_str() {
+            "drop" => RedactionAction::Drop,
+            _ => RedactionAction::Mask,
+        };
+        let corporate_domains = privacy
+            .corporate_domains
+            .iter()
+            .map(|d| d.trim_start_matches('@').to_lowercase())
+            .filter(|d| !d.is_empty())
+            .collect();
+        Self {
+            mode,
+            action,
+            corporate_domains,
+        }
+    }
+
+    fn placeholder(&self, kind: &str) -> String {
+        match self.action {
+            RedactionAction::Mask => format!("[{}]", kind),
+            RedactionAction::Drop => String::new(),
+        }
+    }
+
+    fn is_corporate(&self, domain: &str) -> bool {
+        let domain = domain.to_lowercase();
+        self.corporate_domains
+            .iter()
+            .any(|d| domain == *d || domain.ends_with(&format!(".{}", d)))
+    }
+}
+
+static SSN_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b\d{3}-\d{2}-\d{4}\b").unwrap());
+
+static CARD_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").unwrap());
+
+static PHONE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:\+\d{1,3}[\s.-]?)?(?:\(\d{3}\)|\b\d{3})[\s.-]?\d{3}[\s.-]?\d{4}\b").unwrap()
+});
+
+static EMAIL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b[a-z0-9._%+'-]+@((?:[a-z0-9-]+\.)+[a-z]{2,})\b").unwrap()
+});
+
+static ADDRESS_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"\b\d{1,5}\s+(?:[A-Z][a-z]+\s+){1,3}(?:Street|St|Avenue|Ave|Road|Rd|Boulevard|Blvd|Lane|Ln|Drive|Dr|Court|Ct|Way)\b\.?",
+    )
+    .unwrap()
+});
+
+/// Luhn checksum, so order numbers and timestamps are not mistaken for card numbers.
+fn luhn_valid(digits: &str) -> bool {
+    let digits: Vec<u32> = digits.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() < 13 {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| {
+            if i % 2 == 1 {
+                let dd = d * 2;
+                if dd > 9 {
+                    dd - 9
+                } else {
+                    dd
+                }
+            } else {
+                *d
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+pub fn redact_with(text: &str, policy: &RedactionPolicy) -> String {
+    if policy.mode == RedactionMode::Off {
+        return text.to_string();
+    }
+
+    // Order matters: SSNs and card numbers before the looser phone pattern.
+    let out = SSN_RE.replace_all(text, policy.placeholder("SSN").as_str());
+    let out = CARD_RE.replace_all(&out, |caps: &Captures| {
+        if luhn_valid(&caps[0]) {
+            policy.placeholder("CARD")
+        } else {
+            caps[0].to_string()
+        }
+    });
+    let out = PHONE_RE.replace_all(&out, policy.placeholder("PHONE").as_str());
+    let mut out = EMAIL_RE
+        .replace_all(&out, |caps: &Captures| {
+            if policy.mode != RedactionMode::Strict && policy.is_corporate(&caps[1]) {
+                caps[0].to_string()
+            } else {
+                policy.placeholder("EMAIL")
+            }
+        })
+        .into_owned();
+    if policy.mode == RedactionMode::Strict {
+        out = ADDRESS_RE
+            .replace_all(&out, policy.placeholder("ADDRESS").as_str())
+            .into_owned();
+    }
+    out
+}
+
+/// Redacts `text` with the policy configured in the environment.
+pub fn redact(text: &str) -> String {
+    redact_with(text, &RedactionPolicy::from_env())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(mode: RedactionMode, action: RedactionAction) -> RedactionPolicy {
+        RedactionPolicy { mode, action, corporate_domains: vec!["corp.com".to_string()] }
+    }
+
+    #[test]
+    fn luhn_separates_cards_from_other_long_numbers() {
+        assert!(luhn_valid("4111 1111 1111 1111"));
+        assert!(luhn_valid("5500-0000-0000-0004"));
+        assert!(!luhn_valid("4111 1111 1111 1112"));
+        assert!(!luhn_valid("123456789012"));
+    }
+
+    #[test]
+    fn standard_mode_masks_pii_but_keeps_corporate_addresses() {
+        let text = "SSN 123-45-6789, card 4111 1111 1111 1111, order 1234567890123, \
+                    call (555) 123-4567, mail jane@gmail.com or bob@eng.corp.com";
+        let out = redact_with(text, &policy(RedactionMode::Standard, RedactionAction::Mask));
+        assert_eq!(
+            out,
+            "SSN [SSN], card [CARD], order 1234567890123, \
+             call [PHONE], mail [EMAIL] or bob@eng.corp.com"
+        );
+    }
+
+    #[test]
+    fn strict_mode_also_redacts_corporate_and_street_addresses() {
+        let text = "bob@corp.com lives at 221 Baker Street.";
+        let out = redact_with(text, &policy(RedactionMode::Strict, RedactionAction::Mask));
+        assert_eq!(out, "[EMAIL] lives at [ADDRESS]");
+    }
+
+    #[test]
+    fn drop_removes_spans_and_off_keeps_text() {
+        let text = "ping +1 555-123-4567 now";
+        let dropped = redact_with(text, &policy(RedactionMode::Standard, RedactionAction::Drop));
+        assert_eq!(dropped, "ping  now");
+        let off = redact_with(text, &policy(RedactionMode::Off, RedactionAction::Mask));
+        assert_eq!(off, text);
+    }
+}
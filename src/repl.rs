@@ -0,0 +1,141 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use rustyline::error::ReadlineError;
+use std::sync::Mutex;
+
+/// Agent id used by the terminal flow until `/as <name>` picks someone else.
+pub const DEFAULT_CLI_AGENT_ID: &str = "employee_1";
+
+/// Context key holding the acting employee's agent id.
+pub const CLI_AGENT_ID_KEY: &str = "employee_agent_id";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplCommand {
+    /// Switch the acting employee; holds the resolved agent id.
+    As(String),
+    Traces(usize),
+    Truth,
+    Help,
+    Exit,
+    Stt(String),
+    Text(String),
+    /// A `/...` line that is not a known command, or a command with bad arguments.
+    Invalid(String),
+}
+
+pub const HELP: &str = "Commands:
+  /as <name>    act as another employee (e.g. /as Sarah)
+  /traces [n]   show the n most recent traces you can see (default 5)
+  /truth        show the current organizational truth
+  /help         show this help
+  stt:<path>    transcribe an audio file and send it
+  exit          quit
+Anything else is sent to your EmployeeAgent.";
+
+/// Agent id for an employee name (`Sarah` -> `employee_sarah`); ids already in that form are kept.
+pub fn agent_id_for(name: &str) -> Option<String> {
+    let name = name.trim();
+    if name.starts_with("employee_") {
+        return Some(name.to_string());
+    }
+    let slug = crate::neo4j::writer::slugify_identifier(name);
+    (!slug.is_empty()).then(|| format!("employee_{}", slug))
+}
+
+/// Parses one (already sanitized) input line. Pure, so it can be driven without a TTY.
+pub fn parse_command(line: &str) -> ReplCommand {
+    let line = line.trim();
+    if line == "exit" || line == "/exit" {
+        return ReplCommand::Exit;
+    }
+    if let Some(path) = line.strip_prefix("stt:") {
+        return ReplCommand::Stt(path.trim().to_string());
+    }
+    let Some(cmd) = line.strip_prefix('/') else {
+        return ReplCommand::Text(line.to_string());
+    };
+
+    let (name, arg) = match cmd.split_once(char::is_whitespace) {
+        Some((n, a)) => (n, a.trim()),
+        None => (cmd, ""),
+    };
+    match name {
+        "as" => match agent_id_for(arg) {
+            Some(id) => ReplCommand::As(id),
+            None => ReplCommand::Invalid("usage: /as <name>".to_string()),
+        },
+        "traces" => {
+            if arg.is_empty() {
+                return ReplCommand::Traces(5);
+            }
+            match arg.parse::<usize>() {
+                Ok(n) if n > 0 => ReplCommand::Traces(n),
+                _ => ReplCommand::Invalid("usage: /traces [n]".to_string()),
+            }
+        }
+        "truth" => ReplCommand::Truth,
+        "help" => ReplCommand::Help,
+        other => ReplCommand::Invalid(format!("unknown command /{other}; try /help")),
+    }
+}
+
+/// Lines entered this session, replayed into each new editor for up-arrow history.
+static HISTORY: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Reads one line with line editing and history on a blocking thread.
+/// `None` on Ctrl-C / Ctrl-D.
+pub async fn read_line(prompt: String) -> Result<Option<String>> {
+    tokio::task::spawn_blocking(move || -> Result<Option<String>> {
+        let mut rl = rustyline::DefaultEditor::new()?;
+        let history = HISTORY.lock().map(|h| h.clone()).unwrap_or_default();
+        for entry in &history {
+            let _ = rl.add_history_entry(entry.as_str());
+        }
+        match rl.readline(&prompt) {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    if let Ok(mut h) = HISTORY.lock() {
+                        h.push(line.clone());
+                    }
+                }
+                Ok(Some(line))
+            }
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_parse_with_their_arguments() {
+        let sarah = ReplCommand::As("employee_sarah_connor".into());
+        assert_eq!(parse_command("/as Sarah Connor"), sarah);
+        assert_eq!(parse_command("/as employee_bob"), ReplCommand::As("employee_bob".into()));
+        assert_eq!(parse_command("/traces"), ReplCommand::Traces(5));
+        assert_eq!(parse_command("/traces 12"), ReplCommand::Traces(12));
+        assert_eq!(parse_command(" /truth "), ReplCommand::Truth);
+        assert_eq!(parse_command("/help"), ReplCommand::Help);
+        assert_eq!(parse_command("/exit"), ReplCommand::Exit);
+        assert_eq!(parse_command("exit"), ReplCommand::Exit);
+        assert_eq!(parse_command("stt: memo.wav"), ReplCommand::Stt("memo.wav".into()));
+    }
+
+    #[test]
+    fn anything_else_is_text_or_a_usage_error() {
+        assert_eq!(parse_command("freeze hiring"), ReplCommand::Text("freeze hiring".into()));
+        assert_eq!(parse_command("exit now"), ReplCommand::Text("exit now".into()));
+        assert!(matches!(parse_command("/as"), ReplCommand::Invalid(_)));
+        assert!(matches!(parse_command("/as !!"), ReplCommand::Invalid(_)));
+        assert!(matches!(parse_command("/traces 0"), ReplCommand::Invalid(_)));
+        assert!(matches!(parse_command("/traces many"), ReplCommand::Invalid(_)));
+        assert_eq!(
+            parse_command("/whoami"),
+            ReplCommand::Invalid("unknown command /whoami; try /help".into())
+        );
+    }
+}
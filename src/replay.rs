@@ -0,0 +1,97 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::json;
+use utoipa::ToSchema;
+
+use crate::app_state::handles;
+use crate::domain::ReasoningTrace;
+use crate::error::CosError;
+use crate::service::{ask_and_persist_with, AskOptions};
+use crate::version_diff::{diff_properties, FieldChange, DECISION_DIFF_FIELDS};
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReplayOutcome {
+    pub decision_id: String,
+    /// The version whose trigger events were replayed.
+    pub original_version: i64,
+    /// Whether the replay was written as the decision's next version.
+    pub committed: bool,
+    pub response_text: String,
+    pub trace: ReasoningTrace,
+    /// How the replay differs from the original version (see `GET .../diff`).
+    pub changes: Vec<FieldChange>,
+}
+
+/// Re-runs the OrgBrain on the stored trigger events of the current version of `decision_id`,
+/// with today's prompts, retrieval and truth. Nothing is written unless `commit`, in which case
+/// the result becomes the decision's next version. `None` when the decision doesn't exist.
+pub async fn replay_decision(
+    decision_id: &str,
+    agent_id: String,
+    commit: bool,
+) -> Result<Option<ReplayOutcome>> {
+    let store = handles().graph_store.ok_or(CosError::GraphUnavailable)?;
+    let Some(version) = store.current_decision_version(decision_id).await? else {
+        return Ok(None);
+    };
+    let Some(original) = store.decision_version(decision_id, version).await? else {
+        return Ok(None);
+    };
+    let event_ids: Vec<String> = original
+        .get("trigger_events")
+        .and_then(|v| v.as_array())
+        .map(|ids| ids.iter().filter_map(|id| id.as_str().map(str::to_string)).collect())
+        .unwrap_or_default();
+    let events = store.load_events(&event_ids).await?;
+    if events.is_empty() {
+        return Err(CosError::NotFound(format!(
+            "stored trigger events of decision {decision_id} v{version}"
+        ))
+        .into());
+    }
+
+    let text = format!("Replay of decision {decision_id} v{version}");
+    let (response_text, trace) = ask_and_persist_with(
+        text,
+        Some(agent_id),
+        AskOptions {
+            decision_id: Some(decision_id.to_string()),
+            replay_events: Some(events),
+            dry_run: !commit,
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let replayed = json!({
+        "summary": trace.summary,
+        "confidence": trace.confidence as f64,
+        "routing_agents": trace
+            .routing
+            .iter()
+            .filter(|(_, level)| level.as_str() != "none")
+            .map(|(agent, _)| agent.clone())
+            .collect::<Vec<_>>(),
+        "trigger_events": trace
+            .trigger_events
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>(),
+        "agents_involved": trace
+            .agents_involved
+            .iter()
+            .map(|a| a.0.clone())
+            .collect::<Vec<_>>(),
+        "rationale": (!trace.rationale.is_empty()).then_some(&trace.rationale),
+        "evidence": trace.evidence,
+        "assumptions": trace.assumptions,
+    });
+    Ok(Some(ReplayOutcome {
+        decision_id: decision_id.to_string(),
+        original_version: version,
+        committed: commit,
+        response_text,
+        changes: diff_properties(&original, &replayed, DECISION_DIFF_FIELDS),
+        trace,
+    }))
+}
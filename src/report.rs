@@ -0,0 +1,198 @@
+use crate::domain::ReasoningTrace;
+
+/// A shareable report of one decision: its latest visible trace and the versions the reader may
+/// see, oldest first. Build it from traces already cut to the reader's visibility; sections
+/// whose fields are empty are left out.
+pub struct DecisionReport<'a> {
+    pub trace: &'a ReasoningTrace,
+    pub history: &'a [ReasoningTrace],
+    /// `full` or `summary`: at `summary` the evidence and assumptions sections are omitted.
+    pub level: &'a str,
+}
+
+impl DecisionReport<'_> {
+    fn agents(&self) -> String {
+        let agents: Vec<&str> = self.trace.agents_involved.iter().map(|a| a.0.as_str()).collect();
+        agents.join(", ")
+    }
+
+    fn full(&self) -> bool {
+        self.level == "full"
+    }
+
+    pub fn markdown(&self) -> String {
+        let t = self.trace;
+        let mut out = format!("# Decision {}\n\n", md_inline(&t.decision_id));
+        out.push_str(&format!(
+            "- **Version:** {}\n- **Topic:** {}\n- **Confidence:** {:.2}\n- **Date:** {}\n",
+            t.version,
+            md_inline(&t.topic),
+            t.confidence,
+            t.created_at.to_rfc3339()
+        ));
+        if !t.agents_involved.is_empty() {
+            out.push_str(&format!("- **Agents involved:** {}\n", md_inline(&self.agents())));
+        }
+
+        out.push_str(&format!("\n## Summary\n\n{}\n", t.summary.trim()));
+        if !t.rationale.trim().is_empty() {
+            out.push_str(&format!("\n## Rationale\n\n{}\n", t.rationale.trim()));
+        }
+        if self.full() {
+            md_list(&mut out, "Evidence", &t.evidence);
+            md_list(&mut out, "Assumptions", &t.assumptions);
+        }
+
+        if !self.history.is_empty() {
+            out.push_str("\n## Version history\n\n| Version | Date | Confidence | Summary |\n");
+            out.push_str("|---|---|---|---|\n");
+            for v in self.history {
+                out.push_str(&format!(
+                    "| {} | {} | {:.2} | {} |\n",
+                    v.version,
+                    v.created_at.format("%Y-%m-%d %H:%M UTC"),
+                    v.confidence,
+                    md_inline(v.summary.trim()).replace('|', "\\|")
+                ));
+            }
+        }
+        out
+    }
+
+    pub fn html(&self) -> String {
+        let t = self.trace;
+        let title = format!("Decision {}", esc(&t.decision_id));
+        let mut body = format!("<h1>{title}</h1>\n<ul>\n");
+        body.push_str(&format!(
+            "<li><strong>Version:</strong> {}</li>\n<li><strong>Topic:</strong> {}</li>\n\
+             <li><strong>Confidence:</strong> {:.2}</li>\n<li><strong>Date:</strong> {}</li>\n",
+            t.version,
+            esc(&t.topic),
+            t.confidence,
+            t.created_at.to_rfc3339()
+        ));
+        if !t.agents_involved.is_empty() {
+            body.push_str(&format!(
+                "<li><strong>Agents involved:</strong> {}</li>\n",
+                esc(&self.agents())
+            ));
+        }
+        body.push_str("</ul>\n");
+
+        body.push_str(&format!("<h2>Summary</h2>\n<p>{}</p>\n", esc(t.summary.trim())));
+        if !t.rationale.trim().is_empty() {
+            body.push_str(&format!("<h2>Rationale</h2>\n<p>{}</p>\n", esc(t.rationale.trim())));
+        }
+        if self.full() {
+            html_list(&mut body, "Evidence", &t.evidence);
+            html_list(&mut body, "Assumptions", &t.assumptions);
+        }
+
+        if !self.history.is_empty() {
+            body.push_str(
+                "<h2>Version history</h2>\n<table>\n<tr><th>Version</th><th>Date</th>\
+                 <th>Confidence</th><th>Summary</th></tr>\n",
+            );
+            for v in self.history {
+                body.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td>{:.2}</td><td>{}</td></tr>\n",
+                    v.version,
+                    v.created_at.format("%Y-%m-%d %H:%M UTC"),
+                    v.confidence,
+                    esc(v.summary.trim())
+                ));
+            }
+            body.push_str("</table>\n");
+        }
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+             </head>\n<body>\n{body}</body>\n</html>\n"
+        )
+    }
+}
+
+fn md_list(out: &mut String, heading: &str, items: &[String]) {
+    if items.is_empty() {
+        return;
+    }
+    out.push_str(&format!("\n## {heading}\n\n"));
+    for item in items {
+        out.push_str(&format!("- {}\n", md_inline(item.trim())));
+    }
+}
+
+fn html_list(out: &mut String, heading: &str, items: &[String]) {
+    if items.is_empty() {
+        return;
+    }
+    out.push_str(&format!("<h2>{heading}</h2>\n<ul>\n"));
+    for item in items {
+        out.push_str(&format!("<li>{}</li>\n", esc(item.trim())));
+    }
+    out.push_str("</ul>\n");
+}
+
+/// `text` on one line, so it can't break a list item or table row.
+fn md_inline(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn esc(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn trace(version: i64, summary: &str) -> ReasoningTrace {
+        serde_json::from_value(json!({
+            "decision_id": "offsite",
+            "topic": "team offsite",
+            "summary": summary,
+            "version": version,
+            "confidence": 0.8,
+            "rationale": "Budget allows it.",
+            "evidence": ["Q3 budget <draft>"],
+            "assumptions": ["venue is free"],
+            "trigger_events": [],
+            "agents_involved": ["employee_john"],
+            "graph_updates": { "nodes": [], "edges": [] },
+            "routing": {},
+            "created_at": "2026-05-01T09:30:00Z"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn markdown_reports_keep_rows_on_one_line_and_evidence_at_full() {
+        let history = [trace(1, "Hold it | in Lisbon"), trace(2, "Hold it\nin Porto")];
+        let report = DecisionReport { trace: &history[1], history: &history, level: "full" };
+        let md = report.markdown();
+        assert!(md.starts_with("# Decision offsite\n"));
+        assert!(md.contains("- **Agents involved:** employee_john\n"));
+        assert!(md.contains("## Evidence\n\n- Q3 budget <draft>\n"));
+        assert!(md.contains("| 1 | 2026-05-01 09:30 UTC | 0.80 | Hold it \\| in Lisbon |\n"));
+        assert!(md.contains("| 2 | 2026-05-01 09:30 UTC | 0.80 | Hold it in Porto |\n"));
+
+        let summary = DecisionReport { level: "summary", ..report };
+        let md = summary.markdown();
+        assert!(!md.contains("## Evidence") && !md.contains("## Assumptions"));
+        assert!(md.contains("## Rationale\n\nBudget allows it.\n"));
+    }
+
+    #[test]
+    fn html_reports_escape_their_content() {
+        let history = [trace(1, "Hold it in \"Lisbon\" & Porto")];
+        let report = DecisionReport { trace: &history[0], history: &history, level: "full" };
+        let html = report.html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<li>Q3 budget &lt;draft&gt;</li>"));
+        assert!(html.contains("<p>Hold it in &quot;Lisbon&quot; &amp; Porto</p>"));
+        assert!(html.contains("<td>1</td><td>2026-05-01 09:30 UTC</td>"));
+    }
+}
@@ -0,0 +1,79 @@
+use serde_json::{json, Value};
+
+use crate::domain::Event;
+use crate::graph_store::GraphStore;
+
+/// `COS_RAG_TOP_K` (default 3): vector-RAG snippets sent to the OrgBrain.
+pub fn rag_top_k() -> usize {
+    crate::app_state::config().rag.top_k
+}
+
+/// `COS_GRAPH_CONTEXT_TRUTHS` (default 5): current truth versions related to the events' topics
+/// sent to the OrgBrain as `graph_context`. `0` leaves them out.
+pub fn graph_context_truths() -> usize {
+    crate::app_state::config().orgbrain.graph_context_truths
+}
+
+/// `COS_GRAPH_CONTEXT_DECISIONS` (default 5): current decisions on the events' topics sent to
+/// the OrgBrain as `graph_context`. `0` leaves them out.
+pub fn graph_context_decisions() -> usize {
+    crate::app_state::config().orgbrain.graph_context_decisions
+}
+
+/// The structured half of OrgBrain retrieval: current truth and recent decisions on the topics
+/// of `events`, from the graph. Truth already in the prompt's `org_truth` (`shown`) is skipped.
+/// A failed query is logged and gives empty lists, like a RAG miss.
+pub async fn graph_context(store: &dyn GraphStore, events: &[Event], shown: &[String]) -> Value {
+    let mut topics: Vec<String> = events
+        .iter()
+        .map(|e| e.topic.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+    topics.sort();
+    topics.dedup();
+
+    let truth_limit = graph_context_truths();
+    // Over-fetch by what may be dropped as already shown.
+    let fetch = if truth_limit == 0 { 0 } else { truth_limit + shown.len() };
+    let ctx = store
+        .graph_context(&topics, fetch, graph_context_decisions())
+        .await;
+    let mut ctx = match ctx {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            eprintln!("graph context for {topics:?}: {e:#}");
+            Default::default()
+        }
+    };
+    ctx.truths.retain(|t| !shown.contains(&t.truth_id));
+    ctx.truths.truncate(truth_limit);
+    json!({ "current_truth": ctx.truths, "related_decisions": ctx.decisions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{EmployeeAgentId, EventType};
+    use crate::memory_store::MemoryStore;
+    use tokio::sync::Mutex;
+
+    #[tokio::test]
+    async fn truth_already_shown_is_left_out_of_the_graph_context() {
+        let store = Mutex::new(MemoryStore::new());
+        for (id, summary) in [("hiring_budget", "two hires"), ("hiring_freeze", "no hires")] {
+            let (id, kind) = (id.to_string(), "policy".to_string());
+            store
+                .lock()
+                .await
+                .persist_truth_version(id, kind, summary.into(), 1.0, vec![], vec![], &json!({}))
+                .unwrap();
+        }
+        let agent = EmployeeAgentId("employee_john".to_string());
+        let event = Event::new(agent, EventType::Concern, " Hiring ".to_string(), 0.7, vec![]);
+
+        let ctx = graph_context(&store, &[event], &["hiring_budget".to_string()]).await;
+        assert_eq!(ctx["current_truth"][0]["truth_id"], "hiring_freeze");
+        assert_eq!(ctx["current_truth"].as_array().unwrap().len(), 1);
+        assert_eq!(ctx["related_decisions"], json!([]));
+    }
+}
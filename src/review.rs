@@ -0,0 +1,189 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::domain::Annotation;
+use crate::utils::openai_chat;
+
+/// Author recorded on annotations written by the reviewer.
+pub const REVIEWER_AUTHOR: &str = "reviewer";
+
+/// `COS_REVIEWER=0` skips the review step (one fewer LLM call per decision).
+pub fn reviewer_enabled() -> bool {
+    crate::app_state::config().orgbrain.reviewer
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// The decision stands as is.
+    Approve,
+    /// The decision stands; the notes are attached to the trace.
+    Annotate,
+    /// The notes are attached, confidence is lowered and the trace is marked for approval.
+    Flag,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Review {
+    pub verdict: Verdict,
+    pub notes: Vec<String>,
+    /// Confidence the reviewer would assign, used when flagging.
+    pub confidence: Option<f32>,
+}
+
+impl Review {
+    pub fn approve() -> Self {
+        Self {
+            verdict: Verdict::Approve,
+            notes: Vec::new(),
+            confidence: None,
+        }
+    }
+
+    /// Review notes as trace annotations.
+    pub fn annotations(&self) -> Vec<Annotation> {
+        if self.verdict == Verdict::Approve {
+            return Vec::new();
+        }
+        self.notes
+            .iter()
+            .map(|note| Annotation {
+                annotation_id: Uuid::new_v4().to_string(),
+                author: REVIEWER_AUTHOR.to_string(),
+                text: crate::redaction::redact(note),
+                created_at: Utc::now(),
+            })
+            .collect()
+    }
+
+    /// Confidence after the review: unchanged unless flagged, in which case it never rises.
+    pub fn adjusted_confidence(&self, confidence: f32) -> f32 {
+        match self.verdict {
+            Verdict::Flag => self
+                .confidence
+                .map(|c| c.clamp(0.0, 1.0))
+                .unwrap_or(confidence * 0.5)
+                .min(confidence),
+            _ => confidence,
+        }
+    }
+
+    pub fn needs_approval(&self) -> bool {
+        self.verdict == Verdict::Flag
+    }
+}
+
+fn parse_review(out: &str) -> Review {
+    let start = out.find('{');
+    let end = out.rfind('}');
+    let parsed: Option<Value> = match (start, end) {
+        (Some(s), Some(e)) if e > s => serde_json::from_str(&out[s..=e]).ok(),
+        _ => None,
+    };
+    let Some(parsed) = parsed else {
+        return Review::approve();
+    };
+
+    let notes: Vec<String> = parsed
+        .get("notes")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|x| x.as_str().map(|s| s.trim().to_string()))
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    let verdict = match parsed.get("verdict").and_then(|v| v.as_str()).unwrap_or("approve") {
+        "flag" => Verdict::Flag,
+        "annotate" if !notes.is_empty() => Verdict::Annotate,
+        _ => Verdict::Approve,
+    };
+    Review {
+        verdict,
+        notes,
+        confidence: parsed
+            .get("confidence")
+            .and_then(|v| v.as_f64())
+            .map(|c| c as f32),
+    }
+}
+
+/// Asks for a second opinion on the OrgBrain's parsed output before it is persisted.
+/// A failed or unparseable review approves, so the reviewer never blocks a decision.
+pub async fn review_decision(org_output: &Value, rag_snippets: &[String]) -> Review {
+    if !reviewer_enabled() {
+        return Review::approve();
+    }
+    match request_review(org_output, rag_snippets).await {
+        Ok(review) => review,
+        Err(e) => {
+            eprintln!("reviewer failed, approving unchanged: {e:#}");
+            Review::approve()
+        }
+    }
+}
+
+async fn request_review(org_output: &Value, rag_snippets: &[String]) -> Result<Review> {
+    let system = r#"You are the Reviewer.
+You critique a decision produced by the OrgBrain before it is recorded.
+Check it against the retrieved policy snippets: flag claims the snippets or events do not support, and assumptions it relies on without stating.
+
+Return STRICT JSON with keys:
+- verdict: one of ["approve","annotate","flag"] (annotate: sound but worth a note; flag: unsupported or risky, needs human approval)
+- notes: array of short review notes (empty when approving)
+- confidence: number in [0,1], your confidence in the decision
+"#;
+    let user = json!({
+        "decision": org_output,
+        "rag": rag_snippets,
+    })
+    .to_string();
+
+    let out = openai_chat(system, &user).await?;
+    Ok(parse_review(&out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verdicts_parse_from_json_in_the_answer() {
+        let flag = parse_review(
+            "Here: {\"verdict\": \"flag\", \"notes\": [\" no budget source \", \"\"], \
+             \"confidence\": 0.3}",
+        );
+        assert_eq!(flag.verdict, Verdict::Flag);
+        assert_eq!(flag.notes, vec!["no budget source"]);
+        assert_eq!(flag.confidence, Some(0.3));
+
+        // Annotating without notes has nothing to attach.
+        let bare = parse_review("{\"verdict\": \"annotate\", \"notes\": []}");
+        assert_eq!(bare.verdict, Verdict::Approve);
+        assert_eq!(parse_review("looks fine").verdict, Verdict::Approve);
+    }
+
+    #[test]
+    fn only_a_flag_lowers_confidence_and_needs_approval() {
+        let review = |verdict, confidence| Review {
+            verdict,
+            notes: vec!["check the numbers".to_string()],
+            confidence,
+        };
+        assert_eq!(review(Verdict::Flag, Some(0.2)).adjusted_confidence(0.8), 0.2);
+        assert_eq!(review(Verdict::Flag, Some(0.9)).adjusted_confidence(0.8), 0.8);
+        assert_eq!(review(Verdict::Flag, None).adjusted_confidence(0.8), 0.4);
+        assert_eq!(review(Verdict::Annotate, Some(0.2)).adjusted_confidence(0.8), 0.8);
+        assert!(review(Verdict::Flag, None).needs_approval());
+        assert!(!review(Verdict::Annotate, None).needs_approval());
+
+        let notes = review(Verdict::Annotate, None).annotations();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].author, REVIEWER_AUTHOR);
+        assert!(Review::approve().annotations().is_empty());
+    }
+}
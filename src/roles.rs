@@ -0,0 +1,176 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::domain::EmployeeRole;
+use crate::employees::{EmployeeSeed, SeedConfig};
+
+pub const KNOWN_ROLES: [&str; 3] = ["ceo", "hr", "engineer"];
+
+/// One `pattern->role` entry of `COS_ROLE_RULES`, e.g. `ceo@*->ceo` or `*@hr.example.com->hr`.
+/// `*` matches any run of characters; matching is case-insensitive.
+#[derive(Debug, Clone)]
+pub struct RoleRule {
+    pub pattern: String,
+    pub role: String,
+}
+
+/// Parses `COS_ROLE_RULES` (comma-separated). Entries with an unknown role are skipped.
+pub fn role_rules_from_env() -> Vec<RoleRule> {
+    parse_role_rules(&crate::app_state::config().employees.role_rules)
+}
+
+pub fn parse_role_rules(raw: &str) -> Vec<RoleRule> {
+    raw.split(',')
+        .filter_map(|entry| {
+            let (pattern, role) = entry.split_once("->")?;
+            let pattern = pattern.trim().to_lowercase();
+            let role = role.trim().to_lowercase();
+            if pattern.is_empty() || !KNOWN_ROLES.contains(&role.as_str()) {
+                eprintln!("COS_ROLE_RULES: ignoring entry {:?}", entry.trim());
+                return None;
+            }
+            Some(RoleRule { pattern, role })
+        })
+        .collect()
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of characters.
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` at all: exact match.
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// First rule matching `email`, in configuration order.
+pub fn role_for_email(email: &str, rules: &[RoleRule]) -> Option<String> {
+    let email = email.trim().to_lowercase();
+    rules
+        .iter()
+        .find(|r| wildcard_match(&r.pattern, &email))
+        .map(|r| r.role.clone())
+}
+
+/// Roles stored on unseeded `Employee` nodes (set explicitly or guessed from `COS_ROLE_RULES`)
+/// by employee id. `None` until loaded from Neo4j.
+static STORED_ROLES: Lazy<RwLock<Option<HashMap<String, EmployeeRole>>>> =
+    Lazy::new(|| RwLock::new(None));
+
+/// Replaces the stored roles, as read from the graph at startup.
+pub fn load_stored_roles(roles: HashMap<String, EmployeeRole>) {
+    *STORED_ROLES.write().unwrap_or_else(|e| e.into_inner()) = Some(roles);
+}
+
+/// Records a role just written to an `Employee` node.
+pub fn record_stored_role(employee_id: &str, role: EmployeeRole) {
+    STORED_ROLES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashMap::new)
+        .insert(employee_id.to_string(), role);
+}
+
+/// The role stored for `employee_id`, if the roles were loaded and it has one.
+pub fn stored_role(employee_id: &str) -> Option<EmployeeRole> {
+    let roles = STORED_ROLES.read().unwrap_or_else(|e| e.into_inner());
+    roles.as_ref()?.get(employee_id).cloned()
+}
+
+/// Roles of the seeded employees by employee id: the built-in seeds until `load_seed_roles`
+/// replaces them with the seed file's.
+static SEED_ROLES: Lazy<RwLock<HashMap<String, EmployeeRole>>> =
+    Lazy::new(|| RwLock::new(seed_roles(&SeedConfig::default().employees)));
+
+fn seed_roles(seeds: &[EmployeeSeed]) -> HashMap<String, EmployeeRole> {
+    seeds.iter().map(|e| (e.employee_id.clone(), e.role.clone())).collect()
+}
+
+/// Replaces the seeded roles with `seeds`, as read from `employees.toml` at startup. Employees
+/// left out of the file lose their seeded role.
+pub fn load_seed_roles(seeds: &[EmployeeSeed]) {
+    *SEED_ROLES.write().unwrap_or_else(|e| e.into_inner()) = seed_roles(seeds);
+}
+
+/// `employee_id`'s role: its seed wins over the role stored on its `Employee` node.
+pub fn role_of(employee_id: &str) -> Option<EmployeeRole> {
+    let seeds = SEED_ROLES.read().unwrap_or_else(|e| e.into_inner());
+    resolve_role(&seeds, stored_role, employee_id)
+}
+
+fn resolve_role(
+    seeds: &HashMap<String, EmployeeRole>,
+    stored: impl Fn(&str) -> Option<EmployeeRole>,
+    employee_id: &str,
+) -> Option<EmployeeRole> {
+    seeds.get(employee_id).cloned().or_else(|| stored(employee_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_skip_unknown_roles_and_match_in_order() {
+        let rules = parse_role_rules("ceo@*->ceo, *@hr.example.com->hr, *@x.com->admin, ->hr");
+        assert_eq!(rules.len(), 2);
+        assert_eq!(role_for_email("Mia@HR.example.com", &rules).as_deref(), Some("hr"));
+        assert_eq!(role_for_email("ceo@hr.example.com", &rules).as_deref(), Some("ceo"));
+        assert_eq!(role_for_email("bob@eng.example.com", &rules), None);
+    }
+
+    #[test]
+    fn wildcards_match_any_run_of_characters() {
+        assert!(wildcard_match("*@hr.*.com", "a@hr.example.com"));
+        assert!(wildcard_match("exact@x.io", "exact@x.io"));
+        assert!(!wildcard_match("exact@x.io", "exact@x.io.evil"));
+        assert!(!wildcard_match("*@hr.example.com", "a@hr.example.com.evil"));
+    }
+
+    #[test]
+    fn recorded_roles_are_looked_up_by_employee_id() {
+        record_stored_role("employee_email_mia_hr_example_com", EmployeeRole::Hr);
+        assert_eq!(stored_role("employee_email_mia_hr_example_com"), Some(EmployeeRole::Hr));
+        assert_eq!(stored_role("employee_email_nobody"), None);
+    }
+
+    #[test]
+    fn the_seed_file_decides_seeded_roles() {
+        let seeds = SeedConfig::parse(
+            r#"
+[[employees]]
+employee_id = "employee_ada"
+name = "Ada"
+role = "ceo"
+
+[[employees]]
+employee_id = "employee_sarah"
+name = "Sarah"
+role = "hr"
+"#,
+        )
+        .unwrap();
+        let seeds = seed_roles(&seeds.employees);
+        let stored = |id: &str| (id == "employee_email_mia").then_some(EmployeeRole::Hr);
+        let role = |id| resolve_role(&seeds, stored, id);
+        assert_eq!(role("employee_ada"), Some(EmployeeRole::Ceo));
+        assert_eq!(role("employee_john"), None, "john is no longer seeded");
+        assert_eq!(role("employee_email_mia"), Some(EmployeeRole::Hr));
+
+        let builtin = seed_roles(&SeedConfig::default().employees);
+        assert_eq!(resolve_role(&builtin, stored, "employee_john"), Some(EmployeeRole::Ceo));
+    }
+}
@@ -18,8 +18,21 @@ impl EventBus {
         self.queue.push_back(event);
     }
 
-    pub fn drain(&mut self) -> Vec<Event> {
-        self.queue.drain(..).collect()
+    /// The oldest queued event.
+    pub fn pop(&mut self) -> Option<Event> {
+        self.queue.pop_front()
+    }
+
+    /// Puts `events` back at the front of the queue, in their original order, ahead of
+    /// anything emitted since they were taken.
+    pub fn requeue(&mut self, events: &[Event]) {
+        for event in events.iter().rev() {
+            self.queue.push_front(event.clone());
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
     }
 
     pub fn is_empty(&self) -> bool {
@@ -0,0 +1,291 @@
+use anyhow::{bail, Context as _, Result};
+use pocketflow_rs::Flow;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::nodes::{
+    EmployeeAgentNode, EndNode, FanOutNode, GetInputNode, OrgBrainNode, RecordDecisionNode,
+    RetryNode, ReviewerAgentNode,
+};
+use crate::state::MyState;
+
+/// Declares the registered node kinds once: the list used for validation and the
+/// constructors used to start a flow or add a node to it.
+macro_rules! node_registry {
+    ($($kind:literal => $ctor:expr),* $(,)?) => {
+        pub const NODE_KINDS: &[&str] = &[$($kind),*];
+
+        fn start_flow(name: &str, kind: &str) -> Result<Flow<MyState>> {
+            match kind {
+                $($kind => Ok(Flow::new(name, Arc::new($ctor))),)*
+                other => bail!("unknown node kind `{other}`"),
+            }
+        }
+
+        fn add_node(flow: &mut Flow<MyState>, name: &str, kind: &str) -> Result<()> {
+            match kind {
+                $($kind => {
+                    flow.add_node(name, Arc::new($ctor));
+                })*
+                other => bail!("unknown node kind `{other}`"),
+            }
+            Ok(())
+        }
+    };
+}
+
+node_registry! {
+    "get_input" => GetInputNode,
+    "employee" => EmployeeAgentNode,
+    "org_brain" => OrgBrainNode,
+    "reviewer" => ReviewerAgentNode,
+    "record" => RecordDecisionNode,
+    "fan_out" => FanOutNode,
+    "retry" => RetryNode,
+    "end" => EndNode,
+}
+
+/// A flow as declared in `flows.toml`:
+///
+/// ```toml
+/// start = "get_input"
+///
+/// [[nodes]]
+/// name = "get_input"
+/// kind = "get_input"
+///
+/// [[edges]]
+/// from = "get_input"
+/// to = "employee"
+/// on = "success"
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct FlowConfig {
+    pub start: String,
+    pub nodes: Vec<NodeConfig>,
+    #[serde(default)]
+    pub edges: Vec<EdgeConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NodeConfig {
+    pub name: String,
+    /// One of `NODE_KINDS`.
+    pub kind: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EdgeConfig {
+    pub from: String,
+    pub to: String,
+    /// `success`, `failure`, `retry`, `exit` or `default`.
+    pub on: String,
+}
+
+fn parse_state(s: &str) -> Option<MyState> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "success" => Some(MyState::Success),
+        "failure" => Some(MyState::Failure),
+        "retry" => Some(MyState::Retry),
+        "exit" => Some(MyState::Exit),
+        "default" => Some(MyState::Default),
+        _ => None,
+    }
+}
+
+impl EdgeConfig {
+    fn describe(&self, idx: usize) -> String {
+        format!("edge #{} ({} -> {} on {})", idx + 1, self.from, self.to, self.on)
+    }
+}
+
+impl FlowConfig {
+    pub fn from_toml(raw: &str) -> Result<Self> {
+        toml::from_str(raw).context("invalid flow config")
+    }
+
+    /// Checks that node names are unique and of a registered kind, that the start node and
+    /// every edge endpoint exist, that each edge names a known state, that no node has two
+    /// edges for the same state, that every node is reachable from `start`, and that a flow
+    /// with an `org_brain` also has a `record` node to write its decisions.
+    pub fn validate(&self) -> Result<()> {
+        let has_kind = |kind: &str| self.nodes.iter().any(|n| n.kind == kind);
+        if has_kind("org_brain") && !has_kind("record") {
+            bail!("an org_brain node needs a record node, or its decisions are never saved");
+        }
+        let mut nodes = HashSet::new();
+        for node in &self.nodes {
+            if !NODE_KINDS.contains(&node.kind.as_str()) {
+                bail!(
+                    "node `{}` has unknown kind `{}` (known kinds: {})",
+                    node.name,
+                    node.kind,
+                    NODE_KINDS.join(", ")
+                );
+            }
+            if !nodes.insert(node.name.as_str()) {
+                bail!("node `{}` is declared twice", node.name);
+            }
+        }
+        if !nodes.contains(self.start.as_str()) {
+            bail!("start node `{}` is not declared in [[nodes]]", self.start);
+        }
+
+        let mut adjacency: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut transitions = HashSet::new();
+        for (idx, edge) in self.edges.iter().enumerate() {
+            for endpoint in [&edge.from, &edge.to] {
+                if !nodes.contains(endpoint.as_str()) {
+                    bail!("{}: unknown node `{}`", edge.describe(idx), endpoint);
+                }
+            }
+            if parse_state(&edge.on).is_none() {
+                bail!(
+                    "{}: unknown state `{}` (expected success, failure, retry, exit or default)",
+                    edge.describe(idx),
+                    edge.on
+                );
+            }
+            if !transitions.insert((edge.from.as_str(), edge.on.trim().to_ascii_lowercase())) {
+                bail!(
+                    "{}: `{}` already has an edge for `{}`",
+                    edge.describe(idx),
+                    edge.from,
+                    edge.on
+                );
+            }
+            adjacency.entry(edge.from.as_str()).or_default().push(edge.to.as_str());
+        }
+
+        let mut reached = HashSet::from([self.start.as_str()]);
+        let mut queue = VecDeque::from([self.start.as_str()]);
+        while let Some(name) = queue.pop_front() {
+            for next in adjacency.get(name).into_iter().flatten() {
+                if reached.insert(*next) {
+                    queue.push_back(*next);
+                }
+            }
+        }
+        let mut unreachable: Vec<&str> = self
+            .nodes
+            .iter()
+            .map(|n| n.name.as_str())
+            .filter(|n| !reached.contains(n))
+            .collect();
+        if !unreachable.is_empty() {
+            unreachable.sort_unstable();
+            bail!(
+                "node(s) not reachable from `{}`: {}",
+                self.start,
+                unreachable.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    pub fn build(&self) -> Result<Flow<MyState>> {
+        self.validate()?;
+        let start_kind = self
+            .nodes
+            .iter()
+            .find(|n| n.name == self.start)
+            .map(|n| n.kind.as_str())
+            .unwrap_or_default();
+        let mut flow = start_flow(&self.start, start_kind)?;
+        for node in self.nodes.iter().filter(|n| n.name != self.start) {
+            add_node(&mut flow, &node.name, &node.kind)?;
+        }
+        for edge in &self.edges {
+            if let Some(state) = parse_state(&edge.on) {
+                flow.add_edge(&edge.from, &edge.to, state);
+            }
+        }
+        Ok(flow)
+    }
+}
+
+/// Builds the flow declared in `path`, or `Ok(None)` when the file does not exist.
+pub fn load_flow(path: &Path) -> Result<Option<Flow<MyState>>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let raw = std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    let config = FlowConfig::from_toml(&raw).with_context(|| path.display().to_string())?;
+    let flow = config
+        .build()
+        .with_context(|| format!("invalid flow in {}", path.display()))?;
+    Ok(Some(flow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = include_str!("../../flows.example.toml");
+
+    fn error_of(raw: &str) -> String {
+        FlowConfig::from_toml(raw).unwrap().validate().unwrap_err().to_string()
+    }
+
+    fn two_nodes(edges: &str) -> String {
+        format!(
+            "start = \"a\"\n[[nodes]]\nname = \"a\"\nkind = \"get_input\"\n\
+             [[nodes]]\nname = \"b\"\nkind = \"end\"\n{edges}"
+        )
+    }
+
+    #[test]
+    fn the_example_flow_is_valid() {
+        let config = FlowConfig::from_toml(EXAMPLE).unwrap();
+        assert_eq!(config.start, "get_input");
+        config.build().unwrap();
+    }
+
+    #[test]
+    fn an_org_brain_needs_a_record_node() {
+        let raw = "start = \"brain\"\n[[nodes]]\nname = \"brain\"\nkind = \"org_brain\"";
+        assert_eq!(
+            error_of(raw),
+            "an org_brain node needs a record node, or its decisions are never saved"
+        );
+    }
+
+    #[test]
+    fn nodes_must_be_known_unique_and_include_the_start() {
+        let unknown = "start = \"a\"\n[[nodes]]\nname = \"a\"\nkind = \"oracle\"";
+        assert!(error_of(unknown).starts_with("node `a` has unknown kind `oracle`"));
+        let twice = "start = \"a\"\n[[nodes]]\nname = \"a\"\nkind = \"end\"\n\
+                     [[nodes]]\nname = \"a\"\nkind = \"end\"";
+        assert_eq!(error_of(twice), "node `a` is declared twice");
+        let no_start = "start = \"x\"\n[[nodes]]\nname = \"a\"\nkind = \"end\"";
+        assert_eq!(error_of(no_start), "start node `x` is not declared in [[nodes]]");
+    }
+
+    #[test]
+    fn edges_must_connect_declared_nodes_once_per_state() {
+        let edge = |to: &str, on: &str| {
+            format!("[[edges]]\nfrom = \"a\"\nto = \"{to}\"\non = \"{on}\"\n")
+        };
+
+        assert_eq!(
+            error_of(&two_nodes(&edge("c", "success"))),
+            "edge #1 (a -> c on success): unknown node `c`"
+        );
+        assert!(error_of(&two_nodes(&edge("b", "maybe"))).contains("unknown state `maybe`"));
+        let both = format!("{}{}", edge("b", "success"), edge("a", "Success"));
+        assert_eq!(
+            error_of(&two_nodes(&both)),
+            "edge #2 (a -> a on Success): `a` already has an edge for `Success`"
+        );
+        assert_eq!(error_of(&two_nodes("")), "node(s) not reachable from `a`: b");
+        FlowConfig::from_toml(&two_nodes(&edge("b", "exit"))).unwrap().validate().unwrap();
+    }
+
+    #[test]
+    fn a_missing_flow_file_means_the_built_in_flow() {
+        let path = std::env::temp_dir().join(format!("cos-no-flow-{}.toml", uuid::Uuid::new_v4()));
+        assert!(load_flow(&path).unwrap().is_none());
+    }
+}
@@ -1,2 +1,3 @@
 pub mod event_bus;
+pub mod flow_config;
 
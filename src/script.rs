@@ -0,0 +1,96 @@
+use anyhow::{bail, Context as _, Result};
+use serde::Deserialize;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+use crate::service::{ask_and_persist, ask_max_chars, sanitize_input_text};
+
+/// One line of a scenario file.
+#[derive(Debug, Deserialize)]
+struct ScriptLine {
+    employee: String,
+    text: String,
+}
+
+/// `COS_SCRIPT_OUT`, else `<script>.out.jsonl` next to the script.
+pub fn default_output_path(script: &Path) -> PathBuf {
+    crate::app_state::config()
+        .script
+        .out
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| script.with_extension("out.jsonl"))
+}
+
+/// `COS_SCRIPT_DELAY_MS` (default 0).
+pub fn default_delay_ms() -> u64 {
+    crate::app_state::config().script.delay_ms
+}
+
+/// Runs every `{"employee", "text"}` line of `script` through `ask_and_persist`, in order,
+/// writing one result object per line to `out`. Blank lines and `#` comments are skipped.
+/// A failing line is recorded with its error and the run continues; the run as a whole fails
+/// if any line did.
+pub async fn run(script: &Path, out: &Path, delay: Duration) -> Result<()> {
+    let raw = tokio::fs::read_to_string(script)
+        .await
+        .with_context(|| format!("read {}", script.display()))?;
+    let mut writer = tokio::fs::File::create(out)
+        .await
+        .with_context(|| format!("create {}", out.display()))?;
+
+    let mut asked = 0usize;
+    let mut failed = 0usize;
+    for (idx, line) in raw.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if asked > 0 && !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        asked += 1;
+
+        let record = match ask_line(line).await {
+            Ok((agent_id, response, trace)) => json!({
+                "line": line_no,
+                "agent_id": agent_id,
+                "response": response,
+                "trace": trace,
+            }),
+            Err(e) => {
+                failed += 1;
+                eprintln!("script line {line_no}: {e:#}");
+                json!({ "line": line_no, "error": format!("{e:#}") })
+            }
+        };
+        let mut bytes = serde_json::to_vec(&record)?;
+        bytes.push(b'\n');
+        writer.write_all(&bytes).await?;
+    }
+    writer.flush().await?;
+
+    println!("script: {asked} asks, {failed} failed, results in {}", out.display());
+    if failed > 0 {
+        bail!("{failed} of {asked} script lines failed");
+    }
+    Ok(())
+}
+
+async fn ask_line(line: &str) -> Result<(String, String, crate::domain::ReasoningTrace)> {
+    let parsed: ScriptLine = serde_json::from_str(line).context("invalid script line")?;
+    let agent_id = crate::repl::agent_id_for(&parsed.employee)
+        .with_context(|| format!("invalid employee {:?}", parsed.employee))?;
+    let text = sanitize_input_text(&parsed.text);
+    if text.is_empty() {
+        bail!("empty text");
+    }
+    if text.chars().count() > ask_max_chars() {
+        bail!("text exceeds {} characters", ask_max_chars());
+    }
+    let (response, trace) = ask_and_persist(text, Some(agent_id.clone())).await?;
+    Ok((agent_id, response, trace))
+}
@@ -1,14 +1,17 @@
 use anyhow::Result;
 use serde_json::json;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio_util::sync::CancellationToken;
 
-use crate::app_state::APP_STATE;
+use crate::app_state::{handles, rag_search, sessions, traces, APP_STATE};
+use crate::api::ServerEvent;
 use crate::domain::{EmployeeAgentId, Event, EventType, GraphUpdates, ReasoningTrace};
-use crate::neo4j::writer::{
-    next_decision_version, next_truth_version, persist_decision_version, persist_truth_version,
-    load_recent_conversation_turns, persist_conversation_turn,
-};
+use crate::error::CosError;
+use crate::graph_store::GraphStore;
+use crate::llm_schema::{chat_checked, EMPLOYEE_EVENT_SCHEMA, ORG_DECISION_SCHEMA};
+use crate::neo4j::writer::{load_recent_conversation_turns, AskOutcome, DecisionContent, TruthWrite};
 use crate::utils::openai_chat;
-use rrag::prelude::Document;
 use uuid::Uuid;
 
 fn extract_first_json_object(s: &str) -> Option<String> {
@@ -20,6 +23,199 @@ fn extract_first_json_object(s: &str) -> Option<String> {
     Some(s[start..=end].to_string())
 }
 
+pub fn ask_max_chars() -> usize {
+    crate::app_state::config().limits.ask_max_chars
+}
+
+/// `COS_ASK_CONTEXT_MAX_DOCS` (default 5): context documents plus context truth ids per ask.
+pub fn ask_context_max_docs() -> usize {
+    crate::app_state::config().limits.ask_context_max_docs
+}
+
+/// `COS_ASK_CONTEXT_MAX_CHARS` (default 8000): characters per context document. Longer truth
+/// content is cut to this length.
+pub fn ask_context_max_chars() -> usize {
+    crate::app_state::config().limits.ask_context_max_chars
+}
+
+/// Context the asker chose for an ask, shown to the OrgBrain as evidence next to the RAG
+/// snippets.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ContextDocument {
+    /// `document N` for inline text, `truth:{truth_id} vN` for truth.
+    pub source: String,
+    pub content: String,
+}
+
+/// Inline `documents` (already validated) followed by the current content of each of
+/// `truth_ids`. An unknown or retired truth id is `NotFound`.
+pub async fn resolve_context(
+    documents: &[String],
+    truth_ids: &[String],
+) -> Result<Vec<ContextDocument>> {
+    let mut out: Vec<ContextDocument> = documents
+        .iter()
+        .enumerate()
+        .map(|(i, content)| ContextDocument {
+            source: format!("document {}", i + 1),
+            content: content.clone(),
+        })
+        .collect();
+    if truth_ids.is_empty() {
+        return Ok(out);
+    }
+
+    let store = handles().graph_store.ok_or(CosError::GraphUnavailable)?;
+    let contents = store.current_truth_contents(truth_ids).await?;
+    let max_chars = ask_context_max_chars();
+    for truth_id in truth_ids {
+        let (version, content) = contents
+            .get(truth_id)
+            .ok_or_else(|| CosError::NotFound(format!("truth {truth_id}")))?;
+        out.push(ContextDocument {
+            source: format!("truth:{truth_id} v{version}"),
+            content: content.chars().take(max_chars).collect(),
+        });
+    }
+    Ok(out)
+}
+
+static PERSISTENCE_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+/// Graph writes behind a trace that failed since startup.
+pub fn persistence_failures() -> u64 {
+    PERSISTENCE_FAILURES.load(Ordering::Relaxed)
+}
+
+/// `COS_STRICT_PERSISTENCE=1` fails the ask when its decision version can't be written, for
+/// deployments where the graph is the source of truth. Off by default: the answer is returned
+/// with `persistence_warnings`.
+pub fn strict_persistence() -> bool {
+    crate::app_state::config().storage.strict_persistence
+}
+
+/// Logs and counts a failed write of `what` for `decision_id`, and returns the warning recorded
+/// on the trace. The warning names the failure kind only; the cause goes to the log.
+pub fn persistence_warning(decision_id: &str, what: &str, e: &anyhow::Error) -> String {
+    PERSISTENCE_FAILURES.fetch_add(1, Ordering::Relaxed);
+    eprintln!("error: decision {decision_id}: persist {what}: {e:#}");
+    format!("{what} was not persisted: {e}")
+}
+
+/// `routing` with each agent id replaced by its canonical employee. When an alias and its
+/// canonical employee are both routed, the wider level wins.
+pub async fn canonical_routing(routing: serde_json::Value) -> serde_json::Value {
+    let Some(obj) = routing.as_object() else {
+        return routing;
+    };
+    let rank = |level: &str| {
+        crate::visibility::VISIBILITY_LEVELS
+            .iter()
+            .position(|l| *l == level)
+            .unwrap_or(crate::visibility::VISIBILITY_LEVELS.len())
+    };
+    let mut out = serde_json::Map::new();
+    for (agent_id, level) in obj {
+        let canonical = crate::api::canonical_agent_id(agent_id.clone()).await;
+        let wider = match (out.get(&canonical).and_then(|v| v.as_str()), level.as_str()) {
+            (Some(existing), Some(new)) => rank(new) < rank(existing),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        if wider {
+            out.insert(canonical, level.clone());
+        }
+    }
+    serde_json::Value::Object(out)
+}
+
+/// The truth versions to write for `contents`, the (truth id, content) pairs an OrgBrain answer
+/// records (see `org_updates::truth_contents`). Truth whose content hash equals its current
+/// stored version is left out and reported as a `truth unchanged: <truth_id>` assumption instead.
+pub async fn truth_writes(
+    store: &dyn GraphStore,
+    contents: &[(String, String)],
+) -> (Vec<TruthWrite>, Vec<String>) {
+    let mut writes = Vec::new();
+    let mut unchanged = Vec::new();
+    for (truth_id, content) in contents {
+        // A failed lookup writes the version anyway.
+        let current_hash = store
+            .current_truth_version(truth_id)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|(_, h)| h);
+        if current_hash.as_deref() == Some(crate::utils::content_hash(content).as_str()) {
+            unchanged.push(format!("truth unchanged: {truth_id}"));
+            continue;
+        }
+        writes.push(TruthWrite {
+            truth_id: truth_id.clone(),
+            kind: "org_truth".to_string(),
+            summary: content.clone(),
+        });
+    }
+    (writes, unchanged)
+}
+
+/// Puts back the events drained for a failed ask, like `nodes::requeue_on_transient`, so the
+/// next decision still sees them. The asker's own event (`own`) fails with the ask, and replayed
+/// events never came from the queue.
+async fn requeue_drained(
+    options: &AskOptions,
+    events: &[Event],
+    own: Uuid,
+    e: anyhow::Error,
+) -> anyhow::Error {
+    if options.replay_events.is_none() {
+        requeue_others(events, own).await;
+    }
+    e
+}
+
+/// Requeues the events in `events` other than `own`.
+async fn requeue_others(events: &[Event], own: Uuid) {
+    let others: Vec<Event> = events.iter().filter(|e| e.event_id != own).cloned().collect();
+    if !others.is_empty() {
+        APP_STATE.lock().await.requeue_events(&others);
+    }
+}
+
+fn ask_soft_chars() -> usize {
+    crate::app_state::config().limits.ask_soft_chars
+}
+
+/// Drops control characters (keeping newlines and tabs) and trims the result.
+pub fn sanitize_input_text(s: &str) -> String {
+    s.chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn split_by_chars(s: &str, max: usize) -> Vec<String> {
+    let chars: Vec<char> = s.chars().collect();
+    chars
+        .chunks(max.max(1))
+        .map(|c| c.iter().collect::<String>())
+        .collect()
+}
+
+/// Map-reduce summary for inputs too long to go straight into the employee prompt.
+async fn summarize_long_input(text: &str) -> Result<String> {
+    let system = r#"You condense long documents pasted by an employee.
+Summarize the text in at most 10 short bullet points, keeping decisions, owners, dates and open questions.
+Return plain text only."#;
+
+    let mut parts = Vec::new();
+    for chunk in split_by_chars(text, ask_soft_chars()) {
+        parts.push(openai_chat(system, &chunk).await?);
+    }
+    Ok(parts.join("\n"))
+}
+
 pub async fn ingest_knowledge(
     truth_id: String,
     kind: String,
@@ -27,62 +223,235 @@ pub async fn ingest_knowledge(
     agent_id: Option<String>,
     routing: serde_json::Value,
     add_to_rag: bool,
+    force: bool,
 ) -> Result<ReasoningTrace> {
     let agent_id = EmployeeAgentId(agent_id.unwrap_or_else(|| "employee_1".to_string()));
     let trigger_event = Uuid::new_v4();
+    let content = crate::redaction::redact(&content);
 
     let mut graph_updates = GraphUpdates {
         nodes: Vec::new(),
         edges: Vec::new(),
     };
 
-    let (rag, neo4j) = {
-        let mut state = APP_STATE.lock().await;
-        state.update_org_truth(&truth_id, content.clone());
-        (state.rag.clone(), state.neo4j.clone())
+    let state = handles();
+    let (rag, store) = (state.rag, state.graph_store);
+
+    // Byte-identical to the current version: return it instead of adding version churn, unless
+    // the caller forces a new version.
+    let current = match &store {
+        Some(store) if !force => store
+            .current_truth_version(&truth_id)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|(v, h)| h.map(|h| (v, h))),
+        _ => None,
     };
+    let hash = crate::utils::content_hash(&content);
+    if let Some((version, _)) = current.filter(|(_, h)| *h == hash) {
+        return Ok(knowledge_trace(
+            truth_id,
+            content,
+            version,
+            trigger_event,
+            agent_id,
+            graph_updates,
+            &routing,
+            true,
+        ));
+    }
 
     if add_to_rag {
         if let Some(rag) = rag {
-            let rag = rag.lock().await;
-            let doc = Document::new(content.clone())
-                .with_metadata("source", "frontend".into())
-                .with_metadata("truth_id", truth_id.clone().into())
-                .with_metadata("kind", kind.clone().into())
-                .with_content_hash();
-            let _ = rag.process_document(doc).await;
+            let metadata = [
+                ("source", "frontend".into()),
+                ("truth_id", truth_id.clone().into()),
+                ("kind", kind.clone().into()),
+            ];
+            for doc in crate::rag::documents_for(&content, &metadata) {
+                let _ = rag.process_document(doc).await;
+            }
         }
     }
 
-    let version = if let Some(client) = neo4j {
-        let graph = client.graph();
-        let version = next_truth_version(graph, &truth_id).await.unwrap_or(1);
-        if let Ok(upd) = persist_truth_version(
-            graph,
-            truth_id.clone(),
-            kind,
-            version,
-            content.clone(),
-            1.0,
-            vec![trigger_event],
-            vec![agent_id.0.clone()],
-            routing.clone(),
-        )
-        .await
+    let mut version = 1i64;
+    let mut warnings = Vec::new();
+    if let Some(store) = store {
+        match store
+            .persist_truth_version(
+                truth_id.clone(),
+                kind,
+                content.clone(),
+                1.0,
+                vec![trigger_event],
+                vec![agent_id.0.clone()],
+                &routing,
+            )
+            .await
         {
-            graph_updates.nodes.extend(upd.nodes);
-            graph_updates.edges.extend(upd.edges);
+            Ok((v, upd)) => {
+                version = v;
+                graph_updates.nodes.extend(upd.nodes);
+                graph_updates.edges.extend(upd.edges);
+            }
+            Err(e) => warnings.push(persistence_warning(&truth_id, "truth version", &e)),
         }
-        version
-    } else {
-        1
+    }
+    // Only what was persisted reaches the OrgBrain's in-memory truth.
+    if warnings.is_empty() {
+        APP_STATE.lock().await.update_org_truth(&truth_id, content.clone());
+    }
+
+    let mut trace = knowledge_trace(
+        truth_id,
+        content,
+        version,
+        trigger_event,
+        agent_id,
+        graph_updates,
+        &routing,
+        false,
+    );
+    trace.persistence_warnings = warnings;
+    Ok(trace)
+}
+
+/// Lets the OrgBrain react to knowledge that was just ingested (`trace` is the ingest trace):
+/// an `update` event from the ingesting agent on `kind`, carrying the ingest's trigger event
+/// id so both traces share it, goes through the same pipeline as an ask.
+pub async fn analyze_knowledge(trace: &ReasoningTrace, kind: &str) -> Result<ReasoningTrace> {
+    let event_id = trace.trigger_events.first().copied().unwrap_or_else(Uuid::new_v4);
+    let agent_id = trace.agents_involved.first().map(|a| a.0.clone());
+    let text = format!(
+        "New knowledge was added to {} ({kind}): {}\nCheck whether it changes or contradicts \
+         existing decisions, and say which.",
+        trace.decision_id, trace.summary
+    );
+    let (_, analysis) = ask_and_persist_with(
+        text,
+        agent_id,
+        AskOptions {
+            event: Some(PresetEvent {
+                event_id,
+                event_type: EventType::Update,
+                topic: kind.to_string(),
+                confidence: 1.0,
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+    Ok(analysis)
+}
+
+/// A CEO announcement: the next version of a new decision of kind `announcement`, written
+/// without the EmployeeAgent or OrgBrain. Every seeded employee gets `summary` and the author
+/// `full`; entries in `routing` override both.
+pub async fn announce(
+    title: String,
+    body: String,
+    agent_id: String,
+    routing: std::collections::HashMap<String, String>,
+) -> Result<ReasoningTrace> {
+    let agent_id = EmployeeAgentId(agent_id);
+    let decision_id = format!("announcement_{}", Uuid::new_v4());
+    let trigger_event = Uuid::new_v4();
+    let summary = crate::redaction::redact(&format!("{}\n\n{}", title.trim(), body.trim()));
+
+    let mut routing_map: std::collections::HashMap<String, String> =
+        crate::employees::SeedConfig::from_env()?
+            .employees
+            .into_iter()
+            .map(|e| (e.employee_id, "summary".to_string()))
+            .collect();
+    routing_map.insert(agent_id.0.clone(), "full".to_string());
+    routing_map.extend(routing);
+    let routing_val = canonical_routing(json!(routing_map)).await;
+
+    let mut graph_updates = GraphUpdates {
+        nodes: Vec::new(),
+        edges: Vec::new(),
     };
+    let mut version = 1i64;
+    let mut warnings = Vec::new();
+    if let Some(store) = handles().graph_store {
+        let persisted = store
+            .persist_decision_version(
+                decision_id.clone(),
+                summary.clone(),
+                1.0,
+                vec![trigger_event],
+                vec![agent_id.0.clone()],
+                &routing_val,
+                "announcement",
+                &[],
+                &DecisionContent {
+                    kind: Some("announcement".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await;
+        match persisted {
+            Ok((v, upd)) => {
+                version = v;
+                graph_updates.nodes.extend(upd.nodes);
+                graph_updates.edges.extend(upd.edges);
+            }
+            Err(e) if strict_persistence() => return Err(e),
+            Err(e) => warnings.push(persistence_warning(&decision_id, "decision version", &e)),
+        }
+    }
 
-    Ok(ReasoningTrace {
+    let trace = ReasoningTrace {
+        decision_id,
+        topic: "announcement".to_string(),
+        summary,
+        version,
+        confidence: 1.0,
+        rationale: "announcement".to_string(),
+        evidence: Vec::new(),
+        assumptions: Vec::new(),
+        trigger_events: vec![trigger_event],
+        agents_involved: vec![agent_id],
+        graph_updates,
+        routing: routing_val
+            .as_object()
+            .map(|o| {
+                o.iter()
+                    .map(|(k, v)| (k.clone(), v.as_str().unwrap_or("none").to_string()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        created_at: chrono::Utc::now(),
+        language: None,
+        annotations: Vec::new(),
+        deduplicated: false,
+        needs_approval: false,
+        persistence_warnings: warnings,
+        kind: Some("announcement".to_string()),
+    };
+    traces().await.push(trace.clone());
+    Ok(trace)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn knowledge_trace(
+    truth_id: String,
+    content: String,
+    version: i64,
+    trigger_event: Uuid,
+    agent_id: EmployeeAgentId,
+    graph_updates: GraphUpdates,
+    routing: &serde_json::Value,
+    deduplicated: bool,
+) -> ReasoningTrace {
+    ReasoningTrace {
         decision_id: truth_id,
         topic: "knowledge".to_string(),
         summary: content,
         version,
+        confidence: 1.0,
         rationale: "knowledge_ingest".to_string(),
         evidence: Vec::new(),
         assumptions: Vec::new(),
@@ -97,38 +466,96 @@ pub async fn ingest_knowledge(
                     .collect()
             })
             .unwrap_or_default(),
-    })
+        created_at: chrono::Utc::now(),
+        language: None,
+        annotations: Vec::new(),
+        deduplicated,
+        needs_approval: false,
+        persistence_warnings: Vec::new(),
+        kind: None,
+    }
 }
 
-pub async fn ask_and_persist(text: String, agent_id: Option<String>) -> Result<(String, ReasoningTrace)> {
-    let agent_id = EmployeeAgentId(agent_id.unwrap_or_else(|| "employee_1".to_string()));
-
-    // Load recent per-employee conversation context (Neo4j-backed, cached in memory).
-    let (neo4j, cached) = {
-        let state = APP_STATE.lock().await;
-        (state.neo4j.clone(), state.conversation_cache.get(&agent_id).cloned())
-    };
-    let mut memory_turns = cached.unwrap_or_default();
-    if memory_turns.is_empty() {
-        if let Some(client) = neo4j.clone() {
-            let graph = client.graph();
-            if let Ok(turns) = load_recent_conversation_turns(graph, &agent_id.0, 20).await {
-                // stored DESC; reverse for chronological.
-                memory_turns = turns.into_iter().rev().collect();
-            }
-        }
+/// Trace for an ask whose event was dropped by `COS_MIN_EVENT_CONFIDENCE`: version 0, nothing
+/// persisted, routed in full to the asker only.
+fn noop_trace(
+    event_id: Uuid,
+    topic: String,
+    confidence: f32,
+    agent_id: EmployeeAgentId,
+    language: Option<String>,
+) -> ReasoningTrace {
+    ReasoningTrace {
+        decision_id: format!("noop:{}", event_id),
+        topic,
+        summary: "noop".to_string(),
+        version: 0,
+        confidence,
+        rationale: format!(
+            "event confidence {} is below COS_MIN_EVENT_CONFIDENCE ({})",
+            confidence,
+            crate::app_state::min_event_confidence()
+        ),
+        evidence: Vec::new(),
+        assumptions: Vec::new(),
+        trigger_events: vec![event_id],
+        agents_involved: vec![agent_id.clone()],
+        graph_updates: GraphUpdates {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        },
+        routing: std::collections::HashMap::from([(agent_id.0.clone(), "full".to_string())]),
+        created_at: chrono::Utc::now(),
+        language,
+        annotations: Vec::new(),
+        deduplicated: false,
+        needs_approval: false,
+        persistence_warnings: Vec::new(),
+        kind: None,
     }
+}
 
-    let memory_context = if memory_turns.is_empty() {
-        String::new()
-    } else {
-        let mut s = String::from("Prior conversation (most recent last):\n");
-        for (role, content) in memory_turns.iter() {
-            s.push_str(&format!("- {}: {}\n", role, content));
-        }
-        s
-    };
+/// Trace for an ask the OrgBrain answered with a clarifying question: version 0, no decision
+/// written, routed in full to the asker only.
+fn clarification_trace(
+    event_id: Uuid,
+    topic: String,
+    confidence: f32,
+    agent_id: EmployeeAgentId,
+    language: Option<String>,
+) -> ReasoningTrace {
+    ReasoningTrace {
+        decision_id: format!("clarify:{}", event_id),
+        topic,
+        summary: "clarification".to_string(),
+        version: 0,
+        confidence,
+        rationale: "the OrgBrain needs more information before deciding".to_string(),
+        evidence: Vec::new(),
+        assumptions: Vec::new(),
+        trigger_events: vec![event_id],
+        agents_involved: vec![agent_id.clone()],
+        graph_updates: GraphUpdates {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        },
+        routing: std::collections::HashMap::from([(agent_id.0.clone(), "full".to_string())]),
+        created_at: chrono::Utc::now(),
+        language,
+        annotations: Vec::new(),
+        deduplicated: false,
+        needs_approval: false,
+        persistence_warnings: Vec::new(),
+        kind: Some(CLARIFICATION_KIND.to_string()),
+    }
+}
 
+/// The EmployeeAgent step: the event type, topic, confidence and private note for `text`.
+async fn employee_event(
+    text: &str,
+    memory_context: &str,
+    language_instruction: &str,
+) -> Result<(EventType, String, f32, String)> {
     let employee_system = r#"You are an EmployeeAgent.
 Given the user's input, emit a single event for the OrgBrain to process.
 
@@ -140,11 +567,19 @@ Return STRICT JSON with keys:
 "#;
 
     let employee_user = if memory_context.is_empty() {
-        text.clone()
+        text.to_string()
     } else {
         format!("{}\n\nUser: {}", memory_context, text)
     };
-    let employee_out = openai_chat(employee_system, &employee_user).await?;
+    let employee_system = format!("{}{}", employee_system, language_instruction);
+    let employee_user = &employee_user;
+    let employee_out = chat_checked(
+        "EmployeeAgent",
+        &EMPLOYEE_EVENT_SCHEMA,
+        &employee_system,
+        move |system| async move { openai_chat(&system, employee_user).await },
+    )
+    .await?;
     let employee_parsed: serde_json::Value = serde_json::from_str(&employee_out)
         .or_else(|_| {
             let extracted = extract_first_json_object(&employee_out)
@@ -188,39 +623,235 @@ Return STRICT JSON with keys:
         .and_then(|v| v.as_str())
         .unwrap_or("")
         .to_string();
+    Ok((event_type, topic, confidence, private_note))
+}
 
-    let mut state = APP_STATE.lock().await;
-    let private_key = state.store_private(&agent_id, private_note);
-    let event = Event::new(
-        agent_id.clone(),
-        event_type,
-        topic.clone(),
-        confidence,
-        vec![private_key],
-    );
-    let event_id = event.event_id;
-    state.emit(event);
+/// Steering for `ask_and_persist_with` when an existing decision is re-evaluated rather than
+/// asked about.
+#[derive(Debug, Clone, Default)]
+pub struct AskOptions {
+    /// Write the outcome as the next version of this decision, whatever id the OrgBrain returns.
+    pub decision_id: Option<String>,
+    /// Write the decision version pending, to become current only once approved
+    /// (`GraphStore::promote_decision_version`), and mark the trace for approval. The
+    /// OrgBrain's org_updates are not applied, since nothing of the outcome may take effect
+    /// before the approval.
+    pub require_approval: bool,
+    /// Shown to the OrgBrain as `refresh` next to the events.
+    pub refresh_context: Option<serde_json::Value>,
+    /// Emit this event instead of deriving one from the text with the EmployeeAgent.
+    pub event: Option<PresetEvent>,
+    /// Where to send `Progress` events as the ask moves through its stages.
+    pub progress: Option<tokio::sync::broadcast::Sender<ServerEvent>>,
+    /// Sent with every `Progress` event so a client can cancel the ask.
+    pub request_id: Option<Uuid>,
+    /// Aborts the model calls in flight. Honoured until the outcome starts being written; a
+    /// cancelled ask leaves no trace and no graph writes behind.
+    pub cancel: Option<CancellationToken>,
+    /// Reason over these stored events instead of deriving one from the text (replay). The
+    /// EmployeeAgent and the event queue are skipped and no conversation turns are written.
+    pub replay_events: Option<Vec<Event>>,
+    /// Run the OrgBrain and the review but change nothing: no graph writes, no org truth
+    /// updates, no recorded trace and no conversation cache entry.
+    pub dry_run: bool,
+    /// Shown to the OrgBrain as `context_documents` (see `resolve_context`).
+    pub context: Vec<ContextDocument>,
+}
+
+/// An event built by the caller, e.g. the `update` knowledge ingest emits for its analysis.
+#[derive(Debug, Clone)]
+pub struct PresetEvent {
+    pub event_id: Uuid,
+    pub event_type: EventType,
+    pub topic: String,
+    pub confidence: f32,
+}
+
+const CONTEXT_INSTRUCTION: &str = "\nThe input includes `context_documents`: documents the asker chose for this question, each with its `source`. Ground the answer in them first, and cite their sources in evidence when you use them.\n";
 
-    let events = state.drain_events();
-    let neo4j = state.neo4j.clone();
-    drop(state);
+const CLARIFY_INSTRUCTION: &str = "\nThe asker's event is a `clarification`: their request is ambiguous or missing information. If you cannot decide responsibly without more, return `needs_clarification: true` and `clarifying_question`: one short question for the user, with empty values for the other keys. Otherwise decide as usual and leave both out.\n";
+
+/// `kind` of the trace returned when the OrgBrain asks a clarifying question instead of deciding.
+pub const CLARIFICATION_KIND: &str = "clarification";
+
+const REFRESH_INSTRUCTION: &str = "\nThe input includes `refresh`: an existing decision to re-evaluate and what changed since it was made. Revise it in light of those changes and keep its decision_id.\n";
+
+fn send_progress(options: &AskOptions, agent_id: &EmployeeAgentId, stage: &str) {
+    if let Some(tx) = &options.progress {
+        let _ = tx.send(ServerEvent::Progress {
+            stage: stage.to_string(),
+            agent_id: agent_id.0.clone(),
+            request_id: options.request_id,
+        });
+    }
+}
+
+/// Runs `fut` unless `options.cancel` fires first, in which case `fut` is dropped.
+async fn unless_cancelled<F: Future>(options: &AskOptions, fut: F) -> Result<F::Output> {
+    let Some(token) = &options.cancel else {
+        return Ok(fut.await);
+    };
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(CosError::Cancelled.into()),
+        out = fut => Ok(out),
+    }
+}
+
+pub async fn ask_and_persist(text: String, agent_id: Option<String>) -> Result<(String, ReasoningTrace)> {
+    ask_and_persist_with(text, agent_id, AskOptions::default()).await
+}
+
+pub async fn ask_and_persist_with(
+    text: String,
+    agent_id: Option<String>,
+    options: AskOptions,
+) -> Result<(String, ReasoningTrace)> {
+    // LLM calls made for this ask are logged under its request id.
+    let request_id = options.request_id;
+    crate::llm_log::scope(request_id, ask_and_persist_inner(text, agent_id, options)).await
+}
+
+async fn ask_and_persist_inner(
+    text: String,
+    agent_id: Option<String>,
+    options: AskOptions,
+) -> Result<(String, ReasoningTrace)> {
+    if text.trim().is_empty() {
+        return Err(CosError::Validation("text must not be empty".to_string()).into());
+    }
+    let agent_id = EmployeeAgentId(agent_id.unwrap_or_else(|| "employee_1".to_string()));
+    send_progress(&options, &agent_id, "analyzing");
+
+    // Inputs above the soft limit are summarized first; the event is emitted from the summary.
+    let mut input_assumptions = Vec::new();
+    let original_len = text.chars().count();
+    let text = if original_len > ask_soft_chars() {
+        let summary = unless_cancelled(&options, summarize_long_input(&text)).await??;
+        input_assumptions.push(format!(
+            "input was {} characters; the event was derived from an automatic summary",
+            original_len
+        ));
+        summary
+    } else {
+        text
+    };
+
+    // Load recent per-employee conversation context (Neo4j-backed, cached in memory).
+    let neo4j = handles().neo4j;
+    let cached = sessions().await.conversation_cache.get(&agent_id).cloned();
+    let mut memory_turns = cached.unwrap_or_default();
+    if memory_turns.is_empty() {
+        if let Some(client) = neo4j.clone() {
+            let load = load_recent_conversation_turns(client.graph(), &agent_id.0, 20);
+            if let Ok(turns) = client.breaker().call(load).await {
+                // stored DESC; reverse for chronological.
+                memory_turns = turns.into_iter().rev().collect();
+            }
+        }
+    }
+
+    let memory_context = if memory_turns.is_empty() {
+        String::new()
+    } else {
+        let mut s = String::from("Prior conversation (most recent last):\n");
+        for (role, content) in memory_turns.iter() {
+            s.push_str(&format!("- {}: {}\n", role, content));
+        }
+        s
+    };
+
+    // The current message decides the response language, not the conversation history.
+    let language = crate::utils::detect_language(&text);
+    let language_instruction = match &language {
+        Some((_, name)) => format!(
+            "\nThe user's latest message is written in {name}. Write any user-facing text in {name}, even if earlier conversation used another language.\n"
+        ),
+        None => "\nWrite any user-facing text in the same language as the user's latest message.\n".to_string(),
+    };
+
+    let (event_id, topic, confidence, events) = match options.replay_events.clone() {
+        Some(events) => {
+            let Some(first) = events.first() else {
+                return Err(CosError::Validation("no events to replay".to_string()).into());
+            };
+            (first.event_id, first.topic.clone(), first.confidence, events)
+        }
+        None => {
+            let (event_type, topic, confidence, private_note) = match &options.event {
+                Some(preset) => {
+                    (preset.event_type.clone(), preset.topic.clone(), preset.confidence, None)
+                }
+                None => {
+                    let (event_type, topic, confidence, note) = unless_cancelled(
+                        &options,
+                        employee_event(&text, &memory_context, &language_instruction),
+                    )
+                    .await??;
+                    (event_type, topic, confidence, Some(note))
+                }
+            };
+
+            let references = match private_note {
+                Some(note) => vec![sessions().await.store_private(&agent_id, note)],
+                None => Vec::new(),
+            };
+            let mut event =
+                Event::new(agent_id.clone(), event_type, topic.clone(), confidence, references);
+            if let Some(preset) = &options.event {
+                event.event_id = preset.event_id;
+            }
+            let event_id = event.event_id;
+            let events = {
+                let mut state = APP_STATE.lock().await;
+                state.emit(event);
+                state.drain_events()
+            };
+            (event_id, topic, confidence, events)
+        }
+    };
+    let store = handles().graph_store;
+
+    // Everything was below the confidence floor: answer without reasoning or a new version.
+    if events.is_empty() {
+        return Ok((
+            "Noted. This was too tentative to act on yet.".to_string(),
+            noop_trace(event_id, topic, confidence, agent_id, language.map(|(code, _)| code)),
+        ));
+    }
 
     let events_json = serde_json::to_string(&events)?;
+    send_progress(&options, &agent_id, "retrieving");
 
-    let rag_snippets = {
-        let state = APP_STATE.lock().await;
-        state.rag_search(format!("{}", events_json), 3).await?
+    // Retrieval is limited to the sources the caller's role may see.
+    let caller_roles = [crate::api::employee_role_from_agent_id(&agent_id.0)];
+    let retrieved = {
+        let k = crate::retrieval::rag_top_k();
+        let search = rag_search(events_json, k, &caller_roles);
+        match unless_cancelled(&options, search).await.and_then(|r| r) {
+            Ok(retrieved) => retrieved,
+            Err(e) => return Err(requeue_drained(&options, &events, event_id, e).await),
+        }
     };
+    let rag_snippets: Vec<String> = retrieved.iter().map(|s| s.content.clone()).collect();
 
-    let truth_snapshot = {
+    // Only the truth relevant to these events, within COS_TRUTH_PROMPT_CHARS.
+    let truth_selection = {
         let state = APP_STATE.lock().await;
-        state.org_truth.clone()
+        let pinned = crate::truth_context::pinned_truth_ids(&traces().await, &events);
+        crate::truth_context::select_truth(
+            &state.org_truth,
+            &events,
+            &pinned,
+            crate::truth_context::truth_top_k(),
+            crate::truth_context::truth_prompt_chars(),
+        )
     };
 
     let org_system = r#"You are the OrgBrain.
 You maintain the Organization Truth (versioned), and produce a reasoning trace.
 
-Use retrieved policy snippets if relevant.
+Use retrieved policy snippets if relevant. graph_context holds the current truth and the latest decisions on these topics from the organization graph; stay consistent with them or say what changed.
 
 Return STRICT JSON with keys:
 - decision_id: stable string identifier for this decision (if new, create a new UUID string)
@@ -232,18 +863,65 @@ Return STRICT JSON with keys:
 - response_text: what to say to the user
 - confidence: number in [0,1]
 - routing: object mapping agent_id -> one of ["full","summary","none"]
-- org_updates: object mapping truth_id -> update_string (can be empty)
+- org_updates: object mapping truth_id -> update_string, or -> {"op": "update"|"retire"|"merge", "content"?, "target"?} (retire drops a truth; merge retires it into target, optionally with new content for target; can be empty)
 "#;
 
-    let org_user = json!({
+    // The structured half of retrieval, next to the RAG snippets.
+    let graph_context = match &store {
+        Some(store) => {
+            crate::retrieval::graph_context(store.as_ref(), &events, &truth_selection.included).await
+        }
+        None => json!({}),
+    };
+
+    let topic_groups = crate::merge::group_by_topic(&events);
+    let mut org_user = json!({
         "events": events,
+        "topic_groups": crate::merge::prompt_groups(&topic_groups),
         "rag": rag_snippets,
-        "org_truth": truth_snapshot
-    })
-    .to_string();
+        "org_truth": truth_selection.prompt_value(),
+        "graph_context": graph_context
+    });
+    if let Some(refresh) = &options.refresh_context {
+        org_user["refresh"] = refresh.clone();
+    }
+    if !options.context.is_empty() {
+        org_user["context_documents"] = json!(options.context);
+    }
+    let org_user = org_user.to_string();
 
-    let org_out = openai_chat(org_system, &org_user).await?;
-    let org_parsed: serde_json::Value = serde_json::from_str(&org_out)
+    // Only a fresh ask whose own event is a clarification may be answered with a question;
+    // refreshes and replays always decide.
+    let may_clarify = options.replay_events.is_none()
+        && options.decision_id.is_none()
+        && events
+            .iter()
+            .any(|e| e.event_id == event_id && matches!(e.event_type, EventType::Clarification));
+    let org_system = format!(
+        "{}{}{}{}{}{}",
+        org_system,
+        crate::merge::TOPIC_GROUPS_INSTRUCTION,
+        if may_clarify { CLARIFY_INSTRUCTION } else { "" },
+        if options.refresh_context.is_some() { REFRESH_INSTRUCTION } else { "" },
+        if options.context.is_empty() { "" } else { CONTEXT_INSTRUCTION },
+        language_instruction
+    );
+    send_progress(&options, &agent_id, "deciding");
+    let (options_ref, org_user) = (&options, &org_user);
+    let org_out = chat_checked(
+        "OrgBrain",
+        &ORG_DECISION_SCHEMA,
+        &org_system,
+        move |system| async move {
+            unless_cancelled(options_ref, openai_chat(&system, org_user)).await?
+        },
+    )
+    .await;
+    let org_out = match org_out {
+        Ok(out) => out,
+        Err(e) => return Err(requeue_drained(&options, &events, event_id, e).await),
+    };
+    let org_json: serde_json::Result<serde_json::Value> = serde_json::from_str(&org_out)
         .or_else(|_| {
             let extracted = extract_first_json_object(&org_out)
                 .ok_or_else(|| serde_json::Error::io(std::io::Error::new(
@@ -251,21 +929,66 @@ Return STRICT JSON with keys:
                     "no json object found in orgbrain output",
                 )))?;
             serde_json::from_str(&extracted)
-        })
-        .unwrap_or_else(|_| {
-            json!({
-                "decision_id": "",
-                "decision": "respond",
-                "summary": "",
-                "rationale": "",
-                "evidence": [],
-                "assumptions": [],
-                "response_text": org_out,
-                "confidence": 0.5,
-                "routing": {},
-                "org_updates": {}
-            })
         });
+    // A plain-text answer is kept as the response; JSON that doesn't parse would only leak
+    // half an object to the employee.
+    if org_json.is_err() && org_out.contains('{') {
+        let e = CosError::malformed(&org_out).into();
+        return Err(requeue_drained(&options, &events, event_id, e).await);
+    }
+    let org_parsed = org_json.unwrap_or_else(|_| {
+        json!({
+            "decision_id": "",
+            "decision": "respond",
+            "summary": "",
+            "rationale": "",
+            "evidence": [],
+            "assumptions": [],
+            "response_text": org_out,
+            "confidence": 0.5,
+            "routing": {},
+            "org_updates": {}
+        })
+    });
+
+    let clarifying_question = org_parsed
+        .get("clarifying_question")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|q| !q.is_empty());
+    let needs_clarification = org_parsed
+        .get("needs_clarification")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if let Some(question) = clarifying_question.filter(|_| may_clarify && needs_clarification) {
+        let question = question.to_string();
+        // Other employees' events drained with this one wait for the next decision.
+        requeue_others(&events, event_id).await;
+        let mut trace = clarification_trace(
+            event_id,
+            topic,
+            confidence,
+            agent_id.clone(),
+            language.map(|(code, _)| code),
+        );
+        if options.dry_run {
+            return Ok((question, trace));
+        }
+        // No decision, but the question and the asker's message go into the conversation so
+        // the answer to the question is read in context.
+        let turns = vec![
+            ("user".to_string(), text),
+            ("assistant".to_string(), question.clone()),
+        ];
+        if let Some(store) = &store {
+            if let Err(e) = store.append_conversation_turns(&agent_id.0, &turns).await {
+                let warning = persistence_warning(&trace.decision_id, "conversation turns", &e);
+                trace.persistence_warnings.push(warning);
+            }
+        }
+        remember_turns(&agent_id, turns).await;
+        return Ok((question, trace));
+    }
 
     let decision_id_in = org_parsed
         .get("decision_id")
@@ -287,16 +1010,18 @@ Return STRICT JSON with keys:
         .and_then(|v| v.as_str())
         .unwrap_or("")
         .to_string();
-    let evidence: Vec<String> = org_parsed
+    let mut evidence: Vec<String> = org_parsed
         .get("evidence")
         .and_then(|v| v.as_array())
         .map(|arr| {
             arr.iter()
                 .filter_map(|x| x.as_str().map(|s| s.to_string()))
+                .filter(|s| !s.starts_with(crate::truth_context::TRUTH_EVIDENCE_PREFIX))
                 .collect()
         })
         .unwrap_or_default();
-    let assumptions: Vec<String> = org_parsed
+    evidence.extend(truth_selection.evidence());
+    let mut assumptions: Vec<String> = org_parsed
         .get("assumptions")
         .and_then(|v| v.as_array())
         .map(|arr| {
@@ -305,12 +1030,19 @@ Return STRICT JSON with keys:
                 .collect()
         })
         .unwrap_or_default();
+    assumptions.extend(input_assumptions);
+    for dissent in crate::merge::dissent_assumptions(&topic_groups) {
+        if !assumptions.contains(&dissent) {
+            assumptions.push(dissent);
+        }
+    }
     let response_text = org_parsed
         .get("response_text")
         .and_then(|v| v.as_str())
         .unwrap_or("")
         .to_string();
-    let routing_val = org_parsed.get("routing").cloned().unwrap_or_else(|| json!({}));
+    let routing_val =
+        canonical_routing(org_parsed.get("routing").cloned().unwrap_or_else(|| json!({}))).await;
 
     let routing_map: std::collections::HashMap<String, String> = routing_val
         .as_object()
@@ -321,22 +1053,37 @@ Return STRICT JSON with keys:
         })
         .unwrap_or_default();
 
-    let mut updated_truth_ids = Vec::new();
-    if let Some(obj) = org_parsed.get("org_updates").and_then(|v| v.as_object()) {
-        let mut state = APP_STATE.lock().await;
-        for (k, v) in obj {
-            let upd = v.as_str().unwrap_or("").to_string();
-            if !upd.is_empty() {
-                state.update_org_truth(k, upd);
-                updated_truth_ids.push(k.clone());
-            }
-        }
+    // Second opinion before anything is persisted. The asker's context counts as evidence too.
+    let review_sources: Vec<String> = rag_snippets
+        .iter()
+        .cloned()
+        .chain(options.context.iter().map(|d| format!("[{}] {}", d.source, d.content)))
+        .collect();
+    let review =
+        unless_cancelled(&options, crate::review::review_decision(&org_parsed, &review_sources))
+            .await;
+    let review = match review {
+        Ok(review) => review,
+        Err(e) => return Err(requeue_drained(&options, &events, event_id, e).await),
+    };
+    let confidence = review.adjusted_confidence(confidence);
+
+    // Past this point the ask changes state, so a late cancel is ignored.
+    if options.cancel.as_ref().is_some_and(CancellationToken::is_cancelled) {
+        let e = CosError::Cancelled.into();
+        return Err(requeue_drained(&options, &events, event_id, e).await);
     }
 
-    let final_decision_id = if decision_id_in.is_empty() {
-        uuid::Uuid::new_v4().to_string()
+    let mut org_updates = if options.require_approval || options.dry_run {
+        Vec::new()
     } else {
-        decision_id_in
+        crate::org_updates::parse_org_updates(org_parsed.get("org_updates"))
+    };
+
+    let final_decision_id = match options.decision_id.clone() {
+        Some(id) => id,
+        None if decision_id_in.is_empty() => uuid::Uuid::new_v4().to_string(),
+        None => decision_id_in,
     };
 
     let mut graph_updates = GraphUpdates {
@@ -344,69 +1091,100 @@ Return STRICT JSON with keys:
         edges: Vec::new(),
     };
 
+    let decision_summary = if summary.is_empty() {
+        decision_label.clone()
+    } else {
+        summary.clone()
+    };
     let mut decision_version = 1i64;
-    if let Some(client) = neo4j.clone() {
-        let graph = client.graph();
-
-        decision_version = next_decision_version(graph, &final_decision_id)
-            .await
-            .unwrap_or(1);
-
-        if let Ok(upd) = persist_decision_version(
-            graph,
-            final_decision_id.clone(),
-            decision_version,
-            if summary.is_empty() {
-                decision_label.clone()
-            } else {
-                summary.clone()
-            },
-            confidence as f64,
-            vec![event_id],
-            vec![agent_id.0.clone()],
-            routing_val.clone(),
-        )
-        .await
-        {
-            graph_updates.nodes.extend(upd.nodes);
-            graph_updates.edges.extend(upd.edges);
+    let mut warnings = Vec::new();
+    let replay = options.replay_events.is_some();
+    if let Some(store) = store.filter(|_| !options.dry_run) {
+        let contents = crate::org_updates::truth_contents(&org_updates);
+        let (truths, unchanged) = truth_writes(store.as_ref(), &contents).await;
+        assumptions.extend(unchanged);
+        if let Err(e) = store.persist_events(&events).await {
+            warnings.push(persistence_warning(&final_decision_id, "events", &e));
         }
+        let (trigger_events, agents_involved) = if replay {
+            let emitters = events.iter().map(|e| e.emitted_by.0.clone()).collect();
+            (events.iter().map(|e| e.event_id).collect(), emitters)
+        } else {
+            (vec![event_id], vec![agent_id.0.clone()])
+        };
+        let turns = if replay {
+            Vec::new()
+        } else {
+            vec![
+                ("user".to_string(), text.clone()),
+                ("assistant".to_string(), response_text.clone()),
+            ]
+        };
+        let outcome = AskOutcome {
+            decision_id: final_decision_id.clone(),
+            summary: decision_summary.clone(),
+            confidence: confidence as f64,
+            trigger_events,
+            agents_involved,
+            routing: routing_val.clone(),
+            topic: topic.clone(),
+            based_on: truth_selection.included.clone(),
+            content: DecisionContent {
+                rationale: Some(rationale.clone()),
+                evidence: evidence.clone(),
+                assumptions: assumptions.clone(),
+                retrieved: retrieved.clone(),
+                kind: None,
+            },
+            truths,
+            employee_id: agent_id.0.clone(),
+            turns,
+            pending: options.require_approval,
+        };
 
-        for truth_id in &updated_truth_ids {
-            let v = next_truth_version(graph, truth_id).await.unwrap_or(1);
-            let content = {
-                let state = APP_STATE.lock().await;
-                state.latest_truth(truth_id).unwrap_or("").to_string()
-            };
-
-            if content.is_empty() {
-                continue;
-            }
-
-            if let Ok(upd) = persist_truth_version(
-                graph,
-                truth_id.clone(),
-                "org_truth".to_string(),
-                v,
-                content,
-                confidence as f64,
-                vec![event_id],
-                vec![agent_id.0.clone()],
-                routing_val.clone(),
-            )
-            .await
-            {
+        match store.persist_ask_outcome(&outcome).await {
+            Ok((v, upd)) => {
+                decision_version = v;
+                crate::similar::spawn_embed_decision(
+                    store.clone(),
+                    final_decision_id.clone(),
+                    v,
+                    decision_summary.clone(),
+                );
                 graph_updates.nodes.extend(upd.nodes);
                 graph_updates.edges.extend(upd.edges);
             }
+            Err(e) if strict_persistence() => {
+                persistence_warning(&final_decision_id, "ask outcome", &e);
+                let e = e.context(CosError::GraphWrite);
+                return Err(requeue_drained(&options, &events, event_id, e).await);
+            }
+            Err(e) => {
+                warnings.push(persistence_warning(&final_decision_id, "ask outcome", &e));
+                // The truth versions were rolled back with the outcome.
+                org_updates.clear();
+            }
         }
+
+        warnings.extend(
+            crate::org_updates::persist_structural(
+                store.as_ref(),
+                &mut org_updates,
+                &mut graph_updates,
+                &final_decision_id,
+            )
+            .await,
+        );
     }
+    // Only what was persisted reaches the OrgBrain's in-memory truth.
+    crate::org_updates::apply_to_state(&mut *APP_STATE.lock().await, &org_updates);
 
     let trace = ReasoningTrace {
         decision_id: final_decision_id,
         topic: topic.clone(),
-        summary: if summary.is_empty() { decision_label.clone() } else { summary.clone() },
+        summary: decision_summary,
         version: decision_version,
+        confidence,
         rationale,
         evidence,
         assumptions,
@@ -414,29 +1192,95 @@ Return STRICT JSON with keys:
         agents_involved: events.iter().map(|e| e.emitted_by.clone()).collect(),
         graph_updates,
         routing: routing_map,
+        created_at: chrono::Utc::now(),
+        language: language.map(|(code, _)| code),
+        annotations: review.annotations(),
+        deduplicated: false,
+        needs_approval: options.require_approval || review.needs_approval(),
+        persistence_warnings: warnings,
+        kind: None,
     };
+    if options.dry_run {
+        return Ok((response_text, trace));
+    }
 
-    {
-        let mut state = APP_STATE.lock().await;
-        state.add_trace(trace.clone());
+    traces().await.push(trace.clone());
+
+    // The turns were persisted with the outcome above; keep the cache in step.
+    if !replay {
+        let turns = vec![
+            ("user".to_string(), text),
+            ("assistant".to_string(), response_text.clone()),
+        ];
+        remember_turns(&agent_id, turns).await;
     }
 
-    // Persist per-employee memory (Neo4j-backed) and update in-memory cache.
-    if let Some(client) = neo4j {
-        let graph = client.graph();
-        let _ = persist_conversation_turn(graph, &agent_id.0, "user", &text).await;
-        let _ = persist_conversation_turn(graph, &agent_id.0, "assistant", &response_text).await;
+    Ok((response_text, trace))
+}
+
+/// Adds `turns` to `agent_id`'s cached conversation, keeping the last 40.
+async fn remember_turns(agent_id: &EmployeeAgentId, turns: Vec<(String, String)>) {
+    let mut sessions = sessions().await;
+    let entry = sessions.conversation_cache.entry(agent_id.clone()).or_default();
+    entry.extend(turns);
+    if entry.len() > 40 {
+        let keep_from = entry.len() - 40;
+        *entry = entry.split_off(keep_from);
     }
-    {
-        let mut state = APP_STATE.lock().await;
-        let entry = state.conversation_cache.entry(agent_id.clone()).or_default();
-        entry.push(("user".to_string(), text));
-        entry.push(("assistant".to_string(), response_text.clone()));
-        if entry.len() > 40 {
-            let keep_from = entry.len() - 40;
-            *entry = entry.split_off(keep_from);
-        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_drops_control_characters_and_trims() {
+        assert_eq!(sanitize_input_text("  hello\u{0}\u{7} world \u{1b}[0m\r\n"), "hello world [0m");
+        assert_eq!(sanitize_input_text("line one\n\tline two"), "line one\n\tline two");
+        assert_eq!(sanitize_input_text("\u{0}\u{8}  "), "");
     }
 
-    Ok((response_text, trace))
+    #[test]
+    fn persistence_warnings_name_the_failure_and_are_counted() {
+        let before = persistence_failures();
+        let e = anyhow::anyhow!("bolt: connection reset").context(CosError::GraphWrite);
+        let warning = persistence_warning("d-1", "ask outcome", &e);
+        assert_eq!(warning, "ask outcome was not persisted: graph write failed");
+        assert!(persistence_failures() > before);
+    }
+
+    #[test]
+    fn split_by_chars_keeps_multibyte_characters_whole() {
+        assert_eq!(split_by_chars("héllo wörld", 4), vec!["héll", "o wö", "rld"]);
+        assert_eq!(split_by_chars("abc", 0), vec!["a", "b", "c"]);
+        assert!(split_by_chars("", 3).is_empty());
+    }
+
+    #[tokio::test]
+    async fn unchanged_truth_writes_no_new_version() {
+        let store = tokio::sync::Mutex::new(crate::memory_store::MemoryStore::new());
+        let content = |s: &str| vec![("policy".to_string(), s.to_string())];
+        let (writes, unchanged) = truth_writes(&store, &content("two office days")).await;
+        assert_eq!(writes.len(), 1);
+        assert!(unchanged.is_empty());
+        let w = &writes[0];
+        store
+            .persist_truth_version(
+                w.truth_id.clone(),
+                w.kind.clone(),
+                w.summary.clone(),
+                1.0,
+                Vec::new(),
+                Vec::new(),
+                &serde_json::json!({}),
+            )
+            .await
+            .unwrap();
+
+        let (writes, unchanged) = truth_writes(&store, &content("two office days")).await;
+        assert!(writes.is_empty());
+        assert_eq!(unchanged, vec!["truth unchanged: policy"]);
+        let (writes, _) = truth_writes(&store, &content("three office days")).await;
+        assert_eq!(writes[0].summary, "three office days");
+    }
 }
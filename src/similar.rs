@@ -0,0 +1,57 @@
+use anyhow::Result;
+use std::sync::Arc;
+
+use crate::app_state::{cosine_sim, openai_embedding};
+use crate::graph_store::GraphStore;
+use crate::neo4j::writer::DecisionEmbedding;
+
+/// Embeds `summary` and stores it on the decision version, in the background so the ask isn't
+/// held up. Does nothing without `OPENAI_API_KEY`.
+pub fn spawn_embed_decision(
+    store: Arc<dyn GraphStore>,
+    decision_id: String,
+    version: i64,
+    summary: String,
+) {
+    if !crate::llm::openai_configured() || summary.trim().is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        if let Err(e) = embed_decision(store.as_ref(), &decision_id, version, &summary).await {
+            eprintln!("embed decision {decision_id} v{version}: {e:#}");
+        }
+    });
+}
+
+/// Embeds `summary`, stores it on the decision version and returns it.
+pub async fn embed_decision(
+    store: &dyn GraphStore,
+    decision_id: &str,
+    version: i64,
+    summary: &str,
+) -> Result<Vec<f32>> {
+    let embedding = openai_embedding(summary).await?;
+    store.set_decision_embedding(decision_id, version, &embedding).await?;
+    Ok(embedding)
+}
+
+/// The `k` candidates most similar to `target` by cosine similarity, best first. Candidates
+/// without an embedding, and `target`'s own decision, are skipped.
+pub fn nearest(
+    target_id: &str,
+    target: &[f32],
+    candidates: Vec<DecisionEmbedding>,
+    k: usize,
+) -> Vec<(DecisionEmbedding, f32)> {
+    let mut scored: Vec<(DecisionEmbedding, f32)> = candidates
+        .into_iter()
+        .filter(|c| c.decision_id != target_id && !c.embedding.is_empty())
+        .map(|c| {
+            let sim = cosine_sim(target, &c.embedding);
+            (c, sim)
+        })
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(k);
+    scored
+}
@@ -0,0 +1,122 @@
+use anyhow::Result;
+use serde_json::json;
+use std::time::Duration;
+
+use crate::app_state::{handles, APP_STATE};
+use crate::domain::ReasoningTrace;
+use crate::error::CosError;
+use crate::graph_store::GraphStore;
+use crate::neo4j::writer::{DecisionActivity, StaleMark};
+use crate::service::{ask_and_persist_with, AskOptions};
+
+/// At most this many new email subjects are shown to the OrgBrain on refresh.
+const REFRESH_EMAIL_LIMIT: usize = 20;
+
+/// `COS_STALE_CHECK_INTERVAL_SECS` (default 86400, daily): how often the staleness check runs.
+/// `0` disables it.
+pub fn stale_check_interval() -> Option<Duration> {
+    let secs = crate::app_state::config().maintenance.stale_check_interval_secs;
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// `COS_STALE_AGE_DAYS` (default 90): age at which a decision counts fully towards its score.
+pub fn stale_age_days() -> f64 {
+    crate::app_state::config().maintenance.stale_age_days
+}
+
+/// `COS_STALE_TRUTH_WEIGHT` (default 5): how many emails one newer truth version counts as.
+pub fn stale_truth_weight() -> f64 {
+    crate::app_state::config().maintenance.stale_truth_weight
+}
+
+/// `COS_STALE_THRESHOLD` (default 10): score at or above which a decision is stale.
+pub fn stale_threshold() -> f64 {
+    crate::app_state::config().maintenance.stale_threshold
+}
+
+/// Age, as a fraction of `COS_STALE_AGE_DAYS` capped at 1, times the activity since the decision:
+/// newer emails plus `COS_STALE_TRUTH_WEIGHT` per newer truth version. A fresh decision scores
+/// low however busy its topic is, and an old one on a quiet topic scores 0.
+pub fn staleness_score(activity: &DecisionActivity) -> f64 {
+    let age = (activity.age_days.max(0) as f64 / stale_age_days()).min(1.0);
+    let volume =
+        activity.newer_emails as f64 + stale_truth_weight() * activity.newer_truth_versions as f64;
+    age * volume
+}
+
+/// Scores every current decision version and records the result. Returns the versions that
+/// became stale in this run (they were not marked before) and how many were checked.
+pub async fn check_staleness(
+    store: &dyn GraphStore,
+) -> Result<(Vec<(DecisionActivity, f64)>, usize)> {
+    let threshold = stale_threshold();
+    let activity = store.decision_activity().await?;
+    let checked = activity.len();
+    let mut marks = Vec::with_capacity(checked);
+    let mut newly_stale = Vec::new();
+    for a in activity {
+        let score = staleness_score(&a);
+        let stale = score >= threshold;
+        marks.push(StaleMark {
+            decision_id: a.decision_id.clone(),
+            version: a.version,
+            stale,
+            score,
+        });
+        if stale && !a.stale {
+            newly_stale.push((a, score));
+        }
+    }
+    store.mark_staleness(&marks).await?;
+    Ok((newly_stale, checked))
+}
+
+/// Re-runs the OrgBrain on `decision_id` with what accumulated since its current version. The
+/// result is written as a pending version, which becomes current once approved, and its trace
+/// is marked for approval. `None` when the decision doesn't exist.
+pub async fn refresh_decision(
+    decision_id: &str,
+    agent_id: String,
+) -> Result<Option<(String, ReasoningTrace)>> {
+    let store = handles().graph_store
+        .ok_or(CosError::GraphUnavailable)?;
+    let Some(context) = store.stale_context(decision_id, REFRESH_EMAIL_LIMIT).await? else {
+        return Ok(None);
+    };
+
+    let truth_updates: Vec<serde_json::Value> = {
+        let state = APP_STATE.lock().await;
+        context
+            .truth_ids
+            .iter()
+            .map(|id| json!({ "truth_id": id, "current": state.latest_truth(id) }))
+            .collect()
+    };
+    let topic = context.topic.clone().unwrap_or_else(|| "general".to_string());
+    let refresh = json!({
+        "decision_id": decision_id,
+        "version": context.version,
+        "summary": context.summary,
+        "topic": topic,
+        "new_email_subjects": context.email_subjects,
+        "truth_updates": truth_updates,
+    });
+    let text = format!(
+        "Decision update on {topic}: re-evaluate decision {decision_id} (\"{}\") given {} new emails and {} changed policies since it was made.",
+        context.summary,
+        context.email_subjects.len(),
+        context.truth_ids.len()
+    );
+    let outcome = ask_and_persist_with(
+        text,
+        Some(agent_id),
+        AskOptions {
+            decision_id: Some(decision_id.to_string()),
+            require_approval: true,
+            refresh_context: Some(refresh),
+            ..Default::default()
+        },
+    )
+    .await?;
+    Ok(Some(outcome))
+}
@@ -1,10 +1,14 @@
 use pocketflow_rs::ProcessState;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum MyState {
     Success,
     Failure,
+    /// A transient upstream error; the attempt count lives in the Context under
+    /// `crate::nodes::RETRY_ATTEMPT_KEY`.
+    Retry,
     Exit,
+    #[default]
     Default,
 }
 
@@ -17,14 +21,9 @@ impl ProcessState for MyState {
         match self {
             MyState::Success => "success".to_string(),
             MyState::Failure => "failure".to_string(),
+            MyState::Retry => "retry".to_string(),
             MyState::Exit => "exit".to_string(),
             MyState::Default => "default".to_string(),
         }
     }
 }
-
-impl Default for MyState {
-    fn default() -> Self {
-        MyState::Default
-    }
-}
@@ -0,0 +1,240 @@
+use serde_json::Value;
+
+use crate::utils::openai_chat;
+
+const URGENT_TERMS: [&str; 14] = [
+    "urgent",
+    "asap",
+    "immediately",
+    "emergency",
+    "critical",
+    "deadline",
+    "today",
+    "eod",
+    "end of day",
+    "right away",
+    "time sensitive",
+    "time-sensitive",
+    "important",
+    "action required",
+];
+
+const POSITIVE_TERMS: [&str; 10] = [
+    "thanks", "thank you", "great", "congrat", "appreciate", "glad", "happy", "excellent",
+    "well done", "pleased",
+];
+
+const NEGATIVE_TERMS: [&str; 12] = [
+    "problem", "issue", "concern", "disappoint", "unacceptable", "angry", "complaint", "fail",
+    "broken", "delay", "sorry", "worried",
+];
+
+#[derive(Debug, Clone)]
+pub struct EmailTriage {
+    /// 0.0 (can wait) .. 1.0 (needs attention now).
+    pub urgency: f64,
+    /// One of "positive", "neutral", "negative".
+    pub sentiment: String,
+}
+
+fn count_terms(text: &str, terms: &[&str]) -> usize {
+    terms.iter().filter(|t| text.contains(*t)).count()
+}
+
+/// Keyword heuristic used when no LLM is available (or the LLM output is unusable).
+pub fn heuristic_triage(subject: &str, body: &str) -> EmailTriage {
+    let subject = subject.to_lowercase();
+    let body = body.to_lowercase();
+
+    // Subject hits weigh double: people put urgency markers there.
+    let hits = count_terms(&subject, &URGENT_TERMS) * 2 + count_terms(&body, &URGENT_TERMS);
+    let exclamations = subject.matches('!').count() + body.matches('!').count().min(3);
+    let urgency = (hits as f64 * 0.2 + exclamations as f64 * 0.05).min(1.0);
+
+    let text = format!("{}\n{}", subject, body);
+    let pos = count_terms(&text, &POSITIVE_TERMS);
+    let neg = count_terms(&text, &NEGATIVE_TERMS);
+    let sentiment = if pos > neg {
+        "positive"
+    } else if neg > pos {
+        "negative"
+    } else {
+        "neutral"
+    };
+
+    EmailTriage {
+        urgency,
+        sentiment: sentiment.to_string(),
+    }
+}
+
+/// LLM classification when `use_llm` is set, falling back to the heuristic on any failure.
+pub async fn triage_email(subject: &str, body: &str, use_llm: bool) -> EmailTriage {
+    if !use_llm {
+        return heuristic_triage(subject, body);
+    }
+
+    let system = r#"You triage corporate email.
+Return STRICT JSON with keys:
+- urgency: number in [0,1] (1 = needs attention immediately)
+- sentiment: one of ["positive","neutral","negative"]
+"#;
+    let snippet: String = body.chars().take(1500).collect();
+    let user = format!("Subject: {}\n\n{}", subject, snippet);
+
+    let Ok(out) = openai_chat(system, &user).await else {
+        return heuristic_triage(subject, body);
+    };
+    let parsed: Option<Value> = serde_json::from_str(&out).ok().or_else(|| {
+        let start = out.find('{')?;
+        let end = out.rfind('}')?;
+        if end <= start {
+            return None;
+        }
+        serde_json::from_str(&out[start..=end]).ok()
+    });
+
+    let fallback = heuristic_triage(subject, body);
+    let Some(v) = parsed else {
+        return fallback;
+    };
+    let urgency = v
+        .get("urgency")
+        .and_then(|u| u.as_f64())
+        .map(|u| u.clamp(0.0, 1.0))
+        .unwrap_or(fallback.urgency);
+    let sentiment = v
+        .get("sentiment")
+        .and_then(|s| s.as_str())
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| matches!(s.as_str(), "positive" | "neutral" | "negative"))
+        .unwrap_or(fallback.sentiment);
+
+    EmailTriage { urgency, sentiment }
+}
+
+/// LLM label of a cluster: a short name and a one-sentence description.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterLabel {
+    pub name: String,
+    pub description: String,
+}
+
+/// `COS_CLUSTER_LABEL_MIN_MEMBERS` (default 3): clusters with fewer members keep their heuristic
+/// name instead of an LLM label.
+pub fn cluster_label_min_members() -> usize {
+    crate::app_state::config().ingest.cluster_label_min_members
+}
+
+/// `ORG_MIN_CLUSTER_SIZE` (default 2, at least 1): clusters with fewer members are not listed or
+/// labeled, and their messages count as unclustered.
+pub fn min_cluster_size() -> usize {
+    crate::app_state::config().ingest.min_cluster_size.max(1)
+}
+
+/// `ORG_UNCLUSTERED_GROUP=1`: list the messages outside every listed cluster as one catch-all
+/// group, `UNCLUSTERED_ID`, so every message belongs somewhere.
+pub fn unclustered_group_enabled() -> bool {
+    crate::app_state::config().ingest.unclustered_group
+}
+
+/// Cluster id of the catch-all group of `unclustered_group_enabled`.
+pub const UNCLUSTERED_ID: &str = "unclustered";
+
+/// `COS_CLUSTER_LABEL_SAMPLES` (default 10): member lines sent to the LLM per cluster.
+fn cluster_label_samples() -> usize {
+    crate::app_state::config().ingest.cluster_label_samples.max(1)
+}
+
+/// Asks the LLM for a 3-6 word name and a one-sentence description of a cluster of emails,
+/// given one `subject: snippet` line per member. `None` when the call fails or the name is
+/// empty, in which case the caller keeps its default label.
+pub async fn label_cluster(samples: &[String]) -> Option<ClusterLabel> {
+    let system = r#"You name clusters of related corporate email.
+Given one "subject: snippet" line per message, reply with STRICT JSON only:
+{"label": "3-6 word label of what the messages have in common",
+ "description": "one sentence describing the cluster"}
+"#;
+    let user = samples
+        .iter()
+        .take(cluster_label_samples())
+        .map(|s| format!("- {}", s))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let out = openai_chat(system, &user).await.ok()?;
+    parse_cluster_label(&out)
+}
+
+/// Reads the labeller's reply: the JSON object asked for, or a plain-text first line.
+fn parse_cluster_label(out: &str) -> Option<ClusterLabel> {
+    let (label, description) = match crate::llm_schema::parse_llm_json(out) {
+        Some(v) => (
+            v.get("label").and_then(|l| l.as_str()).unwrap_or("").to_string(),
+            v.get("description").and_then(|d| d.as_str()).unwrap_or("").to_string(),
+        ),
+        // Plain text: the first line is the label.
+        None => (out.lines().next().unwrap_or("").to_string(), String::new()),
+    };
+    let name: String = label
+        .trim()
+        .trim_matches(|c: char| c == '"' || c == '\'' || c == '.')
+        .chars()
+        .take(80)
+        .collect();
+    let name = name.trim().to_string();
+    let description: String = description.trim().chars().take(300).collect();
+    (!name.is_empty()).then_some(ClusterLabel { name, description })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subject_urgency_terms_weigh_double() {
+        let in_subject = heuristic_triage("URGENT: contract", "please review");
+        let in_body = heuristic_triage("contract", "urgent, please review");
+        assert!((in_subject.urgency - 0.4).abs() < 1e-9);
+        assert!((in_body.urgency - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn urgency_is_capped_at_one() {
+        let t = heuristic_triage(
+            "URGENT critical deadline today!!!",
+            "asap, emergency, action required immediately!!!!!!",
+        );
+        assert_eq!(t.urgency, 1.0);
+        assert_eq!(heuristic_triage("lunch menu", "soup").urgency, 0.0);
+    }
+
+    #[test]
+    fn sentiment_follows_the_majority_of_terms() {
+        assert_eq!(heuristic_triage("Thanks!", "Great work, well done").sentiment, "positive");
+        assert_eq!(heuristic_triage("Delay", "The build is broken again").sentiment, "negative");
+        assert_eq!(heuristic_triage("Thanks", "but there is a problem").sentiment, "neutral");
+    }
+
+    #[tokio::test]
+    async fn triage_without_llm_is_the_heuristic() {
+        let t = triage_email("ASAP", "thank you", false).await;
+        assert!((t.urgency - 0.4).abs() < 1e-9);
+        assert_eq!(t.sentiment, "positive");
+    }
+
+    #[test]
+    fn cluster_labels_are_read_from_json_or_the_first_line() {
+        let json = r#"{"label": "\"Q3 budget review.\"", "description": " Budget threads. "}"#;
+        assert_eq!(
+            parse_cluster_label(json),
+            Some(ClusterLabel {
+                name: "Q3 budget review".into(),
+                description: "Budget threads.".into(),
+            })
+        );
+        let plain = parse_cluster_label("Hiring freeze\nMessages about hiring.").unwrap();
+        assert_eq!((plain.name.as_str(), plain.description.as_str()), ("Hiring freeze", ""));
+        assert_eq!(parse_cluster_label(r#"{"label": "  "}"#), None);
+    }
+}
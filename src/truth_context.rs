@@ -0,0 +1,135 @@
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+
+use crate::domain::{Event, ReasoningTrace};
+
+/// Evidence prefix recording a truth id the OrgBrain was shown.
+pub const TRUTH_EVIDENCE_PREFIX: &str = "org_truth:";
+
+/// Recent traces searched for truth ids to pin.
+const PIN_LOOKBACK: usize = 20;
+
+/// `COS_TRUTH_TOP_K` (default 8): relevance-ranked truth entries sent to the OrgBrain.
+pub fn truth_top_k() -> usize {
+    crate::app_state::config().orgbrain.truth_top_k
+}
+
+/// `COS_TRUTH_PROMPT_CHARS` (default 8000): character budget for the truth part of the prompt.
+pub fn truth_prompt_chars() -> usize {
+    crate::app_state::config().orgbrain.truth_prompt_chars
+}
+
+fn terms(s: &str) -> HashSet<String> {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() > 2)
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+/// Truth ids shown to the OrgBrain for earlier traces on the same topics, newest first.
+pub fn pinned_truth_ids(traces: &[ReasoningTrace], events: &[Event]) -> Vec<String> {
+    let topics: HashSet<String> = events.iter().map(|e| e.topic.trim().to_lowercase()).collect();
+    let mut out: Vec<String> = Vec::new();
+    for trace in traces.iter().rev().take(PIN_LOOKBACK) {
+        if !topics.contains(&trace.topic.trim().to_lowercase()) {
+            continue;
+        }
+        for id in trace
+            .evidence
+            .iter()
+            .filter_map(|e| e.strip_prefix(TRUTH_EVIDENCE_PREFIX))
+        {
+            if !out.iter().any(|o| o == id) {
+                out.push(id.to_string());
+            }
+        }
+    }
+    out
+}
+
+/// The part of the organizational truth the OrgBrain sees for one set of events.
+#[derive(Debug, Clone, Default)]
+pub struct TruthSelection {
+    /// `truth_id -> latest content`, possibly truncated.
+    pub entries: Map<String, Value>,
+    /// Truth ids in `entries`, in the order they were picked.
+    pub included: Vec<String>,
+    /// Relevant entries left out by the character budget.
+    pub omitted: usize,
+}
+
+impl TruthSelection {
+    pub fn prompt_value(&self) -> Value {
+        let mut entries = self.entries.clone();
+        if self.omitted > 0 {
+            entries.insert(
+                "_omitted".to_string(),
+                json!(format!("[{} more relevant truth entries omitted]", self.omitted)),
+            );
+        }
+        Value::Object(entries)
+    }
+
+    /// One evidence line per included truth id, so traces record what the brain saw.
+    pub fn evidence(&self) -> Vec<String> {
+        self.included
+            .iter()
+            .map(|id| format!("{}{}", TRUTH_EVIDENCE_PREFIX, id))
+            .collect()
+    }
+}
+
+/// Picks the truth to show the OrgBrain. Pinned ids come first, then the `top_k` entries whose
+/// id and latest content share the most terms with the events' topics. Only the latest content
+/// of each entry is sent. Entries stop once `max_chars` is spent, and an entry that doesn't fit
+/// is cut with a `…[truncated]` marker.
+pub fn select_truth(
+    org_truth: &HashMap<String, Vec<String>>,
+    events: &[Event],
+    pinned: &[String],
+    top_k: usize,
+    max_chars: usize,
+) -> TruthSelection {
+    let wanted: HashSet<String> = events.iter().flat_map(|e| terms(&e.topic)).collect();
+
+    let mut ranked: Vec<(usize, &String)> = org_truth
+        .iter()
+        .filter(|(id, _)| !pinned.contains(*id))
+        .filter_map(|(id, versions)| {
+            let latest = versions.last()?;
+            let have = terms(&format!("{} {}", id, latest));
+            let score = wanted.intersection(&have).count();
+            (score > 0).then_some((score, id))
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+    let candidates = pinned
+        .iter()
+        .filter(|id| org_truth.contains_key(*id))
+        .chain(ranked.into_iter().take(top_k).map(|(_, id)| id));
+
+    const MARKER: &str = "…[truncated]";
+    let mut selection = TruthSelection::default();
+    let mut budget = max_chars;
+    for id in candidates {
+        let Some(latest) = org_truth.get(id).and_then(|v| v.last()) else {
+            continue;
+        };
+        let id_len = id.chars().count();
+        let marker_len = MARKER.chars().count();
+        let content = if id_len + latest.chars().count() <= budget {
+            latest.clone()
+        } else if budget > id_len + marker_len {
+            let keep = budget - id_len - marker_len;
+            format!("{}{}", latest.chars().take(keep).collect::<String>(), MARKER)
+        } else {
+            selection.omitted += 1;
+            continue;
+        };
+        budget -= id_len + content.chars().count();
+        selection.entries.insert(id.clone(), json!(content));
+        selection.included.push(id.clone());
+    }
+    selection
+}
@@ -0,0 +1,80 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use utoipa::ToSchema;
+
+/// Chat completion tokens one component spent since startup.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ComponentUsage {
+    /// `employee_agent`, `org_brain`, `reviewer`, `triage` or `other`.
+    pub component: String,
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// Opening line of each prompt in this crate, and the component it is counted under.
+const COMPONENTS: &[(&str, &str)] = &[
+    ("You are an EmployeeAgent.", "employee_agent"),
+    ("You are the OrgBrain.", "org_brain"),
+    ("You are the Reviewer.", "reviewer"),
+    ("You triage corporate email.", "triage"),
+];
+
+static USAGE: Lazy<Mutex<BTreeMap<&'static str, ComponentUsage>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// The component a system prompt belongs to, recognised by its opening line like
+/// `llm::OfflineChat` does.
+pub fn component_for(system: &str) -> &'static str {
+    COMPONENTS
+        .iter()
+        .find(|(opening, _)| system.starts_with(opening))
+        .map(|(_, component)| *component)
+        .unwrap_or("other")
+}
+
+/// Counts one completion for the component `system` belongs to.
+pub fn record(system: &str, prompt_tokens: u64, completion_tokens: u64) {
+    let component = component_for(system);
+    let mut usage = USAGE.lock().unwrap_or_else(|e| e.into_inner());
+    let entry = usage.entry(component).or_insert_with(|| ComponentUsage {
+        component: component.to_string(),
+        ..Default::default()
+    });
+    entry.calls += 1;
+    entry.prompt_tokens += prompt_tokens;
+    entry.completion_tokens += completion_tokens;
+}
+
+/// Usage per component, by component name.
+pub fn snapshot() -> Vec<ComponentUsage> {
+    let usage = USAGE.lock().unwrap_or_else(|e| e.into_inner());
+    usage.values().cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompts_are_attributed_by_their_opening_line() {
+        assert_eq!(component_for("You are the Reviewer.\nYou critique..."), "reviewer");
+        assert_eq!(component_for("You are the OrgBrain.\n..."), "org_brain");
+        assert_eq!(component_for("You advise HR."), "other");
+    }
+
+    #[test]
+    fn the_reviewer_is_counted_apart_from_the_org_brain() {
+        record("You are the Reviewer.\n...", 120, 30);
+        record("You are the Reviewer.\n...", 80, 10);
+        let usage = snapshot();
+        let reviewer = usage.iter().find(|u| u.component == "reviewer").unwrap();
+        assert_eq!(
+            (reviewer.calls, reviewer.prompt_tokens, reviewer.completion_tokens),
+            (2, 200, 40)
+        );
+        assert!(usage.iter().all(|u| u.component != "org_brain"));
+    }
+}
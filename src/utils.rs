@@ -1,40 +1,72 @@
-use anyhow::Result;
-use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs};
-use async_openai::Client;
+use anyhow::{Context as _, Result};
 use reqwest::header;
 use rodio::{Decoder, OutputStream, Sink};
-use std::env;
+use std::future::Future;
 use std::io::Cursor;
+use std::time::Duration;
 
+use crate::app_state::config;
+use crate::config::SpeechConfig;
+use crate::error::CosError;
+
+/// `OPENAI_TIMEOUT_MS` (default 60000), for chat completions, embeddings and RAG search.
+pub fn openai_timeout() -> Duration {
+    Duration::from_millis(config().llm.timeout_ms)
+}
+
+/// `ELEVEN_TIMEOUT_MS` (default 30000), for speech-to-text and text-to-speech.
+pub fn eleven_timeout() -> Duration {
+    Duration::from_millis(config().speech.timeout_ms)
+}
+
+/// `NEO4J_TIMEOUT_MS` (default 10000), per graph call.
+pub fn neo4j_timeout() -> Duration {
+    Duration::from_millis(config().neo4j.timeout_ms)
+}
+
+/// The configured `ELEVEN_API_KEY`.
+fn eleven_api_key() -> Result<String> {
+    config().speech.api_key.clone().context("ELEVEN_API_KEY is not set")
+}
+
+/// Runs `call` for at most `limit`, failing with `CosError::Timeout(upstream)` after that. The
+/// call is dropped on expiry, so any lock guard it holds is released with it.
+pub async fn with_timeout<T, E>(
+    upstream: &'static str,
+    limit: Duration,
+    call: impl Future<Output = std::result::Result<T, E>>,
+) -> Result<T>
+where
+    E: Into<anyhow::Error>,
+{
+    match tokio::time::timeout(limit, call).await {
+        Ok(result) => result.map_err(Into::into),
+        Err(elapsed) => Err(anyhow::Error::new(elapsed).context(CosError::Timeout(upstream))),
+    }
+}
+
+/// Chat completion through the configured `ChatProvider` (OpenAI, or the offline stub).
+/// Logged to `COS_LLM_LOG_DIR` when set (see `llm_log`).
 pub async fn openai_chat(system: &str, user: &str) -> Result<String> {
-    let model = env::var("OPENAI_MODEL").unwrap_or_else(|_| "gpt-4o-mini".to_string());
-    let client = Client::new();
-
-    let system_msg: ChatCompletionRequestMessage = ChatCompletionRequestSystemMessageArgs::default()
-        .content(system)
-        .build()?
-        .into();
-    let user_msg: ChatCompletionRequestMessage = ChatCompletionRequestUserMessageArgs::default()
-        .content(user)
-        .build()?
-        .into();
-
-    let req = CreateChatCompletionRequestArgs::default()
-        .model(model)
-        .messages(vec![system_msg, user_msg])
-        .build()?;
-
-    let resp = client.chat().create(req).await?;
-    let content = resp
-        .choices
-        .first()
-        .and_then(|c| c.message.content.clone())
-        .unwrap_or_default();
-    Ok(content)
+    let started = std::time::Instant::now();
+    let chat = crate::llm::chat_provider().chat(system, user);
+    let out = with_timeout("openai", openai_timeout(), async {
+        chat.await.context(CosError::LlmUnavailable)
+    })
+    .await;
+    crate::llm_log::record(system, user, &out, started.elapsed().as_millis()).await;
+    out
 }
 
 pub async fn elevenlabs_stt_from_file(path: &str) -> Result<String> {
-    let api_key = env::var("ELEVEN_API_KEY")?;
+    with_timeout("elevenlabs", eleven_timeout(), async {
+        stt_from_file(path).await.context(CosError::SttFailed)
+    })
+    .await
+}
+
+async fn stt_from_file(path: &str) -> Result<String> {
+    let api_key = eleven_api_key()?;
     let client = reqwest::Client::new();
     let url = "https://api.elevenlabs.io/v1/speech-to-text";
 
@@ -64,7 +96,14 @@ pub async fn elevenlabs_stt_from_file(path: &str) -> Result<String> {
 }
 
 pub async fn elevenlabs_stt_from_bytes(data: Vec<u8>, mime: Option<&str>) -> Result<String> {
-    let api_key = env::var("ELEVEN_API_KEY")?;
+    with_timeout("elevenlabs", eleven_timeout(), async {
+        stt_from_bytes(data, mime).await.context(CosError::SttFailed)
+    })
+    .await
+}
+
+async fn stt_from_bytes(data: Vec<u8>, mime: Option<&str>) -> Result<String> {
+    let api_key = eleven_api_key()?;
     let client = reqwest::Client::new();
     let url = "https://api.elevenlabs.io/v1/speech-to-text";
 
@@ -95,10 +134,127 @@ pub async fn elevenlabs_stt_from_bytes(data: Vec<u8>, mime: Option<&str>) -> Res
         .to_string())
 }
 
+/// Hex SHA-256 of `text`, used to spot byte-identical knowledge versions.
+pub fn content_hash(text: &str) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Detected language of `text` as (ISO 639-3 code, English name), e.g. ("fra", "French").
+pub fn detect_language(text: &str) -> Option<(String, String)> {
+    let info = whatlang::detect(text)?;
+    if !info.is_reliable() && text.split_whitespace().count() > 3 {
+        return None;
+    }
+    Some((info.lang().code().to_string(), info.lang().eng_name().to_string()))
+}
+
 pub async fn elevenlabs_tts_to_mp3_bytes(text: &str) -> Result<Vec<u8>> {
-    let api_key = env::var("ELEVEN_API_KEY")?;
-    let voice_id = env::var("ELEVEN_VOICE_ID").unwrap_or_else(|_| "21m00Tcm4TlvDq8ikWAM".to_string());
-    let model_id = env::var("ELEVEN_TTS_MODEL").unwrap_or_else(|_| "eleven_multilingual_v2".to_string());
+    elevenlabs_tts_to_mp3_bytes_for_language(text, None).await
+}
+
+/// ElevenLabs `output_format` values and the MIME type of the audio each returns.
+pub const TTS_OUTPUT_FORMATS: &[(&str, &str)] = &[
+    ("mp3_22050_32", "audio/mpeg"),
+    ("mp3_44100_32", "audio/mpeg"),
+    ("mp3_44100_64", "audio/mpeg"),
+    ("mp3_44100_96", "audio/mpeg"),
+    ("mp3_44100_128", "audio/mpeg"),
+    ("mp3_44100_192", "audio/mpeg"),
+    ("pcm_8000", "audio/pcm"),
+    ("pcm_16000", "audio/pcm"),
+    ("pcm_22050", "audio/pcm"),
+    ("pcm_24000", "audio/pcm"),
+    ("pcm_44100", "audio/pcm"),
+    ("pcm_48000", "audio/pcm"),
+    ("ulaw_8000", "audio/basic"),
+    ("alaw_8000", "audio/x-alaw-basic"),
+    ("opus_48000_32", "audio/opus"),
+    ("opus_48000_64", "audio/opus"),
+    ("opus_48000_96", "audio/opus"),
+    ("opus_48000_128", "audio/opus"),
+    ("opus_48000_192", "audio/opus"),
+];
+
+/// MIME type of `output_format`, `None` if ElevenLabs doesn't accept it. No format means
+/// ElevenLabs' default MP3.
+pub fn tts_mime(output_format: Option<&str>) -> Option<&'static str> {
+    match output_format {
+        None => Some("audio/mpeg"),
+        Some(f) => TTS_OUTPUT_FORMATS
+            .iter()
+            .find(|(name, _)| *name == f)
+            .map(|(_, mime)| *mime),
+    }
+}
+
+/// TTS with optional per-language overrides: `ELEVEN_VOICE_ID_<LANG>` and
+/// `ELEVEN_TTS_MODEL_<LANG>` (LANG is the upper-case ISO 639-3 code, e.g. `FRA`).
+/// Non-English text falls back to the multilingual model when no override is set.
+pub async fn elevenlabs_tts_to_mp3_bytes_for_language(
+    text: &str,
+    language: Option<&str>,
+) -> Result<Vec<u8>> {
+    elevenlabs_tts_bytes(text, language, None).await
+}
+
+/// TTS in `output_format` (one of `TTS_OUTPUT_FORMATS`, default MP3), with the same per-language
+/// overrides as `elevenlabs_tts_to_mp3_bytes_for_language`.
+pub async fn elevenlabs_tts_bytes(
+    text: &str,
+    language: Option<&str>,
+    output_format: Option<&str>,
+) -> Result<Vec<u8>> {
+    let Some(mime) = tts_mime(output_format) else {
+        return Err(CosError::Validation(format!(
+            "unsupported TTS output_format {:?}",
+            output_format.unwrap_or_default()
+        ))
+        .into());
+    };
+    if crate::llm::offline_mode() {
+        // The stub only has silent MP3; other formats get no audio.
+        return Ok(if mime == "audio/mpeg" { crate::llm::silent_mp3() } else { Vec::new() });
+    }
+    with_timeout("elevenlabs", eleven_timeout(), async {
+        tts_bytes(text, language, output_format, mime)
+            .await
+            .context(CosError::TtsFailed)
+    })
+    .await
+}
+
+/// The ElevenLabs voice and model for `language` (ISO 639-3): its `ELEVEN_*_<LANG>` overrides,
+/// else the defaults, with the multilingual model for non-English text when the default model
+/// isn't multilingual.
+fn tts_voice_and_model(speech: &SpeechConfig, language: Option<&str>) -> (String, String) {
+    let lang_suffix = language.map(|l| l.trim().to_uppercase()).filter(|l| !l.is_empty());
+    let lang_model = lang_suffix.as_ref().and_then(|l| speech.tts_model_by_language.get(l));
+
+    let voice_id = lang_suffix
+        .as_ref()
+        .and_then(|l| speech.voice_id_by_language.get(l))
+        .unwrap_or(&speech.voice_id)
+        .clone();
+    let mut model_id = lang_model.unwrap_or(&speech.tts_model).clone();
+    let non_english = lang_suffix.as_deref().map(|l| l != "ENG").unwrap_or(false);
+    if non_english && !model_id.contains("multilingual") && lang_model.is_none() {
+        model_id = "eleven_multilingual_v2".to_string();
+    }
+    (voice_id, model_id)
+}
+
+async fn tts_bytes(
+    text: &str,
+    language: Option<&str>,
+    output_format: Option<&str>,
+    mime: &str,
+) -> Result<Vec<u8>> {
+    let api_key = eleven_api_key()?;
+    let (voice_id, model_id) = tts_voice_and_model(&config().speech, language);
 
     let url = format!(
         "https://api.elevenlabs.io/v1/text-to-speech/{}",
@@ -115,10 +271,13 @@ pub async fn elevenlabs_tts_to_mp3_bytes(text: &str) -> Result<Vec<u8>> {
     });
 
     let client = reqwest::Client::new();
-    let bytes = client
-        .post(url)
+    let mut request = client.post(url);
+    if let Some(format) = output_format {
+        request = request.query(&[("output_format", format)]);
+    }
+    let bytes = request
         .header("xi-api-key", api_key)
-        .header(header::ACCEPT, "audio/mpeg")
+        .header(header::ACCEPT, mime)
         .json(&body)
         .send()
         .await?
@@ -138,3 +297,50 @@ pub fn play_mp3_bytes(mp3: &[u8]) -> Result<()> {
     sink.sleep_until_end();
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CosConfig;
+
+    #[test]
+    fn detects_the_language_of_the_latest_message() {
+        let fr = detect_language(
+            "Bonjour, nous avons décidé de geler toutes les embauches dans l'équipe \
+             d'ingénierie jusqu'à la fin du trimestre prochain, car le budget est très serré.",
+        );
+        assert_eq!(fr, Some(("fra".to_string(), "French".to_string())));
+        let en = detect_language(
+            "Hello, we decided to freeze all hiring in the engineering team until the end of \
+             next quarter, because the budget is very tight.",
+        );
+        assert_eq!(en.map(|(code, _)| code).as_deref(), Some("eng"));
+        assert_eq!(detect_language(""), None);
+    }
+
+    #[test]
+    fn tts_uses_language_overrides_then_the_multilingual_model() {
+        let mut speech = CosConfig::default().speech;
+        speech.tts_model = "eleven_monolingual_v1".to_string();
+        speech.voice_id_by_language.insert("FRA".to_string(), "voice-fr".to_string());
+        speech.tts_model_by_language.insert("DEU".to_string(), "model-de".to_string());
+
+        let (voice, model) = tts_voice_and_model(&speech, Some("fra"));
+        assert_eq!((voice.as_str(), model.as_str()), ("voice-fr", "eleven_multilingual_v2"));
+        let (voice, model) = tts_voice_and_model(&speech, Some("deu"));
+        assert_eq!((voice, model.as_str()), (speech.voice_id.clone(), "model-de"));
+        let (_, model) = tts_voice_and_model(&speech, Some("eng"));
+        assert_eq!(model, "eleven_monolingual_v1");
+        let (_, model) = tts_voice_and_model(&speech, None);
+        assert_eq!(model, "eleven_monolingual_v1");
+    }
+
+    #[test]
+    fn tts_formats_map_to_their_mime_type() {
+        assert_eq!(tts_mime(None), Some("audio/mpeg"));
+        assert_eq!(tts_mime(Some("mp3_22050_32")), Some("audio/mpeg"));
+        assert_eq!(tts_mime(Some("pcm_16000")), Some("audio/pcm"));
+        assert_eq!(tts_mime(Some("ulaw_8000")), Some("audio/basic"));
+        assert_eq!(tts_mime(Some("wav_44100")), None);
+    }
+}
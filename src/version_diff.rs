@@ -0,0 +1,74 @@
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// Decision version fields compared by the diff endpoint.
+pub const DECISION_DIFF_FIELDS: &[&str] = &[
+    "summary",
+    "confidence",
+    "routing_agents",
+    "trigger_events",
+    "agents_involved",
+    "rationale",
+    "evidence",
+    "assumptions",
+];
+
+/// How one property differs between two versions. Scalars report `from`/`to`; arrays report the
+/// elements `added` and `removed`, ignoring order.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct FieldChange {
+    pub field: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub added: Vec<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<Value>,
+}
+
+/// Compares `fields` of two JSON objects; unchanged fields are left out. A missing field counts
+/// as `null` (or as an empty array when the other side is an array).
+pub fn diff_properties(from: &Value, to: &Value, fields: &[&str]) -> Vec<FieldChange> {
+    let mut out = Vec::new();
+    for field in fields {
+        let a = from.get(*field).unwrap_or(&Value::Null);
+        let b = to.get(*field).unwrap_or(&Value::Null);
+        if a == b {
+            continue;
+        }
+        let as_list = |v: &Value| match v {
+            Value::Array(items) => Some(items.clone()),
+            Value::Null => Some(Vec::new()),
+            _ => None,
+        };
+        let change = match (as_list(a), as_list(b)) {
+            (Some(xs), Some(ys)) if a.is_array() || b.is_array() => {
+                let added: Vec<Value> = ys.iter().filter(|y| !xs.contains(y)).cloned().collect();
+                let removed: Vec<Value> = xs.iter().filter(|x| !ys.contains(x)).cloned().collect();
+                if added.is_empty() && removed.is_empty() {
+                    // Same elements, different order.
+                    continue;
+                }
+                FieldChange {
+                    field: field.to_string(),
+                    from: None,
+                    to: None,
+                    added,
+                    removed,
+                }
+            }
+            _ => FieldChange {
+                field: field.to_string(),
+                from: Some(a.clone()),
+                to: Some(b.clone()),
+                added: Vec::new(),
+                removed: Vec::new(),
+            },
+        };
+        out.push(change);
+    }
+    out
+}
@@ -0,0 +1,335 @@
+use anyhow::{bail, Context as _, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::config::VisibilityConfig;
+use crate::domain::EmployeeRole;
+
+/// The levels a trace can be routed at, most to least visible.
+pub const VISIBILITY_LEVELS: [&str; 3] = ["full", "summary", "none"];
+
+/// Whole words that give HR `summary` visibility without a routing entry.
+const HR_TOPIC_WORDS: &[&str] = &["hr"];
+/// Word starts that give HR `summary` visibility (`hiring`, `policies`, ...).
+const HR_TOPIC_PREFIX: &str = r"\b(people|hiring|polic|compensation|performance)";
+/// Whole words that give engineers `summary` visibility without a routing entry.
+const ENGINEER_TOPIC_WORDS: &[&str] = &["eng"];
+/// Word starts that give engineers `summary` visibility (`engineering`, `technical`, ...).
+const ENGINEER_TOPIC_PREFIX: &str = r"\b(engineer|tech|product|reliab|infra)";
+
+/// How a rule matches a trace topic (trimmed and lowercased).
+#[derive(Debug, Clone)]
+pub enum TopicMatch {
+    /// One of the topic's tags equals this. Tags are the topic split on `,`, `/` and `;`.
+    Tag(String),
+    /// This appears in the topic as a whole word: `eng` matches `eng roadmap`, not `england`.
+    Word(String),
+    /// This appears anywhere in the topic.
+    Substring(String),
+    Regex(Regex),
+}
+
+impl TopicMatch {
+    pub fn matches(&self, topic: &str) -> bool {
+        match self {
+            Self::Tag(tag) => topic.split([',', '/', ';']).any(|t| t.trim() == tag),
+            Self::Word(word) => topic.match_indices(word.as_str()).any(|(i, m)| {
+                let before = topic[..i].chars().next_back();
+                let after = topic[i + m.len()..].chars().next();
+                !before.is_some_and(char::is_alphanumeric)
+                    && !after.is_some_and(char::is_alphanumeric)
+            }),
+            Self::Substring(s) => topic.contains(s.as_str()),
+            Self::Regex(re) => re.is_match(topic),
+        }
+    }
+
+    /// The configured tag, word, substring or regex, reported as `matched_keyword`.
+    pub fn pattern(&self) -> &str {
+        match self {
+            Self::Tag(s) | Self::Word(s) | Self::Substring(s) => s,
+            Self::Regex(re) => re.as_str(),
+        }
+    }
+}
+
+/// One ordered rule of a role: topics it matches get `level`.
+#[derive(Debug, Clone)]
+pub struct TopicRule {
+    pub matcher: TopicMatch,
+    pub level: String,
+}
+
+/// How one role sees traces that have no routing entry for it.
+#[derive(Debug, Clone)]
+pub struct RoleVisibility {
+    pub default_level: String,
+    /// Evaluated in order; the first rule that matches decides.
+    pub rules: Vec<TopicRule>,
+}
+
+impl RoleVisibility {
+    fn builtin(summary_words: &[&str], summary_prefix: &str) -> Self {
+        let summary = |matcher| TopicRule { matcher, level: "summary".to_string() };
+        let mut rules: Vec<TopicRule> = summary_words
+            .iter()
+            .map(|w| summary(TopicMatch::Word(w.to_string())))
+            .collect();
+        rules.push(summary(TopicMatch::Regex(
+            Regex::new(summary_prefix).expect("built-in visibility regex"),
+        )));
+        Self { default_level: "none".to_string(), rules }
+    }
+}
+
+/// A role as written in `COS_VISIBILITY_RULES`.
+#[derive(Debug, Deserialize)]
+struct RoleConfig {
+    default_level: String,
+    #[serde(default)]
+    rules: Vec<RuleConfig>,
+    /// Topic substring -> level, tried after `rules`, longest keyword first.
+    #[serde(default)]
+    keywords: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleConfig {
+    #[serde(flatten)]
+    matcher: MatcherConfig,
+    level: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum MatcherConfig {
+    Tag(String),
+    Word(String),
+    Substring(String),
+    Regex(String),
+}
+
+impl MatcherConfig {
+    fn build(self) -> Result<TopicMatch> {
+        let normalized = |s: String| -> Result<String> {
+            let s = s.trim().to_lowercase();
+            if s.is_empty() {
+                bail!("empty pattern");
+            }
+            Ok(s)
+        };
+        Ok(match self {
+            Self::Tag(s) => TopicMatch::Tag(normalized(s)?),
+            Self::Word(s) => TopicMatch::Word(normalized(s)?),
+            Self::Substring(s) => TopicMatch::Substring(normalized(s)?),
+            Self::Regex(s) => {
+                TopicMatch::Regex(Regex::new(&s).with_context(|| format!("regex `{s}`"))?)
+            }
+        })
+    }
+}
+
+impl RoleConfig {
+    fn build(self) -> Result<RoleVisibility> {
+        check_level(&self.default_level).context("default_level")?;
+        let mut rules = Vec::new();
+        for (i, rule) in self.rules.into_iter().enumerate() {
+            check_level(&rule.level).with_context(|| format!("rule {}", i + 1))?;
+            let matcher = rule.matcher.build().with_context(|| format!("rule {}", i + 1))?;
+            rules.push(TopicRule { matcher, level: rule.level });
+        }
+        let mut keywords: Vec<(String, String)> = Vec::new();
+        for (keyword, level) in self.keywords {
+            let keyword = keyword.trim().to_lowercase();
+            if keyword.is_empty() {
+                bail!("empty keyword");
+            }
+            check_level(&level).with_context(|| format!("keyword `{keyword}`"))?;
+            keywords.push((keyword, level));
+        }
+        keywords.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.0.cmp(&b.0)));
+        rules.extend(keywords.into_iter().map(|(keyword, level)| TopicRule {
+            matcher: TopicMatch::Substring(keyword),
+            level,
+        }));
+        Ok(RoleVisibility { default_level: self.default_level, rules })
+    }
+}
+
+/// Role defaults consulted by `api::visibility_for_agent`, from `COS_VISIBILITY_RULES` or the
+/// built-in lists. A role the config leaves out keeps its built-in rule.
+#[derive(Debug, Clone)]
+pub struct VisibilityRules {
+    roles: HashMap<EmployeeRole, RoleVisibility>,
+    /// `COS_VISIBILITY_DEFAULT=deny`: only a matching rule grants access (the CEO keeps `full`).
+    default_deny: bool,
+}
+
+impl Default for VisibilityRules {
+    fn default() -> Self {
+        Self {
+            roles: HashMap::from([
+                (
+                    EmployeeRole::Ceo,
+                    RoleVisibility { default_level: "full".to_string(), rules: Vec::new() },
+                ),
+                (EmployeeRole::Hr, RoleVisibility::builtin(HR_TOPIC_WORDS, HR_TOPIC_PREFIX)),
+                (
+                    EmployeeRole::Engineer,
+                    RoleVisibility::builtin(ENGINEER_TOPIC_WORDS, ENGINEER_TOPIC_PREFIX),
+                ),
+            ]),
+            default_deny: false,
+        }
+    }
+}
+
+impl VisibilityRules {
+    /// Parses a role -> rule map, JSON when `toml` is false:
+    ///
+    /// ```json
+    /// { "engineer": { "default_level": "none", "rules": [
+    ///     { "tag": "oncall", "level": "full" },
+    ///     { "word": "eng", "level": "summary" },
+    ///     { "regex": "^infra-", "level": "summary" }
+    /// ] } }
+    /// ```
+    ///
+    /// `keywords` (substring -> level) is still accepted and tried after `rules`. Every level
+    /// must be one of `VISIBILITY_LEVELS`.
+    pub fn parse(raw: &str, toml: bool) -> Result<Self> {
+        let configured: HashMap<String, RoleConfig> = if toml {
+            toml::from_str(raw).context("invalid visibility rules")?
+        } else {
+            serde_json::from_str(raw).context("invalid visibility rules")?
+        };
+
+        let mut rules = Self::default();
+        for (name, config) in configured {
+            let Some(role) = EmployeeRole::parse(&name) else {
+                bail!("unknown role `{name}` (expected ceo, hr or engineer)");
+            };
+            rules.roles.insert(role, config.build().with_context(|| name.clone())?);
+        }
+        Ok(rules)
+    }
+
+    /// `COS_VISIBILITY_RULES`: inline JSON, or the path of a `.json` or `.toml` file. Unset
+    /// keeps the built-in rules. `COS_VISIBILITY_DEFAULT` is `allow` (default) or `deny`.
+    pub fn from_config(config: &VisibilityConfig) -> Result<Self> {
+        let rules = match &config.rules {
+            Some(raw) => Self::rules_from(raw)?,
+            None => Self::default(),
+        };
+        Ok(rules.with_default_deny(config.default == "deny"))
+    }
+
+    /// These rules with `COS_VISIBILITY_DEFAULT=deny` (`true`) or `allow` behaviour.
+    pub fn with_default_deny(mut self, default_deny: bool) -> Self {
+        self.default_deny = default_deny;
+        self
+    }
+
+    fn rules_from(raw: &str) -> Result<Self> {
+        if raw.starts_with('{') {
+            return Self::parse(raw, false).context("COS_VISIBILITY_RULES");
+        }
+        let path = Path::new(raw);
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("read {}", path.display()))?;
+        let toml = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("toml"));
+        Self::parse(&content, toml).with_context(|| path.display().to_string())
+    }
+
+    pub fn default_deny(&self) -> bool {
+        self.default_deny
+    }
+
+    /// `role`'s level on `topic`, with the pattern of the rule that decided it if one did.
+    /// Without a matching rule, deny mode gives `none` (`full` for the CEO) instead of the
+    /// role's `default_level`.
+    pub fn role_default(&self, role: &EmployeeRole, topic: &str) -> (&str, Option<&str>) {
+        let fallback = match (self.default_deny, role) {
+            (true, EmployeeRole::Ceo) => Some("full"),
+            (true, _) => Some("none"),
+            (false, _) => None,
+        };
+        let Some(rule_set) = self.roles.get(role) else {
+            return (fallback.unwrap_or("none"), None);
+        };
+        let t = topic.trim().to_lowercase();
+        match rule_set.rules.iter().find(|r| r.matcher.matches(&t)) {
+            Some(rule) => (rule.level.as_str(), Some(rule.matcher.pattern())),
+            None => (fallback.unwrap_or(rule_set.default_level.as_str()), None),
+        }
+    }
+}
+
+fn check_level(level: &str) -> Result<()> {
+    if !VISIBILITY_LEVELS.contains(&level) {
+        bail!(
+            "invalid level `{level}` (expected one of {})",
+            VISIBILITY_LEVELS.join(", ")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_rules_match_whole_words_and_word_starts() {
+        let rules = VisibilityRules::default();
+        let eng = |topic| rules.role_default(&EmployeeRole::Engineer, topic);
+        assert_eq!(eng("eng roadmap"), ("summary", Some("eng")));
+        assert_eq!(eng("Infrastructure budget").0, "summary");
+        assert_eq!(eng("england offsite"), ("none", None));
+        assert_eq!(rules.role_default(&EmployeeRole::Hr, "Hiring freeze").0, "summary");
+        assert_eq!(rules.role_default(&EmployeeRole::Ceo, "anything"), ("full", None));
+    }
+
+    #[test]
+    fn configured_rules_apply_in_order_before_keywords() {
+        let rules = VisibilityRules::parse(
+            r#"{ "engineer": { "default_level": "summary",
+                   "rules": [ { "tag": "oncall", "level": "full" },
+                              { "regex": "^secret-", "level": "none" } ],
+                   "keywords": { "sec": "full", "security": "none" } } }"#,
+            false,
+        )
+        .unwrap();
+        let eng = |topic| rules.role_default(&EmployeeRole::Engineer, topic);
+        assert_eq!(eng("infra / OnCall"), ("full", Some("oncall")));
+        assert_eq!(eng("secret-project"), ("none", Some("^secret-")));
+        assert_eq!(eng("security review"), ("none", Some("security")), "longest keyword first");
+        assert_eq!(eng("second hand"), ("full", Some("sec")));
+        assert_eq!(eng("lunch"), ("summary", None));
+        // Roles left out of the config keep the built-in rules.
+        assert_eq!(rules.role_default(&EmployeeRole::Hr, "hiring plan").0, "summary");
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        let err = |raw: &str| format!("{:#}", VisibilityRules::parse(raw, false).unwrap_err());
+        assert!(err(r#"{ "intern": { "default_level": "none" } }"#).contains("unknown role"));
+        assert!(err(r#"{ "hr": { "default_level": "some" } }"#).contains("invalid level"));
+        let bad_regex = r#"{ "hr": { "default_level": "none",
+                                     "rules": [ { "regex": "(", "level": "full" } ] } }"#;
+        assert!(err(bad_regex).contains("rule 1"));
+        let toml = "[hr]\ndefault_level = \"full\"\n";
+        let rules = VisibilityRules::parse(toml, true).unwrap();
+        assert_eq!(rules.role_default(&EmployeeRole::Hr, "lunch"), ("full", None));
+    }
+
+    #[test]
+    fn deny_mode_only_grants_access_through_a_rule() {
+        let rules = VisibilityRules::default().with_default_deny(true);
+        assert_eq!(rules.role_default(&EmployeeRole::Hr, "lunch"), ("none", None));
+        assert_eq!(rules.role_default(&EmployeeRole::Hr, "hiring plan").0, "summary");
+        assert_eq!(rules.role_default(&EmployeeRole::Ceo, "lunch"), ("full", None));
+    }
+}
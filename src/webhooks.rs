@@ -0,0 +1,353 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::api::{canonical_agent_id, trace_visible_to, ServerEvent};
+use crate::app_state::{config, handles};
+use crate::config::WebhooksConfig;
+
+/// Header carrying `sha256=<hex HMAC-SHA256 of the body>` under `COS_WEBHOOK_SECRET`.
+pub const SIGNATURE_HEADER: &str = "x-cos-signature";
+/// Header carrying a per-event id, the same on every retry, so receivers can drop duplicates.
+pub const DELIVERY_HEADER: &str = "x-cos-delivery";
+
+static DELIVERED: AtomicU64 = AtomicU64::new(0);
+static FAILED: AtomicU64 = AtomicU64::new(0);
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// Webhook counters since startup: (delivered, failed after all retries, dropped on a full
+/// queue).
+pub fn stats() -> (u64, u64, u64) {
+    (
+        DELIVERED.load(Ordering::Relaxed),
+        FAILED.load(Ordering::Relaxed),
+        DROPPED.load(Ordering::Relaxed),
+    )
+}
+
+/// Outbound trace webhooks.
+///
+/// - `COS_WEBHOOK_URLS`: comma-separated endpoints; unset disables webhooks.
+/// - `COS_WEBHOOK_SECRET`: shared HMAC key (required).
+/// - `COS_WEBHOOK_RECIPIENT`: employee name or agent id whose visibility traces are cut to
+///   (required).
+/// - `COS_WEBHOOK_QUEUE` (default 256): events buffered per endpoint before new ones are dropped.
+/// - `COS_WEBHOOK_MAX_ATTEMPTS` (default 5), `COS_WEBHOOK_BACKOFF_MS` (default 1000, doubled
+///   per retry, at most 60 s), `COS_WEBHOOK_TIMEOUT_MS` (default 10000).
+struct WebhookSettings {
+    urls: Vec<String>,
+    secret: String,
+    recipient: String,
+    queue: usize,
+    max_attempts: u64,
+    backoff_ms: u64,
+    timeout: Duration,
+}
+
+impl WebhookSettings {
+    /// `None` when no endpoint is configured. `CosConfig::validate` has already checked the URLs
+    /// and that the secret and recipient are set.
+    fn from_config(config: &WebhooksConfig) -> Option<Self> {
+        if config.urls.is_empty() {
+            return None;
+        }
+        Some(Self {
+            urls: config.urls.clone(),
+            secret: config.secret.clone()?,
+            recipient: config.recipient.clone()?,
+            queue: config.queue.max(1),
+            max_attempts: config.max_attempts.max(1),
+            backoff_ms: config.backoff_ms,
+            timeout: Duration::from_millis(config.timeout_ms.max(1)),
+        })
+    }
+}
+
+/// `sha256=<hex>` HMAC-SHA256 of `body` under `secret`, the value of `SIGNATURE_HEADER`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("sha256={hex}")
+}
+
+/// One signed event waiting for delivery.
+#[derive(Clone)]
+struct Delivery {
+    id: String,
+    body: Arc<Vec<u8>>,
+    signature: Arc<String>,
+}
+
+/// Starts webhook delivery when `COS_WEBHOOK_URLS` is set: every new trace on `events_tx` is cut
+/// to the recipient's visibility (skipped when it sees nothing) and POSTed as
+/// `{"type":"trace","data":{..}}`, the SSE payload, to each endpoint. Each endpoint has its own
+/// bounded queue and worker, so a slow or failing one doesn't hold up the others.
+pub fn spawn_dispatcher(events_tx: &broadcast::Sender<ServerEvent>) {
+    let Some(settings) = WebhookSettings::from_config(&config().webhooks) else {
+        return;
+    };
+    // Subscribe before spawning so traces sent while the recipient resolves aren't missed.
+    let mut rx = events_tx.subscribe();
+    let client = match reqwest::Client::builder().timeout(settings.timeout).build() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("webhooks disabled: {e}");
+            return;
+        }
+    };
+
+    let settings = Arc::new(settings);
+    let queues: Vec<(String, mpsc::Sender<Delivery>)> = settings
+        .urls
+        .iter()
+        .map(|url| {
+            let (tx, rx) = mpsc::channel(settings.queue);
+            tokio::spawn(endpoint_worker(client.clone(), url.clone(), settings.clone(), rx));
+            (url.clone(), tx)
+        })
+        .collect();
+    println!("webhooks: delivering traces to {} endpoint(s)", queues.len());
+
+    tokio::spawn(async move {
+        let recipient = match crate::repl::agent_id_for(&settings.recipient) {
+            Some(aid) => canonical_agent_id(aid).await,
+            None => {
+                eprintln!(
+                    "webhooks disabled: COS_WEBHOOK_RECIPIENT {:?} is not an employee",
+                    settings.recipient
+                );
+                return;
+            }
+        };
+        loop {
+            let trace = match rx.recv().await {
+                Ok(ServerEvent::Trace(t)) => t,
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    DROPPED.fetch_add(missed * queues.len() as u64, Ordering::Relaxed);
+                    eprintln!("webhooks: fell behind, {missed} events not delivered");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let rules = handles().visibility_rules;
+            let Some(visible) = trace_visible_to(&rules, &trace, &recipient) else {
+                continue;
+            };
+            let body = match serde_json::to_vec(&ServerEvent::trace(visible)) {
+                Ok(b) => b,
+                Err(e) => {
+                    eprintln!("webhooks: serialize trace {}: {e}", trace.decision_id);
+                    continue;
+                }
+            };
+            let delivery = Delivery {
+                id: uuid::Uuid::new_v4().to_string(),
+                signature: Arc::new(sign(&settings.secret, &body)),
+                body: Arc::new(body),
+            };
+            for (url, tx) in &queues {
+                if tx.try_send(delivery.clone()).is_err() {
+                    DROPPED.fetch_add(1, Ordering::Relaxed);
+                    eprintln!(
+                        "webhooks: queue for {url} is full, trace {} dropped",
+                        trace.decision_id
+                    );
+                }
+            }
+        }
+    });
+}
+
+async fn endpoint_worker(
+    client: reqwest::Client,
+    url: String,
+    settings: Arc<WebhookSettings>,
+    mut rx: mpsc::Receiver<Delivery>,
+) {
+    while let Some(delivery) = rx.recv().await {
+        let mut attempt = 1;
+        loop {
+            match post(&client, &url, &delivery).await {
+                Ok(()) => {
+                    DELIVERED.fetch_add(1, Ordering::Relaxed);
+                    break;
+                }
+                Err((e, retryable)) => {
+                    if !retryable || attempt >= settings.max_attempts {
+                        FAILED.fetch_add(1, Ordering::Relaxed);
+                        eprintln!(
+                            "webhooks: delivery {} to {url} failed after {attempt} attempt(s): {e}",
+                            delivery.id
+                        );
+                        break;
+                    }
+                    let delay_ms = retry_delay_ms(settings.backoff_ms, attempt);
+                    tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Wait before the retry after failed `attempt` (1-based): `backoff_ms` doubled per earlier
+/// attempt, at most 60 s.
+fn retry_delay_ms(backoff_ms: u64, attempt: u64) -> u64 {
+    backoff_ms
+        .saturating_mul(1u64 << attempt.saturating_sub(1).min(16))
+        .min(60_000)
+}
+
+/// Whether a delivery answered with `status` is worth retrying.
+fn retryable(status: reqwest::StatusCode) -> bool {
+    status.is_server_error()
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+/// POSTs one delivery. On failure, whether it is worth retrying: network errors, timeouts,
+/// 408, 429 and 5xx are; other statuses mean the receiver rejected it.
+async fn post(
+    client: &reqwest::Client,
+    url: &str,
+    delivery: &Delivery,
+) -> Result<(), (String, bool)> {
+    let res = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, delivery.signature.as_str())
+        .header(DELIVERY_HEADER, &delivery.id)
+        .body(delivery.body.as_ref().clone())
+        .send()
+        .await
+        .map_err(|e| (e.to_string(), true))?;
+    let status = res.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    Err((format!("HTTP {status}"), retryable(status)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+    use std::sync::Mutex;
+
+    #[test]
+    fn bodies_are_signed_with_hmac_sha256() {
+        assert_eq!(
+            sign("key", b"The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn only_transient_failures_are_retried_with_capped_backoff() {
+        for status in [408, 429, 500, 503] {
+            assert!(retryable(reqwest::StatusCode::from_u16(status).unwrap()), "{status}");
+        }
+        for status in [400, 401, 404, 410] {
+            assert!(!retryable(reqwest::StatusCode::from_u16(status).unwrap()), "{status}");
+        }
+        assert_eq!(
+            [1, 2, 3].map(|attempt| retry_delay_ms(1000, attempt)),
+            [1000, 2000, 4000]
+        );
+        assert_eq!(retry_delay_ms(1000, 40), 60_000);
+    }
+
+    #[test]
+    fn settings_need_an_endpoint() {
+        let mut config = WebhooksConfig {
+            secret: Some("s".to_string()),
+            recipient: Some("ceo".to_string()),
+            ..crate::config::CosConfig::default().webhooks
+        };
+        assert!(WebhookSettings::from_config(&config).is_none());
+        config.urls = vec!["https://a.example/hook".to_string()];
+        config.queue = 0;
+        let settings = WebhookSettings::from_config(&config).unwrap();
+        assert_eq!(settings.queue, 1);
+    }
+
+    type Hits = Arc<Mutex<Vec<HeaderMap>>>;
+
+    /// 503 on the first call, 200 afterwards.
+    async fn flaky(State(hits): State<Hits>, headers: HeaderMap) -> StatusCode {
+        let mut hits = hits.lock().unwrap();
+        hits.push(headers);
+        if hits.len() == 1 {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    async fn rejecting(State(hits): State<Hits>, headers: HeaderMap) -> StatusCode {
+        hits.lock().unwrap().push(headers);
+        StatusCode::BAD_REQUEST
+    }
+
+    #[tokio::test]
+    async fn deliveries_retry_transient_failures_with_the_same_id() {
+        let (flaky_hits, rejected_hits) = (Hits::default(), Hits::default());
+        let app = Router::new()
+            .route("/flaky", post(flaky).with_state(flaky_hits.clone()))
+            .route("/reject", post(rejecting).with_state(rejected_hits.clone()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let settings = Arc::new(WebhookSettings {
+            urls: Vec::new(),
+            secret: "s".to_string(),
+            recipient: "ceo".to_string(),
+            queue: 4,
+            max_attempts: 3,
+            backoff_ms: 1,
+            timeout: Duration::from_secs(5),
+        });
+        let body = br#"{"type":"trace"}"#.to_vec();
+        let delivery = Delivery {
+            id: "delivery-1".to_string(),
+            signature: Arc::new(sign(&settings.secret, &body)),
+            body: Arc::new(body),
+        };
+        let mut workers = Vec::new();
+        for path in ["flaky", "reject"] {
+            let (tx, rx) = mpsc::channel(settings.queue);
+            tx.send(delivery.clone()).await.unwrap();
+            drop(tx);
+            let url = format!("http://{addr}/{path}");
+            let worker = endpoint_worker(reqwest::Client::new(), url, settings.clone(), rx);
+            workers.push(tokio::spawn(worker));
+        }
+        for worker in workers {
+            worker.await.unwrap();
+        }
+
+        let flaky_hits = flaky_hits.lock().unwrap();
+        assert_eq!(flaky_hits.len(), 2);
+        for headers in flaky_hits.iter() {
+            assert_eq!(headers[DELIVERY_HEADER], "delivery-1");
+            assert_eq!(headers[SIGNATURE_HEADER], delivery.signature.as_str());
+        }
+        // A rejection isn't retried.
+        assert_eq!(rejected_hits.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,948 @@
+//! The HTTP API driven in-process: memory storage, a scripted chat provider and
+//! `tower::ServiceExt::oneshot` against `api::app`. The headless script runner shares the same
+//! setup, so it is tested here too. Run with `cargo test --test api`.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use tokio::sync::{Mutex, OnceCell};
+use tower::ServiceExt;
+
+use pocketflow_template_rust::api::{self, ApiState, ServerEvent};
+use pocketflow_template_rust::app_state::{self, APP_STATE};
+use pocketflow_template_rust::config::CosConfig;
+use pocketflow_template_rust::domain::{EmployeeAgentId, Event, EventType};
+use pocketflow_template_rust::error::CosError;
+use pocketflow_template_rust::llm::{self, ChatProvider};
+use pocketflow_template_rust::{script, service, triage};
+
+/// Answers the employee, OrgBrain and reviewer prompts with fixed JSON. The employee's topic is
+/// the last `topic-*` word of its prompt (the current message comes after prior turns), and the
+/// decision id is derived from the newest event's topic, so each test can find its own decision.
+/// On `topic-orgbrain-down` the OrgBrain fails; on `topic-truth` it also updates a truth; on
+/// `topic-low-confidence` the employee signals it with confidence 0.1. The sources of any
+/// context documents are cited as evidence. Clusters are always labelled "Budget planning".
+struct ScriptedChat;
+
+fn marker(prompt: &str) -> String {
+    prompt
+        .split(|c: char| !(c.is_alphanumeric() || c == '-'))
+        .rev()
+        .find(|w| w.starts_with("topic-"))
+        .unwrap_or("topic-none")
+        .to_string()
+}
+
+fn newest_event_topic(org_user: &str) -> String {
+    let prompt: Value = serde_json::from_str(org_user).unwrap_or_default();
+    prompt["events"]
+        .as_array()
+        .and_then(|events| events.last())
+        .and_then(|e| e["topic"].as_str())
+        .unwrap_or("topic-none")
+        .to_string()
+}
+
+#[async_trait]
+impl ChatProvider for ScriptedChat {
+    async fn chat(&self, system: &str, user: &str) -> Result<String> {
+        let out = if system.starts_with("You are an EmployeeAgent.") {
+            let topic = marker(user);
+            let event_type = if topic.starts_with("topic-clarify") {
+                "clarification"
+            } else {
+                "decision_signal"
+            };
+            let confidence = if topic == "topic-low-confidence" { 0.1 } else { 0.9 };
+            json!({
+                "event_type": event_type,
+                "topic": topic,
+                "confidence": confidence,
+                "private_note": "scripted"
+            })
+        } else if system.starts_with("You are the OrgBrain.") {
+            let topic = newest_event_topic(user);
+            if topic == "topic-orgbrain-down" {
+                anyhow::bail!("scripted OrgBrain outage");
+            }
+            let org_updates = if topic == "topic-truth" {
+                json!({ "scripted-truth": "Scripted truth" })
+            } else {
+                json!({})
+            };
+            let mut evidence = vec![json!("board minutes")];
+            let prompt: Value = serde_json::from_str(user).unwrap_or_default();
+            if let Some(documents) = prompt["context_documents"].as_array() {
+                evidence.extend(documents.iter().map(|d| d["source"].clone()));
+            }
+            // Asked for whenever the topic mentions it; only clarification events get one.
+            let clarify = topic.contains("clarify");
+            json!({
+                "needs_clarification": clarify,
+                "clarifying_question": if clarify { "Which budget?" } else { "" },
+                "decision_id": format!("decision-{topic}"),
+                "decision": "scripted decision",
+                "summary": format!("Decision on {topic}"),
+                "rationale": "Scripted rationale.",
+                "evidence": evidence,
+                "assumptions": ["budget holds"],
+                "response_text": format!("Decided on {topic}."),
+                "confidence": 0.9,
+                "routing": {
+                    "employee_john": "full",
+                    "employee_sarah": "summary",
+                    "employee_bob": "none"
+                },
+                "org_updates": org_updates
+            })
+        } else if system.starts_with("You are the Reviewer.") {
+            json!({ "verdict": "approve", "notes": [], "confidence": 0.9 })
+        } else if system.starts_with("You name clusters") {
+            json!({
+                "label": " \"Budget planning.\" ",
+                "description": format!("{} messages about budgets.", user.lines().count())
+            })
+        } else {
+            return Ok(user.chars().take(200).collect());
+        };
+        Ok(out.to_string())
+    }
+}
+
+static INIT: OnceCell<()> = OnceCell::const_new();
+/// Asks share the process-wide event queue, so they run one at a time.
+static ASKS: Mutex<()> = Mutex::const_new(());
+
+async fn app() -> Router {
+    app_with(ApiState::new(None, 16)).await
+}
+
+async fn app_with(state: ApiState) -> Router {
+    INIT.get_or_init(|| async {
+        let mut config = CosConfig::default();
+        // No embeddings and silent TTS; chat goes to ScriptedChat.
+        config.llm.offline = true;
+        app_state::set_config(config);
+        llm::set_chat_provider(Box::new(ScriptedChat)).expect("no provider used yet");
+        APP_STATE.lock().await.init_memory_store();
+    })
+    .await;
+    api::app(state)
+}
+
+async fn send_raw(request: Request<Body>) -> (StatusCode, String) {
+    let response = app().await.oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+async fn send(request: Request<Body>) -> (StatusCode, Value) {
+    let (status, body) = send_raw(request).await;
+    (status, serde_json::from_str(&body).unwrap_or(Value::Null))
+}
+
+fn get(uri: &str, employee: Option<&str>) -> Request<Body> {
+    let mut builder = Request::get(uri);
+    if let Some(name) = employee {
+        builder = builder.header("x-employee-name", name);
+    }
+    builder.body(Body::empty()).unwrap()
+}
+
+fn post_json(uri: &str, employee: Option<&str>, body: Value) -> Request<Body> {
+    let mut builder = Request::post(uri).header("content-type", "application/json");
+    if let Some(name) = employee {
+        builder = builder.header("x-employee-name", name);
+    }
+    builder.body(Body::from(body.to_string())).unwrap()
+}
+
+async fn ask(employee: &str, text: &str) -> (StatusCode, Value) {
+    let _one_at_a_time = ASKS.lock().await;
+    send(post_json("/v1/ask", Some(employee), json!({ "text": text }))).await
+}
+
+#[tokio::test]
+async fn ask_returns_the_decision_and_its_trace() {
+    let (status, body) = ask("John", "Freeze hiring until Q3 (topic-ask-happy)").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["response_text"], "Decided on topic-ask-happy.");
+    assert_eq!(body["trace"]["decision_id"], "decision-topic-ask-happy");
+    assert_eq!(body["trace"]["version"], 1);
+    assert_eq!(body["trace"]["persistence_warnings"], json!([]));
+    assert_eq!(body["needs_clarification"], false);
+
+    let (status, body) = ask("John", "Still freezing hiring (topic-ask-happy)").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["trace"]["version"], 2);
+}
+
+#[tokio::test]
+async fn ask_without_identity_is_rejected() {
+    let (status, body) =
+        send(post_json("/v1/ask", None, json!({ "text": "anyone there?" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "missing x-employee-name");
+}
+
+#[tokio::test]
+async fn ask_with_invalid_base64_audio_is_rejected() {
+    let request = post_json("/v1/ask", Some("Bob"), json!({ "audio_base64": "not base64!" }));
+    let (status, body) = send(request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "audio_base64 must be valid base64");
+}
+
+#[tokio::test]
+async fn listing_all_traces_is_ceo_only() {
+    let (status, body) = send(get("/v1/traces", Some("Sarah"))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"], "forbidden");
+
+    let (status, body) = send(get("/v1/traces", Some("John"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["traces"].is_array());
+}
+
+#[tokio::test]
+async fn agent_traces_are_cut_to_the_routing_level() {
+    let (status, _) = ask("John", "Reorganize the platform team (topic-visibility)").await;
+    assert_eq!(status, StatusCode::OK);
+    let find = |body: &Value| {
+        body["traces"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|t| t["decision_id"] == "decision-topic-visibility")
+            .cloned()
+    };
+
+    let (status, body) = send(get("/v1/agents/employee_john/traces", Some("John"))).await;
+    assert_eq!(status, StatusCode::OK);
+    let full = find(&body).expect("john has full visibility");
+    assert_eq!(full["evidence"], json!(["board minutes"]));
+
+    let (status, body) = send(get("/v1/agents/employee_sarah/traces", Some("Sarah"))).await;
+    assert_eq!(status, StatusCode::OK);
+    let summary = find(&body).expect("sarah sees a summary");
+    assert_eq!(summary["summary"], "Decision on topic-visibility");
+    assert_eq!(summary["evidence"], json!([]));
+    assert_eq!(summary["assumptions"], json!([]));
+
+    let (status, body) = send(get("/v1/agents/employee_bob/traces", Some("Bob"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(find(&body).is_none(), "bob is routed none");
+
+    let (status, _) = send(get("/v1/agents/employee_sarah/traces", Some("Bob"))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn trace_export_is_ndjson_filtered_by_since() {
+    let (status, _) = ask("John", "Open a Berlin office (topic-export)").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send_raw(get("/v1/traces/export", Some("John"))).await;
+    assert_eq!(status, StatusCode::OK);
+    let lines: Vec<Value> = body.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert!(lines.iter().any(|t| t["decision_id"] == "decision-topic-export"));
+    assert!(body.ends_with('\n'));
+
+    let (status, body) =
+        send_raw(get("/v1/traces/export?since=2999-01-01T00:00:00Z", Some("John"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "");
+
+    let (status, _) = send_raw(get("/v1/traces/export", Some("Bob"))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn cypher_is_ceo_only_read_only_and_needs_neo4j() {
+    let body = json!({ "query": "MATCH (n) RETURN count(n) AS n" });
+    let (status, _) = send(post_json("/v1/graph/cypher", Some("Sarah"), body.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Write clauses are refused before any database is involved.
+    let write = json!({ "query": "LOAD CSV FROM 'file:///etc/passwd' AS row RETURN row" });
+    let (status, rejected) = send(post_json("/v1/graph/cypher", Some("John"), write)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(rejected["clause"], "LOAD");
+
+    // Memory storage: there is no database to run the query against.
+    let (status, body) = send(post_json("/v1/graph/cypher", Some("John"), body)).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["error"], "neo4j not initialized");
+}
+
+#[tokio::test]
+async fn health_is_not_degraded_in_memory_mode() {
+    let (status, body) = send(get("/health", None)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["ok"], true);
+    assert_eq!(body["degraded"], false);
+}
+
+#[tokio::test]
+async fn audit_log_is_ceo_only() {
+    let (status, body) = send(get("/v1/admin/audit", Some("Sarah"))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"], "forbidden");
+
+    let (status, body) = send(get("/v1/admin/audit", None)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+
+    let (status, body) = send(get("/v1/admin/audit", Some("John"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+}
+
+#[tokio::test]
+async fn annotations_need_visibility_and_show_on_the_trace() {
+    let (status, _) = ask("John", "Move the offsite to May (topic-annotate)").await;
+    assert_eq!(status, StatusCode::OK);
+    let uri = "/v1/traces/decision-topic-annotate/annotations";
+
+    let (status, _) = send(post_json(uri, Some("Bob"), json!({ "text": "why?" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "bob is routed none");
+    let (status, _) = send(post_json(uri, Some("Sarah"), json!({ "text": "  " }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(post_json(
+        "/v1/traces/decision-missing/annotations",
+        Some("John"),
+        json!({ "text": "hello" }),
+    ))
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) =
+        send(post_json(uri, Some("Sarah"), json!({ "text": "Venue confirmed" }))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["version"], 1);
+    assert_eq!(body["annotation"]["author"], "employee_sarah");
+
+    let (_, body) = send(get("/v1/traces", Some("John"))).await;
+    let trace = body["traces"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|t| t["decision_id"] == "decision-topic-annotate")
+        .cloned()
+        .unwrap();
+    assert_eq!(trace["annotations"][0]["text"], "Venue confirmed");
+}
+
+#[tokio::test]
+async fn lagging_stream_clients_are_told_how_much_they_missed() {
+    let state = ApiState::new(None, 2);
+    let tx = state.events_tx.clone();
+    let response = app_with(state).await.oneshot(get("/v1/stream", Some("Bob"))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    for i in 0..5 {
+        let stage = format!("stage-{i}");
+        tx.send(ServerEvent::Progress { stage, agent_id: "employee_bob".into(), request_id: None })
+            .unwrap();
+    }
+    drop(tx);
+
+    let mut frames = String::new();
+    let mut body = response.into_body().into_data_stream();
+    while let Some(chunk) = body.next().await {
+        frames.push_str(std::str::from_utf8(&chunk.unwrap()).unwrap());
+    }
+    let data: Vec<Value> = frames
+        .lines()
+        .filter_map(|l| l.strip_prefix("data: "))
+        .map(|d| serde_json::from_str(d).unwrap())
+        .collect();
+    assert_eq!(data[0]["type"], "connected");
+    assert_eq!(data[1]["type"], "subscription");
+    assert_eq!(data[2], json!({ "type": "lagged", "missed": 3 }));
+    assert_eq!(data[3]["data"]["stage"], "stage-3");
+    assert_eq!(data[4]["data"]["stage"], "stage-4");
+    assert_eq!(data.len(), 5);
+}
+
+#[tokio::test]
+async fn anonymous_stream_clients_are_told_to_identify() {
+    let response = app().await.oneshot(get("/v1/stream", None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut body = response.into_body().into_data_stream();
+    let chunk = body.next().await.unwrap().unwrap();
+    let frame = std::str::from_utf8(&chunk).unwrap();
+    let data = frame.lines().find_map(|l| l.strip_prefix("data: ")).expect(frame);
+    let data: Value = serde_json::from_str(data).unwrap();
+    assert_eq!(data, json!({ "type": "error", "message": "identity required" }));
+}
+
+/// Reads `/v1/stream` frames up to the first one `last` accepts, and returns them all.
+async fn stream_until(
+    body: &mut axum::body::BodyDataStream,
+    last: impl Fn(&Value) -> bool,
+) -> Vec<Value> {
+    let mut data: Vec<Value> = Vec::new();
+    while !data.iter().any(&last) {
+        let next = tokio::time::timeout(std::time::Duration::from_secs(5), body.next());
+        let chunk = next.await.expect("stream stalled").unwrap().unwrap();
+        let frames = std::str::from_utf8(&chunk).unwrap();
+        let frames = frames.lines().filter_map(|l| l.strip_prefix("data: "));
+        data.extend(frames.map(|d| serde_json::from_str::<Value>(d).unwrap()));
+    }
+    data
+}
+
+#[tokio::test]
+async fn low_confidence_alerts_reach_only_the_ceo() {
+    let state = ApiState::new(None, 256);
+    let tx = state.events_tx.clone();
+    let open = |employee| {
+        let app = app_with(state.clone());
+        async move {
+            let response = app.await.oneshot(get("/v1/stream", Some(employee))).await.unwrap();
+            response.into_body().into_data_stream()
+        }
+    };
+    let (mut ceo, mut sarah) = (open("John").await, open("Sarah").await);
+
+    let text = "Cut the travel budget (topic-low-confidence)";
+    let (status, body) = {
+        let _one_at_a_time = ASKS.lock().await;
+        let ask = post_json("/v1/ask", Some("Sarah"), json!({ "text": text }));
+        let response = app_with(state.clone()).await.oneshot(ask).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice::<Value>(&bytes).unwrap())
+    };
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["trace"]["confidence"].as_f64().unwrap() < 0.4, "{body}");
+    // Sent after the alert, so a stream that reached it has been offered everything before.
+    let stage = "after-the-ask".to_string();
+    tx.send(ServerEvent::Progress { stage, agent_id: "employee_sarah".into(), request_id: None })
+        .unwrap();
+
+    let seen = stream_until(&mut ceo, |d| d["type"] == "low_confidence").await;
+    let alert = seen.last().unwrap();
+    assert_eq!(alert["data"]["decision_id"], "decision-topic-low-confidence");
+    let seen = stream_until(&mut sarah, |d| d["data"]["stage"] == "after-the-ask").await;
+    assert!(seen.iter().any(|d| d["type"] == "trace"), "sarah is routed a summary");
+    assert!(seen.iter().all(|d| d["type"] != "low_confidence"), "{seen:?}");
+}
+
+#[tokio::test]
+async fn progress_reaches_only_the_asker_and_the_ceo() {
+    let state = ApiState::new(None, 16);
+    let tx = state.events_tx.clone();
+    let open = |employee| {
+        let app = app_with(state.clone());
+        async move {
+            let response = app.await.oneshot(get("/v1/stream", Some(employee))).await.unwrap();
+            response.into_body().into_data_stream()
+        }
+    };
+    let (mut ceo, mut sarah) = (open("John").await, open("Sarah").await);
+    let mut bob = open("Bob").await;
+    let asks = [("sarah-1", "sarah"), ("bob-1", "bob"), ("sarah-2", "sarah")];
+    for (stage, asker) in asks {
+        let (stage, agent_id) = (stage.to_string(), format!("employee_{asker}"));
+        tx.send(ServerEvent::Progress { stage, agent_id, request_id: None }).unwrap();
+    }
+    let stages = |seen: Vec<Value>| -> Vec<String> {
+        let stages = seen.iter().filter_map(|d| d["data"]["stage"].as_str());
+        stages.map(str::to_string).collect()
+    };
+
+    let seen = stream_until(&mut ceo, |d| d["data"]["stage"] == "sarah-2").await;
+    assert_eq!(stages(seen), ["sarah-1", "bob-1", "sarah-2"]);
+    let seen = stream_until(&mut sarah, |d| d["data"]["stage"] == "sarah-2").await;
+    assert_eq!(stages(seen), ["sarah-1", "sarah-2"]);
+    let seen = stream_until(&mut bob, |d| d["data"]["stage"] == "bob-1").await;
+    assert_eq!(stages(seen), ["bob-1"]);
+}
+
+#[tokio::test]
+async fn identical_knowledge_content_keeps_the_current_version() {
+    let ingest = |content: &str, force: bool| {
+        post_json(
+            "/v1/knowledge",
+            None,
+            json!({
+                "truth_id": "policy-dedupe",
+                "kind": "policy",
+                "content": content,
+                "routing": { "employee_john": "full" },
+                "add_to_rag": false,
+                "force": force
+            }),
+        )
+    };
+
+    let (status, body) = send(ingest("Remote work is allowed.", false)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["trace"]["version"], 1);
+    assert_eq!(body["trace"]["deduplicated"], false);
+
+    let (_, body) = send(ingest("Remote work is allowed.", false)).await;
+    assert_eq!(body["trace"]["version"], 1);
+    assert_eq!(body["trace"]["deduplicated"], true);
+
+    let (_, body) = send(ingest("Remote work is allowed.", true)).await;
+    assert_eq!(body["trace"]["version"], 2);
+    let (_, body) = send(ingest("Remote work is allowed on Fridays.", false)).await;
+    assert_eq!(body["trace"]["version"], 3);
+}
+
+#[tokio::test]
+async fn analyzed_knowledge_gets_an_orgbrain_trace_sharing_its_event() {
+    let ingest = post_json(
+        "/v1/knowledge",
+        None,
+        json!({
+            "truth_id": "policy-analyze",
+            "kind": "topic-analyze",
+            "content": "Travel needs CEO sign-off.",
+            "agent_id": "employee_sarah",
+            "routing": { "employee_john": "full" },
+            "add_to_rag": false,
+            "analyze": true
+        }),
+    );
+    let (status, body) = {
+        let _one_at_a_time = ASKS.lock().await;
+        send(ingest).await
+    };
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["analysis_error"], Value::Null);
+    let analysis = &body["analysis_trace"];
+    assert_eq!(analysis["decision_id"], "decision-topic-analyze", "{body}");
+    assert_eq!(analysis["trigger_events"], json!([body["correlation_id"].clone()]));
+    assert_eq!(body["trace"]["trigger_events"], analysis["trigger_events"]);
+}
+
+#[tokio::test]
+async fn asks_report_their_stages_in_order() {
+    let _ = app().await;
+    let (tx, mut rx) = tokio::sync::broadcast::channel(16);
+    let options = service::AskOptions {
+        progress: Some(tx),
+        ..Default::default()
+    };
+    {
+        let _one_at_a_time = ASKS.lock().await;
+        let text = "Plan topic-progress".to_string();
+        service::ask_and_persist_with(text, Some("employee_bob".into()), options)
+            .await
+            .unwrap();
+    }
+    let mut stages = Vec::new();
+    while let Ok(ServerEvent::Progress { stage, agent_id, .. }) = rx.try_recv() {
+        assert_eq!(agent_id, "employee_bob");
+        stages.push(stage);
+    }
+    assert_eq!(stages, ["analyzing", "retrieving", "deciding"]);
+}
+
+#[tokio::test]
+async fn a_failed_decision_puts_other_employees_events_back() {
+    let _ = app().await;
+    let _one_at_a_time = ASKS.lock().await;
+    let waiting = Event::new(
+        EmployeeAgentId("employee_sarah".into()),
+        EventType::Update,
+        "topic-waiting".into(),
+        0.9,
+        Vec::new(),
+    );
+    APP_STATE.lock().await.emit(waiting.clone());
+
+    let text = json!({ "text": "Plan topic-orgbrain-down" });
+    let (status, body) = send(post_json("/v1/ask", Some("Bob"), text)).await;
+    assert!(status.is_server_error(), "{status} {body}");
+    let queued = APP_STATE.lock().await.drain_events();
+    let ids: Vec<_> = queued.iter().map(|e| e.event_id).collect();
+    assert_eq!(ids, vec![waiting.event_id], "only the failed ask's own event is dropped");
+}
+
+#[tokio::test]
+async fn a_clarification_event_can_be_answered_with_a_question() {
+    let _ = app().await;
+    let _one_at_a_time = ASKS.lock().await;
+    let waiting = Event::new(
+        EmployeeAgentId("employee_john".into()),
+        EventType::Update,
+        "topic-waiting-on-clarify".into(),
+        0.9,
+        Vec::new(),
+    );
+    APP_STATE.lock().await.emit(waiting.clone());
+
+    let text = json!({ "text": "Raise it? topic-clarify-budget" });
+    let (status, body) = send(post_json("/v1/ask", Some("Sarah"), text)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["needs_clarification"], true);
+    assert_eq!(body["clarifying_question"], "Which budget?");
+    assert_eq!(body["response_text"], "Which budget?");
+    let trace = &body["trace"];
+    assert_eq!(trace["kind"], "clarification");
+    assert_eq!(trace["version"], 0);
+    assert!(trace["decision_id"].as_str().unwrap().starts_with("clarify:"));
+
+    // Nothing was decided: John's event waits for the next decision, and the question is part
+    // of Sarah's conversation.
+    let queued = APP_STATE.lock().await.drain_events();
+    let ids: Vec<_> = queued.iter().map(|e| e.event_id).collect();
+    assert_eq!(ids, vec![waiting.event_id]);
+    let (_, traces) = send(get("/v1/traces", Some("John"))).await;
+    assert!(!traces.to_string().contains("topic-clarify-budget"));
+    let sarah = EmployeeAgentId("employee_sarah".into());
+    let turns = app_state::sessions().await.conversation_cache[&sarah].clone();
+    let last: Vec<_> = turns.iter().rev().take(2).map(|(_, text)| text.as_str()).collect();
+    assert_eq!(last, ["Which budget?", "Raise it? topic-clarify-budget"]);
+
+    // Any other event is decided even when the OrgBrain asks a question.
+    let text = json!({ "text": "Raise it? topic-needs-clarify" });
+    let (status, body) = send(post_json("/v1/ask", Some("Sarah"), text)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["needs_clarification"], false);
+    assert_eq!(body["trace"]["decision_id"], "decision-topic-needs-clarify");
+}
+
+#[tokio::test]
+async fn a_cancelled_ask_decides_nothing() {
+    let _ = app().await;
+    let cancel = tokio_util::sync::CancellationToken::new();
+    cancel.cancel();
+    let options = service::AskOptions {
+        cancel: Some(cancel),
+        ..Default::default()
+    };
+    let _one_at_a_time = ASKS.lock().await;
+    let text = "Plan topic-cancelled".to_string();
+    let e = service::ask_and_persist_with(text, Some("employee_bob".into()), options)
+        .await
+        .unwrap_err();
+    assert!(matches!(CosError::find(&e), Some(CosError::Cancelled)), "{e:#}");
+    let (_, traces) = send(get("/v1/traces", Some("John"))).await;
+    assert!(!traces.to_string().contains("topic-cancelled"));
+}
+
+#[tokio::test]
+async fn org_truth_is_updated_once_the_decision_is_stored() {
+    let (status, body) = ask("Bob", "Settle topic-truth").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let state = APP_STATE.lock().await;
+    assert_eq!(state.latest_truth("scripted-truth"), Some("Scripted truth"));
+    drop(state);
+    let (_, graph) = send(get("/v1/graph/snapshot", Some("John"))).await;
+    assert!(graph.to_string().contains("scripted-truth"), "the truth version was stored");
+}
+
+#[tokio::test]
+async fn script_mode_records_each_line_and_fails_if_any_did() {
+    // Only for the shared setup: scripted chat and memory storage.
+    let _ = app().await;
+    let dir = std::env::temp_dir().join(format!("cos-script-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (input, output) = (dir.join("asks.jsonl"), dir.join("asks.out.jsonl"));
+    let lines = [
+        "# hiring scenario",
+        r#"{"employee": "Sarah", "text": "Pause the Q3 hires (topic-script)"}"#,
+        "",
+        r#"{"employee": "Sarah", "text": "   "}"#,
+        "not json",
+    ];
+    std::fs::write(&input, lines.join("\n")).unwrap();
+
+    let result = {
+        let _one_at_a_time = ASKS.lock().await;
+        script::run(&input, &output, std::time::Duration::ZERO).await
+    };
+    assert_eq!(result.unwrap_err().to_string(), "2 of 3 script lines failed");
+
+    let records: Vec<Value> = std::fs::read_to_string(&output)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0]["line"], 2);
+    assert_eq!(records[0]["agent_id"], "employee_sarah");
+    assert_eq!(records[0]["trace"]["decision_id"], "decision-topic-script");
+    assert_eq!(records[1], json!({ "line": 4, "error": "empty text" }));
+    assert_eq!(records[2]["line"], 5);
+    assert!(records[2]["error"].as_str().unwrap().starts_with("invalid script line"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn the_yaml_spec_matches_the_json_one() {
+    let response = app().await.oneshot(get("/openapi.yaml", None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/yaml");
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let from_yaml: Value = serde_yaml::from_slice(&bytes).unwrap();
+
+    let (status, from_json) = send(get("/openapi.json", None)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(from_yaml, from_json);
+    assert!(from_yaml["paths"]["/openapi.yaml"]["get"].is_object());
+}
+
+#[tokio::test]
+async fn ask_audio_comes_in_the_requested_output_format() {
+    let _one_at_a_time = ASKS.lock().await;
+    let body = |format: &str| {
+        json!({
+            "text": "Move standup to 10am (topic-ask-audio)",
+            "response_audio": true,
+            "output_format": format,
+        })
+    };
+    let (status, rejected) = send(post_json("/v1/ask", Some("John"), body("wav_44100"))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(rejected["error"], "unsupported output_format");
+    assert!(rejected["allowed"].as_array().unwrap().contains(&json!("pcm_16000")));
+
+    let (status, answer) = send(post_json("/v1/ask", Some("John"), body("pcm_16000"))).await;
+    assert_eq!(status, StatusCode::OK, "{answer}");
+    assert_eq!(answer["audio_mime"], "audio/pcm");
+    assert!(answer["audio_base64"].is_string());
+}
+
+#[tokio::test]
+async fn a_refreshed_decision_waits_for_approval() {
+    let (status, body) = ask("John", "Open a Berlin office (topic-refresh-approve)").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let decision = "decision-topic-refresh-approve";
+    let uri = format!("/v1/decisions/{decision}/refresh");
+    let (status, refreshed) = {
+        let _one_at_a_time = ASKS.lock().await;
+        send(post_json(&uri, Some("John"), json!({}))).await
+    };
+    assert_eq!(status, StatusCode::OK, "{refreshed}");
+    assert_eq!(refreshed["trace"]["version"], 2);
+    assert_eq!(refreshed["trace"]["needs_approval"], true);
+    let store = app_state::handles().graph_store.unwrap();
+    assert_eq!(store.current_decision_version(decision).await.unwrap(), Some(1));
+
+    let approve = format!("/v1/decisions/{decision}/versions/2/approve");
+    let (status, _) = send(post_json(&approve, Some("Sarah"), json!({}))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, approved) = send(post_json(&approve, Some("John"), json!({}))).await;
+    assert_eq!(status, StatusCode::OK, "{approved}");
+    assert_eq!(approved["version"], 2);
+    assert_eq!(store.current_decision_version(decision).await.unwrap(), Some(2));
+    let (status, _) = send(post_json(&approve, Some("John"), json!({}))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn an_employee_can_clear_their_own_conversation() {
+    let (status, body) = ask("Bob", "Which laptop should I order? (topic-memory-clear)").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let clear = |employee: &str| {
+        Request::delete("/v1/agents/employee_bob/memory")
+            .header("x-employee-name", employee)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let (status, _) = send(clear("Sarah")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = send(clear("Bob")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["turns_cleared"], 2);
+    let (_, body) = send(clear("John")).await;
+    assert_eq!(body["turns_cleared"], 0);
+}
+
+#[tokio::test]
+async fn memory_mode_has_no_email_threads() {
+    let (status, body) = send(get("/v1/threads/thread_0123456789abcdef", Some("John"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"], "thread not found");
+}
+
+#[tokio::test]
+async fn decision_evidence_needs_full_visibility() {
+    let (status, body) = ask("John", "Do we renew the lease? (topic-evidence)").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let uri = "/v1/decisions/decision-topic-evidence/evidence";
+
+    let (status, body) = send(get(uri, Some("John"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["version"], 1);
+    // Memory mode has no RAG, so nothing was retrieved.
+    assert_eq!(body["snippets"], json!([]));
+    assert!(body.get("routing_json").is_none());
+
+    let (status, _) = send(get(uri, Some("Sarah"))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "sarah is routed a summary");
+    let (status, _) = send(get(&format!("{uri}?version=2"), Some("John"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn clusters_are_labelled_from_a_bounded_sample() {
+    let _ = app().await;
+    let samples: Vec<String> = (0..15).map(|i| format!("Q{i} budget: numbers")).collect();
+    let label = triage::label_cluster(&samples).await.unwrap();
+    assert_eq!(label.name, "Budget planning");
+    assert_eq!(label.description, "10 messages about budgets.");
+
+    // Memory mode stores no clusters.
+    let (status, body) = send(get("/v1/clusters", Some("John"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["clusters"], json!([]));
+}
+
+#[tokio::test]
+async fn only_the_ceo_announces_and_routing_overrides_the_default() {
+    let announcement = json!({ "title": "Offsite", "body": "We meet in Lisbon in May." });
+    let (status, _) = send(post_json("/v1/announce", Some("Sarah"), announcement.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let blank = json!({ "title": " ", "body": "We meet in Lisbon in May." });
+    let (status, _) = send(post_json("/v1/announce", Some("John"), blank)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let bad_level =
+        json!({ "title": "Offsite", "body": "Lisbon", "routing": { "employee_bob": "all" } });
+    let (status, body) = send(post_json("/v1/announce", Some("John"), bad_level)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["level"], "all");
+
+    let mut request = announcement;
+    request["routing"] = json!({ "employee_bob": "none" });
+    let (status, trace) = send(post_json("/v1/announce", Some("John"), request)).await;
+    assert_eq!(status, StatusCode::OK, "{trace}");
+    assert_eq!(trace["kind"], "announcement");
+    assert_eq!(trace["version"], 1);
+    assert_eq!(trace["summary"], "Offsite\n\nWe meet in Lisbon in May.");
+    assert_eq!(
+        trace["routing"],
+        json!({ "employee_john": "full", "employee_sarah": "summary", "employee_bob": "none" })
+    );
+    let decision_id = trace["decision_id"].as_str().unwrap();
+    assert!(decision_id.starts_with("announcement_"));
+    let store = app_state::handles().graph_store.unwrap();
+    assert_eq!(store.current_decision_version(decision_id).await.unwrap(), Some(1));
+}
+
+#[tokio::test]
+async fn cluster_listing_checks_its_order_and_caps_its_page() {
+    let (status, body) = send(get("/v1/clusters?order=sideways", Some("John"))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["order"], "sideways");
+    let page = "/v1/clusters?order=asc&limit=900&offset=3";
+    let (status, body) = send(get(page, Some("John"))).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!((body["limit"].as_u64(), body["offset"].as_u64()), (Some(500), Some(3)));
+    let (status, _) = send(get("/v1/clusters/cluster_missing", Some("John"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn decision_reports_follow_the_callers_visibility() {
+    let (status, body) = ask("John", "Where is the offsite? (topic-report)").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let uri = "/v1/decisions/decision-topic-report/report";
+
+    let response = app().await.oneshot(get(uri, Some("John"))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let content_type = response.headers()["content-type"].to_str().unwrap().to_string();
+    assert_eq!(content_type, "text/markdown; charset=utf-8");
+    let (status, full) = send_raw(get(uri, Some("John"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(full.contains("## Evidence\n\n- board minutes\n"), "{full}");
+
+    let (status, summary) = send_raw(get(&format!("{uri}?format=html"), Some("Sarah"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(summary.contains("<h2>Summary</h2>") && !summary.contains("Evidence"));
+
+    let (status, _) = send(get(uri, Some("Bob"))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(get(&format!("{uri}?format=pdf"), Some("John"))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(get("/v1/decisions/decision-missing/report", Some("John"))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn replays_write_nothing_unless_committed() {
+    let (status, body) = ask("John", "Which CRM do we buy? (topic-replay)").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let decision = "decision-topic-replay";
+    let uri = format!("/v1/decisions/{decision}/replay");
+    let store = app_state::handles().graph_store.unwrap();
+
+    let _one_at_a_time = ASKS.lock().await;
+    let (status, _) = send(post_json(&uri, Some("Sarah"), json!({}))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, dry) = send(post_json(&uri, Some("John"), json!({}))).await;
+    assert_eq!(status, StatusCode::OK, "{dry}");
+    assert_eq!(dry["original_version"], 1);
+    assert_eq!(dry["committed"], false);
+    assert_eq!(dry["response_text"], "Decided on topic-replay.");
+    assert_eq!(store.current_decision_version(decision).await.unwrap(), Some(1));
+
+    let commit = format!("{uri}?commit=true");
+    let (status, committed) = send(post_json(&commit, Some("John"), json!({}))).await;
+    assert_eq!(status, StatusCode::OK, "{committed}");
+    assert_eq!(committed["trace"]["version"], 2);
+    assert_eq!(store.current_decision_version(decision).await.unwrap(), Some(2));
+
+    let missing = "/v1/decisions/decision-missing/replay";
+    let (status, _) = send(post_json(missing, Some("John"), json!({}))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn ask_context_is_checked_and_cited() {
+    let ask_with = |context: Value| {
+        let mut body = json!({ "text": "Which venue? (topic-context)" });
+        body.as_object_mut().unwrap().extend(context.as_object().unwrap().clone());
+        post_json("/v1/ask", Some("John"), body)
+    };
+    let (status, body) = send(ask_with(json!({ "context_documents": vec!["memo"; 6] }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!((body["max_documents"].as_u64(), body["count"].as_u64()), (Some(5), Some(6)));
+    let (status, body) = send(ask_with(json!({ "context_documents": ["memo", "  "] }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["index"], 1);
+    let long = "x".repeat(8_001);
+    let (status, _) = send(ask_with(json!({ "context_documents": [long] }))).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    let unknown = json!({ "context_truth_ids": ["truth-never-written"] });
+    let (status, _) = send(ask_with(unknown)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let store = app_state::handles().graph_store.unwrap();
+    store
+        .persist_truth_version(
+            "venue-policy".to_string(),
+            "policy".to_string(),
+            "Offsites stay in Europe.".to_string(),
+            1.0,
+            Vec::new(),
+            Vec::new(),
+            &json!({}),
+        )
+        .await
+        .unwrap();
+    let context = json!({
+        "context_documents": ["Lisbon quote: 40k"],
+        "context_truth_ids": ["venue-policy", " venue-policy "]
+    });
+    let (status, body) = {
+        let _one_at_a_time = ASKS.lock().await;
+        send(ask_with(context)).await
+    };
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        body["trace"]["evidence"],
+        json!(["board minutes", "document 1", "truth:venue-policy v1"])
+    );
+}
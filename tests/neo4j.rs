@@ -0,0 +1,496 @@
+//! Against a live Neo4j (`NEO4J_URI`, `NEO4J_USER`, `NEO4J_PASSWORD`, or `cos.toml`), so
+//! ignored by default: `cargo test --test neo4j -- --ignored`.
+
+use std::time::Duration;
+
+use pocketflow_template_rust::config::CosConfig;
+use pocketflow_template_rust::domain::EmployeeRole;
+use pocketflow_template_rust::employees::EmployeeSeed;
+use pocketflow_template_rust::error::CosError;
+use pocketflow_template_rust::graph_store::GraphStore;
+use pocketflow_template_rust::neo4j::{writer, Neo4jClient};
+use pocketflow_template_rust::roles;
+
+async fn client() -> Neo4jClient {
+    let client = Neo4jClient::connect(&CosConfig::load().unwrap().neo4j).await.unwrap();
+    client.run_migrations().await.unwrap();
+    client
+}
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[tokio::test]
+#[ignore = "needs a running Neo4j"]
+async fn read_only_query_returns_match_rows() {
+    let client = client().await;
+    let marker = "cypher-match-test";
+    let seed =
+        neo4rs::query("UNWIND range(1, 5) AS i MERGE (:ReadOnlyProbe {marker: $marker, i: i})")
+            .param("marker", marker);
+    client.graph().run(seed).await.unwrap();
+
+    let q = neo4rs::query("MATCH (n:ReadOnlyProbe {marker: $marker}) RETURN n.i AS i ORDER BY i")
+        .param("marker", marker);
+    let (rows, truncated) = client.read_only_query(q, 3, TIMEOUT).await.unwrap();
+    assert_eq!(rows.len(), 3);
+    assert!(truncated);
+    assert_eq!(rows[0]["i"], neo4rs::BoltType::from(1));
+
+    let cleanup = neo4rs::query("MATCH (n:ReadOnlyProbe {marker: $marker}) DELETE n")
+        .param("marker", marker);
+    client.graph().run(cleanup).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a running Neo4j"]
+async fn read_only_query_never_commits_a_write() {
+    let client = client().await;
+    let marker = "cypher-read-only-test";
+    let q = neo4rs::query("MERGE (n:ReadOnlyProbe {marker: $marker}) RETURN n")
+        .param("marker", marker);
+    client.read_only_query(q, 10, TIMEOUT).await.unwrap();
+
+    let q = neo4rs::query("MATCH (n:ReadOnlyProbe {marker: $marker}) RETURN n")
+        .param("marker", marker);
+    let (rows, _) = client.read_only_query(q, 10, TIMEOUT).await.unwrap();
+    assert!(rows.is_empty(), "the MERGE must have been rolled back");
+}
+
+#[tokio::test]
+#[ignore = "needs a running Neo4j"]
+async fn read_only_query_times_out_and_frees_the_connection() {
+    let client = client().await;
+    let q = neo4rs::query("UNWIND range(1, 100000000) AS i WITH i WHERE i < 0 RETURN i");
+    let e = client.read_only_query(q, 10, Duration::from_millis(1)).await.unwrap_err();
+    assert!(matches!(CosError::find(&e), Some(CosError::Timeout(_))), "{e:#}");
+
+    let q = neo4rs::query("RETURN 1 AS one");
+    let (rows, _) = client.read_only_query(q, 10, TIMEOUT).await.unwrap();
+    assert_eq!(rows.len(), 1);
+}
+
+#[tokio::test]
+#[ignore = "needs a running Neo4j"]
+async fn role_rules_guess_roles_without_clobbering_explicit_ones() {
+    let client = client().await;
+    let graph = client.graph();
+    let rules = roles::parse_role_rules("*@hr.roles-test.example->hr");
+
+    // A seeded employee whose email matches the HR rule keeps its seeded role.
+    let seed = EmployeeSeed {
+        employee_id: "employee_roles_test_ceo".into(),
+        name: "Roles Test CEO".into(),
+        role: EmployeeRole::Ceo,
+    };
+    writer::seed_employees(graph, &[seed], false).await.unwrap();
+    let q = neo4rs::query(
+        "MATCH (e:Employee {employee_id: 'employee_roles_test_ceo'}) \
+         SET e.email = 'boss@hr.roles-test.example'",
+    );
+    graph.run(q).await.unwrap();
+
+    // Discovered without a role, then backfilled from its HR-domain email.
+    writer::merge_employee_from_email(graph, "mia@hr.roles-test.example", Some("Mia"))
+        .await
+        .unwrap();
+    let q = neo4rs::query("MATCH (e:Employee {email: 'mia@hr.roles-test.example'}) REMOVE e.role");
+    graph.run(q).await.unwrap();
+    writer::backfill_employee_roles(graph, &rules).await.unwrap();
+
+    let stored = writer::load_employee_roles(graph).await.unwrap();
+    let mia = writer::canonical_employee_id_from_email("mia@hr.roles-test.example");
+    assert_eq!(stored.get(&mia), Some(&EmployeeRole::Hr));
+    assert_eq!(roles::stored_role(&mia), Some(EmployeeRole::Hr));
+    // Seeded roles come from the seed file, not the graph.
+    assert_eq!(stored.get("employee_roles_test_ceo"), None);
+    let q = neo4rs::query(
+        "MATCH (e:Employee {employee_id: 'employee_roles_test_ceo'}) RETURN e.role AS role",
+    );
+    let row = graph.execute(q).await.unwrap().next().await.unwrap().unwrap();
+    assert_eq!(row.get::<String>("role").unwrap(), "ceo");
+
+    let cleanup = neo4rs::query(
+        "MATCH (e:Employee) WHERE e.email ENDS WITH '@hr.roles-test.example' DETACH DELETE e",
+    );
+    graph.run(cleanup).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a running Neo4j"]
+async fn current_truth_is_rehydrated_from_the_latest_version() {
+    let client = client().await;
+    let graph = client.graph();
+    let truth_id = "truth_rehydrate_test".to_string();
+    for summary in ["Remote on Fridays", "Remote on Mondays and Fridays"] {
+        let (kind, routing) = ("policy".to_string(), serde_json::json!({}));
+        writer::persist_truth_version(
+            graph,
+            truth_id.clone(),
+            kind,
+            summary.into(),
+            0.9,
+            vec![],
+            vec![],
+            routing,
+        )
+        .await
+        .unwrap();
+    }
+
+    let truth = writer::load_current_truth(graph).await.unwrap();
+    assert_eq!(truth.get(&truth_id), Some(&vec!["Remote on Mondays and Fridays".to_string()]));
+
+    let cleanup = neo4rs::query(
+        "MATCH (n) WHERE n.truth_id = 'truth_rehydrate_test' DETACH DELETE n",
+    );
+    graph.run(cleanup).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a running Neo4j"]
+async fn pending_decision_versions_wait_for_promotion() {
+    let client = client().await;
+    let graph = client.graph();
+    let decision_id = "decision_pending_test";
+    let outcome = |summary: &str, pending: bool| writer::AskOutcome {
+        decision_id: decision_id.to_string(),
+        summary: summary.to_string(),
+        confidence: 0.8,
+        trigger_events: vec![],
+        agents_involved: vec![],
+        routing: serde_json::json!({}),
+        topic: String::new(),
+        based_on: vec![],
+        content: writer::DecisionContent::default(),
+        truths: vec![],
+        employee_id: "employee_pending_test".to_string(),
+        turns: vec![],
+        pending,
+    };
+    let write = |o| async move { writer::persist_ask_outcome(graph, &o).await.unwrap().0 };
+    assert_eq!(write(outcome("freeze hiring", false)).await, 1);
+    assert_eq!(write(outcome("lift the freeze", true)).await, 2);
+    assert_eq!(writer::current_decision_version(graph, decision_id).await.unwrap(), Some(1));
+
+    assert!(writer::promote_decision_version(graph, decision_id, 2).await.unwrap().is_some());
+    assert_eq!(writer::current_decision_version(graph, decision_id).await.unwrap(), Some(2));
+    assert!(writer::promote_decision_version(graph, decision_id, 2).await.unwrap().is_none());
+
+    // A proposal overtaken by a newer current version is a conflict.
+    assert_eq!(write(outcome("freeze again", true)).await, 3);
+    assert_eq!(write(outcome("freeze until Q3", false)).await, 4);
+    let e = writer::promote_decision_version(graph, decision_id, 3).await.unwrap_err();
+    assert!(matches!(CosError::find(&e), Some(CosError::Conflict(_))), "{e:#}");
+
+    let cleanup = neo4rs::query(
+        "MATCH (n) WHERE n.decision_id = 'decision_pending_test' DETACH DELETE n",
+    );
+    graph.run(cleanup).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a running Neo4j"]
+async fn compaction_keeps_current_pending_and_annotated_versions() {
+    let client = client().await;
+    let graph = client.graph();
+    let decision_id = "decision_compaction_test";
+    let outcome = |summary: String, pending: bool| writer::AskOutcome {
+        decision_id: decision_id.to_string(),
+        summary,
+        confidence: 0.8,
+        trigger_events: vec![],
+        agents_involved: vec![],
+        routing: serde_json::json!({}),
+        topic: String::new(),
+        based_on: vec![],
+        content: writer::DecisionContent::default(),
+        truths: vec![],
+        employee_id: "employee_compaction_test".to_string(),
+        turns: vec![],
+        pending,
+    };
+    let write = |o| async move { writer::persist_ask_outcome(graph, &o).await.unwrap().0 };
+    for i in 1..=7 {
+        assert_eq!(write(outcome(format!("freeze hiring, take {i}"), false)).await, i);
+    }
+    for i in 8..=10 {
+        assert_eq!(write(outcome(format!("lift the freeze, take {i}"), true)).await, i);
+    }
+    writer::persist_annotation(graph, decision_id, 3, "annotation_compaction_test", "ceo", "ok")
+        .await
+        .unwrap();
+
+    writer::compact_versions(graph, 2).await.unwrap();
+    assert_eq!(writer::current_decision_version(graph, decision_id).await.unwrap(), Some(7));
+    let chain = neo4rs::query(
+        "MATCH (:Decision {decision_id: $id})-[:CURRENT]->(cur) \
+         MATCH p = (cur)-[:SUPERSEDES*0..]->(v) \
+         RETURN v.version AS version, coalesce(v.compacted_count, 0) AS compacted \
+         ORDER BY length(p)",
+    )
+    .param("id", decision_id);
+    let mut rows = graph.execute(chain).await.unwrap();
+    let mut walked = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        walked.push((row.get::<i64>("version").unwrap(), row.get::<i64>("compacted").unwrap()));
+    }
+    assert_eq!(walked, vec![(7, 0), (6, 2), (3, 1), (1, 0)]);
+    for version in 8..=10 {
+        let pending = writer::decision_version(graph, decision_id, version).await.unwrap();
+        assert!(pending.is_some(), "pending v{version} survives");
+    }
+    assert!(writer::promote_decision_version(graph, decision_id, 10).await.unwrap().is_some());
+
+    let cleanup = neo4rs::query(
+        "MATCH (n) WHERE n.decision_id = 'decision_compaction_test' \
+         OR n.annotation_id = 'annotation_compaction_test' \
+         OR n.employee_id = 'employee_compaction_test' DETACH DELETE n",
+    );
+    graph.run(cleanup).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a running Neo4j"]
+async fn a_failed_ask_outcome_write_leaves_nothing_behind() {
+    let client = client().await;
+    let graph = client.graph();
+    // A stray version node takes the id the outcome's truth write needs.
+    let stray = neo4rs::query(
+        "MERGE (:TruthVersion {truth_version_id: 'truth_txn_test:v1', truth_id: 'stray'})",
+    );
+    graph.run(stray).await.unwrap();
+
+    let outcome = writer::AskOutcome {
+        decision_id: "decision_txn_test".to_string(),
+        summary: "open Berlin".to_string(),
+        confidence: 0.8,
+        trigger_events: vec![],
+        agents_involved: vec![],
+        routing: serde_json::json!({}),
+        topic: String::new(),
+        based_on: vec![],
+        content: writer::DecisionContent::default(),
+        truths: vec![writer::TruthWrite {
+            truth_id: "truth_txn_test".to_string(),
+            kind: "policy".to_string(),
+            summary: "offices in Berlin".to_string(),
+        }],
+        employee_id: "employee_txn_test".to_string(),
+        turns: vec![("user".to_string(), "open Berlin?".to_string())],
+        pending: false,
+    };
+    let e = writer::persist_ask_outcome(graph, &outcome).await.unwrap_err();
+    assert!(matches!(CosError::find(&e), Some(CosError::GraphWrite)), "{e:#}");
+    let decision = writer::current_decision_version(graph, "decision_txn_test").await.unwrap();
+    assert_eq!(decision, None, "the decision version was rolled back with the truth");
+
+    let cleanup = neo4rs::query(
+        "MATCH (n) WHERE n.truth_version_id = 'truth_txn_test:v1' \
+         OR n.employee_id = 'employee_txn_test' DETACH DELETE n",
+    );
+    graph.run(cleanup).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a running Neo4j"]
+async fn a_reply_and_its_placeholder_parent_share_a_thread() {
+    let client = client().await;
+    let graph = client.graph();
+    let (root, reply) = ("<root@thread.test>", "<reply@thread.test>");
+    graph
+        .run(neo4rs::query(
+            "MERGE (m:EmailMessage {message_id: '<reply@thread.test>'}) \
+             SET m.subject = 'Re: Q3 budget', m.created_at = datetime()",
+        ))
+        .await
+        .unwrap();
+    writer::persist_email_thread(graph, reply, root, &[root.to_string()]).await.unwrap();
+    let thread_id = writer::email_thread_id(reply, Some(root), &[root.to_string()]);
+    let members = vec![reply.to_string(), root.to_string()];
+    writer::persist_email_thread_membership(graph, &thread_id, &members, "Re: Q3  Budget")
+        .await
+        .unwrap();
+
+    let thread = writer::load_email_thread(graph, &thread_id).await.unwrap().unwrap();
+    assert_eq!(thread.subject, "q3 budget");
+    assert_eq!(thread.messages.len(), 2);
+    let parent = thread.messages.iter().find(|m| m.message_id == root).unwrap();
+    assert!(parent.placeholder);
+    let child = thread.messages.iter().find(|m| m.message_id == reply).unwrap();
+    assert_eq!(child.in_reply_to.as_deref(), Some(root));
+    assert!(writer::load_email_thread(graph, "thread_missing").await.unwrap().is_none());
+
+    let cleanup = neo4rs::query(
+        "MATCH (n) WHERE n.message_id ENDS WITH '@thread.test>' OR n.thread_id = $thread_id \
+         DETACH DELETE n",
+    )
+    .param("thread_id", thread_id);
+    graph.run(cleanup).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a running Neo4j"]
+async fn clusters_are_listed_by_size_with_their_members() {
+    let client = client().await;
+    let graph = client.graph();
+    let setup = neo4rs::query(
+        "UNWIND [['cl_test_small', 2], ['cl_test_large', 3]] AS cluster \
+         MERGE (c:KnowledgeCluster {cluster_id: cluster[0]}) SET c.name = cluster[0] \
+         WITH c, cluster UNWIND range(1, cluster[1]) AS i \
+         MERGE (m:EmailMessage {message_id: '<' + cluster[0] + '-' + i + '@cl.test>'}) \
+         SET m.subject = 'Budget ' + i, m.created_at = datetime() \
+         MERGE (m)-[:IN_CLUSTER]->(c) \
+         MERGE (t:Topic {topic_id: 'cl_test_budget'}) MERGE (m)-[:ABOUT]->(t)",
+    );
+    graph.run(setup).await.unwrap();
+
+    let ids = |clusters: Vec<writer::KnowledgeClusterSummary>| -> Vec<String> {
+        clusters
+            .into_iter()
+            .map(|c| c.cluster_id)
+            .filter(|id| id.starts_with("cl_test_"))
+            .collect()
+    };
+    let desc = writer::list_knowledge_clusters(graph, 2, false, 0, 1000).await.unwrap();
+    assert_eq!(ids(desc), vec!["cl_test_large", "cl_test_small"]);
+    let asc = writer::list_knowledge_clusters(graph, 2, true, 0, 1000).await.unwrap();
+    assert_eq!(ids(asc), vec!["cl_test_small", "cl_test_large"]);
+    let large = writer::list_knowledge_clusters(graph, 3, false, 0, 1000).await.unwrap();
+    let large = large.into_iter().find(|c| c.cluster_id == "cl_test_large").unwrap();
+    assert_eq!(large.member_count, 3);
+    assert_eq!(large.top_topics[0].topic, "cl_test_budget");
+
+    let detail = writer::load_knowledge_cluster(graph, "cl_test_small", 10).await.unwrap();
+    assert_eq!(detail.unwrap().members.len(), 2);
+    assert!(writer::load_knowledge_cluster(graph, "cl_test_missing", 10).await.unwrap().is_none());
+
+    let cleanup = neo4rs::query(
+        "MATCH (n) WHERE n.cluster_id STARTS WITH 'cl_test_' \
+         OR n.message_id ENDS WITH '@cl.test>' OR n.topic_id = 'cl_test_budget' \
+         DETACH DELETE n",
+    );
+    graph.run(cleanup).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a running Neo4j"]
+async fn clusters_are_assigned_incrementally_and_concurrently() {
+    let client = client().await;
+    let graph = client.graph();
+    let setup = neo4rs::query(
+        "UNWIND range(0, 11) AS i \
+         MERGE (m:EmailMessage {message_id: '<m' + i + '@centroid.test>'}) \
+         SET m.created_at = datetime()",
+    );
+    graph.run(setup).await.unwrap();
+    let id = |i: usize| format!("<m{i}@centroid.test>");
+    // Far from anything a real embedding model returns, so other clusters never match.
+    let (north, east) = (vec![0.0, 0.0, 1e3, 1.0], vec![0.0, 0.0, 1.0, 1e3]);
+
+    let first = writer::assign_message_to_cluster(graph, &id(0), &north, 0.99).await.unwrap();
+    assert!(!first.existing);
+    let again = writer::assign_message_to_cluster(graph, &id(0), &east, 0.99).await.unwrap();
+    assert!(again.existing);
+    assert_eq!(again.cluster_id, first.cluster_id);
+    let other = writer::assign_message_to_cluster(graph, &id(1), &east, 0.99).await.unwrap();
+    assert_ne!(other.cluster_id, first.cluster_id);
+    // A reply joins its thread's cluster however unlike it is.
+    let joined = writer::join_knowledge_cluster(graph, &first.cluster_id, &id(2), &east).await;
+    assert!(joined.unwrap());
+    let missing = writer::join_knowledge_cluster(graph, "cluster_missing", &id(3), &east).await;
+    assert!(!missing.unwrap());
+
+    let assignments = (3..11).map(|i| {
+        let (client, north) = (client.clone(), north.clone());
+        tokio::spawn(async move {
+            writer::assign_message_to_cluster(client.graph(), &id(i), &north, 0.9).await
+        })
+    });
+    for assignment in assignments.collect::<Vec<_>>() {
+        assert_eq!(assignment.await.unwrap().unwrap().cluster_id, first.cluster_id);
+    }
+    let mut rows = graph
+        .execute(
+            neo4rs::query(
+                "MATCH (c:KnowledgeCluster {cluster_id: $id}) \
+                 RETURN c.member_count AS count, c.centroid_version AS version",
+            )
+            .param("id", first.cluster_id.clone()),
+        )
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<i64>("count").unwrap(), 10, "no update was lost");
+    assert_eq!(row.get::<i64>("version").unwrap(), 10);
+
+    let cleanup = neo4rs::query(
+        "MATCH (m:EmailMessage) WHERE m.message_id ENDS WITH '@centroid.test>' \
+         OPTIONAL MATCH (m)-[:IN_CLUSTER]->(c:KnowledgeCluster) DETACH DELETE m, c",
+    );
+    graph.run(cleanup).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a running Neo4j"]
+async fn small_clusters_count_as_unclustered() {
+    let client = client().await;
+    let graph = client.graph();
+    let setup = neo4rs::query(
+        "UNWIND [['mc_test_single', 1], ['mc_test_trio', 3]] AS cluster \
+         MERGE (c:KnowledgeCluster {cluster_id: cluster[0]}) \
+         WITH c, cluster UNWIND range(1, cluster[1]) AS i \
+         MERGE (m:EmailMessage {message_id: '<' + cluster[0] + '-' + i + '@mc.test>'}) \
+         SET m.created_at = datetime() \
+         MERGE (m)-[:IN_CLUSTER]->(c) \
+         WITH count(*) AS linked \
+         MERGE (loose:EmailMessage {message_id: '<loose@mc.test>'}) \
+         SET loose.created_at = datetime()",
+    );
+    graph.run(setup).await.unwrap();
+
+    let listed = writer::list_knowledge_clusters(graph, 2, false, 0, 1000).await.unwrap();
+    let listed: Vec<String> = listed.into_iter().map(|c| c.cluster_id).collect();
+    assert!(listed.contains(&"mc_test_trio".to_string()));
+    assert!(!listed.contains(&"mc_test_single".to_string()));
+
+    let summary = writer::unclustered_summary(graph, 2).await.unwrap();
+    assert_eq!(summary.cluster_id, "unclustered");
+    assert!(summary.member_count >= 2);
+    let detail = writer::load_unclustered_messages(graph, 2, 10).await.unwrap();
+    let members: Vec<String> = detail.members.into_iter().map(|m| m.message_id).collect();
+    assert!(members.contains(&"<mc_test_single-1@mc.test>".to_string()));
+    assert!(members.contains(&"<loose@mc.test>".to_string()));
+    assert!(!members.contains(&"<mc_test_trio-1@mc.test>".to_string()));
+
+    let cleanup = neo4rs::query(
+        "MATCH (n) WHERE n.cluster_id STARTS WITH 'mc_test_' \
+         OR n.message_id ENDS WITH '@mc.test>' DETACH DELETE n",
+    );
+    graph.run(cleanup).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a running Neo4j"]
+async fn clarification_turns_join_the_conversation_without_a_decision() {
+    let client = client().await;
+    let graph = client.graph();
+    let employee_id = "employee_clarify_test";
+    graph
+        .run(neo4rs::query("MERGE (:Employee {employee_id: $id})").param("id", employee_id))
+        .await
+        .unwrap();
+    let turns = vec![
+        ("user".to_string(), "Raise it?".to_string()),
+        ("assistant".to_string(), "Which budget?".to_string()),
+    ];
+    client.append_conversation_turns(employee_id, &turns).await.unwrap();
+
+    let mut loaded = writer::load_recent_conversation_turns(graph, employee_id, 10).await.unwrap();
+    loaded.sort();
+    assert_eq!(loaded, [turns[1].clone(), turns[0].clone()]);
+    assert_eq!(writer::clear_conversation(graph, employee_id).await.unwrap(), 2);
+
+    let cleanup = neo4rs::query("MATCH (e:Employee {employee_id: $id}) DETACH DELETE e")
+        .param("id", employee_id);
+    graph.run(cleanup).await.unwrap();
+}